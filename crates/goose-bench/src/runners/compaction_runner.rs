@@ -0,0 +1,90 @@
+use anyhow::{bail, Result};
+use goose::config::Config;
+use goose::context_mgmt::benchmark::{
+    build_haystack, evaluate, CompactionReport, CompactionStrategy,
+};
+use goose::model::ModelConfig;
+use goose::providers::create;
+use std::path::PathBuf;
+
+pub struct CompactionRunner {
+    pub turns: usize,
+    pub seed: u64,
+    /// The size to compact to, as a fraction of the session
+    pub target: f64,
+    pub strategies: Vec<CompactionStrategy>,
+    /// Summarize and ask the probe questions with the configured provider
+    pub with_model: bool,
+    pub output: Option<PathBuf>,
+}
+
+impl CompactionRunner {
+    pub fn parse_strategies(names: &[String]) -> Result<Vec<CompactionStrategy>> {
+        if names.is_empty() {
+            return Ok(CompactionStrategy::all());
+        }
+        names
+            .iter()
+            .map(|name| match CompactionStrategy::from_name(name) {
+                Some(strategy) => Ok(strategy),
+                None => bail!("Unknown compaction strategy: {}", name),
+            })
+            .collect()
+    }
+
+    pub async fn run(&self) -> Result<CompactionReport> {
+        if self.target <= 0.0 || self.target >= 1.0 {
+            bail!("The target must be between 0 and 1");
+        }
+
+        let provider = if self.with_model {
+            let config = Config::global();
+            let provider_name: String = config.get_param("GOOSE_PROVIDER")?;
+            let model: String = config.get_param("GOOSE_MODEL")?;
+            Some(create(&provider_name, ModelConfig::new(&model)?)?)
+        } else {
+            None
+        };
+
+        let haystack = build_haystack(self.turns, self.seed);
+        let report = evaluate(&haystack, &self.strategies, self.target, provider).await;
+
+        print_report(&report);
+        if let Some(output) = &self.output {
+            std::fs::write(output, serde_json::to_string_pretty(&report)?)?;
+            println!("Wrote {}", output.display());
+        }
+        Ok(report)
+    }
+}
+
+fn print_report(report: &CompactionReport) {
+    println!(
+        "Compacting {} messages to {:.0}% of their tokens\n",
+        report.messages,
+        report.target * 100.0
+    );
+    println!(
+        "{:<18} {:>8} {:>8} {:>8} {:>8}  lost",
+        "strategy", "tokens", "kept", "recall", "answers"
+    );
+    for result in &report.results {
+        if let Some(error) = &result.error {
+            println!("{:<18} skipped: {}", result.strategy, error);
+            continue;
+        }
+        let answers = result
+            .answer_recall()
+            .map(|recall| format!("{:.0}%", recall * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<18} {:>8} {:>7.0}% {:>7.0}% {:>8}  {}",
+            result.strategy,
+            result.tokens_after,
+            result.compression() * 100.0,
+            result.recall() * 100.0,
+            answers,
+            result.lost.join(", ")
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use goose::config::Config;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use super::{ChatPlatform, IncomingMessage};
+
+/// Secret holding the bot token
+pub const DISCORD_TOKEN_KEY: &str = "GOOSE_BRIDGE_DISCORD_TOKEN";
+/// Config key for the id of the channel where mentions start conversations
+pub const DISCORD_CHANNEL_KEY: &str = "GOOSE_BRIDGE_DISCORD_CHANNEL";
+
+const API_URL: &str = "https://discord.com/api/v10";
+/// Guild messages and their content
+const GATEWAY_INTENTS: u64 = (1 << 9) | (1 << 15);
+/// Longest message Discord accepts
+const MESSAGE_LIMIT: usize = 2000;
+/// Longest thread name Discord accepts
+const THREAD_NAME_LIMIT: usize = 100;
+
+/// A Discord channel, read from the gateway. A mention in the channel opens a thread on that
+/// message, and the thread is the conversation.
+pub struct Discord {
+    client: Client,
+    token: String,
+    channel: String,
+    bot_id: String,
+    threads: HashSet<String>,
+    events: mpsc::Receiver<Value>,
+}
+
+impl Discord {
+    pub async fn from_config() -> Result<Self> {
+        let config = Config::global();
+        let token: String = config
+            .get_secret(DISCORD_TOKEN_KEY)
+            .with_context(|| format!("{} is not set", DISCORD_TOKEN_KEY))?;
+        let channel: String = config
+            .get_param(DISCORD_CHANNEL_KEY)
+            .with_context(|| format!("{} is not set", DISCORD_CHANNEL_KEY))?;
+
+        let client = Client::new();
+        let gateway: Value = client
+            .get(format!("{}/gateway/bot", API_URL))
+            .header("Authorization", format!("Bot {}", token))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let gateway_url = gateway["url"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Discord did not return a gateway URL"))?;
+        let (socket, _) =
+            tokio_tungstenite::connect_async(format!("{}/?v=10&encoding=json", gateway_url))
+                .await
+                .context("Failed to connect to the Discord gateway")?;
+
+        let (sender, mut events) = mpsc::channel(64);
+        let gateway_token = token.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_gateway(socket, gateway_token, sender).await {
+                tracing::error!("Discord gateway connection ended: {}", e);
+            }
+        });
+
+        // The gateway says who the bot is once it has identified
+        let bot_id = loop {
+            let event = events
+                .recv()
+                .await
+                .ok_or_else(|| anyhow!("Discord gateway closed before it was ready"))?;
+            if event["t"] == "READY" {
+                break event["d"]["user"]["id"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Discord did not say who the bot is"))?
+                    .to_string();
+            }
+        };
+
+        Ok(Self {
+            client,
+            token,
+            channel,
+            bot_id,
+            threads: HashSet::new(),
+            events,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", API_URL, path))
+            .header("Authorization", format!("Bot {}", self.token))
+    }
+
+    async fn start_thread(&self, message_id: &str, text: &str) -> Result<String> {
+        let name: String = match text.lines().next() {
+            Some(line) if !line.trim().is_empty() => line.chars().take(THREAD_NAME_LIMIT).collect(),
+            _ => "goose".to_string(),
+        };
+        let thread: Value = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/channels/{}/messages/{}/threads", self.channel, message_id),
+            )
+            .json(&json!({"name": name}))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        thread["id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow!("Discord did not return the new thread's id"))
+    }
+
+    fn without_mention(&self, text: &str) -> String {
+        text.replace(&format!("<@{}>", self.bot_id), "")
+            .replace(&format!("<@!{}>", self.bot_id), "")
+            .trim()
+            .to_string()
+    }
+}
+
+/// Keep the gateway connection alive, passing dispatched events on until the bridge goes away
+async fn run_gateway(
+    socket: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+    token: String,
+    events: mpsc::Sender<Value>,
+) -> Result<()> {
+    let (mut write, mut read) = socket.split();
+
+    let hello = next_payload(&mut read).await?;
+    let interval = hello["d"]["heartbeat_interval"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("Discord gateway did not send a heartbeat interval"))?;
+    write
+        .send(WsMessage::Text(
+            json!({
+                "op": 2,
+                "d": {
+                    "token": token,
+                    "intents": GATEWAY_INTENTS,
+                    "properties": {"os": std::env::consts::OS, "browser": "goose", "device": "goose"}
+                }
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let mut heartbeat = tokio::time::interval(Duration::from_millis(interval));
+    let mut sequence = Value::Null;
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                write
+                    .send(WsMessage::Text(json!({"op": 1, "d": sequence}).to_string().into()))
+                    .await?;
+            }
+            payload = next_payload(&mut read) => {
+                let payload = payload?;
+                if !payload["s"].is_null() {
+                    sequence = payload["s"].clone();
+                }
+                match payload["op"].as_u64() {
+                    Some(0) => {
+                        if events.send(payload).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Some(1) => heartbeat.reset_immediately(),
+                    Some(7) | Some(9) => bail!("Discord asked the bridge to reconnect"),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+async fn next_payload<S>(read: &mut S) -> Result<Value>
+where
+    S: futures::Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        match read.next().await {
+            Some(Ok(WsMessage::Text(text))) => return Ok(serde_json::from_str(text.as_str())?),
+            Some(Ok(WsMessage::Close(frame))) => {
+                bail!("Discord closed the gateway connection: {:?}", frame)
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+            None => bail!("Discord closed the gateway connection"),
+        }
+    }
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MESSAGE_LIMIT {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MESSAGE_LIMIT - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[async_trait]
+impl ChatPlatform for Discord {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    async fn next_messages(&mut self) -> Result<Vec<IncomingMessage>> {
+        loop {
+            let event = self
+                .events
+                .recv()
+                .await
+                .ok_or_else(|| anyhow!("Lost the connection to the Discord gateway"))?;
+            if event["t"] != "MESSAGE_CREATE" {
+                continue;
+            }
+            let message = &event["d"];
+            if message["author"]["bot"].as_bool() == Some(true) {
+                continue;
+            }
+            let (Some(channel), Some(id), Some(user), Some(content)) = (
+                message["channel_id"].as_str(),
+                message["id"].as_str(),
+                message["author"]["id"].as_str(),
+                message["content"].as_str(),
+            ) else {
+                continue;
+            };
+            let mentioned = message["mentions"]
+                .as_array()
+                .is_some_and(|mentions| mentions.iter().any(|m| m["id"] == self.bot_id.as_str()));
+            let text = self.without_mention(content);
+
+            let thread = if channel == self.channel {
+                if !mentioned {
+                    continue;
+                }
+                let thread = self.start_thread(id, &text).await?;
+                self.threads.insert(thread.clone());
+                thread
+            } else {
+                channel.to_string()
+            };
+
+            return Ok(vec![IncomingMessage {
+                // Elsewhere a mention only counts in threads the bridge opened; threads it has
+                // a session for are answered either way
+                mentioned: mentioned && self.threads.contains(&thread),
+                thread,
+                user: user.to_string(),
+                text,
+            }]);
+        }
+    }
+
+    async fn send(&self, thread: &str, text: &str) -> Result<String> {
+        let message: Value = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/channels/{}/messages", thread),
+            )
+            .json(&json!({"content": truncate(text)}))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        message["id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow!("Discord did not return the message id"))
+    }
+
+    async fn edit(&self, thread: &str, message_id: &str, text: &str) -> Result<()> {
+        self.request(
+            reqwest::Method::PATCH,
+            &format!("/channels/{}/messages/{}", thread, message_id),
+        )
+        .json(&json!({"content": truncate(text)}))
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
+}
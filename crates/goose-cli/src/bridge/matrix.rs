@@ -0,0 +1,224 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use goose::config::Config;
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+
+use super::{ChatPlatform, IncomingMessage};
+
+/// Config key for the homeserver URL, e.g. `https://matrix.example.org`
+pub const MATRIX_HOMESERVER_KEY: &str = "GOOSE_BRIDGE_MATRIX_HOMESERVER";
+/// Secret holding the access token of the agent's Matrix account
+pub const MATRIX_TOKEN_KEY: &str = "GOOSE_BRIDGE_MATRIX_TOKEN";
+/// Config key for the id of the room to answer in, which the account must have joined
+pub const MATRIX_ROOM_KEY: &str = "GOOSE_BRIDGE_MATRIX_ROOM";
+
+/// How long each sync waits for new events
+const SYNC_TIMEOUT_MS: u64 = 30_000;
+
+/// A Matrix room, read by long-polling `/sync`. Conversations are Matrix threads rooted at the
+/// message that mentioned the agent.
+pub struct Matrix {
+    client: Client,
+    homeserver: Url,
+    token: String,
+    room: String,
+    user_id: String,
+    since: Option<String>,
+    transactions: AtomicU64,
+}
+
+impl Matrix {
+    pub async fn from_config() -> Result<Self> {
+        let config = Config::global();
+        let homeserver: String = config
+            .get_param(MATRIX_HOMESERVER_KEY)
+            .with_context(|| format!("{} is not set", MATRIX_HOMESERVER_KEY))?;
+        let token: String = config
+            .get_secret(MATRIX_TOKEN_KEY)
+            .with_context(|| format!("{} is not set", MATRIX_TOKEN_KEY))?;
+        let room: String = config
+            .get_param(MATRIX_ROOM_KEY)
+            .with_context(|| format!("{} is not set", MATRIX_ROOM_KEY))?;
+
+        let mut matrix = Self {
+            client: Client::new(),
+            homeserver: Url::parse(&homeserver)
+                .with_context(|| format!("{} is not a valid URL", homeserver))?,
+            token,
+            room,
+            user_id: String::new(),
+            since: None,
+            transactions: AtomicU64::new(0),
+        };
+
+        let whoami: Value = matrix
+            .client
+            .get(matrix.url(&["account", "whoami"])?)
+            .bearer_auth(&matrix.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        matrix.user_id = whoami["user_id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Homeserver did not say who the token belongs to"))?
+            .to_string();
+
+        // Start from now rather than answering the room's history
+        let sync = matrix.sync(0).await?;
+        matrix.since = sync["next_batch"].as_str().map(String::from);
+        Ok(matrix)
+    }
+
+    fn url(&self, path: &[&str]) -> Result<Url> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("{} is not a valid homeserver URL", self.homeserver))?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"])
+            .extend(path);
+        Ok(url)
+    }
+
+    async fn sync(&self, timeout_ms: u64) -> Result<Value> {
+        let mut url = self.url(&["sync"])?;
+        let filter = json!({
+            "room": {
+                "rooms": [self.room],
+                "timeline": {"types": ["m.room.message"]}
+            }
+        });
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("timeout", &timeout_ms.to_string());
+            query.append_pair("filter", &filter.to_string());
+            if let Some(since) = &self.since {
+                query.append_pair("since", since);
+            }
+        }
+        Ok(self
+            .client
+            .get(url)
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    fn mentions_agent(&self, content: &Value, body: &str) -> bool {
+        let localpart = self
+            .user_id
+            .trim_start_matches('@')
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        content["m.mentions"]["user_ids"]
+            .as_array()
+            .is_some_and(|ids| ids.iter().any(|id| id == self.user_id.as_str()))
+            || body.contains(&self.user_id)
+            || (!localpart.is_empty() && body.to_lowercase().contains(&localpart))
+    }
+
+    async fn put_message(&self, content: Value) -> Result<String> {
+        let transaction = format!(
+            "goose-{}-{}",
+            std::process::id(),
+            self.transactions.fetch_add(1, Ordering::SeqCst)
+        );
+        let url = self.url(&["rooms", &self.room, "send", "m.room.message", &transaction])?;
+        let response: Value = self
+            .client
+            .put(url)
+            .bearer_auth(&self.token)
+            .json(&content)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        response["event_id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow!("Homeserver did not return an event id"))
+    }
+}
+
+#[async_trait]
+impl ChatPlatform for Matrix {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn next_messages(&mut self) -> Result<Vec<IncomingMessage>> {
+        loop {
+            let sync = self.sync(SYNC_TIMEOUT_MS).await?;
+            if let Some(next_batch) = sync["next_batch"].as_str() {
+                self.since = Some(next_batch.to_string());
+            }
+
+            let mut messages = Vec::new();
+            let events = sync["rooms"]["join"][&self.room]["timeline"]["events"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            for event in events {
+                if event["type"] != "m.room.message" || event["sender"] == self.user_id.as_str() {
+                    continue;
+                }
+                let content = &event["content"];
+                let (Some(body), Some(sender)) =
+                    (content["body"].as_str(), event["sender"].as_str())
+                else {
+                    continue;
+                };
+                let relation = &content["m.relates_to"];
+                let mentioned = self.mentions_agent(content, body);
+                let thread = match relation["rel_type"].as_str() {
+                    Some("m.thread") => relation["event_id"].as_str(),
+                    // Edits, replies outside threads and the like
+                    Some(_) => None,
+                    None if mentioned => event["event_id"].as_str(),
+                    None => None,
+                };
+                if let Some(thread) = thread {
+                    messages.push(IncomingMessage {
+                        thread: thread.to_string(),
+                        user: sender.to_string(),
+                        text: body.to_string(),
+                        mentioned,
+                    });
+                }
+            }
+            if !messages.is_empty() {
+                return Ok(messages);
+            }
+        }
+    }
+
+    async fn send(&self, thread: &str, text: &str) -> Result<String> {
+        self.put_message(json!({
+            "msgtype": "m.text",
+            "body": text,
+            "m.relates_to": {"rel_type": "m.thread", "event_id": thread}
+        }))
+        .await
+    }
+
+    async fn edit(&self, _thread: &str, message_id: &str, text: &str) -> Result<()> {
+        self.put_message(json!({
+            "msgtype": "m.text",
+            "body": format!("* {}", text),
+            "m.new_content": {"msgtype": "m.text", "body": text},
+            "m.relates_to": {"rel_type": "m.replace", "event_id": message_id}
+        }))
+        .await?;
+        Ok(())
+    }
+}
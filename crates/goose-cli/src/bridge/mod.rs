@@ -0,0 +1,313 @@
+//! A shared agent in a team chat.
+//!
+//! The bridge joins a Matrix room or a Discord channel and answers there. Mentioning the agent
+//! starts a thread, and everything said in that thread afterwards goes to the same session, kept
+//! in the session directory like any other so it can be resumed or exported later. Replies are
+//! posted as soon as the agent starts working and edited as its answer and tool calls come in.
+//!
+//! Who may have the agent do what is set per chat user by [`policy::BridgePolicy`]. Messages are
+//! answered one at a time, so each runs with the access of the user who sent it.
+
+pub mod discord;
+pub mod matrix;
+pub mod policy;
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use goose::agents::{Agent, AgentEvent, SessionConfig};
+use goose::conversation::message::{Message, MessageContent};
+use goose::conversation::Conversation;
+use goose::permission::permission_confirmation::PrincipalType;
+use goose::permission::{Permission, PermissionConfirmation};
+use goose::session;
+use rmcp::model::Role;
+
+use policy::{Access, BridgePolicy};
+
+/// Edits of a reply in progress are spaced out to stay under the platforms' rate limits
+const EDIT_INTERVAL: Duration = Duration::from_millis(1500);
+/// What a reply says until the agent has something to say
+const PENDING_REPLY: &str = "_Thinking…_";
+
+/// A chat message for the agent
+#[derive(Debug, Clone)]
+pub struct IncomingMessage {
+    /// The thread the message belongs to, which is also the conversation it continues
+    pub thread: String,
+    /// The sender's id on the platform, as used in the policy
+    pub user: String,
+    pub text: String,
+    /// Whether the message mentions the agent; only those start a conversation
+    pub mentioned: bool,
+}
+
+/// A chat service the bridge can sit in
+#[async_trait]
+pub trait ChatPlatform: Send + Sync {
+    /// Short name, used in session names
+    fn name(&self) -> &'static str;
+
+    /// Wait for the next messages, leaving out the agent's own
+    async fn next_messages(&mut self) -> Result<Vec<IncomingMessage>>;
+
+    /// Post a message to a thread, returning its id
+    async fn send(&self, thread: &str, text: &str) -> Result<String>;
+
+    /// Replace the text of a message the bridge posted
+    async fn edit(&self, thread: &str, message_id: &str, text: &str) -> Result<()>;
+}
+
+/// The text of a reply as it is built up
+#[derive(Default)]
+struct Reply {
+    parts: Vec<String>,
+    /// Whether the last part is assistant text that more text continues
+    in_text: bool,
+    changed: bool,
+}
+
+impl Reply {
+    fn push_text(&mut self, text: &str) {
+        match self.parts.last_mut() {
+            Some(last) if self.in_text => last.push_str(text),
+            _ => self.parts.push(text.to_string()),
+        }
+        self.in_text = true;
+        self.changed = true;
+    }
+
+    fn push_note(&mut self, note: String) {
+        self.parts.push(format!("_{}_", note));
+        self.in_text = false;
+        self.changed = true;
+    }
+
+    fn render(&self) -> String {
+        let text = self
+            .parts
+            .iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if text.is_empty() {
+            PENDING_REPLY.to_string()
+        } else {
+            text
+        }
+    }
+}
+
+/// The session a thread is kept in; thread ids are reduced to characters safe in file names
+fn session_name(platform: &str, thread: &str) -> String {
+    let thread: String = thread
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("bridge_{}_{}", platform, thread)
+}
+
+/// Runs an agent in a chat, one message at a time
+pub struct Bridge<P: ChatPlatform> {
+    platform: P,
+    agent: Agent,
+    policy: BridgePolicy,
+}
+
+impl<P: ChatPlatform> Bridge<P> {
+    pub fn new(platform: P, agent: Agent, policy: BridgePolicy) -> Self {
+        Self {
+            platform,
+            agent,
+            policy,
+        }
+    }
+
+    /// Answer messages until the platform connection fails
+    pub async fn run(&mut self) -> Result<()> {
+        loop {
+            for message in self.platform.next_messages().await? {
+                if let Err(e) = self.handle(message).await {
+                    tracing::error!("Failed to answer chat message: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, incoming: IncomingMessage) -> Result<()> {
+        let session_file = session::get_path(session::Identifier::Name(session_name(
+            self.platform.name(),
+            &incoming.thread,
+        )))?;
+        if !incoming.mentioned && !session_file.exists() {
+            return Ok(());
+        }
+
+        let access = self.policy.access(&incoming.user);
+        if access == Access::None {
+            tracing::info!("Ignoring chat message from {}", incoming.user);
+            return Ok(());
+        }
+        self.agent.set_read_only(access == Access::ReadOnly);
+
+        let mut conversation = if session_file.exists() {
+            session::read_messages(&session_file)?
+        } else {
+            Conversation::empty()
+        };
+        conversation.push(Message::user().with_text(&incoming.text));
+
+        let working_dir = std::env::current_dir()?;
+        session::persist_messages(
+            &session_file,
+            &conversation,
+            Some(self.agent.provider().await?),
+            Some(working_dir.clone()),
+        )
+        .await?;
+
+        let reply_id = self.platform.send(&incoming.thread, PENDING_REPLY).await?;
+        let mut reply = Reply::default();
+        let mut last_edit = Instant::now();
+
+        let session_config = SessionConfig {
+            id: session::Identifier::Path(session_file.clone()),
+            working_dir: working_dir.clone(),
+            schedule_id: None,
+            execution_mode: None,
+            max_turns: None,
+            retry_config: None,
+        };
+        let mut stream = self
+            .agent
+            .reply(conversation.clone(), Some(session_config), None)
+            .await?;
+
+        while let Some(event) = stream.next().await {
+            match event {
+                Ok(AgentEvent::Message(message)) => {
+                    conversation.push(message.clone());
+                    session::persist_messages(
+                        &session_file,
+                        &conversation,
+                        None,
+                        Some(working_dir.clone()),
+                    )
+                    .await?;
+
+                    for content in &message.content {
+                        match content {
+                            MessageContent::Text(text) if message.role == Role::Assistant => {
+                                reply.push_text(&text.text);
+                            }
+                            MessageContent::ToolRequest(request) => {
+                                if let Ok(call) = &request.tool_call {
+                                    reply.push_note(format!("Running {}", call.name));
+                                }
+                            }
+                            MessageContent::ToolConfirmationRequest(confirmation) => {
+                                // Nobody is asked in the chat; the sender's access decides
+                                let permission = if access == Access::Full {
+                                    Permission::AllowOnce
+                                } else {
+                                    reply.push_note(format!(
+                                        "Not allowed to run {} for {}",
+                                        confirmation.tool_name, incoming.user
+                                    ));
+                                    Permission::DenyOnce
+                                };
+                                self.agent
+                                    .handle_confirmation(
+                                        confirmation.id.clone(),
+                                        PermissionConfirmation {
+                                            principal_type: PrincipalType::Tool,
+                                            permission,
+                                        },
+                                    )
+                                    .await;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(AgentEvent::HistoryReplaced(messages)) => {
+                    conversation = Conversation::new_unvalidated(messages);
+                    session::persist_messages(
+                        &session_file,
+                        &conversation,
+                        None,
+                        Some(working_dir.clone()),
+                    )
+                    .await?;
+                }
+                Ok(AgentEvent::CredentialRequest(request)) => {
+                    // A chat is no place to type a secret
+                    reply.push_note(format!(
+                        "{} is needed but can't be provided in chat",
+                        request.name
+                    ));
+                    self.agent.provide_credential(request.id, None).await;
+                }
+                Ok(AgentEvent::SecretBlocked(blocked)) => {
+                    reply.push_note(format!(
+                        "Blocked {} from writing credentials",
+                        blocked.tool_name
+                    ));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    reply.push_note(format!("Error: {}", e));
+                    break;
+                }
+            }
+
+            if reply.changed && last_edit.elapsed() >= EDIT_INTERVAL {
+                if let Err(e) = self
+                    .platform
+                    .edit(&incoming.thread, &reply_id, &reply.render())
+                    .await
+                {
+                    tracing::warn!("Failed to update chat reply: {}", e);
+                }
+                reply.changed = false;
+                last_edit = Instant::now();
+            }
+        }
+
+        self.platform
+            .edit(&incoming.thread, &reply_id, &reply.render())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_joins_text_and_notes() {
+        let mut reply = Reply::default();
+        assert_eq!(reply.render(), PENDING_REPLY);
+        reply.push_text("Let me ");
+        reply.push_text("look.");
+        reply.push_note("Running developer__shell".to_string());
+        reply.push_text("Found it.");
+        assert_eq!(
+            reply.render(),
+            "Let me look.\n\n_Running developer__shell_\n\nFound it."
+        );
+        assert_eq!(
+            session_name("matrix", "$abc:example.org"),
+            "bridge_matrix__abc_example_org"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use goose::config::{Config, ConfigError};
+use serde::Deserialize;
+
+/// Config key holding who may do what through the bridge, as a map like
+/// `{"default": "read_only", "users": {"@alice:example.org": "full"}}`
+pub const BRIDGE_POLICY_KEY: &str = "GOOSE_BRIDGE_POLICY";
+
+/// What a chat user may have the agent do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    /// Messages from the user are ignored
+    None,
+    /// Only tools annotated as read-only run, and nothing that needs approval is approved
+    #[default]
+    ReadOnly,
+    /// Any tool may run, and tools that need approval are approved on the user's behalf
+    Full,
+}
+
+/// Per-user access for the chat bridge, by the platform's user id
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BridgePolicy {
+    /// Access for users not listed
+    #[serde(default)]
+    pub default: Access,
+    #[serde(default)]
+    pub users: HashMap<String, Access>,
+}
+
+impl BridgePolicy {
+    /// The configured policy; everyone gets read-only access when none is set
+    pub fn from_config() -> Result<Self> {
+        match Config::global().get_param::<BridgePolicy>(BRIDGE_POLICY_KEY) {
+            Ok(policy) => Ok(policy),
+            Err(ConfigError::NotFound(_)) => Ok(Self::default()),
+            Err(e) => Err(anyhow::anyhow!("Invalid {}: {}", BRIDGE_POLICY_KEY, e)),
+        }
+    }
+
+    pub fn access(&self, user: &str) -> Access {
+        self.users.get(user).copied().unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_listed_users_override_the_default() {
+        let policy: BridgePolicy = serde_json::from_value(json!({
+            "default": "none",
+            "users": {"@alice:example.org": "full", "1234": "read_only"}
+        }))
+        .unwrap();
+        assert_eq!(policy.access("@alice:example.org"), Access::Full);
+        assert_eq!(policy.access("1234"), Access::ReadOnly);
+        assert_eq!(policy.access("@mallory:example.org"), Access::None);
+    }
+
+    #[test]
+    fn test_missing_fields_are_read_only() {
+        let policy: BridgePolicy = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(policy.access("anyone"), Access::ReadOnly);
+        assert!(serde_json::from_value::<BridgePolicy>(json!({"default": "admin"})).is_err());
+    }
+}
@@ -5,23 +5,31 @@ use goose::config::{Config, ExtensionConfig};
 
 use crate::commands::bench::agent_generator;
 use crate::commands::configure::handle_configure;
+use crate::commands::diff::handle_diff;
+use crate::commands::doctor::handle_doctor;
+use crate::commands::extension::handle_extension_update;
 use crate::commands::info::handle_info;
 use crate::commands::mcp::run_server;
 use crate::commands::project::{handle_project_default, handle_projects_interactive};
 use crate::commands::recipe::{handle_deeplink, handle_list, handle_validate};
+use crate::commands::report::{handle_report, ReportFormat};
+use crate::commands::room::handle_room;
 // Import the new handlers from commands::schedule
 use crate::commands::schedule::{
     handle_schedule_add, handle_schedule_cron_help, handle_schedule_list, handle_schedule_remove,
     handle_schedule_run_now, handle_schedule_services_status, handle_schedule_services_stop,
     handle_schedule_sessions,
 };
-use crate::commands::session::{handle_session_list, handle_session_remove};
+use crate::commands::session::{handle_session_list, handle_session_remove, ExportFormat};
+use crate::commands::warm::handle_warm;
 use crate::recipes::extract_from_cli::extract_recipe_info_from_cli;
+use crate::recipes::lock::lock_recipe_run;
 use crate::recipes::recipe::{explain_recipe, render_recipe_as_yaml};
 use crate::session;
 use crate::session::{build_session, SessionBuilderConfig, SessionSettings};
 use goose_bench::bench_config::BenchRunConfig;
 use goose_bench::runners::bench_runner::BenchRunner;
+use goose_bench::runners::compaction_runner::CompactionRunner;
 use goose_bench::runners::eval_runner::EvalRunner;
 use goose_bench::runners::metric_aggregator::MetricAggregator;
 use goose_bench::runners::model_runner::ModelRunner;
@@ -68,6 +76,16 @@ fn extract_identifier(identifier: Identifier) -> session::Identifier {
     }
 }
 
+/// A session given as a positional argument: a path when it names a session file, else a name
+fn session_identifier_from_arg(arg: String) -> session::Identifier {
+    let path = PathBuf::from(&arg);
+    if path.extension().is_some_and(|ext| ext == "jsonl") || path.components().count() > 1 {
+        session::Identifier::Path(path)
+    } else {
+        session::Identifier::Name(arg)
+    }
+}
+
 fn parse_key_val(s: &str) -> Result<(String, String), String> {
     match s.split_once('=') {
         Some((key, value)) => Ok((key.to_string(), value.to_string())),
@@ -96,6 +114,54 @@ enum SessionCommand {
             long_help = "Sort sessions by date in ascending order (oldest first). Default is descending order (newest first)."
         )]
         ascending: bool,
+
+        #[arg(
+            long = "tag",
+            value_name = "TAG",
+            help = "Only list sessions with this tag (repeatable, all must match)"
+        )]
+        tags: Vec<String>,
+
+        #[arg(
+            long = "meta",
+            value_name = "KEY=VALUE",
+            help = "Only list sessions with this metadata value (repeatable, all must match)",
+            value_parser = parse_key_val
+        )]
+        properties: Vec<(String, String)>,
+    },
+    #[command(
+        about = "Tag a session or attach key/value metadata to it",
+        long_about = "Add or remove tags and key/value metadata on a session, to organize sessions and find them again with `goose session list --tag` and `--meta`. Without any changes, shows the session's tags and metadata."
+    )]
+    Tag {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+
+        #[arg(value_name = "TAG", help = "Tags to add")]
+        add: Vec<String>,
+
+        #[arg(
+            long = "remove",
+            value_name = "TAG",
+            help = "Tag to remove (repeatable)"
+        )]
+        remove: Vec<String>,
+
+        #[arg(
+            long = "set",
+            value_name = "KEY=VALUE",
+            help = "Metadata to set (repeatable)",
+            value_parser = parse_key_val
+        )]
+        set: Vec<(String, String)>,
+
+        #[arg(
+            long = "unset",
+            value_name = "KEY",
+            help = "Metadata key to remove (repeatable)"
+        )]
+        unset: Vec<String>,
     },
     #[command(about = "Remove sessions. Runs interactively if no ID or regex is provided.")]
     Remove {
@@ -104,19 +170,207 @@ enum SessionCommand {
         #[arg(short, long, help = "Regex for removing matched sessions (optional)")]
         regex: Option<String>,
     },
-    #[command(about = "Export a session to Markdown format")]
+    #[command(
+        about = "Export a session to Markdown, HTML, JSON or a portable bundle",
+        long_about = "Export a session to Markdown, HTML or JSON for reading, or with --format bundle to a .goosebundle file that `goose session import` resumes on another machine. A bundle holds the session's history, the large tool outputs it refers to and the config it ran with, without secrets."
+    )]
     Export {
         #[command(flatten)]
         identifier: Option<Identifier>,
 
+        #[arg(
+            long,
+            value_enum,
+            default_value = "md",
+            help = "Export format (md, html, json, bundle)"
+        )]
+        format: ExportFormat,
+
         #[arg(
             short,
             long,
             help = "Output file path (default: stdout)",
-            long_help = "Path to save the exported Markdown. If not provided, output will be sent to stdout"
+            long_help = "Path to save the exported session. If not provided, output will be sent to stdout, except for bundles, which are saved as <session>.goosebundle"
         )]
         output: Option<PathBuf>,
     },
+    #[command(
+        about = "Import a session from a .goosebundle file",
+        long_about = "Store the session in a bundle made with `goose session export --format bundle`, so it can be resumed with `goose session --resume --name <NAME>`. If the session's working directory doesn't exist here, it resumes in the current directory."
+    )]
+    Import {
+        #[arg(value_name = "BUNDLE", help = "Path to the .goosebundle file")]
+        bundle: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "NAME",
+            help = "Name for the imported session (default: its name when exported)"
+        )]
+        name: Option<String>,
+
+        #[arg(
+            long = "working-dir",
+            value_name = "DIR",
+            help = "Directory to resume the session in"
+        )]
+        working_dir: Option<PathBuf>,
+
+        #[arg(
+            long = "apply-config",
+            help = "Set config values from the bundle that aren't configured here"
+        )]
+        apply_config: bool,
+    },
+    #[command(about = "Show where each message in a session came from and how far it is trusted")]
+    Inspect {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(about = "Show the tokens a session used and what they cost")]
+    Cost {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(
+        about = "Compare two sessions' context and find where their conversations diverge",
+        long_help = "Compare the system prompts, tool sets and model configurations two sessions ran with, and show the first message at which their conversations differ. Sessions are given by name or by path to a .jsonl session file."
+    )]
+    Diff {
+        #[arg(value_name = "SESSION_A", help = "First session name or path")]
+        a: String,
+
+        #[arg(value_name = "SESSION_B", help = "Second session name or path")]
+        b: String,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(
+        about = "Search message text, tool names and file paths across all sessions",
+        long_help = "Search every stored session for messages containing all the given words, matching message text, the tools called and the file paths they touched. Shows the best matching message of each session, best match first."
+    )]
+    Search {
+        #[arg(
+            value_name = "QUERY",
+            required = true,
+            num_args = 1..,
+            help = "Words to search for"
+        )]
+        query: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of sessions to show",
+            default_value = "20"
+        )]
+        limit: usize,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(
+        about = "Re-drive a session from its stored user messages",
+        long_about = "Send a stored session's user messages to the agent again, one turn at a time. By default the model's recorded responses and the recorded tool results are replayed, so no model is called and nothing is run, for debugging the agent. With --live the messages go to a real model with real tools, and each turn is compared with the recorded one to show how model behavior changed."
+    )]
+    Replay {
+        #[arg(value_name = "SESSION", help = "Session name or path to replay")]
+        session: String,
+
+        #[arg(
+            long,
+            help = "Call the model and run tools for real, then compare with the recording"
+        )]
+        live: bool,
+
+        #[arg(long, value_name = "PROVIDER", help = "Provider for a live replay")]
+        provider: Option<String>,
+
+        #[arg(long, value_name = "MODEL", help = "Model for a live replay")]
+        model: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format of the comparison (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(
+        about = "Remove old sessions according to a retention policy",
+        long_about = "Remove sessions older than a maximum age, beyond a maximum count, or beyond a maximum total size, oldest first. Limits not given as options come from GOOSE_SESSION_RETENTION_MAX_AGE_DAYS, GOOSE_SESSION_RETENTION_MAX_COUNT and GOOSE_SESSION_RETENTION_MAX_SIZE_MB; with GOOSE_SESSION_PRUNE_ON_STARTUP set, the configured limits are also applied whenever a session starts. Tagged sessions are never removed, so tag a session (e.g. `goose session tag pinned`) to keep it."
+    )]
+    Prune {
+        #[arg(
+            long = "max-age-days",
+            value_name = "DAYS",
+            help = "Remove sessions older than this"
+        )]
+        max_age_days: Option<u64>,
+
+        #[arg(
+            long = "max-count",
+            value_name = "COUNT",
+            help = "Keep at most this many sessions"
+        )]
+        max_count: Option<usize>,
+
+        #[arg(
+            long = "max-size-mb",
+            value_name = "MB",
+            help = "Keep at most this many megabytes of sessions"
+        )]
+        max_size_mb: Option<u64>,
+
+        #[arg(
+            long = "dry-run",
+            help = "Show what would be removed without removing it"
+        )]
+        dry_run: bool,
+
+        #[arg(short, long, help = "Remove without asking for confirmation")]
+        yes: bool,
+    },
+    #[command(
+        about = "Move session files into the configured session store",
+        long_about = "Copy every JSONL session file into the store GOOSE_SESSION_STORAGE selects (sqlite, postgres or s3). Sessions the store already has are left alone, so it is safe to run again."
+    )]
+    Migrate {
+        #[arg(
+            long = "remove-files",
+            help = "Delete each session file once it is in the store"
+        )]
+        remove_files: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -224,6 +478,54 @@ pub enum BenchCommand {
         )]
         benchmark_dir: PathBuf,
     },
+
+    #[command(about = "Measure how much of a long session each compaction strategy keeps")]
+    Compaction {
+        #[arg(
+            long,
+            default_value = "40",
+            help = "Number of turns in the synthetic session"
+        )]
+        turns: usize,
+
+        #[arg(
+            long,
+            default_value = "0.5",
+            help = "Size to compact to, as a fraction of the session's tokens"
+        )]
+        target: f64,
+
+        #[arg(
+            long = "strategy",
+            value_name = "NAME",
+            help = "Strategy to evaluate (repeatable, defaults to all)",
+            long_help = "Strategy to evaluate: oldest_first, middle_out, tool_output_first, importance or summarize. Can be given several times; defaults to all of them."
+        )]
+        strategies: Vec<String>,
+
+        #[arg(long, default_value = "1", help = "Seed for the synthetic session")]
+        seed: u64,
+
+        #[arg(
+            long,
+            help = "Summarize and ask the model about the planted facts with the configured provider"
+        )]
+        with_model: bool,
+
+        #[arg(short, long, value_name = "FILE", help = "Write the report as JSON")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExtensionCommand {
+    /// Resolve extensions again and write them to the lockfile
+    #[command(about = "Refresh the versions and hashes in .goose/extensions.lock")]
+    Update {
+        /// Extensions to refresh, all enabled extensions if none are given
+        #[arg(help = "Names of the extensions to refresh (default: all enabled extensions)")]
+        names: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -563,10 +865,22 @@ enum Command {
         #[arg(
             short = 'q',
             long = "quiet",
-            help = "Quiet mode. Suppress non-response output, printing only the model response to stdout"
+            conflicts_with = "interactive",
+            help = "Quiet mode. Suppress non-response output, printing only the model response to stdout",
+            long_help = "Quiet mode for calling goose from other programs. Prints only the final response to stdout and one JSON event per line to stderr, with no spinners or prompts: tool calls that need confirmation are denied and credential requests declined. Also enabled by GOOSE_QUIET=1"
         )]
         quiet: bool,
 
+        /// Run the recipe through the provider's batch API
+        #[arg(
+            long = "batch",
+            requires = "recipe",
+            conflicts_with = "interactive",
+            help = "Run the recipe through the provider's batch API (OpenAI only)",
+            long_help = "Submit each request through the OpenAI Batch API and wait for it to finish. Batched requests cost less but can take hours, so this is meant for unattended recipe runs."
+        )]
+        batch: bool,
+
         /// Scheduled job ID (used internally for scheduled executions)
         #[arg(
             long = "scheduled-job-id",
@@ -606,6 +920,25 @@ enum Command {
         model: Option<String>,
     },
 
+    /// Check that the configured provider is ready to use
+    #[command(about = "Check the provider's credentials, endpoint and model")]
+    Doctor {
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+
+    /// Manage the extensions a project locks
+    #[command(about = "Manage locked extensions")]
+    Extension {
+        #[command(subcommand)]
+        command: ExtensionCommand,
+    },
+
     /// Recipe utilities for validation and deeplinking
     #[command(about = "Recipe utilities for validation and deeplinking")]
     Recipe {
@@ -613,6 +946,105 @@ enum Command {
         command: RecipeCommand,
     },
 
+    /// Run several agents with distinct roles on one task in a shared thread
+    #[command(
+        about = "Experimental: Run a room of collaborating agents on a task",
+        long_about = "Experimental: Run several named agents, each with its own role, model and extensions, on one task in a shared thread. The room is described by a YAML file listing its members, how turns are taken and the combined token budget."
+    )]
+    Room {
+        /// Room configuration file
+        #[arg(value_name = "FILE", help = "Path to the room's YAML configuration")]
+        config: PathBuf,
+
+        /// The task for the room
+        #[arg(short, long, value_name = "TEXT", help = "Task the members work on")]
+        task: String,
+
+        /// Where to write the shared artifacts
+        #[arg(
+            short,
+            long,
+            value_name = "DIR",
+            help = "Directory to write the shared artifacts to when the room finishes"
+        )]
+        output: Option<PathBuf>,
+    },
+
+    /// Warm caches ahead of a scheduled recipe run
+    #[command(
+        about = "Warm provider prompt caches and local indexes for a recipe",
+        long_about = "Send a recipe's opening request (system prompt, tools and rendered prompt) once without acting on the answer, so providers with prompt caching store that prefix and the tool index and tokenizer are ready. Run it shortly before a scheduled run to take that work off the run's critical path; provider caches typically expire within minutes to an hour."
+    )]
+    Warm {
+        /// Recipe name or full path to the recipe file
+        #[arg(
+            long = "recipe",
+            value_name = "RECIPE_NAME or FULL_PATH_TO_RECIPE_FILE",
+            help = "Recipe to warm caches for"
+        )]
+        recipe: String,
+
+        #[arg(
+            long,
+            value_name = "KEY=VALUE",
+            help = "Recipe parameters, as the run will pass them",
+            action = clap::ArgAction::Append,
+            value_parser = parse_key_val,
+        )]
+        params: Vec<(String, String)>,
+
+        /// Provider the run will use, if not the recipe's or the configured one
+        #[arg(long, value_name = "PROVIDER", help = "Provider the run will use")]
+        provider: Option<String>,
+
+        /// Model the run will use, if not the recipe's or the configured one
+        #[arg(long, value_name = "MODEL", help = "Model the run will use")]
+        model: Option<String>,
+    },
+
+    /// Review multi-file changesets the developer extension applied or rejected
+    #[command(
+        about = "Show the diff of an edit changeset",
+        long_about = "Show the diff of a changeset of multi-file edits made with the developer extension's apply_changeset tool, including changesets that failed validation and were not applied. Without an ID, shows the most recent one."
+    )]
+    Diff {
+        /// Changeset to show
+        #[arg(
+            value_name = "ID",
+            help = "Changeset to show (default: the most recent)"
+        )]
+        id: Option<String>,
+
+        /// List changesets instead of showing one
+        #[arg(short, long, help = "List recorded changesets, newest first")]
+        list: bool,
+    },
+
+    /// Summarize recent sessions for people evaluating goose
+    #[command(about = "Generate a usage report of recent sessions")]
+    Report {
+        /// How far back to look
+        #[arg(
+            long,
+            default_value = "30d",
+            help = "Period to report on, e.g. 30d, 12h or 2w"
+        )]
+        since: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "markdown", help = "Report format")]
+        format: ReportFormat,
+
+        /// File to write the report to
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Write the report to a file instead of stdout"
+        )]
+        output: Option<PathBuf>,
+    },
+
     /// Manage scheduled jobs
     #[command(about = "Manage scheduled jobs", visible_alias = "sched")]
     Schedule {
@@ -668,6 +1100,18 @@ enum Command {
         #[arg(long, help = "Open browser automatically when server starts")]
         open: bool,
     },
+
+    /// Answer in a team chat as a shared agent
+    #[cfg(feature = "chat-bridge")]
+    #[command(
+        about = "Experimental: Join a Matrix room or Discord channel as a shared agent",
+        long_about = "Experimental: Join a Matrix room or Discord channel and answer there. Mentioning the agent starts a thread, and each thread is kept as its own session. What each chat user may have the agent do is set by GOOSE_BRIDGE_POLICY."
+    )]
+    Bridge {
+        /// Chat service to join
+        #[arg(value_enum, help = "Chat service to join")]
+        platform: crate::commands::bridge::BridgePlatform,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -690,6 +1134,8 @@ pub struct RecipeInfo {
     pub sub_recipes: Option<Vec<goose::recipe::SubRecipe>>,
     pub final_output_response: Option<goose::recipe::Response>,
     pub retry_config: Option<goose::agents::types::RetryConfig>,
+    /// Where the lockfile of a pinned recipe lives
+    pub lockfile: Option<PathBuf>,
 }
 
 pub async fn cli() -> Result<()> {
@@ -703,6 +1149,8 @@ pub async fn cli() -> Result<()> {
     let command_name = match &cli.command {
         Some(Command::Configure {}) => "configure",
         Some(Command::Info { .. }) => "info",
+        Some(Command::Doctor { .. }) => "doctor",
+        Some(Command::Extension { .. }) => "extension",
         Some(Command::Mcp { .. }) => "mcp",
         Some(Command::Session { .. }) => "session",
         Some(Command::Project {}) => "project",
@@ -712,7 +1160,13 @@ pub async fn cli() -> Result<()> {
         Some(Command::Update { .. }) => "update",
         Some(Command::Bench { .. }) => "bench",
         Some(Command::Recipe { .. }) => "recipe",
+        Some(Command::Report { .. }) => "report",
+        Some(Command::Room { .. }) => "room",
+        Some(Command::Warm { .. }) => "warm",
+        Some(Command::Diff { .. }) => "diff",
         Some(Command::Web { .. }) => "web",
+        #[cfg(feature = "chat-bridge")]
+        Some(Command::Bridge { .. }) => "bridge",
         None => "default_session",
     };
 
@@ -731,6 +1185,10 @@ pub async fn cli() -> Result<()> {
             handle_info(verbose)?;
             return Ok(());
         }
+        Some(Command::Doctor { format }) => {
+            handle_doctor(&format).await?;
+            return Ok(());
+        }
         Some(Command::Mcp { name }) => {
             let _ = run_server(&name).await;
         }
@@ -752,15 +1210,49 @@ pub async fn cli() -> Result<()> {
                     verbose,
                     format,
                     ascending,
+                    tags,
+                    properties,
                 }) => {
-                    handle_session_list(verbose, format, ascending)?;
+                    handle_session_list(verbose, format, ascending, &tags, &properties)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Tag {
+                    identifier,
+                    add,
+                    remove,
+                    set,
+                    unset,
+                }) => {
+                    let session_identifier = if let Some(id) = identifier {
+                        extract_identifier(id)
+                    } else {
+                        match crate::commands::session::prompt_interactive_session_selection() {
+                            Ok(id) => id,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                return Ok(());
+                            }
+                        }
+                    };
+                    crate::commands::session::handle_session_tag(
+                        session_identifier,
+                        add,
+                        remove,
+                        set,
+                        unset,
+                    )
+                    .await?;
                     Ok(())
                 }
                 Some(SessionCommand::Remove { id, regex }) => {
                     handle_session_remove(id, regex)?;
                     return Ok(());
                 }
-                Some(SessionCommand::Export { identifier, output }) => {
+                Some(SessionCommand::Export {
+                    identifier,
+                    format,
+                    output,
+                }) => {
                     let session_identifier = if let Some(id) = identifier {
                         extract_identifier(id)
                     } else {
@@ -774,7 +1266,115 @@ pub async fn cli() -> Result<()> {
                         }
                     };
 
-                    crate::commands::session::handle_session_export(session_identifier, output)?;
+                    crate::commands::session::handle_session_export(
+                        session_identifier,
+                        format,
+                        output,
+                    )?;
+                    Ok(())
+                }
+                Some(SessionCommand::Inspect { identifier, format }) => {
+                    let session_identifier = if let Some(id) = identifier {
+                        extract_identifier(id)
+                    } else {
+                        match crate::commands::session::prompt_interactive_session_selection() {
+                            Ok(id) => id,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                return Ok(());
+                            }
+                        }
+                    };
+
+                    crate::commands::session::handle_session_inspect(session_identifier, format)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Cost { identifier, format }) => {
+                    let session_identifier = if let Some(id) = identifier {
+                        extract_identifier(id)
+                    } else {
+                        match crate::commands::session::prompt_interactive_session_selection() {
+                            Ok(id) => id,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                return Ok(());
+                            }
+                        }
+                    };
+
+                    crate::commands::session::handle_session_cost(session_identifier, format)
+                        .await?;
+                    Ok(())
+                }
+                Some(SessionCommand::Import {
+                    bundle,
+                    name,
+                    working_dir,
+                    apply_config,
+                }) => {
+                    crate::commands::session::handle_session_import(
+                        &bundle,
+                        name,
+                        working_dir,
+                        apply_config,
+                    )?;
+                    Ok(())
+                }
+                Some(SessionCommand::Diff { a, b, format }) => {
+                    crate::commands::session::handle_session_diff(
+                        session_identifier_from_arg(a),
+                        session_identifier_from_arg(b),
+                        format,
+                    )?;
+                    Ok(())
+                }
+                Some(SessionCommand::Replay {
+                    session,
+                    live,
+                    provider,
+                    model,
+                    format,
+                }) => {
+                    crate::commands::session::handle_session_replay(
+                        session_identifier_from_arg(session),
+                        live,
+                        provider,
+                        model,
+                        format,
+                    )
+                    .await?;
+                    Ok(())
+                }
+                Some(SessionCommand::Prune {
+                    max_age_days,
+                    max_count,
+                    max_size_mb,
+                    dry_run,
+                    yes,
+                }) => {
+                    crate::commands::session::handle_session_prune(
+                        max_age_days,
+                        max_count,
+                        max_size_mb,
+                        dry_run,
+                        yes,
+                    )?;
+                    Ok(())
+                }
+                Some(SessionCommand::Migrate { remove_files }) => {
+                    crate::commands::session::handle_session_migrate(remove_files)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Search {
+                    query,
+                    limit,
+                    format,
+                }) => {
+                    crate::commands::session::handle_session_search(
+                        &query.join(" "),
+                        limit,
+                        format,
+                    )?;
                     Ok(())
                 }
                 None => {
@@ -889,6 +1489,7 @@ pub async fn cli() -> Result<()> {
             render_recipe,
             scheduled_job_id,
             quiet,
+            batch,
             additional_sub_recipes,
             provider,
             model,
@@ -962,6 +1563,25 @@ pub async fn cli() -> Result<()> {
                 }
             };
 
+            let mut settings = recipe_info
+                .as_ref()
+                .and_then(|r| r.session_settings.clone());
+            if batch {
+                settings.get_or_insert_with(SessionSettings::default).batch = Some(true);
+            }
+            let lockfile = recipe_info.as_ref().and_then(|r| r.lockfile.clone());
+            let (provider, model) = if lockfile.is_some() && (provider.is_some() || model.is_some())
+            {
+                eprintln!("Ignoring --provider and --model, the recipe pins its own");
+                (None, None)
+            } else {
+                (provider, model)
+            };
+            let lock_settings = settings.clone();
+
+            let quiet = !interactive && (quiet || session::quiet_from_config());
+            session::set_quiet(quiet);
+
             let mut session = build_session(SessionBuilderConfig {
                 identifier: identifier.map(extract_identifier),
                 resume,
@@ -972,9 +1592,7 @@ pub async fn cli() -> Result<()> {
                 builtins,
                 extensions_override: input_config.extensions_override,
                 additional_system_prompt: input_config.additional_system_prompt,
-                settings: recipe_info
-                    .as_ref()
-                    .and_then(|r| r.session_settings.clone()),
+                settings,
                 provider,
                 model,
                 debug,
@@ -992,7 +1610,10 @@ pub async fn cli() -> Result<()> {
             .await;
 
             if interactive {
-                let _ = session.interactive(input_config.contents).await;
+                let result = session.interactive(input_config.contents).await;
+                if let (Ok(()), Some(lockfile)) = (result, &lockfile) {
+                    lock_recipe_run(lockfile, lock_settings.as_ref())?;
+                }
             } else if let Some(contents) = input_config.contents {
                 let session_start = std::time::Instant::now();
                 let session_type = if recipe_info.is_some() {
@@ -1044,6 +1665,9 @@ pub async fn cli() -> Result<()> {
                 }
 
                 result?;
+                if let Some(lockfile) = &lockfile {
+                    lock_recipe_run(lockfile, lock_settings.as_ref())?;
+                }
             } else {
                 eprintln!("Error: no text provided for prompt in headless mode");
                 std::process::exit(1);
@@ -1111,6 +1735,25 @@ pub async fn cli() -> Result<()> {
                 BenchCommand::GenerateLeaderboard { benchmark_dir } => {
                     MetricAggregator::generate_csv_from_benchmark_dir(&benchmark_dir)?
                 }
+                BenchCommand::Compaction {
+                    turns,
+                    target,
+                    strategies,
+                    seed,
+                    with_model,
+                    output,
+                } => {
+                    CompactionRunner {
+                        turns,
+                        seed,
+                        target,
+                        strategies: CompactionRunner::parse_strategies(&strategies)?,
+                        with_model,
+                        output,
+                    }
+                    .run()
+                    .await?;
+                }
             }
             return Ok(());
         }
@@ -1128,10 +1771,50 @@ pub async fn cli() -> Result<()> {
             }
             return Ok(());
         }
+        Some(Command::Extension { command }) => {
+            match command {
+                ExtensionCommand::Update { names } => handle_extension_update(names).await?,
+            }
+            return Ok(());
+        }
+        Some(Command::Report {
+            since,
+            format,
+            output,
+        }) => {
+            handle_report(&since, format, output).await?;
+            return Ok(());
+        }
+        Some(Command::Room {
+            config,
+            task,
+            output,
+        }) => {
+            handle_room(config, task, output).await?;
+            return Ok(());
+        }
+        Some(Command::Warm {
+            recipe,
+            params,
+            provider,
+            model,
+        }) => {
+            handle_warm(recipe, params, provider, model).await?;
+            return Ok(());
+        }
+        Some(Command::Diff { id, list }) => {
+            handle_diff(id, list)?;
+            return Ok(());
+        }
         Some(Command::Web { port, host, open }) => {
             crate::commands::web::handle_web(port, host, open).await?;
             return Ok(());
         }
+        #[cfg(feature = "chat-bridge")]
+        Some(Command::Bridge { platform }) => {
+            crate::commands::bridge::handle_bridge(platform).await?;
+            return Ok(());
+        }
         None => {
             return if !Config::global().exists() {
                 let _ = handle_configure().await;
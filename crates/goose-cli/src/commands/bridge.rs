@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use goose::agents::Agent;
+use goose::config::{Config, ExtensionConfigManager};
+use goose::model::ModelConfig;
+
+use crate::bridge::discord::Discord;
+use crate::bridge::matrix::Matrix;
+use crate::bridge::policy::BridgePolicy;
+use crate::bridge::Bridge;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum BridgePlatform {
+    Matrix,
+    Discord,
+}
+
+/// Join the configured chat and answer there until the connection drops
+pub async fn handle_bridge(platform: BridgePlatform) -> Result<()> {
+    crate::logging::setup_logging(Some("goose-bridge"), None)?;
+
+    let config = Config::global();
+    let provider_name: String = config
+        .get_param("GOOSE_PROVIDER")
+        .context("No provider configured. Run 'goose configure' first")?;
+    let model: String = config
+        .get_param("GOOSE_MODEL")
+        .context("No model configured. Run 'goose configure' first")?;
+    let policy = BridgePolicy::from_config()?;
+
+    let agent = Agent::new();
+    let provider = goose::providers::create(&provider_name, ModelConfig::new(&model)?)?;
+    agent.update_provider(provider).await?;
+
+    for extension in ExtensionConfigManager::get_all()? {
+        if extension.enabled {
+            if let Err(e) = agent.add_extension(extension.config.clone()).await {
+                eprintln!(
+                    "Warning: Failed to load extension {}: {}",
+                    extension.config.name(),
+                    e
+                );
+            }
+        }
+    }
+
+    println!("\n🪿 Starting Goose chat bridge ({:?})", platform);
+    println!("   Provider: {} | Model: {}", provider_name, model);
+    println!(
+        "   Working directory: {}",
+        std::env::current_dir()?.display()
+    );
+    println!("   Press Ctrl+C to stop\n");
+
+    match platform {
+        BridgePlatform::Matrix => {
+            Bridge::new(Matrix::from_config().await?, agent, policy)
+                .run()
+                .await
+        }
+        BridgePlatform::Discord => {
+            Bridge::new(Discord::from_config().await?, agent, policy)
+                .run()
+                .await
+        }
+    }
+}
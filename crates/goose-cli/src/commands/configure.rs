@@ -7,6 +7,7 @@ use goose::agents::platform_tools::{
 };
 use goose::agents::Agent;
 use goose::agents::{extension::Envs, ExtensionConfig};
+use goose::config::extension_lock::ExtensionLock;
 use goose::config::extensions::name_to_key;
 use goose::config::permission::PermissionLevel;
 use goose::config::{
@@ -21,6 +22,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
 
+use crate::commands::doctor::print_diagnostic;
 use crate::recipes::github_recipe::GOOSE_RECIPE_GITHUB_REPO_CONFIG_KEY;
 
 // useful for light themes where there is no dicernible colour contrast between
@@ -33,6 +35,8 @@ fn get_display_name(extension_id: &str) -> String {
         "computercontroller" => "Computer Controller".to_string(),
         "googledrive" => "Google Drive".to_string(),
         "memory" => "Memory".to_string(),
+        "email" => "Email".to_string(),
+        "calendar" => "Calendar".to_string(),
         "tutorial" => "Tutorial".to_string(),
         "jetbrains" => "JetBrains".to_string(),
         // Add other extensions as needed
@@ -457,10 +461,17 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
 
     let model_config = goose::model::ModelConfig::new(&model)?
         .with_max_tokens(Some(50))
-        .with_toolshim(toolshim_enabled)
-        .with_toolshim_model(std::env::var("GOOSE_TOOLSHIM_OLLAMA_MODEL").ok());
+        .with_toolshim(toolshim_enabled);
 
     let provider = create(provider_name, model_config)?;
+    // Providers can turn toolshim on for models the API reports have no native tool support
+    if let Err(e) = goose::providers::model_registry::ModelRegistry::global()
+        .refresh_provider(provider_name, provider.as_ref())
+        .await
+    {
+        tracing::debug!("Failed to refresh model registry: {}", e);
+    }
+    let toolshim_enabled = provider.get_model_config().toolshim;
 
     let messages =
         vec![Message::user().with_text("What is the weather like in San Francisco today?")];
@@ -506,6 +517,10 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
         }
         Err(e) => {
             spin.stop(style(e.to_string()).red());
+            // Tell the user which part of the setup is wrong rather than just that it failed
+            for diagnostic in provider.health_check().await {
+                print_diagnostic(&diagnostic);
+            }
             cliclack::outro(style("Failed to configure provider: init chat completion request with tool did not succeed.").on_red().white())?;
             Ok(false)
         }
@@ -593,7 +608,12 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
     match extension_type {
         // TODO we'll want a place to collect all these options, maybe just an enum in goose-mcp
         "built-in" => {
-            let extension = cliclack::select("Which built-in extension would you like to enable?")
+            let select = cliclack::select("Which built-in extension would you like to enable?")
+                .item(
+                    "calendar",
+                    "Calendar",
+                    "List events, find free time and create events - additional config required",
+                )
                 .item(
                     "computercontroller",
                     "Computer Controller",
@@ -603,7 +623,14 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     "developer",
                     "Developer Tools",
                     "Code editing and shell access",
-                )
+                );
+            #[cfg(feature = "email")]
+            let select = select.item(
+                "email",
+                "Email",
+                "Search, read and send email over IMAP/SMTP - additional config required",
+            );
+            let extension = select
                 .item(
                     "googledrive",
                     "Google Drive",
@@ -751,6 +778,14 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                 },
             })?;
 
+            // Projects that lock their extensions run unlocked ones unpinned, with a warning
+            if ExtensionLock::path(&std::env::current_dir()?).exists() {
+                cliclack::log::info(format!(
+                    "This project locks its extensions; run `goose extension update {}` to lock it",
+                    name
+                ))?;
+            }
+
             cliclack::outro(format!("Added {} extension", style(name).green()))?;
         }
         "sse" => {
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use console::style;
+use goose_mcp::changeset::{Changeset, ChangesetStatus};
+
+/// Print a recorded changeset's diff, or list the recorded changesets
+pub fn handle_diff(id: Option<String>, list: bool) -> Result<()> {
+    if list {
+        let changesets = Changeset::list()?;
+        if changesets.is_empty() {
+            println!("No changesets recorded");
+        }
+        for changeset in changesets {
+            println!(
+                "{}  {}  {} files  {}",
+                changeset.id,
+                status_label(changeset.status),
+                changeset.changes.len(),
+                changeset.description.as_deref().unwrap_or("")
+            );
+        }
+        return Ok(());
+    }
+
+    let changeset = match id {
+        Some(id) => Changeset::load(&id)?,
+        None => Changeset::list()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No changesets recorded"))?,
+    };
+
+    println!(
+        "{} {} ({})",
+        style("changeset").bold(),
+        changeset.id,
+        status_label(changeset.status)
+    );
+    if let Some(description) = &changeset.description {
+        println!("{}", description);
+    }
+    if let Some(error) = &changeset.error {
+        println!("{} {}", style("Not applied:").red(), error);
+    }
+    println!();
+    for line in changeset.diff().lines() {
+        let styled = if line.starts_with("+++") || line.starts_with("---") {
+            style(line).bold()
+        } else if line.starts_with('+') {
+            style(line).green()
+        } else if line.starts_with('-') {
+            style(line).red()
+        } else if line.starts_with("@@") {
+            style(line).cyan()
+        } else {
+            style(line)
+        };
+        println!("{}", styled);
+    }
+    Ok(())
+}
+
+fn status_label(status: ChangesetStatus) -> &'static str {
+    match status {
+        ChangesetStatus::Applied => "applied",
+        ChangesetStatus::Rejected => "rejected",
+    }
+}
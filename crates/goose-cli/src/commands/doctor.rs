@@ -0,0 +1,44 @@
+use anyhow::{bail, Result};
+use console::style;
+use goose::providers::health::{self, Diagnostic, HealthReport, HealthStatus};
+
+/// Check the configured provider and model up front and print what, if anything, is wrong
+pub async fn handle_doctor(format: &str) -> Result<()> {
+    let report = health::check_configured().await;
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        _ => print_report(&report),
+    }
+    if !report.is_healthy() {
+        bail!("{} is not ready to use", report.provider);
+    }
+    Ok(())
+}
+
+pub fn print_report(report: &HealthReport) {
+    println!(
+        "{} {} / {}",
+        style("Checking").bold(),
+        report.provider,
+        report.model
+    );
+    for diagnostic in &report.diagnostics {
+        print_diagnostic(diagnostic);
+    }
+}
+
+pub fn print_diagnostic(diagnostic: &Diagnostic) {
+    let mark = match diagnostic.status {
+        HealthStatus::Pass => style("✓").green(),
+        HealthStatus::Warn => style("!").yellow(),
+        HealthStatus::Fail => style("✗").red(),
+    };
+    let check = serde_json::to_value(diagnostic.check)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    println!("  {} {:<14} {}", mark, check, diagnostic.message);
+    if let Some(hint) = &diagnostic.hint {
+        println!("    {}", style(hint).dim());
+    }
+}
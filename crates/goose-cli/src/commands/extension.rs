@@ -0,0 +1,60 @@
+use anyhow::{bail, Result};
+use console::style;
+use goose::config::extension_lock::{self, ExtensionLock};
+use goose::config::ExtensionConfigManager;
+
+/// Resolve the configured extensions again and write them to the project's lockfile. With
+/// names, only those extensions are refreshed and the rest of the lock is kept.
+pub async fn handle_extension_update(names: Vec<String>) -> Result<()> {
+    let project_dir = std::env::current_dir()?;
+    // Refreshing some extensions keeps the rest, so the existing lock has to verify
+    let mut lock = if names.is_empty() {
+        ExtensionLock::default()
+    } else {
+        ExtensionLock::load(&project_dir)?.unwrap_or_default()
+    };
+
+    let extensions = ExtensionConfigManager::get_all()?;
+    for name in &names {
+        if !extensions.iter().any(|entry| &entry.config.name() == name) {
+            bail!("No extension named {} is configured", name);
+        }
+    }
+
+    for entry in extensions {
+        let name = entry.config.name();
+        let selected = if names.is_empty() {
+            entry.enabled
+        } else {
+            names.contains(&name)
+        };
+        if !selected {
+            continue;
+        }
+        match extension_lock::resolve(&entry.config).await? {
+            Some(locked) => {
+                let resolved = match &locked.package {
+                    Some(package) => package.pinned_spec(),
+                    None => format!(
+                        "{} (sha256 {})",
+                        locked.cmd,
+                        locked.sha256.as_deref().unwrap_or("-")
+                    ),
+                };
+                println!("  {} {}", style(&name).bold(), resolved);
+                lock.extensions.insert(name, locked);
+            }
+            None => {
+                lock.extensions.remove(&name);
+            }
+        }
+    }
+
+    let path = lock.save(&project_dir)?;
+    println!(
+        "Locked {} extensions in {}",
+        lock.extensions.len(),
+        path.display()
+    );
+    Ok(())
+}
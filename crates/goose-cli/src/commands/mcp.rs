@@ -1,6 +1,10 @@
 use anyhow::Result;
+use goose::config::Config;
+use goose::session::database::SessionDatabase;
+use goose::session::store::StorageBackend;
 use goose_mcp::{
-    ComputerControllerRouter, DeveloperRouter, GoogleDriveRouter, MemoryRouter, TutorialRouter,
+    CalendarRouter, ComputerControllerRouter, DeveloperRouter, GoogleDriveRouter, MemoryRouter,
+    TutorialRouter,
 };
 use mcp_server::router::RouterService;
 use mcp_server::{BoundedService, ByteTransport, Server};
@@ -29,7 +33,19 @@ pub async fn run_server(name: &str) -> Result<()> {
             let router = GoogleDriveRouter::new().await;
             Some(Box::new(RouterService(router)))
         }
-        "memory" => Some(Box::new(RouterService(MemoryRouter::new()))),
+        "memory" => {
+            // Memories follow sessions into the SQLite database when it is in use
+            let router = match StorageBackend::from_config(Config::global()) {
+                StorageBackend::Sqlite => {
+                    MemoryRouter::with_database(SessionDatabase::default_path()?)
+                }
+                _ => MemoryRouter::new(),
+            };
+            Some(Box::new(RouterService(router)))
+        }
+        #[cfg(feature = "email")]
+        "email" => Some(Box::new(RouterService(goose_mcp::EmailRouter::new()))),
+        "calendar" => Some(Box::new(RouterService(CalendarRouter::new()))),
         "tutorial" => Some(Box::new(RouterService(TutorialRouter::new()))),
         _ => None,
     };
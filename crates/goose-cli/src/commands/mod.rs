@@ -1,10 +1,18 @@
 pub mod bench;
+#[cfg(feature = "chat-bridge")]
+pub mod bridge;
 pub mod configure;
+pub mod diff;
+pub mod doctor;
+pub mod extension;
 pub mod info;
 pub mod mcp;
 pub mod project;
 pub mod recipe;
+pub mod report;
+pub mod room;
 pub mod schedule;
 pub mod session;
 pub mod update;
+pub mod warm;
 pub mod web;
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use goose::session::report::{parse_period, SessionReport};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+pub async fn handle_report(
+    since: &str,
+    format: ReportFormat,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let report = SessionReport::collect(parse_period(since)?).await?;
+    let rendered = match format {
+        ReportFormat::Markdown => report.to_markdown(),
+        ReportFormat::Html => report.to_html(),
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            println!(
+                "Wrote report of {} sessions to {}",
+                report.sessions.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
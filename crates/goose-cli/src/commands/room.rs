@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use console::style;
+use goose::agents::room::{Room, RoomConfig, RoomEvent, RoomOutcome};
+
+/// Run a room of agents on a task, printing the thread as it grows and optionally writing the
+/// shared artifacts to a directory at the end
+pub async fn handle_room(config: PathBuf, task: String, output: Option<PathBuf>) -> Result<()> {
+    let config = RoomConfig::from_file(&config)?;
+    let budget = config.token_budget;
+    let mut room = Room::new(config).await?;
+
+    let outcome = room
+        .run(&task, |event| match event {
+            RoomEvent::Turn(turn) => {
+                println!("\n{}", style(format!("[{}]", turn.speaker)).cyan().bold());
+                println!("{}", turn.text);
+            }
+            RoomEvent::Artifact { name, author } => {
+                println!(
+                    "{}",
+                    style(format!("{} updated artifact {}", author, name)).dim()
+                );
+            }
+            RoomEvent::Finished(_) => {}
+        })
+        .await?;
+
+    let reason = match outcome {
+        RoomOutcome::Done => "the coordinator considered the task done",
+        RoomOutcome::TurnLimit => "the turn limit was reached",
+        RoomOutcome::BudgetExhausted => "the token budget ran out",
+    };
+    let used = match budget {
+        Some(budget) => format!("{} of {} tokens", room.tokens_used(), budget),
+        None => format!("{} tokens", room.tokens_used()),
+    };
+    println!(
+        "\n{}",
+        style(format!("Room finished: {} ({} used)", reason, used)).bold()
+    );
+
+    if let Some(dir) = output {
+        write_artifacts(&room, &dir)?;
+    } else if !room.artifacts().is_empty() {
+        let names = room.artifacts().keys().cloned().collect::<Vec<_>>();
+        println!("Artifacts: {}", names.join(", "));
+    }
+    Ok(())
+}
+
+fn write_artifacts(room: &Room, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for (name, artifact) in room.artifacts() {
+        // Artifact names come from the model, so keep them inside the directory
+        let file_name = Path::new(name)
+            .file_name()
+            .map(|file| file.to_string_lossy().to_string())
+            .unwrap_or_else(|| "artifact".to_string());
+        let path = dir.join(file_name);
+        std::fs::write(&path, &artifact.content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
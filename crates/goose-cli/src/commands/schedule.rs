@@ -100,6 +100,7 @@ pub async fn handle_schedule_add(
         current_session_id: None,
         process_start_time: None,
         execution_mode: Some("background".to_string()), // Default to background for CLI
+        timezone: None,
     };
 
     let scheduler_storage_path =
@@ -1,15 +1,35 @@
-use crate::session::message_to_markdown;
 use anyhow::{Context, Result};
 use cliclack::{confirm, multiselect, select};
+use goose::agents::trust_policy::{message_origin, TrustPolicy};
+use goose::agents::Agent;
+use goose::providers::replay::ReplayProvider;
+use goose::session::bundle::BUNDLE_EXTENSION;
 use goose::session::info::{get_valid_sorted_sessions, SessionInfo, SortOrder};
-use goose::session::{self, Identifier};
+use goose::session::replay::{self, RecordedTools};
+use goose::session::retention::{self, RetentionPolicy};
+use goose::session::store;
+use goose::session::{
+    self, Identifier, SearchIndex, SessionBundle, SessionCost, SessionDiff, SessionExporter,
+};
 use goose::utils::safe_truncate;
 use regex::Regex;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::session::{build_session, SessionBuilderConfig};
 
 const TRUNCATED_DESC_LENGTH: usize = 60;
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Md,
+    Html,
+    Json,
+    Bundle,
+}
+
 pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
     println!("The following sessions will be removed:");
     for session in &sessions {
@@ -22,8 +42,8 @@ pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
 
     if should_delete {
         for session in sessions {
-            fs::remove_file(session.path.clone())
-                .with_context(|| format!("Failed to remove session file '{}'", session.path))?;
+            session::delete_session(Path::new(&session.path))
+                .with_context(|| format!("Failed to remove session '{}'", session.path))?;
             println!("Session `{}` removed.", session.id);
         }
     } else {
@@ -46,10 +66,10 @@ fn prompt_interactive_session_removal(sessions: &[SessionInfo]) -> Result<Vec<Se
     let display_map: std::collections::HashMap<String, SessionInfo> = sessions
         .iter()
         .map(|s| {
-            let desc = if s.metadata.description.is_empty() {
+            let desc = if s.metadata.display_name().is_empty() {
                 "(no description)"
             } else {
-                &s.metadata.description
+                s.metadata.display_name()
             };
             let truncated_desc = safe_truncate(desc, TRUNCATED_DESC_LENGTH);
             let display_text = format!("{} - {} ({})", s.modified, truncated_desc, s.id);
@@ -115,15 +135,24 @@ pub fn handle_session_remove(id: Option<String>, regex_string: Option<String>) -
     remove_sessions(matched_sessions)
 }
 
-pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Result<()> {
+pub fn handle_session_list(
+    verbose: bool,
+    format: String,
+    ascending: bool,
+    tags: &[String],
+    properties: &[(String, String)],
+) -> Result<()> {
     let sort_order = if ascending {
         SortOrder::Ascending
     } else {
         SortOrder::Descending
     };
 
-    let sessions = match get_valid_sorted_sessions(sort_order) {
-        Ok(sessions) => sessions,
+    let sessions: Vec<SessionInfo> = match get_valid_sorted_sessions(sort_order) {
+        Ok(sessions) => sessions
+            .into_iter()
+            .filter(|info| info.metadata.matches(tags, properties))
+            .collect(),
         Err(e) => {
             tracing::error!("Failed to list sessions: {:?}", e);
             return Err(anyhow::anyhow!("Failed to list sessions"));
@@ -147,15 +176,21 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
                     modified,
                 } in sessions
                 {
-                    let description = if metadata.description.is_empty() {
+                    let description = if metadata.display_name().is_empty() {
                         "(none)"
                     } else {
-                        &metadata.description
+                        metadata.display_name()
                     };
-                    let output = format!("{} - {} - {}", id, description, modified);
+                    let mut output = format!("{} - {} - {}", id, description, modified);
+                    if !metadata.tags.is_empty() {
+                        output.push_str(&format!(" [{}]", metadata.tags.join(", ")));
+                    }
                     if verbose {
                         println!("  {}", output);
                         println!("    Path: {}", path);
+                        for (key, value) in &metadata.properties {
+                            println!("    {}: {}", key, value);
+                        }
                     } else {
                         println!("{}", output);
                     }
@@ -166,12 +201,488 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
     Ok(())
 }
 
-/// Export a session to Markdown without creating a full Session object
+/// Add and remove a session's tags and key/value metadata, then show what it has
+pub async fn handle_session_tag(
+    identifier: Identifier,
+    add: Vec<String>,
+    remove: Vec<String>,
+    set: Vec<(String, String)>,
+    unset: Vec<String>,
+) -> Result<()> {
+    let session_file_path = goose::session::get_path(identifier)
+        .map_err(|e| anyhow::anyhow!("Invalid session identifier: {}", e))?;
+    if !session::session_exists(&session_file_path) {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            session_file_path.display()
+        ));
+    }
+
+    let mut metadata = session::read_metadata(&session_file_path)?;
+    let changed = !(add.is_empty() && remove.is_empty() && set.is_empty() && unset.is_empty());
+    for tag in add.iter().filter(|tag| !tag.trim().is_empty()) {
+        metadata.add_tag(tag);
+    }
+    for tag in &remove {
+        metadata.remove_tag(tag);
+    }
+    for (key, value) in set {
+        metadata.properties.insert(key, value);
+    }
+    for key in &unset {
+        metadata.properties.remove(key);
+    }
+    if changed {
+        session::update_metadata(&session_file_path, &metadata).await?;
+    }
+
+    if metadata.tags.is_empty() {
+        println!("Tags: (none)");
+    } else {
+        println!("Tags: {}", metadata.tags.join(", "));
+    }
+    for (key, value) in &metadata.properties {
+        println!("{}: {}", key, value);
+    }
+    Ok(())
+}
+
+/// List the messages of a session with the origin and trust level of each
+pub fn handle_session_inspect(identifier: Identifier, format: String) -> Result<()> {
+    let session_file_path = goose::session::get_path(identifier)
+        .map_err(|e| anyhow::anyhow!("Invalid session identifier: {}", e))?;
+    if !session::session_exists(&session_file_path) {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            session_file_path.display()
+        ));
+    }
+    let messages = goose::session::read_messages(&session_file_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read session messages: {}", e))?;
+
+    let policy = TrustPolicy::from_config();
+    let entries: Vec<serde_json::Value> = messages
+        .messages()
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let origin = message_origin(message);
+            let trust = origin.as_ref().map(|origin| policy.trust_of(origin));
+            serde_json::json!({
+                "index": index,
+                "role": message.role,
+                "origin": origin,
+                "trust": trust,
+                "summary": safe_truncate(&message_summary(message), TRUNCATED_DESC_LENGTH),
+            })
+        })
+        .collect();
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&entries)?),
+        _ => {
+            for (message, entry) in messages.messages().iter().zip(&entries) {
+                let origin = message_origin(message);
+                let tag = match &origin {
+                    Some(origin) => format!("{} ({})", origin, policy.trust_of(origin)),
+                    None => "-".to_string(),
+                };
+                println!(
+                    "{:>4}  {:<9}  {:<30}  {}",
+                    entry["index"],
+                    format!("{:?}", message.role).to_lowercase(),
+                    tag,
+                    entry["summary"].as_str().unwrap_or_default()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn handle_session_cost(identifier: Identifier, format: String) -> Result<()> {
+    let session_file_path = goose::session::get_path(identifier)
+        .map_err(|e| anyhow::anyhow!("Invalid session identifier: {}", e))?;
+    if !session::session_exists(&session_file_path) {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            session_file_path.display()
+        ));
+    }
+    let metadata = goose::session::read_metadata(&session_file_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read session metadata: {}", e))?;
+    let cost = SessionCost::for_session(&metadata).await;
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&cost)?),
+        _ => {
+            let format_cost = |cost: Option<f64>| {
+                cost.map(|cost| format!("${:.4}", cost))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            println!(
+                "{:<30}  {:>8}  {:>12}  {:>12}  {:>12}  {:>10}",
+                "Model", "Requests", "Input", "Output", "Cached", "Cost"
+            );
+            for spend in &cost.models {
+                println!(
+                    "{:<30}  {:>8}  {:>12}  {:>12}  {:>12}  {:>10}",
+                    safe_truncate(&spend.model, 30),
+                    spend.usage.requests,
+                    spend.usage.input_tokens,
+                    spend.usage.output_tokens,
+                    spend.usage.cache_read_tokens,
+                    format_cost(spend.cost)
+                );
+            }
+            println!(
+                "{:<30}  {:>8}  {:>12}  {:>12}  {:>12}  {:>10}",
+                "Total",
+                cost.totals.requests,
+                cost.totals.input_tokens,
+                cost.totals.output_tokens,
+                cost.totals.cache_read_tokens,
+                format_cost(cost.total_cost)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Compare two sessions' recorded context and conversations
+pub fn handle_session_diff(a: Identifier, b: Identifier, format: String) -> Result<()> {
+    let read = |identifier: Identifier| -> Result<_> {
+        let session_file_path = goose::session::get_path(identifier)
+            .map_err(|e| anyhow::anyhow!("Invalid session identifier: {}", e))?;
+        if !session::session_exists(&session_file_path) {
+            return Err(anyhow::anyhow!(
+                "Session file not found (expected path: {})",
+                session_file_path.display()
+            ));
+        }
+        let metadata = goose::session::read_metadata(&session_file_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read session metadata: {}", e))?;
+        let messages = goose::session::read_messages(&session_file_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read session messages: {}", e))?;
+        Ok((metadata, messages))
+    };
+    let (metadata_a, messages_a) = read(a)?;
+    let (metadata_b, messages_b) = read(b)?;
+    let diff = SessionDiff::new((&metadata_a, &messages_a), (&metadata_b, &messages_b));
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&diff)?),
+        _ => print!("{}", diff),
+    }
+    Ok(())
+}
+
+/// Search all sessions, bringing the index up to date with sessions it hasn't seen saved first
+pub fn handle_session_search(query: &str, limit: usize, format: String) -> Result<()> {
+    let index = SearchIndex::global().context("Failed to open the session search index")?;
+    index
+        .refresh()
+        .context("Failed to update the session search index")?;
+    let hits = index.search(query, limit)?;
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&hits)?),
+        _ => {
+            if hits.is_empty() {
+                println!("No sessions match '{}'", query);
+            }
+            for hit in &hits {
+                let timestamp = chrono::DateTime::from_timestamp(hit.timestamp, 0)
+                    .map(|time| {
+                        time.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_else(|| "-".to_string());
+                println!(
+                    "{:<20}  {:<16}  #{:<4}  {}",
+                    hit.session,
+                    timestamp,
+                    hit.message_index,
+                    hit.snippet.replace('\n', " ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Send a session's user messages to the agent again, answering from the recording or, when
+/// `live`, from a real model with real tools, and compare the replay with the recording
+pub async fn handle_session_replay(
+    identifier: Identifier,
+    live: bool,
+    provider: Option<String>,
+    model: Option<String>,
+    format: String,
+) -> Result<()> {
+    let session_file_path = goose::session::get_path(identifier)
+        .map_err(|e| anyhow::anyhow!("Invalid session identifier: {}", e))?;
+    if !session::session_exists(&session_file_path) {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            session_file_path.display()
+        ));
+    }
+    let recorded = goose::session::read_messages(&session_file_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read session messages: {}", e))?;
+    let inputs = replay::user_inputs(&recorded);
+    if inputs.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The session has no user messages to replay"
+        ));
+    }
+
+    let mut replaying = if live {
+        build_session(SessionBuilderConfig {
+            no_session: true,
+            provider,
+            model,
+            quiet: true,
+            ..Default::default()
+        })
+        .await
+    } else {
+        let agent = Agent::new();
+        {
+            let mut extension_manager = agent.extension_manager.write().await;
+            for (name, client) in RecordedTools::from_conversation(&recorded) {
+                extension_manager.add_client(name, Box::new(client));
+            }
+        }
+        agent
+            .update_provider(Arc::new(ReplayProvider::new(&recorded)))
+            .await?;
+        crate::session::Session::new(agent, None, false, None, None, None, None)
+    };
+
+    for input in inputs {
+        if let Err(e) = replaying
+            .process_message(input, CancellationToken::default())
+            .await
+        {
+            eprintln!("Replay stopped: {}", e);
+            break;
+        }
+    }
+
+    let comparison = replay::compare(&recorded, &replaying.message_history());
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string(&comparison)?),
+        _ => {
+            println!();
+            for (index, turn) in comparison.iter().enumerate() {
+                let Some(replayed) = &turn.replayed else {
+                    println!("Turn {}: not replayed", index + 1);
+                    continue;
+                };
+                let tools = if turn.same_tools() {
+                    "same tools".to_string()
+                } else {
+                    format!(
+                        "tools [{}] -> [{}]",
+                        turn.recorded.tools.join(", "),
+                        replayed.tools.join(", ")
+                    )
+                };
+                println!(
+                    "Turn {}: {}, reply {:.0}% similar - {}",
+                    index + 1,
+                    tools,
+                    turn.reply_similarity * 100.0,
+                    safe_truncate(&turn.recorded.input.replace('\n', " "), 60)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Store the session in a bundle so it can be resumed here
+pub fn handle_session_import(
+    bundle_path: &Path,
+    name: Option<String>,
+    working_dir: Option<PathBuf>,
+    apply_config: bool,
+) -> Result<()> {
+    let bundle = SessionBundle::read(bundle_path)?;
+    let name = name.unwrap_or_else(|| bundle.name.clone());
+    let report = bundle.import(&name, working_dir)?;
+
+    println!(
+        "Imported session '{}' ({} messages, {} tool outputs)",
+        name, report.messages, report.tool_outputs
+    );
+    if report.working_dir_replaced {
+        println!(
+            "Its working directory {} doesn't exist here, so it will resume in {}",
+            bundle.metadata.working_dir.display(),
+            report.working_dir.display()
+        );
+    }
+
+    let config = goose::config::Config::global();
+    if apply_config {
+        let applied = bundle.apply_config(config)?;
+        if !applied.is_empty() {
+            println!("Configured {}", applied.join(", "));
+        }
+    } else {
+        let provider = bundle.config.get("GOOSE_PROVIDER").and_then(|v| v.as_str());
+        let model = bundle.config.get("GOOSE_MODEL").and_then(|v| v.as_str());
+        let current_provider = config.get_param::<String>("GOOSE_PROVIDER").ok();
+        let current_model = config.get_param::<String>("GOOSE_MODEL").ok();
+        if let (Some(provider), Some(model)) = (provider, model) {
+            if current_provider.as_deref() != Some(provider)
+                || current_model.as_deref() != Some(model)
+            {
+                println!(
+                    "It ran with {} {}; pass --apply-config to take its settings where you have none",
+                    provider, model
+                );
+            }
+        }
+    }
+    println!("Resume it with: goose session --resume --name {}", name);
+    Ok(())
+}
+
+/// Remove the sessions the retention policy doesn't keep, with limits given on the command line
+/// taking the place of configured ones
+pub fn handle_session_prune(
+    max_age_days: Option<u64>,
+    max_count: Option<usize>,
+    max_size_mb: Option<u64>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let mut policy = RetentionPolicy::from_config(goose::config::Config::global());
+    if let Some(days) = max_age_days {
+        policy.max_age = Some(std::time::Duration::from_secs(days * 24 * 60 * 60));
+    }
+    if let Some(count) = max_count {
+        policy.max_count = Some(count);
+    }
+    if let Some(mb) = max_size_mb {
+        policy.max_total_bytes = Some(mb * 1024 * 1024);
+    }
+    if policy.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No retention limits: pass --max-age-days, --max-count or --max-size-mb, or set {}, {} or {}",
+            retention::RETENTION_MAX_AGE_DAYS_KEY,
+            retention::RETENTION_MAX_COUNT_KEY,
+            retention::RETENTION_MAX_SIZE_MB_KEY
+        ));
+    }
+
+    let preview = retention::prune(&policy, None, true)?;
+    if preview.pruned.is_empty() {
+        println!("No sessions to remove ({} kept)", preview.kept);
+        return Ok(());
+    }
+    for (session, reason) in &preview.pruned {
+        println!("- {} ({})", session.name, reason);
+    }
+    let megabytes = preview.freed_bytes as f64 / (1024.0 * 1024.0);
+    if dry_run {
+        println!(
+            "Would remove {} sessions ({:.1} MB), keeping {}",
+            preview.pruned.len(),
+            megabytes,
+            preview.kept
+        );
+        return Ok(());
+    }
+    if !yes
+        && !confirm(format!(
+            "Remove these {} sessions ({:.1} MB)?",
+            preview.pruned.len(),
+            megabytes
+        ))
+        .initial_value(false)
+        .interact()?
+    {
+        println!("Skipping removal of the sessions.");
+        return Ok(());
+    }
+
+    let report = retention::prune(&policy, None, false)?;
+    println!(
+        "Removed {} sessions ({:.1} MB), kept {}",
+        report.pruned.len(),
+        report.freed_bytes as f64 / (1024.0 * 1024.0),
+        report.kept
+    );
+    for (name, error) in &report.failed {
+        eprintln!("Failed to remove {}: {}", name, error);
+    }
+    Ok(())
+}
+
+/// Copy JSONL session files into the configured session store
+pub fn handle_session_migrate(remove_files: bool) -> Result<()> {
+    let Some(store) = store::active()? else {
+        return Err(anyhow::anyhow!(
+            "Sessions are stored as files; set {} to sqlite, postgres or s3 to choose a store",
+            store::SESSION_STORAGE_KEY
+        ));
+    };
+    let report = store::migrate_files(store, &session::ensure_session_dir()?, remove_files)?;
+
+    println!(
+        "Migrated {} sessions, {} already stored",
+        report.migrated, report.skipped
+    );
+    for (path, error) in &report.failed {
+        eprintln!("Failed to migrate {}: {}", path.display(), error);
+    }
+    if !report.failed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} sessions could not be migrated",
+            report.failed.len()
+        ));
+    }
+    Ok(())
+}
+
+/// A one-line description of a message's content
+fn message_summary(message: &goose::conversation::message::Message) -> String {
+    message
+        .content
+        .iter()
+        .map(|content| {
+            if let Some(text) = content.as_text() {
+                text.to_string()
+            } else if let Some(request) = content.as_tool_request() {
+                match &request.tool_call {
+                    Ok(call) => format!("[tool call {}]", call.name),
+                    Err(_) => "[invalid tool call]".to_string(),
+                }
+            } else if content.as_tool_response().is_some() {
+                "[tool result]".to_string()
+            } else {
+                "[other content]".to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('\n', " ")
+}
+
+/// Export a session without creating a full Session object
 ///
-/// This function directly reads messages from the session file and converts them to Markdown
+/// This function directly reads the session file and renders it in the requested format
 /// without creating an Agent or prompting about working directories.
-pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf>) -> Result<()> {
-    // Get the session file path
+pub fn handle_session_export(
+    identifier: Identifier,
+    format: ExportFormat,
+    output_path: Option<PathBuf>,
+) -> Result<()> {
     let session_file_path = match goose::session::get_path(identifier.clone()) {
         Ok(path) => path,
         Err(e) => {
@@ -179,7 +690,22 @@ pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf
         }
     };
 
-    if !session_file_path.exists() {
+    if format == ExportFormat::Bundle {
+        let bundle = SessionBundle::from_session(&session_file_path)?;
+        let output = output_path
+            .unwrap_or_else(|| PathBuf::from(format!("{}.{}", bundle.name, BUNDLE_EXTENSION)));
+        bundle.write(&output)?;
+        println!(
+            "Session exported to {} ({} messages, {} tool outputs, {} config values)",
+            output.display(),
+            bundle.messages.len(),
+            bundle.tool_outputs.len(),
+            bundle.config.len()
+        );
+        return Ok(());
+    }
+
+    if !session::session_exists(&session_file_path) {
         return Err(anyhow::anyhow!(
             "Session file not found (expected path: {})",
             session_file_path.display()
@@ -193,103 +719,32 @@ pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf
             return Err(anyhow::anyhow!("Failed to read session messages: {}", e));
         }
     };
+    let metadata = goose::session::read_metadata(&session_file_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read session metadata: {}", e))?;
+
+    let session_name = session_file_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Unnamed Session");
+    let exporter = SessionExporter::new(session_name, &metadata, messages.messages());
+    let exported = match format {
+        ExportFormat::Md => exporter.to_markdown(),
+        ExportFormat::Html => exporter.to_html(),
+        ExportFormat::Json => exporter.to_json()?,
+        ExportFormat::Bundle => unreachable!("bundles are written above"),
+    };
 
-    // Generate the markdown content using the export functionality
-    let markdown =
-        export_session_to_markdown(messages.messages().clone(), &session_file_path, None);
-
-    // Output the markdown
     if let Some(output) = output_path {
-        fs::write(&output, markdown)
+        fs::write(&output, exported)
             .with_context(|| format!("Failed to write to output file: {}", output.display()))?;
         println!("Session exported to {}", output.display());
     } else {
-        println!("{}", markdown);
+        println!("{}", exported);
     }
 
     Ok(())
 }
 
-/// Convert a list of messages to markdown format for session export
-///
-/// This function handles the formatting of a complete session including headers,
-/// message organization, and proper tool request/response pairing.
-fn export_session_to_markdown(
-    messages: Vec<goose::conversation::message::Message>,
-    session_file: &Path,
-    session_name_override: Option<&str>,
-) -> String {
-    let mut markdown_output = String::new();
-
-    let session_name = session_name_override.unwrap_or_else(|| {
-        session_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Unnamed Session")
-    });
-
-    markdown_output.push_str(&format!("# Session Export: {}\n\n", session_name));
-
-    if messages.is_empty() {
-        markdown_output.push_str("*(This session has no messages)*\n");
-        return markdown_output;
-    }
-
-    markdown_output.push_str(&format!("*Total messages: {}*\n\n---\n\n", messages.len()));
-
-    // Track if the last message had tool requests to properly handle tool responses
-    let mut skip_next_if_tool_response = false;
-
-    for message in &messages {
-        // Check if this is a User message containing only ToolResponses
-        let is_only_tool_response = message.role == rmcp::model::Role::User
-            && message.content.iter().all(|content| {
-                matches!(
-                    content,
-                    goose::conversation::message::MessageContent::ToolResponse(_)
-                )
-            });
-
-        // If the previous message had tool requests and this one is just tool responses,
-        // don't create a new User section - we'll attach the responses to the tool calls
-        if skip_next_if_tool_response && is_only_tool_response {
-            // Export the tool responses without a User heading
-            markdown_output.push_str(&message_to_markdown(message, false));
-            markdown_output.push_str("\n\n---\n\n");
-            skip_next_if_tool_response = false;
-            continue;
-        }
-
-        // Reset the skip flag - we'll update it below if needed
-        skip_next_if_tool_response = false;
-
-        // Output the role prefix except for tool response-only messages
-        if !is_only_tool_response {
-            let role_prefix = match message.role {
-                rmcp::model::Role::User => "### User:\n",
-                rmcp::model::Role::Assistant => "### Assistant:\n",
-            };
-            markdown_output.push_str(role_prefix);
-        }
-
-        // Add the message content
-        markdown_output.push_str(&message_to_markdown(message, false));
-        markdown_output.push_str("\n\n---\n\n");
-
-        // Check if this message has any tool requests, to handle the next message differently
-        if message.content.iter().any(|content| {
-            matches!(
-                content,
-                goose::conversation::message::MessageContent::ToolRequest(_)
-            )
-        }) {
-            skip_next_if_tool_response = true;
-        }
-    }
-
-    markdown_output
-}
-
 /// Prompt the user to interactively select a session
 ///
 /// Shows a list of available sessions and lets the user select one
@@ -314,10 +769,10 @@ pub fn prompt_interactive_session_selection() -> Result<session::Identifier> {
     let display_map: std::collections::HashMap<String, SessionInfo> = sessions
         .iter()
         .map(|s| {
-            let desc = if s.metadata.description.is_empty() {
+            let desc = if s.metadata.display_name().is_empty() {
                 "(no description)"
             } else {
-                &s.metadata.description
+                s.metadata.display_name()
             };
 
             // Truncate description if too long
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use console::style;
+
+use crate::recipes::extract_from_cli::extract_recipe_info_from_cli;
+use crate::session::{build_session, SessionBuilderConfig};
+
+/// Enough output for the provider to answer; the request is only sent for its prefix
+const WARM_UP_MAX_TOKENS: i32 = 16;
+
+/// Send a recipe's opening request once, without a session and without acting on the answer,
+/// so a scheduled run of it starts with warm prompt caches and indexes
+pub async fn handle_warm(
+    recipe: String,
+    params: Vec<(String, String)>,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Result<()> {
+    let (input_config, recipe_info) = extract_recipe_info_from_cli(recipe, params, Vec::new())?;
+    let Some(prompt) = input_config.contents else {
+        return Err(anyhow::anyhow!(
+            "The recipe has no prompt, so a run of it has no opening request to warm"
+        ));
+    };
+
+    let mut settings = recipe_info.session_settings.unwrap_or_default();
+    settings.max_tokens = Some(WARM_UP_MAX_TOKENS);
+    // A batch request could complete after the caches it warms have expired
+    settings.batch = None;
+    let (provider, model) = if recipe_info.lockfile.is_some() {
+        (None, None)
+    } else {
+        (provider, model)
+    };
+
+    let mut session = build_session(SessionBuilderConfig {
+        no_session: true,
+        extensions_override: input_config.extensions_override,
+        additional_system_prompt: input_config.additional_system_prompt,
+        settings: Some(settings),
+        provider,
+        model,
+        quiet: true,
+        sub_recipes: recipe_info.sub_recipes,
+        final_output_response: recipe_info.final_output_response,
+        retry_config: recipe_info.retry_config,
+        ..Default::default()
+    })
+    .await;
+
+    let report = session.warm(prompt).await?;
+    let usage = &report.usage.usage;
+    println!(
+        "{} {} with {} tools and a {} character system prompt",
+        style("Warmed").green().bold(),
+        report.usage.model,
+        report.tools,
+        report.system_prompt_chars
+    );
+    println!(
+        "  input tokens: {}, written to cache: {}, read from cache: {}",
+        usage.input_tokens.unwrap_or(0),
+        usage.cache_write_tokens.unwrap_or(0),
+        usage.cache_read_tokens.unwrap_or(0)
+    );
+    Ok(())
+}
@@ -232,6 +232,7 @@ async fn list_sessions() -> Json<serde_json::Value> {
                             "name": name,
                             "path": path,
                             "description": metadata.description,
+                            "title": metadata.title,
                             "message_count": metadata.message_count,
                             "working_dir": metadata.working_dir
                         })
@@ -666,6 +667,40 @@ async fn process_message_streaming(
                         // Log model change
                         tracing::info!("Model changed to {} in {} mode", model, mode);
                     }
+                    Ok(AgentEvent::ContextCompacted(event)) => {
+                        tracing::info!(
+                            "Compacted {} earlier messages ({} -> {} tokens)",
+                            event.messages_summarized,
+                            event.tokens_before,
+                            event.tokens_after
+                        );
+                    }
+                    Ok(AgentEvent::ContextUsage(usage)) => {
+                        tracing::debug!(
+                            "Context usage: {}/{} tokens",
+                            usage.used(),
+                            usage.context_limit
+                        );
+                    }
+                    Ok(AgentEvent::CredentialRequest(request)) => {
+                        // The web interface has no private input for secrets yet
+                        tracing::warn!(
+                            "Declining request for credential {}: not supported in the web interface",
+                            request.name
+                        );
+                        agent.provide_credential(request.id, None).await;
+                    }
+                    Ok(AgentEvent::Refusal(refusal)) => {
+                        tracing::warn!("Model {} declined the request", refusal.model);
+                    }
+                    Ok(AgentEvent::SecretBlocked(blocked)) => {
+                        // The model is told why, and its reply is what the page shows
+                        tracing::warn!(
+                            "Blocked {} for writing credentials: {:?}",
+                            blocked.tool_name,
+                            blocked.findings
+                        );
+                    }
 
                     Err(e) => {
                         error!("Error in message stream: {}", e);
@@ -1,5 +1,7 @@
 use etcetera::AppStrategyArgs;
 use once_cell::sync::Lazy;
+#[cfg(feature = "chat-bridge")]
+pub mod bridge;
 pub mod cli;
 pub mod commands;
 pub mod logging;
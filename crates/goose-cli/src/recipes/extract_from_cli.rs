@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
+use goose::recipe::lockfile::RecipeLock;
 use goose::recipe::SubRecipe;
 
 use crate::recipes::print_recipe::print_recipe_info;
@@ -47,6 +48,31 @@ pub fn extract_recipe_info_from_cli(
             }
         }
     }
+
+    // A pinned recipe runs with the configuration its lockfile recorded, once there is one
+    let lockfile = match &recipe.settings {
+        Some(settings) if settings.pin == Some(true) => {
+            let recipe_file = retrieve_recipe_file(&recipe_name)?;
+            Some(RecipeLock::path_for(&recipe_file.file_path))
+        }
+        _ => None,
+    };
+    let mut settings = recipe.settings;
+    if let Some(lock) = lockfile
+        .as_deref()
+        .map(RecipeLock::load)
+        .transpose()?
+        .flatten()
+    {
+        println!(
+            "{} {} {}",
+            console::style("🔒 Using the locked configuration:").dim(),
+            console::style(&lock.provider).dim(),
+            console::style(lock.locked_model()).dim()
+        );
+        settings = settings.map(|s| lock.apply(s));
+    }
+
     let input_config = InputConfig {
         contents: recipe.prompt.filter(|s| !s.trim().is_empty()),
         extensions_override: recipe.extensions,
@@ -54,14 +80,17 @@ pub fn extract_recipe_info_from_cli(
     };
 
     let recipe_info = RecipeInfo {
-        session_settings: recipe.settings.map(|s| SessionSettings {
+        session_settings: settings.map(|s| SessionSettings {
             goose_provider: s.goose_provider,
             goose_model: s.goose_model,
             temperature: s.temperature,
+            max_tokens: s.max_tokens,
+            batch: s.batch,
         }),
         sub_recipes: Some(all_sub_recipes),
         final_output_response: recipe.response,
         retry_config: recipe.retry,
+        lockfile,
     };
 
     Ok((input_config, recipe_info))
@@ -223,6 +252,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_recipe_info_from_cli_pinned_recipe_uses_lockfile() {
+        let (_temp_dir, recipe_path) = create_recipe();
+        let content = std::fs::read_to_string(&recipe_path)
+            .unwrap()
+            .replace("  temperature: 0.7\n", "  temperature: 0.7\n  pin: true\n");
+        std::fs::write(&recipe_path, content).unwrap();
+        let params = vec![("name".to_string(), "my_value".to_string())];
+        let recipe_name = recipe_path.to_str().unwrap().to_string();
+
+        let (_, recipe_info) =
+            extract_recipe_info_from_cli(recipe_name.clone(), params.clone(), Vec::new()).unwrap();
+        let lockfile = recipe_info.lockfile.unwrap();
+        assert_eq!(
+            lockfile,
+            recipe_path.with_file_name("test_recipe.lock.yaml")
+        );
+        let settings = recipe_info.session_settings.unwrap();
+        assert_eq!(settings.goose_model, Some("test_model".to_string()));
+
+        RecipeLock::new(
+            "test_provider",
+            "test_model",
+            Some("test_model-2025-01-01".to_string()),
+            Some(0.1),
+            Some(1024),
+        )
+        .save(&lockfile)
+        .unwrap();
+
+        let (_, recipe_info) =
+            extract_recipe_info_from_cli(recipe_name, params, Vec::new()).unwrap();
+        let settings = recipe_info.session_settings.unwrap();
+        assert_eq!(
+            settings.goose_model,
+            Some("test_model-2025-01-01".to_string())
+        );
+        assert_eq!(settings.temperature, Some(0.1));
+        assert_eq!(settings.max_tokens, Some(1024));
+    }
+
     fn create_recipe() -> (TempDir, PathBuf) {
         let test_recipe_content = r#"
 title: test_recipe
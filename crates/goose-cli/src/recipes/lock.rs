@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use anstream::println;
+use anyhow::{anyhow, Result};
+use console::style;
+use goose::config::Config;
+use goose::providers::base::get_current_model;
+use goose::recipe::lockfile::RecipeLock;
+
+use crate::session::SessionSettings;
+
+/// Record the configuration a pinned recipe just ran with, unless it is locked already
+pub fn lock_recipe_run(lockfile: &Path, settings: Option<&SessionSettings>) -> Result<()> {
+    if lockfile.exists() {
+        return Ok(());
+    }
+
+    let config = Config::global();
+    let provider: String = settings
+        .and_then(|s| s.goose_provider.clone())
+        .or_else(|| config.get_param("GOOSE_PROVIDER").ok())
+        .ok_or_else(|| anyhow!("No provider configured to lock"))?;
+    let model: String = settings
+        .and_then(|s| s.goose_model.clone())
+        .or_else(|| config.get_param("GOOSE_MODEL").ok())
+        .ok_or_else(|| anyhow!("No model configured to lock"))?;
+
+    let lock = RecipeLock::new(
+        provider,
+        model,
+        get_current_model(),
+        settings.and_then(|s| s.temperature),
+        settings.and_then(|s| s.max_tokens),
+    );
+    lock.save(lockfile)?;
+
+    println!(
+        "{} {}",
+        style("🔒 Locked the recipe configuration in").dim(),
+        style(lockfile.display()).dim()
+    );
+    Ok(())
+}
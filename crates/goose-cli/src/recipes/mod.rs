@@ -1,5 +1,6 @@
 pub mod extract_from_cli;
 pub mod github_recipe;
+pub mod lock;
 pub mod print_recipe;
 pub mod recipe;
 pub mod search_recipe;
@@ -0,0 +1,160 @@
+//! Images attached to a message, either named in its text by path or data URL, or pasted from
+//! the clipboard.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use goose::conversation::message::Message;
+use goose::providers::vision::{image_from_bytes, image_from_data_url, load_image};
+use rmcp::model::ImageContent;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+/// A word in a message that names an existing image file
+fn image_path(word: &str) -> Option<PathBuf> {
+    let word = word.trim_matches(|c| matches!(c, '"' | '\'' | '`' | ',' | '(' | ')'));
+    let extension = Path::new(word).extension()?.to_str()?.to_lowercase();
+    if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let path = match word.strip_prefix("~/") {
+        Some(rest) => etcetera::home_dir().ok()?.join(rest),
+        None => PathBuf::from(word),
+    };
+    path.is_file().then_some(path)
+}
+
+/// A user message for `text` carrying `pasted` images and any images the text names. Data URLs
+/// are taken out of the text, paths are left in so the model knows which image is which.
+/// Returns the message and a description of each attachment.
+pub fn user_message(text: &str, pasted: Vec<ImageContent>) -> Result<(Message, Vec<String>)> {
+    let mut images = Vec::new();
+    let mut attached = vec!["image from the clipboard".to_string(); pasted.len()];
+    let mut words = Vec::new();
+
+    for word in text.split_whitespace() {
+        if word.starts_with("data:image/") {
+            let image = image_from_data_url(word)
+                .ok_or_else(|| anyhow!("The image data URL in your message isn't valid"))?;
+            attached.push(format!("{} data URL", image.mime_type));
+            images.push(image);
+            continue;
+        }
+        if let Some(path) = image_path(word) {
+            images.push(load_image(&path)?);
+            attached.push(path.display().to_string());
+        }
+        words.push(word);
+    }
+
+    // Keep the text as typed unless a data URL had to come out of it
+    let text = if words.len() == text.split_whitespace().count() {
+        text.to_string()
+    } else {
+        words.join(" ")
+    };
+    let mut message = Message::user();
+    if !text.trim().is_empty() {
+        message = message.with_text(text);
+    }
+    for image in pasted.into_iter().chain(images) {
+        message = message.with_image(image.raw.data, image.raw.mime_type);
+    }
+    Ok((message, attached))
+}
+
+fn run(command: &str, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new(command).args(args).output().ok()?;
+    (output.status.success() && !output.stdout.is_empty()).then_some(output.stdout)
+}
+
+/// The image on the system clipboard, read with the platform's own tools
+pub fn clipboard_image() -> Result<ImageContent> {
+    let bytes = if cfg!(target_os = "macos") {
+        let file = tempfile::Builder::new().suffix(".png").tempfile()?;
+        let script = format!(
+            "write (the clipboard as «class PNGf») to (open for access POSIX file \"{}\" with write permission)",
+            file.path().display()
+        );
+        // osascript prints nothing either way, so the file tells whether there was an image
+        let _ = Command::new("osascript").args(["-e", &script]).output();
+        Some(std::fs::read(file.path())?).filter(|bytes| !bytes.is_empty())
+    } else if cfg!(windows) {
+        let file = tempfile::Builder::new().suffix(".png").tempfile()?;
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $image = [System.Windows.Forms.Clipboard]::GetImage(); \
+             if ($image) {{ $image.Save('{}') }}",
+            file.path().display()
+        );
+        let _ = Command::new("powershell")
+            .args(["-NoProfile", "-Command", &script])
+            .output();
+        Some(std::fs::read(file.path())?).filter(|bytes| !bytes.is_empty())
+    } else {
+        run("wl-paste", &["--type", "image/png"]).or_else(|| {
+            run(
+                "xclip",
+                &["-selection", "clipboard", "-t", "image/png", "-o"],
+            )
+        })
+    };
+
+    let bytes = bytes.ok_or_else(|| {
+        anyhow!(
+            "There is no image on the clipboard{}",
+            if cfg!(any(target_os = "macos", windows)) {
+                ""
+            } else {
+                " (reading it needs wl-paste or xclip)"
+            }
+        )
+    })?;
+    image_from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXEL: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F,
+        0x15, 0xC4, 0x89, 0x00, 0x00, 0x00, 0x0A, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0D, 0x0A, 0x2D, 0xB4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_attaches_named_image_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let single = dir.path().join("diagram.PNG");
+        std::fs::write(&single, PIXEL).unwrap();
+
+        let text = format!("What does {} show?", single.display());
+        let (message, attached) = user_message(&text, Vec::new()).unwrap();
+        assert_eq!(attached, vec![single.display().to_string()]);
+        assert_eq!(message.as_concat_text(), text);
+        assert_eq!(message.content.len(), 2);
+
+        // Words that aren't image files stay text only
+        let (message, attached) = user_message("fix main.rs and notes.png", Vec::new()).unwrap();
+        assert!(attached.is_empty());
+        assert_eq!(message.content.len(), 1);
+    }
+
+    #[test]
+    fn test_data_urls_move_out_of_the_text() {
+        use base64::Engine;
+        let url = format!(
+            "data:image/png;base64,{}",
+            base64::prelude::BASE64_STANDARD.encode(PIXEL)
+        );
+        let (message, attached) = user_message(&format!("describe {}", url), Vec::new()).unwrap();
+        assert_eq!(attached, vec!["image/png data URL".to_string()]);
+        assert_eq!(message.as_concat_text(), "describe");
+
+        assert!(user_message("data:image/png;base64,???", Vec::new()).is_err());
+    }
+}
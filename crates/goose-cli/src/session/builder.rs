@@ -2,7 +2,9 @@ use console::style;
 use goose::agents::types::RetryConfig;
 use goose::agents::Agent;
 use goose::config::{Config, ExtensionConfig, ExtensionConfigManager};
-use goose::providers::create;
+use goose::project::toolchain;
+use goose::project::workspace_lock::{self, LockAttempt, WorkspaceLock, WorkspaceLockPolicy};
+use goose::providers::{create, openai_batch};
 use goose::recipe::{Response, SubRecipe};
 use goose::session;
 use goose::session::Identifier;
@@ -13,6 +15,85 @@ use std::sync::Arc;
 use super::output;
 use super::Session;
 
+/// Lock the workspace for the session, or decide what to do when another session holds it
+/// per GOOSE_WORKSPACE_LOCK. Returns None when the session runs without the lock.
+async fn lock_workspace(agent: &Agent, owner: &str, interactive: bool) -> Option<WorkspaceLock> {
+    let policy = WorkspaceLockPolicy::from_config();
+    if policy == WorkspaceLockPolicy::Off {
+        return None;
+    }
+    let cwd = std::env::current_dir().ok()?;
+    let holder = match WorkspaceLock::try_acquire(&cwd, owner) {
+        Ok(LockAttempt::Acquired(lock)) => return Some(lock),
+        Ok(LockAttempt::Held(holder)) => holder
+            .map(|holder| holder.to_string())
+            .unwrap_or_else(|| "another session".to_string()),
+        Err(e) => {
+            tracing::warn!("Failed to lock the workspace: {}", e);
+            return None;
+        }
+    };
+
+    let root = workspace_lock::workspace_root(&cwd);
+    let choice = match policy {
+        WorkspaceLockPolicy::Prompt if interactive => cliclack::select(format!(
+            "{} {} is already working in {}",
+            style("WARNING:").yellow(),
+            holder,
+            style(root.display()).cyan()
+        ))
+        .item(
+            WorkspaceLockPolicy::Queue,
+            "Wait",
+            "Start once the other session finishes",
+        )
+        .item(
+            WorkspaceLockPolicy::ReadOnly,
+            "Read-only",
+            "Start now, running only read-only tools",
+        )
+        .item(
+            WorkspaceLockPolicy::Off,
+            "Continue anyway",
+            "Start now with all tools; edits may conflict",
+        )
+        .interact()
+        .unwrap_or_else(|_| process::exit(1)),
+        // Nobody to ask, so wait our turn
+        WorkspaceLockPolicy::Prompt => WorkspaceLockPolicy::Queue,
+        policy => policy,
+    };
+
+    match choice {
+        WorkspaceLockPolicy::Queue => {
+            eprintln!(
+                "{}",
+                style(format!("Waiting for {} to finish...", holder)).dim()
+            );
+            match WorkspaceLock::acquire(&cwd, owner, workspace_lock::queue_timeout()).await {
+                Ok(lock) => Some(lock),
+                Err(e) => {
+                    output::render_error(&e.to_string());
+                    process::exit(1);
+                }
+            }
+        }
+        WorkspaceLockPolicy::ReadOnly => {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "{} holds this workspace; attaching read-only",
+                    holder
+                ))
+                .yellow()
+            );
+            agent.set_read_only(true);
+            None
+        }
+        _ => None,
+    }
+}
+
 /// Configuration for building a new Goose session
 ///
 /// This struct contains all the parameters needed to create a new session,
@@ -170,6 +251,9 @@ pub struct SessionSettings {
     pub goose_model: Option<String>,
     pub goose_provider: Option<String>,
     pub temperature: Option<f32>,
+    pub max_tokens: Option<i32>,
+    /// Send requests through the provider's batch API, for runs nobody is waiting on
+    pub batch: Option<bool>,
 }
 
 pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
@@ -199,13 +283,15 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         .expect("No model configured. Run 'goose configure' first");
 
     let temperature = session_config.settings.as_ref().and_then(|s| s.temperature);
+    let max_tokens = session_config.settings.as_ref().and_then(|s| s.max_tokens);
 
     let model_config = goose::model::ModelConfig::new(&model_name)
         .unwrap_or_else(|e| {
             output::render_error(&format!("Failed to create model configuration: {}", e));
             process::exit(1);
         })
-        .with_temperature(temperature);
+        .with_temperature(temperature)
+        .with_max_tokens(max_tokens);
 
     // Create the agent
     let agent: Agent = Agent::new();
@@ -218,7 +304,20 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         agent.add_final_output_tool(final_output_response).await;
     }
 
-    let new_provider = match create(&provider_name, model_config) {
+    let batch = session_config
+        .settings
+        .as_ref()
+        .and_then(|s| s.batch)
+        .unwrap_or(false);
+    let provider = if batch && !session_config.interactive {
+        openai_batch::create(&provider_name, model_config)
+    } else {
+        if batch {
+            tracing::info!("Ignoring batch mode for an interactive session");
+        }
+        create(&provider_name, model_config)
+    };
+    let new_provider = match provider {
         Ok(provider) => provider,
         Err(e) => {
             output::render_error(&format!(
@@ -271,7 +370,7 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
                 }
                 Ok(path) => path,
             };
-            if !session_file.exists() {
+            if !session::session_exists(&session_file) {
                 output::render_error(&format!(
                     "Cannot resume session {} - no such session exists",
                     style(session_file.display()).cyan()
@@ -307,6 +406,10 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         }
     };
 
+    if let Some(session_file) = session_file.as_ref() {
+        session::retention::prune_on_startup(session_file);
+    }
+
     if session_config.resume {
         if let Some(session_file) = session_file.as_ref() {
             // Read the session metadata
@@ -317,7 +420,8 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
 
             let current_workdir =
                 std::env::current_dir().expect("Failed to get current working directory");
-            if current_workdir != metadata.working_dir {
+            // Quiet runs can't be asked, so they stay where they were started
+            if current_workdir != metadata.working_dir && !output::is_quiet() {
                 // Ask user if they want to change the working directory
                 let change_workdir = cliclack::confirm(format!("{} The original working directory of this session was set to {}. Your current directory is {}. Do you want to switch back to the original working directory?", style("WARNING:").yellow(), style(metadata.working_dir.display()).cyan(), style(current_workdir.display()).cyan()))
             .initial_value(true)
@@ -340,8 +444,19 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         }
     }
 
+    // Keep other sessions out of the workspace while this one works in it
+    let owner = session_file
+        .as_ref()
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "an unsaved session".to_string());
+    let workspace_lock = lock_workspace(&agent, &owner, session_config.interactive).await;
+
     // Setup extensions for the agent
     // Extensions need to be added after the session is created because we change directory when resuming a session
+    if let Ok(working_dir) = std::env::current_dir() {
+        agent.set_working_dir(working_dir).await;
+    }
     // If we get extensions_override, only run those extensions and none other
     let extensions_to_run: Vec<_> = if let Some(extensions) = session_config.extensions_override {
         agent.disable_router_for_recipe().await;
@@ -413,6 +528,9 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         edit_mode,
         session_config.retry_config.clone(),
     );
+    if let Some(lock) = workspace_lock {
+        session.hold_workspace_lock(lock);
+    }
 
     // Add extensions if provided
     for extension_str in session_config.extensions {
@@ -559,6 +677,13 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         session.agent.extend_system_prompt(additional_prompt).await;
     }
 
+    // Tell the model how the repository it starts in is built and tested
+    if let Ok(cwd) = std::env::current_dir() {
+        if let Some(note) = toolchain::bootstrap_note(&cwd) {
+            session.agent.extend_system_prompt(note).await;
+        }
+    }
+
     // Only override system prompt if a system override exists
     let system_prompt_file: Option<String> = config.get_param("GOOSE_SYSTEM_PROMPT_FILE_PATH").ok();
     if let Some(ref path) = system_prompt_file {
@@ -21,6 +21,10 @@ pub enum InputResult {
     Clear,
     Recipe(Option<String>),
     Summarize,
+    Pin(Option<String>),
+    Fork(Option<String>),
+    SwitchModel(String),
+    PasteImage,
 }
 
 #[derive(Debug)]
@@ -121,9 +125,13 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
     const CMD_CLEAR: &str = "/clear";
     const CMD_RECIPE: &str = "/recipe";
     const CMD_SUMMARIZE: &str = "/summarize";
+    const CMD_PIN: &str = "/pin";
+    const CMD_FORK: &str = "/fork";
+    const CMD_MODEL: &str = "/model ";
 
     match input {
         "/exit" | "/quit" => Some(InputResult::Exit),
+        "/paste" => Some(InputResult::PasteImage),
         "/?" | "/help" => {
             print_help();
             Some(InputResult::Retry)
@@ -173,6 +181,9 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
         s if s.starts_with(CMD_BUILTIN) => {
             Some(InputResult::AddBuiltin(s[CMD_BUILTIN.len()..].to_string()))
         }
+        s if s.starts_with(CMD_MODEL) => Some(InputResult::SwitchModel(
+            s[CMD_MODEL.len()..].trim().to_string(),
+        )),
         s if s.starts_with(CMD_MODE) => {
             Some(InputResult::GooseMode(s[CMD_MODE.len()..].to_string()))
         }
@@ -181,6 +192,14 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
         s if s == CMD_CLEAR => Some(InputResult::Clear),
         s if s.starts_with(CMD_RECIPE) => parse_recipe_command(s),
         s if s == CMD_SUMMARIZE => Some(InputResult::Summarize),
+        s if s == CMD_PIN => Some(InputResult::Pin(None)),
+        s if s.starts_with("/pin ") => Some(InputResult::Pin(Some(
+            s[CMD_PIN.len()..].trim().to_string(),
+        ))),
+        s if s == CMD_FORK => Some(InputResult::Fork(None)),
+        s if s.starts_with("/fork ") => Some(InputResult::Fork(Some(
+            s[CMD_FORK.len()..].trim().to_string(),
+        ))),
         _ => None,
     }
 }
@@ -282,6 +301,8 @@ fn print_help() {
 /prompts [--extension <name>] - List all available prompts, optionally filtered by extension
 /prompt <n> [--info] [key=value...] - Get prompt info or execute a prompt
 /mode <name> - Set the goose mode to use ('auto', 'approve', 'chat')
+/model <name> - Switch this session to another model from the same provider
+/paste - Attach the image on the clipboard to your next message. Image paths and data URLs in a message are attached too.
 /plan <message_text> -  Enters 'plan' mode with optional message. Create a plan based on the current messages and asks user if they want to act on it.
                         If user acts on the plan, goose mode is set to 'auto' and returns to 'normal' goose mode.
                         To warm up goose before using '/plan', we recommend setting '/mode approve' & putting appropriate context into goose.
@@ -291,6 +312,8 @@ fn print_help() {
 /recipe [filepath] - Generate a recipe from the current conversation and save it to the specified filepath (must end with .yaml).
                        If no filepath is provided, it will be saved to ./recipe.yaml.
 /summarize - Summarize the current conversation to reduce context length while preserving key information.
+/pin [id] - Pin your last message (or the message with the given id) so it is never dropped when the context is truncated or summarized.
+/fork [id] - Continue in a new session holding the history before your last message (or up to the message with the given id or index). The original session is kept as it was.
 /? or /help - Display this help message
 /clear - Clears the current chat history
 
@@ -349,6 +372,21 @@ mod tests {
 
         // Test unknown commands
         assert!(handle_slash_command("/unknown").is_none());
+
+        // Test model switching, which shares a prefix with /mode
+        assert!(matches!(
+            handle_slash_command("/model gpt-4o-mini"),
+            Some(InputResult::SwitchModel(model)) if model == "gpt-4o-mini"
+        ));
+        assert!(matches!(
+            handle_slash_command("/mode auto"),
+            Some(InputResult::GooseMode(mode)) if mode == "auto"
+        ));
+
+        assert!(matches!(
+            handle_slash_command("/paste"),
+            Some(InputResult::PasteImage)
+        ));
     }
 
     #[test]
@@ -536,4 +574,32 @@ mod tests {
         let result = handle_slash_command("  /summarize  ");
         assert!(matches!(result, Some(InputResult::Summarize)));
     }
+
+    #[test]
+    fn test_pin_command() {
+        assert!(matches!(
+            handle_slash_command("/pin"),
+            Some(InputResult::Pin(None))
+        ));
+        if let Some(InputResult::Pin(Some(id))) = handle_slash_command("/pin  msg_123 ") {
+            assert_eq!(id, "msg_123");
+        } else {
+            panic!("Expected Pin with an id");
+        }
+        assert!(handle_slash_command("/pinned").is_none());
+    }
+
+    #[test]
+    fn test_fork_command() {
+        assert!(matches!(
+            handle_slash_command("/fork"),
+            Some(InputResult::Fork(None))
+        ));
+        if let Some(InputResult::Fork(Some(id))) = handle_slash_command("/fork 3") {
+            assert_eq!(id, "3");
+        } else {
+            panic!("Expected Fork with an id");
+        }
+        assert!(handle_slash_command("/forked").is_none());
+    }
 }
@@ -1,6 +1,6 @@
+mod attachments;
 mod builder;
 mod completion;
-mod export;
 mod input;
 mod output;
 mod prompt;
@@ -13,16 +13,18 @@ use crate::session::task_execution_display::{
 use goose::conversation::Conversation;
 use std::io::Write;
 
-pub use self::export::message_to_markdown;
 pub use builder::{build_session, SessionBuilderConfig, SessionSettings};
 use console::Color;
+use goose::agents::plan::{execution_message, Plan, PLAN_FIRST_KEY};
 use goose::agents::AgentEvent;
 use goose::permission::permission_confirmation::PrincipalType;
 use goose::permission::Permission;
 use goose::permission::PermissionConfirmation;
-use goose::providers::base::Provider;
+use goose::project::workspace_lock::WorkspaceLock;
+use goose::providers::base::{Provider, ToolChoice};
 pub use goose::session::Identifier;
 use goose::utils::safe_truncate;
+pub use output::{quiet_from_config, set_quiet};
 
 use anyhow::{Context, Result};
 use completion::GooseCompleter;
@@ -33,16 +35,18 @@ use goose::agents::{Agent, SessionConfig};
 use goose::config::Config;
 use goose::providers::pricing::initialize_pricing_cache;
 use goose::session;
+use goose::session::advisor::{self, SuggestionKind};
 use input::InputResult;
 use mcp_core::handler::ToolError;
-use rmcp::model::PromptMessage;
 use rmcp::model::ServerNotification;
+use rmcp::model::{ImageContent, PromptMessage};
 
 use goose::conversation::message::{Message, MessageContent};
+use goose::conversation::origin::Origin;
 use rand::{distributions::Alphanumeric, Rng};
 use rustyline::EditMode;
-use serde_json::Value;
-use std::collections::HashMap;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
@@ -66,6 +70,12 @@ pub struct Session {
     max_turns: Option<u32>,
     edit_mode: Option<EditMode>,
     retry_config: Option<RetryConfig>,
+    /// Only held, so the workspace is released when the session ends
+    _workspace_lock: Option<WorkspaceLock>,
+    /// Kinds of suggestion already shown, so each is offered once
+    advised: HashSet<SuggestionKind>,
+    /// Images pasted with /paste, sent with the next message
+    pending_images: Vec<ImageContent>,
 }
 
 // Cache structure for completion data
@@ -147,14 +157,26 @@ impl Session {
             session_file,
             completion_cache: Arc::new(std::sync::RwLock::new(CompletionCache::new())),
             debug,
-            run_mode: RunMode::Normal,
+            run_mode: if plan_first() {
+                RunMode::Plan
+            } else {
+                RunMode::Normal
+            },
             scheduled_job_id,
             max_turns,
             edit_mode,
             retry_config,
+            _workspace_lock: None,
+            advised: HashSet::new(),
+            pending_images: Vec::new(),
         }
     }
 
+    /// Hold the workspace lock for as long as the session lives
+    pub fn hold_workspace_lock(&mut self, lock: WorkspaceLock) {
+        self._workspace_lock = Some(lock);
+    }
+
     /// Helper function to summarize context messages
     async fn summarize_context_messages(
         messages: &mut Conversation,
@@ -416,11 +438,22 @@ impl Session {
 
     /// Start an interactive session, optionally with an initial message
     pub async fn interactive(&mut self, prompt: Option<String>) -> Result<()> {
+        if matches!(self.run_mode, RunMode::Plan) {
+            output::render_enter_plan_mode();
+        }
+
         // Process initial message if provided
         if let Some(prompt) = prompt {
-            let msg = Message::user().with_text(&prompt);
-            self.process_message(msg, CancellationToken::default())
-                .await?;
+            if matches!(self.run_mode, RunMode::Plan) {
+                let mut plan_messages = self.messages.clone();
+                plan_messages.push(Message::user().with_text(&prompt));
+                self.plan_with_reasoner_model(plan_messages, get_reasoner()?)
+                    .await?;
+            } else {
+                let msg = Message::user().with_text(&prompt).with_origin(Origin::User);
+                self.process_message(msg, CancellationToken::default())
+                    .await?;
+            }
         }
 
         // Initialize the completion cache
@@ -479,6 +512,7 @@ impl Session {
         loop {
             // Display context usage before each prompt
             self.display_context_usage().await?;
+            self.display_suggestions().await;
 
             match input::get_input(&mut editor)? {
                 InputResult::Message(content) => {
@@ -486,7 +520,23 @@ impl Session {
                         RunMode::Normal => {
                             save_history(&mut editor);
 
-                            self.push_message(Message::user().with_text(&content));
+                            let pasted = std::mem::take(&mut self.pending_images);
+                            let message = match attachments::user_message(&content, pasted) {
+                                Ok((message, attached)) => {
+                                    for attachment in attached {
+                                        output::goose_mode_message(&format!(
+                                            "Attached {}",
+                                            attachment
+                                        ));
+                                    }
+                                    message
+                                }
+                                Err(e) => {
+                                    output::render_error(&e.to_string());
+                                    continue;
+                                }
+                            };
+                            self.push_message(message.with_origin(Origin::User));
 
                             // Track the current directory and last instruction in projects.json
                             let session_id = self
@@ -643,6 +693,28 @@ impl Session {
                     output::render_exit_plan_mode();
                     continue;
                 }
+                input::InputResult::PasteImage => {
+                    save_history(&mut editor);
+                    match attachments::clipboard_image() {
+                        Ok(image) => {
+                            self.pending_images.push(image);
+                            output::goose_mode_message(
+                                "The clipboard image will be sent with your next message",
+                            );
+                        }
+                        Err(e) => output::render_error(&e.to_string()),
+                    }
+                    continue;
+                }
+                input::InputResult::SwitchModel(model) => {
+                    save_history(&mut editor);
+                    if let Err(e) = self.switch_model(&model).await {
+                        output::render_error(&format!("Failed to switch to '{}': {}", model, e));
+                    } else {
+                        output::goose_mode_message(&format!("Model set to '{}'", model));
+                    }
+                    continue;
+                }
                 input::InputResult::Clear => {
                     save_history(&mut editor);
 
@@ -652,8 +724,12 @@ impl Session {
                         &Message::assistant().with_text("Chat context cleared."),
                         self.debug,
                     );
-                    if let Some(file) = self.session_file.as_ref().filter(|f| f.exists()) {
-                        std::fs::remove_file(file)?;
+                    if let Some(file) = self
+                        .session_file
+                        .as_ref()
+                        .filter(|f| session::session_exists(f))
+                    {
+                        session::delete_session(file)?;
                         std::fs::File::create(file)?;
                     }
                     continue;
@@ -758,6 +834,57 @@ impl Session {
 
                     continue;
                 }
+                InputResult::Pin(id) => {
+                    save_history(&mut editor);
+
+                    let pinned = match id {
+                        Some(id) => self.pin_message(&id).await.map(|_| id),
+                        None => self.pin_last_message().await,
+                    };
+                    match pinned {
+                        Ok(id) => println!(
+                            "{}",
+                            console::style(format!(
+                                "Pinned message {id}; it will be kept when the context is truncated or summarized."
+                            ))
+                            .green()
+                        ),
+                        Err(e) => eprintln!(
+                            "{}",
+                            console::style(format!("Could not pin message: {e}")).red()
+                        ),
+                    }
+                    continue;
+                }
+                InputResult::Fork(id) => {
+                    save_history(&mut editor);
+
+                    let original = self.session_file.clone();
+                    match self.fork_at(id.as_deref()) {
+                        Ok(fork_file) => {
+                            println!(
+                                "{}",
+                                console::style(format!(
+                                    "Forked with {} messages into {}",
+                                    self.messages.len(),
+                                    fork_file.display()
+                                ))
+                                .green()
+                            );
+                            if let Some(original) = original {
+                                println!(
+                                    "The original session is unchanged at {}",
+                                    original.display()
+                                );
+                            }
+                        }
+                        Err(e) => eprintln!(
+                            "{}",
+                            console::style(format!("Could not fork the session: {e}")).red()
+                        ),
+                    }
+                    continue;
+                }
             }
         }
 
@@ -778,34 +905,61 @@ impl Session {
     ) -> Result<(), anyhow::Error> {
         let plan_prompt = self.agent.get_plan_prompt().await?;
         output::show_thinking();
+        // The planner describes the steps; acting on them is left to the agent
         let (plan_response, _usage) = reasoner
-            .complete(&plan_prompt, plan_messages.messages(), &[])
+            .complete_with_tool_choice(
+                &plan_prompt,
+                plan_messages.messages(),
+                &[],
+                &ToolChoice::None,
+            )
             .await?;
-        output::render_message(&plan_response, self.debug);
         output::hide_thinking();
-        let planner_response_type =
-            classify_planner_response(plan_response.as_concat_text(), self.agent.provider().await?)
-                .await?;
+        let response_text = plan_response.as_concat_text();
+        // A structured plan needs no classifying; other responses are judged by the model
+        let (planner_response_type, plan_text) = match Plan::parse(&response_text) {
+            Some(plan) => (PlannerResponseType::Plan, plan.to_markdown()),
+            None => (
+                classify_planner_response(response_text.clone(), self.agent.provider().await?)
+                    .await?,
+                response_text,
+            ),
+        };
+        output::render_message(&Message::assistant().with_text(&plan_text), self.debug);
 
         match planner_response_type {
             PlannerResponseType::Plan => {
                 println!();
-                let should_act = match cliclack::confirm(
+                let choice = match cliclack::select(
                     "Do you want to clear message history & act on this plan?",
                 )
-                .initial_value(true)
+                .item("approve", "Act on the plan", "")
+                .item("edit", "Edit the plan, then act on it", "")
+                .item("revise", "Keep planning", "Reply with changes you want")
                 .interact()
                 {
                     Ok(choice) => choice,
                     Err(e) => {
                         if e.kind() == std::io::ErrorKind::Interrupted {
-                            false // If interrupted, set should_act to false
+                            "revise" // If interrupted, keep planning
                         } else {
                             return Err(e.into());
                         }
                     }
                 };
-                if should_act {
+                let approved_plan = match choice {
+                    "approve" => Some(plan_text.clone()),
+                    "edit" => match edit_in_editor(&plan_text) {
+                        Ok(edited) if !edited.trim().is_empty() => Some(edited),
+                        Ok(_) => None,
+                        Err(e) => {
+                            output::render_error(&format!("Could not edit the plan: {}", e));
+                            None
+                        }
+                    },
+                    _ => None,
+                };
+                if let Some(approved_plan) = approved_plan {
                     output::render_act_on_plan();
                     self.run_mode = RunMode::Normal;
                     // set goose mode: auto if that isn't already the case
@@ -820,8 +974,8 @@ impl Session {
 
                     // clear the messages before acting on the plan
                     self.messages.clear();
-                    // add the plan response as a user message
-                    let plan_message = Message::user().with_text(plan_response.as_concat_text());
+                    // add the approved plan as a user message
+                    let plan_message = Message::user().with_text(execution_message(&approved_plan));
                     self.push_message(plan_message);
                     // act on the plan
                     output::show_thinking();
@@ -835,6 +989,10 @@ impl Session {
                             .set_param("GOOSE_MODE", Value::String(curr_goose_mode.to_string()))
                             .unwrap();
                     }
+                    if plan_first() {
+                        self.run_mode = RunMode::Plan;
+                        output::render_enter_plan_mode();
+                    }
                 } else {
                     // add the plan response (assistant message) & carry the conversation forward
                     // in the next round, the user might wanna slightly modify the plan
@@ -853,12 +1011,33 @@ impl Session {
 
     /// Process a single message and exit
     pub async fn headless(&mut self, prompt: String) -> Result<()> {
-        let message = Message::user().with_text(&prompt);
-        self.process_message(message, CancellationToken::default())
-            .await?;
+        let (message, _) = attachments::user_message(&prompt, Vec::new())?;
+        self.process_message(
+            message.with_origin(Origin::User),
+            CancellationToken::default(),
+        )
+        .await?;
+        if output::is_quiet() {
+            // The answer is the only thing a quiet run writes to stdout
+            let answer = self
+                .messages
+                .last()
+                .filter(|message| message.role == rmcp::model::Role::Assistant)
+                .map(|message| message.as_concat_text())
+                .unwrap_or_default();
+            println!("{}", answer);
+            output::emit_event(json!({"type": "finish"}));
+        }
         Ok(())
     }
 
+    /// Send the opening request `headless` would, without acting on the answer, to warm the
+    /// provider's prompt cache and local indexes ahead of a run
+    pub async fn warm(&mut self, prompt: String) -> Result<goose::agents::WarmUpReport> {
+        let (message, _) = attachments::user_message(&prompt, Vec::new())?;
+        self.agent.warm_up(message.with_origin(Origin::User)).await
+    }
+
     async fn process_agent_response(
         &mut self,
         interactive: bool,
@@ -887,10 +1066,19 @@ impl Session {
             .await?;
 
         let mut progress_bars = output::McpSpinners::new();
+        let mut retry_events = goose::providers::retry::subscribe_retry_events();
 
         use futures::StreamExt;
         loop {
             tokio::select! {
+                Ok(event) = retry_events.recv() => {
+                    output::hide_thinking();
+                    output::emit_event(json!({"type": "retry", "message": event.to_string()}));
+                    output::render_text(&event.to_string(), Some(Color::Yellow), true);
+                    if self.debug {
+                        eprintln!("{}", event.error);
+                    }
+                }
                 result = stream.next() => {
                     match result {
                         Some(Ok(AgentEvent::Message(message))) => {
@@ -898,6 +1086,21 @@ impl Session {
                             if let Some(MessageContent::ToolConfirmationRequest(confirmation)) = message.content.first() {
                                 output::hide_thinking();
 
+                                // Nobody can answer in quiet mode, so deny
+                                if output::is_quiet() {
+                                    output::emit_event(json!({
+                                        "type": "tool_denied",
+                                        "id": confirmation.id,
+                                        "name": confirmation.tool_name,
+                                        "reason": "confirmation required",
+                                    }));
+                                    self.agent.handle_confirmation(confirmation.id.clone(), PermissionConfirmation {
+                                        principal_type: PrincipalType::Tool,
+                                        permission: Permission::DenyOnce,
+                                    },).await;
+                                    continue;
+                                }
+
                                 // Format the confirmation prompt
                                 let prompt = "Goose would like to call the above tool, do you allow?".to_string();
 
@@ -1036,6 +1239,12 @@ impl Session {
                                                 tool_name = %tool_call.name,
                                                 "Tool call started"
                                             );
+                                            output::emit_event(json!({
+                                                "type": "tool_call",
+                                                "id": tool_request.id,
+                                                "name": tool_call.name,
+                                                "arguments": tool_call.arguments,
+                                            }));
                                         }
                                     }
                                     if let MessageContent::ToolResponse(tool_response) = content {
@@ -1070,6 +1279,12 @@ impl Session {
                                             result = %result_status,
                                             "Tool call completed"
                                         );
+                                        output::emit_event(json!({
+                                            "type": "tool_result",
+                                            "id": tool_response.id,
+                                            "name": tool_name,
+                                            "success": success,
+                                        }));
                                     }
                                 }
 
@@ -1158,7 +1373,9 @@ impl Session {
                                     };
 
                                     // Handle subagent notifications - show immediately
-                                    if let Some(_id) = subagent_id {
+                                    if output::is_quiet() {
+                                        // Progress is decoration; only the result matters
+                                    } else if let Some(_id) = subagent_id {
                                         // TODO: proper display for subagent notifications
                                         if interactive {
                                             let _ = progress_bars.hide();
@@ -1219,14 +1436,90 @@ impl Session {
                             }
                         }
                         Some(Ok(AgentEvent::ModelChange { model, mode })) => {
+                            output::emit_event(json!({"type": "model_change", "model": model, "mode": mode}));
                             // Log model change if in debug mode
                             if self.debug {
                                 eprintln!("Model changed to {} in {} mode", model, mode);
                             }
                         }
+                        Some(Ok(AgentEvent::ContextCompacted(event))) => {
+                            if self.debug {
+                                eprintln!(
+                                    "Compacted {} earlier messages ({} -> {} tokens)",
+                                    event.messages_summarized, event.tokens_before, event.tokens_after
+                                );
+                            }
+                        }
+                        Some(Ok(AgentEvent::ContextUsage(usage))) => {
+                            if self.debug {
+                                eprintln!(
+                                    "Context: {}/{} tokens (system {}, tools {}, history {}, last response {})",
+                                    usage.used(),
+                                    usage.context_limit,
+                                    usage.system_prompt,
+                                    usage.tools,
+                                    usage.history,
+                                    usage.last_response
+                                );
+                            }
+                        }
+                        Some(Ok(AgentEvent::CredentialRequest(request))) if output::is_quiet() => {
+                            output::emit_event(json!({"type": "credential_declined", "name": request.name}));
+                            self.agent.provide_credential(request.id, None).await;
+                        }
+                        Some(Ok(AgentEvent::CredentialRequest(request))) => {
+                            output::hide_thinking();
+                            // Read without echo; the value goes straight to the agent and is
+                            // never rendered or persisted
+                            let value = cliclack::password(format!(
+                                "{} (stored as ${}) - press ESC to decline",
+                                request.prompt, request.name
+                            ))
+                            .mask('▪')
+                            .interact()
+                            .ok();
+                            self.agent.provide_credential(request.id, value).await;
+                        }
+                        Some(Ok(AgentEvent::Refusal(refusal))) => {
+                            output::emit_event(json!({
+                                "type": "refusal",
+                                "model": refusal.model,
+                                "text": refusal.text,
+                                "retrying": refusal.recovery.is_some(),
+                            }));
+                            output::hide_thinking();
+                            let retrying = if refusal.recovery.is_some() { ", retrying..." } else { "" };
+                            output::render_text(
+                                &format!("{} declined the request{}\n{}", refusal.model, retrying, refusal.text.trim()),
+                                Some(Color::Yellow),
+                                true,
+                            );
+                        }
+                        Some(Ok(AgentEvent::SecretBlocked(blocked))) => {
+                            output::hide_thinking();
+                            output::emit_event(json!({"type": "secret_blocked", "name": blocked.tool_name}));
+                            let found: Vec<String> = blocked
+                                .findings
+                                .iter()
+                                .map(|f| format!("{} {} in {}", f.kind, f.preview, f.argument))
+                                .collect();
+                            output::render_text(
+                                &format!(
+                                    "Blocked {}: it would have written {}",
+                                    blocked.tool_name,
+                                    found.join(", ")
+                                ),
+                                Some(Color::Red),
+                                true,
+                            );
+                        }
 
                         Some(Err(e)) => {
-                            eprintln!("Error: {}", e);
+                            if output::is_quiet() {
+                                output::emit_event(json!({"type": "error", "message": e.to_string()}));
+                            } else {
+                                eprintln!("Error: {}", e);
+                            }
                             cancel_token_clone.cancel();
                             drop(stream);
                             if let Err(e) = self.handle_interrupted_messages(false).await {
@@ -1253,7 +1546,9 @@ impl Session {
                 }
             }
         }
-        println!();
+        if !output::is_quiet() {
+            println!();
+        }
 
         Ok(())
     }
@@ -1452,13 +1747,49 @@ impl Session {
     }
 
     pub fn get_metadata(&self) -> Result<session::SessionMetadata> {
-        if !self.session_file.as_ref().is_some_and(|f| f.exists()) {
+        if !self
+            .session_file
+            .as_ref()
+            .is_some_and(|f| session::session_exists(f))
+        {
             return Err(anyhow::anyhow!("Session file does not exist"));
         }
 
         session::read_metadata(self.session_file.as_ref().unwrap())
     }
 
+    /// Set a key/value pair in the session's metadata, or remove the key when `value` is `None`
+    pub async fn set_metadata(&mut self, key: &str, value: Option<String>) -> Result<()> {
+        self.edit_metadata(|metadata| match value {
+            Some(value) => {
+                metadata.properties.insert(key.to_string(), value);
+            }
+            None => {
+                metadata.properties.remove(key);
+            }
+        })
+        .await
+    }
+
+    /// Add tags to the session
+    pub async fn tag(&mut self, tags: &[String]) -> Result<()> {
+        self.edit_metadata(|metadata| {
+            for tag in tags {
+                metadata.add_tag(tag);
+            }
+        })
+        .await
+    }
+
+    async fn edit_metadata(&self, edit: impl FnOnce(&mut session::SessionMetadata)) -> Result<()> {
+        let session_file = self.session_file.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("This session is not recorded, so it has no metadata")
+        })?;
+        let mut metadata = session::read_metadata(session_file)?;
+        edit(&mut metadata);
+        session::update_metadata(session_file, &metadata).await
+    }
+
     // Get the session's total token usage
     pub fn get_total_token_usage(&self) -> Result<Option<i32>> {
         let metadata = self.get_metadata()?;
@@ -1497,9 +1828,12 @@ impl Session {
                 if show_cost {
                     let input_tokens = metadata.input_tokens.unwrap_or(0) as usize;
                     let output_tokens = metadata.output_tokens.unwrap_or(0) as usize;
+                    // Routers like openrouter/auto report the model that served the request
+                    let model_name = goose::providers::base::get_current_model()
+                        .unwrap_or_else(|| model_config.model_name.clone());
                     output::display_cost_usage(
                         &provider_name,
-                        &model_config.model_name,
+                        &model_name,
                         input_tokens,
                         output_tokens,
                     )
@@ -1514,6 +1848,39 @@ impl Session {
         Ok(())
     }
 
+    /// Offer the advisor's suggestions for the session's recent usage, each kind once
+    async fn display_suggestions(&mut self) {
+        if !advisor::enabled() {
+            return;
+        }
+        let (Ok(provider), Ok(metadata)) = (self.agent.provider().await, self.get_metadata())
+        else {
+            return;
+        };
+        let provider_name = Config::global()
+            .get_param::<String>("GOOSE_PROVIDER")
+            .unwrap_or_default();
+        let suggestions = advisor::suggestions(
+            &provider_name,
+            &provider.get_model_config(),
+            &metadata.usage_ledger,
+        )
+        .await;
+        for suggestion in suggestions {
+            if self.advised.insert(suggestion.kind) {
+                output::render_suggestion(&suggestion);
+            }
+        }
+    }
+
+    /// Serve the rest of the session with another model from the configured provider
+    async fn switch_model(&mut self, model: &str) -> Result<()> {
+        let provider_name: String = Config::global().get_param("GOOSE_PROVIDER")?;
+        let provider =
+            goose::providers::create(&provider_name, goose::model::ModelConfig::new(model)?)?;
+        self.agent.update_provider(provider).await
+    }
+
     /// Handle prompt command execution
     async fn handle_prompt_command(&mut self, opts: input::PromptCommandOptions) -> Result<()> {
         // name is required
@@ -1622,6 +1989,91 @@ impl Session {
     fn push_message(&mut self, message: Message) {
         self.messages.push(message);
     }
+
+    /// Pin the message with `id` so truncation and summarization never drop it
+    pub async fn pin_message(&mut self, id: &str) -> Result<()> {
+        self.messages.pin_message(id)?;
+        self.persist_pins().await
+    }
+
+    /// Pin the last message the user sent, returning its id
+    async fn pin_last_message(&mut self) -> Result<String> {
+        let id = self.messages.pin_last_user_message()?;
+        self.persist_pins().await?;
+        Ok(id)
+    }
+
+    /// Copy the history up to a message into a new session and continue in that one, keeping
+    /// the original as it was. The history is kept through the message with `message_id`, which
+    /// may also be an index as shown by `goose session inspect`. Without one, the history before
+    /// the last message the user typed is kept, so it can be asked differently.
+    pub fn fork_at(&mut self, message_id: Option<&str>) -> Result<PathBuf> {
+        let session_file = self.session_file.clone().ok_or_else(|| {
+            anyhow::anyhow!("This session is not recorded, so it can't be forked")
+        })?;
+        let messages = self.messages.messages();
+        let len = match message_id {
+            Some(id) => {
+                let position = messages
+                    .iter()
+                    .position(|m| m.id.as_deref() == Some(id))
+                    .or_else(|| id.parse::<usize>().ok().filter(|i| *i < messages.len()))
+                    .ok_or_else(|| anyhow::anyhow!("No message with id {}", id))?;
+                position + 1
+            }
+            None => messages
+                .iter()
+                .rposition(|m| m.role == rmcp::model::Role::User && m.has_only_text_content())
+                .ok_or_else(|| anyhow::anyhow!("There is no message to fork before yet"))?,
+        };
+
+        let forked = Conversation::new_unvalidated(messages[..len].to_vec());
+        let fork_file = session::fork_session(&session_file, &forked)?;
+        self.messages = forked;
+        self.session_file = Some(fork_file.clone());
+        Ok(fork_file)
+    }
+
+    async fn persist_pins(&self) -> Result<()> {
+        if let Some(session_file) = &self.session_file {
+            session::persist_messages_with_schedule_id(
+                session_file,
+                &self.messages,
+                None,
+                self.scheduled_job_id.clone(),
+                None,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether requests are planned and approved before the agent acts on them
+fn plan_first() -> bool {
+    Config::global()
+        .get_param::<bool>(PLAN_FIRST_KEY)
+        .unwrap_or(false)
+}
+
+/// Let the user change `text` in their editor, returning what they saved
+fn edit_in_editor(text: &str) -> Result<String> {
+    let file = tempfile::Builder::new().suffix(".md").tempfile()?;
+    std::fs::write(file.path(), text)?;
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // The editor may carry arguments, such as "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(file.path())
+        .status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", editor, status));
+    }
+    Ok(std::fs::read_to_string(file.path())?)
 }
 
 fn get_reasoner() -> Result<Arc<dyn Provider>, anyhow::Error> {
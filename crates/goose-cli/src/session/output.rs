@@ -2,9 +2,10 @@ use anstream::println;
 use bat::WrappingMode;
 use console::{style, Color};
 use goose::config::Config;
-use goose::conversation::message::{Message, MessageContent, ToolRequest, ToolResponse};
+use goose::conversation::message::{Citation, Message, MessageContent, ToolRequest, ToolResponse};
 use goose::providers::pricing::get_model_pricing;
 use goose::providers::pricing::parse_model_id;
+use goose::session::advisor::Suggestion;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use mcp_core::tool::ToolCall;
 use regex::Regex;
@@ -14,6 +15,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::{Error, IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -88,6 +90,38 @@ pub fn get_theme() -> Theme {
     CURRENT_THEME.with(|t| *t.borrow())
 }
 
+/// Config key that turns on quiet mode, as `--quiet` does
+pub const QUIET_KEY: &str = "GOOSE_QUIET";
+
+// Quiet mode: no decoration, spinners or prompts, only the final result on stdout and
+// machine-readable events on stderr
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Whether `GOOSE_QUIET` asks for quiet mode; accepts `true` as well as `1`
+pub fn quiet_from_config() -> bool {
+    match Config::global().get_param::<Value>(QUIET_KEY) {
+        Ok(Value::Bool(quiet)) => quiet,
+        Ok(Value::Number(n)) => n.as_i64().is_some_and(|n| n != 0),
+        _ => false,
+    }
+}
+
+/// Write an event for the calling program to stderr as one line of JSON. Only in quiet mode,
+/// where nothing else goes to stderr.
+pub fn emit_event(event: Value) {
+    if is_quiet() {
+        eprintln!("{}", event);
+    }
+}
+
 // Simple wrapper around spinner to manage its state
 #[derive(Default)]
 pub struct ThinkingIndicator {
@@ -135,8 +169,13 @@ thread_local! {
     static THINKING: RefCell<ThinkingIndicator> = RefCell::new(ThinkingIndicator::default());
 }
 
+thread_local! {
+    // Sources cited so far in this session, numbered by their position
+    static CITED_SOURCES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
 pub fn show_thinking() {
-    if std::io::stdout().is_terminal() {
+    if std::io::stdout().is_terminal() && !is_quiet() {
         THINKING.with(|t| t.borrow_mut().show());
     }
 }
@@ -162,6 +201,9 @@ pub fn set_thinking_message(s: &String) {
 }
 
 pub fn render_message(message: &Message, debug: bool) {
+    if is_quiet() {
+        return;
+    }
     let theme = get_theme();
 
     for content in &message.content {
@@ -185,6 +227,7 @@ pub fn render_message(message: &Message, debug: bool) {
                 println!("\n{}", style("Thinking:").dim().italic());
                 print_markdown("Thinking was redacted", theme);
             }
+            MessageContent::Citation(citation) => render_citation(citation),
             _ => {
                 println!("WARNING: Message content type could not be rendered");
             }
@@ -194,11 +237,31 @@ pub fn render_message(message: &Message, debug: bool) {
     let _ = std::io::stdout().flush();
 }
 
+/// Print a source as a footnote the first time it is cited, numbered in the order sources
+/// appear in the session
+fn render_citation(citation: &Citation) {
+    let label = citation.label();
+    let number = CITED_SOURCES.with(|sources| {
+        let mut sources = sources.borrow_mut();
+        if sources.contains(&label) {
+            return None;
+        }
+        sources.push(label.clone());
+        Some(sources.len())
+    });
+    if let Some(number) = number {
+        println!("{}", style(format!("[{}] {}", number, label)).dim());
+    }
+}
+
 pub fn render_text(text: &str, color: Option<Color>, dim: bool) {
     render_text_no_newlines(format!("\n{}\n\n", text).as_str(), color, dim);
 }
 
 pub fn render_text_no_newlines(text: &str, color: Option<Color>, dim: bool) {
+    if is_quiet() {
+        return;
+    }
     if !std::io::stdout().is_terminal() {
         println!("{}", text);
         return;
@@ -242,6 +305,15 @@ pub fn goose_mode_message(text: &str) {
     println!("\n{}", style(text).yellow(),);
 }
 
+pub fn render_suggestion(suggestion: &Suggestion) {
+    println!(
+        "{} {} {}",
+        style("tip:").cyan().bold(),
+        suggestion.message,
+        style(format!("(run {})", suggestion.command)).dim()
+    );
+}
+
 fn render_tool_request(req: &ToolRequest, theme: Theme, debug: bool) {
     match &req.tool_call {
         Ok(call) => match call.name.as_str() {
@@ -291,6 +363,9 @@ fn render_tool_response(resp: &ToolResponse, theme: Theme, debug: bool) {
 }
 
 pub fn render_error(message: &str) {
+    if is_quiet() {
+        return emit_event(serde_json::json!({"type": "error", "message": message}));
+    }
     println!("\n  {} {}\n", style("error:").red().bold(), message);
 }
 
@@ -812,6 +887,9 @@ impl McpSpinners {
     }
 
     pub fn log(&mut self, message: &str) {
+        if is_quiet() {
+            return;
+        }
         let spinner = self.log_spinner.get_or_insert_with(|| {
             let bar = self.multi_bar.add(
                 ProgressBar::new_spinner()
@@ -830,6 +908,9 @@ impl McpSpinners {
     }
 
     pub fn update(&mut self, token: &str, value: u32, total: Option<u32>, message: Option<&str>) {
+        if is_quiet() {
+            return;
+        }
         let bar = self.bars.entry(token.to_string()).or_insert_with(|| {
             if let Some(total) = total {
                 self.multi_bar.add(
@@ -916,4 +997,15 @@ mod tests {
             "/v/l/p/w/m/components/file.txt"
         );
     }
+
+    #[test]
+    fn test_quiet_from_config() {
+        env::set_var(QUIET_KEY, "1");
+        assert!(quiet_from_config());
+        env::set_var(QUIET_KEY, "true");
+        assert!(quiet_from_config());
+        env::set_var(QUIET_KEY, "0");
+        assert!(!quiet_from_config());
+        env::remove_var(QUIET_KEY);
+    }
 }
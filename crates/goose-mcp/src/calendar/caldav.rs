@@ -0,0 +1,346 @@
+use std::env;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+use super::{CalendarBackend, Event, NewEvent};
+
+static CALENDAR_DATA: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<(?:[A-Za-z0-9]+:)?calendar-data[^>]*>(.*?)</(?:[A-Za-z0-9]+:)?calendar-data>")
+        .expect("valid calendar-data pattern")
+});
+
+const ICS_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A calendar collection on a CalDAV server, e.g. Fastmail, iCloud or Nextcloud
+pub struct CalDav {
+    client: reqwest::Client,
+    url: Url,
+    username: String,
+    password: String,
+}
+
+impl CalDav {
+    pub fn from_env() -> Result<Self> {
+        let url = env::var("CALDAV_URL").context("CALDAV_URL is not set")?;
+        let mut url = Url::parse(&url).with_context(|| format!("'{}' is not a valid URL", url))?;
+        // Event resources are created inside the collection
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            username: env::var("CALDAV_USERNAME").context("CALDAV_USERNAME is not set")?,
+            password: env::var("CALDAV_PASSWORD").context("CALDAV_PASSWORD is not set")?,
+        })
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for CalDav {
+    async fn events(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Event>> {
+        let range = format!(
+            r#"start="{}" end="{}""#,
+            start.format(ICS_TIME_FORMAT),
+            end.format(ICS_TIME_FORMAT)
+        );
+        // Expanded on the server, so each occurrence of a recurring event comes back on its own
+        let query = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data><c:expand {range}/></c:calendar-data></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT"><c:time-range {range}/></c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#
+        );
+        let response = self
+            .client
+            .request(
+                Method::from_bytes(b"REPORT").expect("REPORT is a valid method"),
+                self.url.clone(),
+            )
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut events: Vec<Event> = CALENDAR_DATA
+            .captures_iter(&response)
+            .flat_map(|data| parse_ics(&unescape_xml(&data[1])))
+            .filter(|event| event.end > start && event.start < end)
+            .collect();
+        events.sort_by_key(|event| event.start);
+        Ok(events)
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<Event> {
+        let uid = format!(
+            "{}-{}@goose",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            std::process::id()
+        );
+        let url = self.url.join(&format!("{}.ics", uid))?;
+        let response = self
+            .client
+            .put(url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .header("If-None-Match", "*")
+            .body(to_ics(&uid, event))
+            .send()
+            .await?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Err(anyhow!("An event with UID {} already exists", uid));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "CalDAV server refused the event: {}",
+                response.status()
+            ));
+        }
+        Ok(Event {
+            id: uid,
+            title: event.title.clone(),
+            start: event.start,
+            end: event.end,
+            all_day: false,
+            location: event.location.clone(),
+            attendees: event.attendees.clone(),
+        })
+    }
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    text.replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+fn to_ics(uid: &str, event: &NewEvent) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Block//goose//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", Utc::now().format(ICS_TIME_FORMAT)),
+        format!("DTSTART:{}", event.start.format(ICS_TIME_FORMAT)),
+        format!("DTEND:{}", event.end.format(ICS_TIME_FORMAT)),
+        format!("SUMMARY:{}", escape_text(&event.title)),
+    ];
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", escape_text(location)));
+    }
+    for attendee in &event.attendees {
+        lines.push(format!("ATTENDEE;RSVP=TRUE:mailto:{}", attendee));
+    }
+    lines.extend(["END:VEVENT".to_string(), "END:VCALENDAR".to_string()]);
+    lines.join("\r\n") + "\r\n"
+}
+
+/// A DTSTART/DTEND value: UTC with a trailing `Z`, in the zone named by `TZID`, a date for
+/// all-day events, or otherwise floating, which is read as local time
+fn parse_ics_time(params: &str, value: &str) -> Option<(DateTime<Utc>, bool)> {
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        let midnight = Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()?;
+        return Some((midnight.with_timezone(&Utc), true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&time), false));
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let tzid = params
+        .split(';')
+        .find_map(|param| param.strip_prefix("TZID="))
+        .and_then(|tzid| tzid.trim_matches('"').parse::<chrono_tz::Tz>().ok());
+    let time = match tzid {
+        Some(tz) => tz
+            .from_local_datetime(&time)
+            .earliest()?
+            .with_timezone(&Utc),
+        None => Local
+            .from_local_datetime(&time)
+            .earliest()?
+            .with_timezone(&Utc),
+    };
+    Some((time, false))
+}
+
+/// The events of an iCalendar document
+fn parse_ics(ics: &str) -> Vec<Event> {
+    // Long lines are folded onto continuation lines starting with whitespace
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    let mut depth = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                current = Some(Event {
+                    id: String::new(),
+                    title: String::new(),
+                    start: DateTime::<Utc>::MIN_UTC,
+                    end: DateTime::<Utc>::MIN_UTC,
+                    all_day: false,
+                    location: None,
+                    attendees: Vec::new(),
+                });
+                depth = 0;
+            }
+            // Alarms and the like inside an event have properties of their own
+            ("BEGIN", _) if current.is_some() => depth += 1,
+            ("END", "VEVENT") => {
+                if let Some(mut event) = current.take() {
+                    if event.start == DateTime::<Utc>::MIN_UTC {
+                        continue;
+                    }
+                    if event.end <= event.start {
+                        event.end = if event.all_day {
+                            event.start + Duration::days(1)
+                        } else {
+                            event.start
+                        };
+                    }
+                    events.push(event);
+                }
+            }
+            ("END", _) if current.is_some() => depth -= 1,
+            _ => {
+                let Some(event) = current.as_mut().filter(|_| depth == 0) else {
+                    continue;
+                };
+                match name {
+                    "UID" => event.id = value.to_string(),
+                    "SUMMARY" => event.title = unescape_text(value),
+                    "LOCATION" => event.location = Some(unescape_text(value)),
+                    "DTSTART" => {
+                        if let Some((start, all_day)) = parse_ics_time(params, value) {
+                            event.start = start;
+                            event.all_day = all_day;
+                        }
+                    }
+                    "DTEND" => {
+                        if let Some((end, _)) = parse_ics_time(params, value) {
+                            event.end = end;
+                        }
+                    }
+                    "ATTENDEE" => {
+                        let email = value
+                            .strip_prefix("mailto:")
+                            .or_else(|| value.strip_prefix("MAILTO:"))
+                            .unwrap_or(value);
+                        event.attendees.push(email.to_string());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_parse_ics_events() {
+        let ics = indoc! {r#"
+            BEGIN:VCALENDAR
+            BEGIN:VEVENT
+            UID:standup-1
+            SUMMARY:Team stand
+             up\, daily
+            DTSTART:20240305T090000Z
+            DTEND:20240305T091500Z
+            ATTENDEE;CN=Dana:mailto:dana@example.com
+            BEGIN:VALARM
+            SUMMARY:Reminder
+            END:VALARM
+            END:VEVENT
+            BEGIN:VEVENT
+            UID:offsite
+            SUMMARY:Offsite
+            DTSTART;TZID=Europe/Berlin:20240306T100000
+            DTEND;TZID=Europe/Berlin:20240306T120000
+            END:VEVENT
+            END:VCALENDAR
+        "#};
+        let events = parse_ics(ics);
+        assert_eq!(events.len(), 2);
+
+        assert_eq!(events[0].id, "standup-1");
+        assert_eq!(events[0].title, "Team standup, daily");
+        assert_eq!(events[0].start.to_rfc3339(), "2024-03-05T09:00:00+00:00");
+        assert_eq!(events[0].end - events[0].start, Duration::minutes(15));
+        assert_eq!(events[0].attendees, vec!["dana@example.com"]);
+
+        // Berlin is an hour ahead of UTC in March
+        assert_eq!(events[1].start.to_rfc3339(), "2024-03-06T09:00:00+00:00");
+
+        let event = NewEvent {
+            title: "Review; round 2".to_string(),
+            start: events[0].start,
+            end: events[0].end,
+            description: None,
+            location: None,
+            attendees: vec![],
+        };
+        let round_trip = parse_ics(&to_ics("new-1", &event));
+        assert_eq!(round_trip[0].title, "Review; round 2");
+        assert_eq!(round_trip[0].start, event.start);
+    }
+}
@@ -0,0 +1,184 @@
+use std::{env, path::Path, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use google_drive3::common::GetToken;
+use serde_json::{json, Value};
+use url::Url;
+
+use super::{CalendarBackend, Event, NewEvent};
+use crate::google_drive::oauth_pkce::PkceOAuth2Client;
+use crate::google_drive::storage::CredentialsManager;
+use crate::google_drive::{KEYCHAIN_DISK_FALLBACK_ENV, KEYCHAIN_SERVICE};
+
+// Stored apart from the Drive token so each extension asks only for its own scope
+const KEYCHAIN_USERNAME: &str = "calendar_oauth_credentials";
+const CALENDAR_SCOPE: &str = "https://www.googleapis.com/auth/calendar";
+const CALENDAR_API: &str = "https://www.googleapis.com/calendar/v3/calendars";
+
+/// A Google Calendar, signed in to with the same OAuth client as the Google Drive extension
+pub struct GoogleCalendar {
+    auth: PkceOAuth2Client,
+    client: reqwest::Client,
+    calendar_id: String,
+}
+
+impl GoogleCalendar {
+    pub fn from_env() -> Result<Self> {
+        let keyfile = env::var("GOOGLE_CALENDAR_OAUTH_PATH")
+            .or_else(|_| env::var("GOOGLE_DRIVE_OAUTH_PATH"))
+            .unwrap_or_else(|_| "./gcp-oauth.keys.json".to_string());
+        let keyfile = shellexpand::tilde(&keyfile).to_string();
+        let credentials_path = env::var("GOOGLE_CALENDAR_CREDENTIALS_PATH")
+            .unwrap_or_else(|_| "./gcal-server-credentials.json".to_string());
+        let fallback_to_disk = env::var(KEYCHAIN_DISK_FALLBACK_ENV)
+            .map(|value| value.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let credentials_manager = Arc::new(CredentialsManager::new(
+            shellexpand::tilde(&credentials_path).to_string(),
+            fallback_to_disk,
+            KEYCHAIN_SERVICE.to_string(),
+            KEYCHAIN_USERNAME.to_string(),
+        ));
+        let auth = PkceOAuth2Client::new(Path::new(&keyfile), credentials_manager)
+            .map_err(|e| anyhow!("Failed to load OAuth keyfile {}: {}", keyfile, e))?;
+        Ok(Self {
+            auth,
+            client: reqwest::Client::new(),
+            calendar_id: env::var("GOOGLE_CALENDAR_ID").unwrap_or_else(|_| "primary".to_string()),
+        })
+    }
+
+    async fn token(&self) -> Result<String> {
+        self.auth
+            .get_token(&[CALENDAR_SCOPE])
+            .await
+            .map_err(|e| anyhow!("Google sign-in failed: {}", e))?
+            .ok_or_else(|| anyhow!("Google sign-in did not return a token"))
+    }
+
+    fn events_url(&self) -> Result<Url> {
+        let mut url = Url::parse(CALENDAR_API)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Calendar API URL cannot have a path"))?
+            .extend([self.calendar_id.as_str(), "events"]);
+        Ok(url)
+    }
+}
+
+/// An event's `start` or `end`: a `dateTime`, or a `date` for all-day events
+fn parse_time(value: &Value) -> Option<(DateTime<Utc>, bool)> {
+    if let Some(time) = value["dateTime"].as_str() {
+        let time = DateTime::parse_from_rfc3339(time).ok()?;
+        return Some((time.with_timezone(&Utc), false));
+    }
+    let date = NaiveDate::parse_from_str(value["date"].as_str()?, "%Y-%m-%d").ok()?;
+    let midnight = Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+    Some((midnight.with_timezone(&Utc), true))
+}
+
+fn parse_event(item: &Value) -> Option<Event> {
+    if item["status"].as_str() == Some("cancelled") {
+        return None;
+    }
+    let (start, all_day) = parse_time(&item["start"])?;
+    let (end, _) = parse_time(&item["end"])?;
+    Some(Event {
+        id: item["id"].as_str()?.to_string(),
+        title: item["summary"].as_str().unwrap_or("(no title)").to_string(),
+        start,
+        end,
+        all_day,
+        location: item["location"].as_str().map(String::from),
+        attendees: item["attendees"]
+            .as_array()
+            .map(|attendees| {
+                attendees
+                    .iter()
+                    .filter_map(|attendee| attendee["email"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+#[async_trait]
+impl CalendarBackend for GoogleCalendar {
+    async fn events(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("timeMin", start.to_rfc3339()),
+                ("timeMax", end.to_rfc3339()),
+                ("singleEvents", "true".to_string()),
+                ("orderBy", "startTime".to_string()),
+                ("maxResults", "250".to_string()),
+            ];
+            if let Some(token) = page_token.take() {
+                query.push(("pageToken", token));
+            }
+            let page: Value = self
+                .client
+                .get(self.events_url()?)
+                .bearer_auth(self.token().await?)
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()
+                .context("Google Calendar did not list events")?
+                .json()
+                .await?;
+            if let Some(items) = page["items"].as_array() {
+                events.extend(items.iter().filter_map(parse_event));
+            }
+            match page["nextPageToken"].as_str() {
+                Some(token) => page_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+        Ok(events)
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<Event> {
+        let mut body = json!({
+            "summary": event.title,
+            "start": {"dateTime": event.start.to_rfc3339()},
+            "end": {"dateTime": event.end.to_rfc3339()},
+            "attendees": event
+                .attendees
+                .iter()
+                .map(|email| json!({"email": email}))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(description) = &event.description {
+            body["description"] = json!(description);
+        }
+        if let Some(location) = &event.location {
+            body["location"] = json!(location);
+        }
+        // Attendees get an invitation the same as when the event is made in Google Calendar
+        let send_updates = if event.attendees.is_empty() {
+            "none"
+        } else {
+            "all"
+        };
+        let created: Value = self
+            .client
+            .post(self.events_url()?)
+            .bearer_auth(self.token().await?)
+            .query(&[("sendUpdates", send_updates)])
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()
+            .context("Google Calendar did not create the event")?
+            .json()
+            .await?;
+        parse_event(&created).ok_or_else(|| anyhow!("Google Calendar returned an unreadable event"))
+    }
+}
@@ -0,0 +1,623 @@
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use indoc::indoc;
+use mcp_core::ToolError;
+use plotters::prelude::*;
+use polars::prelude::*;
+use rmcp::model::{Content, Tool, ToolAnnotations};
+use rmcp::object;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Rows returned by query and aggregate unless a limit is given
+const DEFAULT_ROW_LIMIT: u32 = 50;
+/// Most rows returned by query and aggregate
+const MAX_ROW_LIMIT: u32 = 1_000;
+/// Rows sampled when inferring CSV column types
+const INFER_SCHEMA_ROWS: usize = 10_000;
+/// Most bars a bar chart can show
+const MAX_BARS: usize = 60;
+/// Most points a line or scatter chart plots
+const MAX_POINTS: u32 = 20_000;
+const CHART_SIZE: (u32, u32) = (1024, 640);
+
+/// The data_tool definition; the extension only offers it when built with the data-tool feature
+pub fn data_tool_definition() -> Tool {
+    Tool::new(
+        "data_tool",
+        indoc! {r#"
+            Analyze CSV, TSV and Parquet files locally, without loading them into the conversation.
+            Supports operations:
+            - schema: Column names and types, the number of rows and the first few rows
+            - query: Rows matching the filters, with chosen columns, sorted and limited (returns CSV)
+            - aggregate: Group rows and summarize columns, e.g. revenue summed by region (returns CSV)
+            - chart: Draw a bar, line or scatter chart of y against x as a PNG (saved to the cache
+              directory unless output_path is given); filters and aggregations apply first
+
+            Filters are objects like {"column": "units", "op": "gt", "value": 10}, with op one of
+            eq, ne, gt, ge, lt, le, contains, is_null or not_null. Aggregations are objects like
+            {"column": "revenue", "function": "sum"}, with function one of count, n_unique, sum,
+            mean, median, min, max or std; each result column is named <column>_<function>.
+
+            Use this instead of reading large data files, and start with schema to learn the columns.
+        "#},
+        object!({
+            "type": "object",
+            "required": ["path", "operation"],
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Path to the CSV, TSV or Parquet file"
+                },
+                "operation": {
+                    "type": "string",
+                    "enum": ["schema", "query", "aggregate", "chart"],
+                    "description": "Operation to perform on the data"
+                },
+                "filters": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["column", "op"],
+                        "properties": {
+                            "column": {"type": "string"},
+                            "op": {
+                                "type": "string",
+                                "enum": ["eq", "ne", "gt", "ge", "lt", "le", "contains", "is_null", "not_null"]
+                            },
+                            "value": {
+                                "description": "Value to compare with (string, number or boolean)"
+                            }
+                        }
+                    },
+                    "description": "Conditions rows must all meet"
+                },
+                "group_by": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Columns to group by; groups are counted when no aggregations are given"
+                },
+                "aggregations": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["column", "function"],
+                        "properties": {
+                            "column": {"type": "string"},
+                            "function": {
+                                "type": "string",
+                                "enum": ["count", "n_unique", "sum", "mean", "median", "min", "max", "std"]
+                            }
+                        }
+                    },
+                    "description": "Summaries to compute, per group when group_by is given"
+                },
+                "columns": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Columns to return, after grouping (default: all)"
+                },
+                "sort_by": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Columns to sort by"
+                },
+                "descending": {
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Sort in descending order"
+                },
+                "limit": {
+                    "type": "integer",
+                    "default": 50,
+                    "description": "Most rows to return (at most 1000)"
+                },
+                "chart": {
+                    "type": "string",
+                    "enum": ["bar", "line", "scatter"],
+                    "default": "bar",
+                    "description": "Kind of chart for the chart operation"
+                },
+                "x": {
+                    "type": "string",
+                    "description": "Column for the x axis; bar charts use it for labels"
+                },
+                "y": {
+                    "type": "string",
+                    "description": "Numeric column for the y axis"
+                },
+                "title": {
+                    "type": "string",
+                    "description": "Chart title (default: '<y> by <x>')"
+                },
+                "output_path": {
+                    "type": "string",
+                    "description": "Where to save the chart PNG"
+                }
+            }
+        }),
+    )
+    .annotate(ToolAnnotations {
+        title: Some("Data analysis".to_string()),
+        read_only_hint: Some(false),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    })
+}
+
+fn invalid(message: impl Into<String>) -> ToolError {
+    ToolError::InvalidParameters(message.into())
+}
+
+/// Open a data file lazily, so only what a query needs is read
+fn scan(path: &Path) -> Result<LazyFrame> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let frame = match extension.as_str() {
+        "parquet" | "pq" => LazyFrame::scan_parquet(path, ScanArgsParquet::default())?,
+        "csv" | "tsv" | "txt" => LazyCsvReader::new(path)
+            .with_has_header(true)
+            .with_separator(if extension == "tsv" { b'\t' } else { b',' })
+            .with_infer_schema_length(Some(INFER_SCHEMA_ROWS))
+            .finish()?,
+        _ => {
+            return Err(anyhow!(
+                "Unsupported file type '{}': expected .csv, .tsv or .parquet",
+                extension
+            ))
+        }
+    };
+    Ok(frame)
+}
+
+/// A JSON value as a literal to compare a column with
+fn literal(value: &Value) -> Result<Expr, ToolError> {
+    match value {
+        Value::String(s) => Ok(lit(s.clone())),
+        Value::Bool(b) => Ok(lit(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Ok(lit(i)),
+            None => Ok(lit(n.as_f64().unwrap_or_default())),
+        },
+        _ => Err(invalid(format!(
+            "Filter value {} must be a string, number or boolean",
+            value
+        ))),
+    }
+}
+
+/// One filter, as `{"column", "op", "value"}`
+fn filter_expr(filter: &Value) -> Result<Expr, ToolError> {
+    let column = filter["column"]
+        .as_str()
+        .ok_or_else(|| invalid("Each filter needs a 'column'"))?;
+    let op = filter["op"].as_str().unwrap_or("eq");
+    let value = || literal(&filter["value"]);
+    let column = col(column);
+    Ok(match op {
+        "eq" => column.eq(value()?),
+        "ne" => column.neq(value()?),
+        "gt" => column.gt(value()?),
+        "ge" => column.gt_eq(value()?),
+        "lt" => column.lt(value()?),
+        "le" => column.lt_eq(value()?),
+        "contains" => column.str().contains_literal(value()?),
+        "is_null" => column.is_null(),
+        "not_null" => column.is_not_null(),
+        _ => {
+            return Err(invalid(format!(
+                "Unknown filter op '{}': use eq, ne, gt, ge, lt, le, contains, is_null or not_null",
+                op
+            )))
+        }
+    })
+}
+
+/// One aggregation, as `{"column", "function"}`, named `<column>_<function>`
+fn aggregation_expr(aggregation: &Value) -> Result<Expr, ToolError> {
+    let column = aggregation["column"]
+        .as_str()
+        .ok_or_else(|| invalid("Each aggregation needs a 'column'"))?;
+    let function = aggregation["function"].as_str().unwrap_or("count");
+    let expr = col(column);
+    let expr = match function {
+        "count" => expr.count(),
+        "n_unique" => expr.n_unique(),
+        "sum" => expr.sum(),
+        "mean" => expr.mean(),
+        "median" => expr.median(),
+        "min" => expr.min(),
+        "max" => expr.max(),
+        "std" => expr.std(1),
+        _ => {
+            return Err(invalid(format!(
+                "Unknown aggregation '{}': use count, n_unique, sum, mean, median, min, max or std",
+                function
+            )))
+        }
+    };
+    Ok(expr.alias(format!("{}_{}", column, function)))
+}
+
+fn string_list(params: &Value, key: &str) -> Vec<String> {
+    params[key]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The frame after the filters, grouping, column selection and sorting in `params`
+fn shape(frame: LazyFrame, params: &Value) -> Result<LazyFrame, ToolError> {
+    let mut frame = frame;
+    if let Some(filters) = params["filters"].as_array() {
+        for filter in filters {
+            frame = frame.filter(filter_expr(filter)?);
+        }
+    }
+
+    let group_by = string_list(params, "group_by");
+    let aggregations = params["aggregations"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .map(aggregation_expr)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+    if !group_by.is_empty() {
+        let keys: Vec<Expr> = group_by.iter().map(|key| col(key.as_str())).collect();
+        let aggregations = if aggregations.is_empty() {
+            vec![len().alias("count")]
+        } else {
+            aggregations
+        };
+        frame = frame.group_by_stable(keys).agg(aggregations);
+    } else if !aggregations.is_empty() {
+        frame = frame.select(aggregations);
+    }
+
+    let columns = string_list(params, "columns");
+    if !columns.is_empty() {
+        frame = frame.select(
+            columns
+                .iter()
+                .map(|column| col(column.as_str()))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let sort_by = string_list(params, "sort_by");
+    if !sort_by.is_empty() {
+        let descending = params["descending"].as_bool().unwrap_or(false);
+        frame = frame.sort(
+            sort_by,
+            SortMultipleOptions::default()
+                .with_order_descending(descending)
+                .with_nulls_last(true),
+        );
+    }
+    Ok(frame)
+}
+
+fn row_limit(params: &Value) -> u32 {
+    params["limit"]
+        .as_u64()
+        .map(|limit| (limit as u32).clamp(1, MAX_ROW_LIMIT))
+        .unwrap_or(DEFAULT_ROW_LIMIT)
+}
+
+fn row_count(frame: LazyFrame) -> Result<u64> {
+    let counted = frame.select([len().alias("rows")]).collect()?;
+    let rows = counted
+        .column("rows")?
+        .as_materialized_series()
+        .cast(&DataType::UInt64)?;
+    Ok(rows.u64()?.get(0).unwrap_or(0))
+}
+
+fn to_csv(frame: &mut DataFrame) -> Result<String> {
+    let mut buffer = Vec::new();
+    CsvWriter::new(&mut buffer)
+        .include_header(true)
+        .finish(frame)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Column names and types, the number of rows and the first few rows
+fn schema(path: &Path) -> Result<String> {
+    let frame = scan(path)?;
+    let schema = frame.clone().collect_schema()?;
+    let mut report = String::from("Columns:\n");
+    for (name, dtype) in schema.iter() {
+        report.push_str(&format!("- {}: {}\n", name, dtype));
+    }
+    report.push_str(&format!("\nRows: {}\n", row_count(frame.clone())?));
+    let mut sample = frame.limit(5).collect()?;
+    report.push_str(&format!("\nFirst rows:\n{}", to_csv(&mut sample)?));
+    Ok(report)
+}
+
+/// Matching rows, or groups when aggregating, as CSV
+fn query(path: &Path, params: &Value) -> Result<String, ToolError> {
+    let frame = shape(scan(path).map_err(execution_error)?, params)?;
+    let total = row_count(frame.clone()).map_err(execution_error)?;
+    let limit = row_limit(params);
+    let mut rows = frame.limit(limit).collect().map_err(execution_error)?;
+    let csv = to_csv(&mut rows).map_err(execution_error)?;
+    Ok(if total > rows.height() as u64 {
+        format!(
+            "{} rows matched; showing the first {}. Narrow the query, aggregate, or raise 'limit' to see more.\n\n{}",
+            total,
+            rows.height(),
+            csv
+        )
+    } else {
+        format!("{} rows:\n\n{}", total, csv)
+    })
+}
+
+fn execution_error(e: impl std::fmt::Display) -> ToolError {
+    ToolError::ExecutionError(e.to_string())
+}
+
+fn float_values(frame: &DataFrame, column: &str) -> Result<Vec<Option<f64>>> {
+    let series = frame
+        .column(column)?
+        .as_materialized_series()
+        .cast(&DataType::Float64)
+        .with_context(|| format!("Column '{}' is not numeric", column))?;
+    Ok(series.f64()?.into_iter().collect())
+}
+
+fn string_values(frame: &DataFrame, column: &str) -> Result<Vec<String>> {
+    let series = frame
+        .column(column)?
+        .as_materialized_series()
+        .cast(&DataType::String)?;
+    Ok(series
+        .str()?
+        .into_iter()
+        .map(|value| value.unwrap_or("null").to_string())
+        .collect())
+}
+
+/// Padded bounds of `values`, so points don't sit on the chart's edges
+fn bounds(values: impl Iterator<Item = f64>, include_zero: bool) -> (f64, f64) {
+    let (mut min, mut max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    if include_zero {
+        min = min.min(0.0);
+        max = max.max(0.0);
+    }
+    if !min.is_finite() || !max.is_finite() {
+        return (0.0, 1.0);
+    }
+    let padding = ((max - min) * 0.05).max(f64::EPSILON);
+    (min - padding, max + padding)
+}
+
+/// Draw `y` against `x` as a bar, line or scatter chart into a PNG at `output`
+fn chart(path: &Path, params: &Value, output: &Path) -> Result<String, ToolError> {
+    let kind = params["chart"].as_str().unwrap_or("bar");
+    let x = params["x"]
+        .as_str()
+        .ok_or_else(|| invalid("Charts need an 'x' column"))?;
+    let y = params["y"]
+        .as_str()
+        .ok_or_else(|| invalid("Charts need a 'y' column"))?;
+    let title = params["title"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| format!("{} by {}", y, x));
+
+    let frame = shape(scan(path).map_err(execution_error)?, params)?;
+    let frame = match kind {
+        "bar" => frame.limit(MAX_BARS as u32 + 1),
+        "line" => frame
+            .sort([x], SortMultipleOptions::default())
+            .limit(MAX_POINTS),
+        "scatter" => frame.limit(MAX_POINTS),
+        _ => {
+            return Err(invalid(format!(
+                "Unknown chart '{}': use bar, line or scatter",
+                kind
+            )))
+        }
+    };
+    let frame = frame.collect().map_err(execution_error)?;
+    let ys = float_values(&frame, y).map_err(execution_error)?;
+
+    let root = BitMapBackend::new(output, CHART_SIZE).into_drawing_area();
+    let drawn: Result<usize> = (|| {
+        root.fill(&WHITE)?;
+        let mut builder = ChartBuilder::on(&root);
+        builder
+            .caption(&title, ("sans-serif", 26))
+            .margin(20)
+            .x_label_area_size(70)
+            .y_label_area_size(80);
+
+        if kind == "bar" {
+            if frame.height() > MAX_BARS {
+                return Err(anyhow!(
+                    "A bar chart shows at most {} bars; group or filter the data first",
+                    MAX_BARS
+                ));
+            }
+            let labels = string_values(&frame, x)?;
+            let values: Vec<f64> = ys.iter().map(|v| v.unwrap_or(0.0)).collect();
+            let (min, max) = bounds(values.iter().copied(), true);
+            let mut chart = builder
+                .build_cartesian_2d((0u32..values.len() as u32).into_segmented(), min..max)?;
+            chart
+                .configure_mesh()
+                .disable_x_mesh()
+                .x_labels(values.len())
+                .x_label_formatter(&|segment| match segment {
+                    SegmentValue::CenterOf(i) => {
+                        labels.get(*i as usize).cloned().unwrap_or_default()
+                    }
+                    _ => String::new(),
+                })
+                .x_desc(x)
+                .y_desc(y)
+                .draw()?;
+            chart.draw_series(
+                Histogram::vertical(&chart)
+                    .style(BLUE.mix(0.8).filled())
+                    .margin(4)
+                    .data(values.iter().enumerate().map(|(i, v)| (i as u32, *v))),
+            )?;
+            return Ok(values.len());
+        }
+
+        let xs = float_values(&frame, x)?;
+        let points: Vec<(f64, f64)> = xs
+            .into_iter()
+            .zip(ys.iter().copied())
+            .filter_map(|(x, y)| Some((x?, y?)))
+            .collect();
+        let (x_min, x_max) = bounds(points.iter().map(|p| p.0), false);
+        let (y_min, y_max) = bounds(points.iter().map(|p| p.1), false);
+        let mut chart = builder.build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+        chart.configure_mesh().x_desc(x).y_desc(y).draw()?;
+        if kind == "line" {
+            chart.draw_series(LineSeries::new(
+                points.iter().copied(),
+                BLUE.stroke_width(2),
+            ))?;
+        } else {
+            chart.draw_series(
+                points
+                    .iter()
+                    .map(|point| Circle::new(*point, 3, BLUE.mix(0.6).filled())),
+            )?;
+        }
+        Ok(points.len())
+    })();
+    let count = drawn.map_err(execution_error)?;
+    root.present().map_err(execution_error)?;
+    Ok(format!(
+        "Saved a {} chart of {} values to {}",
+        kind,
+        count,
+        output.display()
+    ))
+}
+
+pub async fn data_tool(
+    path: &str,
+    operation: &str,
+    params: &Value,
+    cache_dir: &Path,
+) -> Result<Vec<Content>, ToolError> {
+    let path = PathBuf::from(path);
+    let operation = operation.to_string();
+    let params = params.clone();
+    let output = params["output_path"]
+        .as_str()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            cache_dir.join(format!(
+                "chart_{}.png",
+                chrono::Local::now().format("%Y%m%d_%H%M%S")
+            ))
+        });
+
+    // Queries can scan large files, so they run off the async runtime
+    tokio::task::spawn_blocking(move || match operation.as_str() {
+        "schema" => Ok(vec![Content::text(schema(&path).map_err(execution_error)?)]),
+        "query" | "aggregate" => Ok(vec![Content::text(query(&path, &params)?)]),
+        "chart" => {
+            let message = chart(&path, &params, &output)?;
+            let png = std::fs::read(&output).map_err(execution_error)?;
+            let data = base64::prelude::BASE64_STANDARD.encode(png);
+            Ok(vec![
+                Content::text(message),
+                Content::image(data, "image/png"),
+            ])
+        }
+        _ => Err(invalid(format!(
+            "Invalid operation: {}. Use schema, query, aggregate or chart",
+            operation
+        ))),
+    })
+    .await
+    .map_err(execution_error)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sales_csv() -> String {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/computercontroller/tests/data/sales.csv")
+            .display()
+            .to_string()
+    }
+
+    async fn text(operation: &str, params: Value) -> String {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let content = data_tool(&sales_csv(), operation, &params, cache_dir.path())
+            .await
+            .unwrap();
+        content[0].as_text().unwrap().text.clone()
+    }
+
+    #[tokio::test]
+    async fn test_data_tool_schema_query_and_aggregate() {
+        let schema = text("schema", json!({})).await;
+        assert!(schema.contains("- region: str"));
+        assert!(schema.contains("- units: i64"));
+        assert!(schema.contains("Rows: 6"));
+
+        let rows = text(
+            "query",
+            json!({
+                "filters": [{"column": "units", "op": "ge", "value": 20}],
+                "columns": ["month", "region", "units"],
+                "sort_by": ["units"],
+                "descending": true,
+                "limit": 2
+            }),
+        )
+        .await;
+        assert!(rows.starts_with("3 rows matched; showing the first 2"));
+        assert!(rows.contains("month,region,units\n2024-02,east,40\n2024-03,east,35\n"));
+
+        let totals = text(
+            "query",
+            json!({
+                "group_by": ["region"],
+                "aggregations": [{"column": "revenue", "function": "sum"}],
+                "sort_by": ["region"]
+            }),
+        )
+        .await;
+        assert!(totals.contains("region,revenue_sum\neast,1125.0\nwest,330.0\n"));
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let unknown = data_tool(
+            &sales_csv(),
+            "query",
+            &json!({"filters": [{"column": "units", "op": "between"}]}),
+            cache_dir.path(),
+        )
+        .await;
+        assert!(matches!(unknown, Err(ToolError::InvalidParameters(_))));
+    }
+}
@@ -24,6 +24,8 @@ use rmcp::model::{
 };
 use rmcp::object;
 
+#[cfg(feature = "data-tool")]
+mod data_tool;
 mod docx_tool;
 mod pdf_tool;
 mod xlsx_tool;
@@ -31,6 +33,15 @@ mod xlsx_tool;
 mod platform;
 use platform::{create_system_automation, SystemAutomation};
 
+/// How the instructions describe data_tool, when the extension has it
+#[cfg(feature = "data-tool")]
+const DATA_TOOL_INSTRUCTIONS: &str = "data_tool
+  - Inspect, filter, aggregate and chart CSV, TSV and Parquet files
+  - Works on files of any size without reading them into the conversation
+";
+#[cfg(not(feature = "data-tool"))]
+const DATA_TOOL_INSTRUCTIONS: &str = "";
+
 /// An extension designed for non-developers to help them with common tasks like
 /// web scraping, data processing, and automation.
 #[derive(Clone)]
@@ -527,7 +538,7 @@ impl ComputerControllerRouter {
               - Save as text, JSON, or binary files
               - Content is cached locally for later use
               - This is not optimised for complex websites, so don't use this as the first tool.
-            cache
+            {data_tool_instructions}cache
               - Manage your cached files
               - List, view, delete files
               - Clear all cached data
@@ -536,19 +547,25 @@ impl ComputerControllerRouter {
             - File organization and cleanup
             "#,
             os_instructions = os_specific_instructions,
+            data_tool_instructions = DATA_TOOL_INSTRUCTIONS,
             cache_dir = cache_dir.display()
         };
 
+        #[allow(unused_mut)]
+        let mut tools = vec![
+            web_scrape_tool,
+            quick_script_tool,
+            computer_control_tool,
+            cache_tool,
+            pdf_tool,
+            docx_tool,
+            xlsx_tool,
+        ];
+        #[cfg(feature = "data-tool")]
+        tools.push(data_tool::data_tool_definition());
+
         Self {
-            tools: vec![
-                web_scrape_tool,
-                quick_script_tool,
-                computer_control_tool,
-                cache_tool,
-                pdf_tool,
-                docx_tool,
-                xlsx_tool,
-            ],
+            tools,
             cache_dir,
             active_resources: Arc::new(Mutex::new(HashMap::new())),
             http_client: Client::builder().user_agent("Goose/1.0").build().unwrap(),
@@ -1007,6 +1024,22 @@ impl ComputerControllerRouter {
         crate::computercontroller::pdf_tool::pdf_tool(path, operation, &self.cache_dir).await
     }
 
+    #[cfg(feature = "data-tool")]
+    async fn data_tool(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let path = params
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'path' parameter".into()))?;
+
+        let operation = params
+            .get("operation")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'operation' parameter".into()))?;
+
+        crate::computercontroller::data_tool::data_tool(path, operation, &params, &self.cache_dir)
+            .await
+    }
+
     async fn cache(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let command = params
             .get("command")
@@ -1121,6 +1154,8 @@ impl Router for ComputerControllerRouter {
                 "pdf_tool" => this.pdf_tool(arguments).await,
                 "docx_tool" => this.docx_tool(arguments).await,
                 "xlsx_tool" => this.xlsx_tool(arguments).await,
+                #[cfg(feature = "data-tool")]
+                "data_tool" => this.data_tool(arguments).await,
                 _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
             }
         })
@@ -0,0 +1,237 @@
+//! Vulnerability audits of dependency changes.
+//!
+//! With `GOOSE_DEPENDENCY_AUDIT` set, a shell command, edit or changeset that changes a
+//! dependency manifest or lockfile is followed by that ecosystem's audit tool (cargo audit, npm
+//! audit, pip-audit, govulncheck or bundle-audit), run in the manifest's directory. What it
+//! reports is added to the tool result, so the model can deal with it before the change is done.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::process::Command;
+
+/// Environment variable turning the audit on
+pub const DEPENDENCY_AUDIT_ENV: &str = "GOOSE_DEPENDENCY_AUDIT";
+
+/// How long one audit may run
+const AUDIT_TIMEOUT: Duration = Duration::from_secs(120);
+/// How much of a failed audit's output is kept
+const AUDIT_OUTPUT_LINES: usize = 40;
+
+/// An audit tool and the files whose changes it checks
+struct Auditor {
+    manifests: &'static [&'static str],
+    command: &'static [&'static str],
+}
+
+const AUDITORS: &[Auditor] = &[
+    Auditor {
+        manifests: &["Cargo.toml", "Cargo.lock"],
+        command: &["cargo", "audit"],
+    },
+    Auditor {
+        manifests: &["package.json", "package-lock.json", "npm-shrinkwrap.json"],
+        command: &["npm", "audit"],
+    },
+    Auditor {
+        manifests: &["pnpm-lock.yaml"],
+        command: &["pnpm", "audit"],
+    },
+    Auditor {
+        manifests: &["requirements.txt"],
+        command: &["pip-audit", "-r", "requirements.txt"],
+    },
+    Auditor {
+        manifests: &["pyproject.toml", "poetry.lock", "uv.lock"],
+        command: &["pip-audit", "."],
+    },
+    Auditor {
+        manifests: &["go.mod", "go.sum"],
+        command: &["govulncheck", "./..."],
+    },
+    Auditor {
+        manifests: &["Gemfile", "Gemfile.lock"],
+        command: &["bundle-audit", "check"],
+    },
+];
+
+pub fn enabled() -> bool {
+    std::env::var(DEPENDENCY_AUDIT_ENV)
+        .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+fn auditor_for(path: &Path) -> Option<&'static Auditor> {
+    let name = path.file_name()?.to_str()?;
+    AUDITORS
+        .iter()
+        .find(|auditor| auditor.manifests.contains(&name))
+}
+
+/// The manifests a shell command run in `dir` might change
+pub fn manifests_in(dir: &Path) -> Vec<PathBuf> {
+    AUDITORS
+        .iter()
+        .flat_map(|auditor| auditor.manifests.iter().map(|name| dir.join(name)))
+        .collect()
+}
+
+fn content_hash(path: &Path) -> Option<u64> {
+    let content = std::fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// The state of the manifests among some paths, taken before a tool call
+#[derive(Debug, Default)]
+pub struct ManifestSnapshot {
+    files: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl ManifestSnapshot {
+    pub fn take(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut files: Vec<(PathBuf, Option<u64>)> = Vec::new();
+        for path in paths {
+            if auditor_for(&path).is_some() && !files.iter().any(|(seen, _)| *seen == path) {
+                let hash = content_hash(&path);
+                files.push((path, hash));
+            }
+        }
+        Self { files }
+    }
+
+    /// The manifests that are different now
+    pub fn changed(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|(path, before)| content_hash(path) != *before)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// What running one audit found, as a line or a short block for the model
+async fn run(auditor: &Auditor, dir: &Path) -> String {
+    let display = auditor.command.join(" ");
+    let output = tokio::time::timeout(
+        AUDIT_TIMEOUT,
+        Command::new(auditor.command[0])
+            .args(&auditor.command[1..])
+            .current_dir(dir)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+    let output = match output {
+        Err(_) => {
+            return format!(
+                "`{}` in {} did not finish within {} seconds.",
+                display,
+                dir.display(),
+                AUDIT_TIMEOUT.as_secs()
+            )
+        }
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            return format!(
+                "`{}` is not installed, so the dependencies in {} were not audited. Mention this \
+                 to the user.",
+                auditor.command[0],
+                dir.display()
+            )
+        }
+        Ok(Err(e)) => return format!("`{}` in {} failed: {}", display, dir.display(), e),
+        Ok(Ok(output)) => output,
+    };
+    if output.status.success() {
+        return format!(
+            "`{}` found no known vulnerabilities in {}.",
+            display,
+            dir.display()
+        );
+    }
+
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let lines: Vec<&str> = combined.lines().collect();
+    let tail = lines[lines.len().saturating_sub(AUDIT_OUTPUT_LINES)..].join("\n");
+    format!(
+        "`{}` reported problems in {} ({}):\n```\n{}\n```\nFix these, for example by moving to \
+         patched versions, before finishing the change, or explain to the user why they are \
+         acceptable.",
+        display,
+        dir.display(),
+        output.status,
+        tail
+    )
+}
+
+/// Audit the projects whose manifests changed, once per project and tool
+pub async fn audit(changed: &[PathBuf]) -> Option<String> {
+    let mut runs: Vec<(&'static Auditor, PathBuf)> = Vec::new();
+    for path in changed {
+        let (Some(auditor), Some(dir)) = (auditor_for(path), path.parent()) else {
+            continue;
+        };
+        if !runs
+            .iter()
+            .any(|(seen, seen_dir)| std::ptr::eq(*seen, auditor) && seen_dir == dir)
+        {
+            runs.push((auditor, dir.to_path_buf()));
+        }
+    }
+    if runs.is_empty() {
+        return None;
+    }
+
+    let mut findings = Vec::new();
+    for (auditor, dir) in &runs {
+        findings.push(run(auditor, dir).await);
+    }
+    Some(format!(
+        "Dependency audit after changes to {}:\n{}",
+        changed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        findings.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_finds_changed_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[dependencies]\n").unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let mut paths = manifests_in(dir.path());
+        paths.push(dir.path().join("main.rs"));
+        let snapshot = ManifestSnapshot::take(paths);
+        assert!(snapshot.changed().is_empty());
+
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[dependencies]\nserde = \"1\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("go.mod"), "module example.com/app\n").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() { todo!() }").unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+
+        assert_eq!(
+            snapshot.changed(),
+            vec![dir.path().join("Cargo.toml"), dir.path().join("go.mod")]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Multi-file edits applied as one transaction.
+//!
+//! The model proposes every edit of a change at once. They are worked out in memory first, so an
+//! edit that doesn't apply fails the whole changeset, then checked: changed files with a syntax
+//! goose can check must parse, and an optional command (a build or a test run) must pass in a
+//! shadow copy of the project with the edits applied. Only then are the files written, and if
+//! one write fails the files already written are restored. Every changeset is recorded, applied
+//! or not, so it can be reviewed with `goose diff`.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use super::shell::{get_shell_config, normalize_line_endings};
+
+/// Lines of unchanged context around each change in a diff
+const CONTEXT_LINES: usize = 3;
+/// How much of a failed validation command's output is kept
+const VALIDATION_OUTPUT_LINES: usize = 50;
+
+/// One edit of a changeset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum FileEdit {
+    /// Create or overwrite a file
+    Write { path: PathBuf, file_text: String },
+    /// Replace the one occurrence of `old_str`, as the edit so far left the file
+    StrReplace {
+        path: PathBuf,
+        old_str: String,
+        new_str: String,
+    },
+    /// Remove a file
+    Delete { path: PathBuf },
+}
+
+impl FileEdit {
+    pub fn path(&self) -> &Path {
+        match self {
+            FileEdit::Write { path, .. }
+            | FileEdit::StrReplace { path, .. }
+            | FileEdit::Delete { path } => path,
+        }
+    }
+}
+
+/// A file's content before and after a changeset; `None` when it doesn't exist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl FileChange {
+    /// The change as a unified diff
+    pub fn diff(&self) -> String {
+        let before = self.before.as_deref().unwrap_or_default();
+        let after = self.after.as_deref().unwrap_or_default();
+        let path = self.path.display().to_string();
+        let old_header = match self.before {
+            Some(_) => format!("a{}", path),
+            None => "/dev/null".to_string(),
+        };
+        let new_header = match self.after {
+            Some(_) => format!("b{}", path),
+            None => "/dev/null".to_string(),
+        };
+        TextDiff::from_lines(before, after)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&old_header, &new_header)
+            .to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangesetStatus {
+    Applied,
+    Rejected,
+}
+
+/// A recorded changeset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Changeset {
+    pub id: String,
+    pub created: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub status: ChangesetStatus,
+    /// Why the changeset wasn't applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub changes: Vec<FileChange>,
+}
+
+impl Changeset {
+    pub fn new(description: Option<String>, changes: Vec<FileChange>) -> Self {
+        let created = Utc::now();
+        Self {
+            id: created.format("%Y%m%d_%H%M%S_%3f").to_string(),
+            created,
+            description,
+            status: ChangesetStatus::Applied,
+            error: None,
+            changes,
+        }
+    }
+
+    pub fn rejected(mut self, error: impl Into<String>) -> Self {
+        self.status = ChangesetStatus::Rejected;
+        self.error = Some(error.into());
+        self
+    }
+
+    /// Every change as one unified diff
+    pub fn diff(&self) -> String {
+        self.changes.iter().map(FileChange::diff).collect()
+    }
+
+    /// Record the changeset so `goose diff` can show it
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let dir = changeset_dir();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", self.id));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn load(id: &str) -> anyhow::Result<Self> {
+        let path = changeset_dir().join(format!("{}.json", id));
+        let json = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("No changeset {}: {}", id, e))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// All recorded changesets, newest first
+    pub fn list() -> anyhow::Result<Vec<Self>> {
+        let dir = changeset_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut changesets: Vec<Self> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+            })
+            .collect();
+        changesets.sort_by(|a, b| b.created.cmp(&a.created));
+        Ok(changesets)
+    }
+}
+
+fn changeset_dir() -> PathBuf {
+    choose_app_strategy(crate::APP_STRATEGY.clone())
+        .map(|strategy| strategy.data_dir())
+        .unwrap_or_else(|_| std::env::temp_dir().join("goose"))
+        .join("changesets")
+}
+
+/// Work out what every file looks like after all the edits, in order, without touching the disk
+pub fn plan(edits: &[FileEdit]) -> Result<Vec<FileChange>, String> {
+    let mut changes: Vec<FileChange> = Vec::new();
+    for edit in edits {
+        let index = match changes.iter().position(|c| c.path == edit.path()) {
+            Some(index) => index,
+            None => {
+                let path = edit.path().to_path_buf();
+                let before = if path.exists() {
+                    Some(
+                        std::fs::read_to_string(&path)
+                            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+                    )
+                } else {
+                    None
+                };
+                changes.push(FileChange {
+                    path,
+                    after: before.clone(),
+                    before,
+                });
+                changes.len() - 1
+            }
+        };
+        let change = &mut changes[index];
+
+        change.after = match edit {
+            FileEdit::Write { file_text, .. } => {
+                let mut text = normalize_line_endings(file_text);
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                Some(text)
+            }
+            FileEdit::StrReplace {
+                old_str, new_str, ..
+            } => {
+                let Some(content) = &change.after else {
+                    return Err(format!(
+                        "Can't replace text in {}, it doesn't exist",
+                        change.path.display()
+                    ));
+                };
+                match content.matches(old_str.as_str()).count() {
+                    1 => Some(content.replacen(old_str.as_str(), new_str, 1)),
+                    0 => {
+                        return Err(format!(
+                            "'old_str' was not found in {}",
+                            change.path.display()
+                        ))
+                    }
+                    _ => {
+                        return Err(format!(
+                            "'old_str' appears more than once in {}",
+                            change.path.display()
+                        ))
+                    }
+                }
+            }
+            FileEdit::Delete { .. } => {
+                if change.after.is_none() {
+                    return Err(format!(
+                        "Can't delete {}, it doesn't exist",
+                        change.path.display()
+                    ));
+                }
+                None
+            }
+        };
+    }
+    changes.retain(|change| change.before != change.after);
+    Ok(changes)
+}
+
+/// Check that changed files in a format goose can parse still parse
+pub fn check_syntax(changes: &[FileChange]) -> Result<(), String> {
+    for change in changes {
+        let Some(after) = &change.after else {
+            continue;
+        };
+        let extension = change.path.extension().and_then(|ext| ext.to_str());
+        if extension == Some("json") {
+            serde_json::from_str::<serde_json::Value>(after)
+                .map_err(|e| format!("{} is not valid JSON: {}", change.path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `command` in a copy of `root` with the changes applied, failing with the tail of its
+/// output if it doesn't succeed. The copy leaves out files ignored by git.
+pub async fn validate_in_shadow(
+    root: &Path,
+    changes: &[FileChange],
+    command: &str,
+) -> Result<(), String> {
+    let shadow = tempfile::tempdir().map_err(|e| format!("Failed to create shadow copy: {}", e))?;
+    copy_project(root, shadow.path())
+        .map_err(|e| format!("Failed to create shadow copy: {}", e))?;
+
+    for change in changes {
+        let Ok(relative) = change.path.strip_prefix(root) else {
+            // Outside the project, so nothing the command runs in depends on it
+            continue;
+        };
+        let target = shadow.path().join(relative);
+        match &change.after {
+            Some(after) => {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(&target, after).map_err(|e| e.to_string())?;
+            }
+            None => {
+                let _ = std::fs::remove_file(&target);
+            }
+        }
+    }
+
+    let shell = get_shell_config();
+    let output = tokio::process::Command::new(&shell.executable)
+        .args(&shell.args)
+        .arg(command)
+        .current_dir(shadow.path())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run '{}': {}", command, e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let lines: Vec<&str> = combined.lines().collect();
+    let tail = lines[lines.len().saturating_sub(VALIDATION_OUTPUT_LINES)..].join("\n");
+    Err(format!(
+        "'{}' failed ({}):\n{}",
+        command, output.status, tail
+    ))
+}
+
+fn copy_project(root: &Path, shadow: &Path) -> std::io::Result<()> {
+    for entry in ignore::WalkBuilder::new(root).hidden(false).build() {
+        let entry = entry.map_err(std::io::Error::other)?;
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if relative
+            .components()
+            .next()
+            .is_some_and(|c| c.as_os_str() == ".git")
+        {
+            continue;
+        }
+        let target = shadow.join(relative);
+        if entry.file_type().is_some_and(|t| t.is_dir()) {
+            std::fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_some_and(|t| t.is_file()) {
+            std::fs::copy(path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write every change. If a write fails, the files already written are put back as they were.
+pub fn apply(changes: &[FileChange]) -> Result<(), String> {
+    for (index, change) in changes.iter().enumerate() {
+        if let Err(e) = write_content(&change.path, change.after.as_deref()) {
+            for written in changes[..index].iter().rev() {
+                if let Err(restore) = write_content(&written.path, written.before.as_deref()) {
+                    tracing::error!("Failed to restore {}: {}", written.path.display(), restore);
+                }
+            }
+            return Err(format!(
+                "Failed to write {}, no files were changed: {}",
+                change.path.display(),
+                e
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn write_content(path: &Path, content: Option<&str>) -> std::io::Result<()> {
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)
+        }
+        None if path.exists() => std::fs::remove_file(path),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_applies_edits_in_order_and_all_or_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib.rs");
+        std::fs::write(&lib, "fn a() {}\nfn b() {}\n").unwrap();
+        let config = dir.path().join("config.json");
+
+        let edits = vec![
+            FileEdit::StrReplace {
+                path: lib.clone(),
+                old_str: "fn a()".into(),
+                new_str: "fn renamed()".into(),
+            },
+            FileEdit::StrReplace {
+                path: lib.clone(),
+                old_str: "fn renamed() {}".into(),
+                new_str: "fn renamed() { b() }".into(),
+            },
+            FileEdit::Write {
+                path: config.clone(),
+                file_text: "{\"a\": 1}".into(),
+            },
+        ];
+        let changes = plan(&edits).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[0].after.as_deref(),
+            Some("fn renamed() { b() }\nfn b() {}\n")
+        );
+        assert_eq!(changes[1].before, None);
+        assert!(check_syntax(&changes).is_ok());
+        assert!(changes[1].diff().contains("+{\"a\": 1}"));
+
+        let mut failing = edits.clone();
+        failing.push(FileEdit::StrReplace {
+            path: lib.clone(),
+            old_str: "fn missing()".into(),
+            new_str: String::new(),
+        });
+        assert!(plan(&failing).is_err());
+        // Planning never writes
+        assert!(!config.exists());
+
+        apply(&changes).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&lib).unwrap(),
+            "fn renamed() { b() }\nfn b() {}\n"
+        );
+        assert!(config.exists());
+    }
+
+    #[test]
+    fn test_invalid_json_is_rejected() {
+        let changes = vec![FileChange {
+            path: PathBuf::from("/repo/package.json"),
+            before: None,
+            after: Some("{\"name\": ".to_string()),
+        }];
+        assert!(check_syntax(&changes).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_validation_runs_against_a_shadow_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("value.txt");
+        std::fs::write(&file, "old\n").unwrap();
+        let changes = vec![FileChange {
+            path: file.clone(),
+            before: Some("old\n".to_string()),
+            after: Some("new\n".to_string()),
+        }];
+
+        assert!(
+            validate_in_shadow(dir.path(), &changes, "grep -q new value.txt")
+                .await
+                .is_ok()
+        );
+        assert!(
+            validate_in_shadow(dir.path(), &changes, "grep -q old value.txt")
+                .await
+                .is_err()
+        );
+        // The project itself is untouched
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "old\n");
+    }
+}
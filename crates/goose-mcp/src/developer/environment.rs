@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Extra variables (or `PREFIX_*` patterns) to pass to shell commands, comma separated
+pub const PASSTHROUGH_KEY: &str = "GOOSE_SHELL_ENV_PASSTHROUGH";
+/// Variables holding secrets that shell commands need; passed through but redacted from output
+pub const SECRETS_KEY: &str = "GOOSE_SHELL_ENV_SECRETS";
+/// Set to `true` to pass the whole environment to shell commands, as goose used to
+pub const INHERIT_KEY: &str = "GOOSE_SHELL_ENV_INHERIT";
+
+/// Variables every shell command gets, when set
+const DEFAULT_VARS: &[&str] = &[
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "COLORTERM",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "TZ",
+    "LANG",
+    "LANGUAGE",
+    "EDITOR",
+    "VISUAL",
+    "PAGER",
+    "SSH_AUTH_SOCK",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "VIRTUAL_ENV",
+    "CONDA_PREFIX",
+    "NVM_DIR",
+    "PYENV_ROOT",
+    "GOPATH",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "JAVA_HOME",
+    // Windows
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERNAME",
+    "USERPROFILE",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "PROGRAMFILES",
+    "PROGRAMFILES(X86)",
+    "PROGRAMW6432",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+    "OS",
+];
+
+/// Prefixes of variables every shell command gets
+const DEFAULT_PREFIXES: &[&str] = &["LC_", "XDG_"];
+
+/// Directories added to PATH when they exist, for when goose is started with a minimal PATH
+/// (e.g. from the desktop app on macOS)
+#[cfg(unix)]
+const EXTRA_PATH_DIRS: &[&str] = &[
+    "/usr/local/bin",
+    "/opt/homebrew/bin",
+    "~/.local/bin",
+    "~/.cargo/bin",
+];
+#[cfg(not(unix))]
+const EXTRA_PATH_DIRS: &[&str] = &[];
+
+/// Secret values shorter than this are not redacted, to avoid mangling ordinary output
+const MIN_REDACTED_LEN: usize = 4;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// The environment shell commands run with.
+///
+/// Rather than inheriting everything from the goose process — which includes provider API keys
+/// and other extensions' secrets — commands get a curated set of variables: the basics a shell
+/// needs, anything listed in `GOOSE_SHELL_ENV_PASSTHROUGH`, and the secrets listed in
+/// `GOOSE_SHELL_ENV_SECRETS`, whose values are redacted from command output.
+#[derive(Debug, Clone, Default)]
+pub struct EnvSnapshot {
+    vars: BTreeMap<String, String>,
+    secrets: Vec<String>,
+}
+
+impl EnvSnapshot {
+    /// Capture the snapshot from the current process environment and its configuration
+    pub fn capture() -> Self {
+        let inherit = std::env::var(INHERIT_KEY)
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+            .unwrap_or(false);
+        Self::from_vars(
+            // Variables that aren't valid unicode can't be passed on faithfully, so leave them out
+            std::env::vars_os().filter_map(|(name, value)| {
+                Some((name.into_string().ok()?, value.into_string().ok()?))
+            }),
+            &list_from_env(PASSTHROUGH_KEY),
+            &list_from_env(SECRETS_KEY),
+            inherit,
+        )
+    }
+
+    fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+        passthrough: &[String],
+        secrets: &[String],
+        inherit: bool,
+    ) -> Self {
+        let mut snapshot = Self::default();
+        let mut path = None;
+        for (name, value) in vars {
+            if is_path_var(&name) {
+                path = Some(value);
+                continue;
+            }
+            let is_secret = secrets.iter().any(|pattern| matches(pattern, &name));
+            if is_secret && value.len() >= MIN_REDACTED_LEN {
+                snapshot.secrets.push(value.clone());
+            }
+            if inherit
+                || is_secret
+                || DEFAULT_VARS.iter().any(|pattern| matches(pattern, &name))
+                || DEFAULT_PREFIXES
+                    .iter()
+                    .any(|prefix| has_prefix(&name, prefix))
+                || passthrough.iter().any(|pattern| matches(pattern, &name))
+            {
+                snapshot.vars.insert(name, value);
+            }
+        }
+
+        if let Some(path) = build_path(path) {
+            snapshot.vars.insert("PATH".to_string(), path);
+        }
+        if cfg!(unix) && !snapshot.has_locale() {
+            // Without a locale many tools fall back to ASCII and mangle non-English output
+            let lang = if cfg!(target_os = "macos") {
+                "en_US.UTF-8"
+            } else {
+                "C.UTF-8"
+            };
+            snapshot.vars.insert("LANG".to_string(), lang.to_string());
+        }
+        // Longest first, so a secret containing another is redacted as a whole
+        snapshot.secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        snapshot
+    }
+
+    fn has_locale(&self) -> bool {
+        ["LANG", "LC_ALL", "LC_CTYPE"]
+            .iter()
+            .any(|name| self.vars.contains_key(*name))
+    }
+
+    /// The value a command will see for `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    /// Add a secret for one command: set as a variable and redacted from its output
+    pub fn add_secret(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let value = value.into();
+        if value.len() >= MIN_REDACTED_LEN && !self.secrets.contains(&value) {
+            self.secrets.push(value.clone());
+            self.secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        }
+        self.vars.insert(name.into(), value);
+    }
+
+    /// Replace the command's environment with the snapshot
+    pub fn apply(&self, command: &mut Command) {
+        command.env_clear().envs(&self.vars);
+    }
+
+    /// Hide the values of injected secrets in command output
+    pub fn redact(&self, output: &str) -> String {
+        let mut output = output.to_string();
+        for secret in &self.secrets {
+            if output.contains(secret.as_str()) {
+                output = output.replace(secret.as_str(), REDACTED);
+            }
+        }
+        output
+    }
+}
+
+fn list_from_env(key: &str) -> Vec<String> {
+    std::env::var(key)
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_path_var(name: &str) -> bool {
+    if cfg!(windows) {
+        name.eq_ignore_ascii_case("PATH")
+    } else {
+        name == "PATH"
+    }
+}
+
+fn has_prefix(name: &str, prefix: &str) -> bool {
+    if cfg!(windows) {
+        name.to_uppercase().starts_with(&prefix.to_uppercase())
+    } else {
+        name.starts_with(prefix)
+    }
+}
+
+/// Match a variable name against a name or a `PREFIX_*` pattern
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => has_prefix(name, prefix),
+        None if cfg!(windows) => pattern.eq_ignore_ascii_case(name),
+        None => pattern == name,
+    }
+}
+
+/// The inherited PATH plus any well-known tool directories it is missing
+fn build_path(inherited: Option<String>) -> Option<String> {
+    let mut dirs: Vec<PathBuf> = inherited
+        .as_deref()
+        .map(|p| std::env::split_paths(p).collect())
+        .unwrap_or_default();
+    for dir in EXTRA_PATH_DIRS {
+        let dir = PathBuf::from(shellexpand::tilde(dir).into_owned());
+        if dir.is_dir() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    if dirs.is_empty() {
+        return inherited;
+    }
+    std::env::join_paths(dirs)
+        .ok()
+        .map(OsString::into_string)
+        .and_then(Result::ok)
+        .or(inherited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn sample() -> Vec<(String, String)> {
+        vars(&[
+            ("HOME", "/home/goose"),
+            ("PATH", "/usr/bin:/bin"),
+            ("LC_ALL", "en_US.UTF-8"),
+            ("OPENAI_API_KEY", "sk-secret-value"),
+            ("GITHUB_TOKEN", "ghp_token_value"),
+            ("NPM_CONFIG_REGISTRY", "https://registry.example.com"),
+        ])
+    }
+
+    #[test]
+    fn test_secrets_are_not_inherited() {
+        let snapshot = EnvSnapshot::from_vars(sample(), &[], &[], false);
+        assert_eq!(snapshot.get("HOME"), Some("/home/goose"));
+        assert_eq!(snapshot.get("LC_ALL"), Some("en_US.UTF-8"));
+        assert!(snapshot.get("PATH").unwrap().starts_with("/usr/bin"));
+        assert_eq!(snapshot.get("OPENAI_API_KEY"), None);
+        assert_eq!(snapshot.get("GITHUB_TOKEN"), None);
+        assert_eq!(snapshot.get("NPM_CONFIG_REGISTRY"), None);
+    }
+
+    #[test]
+    fn test_passthrough_and_secrets() {
+        let snapshot = EnvSnapshot::from_vars(
+            sample(),
+            &["NPM_CONFIG_*".to_string()],
+            &["GITHUB_TOKEN".to_string()],
+            false,
+        );
+        assert_eq!(
+            snapshot.get("NPM_CONFIG_REGISTRY"),
+            Some("https://registry.example.com")
+        );
+        assert_eq!(snapshot.get("GITHUB_TOKEN"), Some("ghp_token_value"));
+        assert_eq!(snapshot.get("OPENAI_API_KEY"), None);
+        assert_eq!(
+            snapshot.redact("token is ghp_token_value\n"),
+            "token is [REDACTED]\n"
+        );
+    }
+
+    #[test]
+    fn test_add_secret() {
+        let mut snapshot = EnvSnapshot::from_vars(sample(), &[], &[], false);
+        snapshot.add_secret("PGPASSWORD", "hunter22");
+        assert_eq!(snapshot.get("PGPASSWORD"), Some("hunter22"));
+        assert_eq!(snapshot.redact("password=hunter22"), "password=[REDACTED]");
+    }
+
+    #[test]
+    fn test_inherit_everything() {
+        let snapshot = EnvSnapshot::from_vars(sample(), &[], &[], true);
+        assert_eq!(snapshot.get("OPENAI_API_KEY"), Some("sk-secret-value"));
+        // Secrets are only redacted when listed explicitly
+        assert_eq!(snapshot.redact("sk-secret-value"), "sk-secret-value");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_locale_defaults_to_utf8() {
+        let snapshot = EnvSnapshot::from_vars(vars(&[("HOME", "/home/goose")]), &[], &[], false);
+        assert!(snapshot.get("LANG").unwrap().ends_with("UTF-8"));
+
+        let snapshot = EnvSnapshot::from_vars(vars(&[("LANG", "de_DE.UTF-8")]), &[], &[], false);
+        assert_eq!(snapshot.get("LANG"), Some("de_DE.UTF-8"));
+    }
+}
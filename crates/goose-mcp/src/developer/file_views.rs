@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use similar::TextDiff;
+
+/// Set to `true` to show the model only what changed when it views a file it has already read
+pub const DIFF_VIEWS_KEY: &str = "GOOSE_FILE_VIEW_DIFFS";
+
+/// Lines of unchanged context around each change
+const CONTEXT_LINES: usize = 3;
+
+/// How a file should be shown to the model
+#[derive(Debug, PartialEq)]
+pub enum FileView {
+    /// The whole file, because it is new to the model or too changed for a diff to help
+    Full,
+    /// Nothing changed since the model last saw it
+    Unchanged { total_lines: usize },
+    /// The changes since the model last saw it, as a unified diff
+    Diff {
+        diff: String,
+        old_lines: usize,
+        new_lines: usize,
+    },
+}
+
+/// The content of each file as the model last saw it in full, so a later view of a changed
+/// file can show just the differences
+#[derive(Default)]
+pub struct FileViews {
+    enabled: bool,
+    seen: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl FileViews {
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var(DIFF_VIEWS_KEY)
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false),
+        )
+    }
+
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Decide how to show a full view of `path` with this `content`, and remember the content
+    /// as what the model has now seen
+    pub fn view(&self, path: &Path, content: &str) -> FileView {
+        if !self.enabled {
+            return FileView::Full;
+        }
+        let Ok(mut seen) = self.seen.lock() else {
+            return FileView::Full;
+        };
+        let Some(previous) = seen.insert(path.to_path_buf(), content.to_string()) else {
+            return FileView::Full;
+        };
+        if previous == content {
+            return FileView::Unchanged {
+                total_lines: content.lines().count(),
+            };
+        }
+
+        let diff = TextDiff::from_lines(previous.as_str(), content)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .to_string();
+        // A diff of a heavily rewritten file is no cheaper than the file itself
+        if diff.len() * 2 > content.len() {
+            return FileView::Full;
+        }
+        FileView::Diff {
+            diff,
+            old_lines: previous.lines().count(),
+            new_lines: content.lines().count(),
+        }
+    }
+
+    /// Remember content the model wrote itself
+    pub fn record(&self, path: &Path, content: &str) {
+        if !self.enabled {
+            return;
+        }
+        if let Ok(mut seen) = self.seen.lock() {
+            seen.insert(path.to_path_buf(), content.to_string());
+        }
+    }
+
+    /// Follow an edit the model made, if what it last saw was still current when it made it.
+    /// Otherwise the next view shows the outside changes as well.
+    pub fn record_edit(&self, path: &Path, before: &str, after: &str) {
+        if let Ok(mut seen) = self.seen.lock() {
+            if let Some(content) = seen.get_mut(path) {
+                if content == before {
+                    *content = after.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// What the model is shown instead of the full file when viewing it again
+pub fn describe(path: &Path, view: &FileView) -> Option<String> {
+    match view {
+        FileView::Full => None,
+        FileView::Unchanged { total_lines } => Some(format!(
+            "{} is unchanged since you last viewed it ({} lines).",
+            path.display(),
+            total_lines
+        )),
+        FileView::Diff {
+            diff,
+            old_lines,
+            new_lines,
+        } => Some(format!(
+            "{} changed since you last viewed it ({} -> {} lines). Only the changes are shown; \
+            view it with view_range [1, -1] for the whole file.\n```diff\n{}```",
+            path.display(),
+            old_lines,
+            new_lines,
+            diff
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(count: usize) -> String {
+        (1..=count).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_second_view_shows_diff() {
+        let views = FileViews::new(true);
+        let path = Path::new("/repo/src/main.rs");
+        let original = numbered(100);
+        assert_eq!(views.view(path, &original), FileView::Full);
+        assert_eq!(
+            views.view(path, &original),
+            FileView::Unchanged { total_lines: 100 }
+        );
+
+        let changed = original.replace("line 50\n", "line fifty\n");
+        let FileView::Diff {
+            diff,
+            old_lines,
+            new_lines,
+        } = views.view(path, &changed)
+        else {
+            panic!("expected a diff");
+        };
+        assert_eq!((old_lines, new_lines), (100, 100));
+        assert!(diff.contains("-line 50\n+line fifty\n"));
+        assert!(!diff.contains("line 10\n"));
+    }
+
+    #[test]
+    fn test_rewritten_file_is_shown_in_full() {
+        let views = FileViews::new(true);
+        let path = Path::new("/repo/notes.md");
+        views.view(path, &numbered(20));
+        let rewritten: String = (1..=20).map(|i| format!("entry {}\n", i)).collect();
+        assert_eq!(views.view(path, &rewritten), FileView::Full);
+    }
+
+    #[test]
+    fn test_edits_follow_only_current_views() {
+        let views = FileViews::new(true);
+        let path = Path::new("/repo/lib.rs");
+        let original = numbered(50);
+        views.view(path, &original);
+
+        let edited = original.replace("line 3\n", "line three\n");
+        views.record_edit(path, &original, &edited);
+        assert_eq!(
+            views.view(path, &edited),
+            FileView::Unchanged { total_lines: 50 }
+        );
+
+        // An edit on top of an outside change keeps the outside change in the next diff
+        let outside = edited.replace("line 40\n", "line forty\n");
+        let both = outside.replace("line 4\n", "line four\n");
+        views.record_edit(path, &outside, &both);
+        assert!(matches!(views.view(path, &both), FileView::Diff { .. }));
+    }
+
+    #[test]
+    fn test_disabled_always_shows_full() {
+        let views = FileViews::new(false);
+        let path = Path::new("/repo/lib.rs");
+        views.view(path, "fn main() {}\n");
+        assert_eq!(views.view(path, "fn main() {}\n"), FileView::Full);
+    }
+}
@@ -1,5 +1,10 @@
+mod audit;
+pub mod changeset;
 mod editor_models;
+mod environment;
+mod file_views;
 mod lang;
+mod process;
 mod shell;
 
 use anyhow::Result;
@@ -38,7 +43,11 @@ use rmcp::model::{
 };
 use rmcp::object;
 
+use self::changeset::{Changeset, FileEdit};
 use self::editor_models::{create_editor_model, EditorModel};
+use self::environment::EnvSnapshot;
+use self::file_views::FileViews;
+use self::process::ProcessTree;
 use self::shell::{expand_path, get_shell_config, is_absolute_path, normalize_line_endings};
 use indoc::indoc;
 use std::process::Stdio;
@@ -115,6 +124,8 @@ pub struct DeveloperRouter {
     file_history: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
     ignore_patterns: Arc<Gitignore>,
     editor_model: Option<EditorModel>,
+    env_snapshot: Arc<EnvSnapshot>,
+    file_views: Arc<FileViews>,
 }
 
 impl Default for DeveloperRouter {
@@ -276,6 +287,57 @@ impl DeveloperRouter {
             }),
         );
 
+        let apply_changeset_tool = Tool::new(
+            "apply_changeset",
+            indoc! {r#"
+                Apply edits to several files as one change: either every edit is applied or none is.
+
+                Use this instead of a series of text_editor calls when a change spans files and would
+                leave the project broken half way, such as renaming something and its uses.
+
+                Edits apply in order, so a later edit of a file sees the earlier ones. Each has a `command`:
+                - `write`: create or overwrite `path` with `file_text`
+                - `str_replace`: replace `old_str`, which must appear exactly once, with `new_str`
+                - `delete`: remove `path`
+
+                Changed JSON files must still parse. If `validate_command` is given (e.g. `cargo check`
+                or `npm test`), it runs in a copy of the current directory with the edits applied, and
+                the edits are only applied if it succeeds. The user can review every changeset with
+                `goose diff`.
+            "#},
+            object!({
+                "type": "object",
+                "required": ["edits"],
+                "properties": {
+                    "description": {
+                        "type": "string",
+                        "description": "What the change does, shown when reviewing it"
+                    },
+                    "edits": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["command", "path"],
+                            "properties": {
+                                "command": {"type": "string", "enum": ["write", "str_replace", "delete"]},
+                                "path": {
+                                    "type": "string",
+                                    "description": "Absolute path to the file"
+                                },
+                                "file_text": {"type": "string"},
+                                "old_str": {"type": "string"},
+                                "new_str": {"type": "string"}
+                            }
+                        }
+                    },
+                    "validate_command": {
+                        "type": "string",
+                        "description": "Optional command that must succeed with the edits applied, run from a copy of the current directory"
+                    }
+                }
+            }),
+        );
+
         let list_windows_tool = Tool::new(
             "list_windows",
             indoc! {r#"
@@ -517,6 +579,7 @@ impl DeveloperRouter {
             tools: vec![
                 bash_tool,
                 text_editor_tool,
+                apply_changeset_tool,
                 list_windows_tool,
                 screen_capture_tool,
                 image_processor_tool,
@@ -526,6 +589,8 @@ impl DeveloperRouter {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model,
+            env_snapshot: Arc::new(EnvSnapshot::capture()),
+            file_views: Arc::new(FileViews::from_env()),
         }
     }
 
@@ -592,6 +657,32 @@ impl DeveloperRouter {
         }
     }
 
+    // Paths a tool call may change, for spotting dependency changes to audit
+    fn audited_paths(&self, tool_name: &str, arguments: &Value) -> Vec<PathBuf> {
+        let resolve = |value: &Value| value.as_str().and_then(|path| self.resolve_path(path).ok());
+        match tool_name {
+            "shell" => std::env::current_dir()
+                .map(|cwd| audit::manifests_in(&cwd))
+                .unwrap_or_default(),
+            "text_editor" => arguments
+                .get("path")
+                .and_then(resolve)
+                .into_iter()
+                .collect(),
+            "apply_changeset" => arguments
+                .get("edits")
+                .and_then(|edits| edits.as_array())
+                .map(|edits| {
+                    edits
+                        .iter()
+                        .filter_map(|edit| edit.get("path").and_then(resolve))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
     // Shell command execution with platform-specific handling
     async fn bash(
         &self,
@@ -627,19 +718,38 @@ impl DeveloperRouter {
             }
         }
 
+        // Secrets the user provided through goose for this command; they are never part of
+        // the conversation, so keep them out of the output too
+        let mut env_snapshot = (*self.env_snapshot).clone();
+        if let Some(secret_env) = params.get("secret_env").and_then(|v| v.as_object()) {
+            for (name, value) in secret_env {
+                if let Some(value) = value.as_str() {
+                    env_snapshot.add_secret(name, value);
+                }
+            }
+        }
+        let env_snapshot = Arc::new(env_snapshot);
+
         // Get platform-specific shell configuration
         let shell_config = get_shell_config();
 
-        // Execute the command using platform-specific shell
-        let mut child = Command::new(&shell_config.executable)
+        // Execute the command using platform-specific shell, in its own process group so
+        // cancelling the call stops everything it started
+        let mut shell_command = Command::new(&shell_config.executable);
+        shell_command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
             .kill_on_drop(true)
             .args(&shell_config.args)
-            .arg(command)
+            .arg(command);
+        env_snapshot.apply(&mut shell_command);
+        ProcessTree::configure(&mut shell_command);
+        let mut child = shell_command
             .spawn()
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        let mut process_tree =
+            ProcessTree::attach(&child).map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
@@ -647,6 +757,7 @@ impl DeveloperRouter {
         let mut stdout_reader = BufReader::new(stdout);
         let mut stderr_reader = BufReader::new(stderr);
 
+        let output_redactor = Arc::clone(&env_snapshot);
         let output_task = tokio::spawn(async move {
             let mut combined_output = String::new();
 
@@ -662,7 +773,7 @@ impl DeveloperRouter {
                         if n? == 0 {
                             stdout_done = true;
                         } else {
-                            let line = String::from_utf8_lossy(&stdout_buf);
+                            let line = output_redactor.redact(&String::from_utf8_lossy(&stdout_buf));
 
                             notifier.try_send(JsonRpcMessage::Notification(JsonRpcNotification {
                                 jsonrpc: JsonRpcVersion2_0,
@@ -689,7 +800,7 @@ impl DeveloperRouter {
                         if n? == 0 {
                             stderr_done = true;
                         } else {
-                            let line = String::from_utf8_lossy(&stderr_buf);
+                            let line = output_redactor.redact(&String::from_utf8_lossy(&stderr_buf));
 
                             notifier.try_send(JsonRpcMessage::Notification(JsonRpcNotification {
                                 jsonrpc: JsonRpcVersion2_0,
@@ -727,6 +838,7 @@ impl DeveloperRouter {
             .wait()
             .await
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        process_tree.disarm();
 
         let output_str = match output_task.await {
             Ok(result) => result.map_err(|e| ToolError::ExecutionError(e.to_string()))?,
@@ -976,6 +1088,19 @@ impl DeveloperRouter {
         let (start_idx, end_idx) = self.calculate_view_range(view_range, total_lines)?;
         let formatted = self.format_file_content(path, &lines, start_idx, end_idx, view_range);
 
+        // A file the model has already read is shown as the changes since then
+        if view_range.is_none() {
+            let view = self.file_views.view(path, &content);
+            if let Some(changes) = file_views::describe(path, &view) {
+                return Ok(vec![
+                    Content::text(changes).with_audience(vec![Role::Assistant]),
+                    Content::text(formatted)
+                        .with_audience(vec![Role::User])
+                        .with_priority(0.0),
+                ]);
+            }
+        }
+
         // The LLM gets just a quick update as we expect the file to view in the status
         // but we send a low priority message for the human
         Ok(vec![
@@ -1002,6 +1127,7 @@ impl DeveloperRouter {
         // Write to the file
         std::fs::write(path, &normalized_text) // Write the potentially modified text
             .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+        self.file_views.record(path, &normalized_text);
 
         // Try to detect the language from the file extension
         let language = lang::get_language_identifier(path);
@@ -1098,6 +1224,8 @@ impl DeveloperRouter {
         let normalized_content = normalize_line_endings(&new_content);
         std::fs::write(path, &normalized_content)
             .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+        self.file_views
+            .record_edit(path, &content, &normalized_content);
 
         // Try to detect the language from the file extension
         let language = lang::get_language_identifier(path);
@@ -1214,6 +1342,7 @@ impl DeveloperRouter {
 
         std::fs::write(path, &final_content)
             .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+        self.file_views.record_edit(path, &content, &final_content);
 
         // Try to detect the language from the file extension
         let language = lang::get_language_identifier(path);
@@ -1283,6 +1412,88 @@ impl DeveloperRouter {
         }
     }
 
+    async fn apply_changeset(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let mut edits: Vec<FileEdit> = params
+            .get("edits")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid 'edits': {}", e)))?
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'edits' parameter".into()))?;
+        let description = params
+            .get("description")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let validate_command = params.get("validate_command").and_then(|v| v.as_str());
+
+        for edit in &mut edits {
+            let path = self.resolve_path(&edit.path().to_string_lossy())?;
+            if self.is_ignored(&path) {
+                return Err(ToolError::ExecutionError(format!(
+                    "Access to '{}' is restricted by .gooseignore",
+                    path.display()
+                )));
+            }
+            match edit {
+                FileEdit::Write { path: p, .. }
+                | FileEdit::StrReplace { path: p, .. }
+                | FileEdit::Delete { path: p } => *p = path,
+            }
+        }
+
+        let changes = changeset::plan(&edits)
+            .map_err(|e| ToolError::ExecutionError(format!("{}. No files were changed.", e)))?;
+        let mut recorded = Changeset::new(description, changes);
+
+        let mut outcome = changeset::check_syntax(&recorded.changes);
+        if let (Ok(()), Some(command)) = (&outcome, validate_command) {
+            let cwd = std::env::current_dir().expect("should have a current working dir");
+            outcome = changeset::validate_in_shadow(&cwd, &recorded.changes, command).await;
+        }
+        if outcome.is_ok() {
+            for change in &recorded.changes {
+                self.save_file_history(&change.path)?;
+            }
+            outcome = changeset::apply(&recorded.changes);
+        }
+        if let Err(e) = &outcome {
+            recorded = recorded.rejected(e.clone());
+        }
+        if let Err(e) = recorded.save() {
+            tracing::warn!("Failed to record changeset {}: {}", recorded.id, e);
+        }
+
+        if let Err(e) = outcome {
+            return Err(ToolError::ExecutionError(format!(
+                "Changeset {} was not applied, no files were changed: {}",
+                recorded.id, e
+            )));
+        }
+        for change in &recorded.changes {
+            if let Some(after) = &change.after {
+                self.file_views.record(&change.path, after);
+            }
+        }
+
+        let files: Vec<String> = recorded
+            .changes
+            .iter()
+            .map(|change| change.path.display().to_string())
+            .collect();
+        Ok(vec![
+            Content::text(format!(
+                "Applied changeset {} to {} files:\n{}",
+                recorded.id,
+                files.len(),
+                files.join("\n")
+            ))
+            .with_audience(vec![Role::Assistant]),
+            Content::text(format!("```diff\n{}```\n", recorded.diff()))
+                .with_audience(vec![Role::User])
+                .with_priority(0.2),
+        ])
+    }
+
     fn save_file_history(&self, path: &PathBuf) -> Result<(), ToolError> {
         let mut history = self.file_history.lock().unwrap();
         let content = if path.exists() {
@@ -1541,14 +1752,23 @@ impl Router for DeveloperRouter {
         let this = self.clone();
         let tool_name = tool_name.to_string();
         Box::pin(async move {
-            match tool_name.as_str() {
+            let snapshot = audit::enabled()
+                .then(|| audit::ManifestSnapshot::take(this.audited_paths(&tool_name, &arguments)));
+            let mut result = match tool_name.as_str() {
                 "shell" => this.bash(arguments, notifier).await,
                 "text_editor" => this.text_editor(arguments).await,
+                "apply_changeset" => this.apply_changeset(arguments).await,
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
                 "image_processor" => this.image_processor(arguments).await,
                 _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
+            };
+            if let (Ok(content), Some(snapshot)) = (&mut result, snapshot) {
+                if let Some(report) = audit::audit(&snapshot.changed()).await {
+                    content.push(Content::text(report).with_audience(vec![Role::Assistant]));
+                }
             }
+            result
         })
     }
 
@@ -1605,6 +1825,8 @@ impl Clone for DeveloperRouter {
             file_history: Arc::clone(&self.file_history),
             ignore_patterns: Arc::clone(&self.ignore_patterns),
             editor_model: create_editor_model(),
+            env_snapshot: Arc::clone(&self.env_snapshot),
+            file_views: Arc::clone(&self.file_views),
         }
     }
 }
@@ -2071,6 +2293,8 @@ mod tests {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            env_snapshot: Arc::new(EnvSnapshot::capture()),
+            file_views: Arc::new(FileViews::from_env()),
         };
 
         // Test basic file matching
@@ -2122,6 +2346,8 @@ mod tests {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            env_snapshot: Arc::new(EnvSnapshot::capture()),
+            file_views: Arc::new(FileViews::from_env()),
         };
 
         // Try to write to an ignored file
@@ -2182,6 +2408,8 @@ mod tests {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            env_snapshot: Arc::new(EnvSnapshot::capture()),
+            file_views: Arc::new(FileViews::from_env()),
         };
 
         // Create an ignored file
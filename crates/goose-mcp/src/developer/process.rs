@@ -0,0 +1,262 @@
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+/// How long a cancelled command gets to exit after the polite signal before it is killed
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// How often to check whether a terminated process tree has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A shell command together with everything it spawned.
+///
+/// Each command runs in its own process group (a job object on Windows), so that cancelling
+/// the tool call — the user pressing Ctrl-C, or a timeout dropping the call — also stops
+/// grandchildren such as dev servers started by the command. Dropping an armed tree sends
+/// TERM (CTRL_BREAK on Windows) to the whole group and escalates to KILL if it is still
+/// running after a short grace period.
+///
+/// Commands that finish normally are disarmed, so processes they deliberately put in the
+/// background keep running.
+pub struct ProcessTree {
+    pid: u32,
+    #[cfg(windows)]
+    job: Option<windows::Job>,
+    armed: bool,
+}
+
+impl ProcessTree {
+    /// Set up a command to start in a new process group. Call before spawning.
+    pub fn configure(command: &mut Command) {
+        #[cfg(unix)]
+        command.process_group(0);
+        #[cfg(windows)]
+        command.creation_flags(windows::CREATE_NEW_PROCESS_GROUP);
+    }
+
+    /// Track a child spawned from a command prepared with [`ProcessTree::configure`]
+    pub fn attach(child: &Child) -> std::io::Result<Self> {
+        let pid = child
+            .id()
+            .ok_or_else(|| std::io::Error::other("process exited before it could be tracked"))?;
+
+        #[cfg(windows)]
+        let job = match child.raw_handle() {
+            Some(handle) => Some(windows::Job::assign(handle)?),
+            None => None,
+        };
+
+        Ok(Self {
+            pid,
+            #[cfg(windows)]
+            job,
+            armed: true,
+        })
+    }
+
+    /// The command finished on its own; leave anything it started in the background alone
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+
+    /// Stop the whole tree: TERM now, KILL after the grace period if anything is left.
+    /// Escalation happens on a background thread so this never blocks the caller.
+    pub fn terminate(&mut self) {
+        if !self.armed {
+            return;
+        }
+        self.armed = false;
+
+        #[cfg(unix)]
+        {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+
+            let pgid = Pid::from_raw(self.pid as i32);
+            if killpg(pgid, Signal::SIGTERM).is_err() {
+                // The group is already gone
+                return;
+            }
+            std::thread::spawn(move || {
+                let deadline = std::time::Instant::now() + TERMINATE_GRACE_PERIOD;
+                while std::time::Instant::now() < deadline {
+                    if killpg(pgid, None).is_err() {
+                        return;
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                tracing::debug!("Process group {} ignored SIGTERM, sending SIGKILL", pgid);
+                let _ = killpg(pgid, Signal::SIGKILL);
+            });
+        }
+
+        #[cfg(windows)]
+        {
+            windows::send_ctrl_break(self.pid);
+            if let Some(job) = self.job.take() {
+                std::thread::spawn(move || {
+                    let deadline = std::time::Instant::now() + TERMINATE_GRACE_PERIOD;
+                    while std::time::Instant::now() < deadline {
+                        if job.is_empty() {
+                            return;
+                        }
+                        std::thread::sleep(POLL_INTERVAL);
+                    }
+                    job.terminate();
+                });
+            }
+        }
+    }
+}
+
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::os::windows::io::RawHandle;
+    use std::ptr;
+    use winapi::shared::minwindef::{DWORD, FALSE};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::jobapi2::{
+        AssignProcessToJobObject, CreateJobObjectW, QueryInformationJobObject, TerminateJobObject,
+    };
+    use winapi::um::wincon::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+    use winapi::um::winnt::{
+        JobObjectBasicAccountingInformation, HANDLE, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION,
+    };
+
+    pub const CREATE_NEW_PROCESS_GROUP: u32 = winapi::um::winbase::CREATE_NEW_PROCESS_GROUP;
+
+    /// A job object holding a command and every process it starts
+    pub struct Job(HANDLE);
+
+    // The handle is only used through thread-safe Win32 calls
+    unsafe impl Send for Job {}
+
+    impl Job {
+        pub fn assign(process: RawHandle) -> std::io::Result<Self> {
+            unsafe {
+                let handle = CreateJobObjectW(ptr::null_mut(), ptr::null());
+                if handle.is_null() {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let job = Job(handle);
+                if AssignProcessToJobObject(job.0, process as HANDLE) == FALSE {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(job)
+            }
+        }
+
+        /// Whether every process in the job has exited
+        pub fn is_empty(&self) -> bool {
+            unsafe {
+                let mut info: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION = std::mem::zeroed();
+                let ok = QueryInformationJobObject(
+                    self.0,
+                    JobObjectBasicAccountingInformation,
+                    &mut info as *mut _ as *mut _,
+                    std::mem::size_of::<JOBOBJECT_BASIC_ACCOUNTING_INFORMATION>() as DWORD,
+                    ptr::null_mut(),
+                );
+                ok != FALSE && info.ActiveProcesses == 0
+            }
+        }
+
+        pub fn terminate(&self) {
+            tracing::debug!("Job ignored CTRL_BREAK, terminating it");
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    /// Ask a console process group to exit, the Windows counterpart of SIGTERM
+    pub fn send_ctrl_break(pid: u32) {
+        unsafe {
+            GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    fn is_running(pid: i32) -> bool {
+        kill(Pid::from_raw(pid), None).is_ok()
+    }
+
+    async fn spawn_with_grandchild(script: &str) -> (Child, ProcessTree, i32) {
+        let mut command = Command::new("bash");
+        command
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        ProcessTree::configure(&mut command);
+        let mut child = command.spawn().unwrap();
+        let tree = ProcessTree::attach(&child).unwrap();
+
+        let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        let grandchild = lines.next_line().await.unwrap().unwrap().parse().unwrap();
+        (child, tree, grandchild)
+    }
+
+    async fn wait_for_exit(pid: i32) -> bool {
+        for _ in 0..100 {
+            if !is_running(pid) {
+                return true;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_drop_kills_grandchildren() {
+        let (child, tree, grandchild) = spawn_with_grandchild("sleep 30 & echo $!; wait").await;
+        assert!(is_running(grandchild));
+
+        drop(tree);
+        drop(child);
+        assert!(wait_for_exit(grandchild).await);
+    }
+
+    #[tokio::test]
+    async fn test_escalates_to_kill() {
+        let (child, tree, grandchild) =
+            spawn_with_grandchild("bash -c 'trap \"\" TERM; sleep 30' & echo $!; wait").await;
+
+        drop(tree);
+        drop(child);
+        assert!(wait_for_exit(grandchild).await);
+    }
+
+    #[tokio::test]
+    async fn test_disarmed_tree_leaves_background_processes() {
+        let (mut child, mut tree, grandchild) = spawn_with_grandchild("sleep 30 & echo $!").await;
+        child.wait().await.unwrap();
+        tree.disarm();
+        drop(tree);
+
+        tokio::time::sleep(POLL_INTERVAL * 2).await;
+        assert!(is_running(grandchild));
+        let _ = kill(Pid::from_raw(grandchild), nix::sys::signal::Signal::SIGKILL);
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_imap::Session;
+use futures::TryStreamExt;
+use mail_parser::{Address, MessageParser, MimeHeaders};
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use super::{Auth, EmailConfig};
+
+/// Longest message body returned, in characters
+const MAX_BODY_CHARS: usize = 20_000;
+
+/// What a mailbox search looks for; unset fields don't narrow it
+#[derive(Debug, Default)]
+pub struct SearchCriteria {
+    pub unread: bool,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub text: Option<String>,
+    /// `YYYY-MM-DD`
+    pub since: Option<String>,
+}
+
+impl SearchCriteria {
+    /// The criteria as an IMAP `SEARCH` query
+    pub fn to_query(&self) -> Result<String> {
+        let mut terms = Vec::new();
+        if self.unread {
+            terms.push("UNSEEN".to_string());
+        }
+        if let Some(from) = &self.from {
+            terms.push(format!("FROM {}", quote(from)));
+        }
+        if let Some(subject) = &self.subject {
+            terms.push(format!("SUBJECT {}", quote(subject)));
+        }
+        if let Some(text) = &self.text {
+            terms.push(format!("TEXT {}", quote(text)));
+        }
+        if let Some(since) = &self.since {
+            let date = chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d")
+                .with_context(|| format!("'{}' is not a YYYY-MM-DD date", since))?;
+            terms.push(format!("SINCE {}", date.format("%-d-%b-%Y")));
+        }
+        if terms.is_empty() {
+            terms.push("ALL".to_string());
+        }
+        Ok(terms.join(" "))
+    }
+}
+
+/// An IMAP quoted string
+fn quote(value: &str) -> String {
+    let escaped: String = value
+        .chars()
+        .filter(|c| *c != '\r' && *c != '\n')
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect();
+    format!("\"{}\"", escaped)
+}
+
+/// One message in a search result
+#[derive(Debug, Serialize)]
+pub struct MessageSummary {
+    pub uid: u32,
+    pub from: String,
+    pub subject: String,
+    pub date: String,
+    pub unread: bool,
+}
+
+/// A message as read
+#[derive(Debug, Serialize)]
+pub struct MessageDetail {
+    pub uid: u32,
+    pub message_id: Option<String>,
+    pub from: String,
+    pub to: String,
+    pub cc: String,
+    pub subject: String,
+    pub date: String,
+    pub body: String,
+    pub attachments: Vec<String>,
+}
+
+pub(super) fn format_addresses(address: Option<&Address>) -> String {
+    address
+        .map(|address| {
+            address
+                .iter()
+                .map(|addr| match (addr.name(), addr.address()) {
+                    (Some(name), Some(email)) => format!("{} <{}>", name, email),
+                    (None, Some(email)) => email.to_string(),
+                    (Some(name), None) => name.to_string(),
+                    (None, None) => String::new(),
+                })
+                .filter(|addr| !addr.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default()
+}
+
+struct XOAuth2 {
+    user: String,
+    token: String,
+}
+
+impl async_imap::Authenticator for &XOAuth2 {
+    type Response = String;
+
+    fn process(&mut self, _challenge: &[u8]) -> Self::Response {
+        format!("user={}\x01auth=Bearer {}\x01\x01", self.user, self.token)
+    }
+}
+
+/// A logged-in IMAP connection
+pub struct Mailbox {
+    session: Session<TlsStream<TcpStream>>,
+}
+
+impl Mailbox {
+    pub async fn connect(config: &EmailConfig) -> Result<Self> {
+        let host = config
+            .imap_host
+            .as_deref()
+            .ok_or_else(|| anyhow!("EMAIL_IMAP_HOST is not set"))?;
+
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_native_certs::load_native_certs().certs {
+            let _ = roots.add(cert);
+        }
+        let tls = TlsConnector::from(Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        ));
+        let server_name = ServerName::try_from(host.to_string())
+            .with_context(|| format!("'{}' is not a valid host name", host))?;
+        let tcp = TcpStream::connect((host, config.imap_port))
+            .await
+            .with_context(|| format!("Failed to connect to {}:{}", host, config.imap_port))?;
+        let stream = tls.connect(server_name, tcp).await?;
+
+        let mut client = async_imap::Client::new(stream);
+        client
+            .read_response()
+            .await
+            .ok_or_else(|| anyhow!("IMAP server closed the connection"))??;
+        let session = match &config.auth {
+            Auth::Password(password) => client
+                .login(&config.username, password)
+                .await
+                .map_err(|(e, _)| anyhow!("IMAP login failed: {}", e))?,
+            Auth::OAuth(token) => {
+                let auth = XOAuth2 {
+                    user: config.username.clone(),
+                    token: token.clone(),
+                };
+                client
+                    .authenticate("XOAUTH2", &auth)
+                    .await
+                    .map_err(|(e, _)| anyhow!("IMAP authentication failed: {}", e))?
+            }
+        };
+        Ok(Self { session })
+    }
+
+    pub async fn folders(&mut self) -> Result<Vec<String>> {
+        let names: Vec<_> = self
+            .session
+            .list(None, Some("*"))
+            .await?
+            .try_collect()
+            .await?;
+        Ok(names.iter().map(|name| name.name().to_string()).collect())
+    }
+
+    /// The newest messages in a folder matching the criteria, newest first
+    pub async fn search(
+        &mut self,
+        folder: &str,
+        criteria: &SearchCriteria,
+        limit: usize,
+    ) -> Result<Vec<MessageSummary>> {
+        self.session.examine(folder).await?;
+        let mut uids: Vec<u32> = self
+            .session
+            .uid_search(criteria.to_query()?)
+            .await?
+            .into_iter()
+            .collect();
+        uids.sort_unstable_by(|a, b| b.cmp(a));
+        uids.truncate(limit);
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let set = uids
+            .iter()
+            .map(|uid| uid.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let fetches: Vec<_> = self
+            .session
+            .uid_fetch(set, "(UID FLAGS BODY.PEEK[HEADER])")
+            .await?
+            .try_collect()
+            .await?;
+
+        let parser = MessageParser::default();
+        let mut summaries: Vec<MessageSummary> = fetches
+            .iter()
+            .filter_map(|fetch| {
+                let uid = fetch.uid?;
+                let headers = parser.parse_headers(fetch.header()?)?;
+                Some(MessageSummary {
+                    uid,
+                    from: format_addresses(headers.from()),
+                    subject: headers.subject().unwrap_or_default().to_string(),
+                    date: headers.date().map(|d| d.to_rfc3339()).unwrap_or_default(),
+                    unread: !fetch
+                        .flags()
+                        .any(|flag| flag == async_imap::types::Flag::Seen),
+                })
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.uid.cmp(&a.uid));
+        Ok(summaries)
+    }
+
+    /// Read one message, marking it read only when asked to
+    pub async fn read(&mut self, folder: &str, uid: u32, mark_read: bool) -> Result<MessageDetail> {
+        if mark_read {
+            self.session.select(folder).await?;
+        } else {
+            self.session.examine(folder).await?;
+        }
+        let query = if mark_read {
+            "(UID BODY[])"
+        } else {
+            "(UID BODY.PEEK[])"
+        };
+        let fetches: Vec<_> = self
+            .session
+            .uid_fetch(uid.to_string(), query)
+            .await?
+            .try_collect()
+            .await?;
+        let raw = fetches
+            .iter()
+            .find_map(|fetch| fetch.body())
+            .ok_or_else(|| anyhow!("No message with UID {} in {}", uid, folder))?;
+        let message = MessageParser::default()
+            .parse(raw)
+            .ok_or_else(|| anyhow!("Message {} could not be parsed", uid))?;
+
+        let mut body: String = message.body_text(0).unwrap_or_default().into_owned();
+        if body.chars().count() > MAX_BODY_CHARS {
+            body = body.chars().take(MAX_BODY_CHARS).collect();
+            body.push_str("\n[truncated]");
+        }
+        Ok(MessageDetail {
+            uid,
+            message_id: message.message_id().map(String::from),
+            from: format_addresses(message.from()),
+            to: format_addresses(message.to()),
+            cc: format_addresses(message.cc()),
+            subject: message.subject().unwrap_or_default().to_string(),
+            date: message.date().map(|d| d.to_rfc3339()).unwrap_or_default(),
+            body,
+            attachments: message
+                .attachments()
+                .map(|part| part.attachment_name().unwrap_or("(unnamed)").to_string())
+                .collect(),
+        })
+    }
+
+    /// Store a composed message in a folder as a draft
+    pub async fn save_draft(&mut self, folder: &str, message: &[u8]) -> Result<()> {
+        self.session
+            .append(folder, Some("(\\Draft \\Seen)"), None, message)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn logout(mut self) {
+        let _ = self.session.logout().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_criteria_build_imap_queries() {
+        assert_eq!(SearchCriteria::default().to_query().unwrap(), "ALL");
+
+        let criteria = SearchCriteria {
+            unread: true,
+            from: Some("alerts@example.com".to_string()),
+            subject: Some("disk \"full\"".to_string()),
+            text: None,
+            since: Some("2024-03-05".to_string()),
+        };
+        assert_eq!(
+            criteria.to_query().unwrap(),
+            r#"UNSEEN FROM "alerts@example.com" SUBJECT "disk \"full\"" SINCE 5-Mar-2024"#
+        );
+
+        let criteria = SearchCriteria {
+            since: Some("last week".to_string()),
+            ..Default::default()
+        };
+        assert!(criteria.to_query().is_err());
+    }
+}
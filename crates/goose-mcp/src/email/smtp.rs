@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Context, Result};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox as Address;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::Value;
+
+use super::{Auth, EmailConfig};
+
+/// Port where SMTP speaks TLS from the start; other ports upgrade with STARTTLS
+const IMPLICIT_TLS_PORT: u16 = 465;
+
+/// A message to be drafted or sent
+#[derive(Debug)]
+pub struct Draft {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    /// Message-ID of the message this answers
+    pub in_reply_to: Option<String>,
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Draft {
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let to = string_list(&args["to"]);
+        if to.is_empty() {
+            return Err(anyhow!("At least one recipient is required in 'to'"));
+        }
+        Ok(Self {
+            to,
+            cc: string_list(&args["cc"]),
+            subject: args["subject"]
+                .as_str()
+                .ok_or_else(|| anyhow!("'subject' is required"))?
+                .to_string(),
+            body: args["body"]
+                .as_str()
+                .ok_or_else(|| anyhow!("'body' is required"))?
+                .to_string(),
+            in_reply_to: args["in_reply_to"].as_str().map(String::from),
+        })
+    }
+
+    pub fn build(&self, from: &str) -> Result<Message> {
+        let parse = |address: &str| -> Result<Address> {
+            address
+                .parse()
+                .with_context(|| format!("'{}' is not a valid email address", address))
+        };
+        let mut builder = Message::builder()
+            .from(parse(from)?)
+            .subject(&self.subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(parse(to)?);
+        }
+        for cc in &self.cc {
+            builder = builder.cc(parse(cc)?);
+        }
+        if let Some(in_reply_to) = &self.in_reply_to {
+            builder = builder
+                .in_reply_to(in_reply_to.clone())
+                .references(in_reply_to.clone());
+        }
+        Ok(builder.body(self.body.clone())?)
+    }
+
+    /// The message as the user should see it before it goes anywhere
+    pub fn preview(&self, from: &str) -> String {
+        let mut preview = format!("From: {}\nTo: {}\n", from, self.to.join(", "));
+        if !self.cc.is_empty() {
+            preview.push_str(&format!("Cc: {}\n", self.cc.join(", ")));
+        }
+        preview.push_str(&format!("Subject: {}\n\n{}", self.subject, self.body));
+        preview
+    }
+}
+
+pub async fn send(config: &EmailConfig, message: Message) -> Result<()> {
+    let host = config
+        .smtp_host
+        .as_deref()
+        .ok_or_else(|| anyhow!("EMAIL_SMTP_HOST is not set"))?;
+    let builder = if config.smtp_port == IMPLICIT_TLS_PORT {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+    };
+    let (secret, mechanisms) = match &config.auth {
+        Auth::Password(password) => (password, vec![Mechanism::Plain, Mechanism::Login]),
+        Auth::OAuth(token) => (token, vec![Mechanism::Xoauth2]),
+    };
+    builder
+        .port(config.smtp_port)
+        .credentials(Credentials::new(config.username.clone(), secret.clone()))
+        .authentication(mechanisms)
+        .build()
+        .send(message)
+        .await
+        .context("SMTP server did not accept the message")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_draft_builds_a_reply() {
+        let draft = Draft::from_args(&json!({
+            "to": "ops@example.com, Dana <dana@example.com>",
+            "subject": "Re: Disk alert",
+            "body": "Cleaned up /var/log.",
+            "in_reply_to": "<alert-1@example.com>"
+        }))
+        .unwrap();
+        assert_eq!(draft.to.len(), 2);
+
+        let message =
+            String::from_utf8(draft.build("goose@example.com").unwrap().formatted()).unwrap();
+        assert!(message.contains("Subject: Re: Disk alert"));
+        assert!(message.contains("In-Reply-To: <alert-1@example.com>"));
+        assert!(message.contains("Cleaned up /var/log."));
+
+        assert!(Draft::from_args(&json!({"subject": "Hi", "body": "x"})).is_err());
+        assert!(
+            Draft::from_args(&json!({"to": "not an address", "subject": "Hi", "body": "x"}))
+                .unwrap()
+                .build("goose@example.com")
+                .is_err()
+        );
+    }
+}
@@ -1,5 +1,5 @@
 mod google_labels;
-mod oauth_pkce;
+pub(crate) mod oauth_pkce;
 pub mod storage;
 
 use anyhow::{Context, Error};
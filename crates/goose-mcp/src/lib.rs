@@ -7,14 +7,21 @@ pub static APP_STRATEGY: Lazy<AppStrategyArgs> = Lazy::new(|| AppStrategyArgs {
     app_name: "goose".to_string(),
 });
 
+mod calendar;
 pub mod computercontroller;
 mod developer;
+#[cfg(feature = "email")]
+mod email;
 pub mod google_drive;
 mod memory;
 mod tutorial;
 
+pub use calendar::CalendarRouter;
 pub use computercontroller::ComputerControllerRouter;
+pub use developer::changeset;
 pub use developer::DeveloperRouter;
+#[cfg(feature = "email")]
+pub use email::EmailRouter;
 pub use google_drive::GoogleDriveRouter;
 pub use memory::MemoryRouter;
 pub use tutorial::TutorialRouter;
@@ -0,0 +1,102 @@
+//! Memories kept in goose's SQLite database.
+//!
+//! Used when goose stores its sessions in SQLite. Each category is one row holding the same text
+//! its file would, keyed by scope: `global`, or the local memory directory for project memories.
+//! A connection is opened per operation, so the extension never holds one between tool calls.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long a write waits for another process to release the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CREATE_MEMORIES: &str = "
+    CREATE TABLE IF NOT EXISTS memories (
+        scope TEXT NOT NULL,
+        category TEXT NOT NULL,
+        content TEXT NOT NULL,
+        PRIMARY KEY (scope, category)
+    );
+";
+
+fn to_io(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+#[derive(Clone)]
+pub struct MemoryDatabase {
+    path: PathBuf,
+}
+
+impl MemoryDatabase {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn connect(&self) -> io::Result<Connection> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(&self.path).map_err(to_io)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(to_io)?;
+        conn.execute_batch(CREATE_MEMORIES).map_err(to_io)?;
+        Ok(conn)
+    }
+
+    /// The stored text of a category, or `None` if the database doesn't have it
+    pub fn read(&self, scope: &str, category: &str) -> io::Result<Option<String>> {
+        self.connect()?
+            .query_row(
+                "SELECT content FROM memories WHERE scope = ?1 AND category = ?2",
+                params![scope, category],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(to_io)
+    }
+
+    /// Replace the text of a category
+    pub fn write(&self, scope: &str, category: &str, content: &str) -> io::Result<()> {
+        self.connect()?
+            .execute(
+                "INSERT INTO memories (scope, category, content) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(scope, category) DO UPDATE SET content = excluded.content",
+                params![scope, category, content],
+            )
+            .map_err(to_io)?;
+        Ok(())
+    }
+
+    /// Every category stored under `scope`
+    pub fn categories(&self, scope: &str) -> io::Result<Vec<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn
+            .prepare("SELECT category FROM memories WHERE scope = ?1 ORDER BY category")
+            .map_err(to_io)?;
+        let categories = stmt
+            .query_map(params![scope], |row| row.get(0))
+            .map_err(to_io)?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .map_err(to_io)?;
+        Ok(categories)
+    }
+
+    pub fn delete(&self, scope: &str, category: &str) -> io::Result<()> {
+        self.connect()?
+            .execute(
+                "DELETE FROM memories WHERE scope = ?1 AND category = ?2",
+                params![scope, category],
+            )
+            .map_err(to_io)?;
+        Ok(())
+    }
+
+    pub fn delete_scope(&self, scope: &str) -> io::Result<()> {
+        self.connect()?
+            .execute("DELETE FROM memories WHERE scope = ?1", params![scope])
+            .map_err(to_io)?;
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+mod database;
+
 use async_trait::async_trait;
 use etcetera::{choose_app_strategy, AppStrategy};
 use indoc::formatdoc;
@@ -11,16 +13,11 @@ use mcp_server::Router;
 use rmcp::model::{Content, JsonRpcMessage, Prompt, Resource, Tool, ToolAnnotations};
 use rmcp::object;
 use serde_json::Value;
-use std::{
-    collections::HashMap,
-    fs,
-    future::Future,
-    io::{self, Read, Write},
-    path::PathBuf,
-    pin::Pin,
-};
+use std::{collections::HashMap, fs, future::Future, io, path::PathBuf, pin::Pin};
 use tokio::sync::mpsc;
 
+use database::MemoryDatabase;
+
 // MemoryRouter implementation
 #[derive(Clone)]
 pub struct MemoryRouter {
@@ -28,6 +25,8 @@ pub struct MemoryRouter {
     instructions: String,
     global_memory_dir: PathBuf,
     local_memory_dir: PathBuf,
+    /// When set, memories are kept in goose's SQLite database instead of category files
+    database: Option<MemoryDatabase>,
 }
 
 impl Default for MemoryRouter {
@@ -38,6 +37,16 @@ impl Default for MemoryRouter {
 
 impl MemoryRouter {
     pub fn new() -> Self {
+        Self::create(None)
+    }
+
+    /// A router that keeps memories in the SQLite database at `path`. Categories that only
+    /// exist as files are still read, and move into the database the next time they change.
+    pub fn with_database(path: PathBuf) -> Self {
+        Self::create(Some(MemoryDatabase::new(path)))
+    }
+
+    fn create(database: Option<MemoryDatabase>) -> Self {
         let remember_memory = Tool::new(
             "remember_memory",
             "Stores a memory with optional tags in a specified category",
@@ -249,6 +258,7 @@ impl MemoryRouter {
             instructions: instructions.clone(),
             global_memory_dir,
             local_memory_dir,
+            database,
         };
 
         let retrieved_global_memories = memory_router.retrieve_all(true);
@@ -305,36 +315,83 @@ impl MemoryRouter {
         &self.instructions
     }
 
-    fn get_memory_file(&self, category: &str, is_global: bool) -> PathBuf {
-        // Defaults to local memory if no is_global flag is provided
-        let base_dir = if is_global {
+    fn memory_dir(&self, is_global: bool) -> &PathBuf {
+        if is_global {
             &self.global_memory_dir
         } else {
             &self.local_memory_dir
-        };
-        base_dir.join(format!("{}.txt", category))
+        }
     }
 
-    pub fn retrieve_all(&self, is_global: bool) -> io::Result<HashMap<String, Vec<String>>> {
-        let base_dir = if is_global {
-            &self.global_memory_dir
+    fn get_memory_file(&self, category: &str, is_global: bool) -> PathBuf {
+        // Defaults to local memory if no is_global flag is provided
+        self.memory_dir(is_global).join(format!("{}.txt", category))
+    }
+
+    /// The database scope of global or local memories; local memories belong to their project
+    fn scope(&self, is_global: bool) -> String {
+        if is_global {
+            "global".to_string()
         } else {
-            &self.local_memory_dir
-        };
-        let mut memories = HashMap::new();
+            self.local_memory_dir.to_string_lossy().to_string()
+        }
+    }
+
+    fn read_category(&self, category: &str, is_global: bool) -> io::Result<Option<String>> {
+        if let Some(database) = &self.database {
+            if let Some(content) = database.read(&self.scope(is_global), category)? {
+                return Ok(Some(content));
+            }
+        }
+        let memory_file_path = self.get_memory_file(category, is_global);
+        if !memory_file_path.exists() {
+            return Ok(None);
+        }
+        fs::read_to_string(memory_file_path).map(Some)
+    }
+
+    fn write_category(&self, category: &str, content: &str, is_global: bool) -> io::Result<()> {
+        let memory_file_path = self.get_memory_file(category, is_global);
+        if let Some(database) = &self.database {
+            database.write(&self.scope(is_global), category, content)?;
+            if memory_file_path.exists() {
+                fs::remove_file(memory_file_path)?;
+            }
+            return Ok(());
+        }
+
+        if let Some(parent) = memory_file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(memory_file_path, content)
+    }
+
+    pub fn retrieve_all(&self, is_global: bool) -> io::Result<HashMap<String, Vec<String>>> {
+        let base_dir = self.memory_dir(is_global);
+        let mut categories = Vec::new();
+        if let Some(database) = &self.database {
+            categories.extend(database.categories(&self.scope(is_global))?);
+        }
         if base_dir.exists() {
             for entry in fs::read_dir(base_dir)? {
                 let entry = entry?;
                 if entry.file_type()?.is_file() {
-                    let category = entry.file_name().to_string_lossy().replace(".txt", "");
-                    let category_memories = self.retrieve(&category, is_global)?;
-                    memories.insert(
-                        category,
-                        category_memories.into_iter().flat_map(|(_, v)| v).collect(),
-                    );
+                    categories.push(entry.file_name().to_string_lossy().replace(".txt", ""));
                 }
             }
         }
+
+        let mut memories = HashMap::new();
+        for category in categories {
+            if memories.contains_key(&category) {
+                continue;
+            }
+            let category_memories = self.retrieve(&category, is_global)?;
+            memories.insert(
+                category,
+                category_memories.into_iter().flat_map(|(_, v)| v).collect(),
+            );
+        }
         Ok(memories)
     }
 
@@ -346,22 +403,13 @@ impl MemoryRouter {
         tags: &[&str],
         is_global: bool,
     ) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-
-        if let Some(parent) = memory_file_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-
-        let mut file = fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&memory_file_path)?;
+        let mut content = self.read_category(category, is_global)?.unwrap_or_default();
         if !tags.is_empty() {
-            writeln!(file, "# {}", tags.join(" "))?;
+            content.push_str(&format!("# {}\n", tags.join(" ")));
         }
-        writeln!(file, "{}\n", data)?;
+        content.push_str(&format!("{}\n\n", data));
 
-        Ok(())
+        self.write_category(category, &content, is_global)
     }
 
     pub fn retrieve(
@@ -369,14 +417,9 @@ impl MemoryRouter {
         category: &str,
         is_global: bool,
     ) -> io::Result<HashMap<String, Vec<String>>> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if !memory_file_path.exists() {
+        let Some(content) = self.read_category(category, is_global)? else {
             return Ok(HashMap::new());
-        }
-
-        let mut file = fs::File::open(memory_file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
+        };
 
         let mut memories = HashMap::new();
         for entry in content.split("\n\n") {
@@ -409,14 +452,9 @@ impl MemoryRouter {
         memory_content: &str,
         is_global: bool,
     ) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if !memory_file_path.exists() {
+        let Some(content) = self.read_category(category, is_global)? else {
             return Ok(());
-        }
-
-        let mut file = fs::File::open(&memory_file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
+        };
 
         let memories: Vec<&str> = content.split("\n\n").collect();
         let new_content: Vec<String> = memories
@@ -425,12 +463,13 @@ impl MemoryRouter {
             .map(|s| s.to_string())
             .collect();
 
-        fs::write(memory_file_path, new_content.join("\n\n"))?;
-
-        Ok(())
+        self.write_category(category, &new_content.join("\n\n"), is_global)
     }
 
     pub fn clear_memory(&self, category: &str, is_global: bool) -> io::Result<()> {
+        if let Some(database) = &self.database {
+            database.delete(&self.scope(is_global), category)?;
+        }
         let memory_file_path = self.get_memory_file(category, is_global);
         if memory_file_path.exists() {
             fs::remove_file(memory_file_path)?;
@@ -440,11 +479,10 @@ impl MemoryRouter {
     }
 
     pub fn clear_all_global_or_local_memories(&self, is_global: bool) -> io::Result<()> {
-        let base_dir = if is_global {
-            &self.global_memory_dir
-        } else {
-            &self.local_memory_dir
-        };
+        if let Some(database) = &self.database {
+            database.delete_scope(&self.scope(is_global))?;
+        }
+        let base_dir = self.memory_dir(is_global);
         if base_dir.exists() {
             fs::remove_dir_all(base_dir)?;
         }
@@ -634,6 +672,7 @@ mod tests {
             instructions: String::new(),
             global_memory_dir: memory_base.join("global"),
             local_memory_dir: memory_base.join("local"),
+            database: None,
         };
 
         assert!(!router.global_memory_dir.exists());
@@ -675,6 +714,7 @@ mod tests {
             instructions: String::new(),
             global_memory_dir: memory_base.join("global"),
             local_memory_dir: memory_base.join("local"),
+            database: None,
         };
 
         assert!(router.clear_all_global_or_local_memories(false).is_ok());
@@ -691,6 +731,7 @@ mod tests {
             instructions: String::new(),
             global_memory_dir: memory_base.join("global"),
             local_memory_dir: memory_base.join("local"),
+            database: None,
         };
 
         router
@@ -728,6 +769,7 @@ mod tests {
             instructions: String::new(),
             global_memory_dir: memory_base.join("global"),
             local_memory_dir: memory_base.join("local"),
+            database: None,
         };
 
         assert!(!router.local_memory_dir.exists());
@@ -750,6 +792,7 @@ mod tests {
             instructions: String::new(),
             global_memory_dir: memory_base.join("global"),
             local_memory_dir: memory_base.join("local"),
+            database: None,
         };
 
         router
@@ -777,4 +820,43 @@ mod tests {
             .any(|v| v.iter().any(|content| content.contains("keep_this")));
         assert!(has_kept);
     }
+
+    #[test]
+    fn test_database_storage_moves_category_files() {
+        let temp_dir = tempdir().unwrap();
+        let memory_base = temp_dir.path().join("database_test");
+
+        let router = MemoryRouter {
+            tools: vec![],
+            instructions: String::new(),
+            global_memory_dir: memory_base.join("global"),
+            local_memory_dir: memory_base.join("local"),
+            database: Some(MemoryDatabase::new(temp_dir.path().join("goose.db"))),
+        };
+
+        // A category saved as a file before the database was in use
+        fs::create_dir_all(&router.local_memory_dir).unwrap();
+        let legacy_file = router.local_memory_dir.join("category.txt");
+        fs::write(&legacy_file, "# old\nfrom_file\n\n").unwrap();
+        assert_eq!(router.retrieve_all(false).unwrap().len(), 1);
+
+        router
+            .remember("context", "category", "from_database", &["new"], false)
+            .unwrap();
+        assert!(!legacy_file.exists());
+
+        let memories = router.retrieve("category", false).unwrap();
+        assert_eq!(memories["old"], vec!["from_file"]);
+        assert_eq!(memories["new"], vec!["from_database"]);
+
+        router
+            .remember("context", "global_category", "global_data", &[], true)
+            .unwrap();
+        assert!(!router.global_memory_dir.exists());
+        assert_eq!(router.retrieve_all(true).unwrap().len(), 1);
+
+        router.clear_all_global_or_local_memories(false).unwrap();
+        assert!(router.retrieve_all(false).unwrap().is_empty());
+        assert_eq!(router.retrieve_all(true).unwrap().len(), 1);
+    }
 }
@@ -6,7 +6,10 @@ use goose::config::ExtensionEntry;
 use goose::permission::permission_confirmation::PrincipalType;
 use goose::providers::base::{ConfigKey, ModelInfo, ProviderMetadata};
 use goose::session::info::SessionInfo;
-use goose::session::SessionMetadata;
+use goose::session::report::ModelSpend;
+use goose::session::{
+    ContextSnapshot, ModelUsage, SessionCost, SessionMetadata, UsageEntry, UsageLedger, UsageTotals,
+};
 use rmcp::model::{
     Annotations, Content, EmbeddedResource, ImageContent, RawEmbeddedResource, RawImageContent,
     RawTextContent, ResourceContents, Role, TextContent, Tool, ToolAnnotations,
@@ -14,9 +17,11 @@ use rmcp::model::{
 use utoipa::{OpenApi, ToSchema};
 
 use goose::conversation::message::{
-    ContextLengthExceeded, FrontendToolRequest, Message, MessageContent, RedactedThinkingContent,
-    SummarizationRequested, ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
+    Citation, ContextLengthExceeded, FrontendToolRequest, Message, MessageContent,
+    RedactedThinkingContent, StopReason, SummarizationRequested, ThinkingContent, TokenLogprob,
+    ToolConfirmationRequest, ToolRequest, ToolResponse, TopLogprob,
 };
+use goose::conversation::origin::{Origin, TrustLevel};
 use utoipa::openapi::schema::{
     AdditionalProperties, AnyOfBuilder, ArrayBuilder, ObjectBuilder, OneOfBuilder, Schema,
     SchemaFormat, SchemaType,
@@ -370,9 +375,14 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         super::routes::agent::get_tools,
         super::routes::agent::add_sub_recipes,
         super::routes::reply::confirm_permission,
+        super::routes::reply::provide_credential,
         super::routes::context::manage_context,
+        super::routes::editor::update_buffer,
+        super::routes::editor::update_selection,
+        super::routes::editor::submit_edit_result,
         super::routes::session::list_sessions,
         super::routes::session::get_session_history,
+        super::routes::session::get_session_cost,
         super::routes::schedule::create_schedule,
         super::routes::schedule::list_schedules,
         super::routes::schedule::delete_schedule,
@@ -398,8 +408,17 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         super::routes::config_management::ToolPermission,
         super::routes::config_management::UpsertPermissionsQuery,
         super::routes::reply::PermissionConfirmationRequest,
+        super::routes::reply::CredentialResponse,
         super::routes::context::ContextManageRequest,
         super::routes::context::ContextManageResponse,
+        super::routes::editor::SelectionRequest,
+        super::routes::editor::EditResultRequest,
+        goose::agents::editor::BufferUpdate,
+        goose::agents::editor::Selection,
+        goose::agents::editor::EditorRequest,
+        goose::agents::editor::TextEdit,
+        goose::agents::editor::Range,
+        goose::agents::editor::Position,
         super::routes::session::SessionListResponse,
         super::routes::session::SessionHistoryResponse,
         Message,
@@ -422,6 +441,12 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         ResourceContentsSchema,
         ContextLengthExceeded,
         SummarizationRequested,
+        Citation,
+        StopReason,
+        TokenLogprob,
+        TopLogprob,
+        Origin,
+        TrustLevel,
         RoleSchema,
         ProviderMetadata,
         ExtensionEntry,
@@ -436,6 +461,13 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         ModelInfo,
         SessionInfo,
         SessionMetadata,
+        ContextSnapshot,
+        ModelUsage,
+        UsageEntry,
+        UsageLedger,
+        UsageTotals,
+        SessionCost,
+        ModelSpend,
         super::routes::schedule::CreateScheduleRequest,
         super::routes::schedule::UpdateScheduleRequest,
         super::routes::schedule::KillJobResponse,
@@ -0,0 +1,171 @@
+use super::reply::SseResponse;
+use super::utils::verify_secret_key;
+use crate::state::AppState;
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use goose::agents::editor::{BufferUpdate, Selection};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::ToSchema;
+
+/// How often the event stream pings, so a closed editor is noticed
+const EDITOR_PING_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SelectionRequest {
+    /// The selection, or null once nothing is selected
+    selection: Option<Selection>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditResultRequest {
+    id: String,
+    /// Why the editor could not apply the request, or null if it did
+    error: Option<String>,
+}
+
+/// Connect an editor: a stream of the requests the agent sends it, one `EditorRequest` per
+/// event, for as long as the editor stays connected
+async fn editor_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<SseResponse, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    let mut requests = agent.editor().subscribe();
+
+    let (tx, rx) = mpsc::channel::<String>(32);
+    tokio::spawn(async move {
+        let mut ping = tokio::time::interval(EDITOR_PING_INTERVAL);
+        loop {
+            let event = tokio::select! {
+                _ = ping.tick() => json!({"type": "ping"}).to_string(),
+                request = requests.recv() => match request {
+                    Ok(request) => match serde_json::to_string(&request) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            tracing::error!("Failed to serialize editor request: {}", e);
+                            continue;
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Editor missed {} requests", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+            if tx.send(format!("data: {}\n\n", event)).await.is_err() {
+                tracing::info!("editor disconnected");
+                break;
+            }
+        }
+    });
+    Ok(SseResponse::new(ReceiverStream::new(rx)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/editor/buffer",
+    request_body = BufferUpdate,
+    responses(
+        (status = 200, description = "Buffer recorded", body = Value),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Precondition failed - Agent not available")
+    )
+)]
+pub async fn update_buffer(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(update): Json<BufferUpdate>,
+) -> Result<Json<Value>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    agent.editor().update_buffer(update);
+    Ok(Json(json!({"status": "ok"})))
+}
+
+#[utoipa::path(
+    post,
+    path = "/editor/selection",
+    request_body = SelectionRequest,
+    responses(
+        (status = 200, description = "Selection recorded", body = Value),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Precondition failed - Agent not available")
+    )
+)]
+pub async fn update_selection(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<SelectionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    agent.editor().set_selection(request.selection);
+    Ok(Json(json!({"status": "ok"})))
+}
+
+#[utoipa::path(
+    post,
+    path = "/editor/result",
+    request_body = EditResultRequest,
+    responses(
+        (status = 200, description = "Result passed to the agent", body = Value),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "No request with this id is waiting"),
+        (status = 412, description = "Precondition failed - Agent not available")
+    )
+)]
+pub async fn submit_edit_result(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<EditResultRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    let result = match request.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    };
+    if !agent.editor().complete(&request.id, result) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(json!({"status": "ok"})))
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/editor/events", get(editor_events))
+        .route(
+            "/editor/buffer",
+            post(update_buffer).layer(DefaultBodyLimit::max(50 * 1024 * 1024)),
+        )
+        .route("/editor/selection", post(update_selection))
+        .route("/editor/result", post(submit_edit_result))
+        .with_state(state)
+}
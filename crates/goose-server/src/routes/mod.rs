@@ -3,6 +3,7 @@ pub mod agent;
 pub mod audio;
 pub mod config_management;
 pub mod context;
+pub mod editor;
 pub mod extension;
 pub mod health;
 pub mod project;
@@ -24,6 +25,7 @@ pub fn configure(state: Arc<crate::state::AppState>) -> Router {
         .merge(agent::routes(state.clone()))
         .merge(audio::routes(state.clone()))
         .merge(context::routes(state.clone()))
+        .merge(editor::routes(state.clone()))
         .merge(extension::routes(state.clone()))
         .merge(config_management::routes(state.clone()))
         .merge(recipe::routes(state.clone()))
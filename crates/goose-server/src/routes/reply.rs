@@ -12,7 +12,11 @@ use futures::{stream::StreamExt, Stream};
 use goose::conversation::message::{Message, MessageContent};
 use goose::conversation::Conversation;
 use goose::{
-    agents::{AgentEvent, SessionConfig},
+    agents::{
+        credentials::CredentialRequest, refusal::RefusalEvent, secret_guard::SecretBlockedEvent,
+        AgentEvent, SessionConfig,
+    },
+    context_mgmt::{auto_compact::CompactionEvent, usage::ContextUsage},
     permission::permission_confirmation::PrincipalType,
 };
 use goose::{
@@ -98,7 +102,7 @@ pub struct SseResponse {
 }
 
 impl SseResponse {
-    fn new(rx: ReceiverStream<String>) -> Self {
+    pub(crate) fn new(rx: ReceiverStream<String>) -> Self {
         Self { rx }
     }
 }
@@ -143,6 +147,21 @@ enum MessageEvent {
         model: String,
         mode: String,
     },
+    Refusal {
+        refusal: RefusalEvent,
+    },
+    SecretBlocked {
+        blocked: SecretBlockedEvent,
+    },
+    ContextCompacted {
+        compaction: CompactionEvent,
+    },
+    ContextUsage {
+        usage: ContextUsage,
+    },
+    CredentialRequest {
+        request: CredentialRequest,
+    },
     Notification {
         request_id: String,
         message: ServerNotification,
@@ -245,7 +264,6 @@ async fn reply_handler(
             }
         };
 
-        let mut all_messages = messages.clone();
         let session_path = match session::get_path(session::Identifier::Name(session_id.clone())) {
             Ok(path) => path,
             Err(e) => {
@@ -261,7 +279,17 @@ async fn reply_handler(
                 return;
             }
         };
-        let saved_message_count = all_messages.len();
+
+        // Continue the session's event log with what the client added; a history edited or
+        // compacted elsewhere records only the messages that changed
+        let events_path = session::event_log_path(&session_path);
+        let mut event_log = session::SessionEventLog::load(&events_path).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load session events: {}", e);
+            session::SessionEventLog::new()
+        });
+        let saved_event_count = event_log.events().len();
+        event_log.record_history(messages.messages());
+        let mut approvals = state.subscribe_approvals();
 
         let mut heartbeat_interval = tokio::time::interval(Duration::from_millis(500));
         loop {
@@ -273,25 +301,50 @@ async fn reply_handler(
                 _ = heartbeat_interval.tick() => {
                     stream_event(MessageEvent::Ping, &tx, &cancel_token).await;
                 }
+                Ok((request_id, permission)) = approvals.recv() => {
+                    // Approvals for other sessions' tool calls are theirs to record
+                    let ours = event_log.events().iter().any(|event| matches!(
+                        &event.kind,
+                        session::SessionEventKind::ToolStarted { request_id: id, .. } if *id == request_id
+                    ));
+                    if ours {
+                        event_log.record_approval(request_id, permission);
+                    }
+                }
                 response = timeout(Duration::from_millis(500), stream.next()) => {
                     match response {
                         Ok(Some(Ok(AgentEvent::Message(message)))) => {
                             for content in &message.content {
-                                track_tool_telemetry(content, all_messages.messages());
+                                track_tool_telemetry(content, event_log.conversation().messages());
                             }
 
-                            all_messages.push(message.clone());
+                            event_log.record_message(message.clone());
                             stream_event(MessageEvent::Message { message }, &tx, &cancel_token).await;
                         }
                         Ok(Some(Ok(AgentEvent::HistoryReplaced(new_messages)))) => {
                             // Replace the message history with the compacted messages
-                            all_messages = Conversation::new_unvalidated(new_messages);
+                            event_log.record_compaction(new_messages);
                             // Note: We don't send this as a stream event since it's an internal operation
                             // The client will see the compaction notification message that was sent before this event
                         }
                         Ok(Some(Ok(AgentEvent::ModelChange { model, mode }))) => {
                             stream_event(MessageEvent::ModelChange { model, mode }, &tx, &cancel_token).await;
                         }
+                        Ok(Some(Ok(AgentEvent::ContextCompacted(compaction)))) => {
+                            stream_event(MessageEvent::ContextCompacted { compaction }, &tx, &cancel_token).await;
+                        }
+                        Ok(Some(Ok(AgentEvent::ContextUsage(usage)))) => {
+                            stream_event(MessageEvent::ContextUsage { usage }, &tx, &cancel_token).await;
+                        }
+                        Ok(Some(Ok(AgentEvent::CredentialRequest(request)))) => {
+                            stream_event(MessageEvent::CredentialRequest { request }, &tx, &cancel_token).await;
+                        }
+                        Ok(Some(Ok(AgentEvent::Refusal(refusal)))) => {
+                            stream_event(MessageEvent::Refusal { refusal }, &tx, &cancel_token).await;
+                        }
+                        Ok(Some(Ok(AgentEvent::SecretBlocked(blocked)))) => {
+                            stream_event(MessageEvent::SecretBlocked { blocked }, &tx, &cancel_token).await;
+                        }
                         Ok(Some(Ok(AgentEvent::McpNotification((request_id, n))))) => {
                             stream_event(MessageEvent::Notification{
                                 request_id: request_id.clone(),
@@ -324,11 +377,14 @@ async fn reply_handler(
             }
         }
 
-        if all_messages.len() > saved_message_count {
+        if event_log.events().len() > saved_event_count {
+            if let Err(e) = event_log.save(&events_path) {
+                tracing::error!("Failed to store session events: {:?}", e);
+            }
             if let Ok(provider) = agent.provider().await {
                 let provider = Arc::clone(&provider);
                 let session_path_clone = session_path.to_path_buf();
-                let all_messages_clone = all_messages.clone();
+                let all_messages_clone = event_log.conversation().clone();
                 tokio::spawn(async move {
                     if let Err(e) = session::persist_messages(
                         &session_path_clone,
@@ -384,7 +440,7 @@ async fn reply_handler(
                 exit_type = "normal",
                 duration_ms = session_duration.as_millis() as u64,
                 total_tokens = 0u64,
-                message_count = all_messages.len(),
+                message_count = event_log.conversation().len(),
                 "Session completed"
             );
 
@@ -454,10 +510,45 @@ pub async fn confirm_permission(
             request.id.clone(),
             PermissionConfirmation {
                 principal_type: request.principal_type,
-                permission,
+                permission: permission.clone(),
             },
         )
         .await;
+    state.publish_approval(request.id, permission);
+    Ok(Json(Value::Object(serde_json::Map::new())))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CredentialResponse {
+    id: String,
+    /// The secret, or null if the user declined to provide it
+    value: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/credential",
+    request_body = CredentialResponse,
+    responses(
+        (status = 200, description = "Credential has been passed to the agent", body = Value),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn provide_credential(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CredentialResponse>,
+) -> Result<Json<Value>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+
+    // The value must never be logged
+    agent.provide_credential(request.id, request.value).await;
     Ok(Json(Value::Object(serde_json::Map::new())))
 }
 
@@ -506,6 +597,7 @@ pub fn routes(state: Arc<AppState>) -> Router {
             post(reply_handler).layer(DefaultBodyLimit::max(50 * 1024 * 1024)),
         )
         .route("/confirm", post(confirm_permission))
+        .route("/credential", post(provide_credential))
         .route(
             "/tool_result",
             post(submit_tool_result).layer(DefaultBodyLimit::max(10 * 1024 * 1024)),
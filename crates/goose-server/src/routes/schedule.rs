@@ -21,6 +21,9 @@ pub struct CreateScheduleRequest {
     cron: String,
     #[serde(default)]
     execution_mode: Option<String>, // "foreground" or "background"
+    /// IANA timezone the cron expression is in; defaults to the server's timezone
+    #[serde(default)]
+    timezone: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, utoipa::ToSchema)]
@@ -127,6 +130,7 @@ async fn create_schedule(
         current_session_id: None,
         process_start_time: None,
         execution_mode: req.execution_mode.or(Some("background".to_string())), // Default to background
+        timezone: req.timezone,
     };
     scheduler
         .add_scheduled_job(job.clone())
@@ -1,5 +1,5 @@
 use super::utils::verify_secret_key;
-use chrono::{DateTime, Datelike};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone};
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -10,10 +10,11 @@ use axum::{
     routing::{get, put},
     Json, Router,
 };
+use goose::clock;
 use goose::conversation::message::Message;
 use goose::session;
 use goose::session::info::{get_valid_sorted_sessions, SessionInfo, SortOrder};
-use goose::session::SessionMetadata;
+use goose::session::{SessionCost, SessionMetadata};
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 use utoipa::ToSchema;
@@ -141,6 +142,38 @@ async fn get_session_history(
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/cost",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session")
+    ),
+    responses(
+        (status = 200, description = "Session token usage and cost retrieved successfully", body = SessionCost),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Session Management"
+)]
+// Get the tokens a session used and what they cost
+async fn get_session_cost(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionCost>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let session_path = session::get_path(session::Identifier::Name(session_id))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let metadata = session::read_metadata(&session_path).map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(SessionCost::for_session(&metadata).await))
+}
+
 #[utoipa::path(
     get,
     path = "/sessions/insights",
@@ -188,6 +221,7 @@ async fn get_session_insights(
     let mut total_duration = 0.0;
     let mut total_tokens = 0;
     let mut activity_by_date: HashMap<String, usize> = HashMap::new();
+    let timezone = clock::user_timezone();
 
     for session in &sessions {
         // Track directory usage
@@ -214,7 +248,7 @@ async fn get_session_insights(
         }
 
         // Track activity by date
-        if let Ok(date) = DateTime::parse_from_str(&session.modified, "%Y-%m-%d %H:%M:%S UTC") {
+        if let Some(date) = modified_date(&session.modified, &timezone) {
             let date_str = date.format("%Y-%m-%d").to_string();
             *activity_by_date.entry(date_str).or_insert(0) += 1;
         }
@@ -290,11 +324,9 @@ async fn get_activity_heatmap(
     let mut heatmap: std::collections::HashMap<(usize, usize), usize> =
         std::collections::HashMap::new();
 
+    let timezone = clock::user_timezone();
     for session in &sessions {
-        if let Ok(date) =
-            chrono::NaiveDateTime::parse_from_str(&session.modified, "%Y-%m-%d %H:%M:%S UTC")
-        {
-            let date = date.date();
+        if let Some(date) = modified_date(&session.modified, &timezone) {
             let week = date.iso_week().week() as usize - 1; // 0-based week
             let day = date.weekday().num_days_from_sunday() as usize; // 0=Sun, 6=Sat
             *heatmap.entry((week, day)).or_insert(0) += 1;
@@ -359,11 +391,20 @@ async fn update_session_metadata(
     Ok(StatusCode::OK)
 }
 
+/// The day a session was last active on, in the user's timezone. Modification times are
+/// recorded in UTC, which would put evening sessions on the next day for users west of it.
+fn modified_date<Tz: TimeZone>(modified: &str, timezone: &Tz) -> Option<NaiveDate> {
+    NaiveDateTime::parse_from_str(modified, "%Y-%m-%d %H:%M:%S UTC")
+        .ok()
+        .map(|modified| modified.and_utc().with_timezone(timezone).date_naive())
+}
+
 // Configure routes for this module
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/sessions", get(list_sessions))
         .route("/sessions/{session_id}", get(get_session_history))
+        .route("/sessions/{session_id}/cost", get(get_session_cost))
         .route("/sessions/insights", get(get_session_insights))
         .route("/sessions/activity-heatmap", get(get_activity_heatmap))
         .route(
@@ -377,6 +418,20 @@ pub fn routes(state: Arc<AppState>) -> Router {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_modified_date_in_user_timezone() {
+        let pacific = chrono::FixedOffset::west_opt(7 * 3600).unwrap();
+        assert_eq!(
+            modified_date("2026-10-16 03:30:00 UTC", &pacific),
+            NaiveDate::from_ymd_opt(2026, 10, 15)
+        );
+        assert_eq!(
+            modified_date("2026-10-16 03:30:00 UTC", &chrono::Utc),
+            NaiveDate::from_ymd_opt(2026, 10, 16)
+        );
+        assert_eq!(modified_date("Unknown", &chrono::Utc), None);
+    }
+
     #[tokio::test]
     async fn test_update_session_metadata_request_deserialization() {
         // Test that our request struct can be deserialized properly
@@ -1,7 +1,8 @@
 use goose::agents::Agent;
+use goose::permission::Permission;
 use goose::scheduler_trait::SchedulerTrait;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 
 pub type AgentRef = Arc<Agent>;
 
@@ -10,6 +11,8 @@ pub struct AppState {
     agent: Option<AgentRef>,
     pub secret_key: String,
     pub scheduler: Arc<Mutex<Option<Arc<dyn SchedulerTrait>>>>,
+    /// The user's answers to tool permission requests, for the replies recording them
+    approvals: broadcast::Sender<(String, Permission)>,
 }
 
 impl AppState {
@@ -18,6 +21,7 @@ impl AppState {
             agent: Some(agent.clone()),
             secret_key,
             scheduler: Arc::new(Mutex::new(None)),
+            approvals: broadcast::channel(32).0,
         })
     }
 
@@ -27,6 +31,15 @@ impl AppState {
             .ok_or_else(|| anyhow::anyhow!("Agent needs to be created first."))
     }
 
+    pub fn subscribe_approvals(&self) -> broadcast::Receiver<(String, Permission)> {
+        self.approvals.subscribe()
+    }
+
+    pub fn publish_approval(&self, request_id: String, permission: Permission) {
+        // Nobody listens when no reply is running
+        let _ = self.approvals.send((request_id, permission));
+    }
+
     pub async fn set_scheduler(&self, sched: Arc<dyn SchedulerTrait>) {
         let mut guard = self.scheduler.lock().await;
         *guard = Some(sched);
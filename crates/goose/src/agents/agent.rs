@@ -1,6 +1,8 @@
 use std::collections::{HashMap, HashSet};
 use std::future::Future;
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
@@ -8,21 +10,34 @@ use futures::stream::BoxStream;
 use futures::{stream, FutureExt, Stream, StreamExt, TryStreamExt};
 use uuid::Uuid;
 
+use crate::agents::confidence::ConfidenceCheck;
+use crate::agents::credentials::{CredentialRequest, CredentialStore};
+use crate::agents::dispatch::{self, SubagentReport, DISPATCH_TOOL_NAME};
+use crate::agents::editor::EditorBridge;
 use crate::agents::extension::{ExtensionConfig, ExtensionError, ExtensionResult, ToolInfo};
 use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
 use crate::agents::final_output_tool::{FINAL_OUTPUT_CONTINUATION_MESSAGE, FINAL_OUTPUT_TOOL_NAME};
+use crate::agents::freshness::{DocFreshness, FreshnessCheck, FAST_MOVING_LIBRARIES_KEY};
+use crate::agents::injection_guard::InjectionGuard;
 use crate::agents::platform_tools::{
-    PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME,
-    PLATFORM_MANAGE_SCHEDULE_TOOL_NAME, PLATFORM_READ_RESOURCE_TOOL_NAME,
-    PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
+    PLATFORM_CONVERT_TIMEZONE_TOOL_NAME, PLATFORM_GET_CURRENT_TIME_TOOL_NAME,
+    PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_LOAD_TOOL_TOOL_NAME,
+    PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME, PLATFORM_MANAGE_SCHEDULE_TOOL_NAME,
+    PLATFORM_READ_MORE_TOOL_NAME, PLATFORM_READ_RESOURCE_TOOL_NAME,
+    PLATFORM_REQUEST_CREDENTIAL_TOOL_NAME, PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
 };
 use crate::agents::prompt_manager::PromptManager;
 use crate::agents::recipe_tools::dynamic_task_tools::{
     create_dynamic_task, create_dynamic_task_tool, DYNAMIC_TASK_TOOL_NAME_PREFIX,
 };
+use crate::agents::refusal::{
+    create_fallback_provider, is_refusal, last_user_request, RefusalEvent, RefusalPolicy,
+    MAX_REFUSAL_RECOVERIES, REFUSAL_MESSAGE,
+};
 use crate::agents::retry::{RetryManager, RetryResult};
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
 use crate::agents::router_tools::{ROUTER_LLM_SEARCH_TOOL_NAME, ROUTER_VECTOR_SEARCH_TOOL_NAME};
+use crate::agents::secret_guard::{self, SecretBlockedEvent, SecretGuard};
 use crate::agents::sub_recipe_manager::SubRecipeManager;
 use crate::agents::subagent_execution_tool::subagent_execute_task_tool::{
     self, SUBAGENT_EXECUTE_TASK_TOOL_NAME,
@@ -30,18 +45,25 @@ use crate::agents::subagent_execution_tool::subagent_execute_task_tool::{
 use crate::agents::subagent_execution_tool::tasks_manager::TasksManager;
 use crate::agents::tool_route_manager::ToolRouteManager;
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
+use crate::agents::trust_policy::TrustPolicy;
 use crate::agents::types::SessionConfig;
-use crate::agents::types::{FrontendTool, ToolResultReceiver};
+use crate::agents::types::{FrontendTool, ToolResultOrdering, ToolResultReceiver};
+use crate::clock;
 use crate::config::{Config, ExtensionConfigManager, PermissionManager};
-use crate::context_mgmt::auto_compact;
+use crate::context_mgmt::auto_compact::{self, CompactionEvent};
+use crate::context_mgmt::constrained;
+use crate::context_mgmt::usage::ContextUsage;
 use crate::conversation::{debug_conversation_fix, fix_conversation, Conversation};
+use crate::model::ModelConfig;
 use crate::permission::permission_judge::{check_tool_permissions, PermissionCheckResult};
 use crate::permission::PermissionConfirmation;
-use crate::providers::base::Provider;
+use crate::providers::base::{Provider, ToolChoice};
+use crate::providers::delta::merge_content;
 use crate::providers::errors::ProviderError;
 use crate::recipe::{Author, Recipe, Response, Settings, SubRecipe};
 use crate::scheduler_trait::SchedulerTrait;
 use crate::session;
+use crate::session::diff::ContextSnapshot;
 use crate::tool_monitor::{ToolCall, ToolMonitor};
 use crate::utils::is_token_cancelled;
 use mcp_core::{ToolError, ToolResult};
@@ -54,9 +76,13 @@ use tracing::{debug, error, info, instrument};
 
 use super::final_output_tool::FinalOutputTool;
 use super::platform_tools;
-use super::tool_execution::{ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::tool_execution::{
+    ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE,
+    READ_ONLY_TOOL_SKIPPED_RESPONSE,
+};
 use crate::agents::subagent_task_config::TaskConfig;
-use crate::conversation::message::{Message, ToolRequest};
+use crate::agents::types::{MAX_TURNS_MESSAGE, PROVIDER_ERROR_PREFIX};
+use crate::conversation::message::{Message, MessageContent, StopReason, ToolRequest};
 
 const DEFAULT_MAX_TURNS: u32 = 1000;
 
@@ -91,12 +117,27 @@ pub struct Agent {
     pub(super) prompt_manager: Mutex<PromptManager>,
     pub(super) confirmation_tx: mpsc::Sender<(String, PermissionConfirmation)>,
     pub(super) confirmation_rx: Mutex<mpsc::Receiver<(String, PermissionConfirmation)>>,
+    pub(super) credential_tx: mpsc::Sender<(String, Option<String>)>,
+    pub(super) credential_rx: Mutex<mpsc::Receiver<(String, Option<String>)>>,
+    pub(super) credentials: CredentialStore,
     pub(super) tool_result_tx: mpsc::Sender<(String, ToolResult<Vec<Content>>)>,
     pub(super) tool_result_rx: ToolResultReceiver,
     pub(super) tool_monitor: Arc<Mutex<Option<ToolMonitor>>>,
     pub(super) tool_route_manager: ToolRouteManager,
     pub(super) scheduler_service: Mutex<Option<Arc<dyn SchedulerTrait>>>,
     pub(super) retry_manager: RetryManager,
+    pub(super) tool_result_ordering: Mutex<ToolResultOrdering>,
+    /// The one tool whose schema is sent in constrained mode
+    pub(super) loaded_tool: Mutex<Option<String>>,
+    pub(super) injection_guard: InjectionGuard,
+    /// Set once untrusted output was quarantined during the current reply
+    pub(super) quarantined: Arc<AtomicBool>,
+    pub(super) trust_policy: TrustPolicy,
+    pub(super) confidence_check: ConfidenceCheck,
+    pub(super) secret_guard: SecretGuard,
+    /// Set when another session holds the workspace, so only read-only tools may run
+    pub(super) read_only: AtomicBool,
+    pub(super) editor: EditorBridge,
 }
 
 #[derive(Clone, Debug)]
@@ -105,6 +146,11 @@ pub enum AgentEvent {
     McpNotification((String, ServerNotification)),
     ModelChange { model: String, mode: String },
     HistoryReplaced(Vec<Message>),
+    ContextCompacted(CompactionEvent),
+    ContextUsage(ContextUsage),
+    CredentialRequest(CredentialRequest),
+    Refusal(RefusalEvent),
+    SecretBlocked(SecretBlockedEvent),
 }
 
 impl Default for Agent {
@@ -152,8 +198,9 @@ impl Agent {
         // Create channels with buffer size 32 (adjust if needed)
         let (confirm_tx, confirm_rx) = mpsc::channel(32);
         let (tool_tx, tool_rx) = mpsc::channel(32);
+        let (credential_tx, credential_rx) = mpsc::channel(32);
 
-        let tool_monitor = Arc::new(Mutex::new(None));
+        let tool_monitor = Arc::new(Mutex::new(Some(ToolMonitor::from_config())));
         let retry_manager = RetryManager::with_tool_monitor(tool_monitor.clone());
 
         Self {
@@ -167,18 +214,47 @@ impl Agent {
             prompt_manager: Mutex::new(PromptManager::new()),
             confirmation_tx: confirm_tx,
             confirmation_rx: Mutex::new(confirm_rx),
+            credential_tx,
+            credential_rx: Mutex::new(credential_rx),
+            credentials: CredentialStore::default(),
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
             tool_monitor,
             tool_route_manager: ToolRouteManager::new(),
             scheduler_service: Mutex::new(None),
             retry_manager,
+            tool_result_ordering: Mutex::new(ToolResultOrdering::default()),
+            loaded_tool: Mutex::new(None),
+            injection_guard: InjectionGuard::from_config(),
+            quarantined: Arc::new(AtomicBool::new(false)),
+            trust_policy: TrustPolicy::from_config(),
+            confidence_check: ConfidenceCheck::from_config(),
+            secret_guard: SecretGuard::from_config(),
+            read_only: AtomicBool::new(false),
+            editor: EditorBridge::new(),
         }
     }
 
+    /// The connection to the user's editor, if one attaches to this agent
+    pub fn editor(&self) -> &EditorBridge {
+        &self.editor
+    }
+
+    /// Only run tools annotated as read-only, for a session attached to a workspace another
+    /// session is editing
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
+
     pub async fn configure_tool_monitor(&self, max_repetitions: Option<u32>) {
         let mut tool_monitor = self.tool_monitor.lock().await;
-        *tool_monitor = Some(ToolMonitor::new(max_repetitions));
+        *tool_monitor = Some(ToolMonitor::from_config().with_max_repetitions(max_repetitions));
+    }
+
+    /// Choose the order in which parallel tool results are added to the conversation.
+    /// Defaults to the order the model requested them in.
+    pub async fn set_tool_result_ordering(&self, ordering: ToolResultOrdering) {
+        *self.tool_result_ordering.lock().await = ordering;
     }
 
     /// Reset the retry attempts counter to 0
@@ -355,6 +431,19 @@ impl Agent {
         sub_recipe_manager.add_sub_recipe_tools(sub_recipes);
     }
 
+    /// Run `task` in a subagent on `model`, with only the named enabled extensions when given,
+    /// held to the configured subagent budget
+    pub async fn spawn_subagent(
+        &self,
+        task: impl Into<String>,
+        model: ModelConfig,
+        extensions: Option<Vec<String>>,
+    ) -> Result<SubagentReport> {
+        let provider = dispatch::subagent_provider(model)?;
+        let task_config = TaskConfig::new(Some(provider)).with_extensions(extensions);
+        Ok(dispatch::run_subagent(task.into(), task_config).await)
+    }
+
     /// Dispatch a single tool call to the appropriate client
     #[instrument(skip(self, tool_call, request_id), fields(input, output))]
     pub async fn dispatch_tool_call(
@@ -367,12 +456,10 @@ impl Agent {
         if let Some(monitor) = self.tool_monitor.lock().await.as_mut() {
             let tool_call_info = ToolCall::new(tool_call.name.clone(), tool_call.arguments.clone());
 
-            if !monitor.check_tool_call(tool_call_info) {
+            if let Err(detected) = monitor.check_tool_call(tool_call_info) {
                 return (
                     request_id,
-                    Err(ToolError::ExecutionError(
-                        "Tool call rejected: exceeded maximum allowed repetitions".to_string(),
-                    )),
+                    Err(ToolError::ExecutionError(detected.rejection().to_string())),
                 );
             }
         }
@@ -404,6 +491,30 @@ impl Agent {
             return (request_id, Ok(ToolCallResult::from(result)));
         }
 
+        if tool_call.name == PLATFORM_LOAD_TOOL_TOOL_NAME {
+            let name = tool_call
+                .arguments
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let mut tools = self.list_tools(None).await;
+            tools.extend(
+                self.frontend_tools
+                    .lock()
+                    .await
+                    .values()
+                    .map(|frontend_tool| frontend_tool.tool.clone()),
+            );
+            let result = match constrained::load_tool(&tools, name) {
+                Ok((loaded, message)) => {
+                    *self.loaded_tool.lock().await = Some(loaded);
+                    Ok(vec![Content::text(message)])
+                }
+                Err(message) => Err(ToolError::InvalidParameters(message)),
+            };
+            return (request_id, Ok(ToolCallResult::from(result)));
+        }
+
         if tool_call.name == FINAL_OUTPUT_TOOL_NAME {
             return if let Some(final_output_tool) = self.final_output_tool.lock().await.as_mut() {
                 let result = final_output_tool.execute_tool_call(tool_call.clone()).await;
@@ -420,6 +531,7 @@ impl Agent {
 
         let extension_manager = self.extension_manager.read().await;
         let sub_recipe_manager = self.sub_recipe_manager.lock().await;
+        let tool_name = tool_call.name.clone();
         let result: ToolCallResult = if sub_recipe_manager.is_sub_recipe_tool(&tool_call.name) {
             sub_recipe_manager
                 .dispatch_sub_recipe_tool_call(
@@ -439,6 +551,16 @@ impl Agent {
                 cancellation_token,
             )
             .await
+        } else if tool_call.name == DISPATCH_TOOL_NAME {
+            let provider = self.provider().await.ok();
+            ToolCallResult {
+                result: Box::new(Box::pin(dispatch::dispatch(
+                    tool_call.arguments.clone(),
+                    provider,
+                    cancellation_token,
+                ))),
+                notification_stream: None,
+            }
         } else if tool_call.name == DYNAMIC_TASK_TOOL_NAME_PREFIX {
             create_dynamic_task(tool_call.arguments.clone(), &self.tasks_manager).await
         } else if tool_call.name == PLATFORM_READ_RESOURCE_TOOL_NAME {
@@ -462,6 +584,33 @@ impl Agent {
             )
         } else if tool_call.name == PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME {
             ToolCallResult::from(extension_manager.search_available_extensions().await)
+        } else if tool_call.name == PLATFORM_GET_CURRENT_TIME_TOOL_NAME {
+            let timezone = tool_call.arguments.get("timezone").and_then(|v| v.as_str());
+            ToolCallResult::from(
+                clock::current_time(timezone)
+                    .map(|time| vec![Content::text(time)])
+                    .map_err(|e| ToolError::InvalidParameters(e.to_string())),
+            )
+        } else if tool_call.name == PLATFORM_CONVERT_TIMEZONE_TOOL_NAME {
+            let argument = |name: &str| tool_call.arguments.get(name).and_then(|v| v.as_str());
+            let result = match (argument("time"), argument("to")) {
+                (Some(time), Some(to)) => clock::convert_timezone(time, argument("from"), to)
+                    .map(|converted| vec![Content::text(converted)])
+                    .map_err(|e| ToolError::InvalidParameters(e.to_string())),
+                _ => Err(ToolError::InvalidParameters(
+                    "Both 'time' and 'to' are required".to_string(),
+                )),
+            };
+            ToolCallResult::from(result)
+        } else if tool_call.name == PLATFORM_READ_MORE_TOOL_NAME {
+            ToolCallResult::from(super::large_response_handler::read_more(
+                tool_call.arguments.clone(),
+            ))
+        } else if let Some(result) = self.editor.route(&tool_call) {
+            ToolCallResult {
+                result: Box::new(result),
+                notification_stream: None,
+            }
         } else if self.is_frontend_tool(&tool_call.name).await {
             // For frontend tools, return an error indicating we need frontend execution
             ToolCallResult::from(Err(ToolError::ExecutionError(
@@ -479,24 +628,38 @@ impl Agent {
                 Err(e) => return (request_id, Err(e)),
             }
         } else {
+            // Credentials are added only on the way to the extension, never to the recorded call
+            let tool_call = self.credentials.inject(tool_call);
             // Clone the result to ensure no references to extension_manager are returned
             let result = extension_manager
-                .dispatch_tool_call(tool_call.clone(), cancellation_token.unwrap_or_default())
+                .dispatch_tool_call(tool_call, cancellation_token.unwrap_or_default())
                 .await;
             result.unwrap_or_else(|e| {
                 ToolCallResult::from(Err(ToolError::ExecutionError(e.to_string())))
             })
         };
 
+        // Untrusted output is checked for injected instructions before the model sees it
+        let guard = self.injection_guard.clone();
+        let quarantined = self.quarantined.clone();
+        let provider = self.provider().await.ok();
+        // The full output is scanned, before a large one is cut down to a preview
+        let result_future = result
+            .result
+            .then(move |output| async move {
+                let (output, flagged) = guard.inspect(&tool_name, output, provider).await;
+                if flagged {
+                    quarantined.store(true, Ordering::SeqCst);
+                }
+                output
+            })
+            .map(super::large_response_handler::process_tool_response);
+
         (
             request_id,
             Ok(ToolCallResult {
                 notification_stream: result.notification_stream,
-                result: Box::new(
-                    result
-                        .result
-                        .map(super::large_response_handler::process_tool_response),
-                ),
+                result: Box::new(Box::pin(result_future)),
             }),
         )
     }
@@ -609,6 +772,15 @@ impl Agent {
         (request_id, result)
     }
 
+    /// Set the session's working directory, whose extension lockfile and `.gooseignore` apply
+    pub async fn set_working_dir(&self, working_dir: PathBuf) {
+        self.editor.set_working_dir(working_dir.clone());
+        self.extension_manager
+            .write()
+            .await
+            .set_working_dir(working_dir);
+    }
+
     pub async fn add_extension(&self, extension: ExtensionConfig) -> ExtensionResult<()> {
         match &extension {
             ExtensionConfig::Frontend {
@@ -682,6 +854,10 @@ impl Agent {
                 platform_tools::search_available_extensions_tool(),
                 platform_tools::manage_extensions_tool(),
                 platform_tools::manage_schedule_tool(),
+                platform_tools::request_credential_tool(),
+                platform_tools::read_more_tool(),
+                platform_tools::get_current_time_tool(),
+                platform_tools::convert_timezone_tool(),
             ]);
 
             // Dynamic task tool
@@ -704,6 +880,7 @@ impl Agent {
                 prefixed_tools.push(final_output_tool.tool());
             }
             prefixed_tools.push(subagent_execute_task_tool::create_subagent_execute_task_tool());
+            prefixed_tools.push(dispatch::create_dispatch_tool());
         }
 
         prefixed_tools
@@ -760,12 +937,19 @@ impl Agent {
         }
     }
 
+    /// Answer a credential request. `None` means the user declined to provide it.
+    pub async fn provide_credential(&self, request_id: String, value: Option<String>) {
+        if let Err(e) = self.credential_tx.send((request_id, value)).await {
+            error!("Failed to send credential: {}", e);
+        }
+    }
+
     /// Handle auto-compaction logic and return compacted messages if needed
     async fn handle_auto_compaction(
         &self,
         messages: &[Message],
         session: &Option<SessionConfig>,
-    ) -> Result<Option<(Conversation, String)>> {
+    ) -> Result<Option<(Conversation, String, CompactionEvent)>> {
         // Try to get session metadata for more accurate token counts
         let session_metadata = if let Some(session_config) = session {
             match session::storage::get_path(session_config.id.clone()) {
@@ -776,10 +960,13 @@ impl Agent {
             None
         };
 
+        // Small models get their earlier turns summarized much sooner
+        let threshold_override =
+            constrained::compaction_threshold(&self.provider().await?.get_model_config());
         let compact_result = auto_compact::check_and_compact_messages(
             self,
             messages,
-            None,
+            threshold_override,
             session_metadata.as_ref(),
         )
         .await?;
@@ -787,21 +974,14 @@ impl Agent {
         if compact_result.compacted {
             let compacted_messages = compact_result.messages;
 
-            // Create compaction notification message
-            let compaction_msg = if let (Some(before), Some(after)) =
-                (compact_result.tokens_before, compact_result.tokens_after)
-            {
-                format!(
-                    "Auto-compacted context: {} → {} tokens ({:.0}% reduction)\n\n",
-                    before,
-                    after,
-                    (1.0 - (after as f64 / before as f64)) * 100.0
-                )
-            } else {
-                "Auto-compacted context to reduce token usage\n\n".to_string()
+            let event = CompactionEvent {
+                tokens_before: compact_result.tokens_before.unwrap_or_default(),
+                tokens_after: compact_result.tokens_after.unwrap_or_default(),
+                messages_summarized: compact_result.messages_summarized,
             };
+            let compaction_msg = event.notice();
 
-            return Ok(Some((compacted_messages, compaction_msg)));
+            return Ok(Some((compacted_messages, compaction_msg, event)));
         }
 
         Ok(None)
@@ -814,12 +994,17 @@ impl Agent {
         session: Option<SessionConfig>,
         cancel_token: Option<CancellationToken>,
     ) -> Result<BoxStream<'_, Result<AgentEvent>>> {
+        if let Some(session_config) = &session {
+            self.set_working_dir(session_config.working_dir.clone())
+                .await;
+        }
+
         // Handle auto-compaction before processing
         let (messages, compaction_msg) = match self
             .handle_auto_compaction(unfixed_conversation.messages(), &session)
             .await?
         {
-            Some((compacted_messages, msg)) => (compacted_messages, Some(msg)),
+            Some((compacted_messages, msg, event)) => (compacted_messages, Some((msg, event))),
             None => {
                 let context = self
                     .prepare_reply_context(unfixed_conversation, &session)
@@ -829,10 +1014,11 @@ impl Agent {
         };
 
         // If we compacted, yield the compaction message and history replacement event
-        if let Some(compaction_msg) = compaction_msg {
+        if let Some((compaction_msg, compaction_event)) = compaction_msg {
             return Ok(Box::pin(async_stream::try_stream! {
                 yield AgentEvent::Message(Message::assistant().with_text(compaction_msg));
                 yield AgentEvent::HistoryReplaced(messages.messages().clone());
+                yield AgentEvent::ContextCompacted(compaction_event);
 
                 // Continue with normal reply processing using compacted messages
                 let mut reply_stream = self.reply_internal(messages, session, cancel_token).await?;
@@ -863,8 +1049,30 @@ impl Agent {
             initial_messages,
             config,
         } = context;
+        let freshness = DocFreshness::from_config(config).check(
+            messages.messages(),
+            tools.iter().chain(&toolshim_tools),
+            &config
+                .get_param::<Vec<String>>(FAST_MOVING_LIBRARIES_KEY)
+                .unwrap_or_default(),
+        );
+        let freshness_note = match &freshness {
+            Some(check) => {
+                let model_name = self.provider().await?.get_model_config().model_name;
+                Some(check.note(ModelConfig::knowledge_cutoff(&model_name).as_deref()))
+            }
+            None => None,
+        };
+        let turn_notes: Vec<String> = freshness_note
+            .into_iter()
+            .chain(self.editor.context_note())
+            .collect();
+        for note in &turn_notes {
+            system_prompt = format!("{}\n\n{}", system_prompt, note);
+        }
         let reply_span = tracing::Span::current();
         self.reset_retry_attempts().await;
+        self.quarantined.store(false, Ordering::SeqCst);
 
         if let Some(content) = messages
             .last()
@@ -874,9 +1082,26 @@ impl Agent {
             debug!("user_message" = &content);
         }
 
+        // Repeated calls and oscillating edits are counted per request
+        if let Some(monitor) = self.tool_monitor.lock().await.as_mut() {
+            monitor.start_request();
+        }
+
         Ok(Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
             let mut turns_taken = 0u32;
+            let refusal_policy = RefusalPolicy::from_config(config);
+            let mut refusal_recoveries = 0u32;
+            let mut provider_override: Option<Arc<dyn Provider>> = None;
+            // Sent with the next request only, so they never become part of the saved history
+            let mut retry_messages: Vec<Message> = Vec::new();
+            let mut last_input_tokens: Option<usize> = None;
+            let mut compaction_crossing = auto_compact::ThresholdCrossing::default();
+            // Reset after every request, so a constraint only holds for the turn that set it
+            let mut tool_choice = match freshness {
+                Some(FreshnessCheck { fetch: true, tool: Some(tool), .. }) => ToolChoice::Tool(tool),
+                _ => ToolChoice::Auto,
+            };
             let max_turns = session
                 .as_ref()
                 .and_then(|s| s.max_turns)
@@ -901,23 +1126,34 @@ impl Agent {
 
                 turns_taken += 1;
                 if turns_taken > max_turns {
-                    yield AgentEvent::Message(Message::assistant().with_text(
-                        "I've reached the maximum number of actions I can do without user input. Would you like me to continue?"
-                    ));
+                    yield AgentEvent::Message(Message::assistant().with_text(MAX_TURNS_MESSAGE));
                     break;
                 }
 
+                let turn_provider = match &provider_override {
+                    Some(provider) => provider.clone(),
+                    None => self.provider().await?,
+                };
+                let mut request_messages = Self::fit_history_to_budget(&turn_provider, messages.messages()).await;
+                request_messages.append(&mut retry_messages);
                 let mut stream = Self::stream_response_from_provider(
-                    self.provider().await?,
+                    turn_provider.clone(),
                     &system_prompt,
-                    messages.messages(),
+                    &request_messages,
                     &tools,
                     &toolshim_tools,
+                    &std::mem::take(&mut tool_choice),
                 ).await?;
 
                 let mut added_message = false;
                 let mut messages_to_add = Vec::new();
                 let mut tools_updated = false;
+                let mut loop_detected = None;
+                let mut stop_reason: Option<StopReason> = None;
+                let mut response_text = String::new();
+                let mut response_id: Option<String> = None;
+                // Text and reasoning streamed ahead of the tool calls they lead up to
+                let mut streamed_content: Vec<MessageContent> = Vec::new();
 
                 while let Some(next) = stream.next().await {
                     if is_token_cancelled(&cancel_token) {
@@ -927,7 +1163,7 @@ impl Agent {
                     match next {
                         Ok((response, usage)) => {
                             // Emit model change event if provider is lead-worker
-                            let provider = self.provider().await?;
+                            let provider = turn_provider.clone();
                             if let Some(lead_worker) = provider.as_lead_worker() {
                                 if let Some(ref usage) = usage {
                                     let active_model = usage.model.clone();
@@ -947,15 +1183,49 @@ impl Agent {
                                 }
                             }
 
+                            // Emit model change event if provider walks a fallback chain
+                            if let Some(fallback) = provider.as_fallback() {
+                                if usage.is_some() {
+                                    let active_model = fallback.get_active_model();
+                                    let mode = if active_model == fallback.get_primary_model() {
+                                        "primary"
+                                    } else {
+                                        "fallback"
+                                    };
+
+                                    yield AgentEvent::ModelChange {
+                                        model: active_model,
+                                        mode: mode.to_string(),
+                                    };
+                                }
+                            }
+
                             // Record usage for the session
                             if let Some(ref session_config) = &session {
                                 if let Some(ref usage) = usage {
                                     Self::update_session_metrics(session_config, usage, messages.len())
                                         .await?;
+                                    let offered: Vec<Tool> = tools.iter().chain(&toolshim_tools).cloned().collect();
+                                    let snapshot = ContextSnapshot::new(&system_prompt, &offered, &turn_provider.get_model_config());
+                                    Self::record_context_snapshot(session_config, snapshot).await;
+                                }
+                            }
+                            if let Some(ref usage) = usage {
+                                self.record_accepted_prompt(&provider, usage).await;
+                                if let Some(input_tokens) = usage.usage.input_tokens {
+                                    last_input_tokens = Some(input_tokens.max(0) as usize);
                                 }
                             }
 
                             if let Some(response) = response {
+                                if response.stop_reason.is_some() {
+                                    stop_reason = response.stop_reason;
+                                }
+                                response_text.push_str(&response.as_concat_text());
+                                if response.id.is_some() {
+                                    response_id = response.id.clone();
+                                }
+
                                 let ToolCategorizeResult {
                                     frontend_requests,
                                     remaining_requests,
@@ -968,11 +1238,16 @@ impl Agent {
                                     .record_tool_requests(&requests_to_record)
                                     .await;
 
-                                yield AgentEvent::Message(filtered_response.clone());
-                                tokio::task::yield_now().await;
+                                if !filtered_response.content.is_empty() {
+                                    yield AgentEvent::Message(filtered_response.clone());
+                                    tokio::task::yield_now().await;
+                                }
 
                                 let num_tool_requests = frontend_requests.len() + remaining_requests.len();
                                 if num_tool_requests == 0 {
+                                    for content in response.content {
+                                        merge_content(&mut streamed_content, content);
+                                    }
                                     continue;
                                 }
 
@@ -989,6 +1264,20 @@ impl Agent {
                                     yield AgentEvent::Message(msg);
                                 }
 
+                                let (credential_requests, remaining_requests): (Vec<_>, Vec<_>) =
+                                    remaining_requests.into_iter().partition(|request| {
+                                        request.tool_call.as_ref().is_ok_and(|call| {
+                                            call.name == PLATFORM_REQUEST_CREDENTIAL_TOOL_NAME
+                                        })
+                                    });
+                                let mut credential_stream = self.handle_credential_requests(
+                                    &credential_requests,
+                                    message_tool_response.clone(),
+                                );
+                                while let Some(request) = credential_stream.try_next().await? {
+                                    yield AgentEvent::CredentialRequest(request);
+                                }
+
                                 let mode = goose_mode.clone();
                                 if mode.as_str() == "chat" {
                                     // Skip all tool calls in chat mode
@@ -1000,8 +1289,35 @@ impl Agent {
                                         );
                                     }
                                 } else {
+                                    // Calls that would write a credential don't run, whatever the mode
+                                    let mut blocked_events = Vec::new();
+                                    let remaining_requests: Vec<ToolRequest> = {
+                                        let mut response = message_tool_response.lock().await;
+                                        remaining_requests.into_iter().filter(|request| {
+                                            let Ok(call) = &request.tool_call else {
+                                                return true;
+                                            };
+                                            let findings = self.secret_guard.check(call, &self.credentials);
+                                            if findings.is_empty() {
+                                                return true;
+                                            }
+                                            *response = response.clone().with_tool_response(
+                                                request.id.clone(),
+                                                Ok(vec![Content::text(secret_guard::blocked_response(&findings))]),
+                                            );
+                                            blocked_events.push(SecretBlockedEvent {
+                                                tool_name: call.name.clone(),
+                                                findings,
+                                            });
+                                            false
+                                        }).collect()
+                                    };
+                                    for event in blocked_events {
+                                        yield AgentEvent::SecretBlocked(event);
+                                    }
+
                                     let mut permission_manager = PermissionManager::default();
-                                    let (permission_check_result, enable_extension_request_ids) =
+                                    let (mut permission_check_result, enable_extension_request_ids) =
                                         check_tool_permissions(
                                             &remaining_requests,
                                             &mode,
@@ -1011,6 +1327,59 @@ impl Agent {
                                             self.provider().await?,
                                         ).await;
 
+                                    // After quarantining untrusted output, the user approves what happens next
+                                    if self.injection_guard.require_approval()
+                                        && self.quarantined.load(Ordering::SeqCst)
+                                    {
+                                        let approved = std::mem::take(&mut permission_check_result.approved);
+                                        permission_check_result.needs_approval.extend(approved);
+                                    }
+
+                                    // Guarded tools wait for the user while less trusted content is in play
+                                    let context_trust = self.trust_policy.context_trust(messages.messages());
+                                    let (guarded, approved): (Vec<_>, Vec<_>) =
+                                        std::mem::take(&mut permission_check_result.approved)
+                                            .into_iter()
+                                            .partition(|request| {
+                                                request.tool_call.as_ref().is_ok_and(|call| {
+                                                    self.trust_policy.requires_approval(&call.name, context_trust)
+                                                })
+                                            });
+                                    permission_check_result.approved = approved;
+                                    permission_check_result.needs_approval.extend(guarded);
+
+                                    // Risky calls the model isn't sure about wait for the user, even in auto mode
+                                    if self.confidence_check.is_enabled() {
+                                        let (approved, escalated) = self.confidence_check.escalate(
+                                            turn_provider.clone(),
+                                            messages.messages(),
+                                            std::mem::take(&mut permission_check_result.approved),
+                                            &readonly_tools,
+                                        ).await;
+                                        permission_check_result.approved = approved;
+                                        permission_check_result.needs_approval.extend(escalated);
+                                    }
+
+                                    // Attached read-only, anything that may write is skipped
+                                    if self.read_only.load(Ordering::SeqCst) {
+                                        let is_read_only = |request: &ToolRequest| {
+                                            request.tool_call.as_ref().is_ok_and(|call| readonly_tools.contains(&call.name))
+                                        };
+                                        let (approved, skipped): (Vec<_>, Vec<_>) =
+                                            std::mem::take(&mut permission_check_result.approved).into_iter().partition(is_read_only);
+                                        let (needs_approval, skipped_approval): (Vec<_>, Vec<_>) =
+                                            std::mem::take(&mut permission_check_result.needs_approval).into_iter().partition(is_read_only);
+                                        permission_check_result.approved = approved;
+                                        permission_check_result.needs_approval = needs_approval;
+                                        let mut response = message_tool_response.lock().await;
+                                        for request in skipped.iter().chain(&skipped_approval) {
+                                            *response = response.clone().with_tool_response(
+                                                request.id.clone(),
+                                                Ok(vec![Content::text(READ_ONLY_TOOL_SKIPPED_RESPONSE)]),
+                                            );
+                                        }
+                                    }
+
                                     let mut tool_futures = self.handle_approved_and_denied_tools(
                                         &permission_check_result,
                                         message_tool_response.clone(),
@@ -1075,15 +1444,45 @@ impl Agent {
                                     }
                                 }
 
-                                let final_message_tool_resp = message_tool_response.lock().await.clone();
+                                let mut final_message_tool_resp = message_tool_response.lock().await.clone();
+                                if *self.tool_result_ordering.lock().await == ToolResultOrdering::RequestOrder {
+                                    let request_order: Vec<String> = response
+                                        .content
+                                        .iter()
+                                        .filter_map(|c| c.as_tool_request())
+                                        .map(|req| req.id.clone())
+                                        .collect();
+                                    final_message_tool_resp =
+                                        final_message_tool_resp.with_tool_responses_in_order(&request_order);
+                                }
+                                let tool_names = response
+                                    .content
+                                    .iter()
+                                    .filter_map(|c| c.as_tool_request())
+                                    .filter_map(|req| req.tool_call.as_ref().ok())
+                                    .map(|call| call.name.as_str());
+                                if let Some(origin) = self.trust_policy.least_trusted_origin(tool_names, &self.injection_guard) {
+                                    final_message_tool_resp = final_message_tool_resp.with_origin(origin);
+                                }
                                 yield AgentEvent::Message(final_message_tool_resp.clone());
+                                if let Some(monitor) = self.tool_monitor.lock().await.as_mut() {
+                                    loop_detected = monitor.take_detected();
+                                }
 
                                 added_message = true;
+                                let mut response = response;
+                                response.content.splice(0..0, std::mem::take(&mut streamed_content));
                                 messages_to_add.push(response);
                                 messages_to_add.push(final_message_tool_resp);
                             }
                         }
                         Err(ProviderError::ContextLengthExceeded(_)) => {
+                            self.record_rejected_prompt(
+                                &turn_provider,
+                                &system_prompt,
+                                &request_messages,
+                                &tools,
+                            ).await;
                             yield AgentEvent::Message(Message::assistant().with_context_length_exceeded(
                                     "The context length of the model has been exceeded. Please start a new session and try again.",
                                 ));
@@ -1092,22 +1491,126 @@ impl Agent {
                         Err(e) => {
                             error!("Error: {}", e);
                             yield AgentEvent::Message(Message::assistant().with_text(
-                                    format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")
+                                    format!("{PROVIDER_ERROR_PREFIX}{e}.\n\nPlease retry if you think this is a transient or recoverable error.")
                                 ));
                             break;
                         }
                     }
                 }
+                // Going in circles, so hand back to the user instead of spending more turns
+                if let Some(detected) = loop_detected {
+                    tracing::warn!("Stopping the agent loop: {:?}", detected);
+                    yield AgentEvent::Message(Message::assistant().with_text(detected.stop_message()));
+                    break;
+                }
                 if tools_updated {
                     (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+                    for note in &turn_notes {
+                        system_prompt = format!("{}\n\n{}", system_prompt, note);
+                    }
                 }
                 if !added_message {
-                    if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
+                    match stop_reason {
+                        Some(reason) if reason.is_truncated() => {
+                            info!("Response truncated by output token limit, requesting continuation");
+                            match self.complete_truncated_response(
+                                &system_prompt,
+                                &messages,
+                                &tools,
+                                &toolshim_tools,
+                                &response_text,
+                                &session,
+                            ).await {
+                                Ok(remainder) if !remainder.is_empty() => {
+                                    let mut message = Message::assistant().with_text(remainder);
+                                    message.id = response_id.clone();
+                                    yield AgentEvent::Message(message);
+                                }
+                                Ok(_) => {}
+                                Err(e) => {
+                                    tracing::warn!("Failed to continue truncated response: {}", e);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    if is_refusal(stop_reason, &response_text) {
+                        let recovery = match &refusal_policy {
+                            RefusalPolicy::Surface => None,
+                            _ if refusal_recoveries >= MAX_REFUSAL_RECOVERIES => None,
+                            policy => Some(policy.clone()),
+                        };
+                        tracing::warn!("Model declined the request (stop reason: {:?})", stop_reason);
+                        yield AgentEvent::Refusal(RefusalEvent {
+                            model: turn_provider.get_active_model_name(),
+                            stop_reason,
+                            text: response_text.clone(),
+                            recovery: recovery.clone(),
+                        });
+
+                        match recovery {
+                            Some(RefusalPolicy::Rephrase { template }) => {
+                                if let Some(request) = last_user_request(messages.messages()) {
+                                    refusal_recoveries += 1;
+                                    retry_messages = vec![
+                                        Message::assistant().with_text(response_text),
+                                        Message::user()
+                                            .with_text(RefusalPolicy::rephrase(&template, &request)),
+                                    ];
+                                    continue;
+                                }
+                            }
+                            Some(RefusalPolicy::Fallback { model }) => {
+                                match create_fallback_provider(&model) {
+                                    Ok(provider) => {
+                                        refusal_recoveries += 1;
+                                        info!("Retrying refused request with fallback model {}", model);
+                                        provider_override = Some(provider);
+                                        yield AgentEvent::ModelChange {
+                                            model,
+                                            mode: "refusal_fallback".to_string(),
+                                        };
+                                        continue;
+                                    }
+                                    Err(e) => {
+                                        error!("Failed to create refusal fallback provider: {}", e);
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+
+                        yield AgentEvent::Message(Message::assistant().with_text(REFUSAL_MESSAGE));
+                    }
+
+                    if let Some(final_output_tool) = self.final_output_tool.lock().await.as_mut() {
+                        // Providers that can't be made to call the tool are asked for the schema directly
+                        if final_output_tool.final_output.is_none() && !turn_provider.supports_tool_choice() {
+                            let mut history = messages.messages().clone();
+                            history.extend(messages_to_add.iter().cloned());
+                            if !response_text.is_empty() {
+                                history.push(Message::assistant().with_text(response_text.clone()));
+                            }
+                            let schema = final_output_tool.response.json_schema.clone().unwrap_or_default();
+                            match turn_provider.complete_with_response_schema(&system_prompt, &history, &schema).await {
+                                Ok((output, usage)) => {
+                                    if let Some(ref session_config) = &session {
+                                        Self::update_session_metrics(session_config, &usage, messages.len()).await?;
+                                    }
+                                    if let Err(e) = final_output_tool.collect_output(output).await {
+                                        tracing::warn!("Structured final output was rejected: {}", e);
+                                    }
+                                }
+                                Err(e) => tracing::warn!("Failed to get a structured final output: {}", e),
+                            }
+                        }
                         if final_output_tool.final_output.is_none() {
                             tracing::warn!("Final output tool has not been called yet. Continuing agent loop.");
                             let message = Message::user().with_text(FINAL_OUTPUT_CONTINUATION_MESSAGE);
                             messages_to_add.push(message.clone());
                             yield AgentEvent::Message(message);
+                            tool_choice = ToolChoice::Tool(FINAL_OUTPUT_TOOL_NAME.to_string());
                             continue
                         } else {
                             let message = Message::assistant().with_text(final_output_tool.final_output.clone().unwrap());
@@ -1130,11 +1633,51 @@ impl Agent {
                             ));
                         }
                     }
+
+                    // The final response isn't part of `messages` yet
+                    let mut history = messages.messages().clone();
+                    history.extend(messages_to_add);
+                    if !response_text.is_empty() {
+                        history.push(Message::assistant().with_text(response_text.clone()));
+                    }
+                    match Self::measure_context_usage(&turn_provider, &system_prompt, &tools, &history).await {
+                        Ok(usage) => yield AgentEvent::ContextUsage(usage),
+                        Err(e) => tracing::debug!("Failed to measure context usage: {}", e),
+                    }
                     break;
                 }
 
                 messages.extend(messages_to_add);
 
+                // Compact earlier turns before the next request if the last prompt came close
+                // to filling the context window, once each time it crosses the threshold
+                if let Some(input_tokens) = last_input_tokens.take() {
+                    let context_limit = turn_provider.get_model_config().context_limit();
+                    let threshold = auto_compact::compaction_threshold();
+                    let over = auto_compact::exceeds_threshold(input_tokens, context_limit, threshold);
+                    if compaction_crossing.update(over) {
+                        match auto_compact::compact_older_turns(self, messages.messages()).await {
+                            Ok(Some((compacted, event))) => {
+                                info!(
+                                    "Compacted {} earlier messages mid-turn ({} -> {} tokens)",
+                                    event.messages_summarized, event.tokens_before, event.tokens_after
+                                );
+                                messages = compacted;
+                                yield AgentEvent::Message(Message::assistant().with_text(event.notice()));
+                                yield AgentEvent::HistoryReplaced(messages.messages().clone());
+                                yield AgentEvent::ContextCompacted(event);
+                            }
+                            Ok(None) => {}
+                            Err(e) => tracing::warn!("Failed to compact conversation: {}", e),
+                        }
+                    }
+                }
+
+                match Self::measure_context_usage(&turn_provider, &system_prompt, &tools, messages.messages()).await {
+                    Ok(usage) => yield AgentEvent::ContextUsage(usage),
+                    Err(e) => tracing::debug!("Failed to measure context usage: {}", e),
+                }
+
                 tokio::task::yield_now().await;
             }
         }))
@@ -1374,6 +1917,9 @@ impl Agent {
             goose_provider: Some(provider_name.clone()),
             goose_model: Some(model_name.clone()),
             temperature: Some(model_config.temperature.unwrap_or(0.0)),
+            max_tokens: None,
+            batch: None,
+            pin: None,
         };
 
         let recipe = Recipe::builder()
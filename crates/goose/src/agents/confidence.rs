@@ -0,0 +1,315 @@
+//! Self-assessed confidence before risky tool calls.
+//!
+//! When enabled, the model rates how sure it is that each tool call able to change something is
+//! what the user asked for and safe to run, answering in a fixed schema. Calls rated below the
+//! threshold for their capability class wait for the user's approval, even in auto mode.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agents::injection_guard::matches_pattern;
+use crate::config::{Config, ConfigError};
+use crate::conversation::message::{Message, ToolRequest};
+use crate::providers::base::Provider;
+
+/// Config key turning the self-assessment on
+pub const CONFIDENCE_CHECK_KEY: &str = "GOOSE_CONFIDENCE_CHECK";
+/// Config key for the least confidence each capability class runs with unattended, e.g.
+/// `{"shell": 0.9, "network": 0.5}`
+pub const CONFIDENCE_THRESHOLDS_KEY: &str = "GOOSE_CONFIDENCE_THRESHOLDS";
+
+const ASSESSMENT_SYSTEM_PROMPT: &str = "You review actions an AI agent is about to take. For each \
+action, rate from 0 to 1 how confident you are that it is what the user asked for and that it \
+is safe to run without the user checking it first. Destructive or irreversible actions the \
+user did not clearly ask for deserve a low rating.";
+
+/// What a tool can do, which decides how sure the model has to be to run it unattended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilityClass {
+    /// Runs commands or scripts on the machine
+    Shell,
+    /// Creates, edits or deletes files
+    FileWrite,
+    /// Sends requests or messages to other systems
+    Network,
+    /// Any other tool not annotated as read-only
+    Other,
+}
+
+const CLASS_PATTERNS: &[(CapabilityClass, &[&str])] = &[
+    (
+        CapabilityClass::Shell,
+        &["*shell*", "*automation_script*", "*exec*"],
+    ),
+    (
+        CapabilityClass::FileWrite,
+        &["*text_editor*", "*write*", "*edit*", "*delete*", "*remove*"],
+    ),
+    (
+        CapabilityClass::Network,
+        &["*fetch*", "*http*", "*web*", "*send*", "*post*"],
+    ),
+];
+
+impl CapabilityClass {
+    pub fn of_tool(tool_name: &str) -> Self {
+        CLASS_PATTERNS
+            .iter()
+            .find(|(_, patterns)| {
+                patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, tool_name))
+            })
+            .map(|(class, _)| *class)
+            .unwrap_or(CapabilityClass::Other)
+    }
+
+    fn default_threshold(&self) -> f64 {
+        match self {
+            CapabilityClass::Shell => 0.8,
+            CapabilityClass::FileWrite => 0.6,
+            CapabilityClass::Network => 0.7,
+            CapabilityClass::Other => 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConfidenceCheck {
+    enabled: bool,
+    thresholds: HashMap<CapabilityClass, f64>,
+}
+
+impl ConfidenceCheck {
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        let enabled = config.get_param(CONFIDENCE_CHECK_KEY).unwrap_or(false);
+        let thresholds = match config.get_param(CONFIDENCE_THRESHOLDS_KEY) {
+            Ok(thresholds) => thresholds,
+            Err(ConfigError::NotFound(_)) => HashMap::new(),
+            Err(e) => {
+                tracing::warn!("Ignoring {}: {}", CONFIDENCE_THRESHOLDS_KEY, e);
+                HashMap::new()
+            }
+        };
+        Self {
+            enabled,
+            thresholds,
+        }
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_threshold(mut self, class: CapabilityClass, threshold: f64) -> Self {
+        self.thresholds.insert(class, threshold);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The least confidence a call of this class needs to run without approval; 0 never asks
+    pub fn threshold(&self, class: CapabilityClass) -> f64 {
+        self.thresholds
+            .get(&class)
+            .copied()
+            .unwrap_or_else(|| class.default_threshold())
+    }
+
+    /// Split approved calls into those that still run unattended and those the user has to
+    /// approve. Read-only tools are never assessed. If the model can't give a rating, the calls
+    /// it should have rated are escalated.
+    pub async fn escalate(
+        &self,
+        provider: Arc<dyn Provider>,
+        messages: &[Message],
+        requests: Vec<ToolRequest>,
+        readonly_tools: &HashSet<String>,
+    ) -> (Vec<ToolRequest>, Vec<ToolRequest>) {
+        let (risky, mut approved): (Vec<_>, Vec<_>) = requests.into_iter().partition(|request| {
+            request.tool_call.as_ref().is_ok_and(|call| {
+                !readonly_tools.contains(&call.name)
+                    && self.threshold(CapabilityClass::of_tool(&call.name)) > 0.0
+            })
+        });
+        if risky.is_empty() {
+            return (approved, Vec::new());
+        }
+
+        let assessment = match provider
+            .complete_with_response_schema(
+                ASSESSMENT_SYSTEM_PROMPT,
+                &[assessment_request(messages, &risky)],
+                &assessment_schema(),
+            )
+            .await
+        {
+            Ok((assessment, _usage)) => assessment,
+            Err(e) => {
+                tracing::warn!("Could not assess confidence in tool calls: {}", e);
+                Value::Null
+            }
+        };
+
+        let (confident, escalated) = self.apply(&assessment, risky);
+        approved.extend(confident);
+        (approved, escalated)
+    }
+
+    /// Split assessed calls by their rating, escalating any the assessment left out
+    fn apply(
+        &self,
+        assessment: &Value,
+        requests: Vec<ToolRequest>,
+    ) -> (Vec<ToolRequest>, Vec<ToolRequest>) {
+        let ratings: HashMap<&str, f64> = assessment["assessments"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|rating| Some((rating["id"].as_str()?, rating["confidence"].as_f64()?)))
+            .collect();
+
+        requests.into_iter().partition(|request| {
+            let Ok(call) = &request.tool_call else {
+                return false;
+            };
+            let threshold = self.threshold(CapabilityClass::of_tool(&call.name));
+            match ratings.get(request.id.as_str()) {
+                Some(confidence) if *confidence >= threshold => true,
+                confidence => {
+                    tracing::info!(
+                        "Asking before {} ({:?} confidence, needs {})",
+                        call.name,
+                        confidence,
+                        threshold
+                    );
+                    false
+                }
+            }
+        })
+    }
+}
+
+fn assessment_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "assessments": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "confidence": {"type": "number", "minimum": 0, "maximum": 1},
+                        "reason": {"type": "string"}
+                    },
+                    "required": ["id", "confidence"]
+                }
+            }
+        },
+        "required": ["assessments"]
+    })
+}
+
+/// The user's latest request and the calls to rate, leaving out the rest of the conversation
+fn assessment_request(messages: &[Message], requests: &[ToolRequest]) -> Message {
+    let user_request = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == rmcp::model::Role::User && m.has_only_text_content())
+        .map(|m| m.as_concat_text())
+        .unwrap_or_default();
+
+    let mut text = format!(
+        "The user asked:\n{}\n\nThe agent is about to run:\n",
+        user_request
+    );
+    for request in requests {
+        if let Ok(call) = &request.tool_call {
+            let _ = writeln!(
+                text,
+                "- id {}: {} {}",
+                request.id, call.name, call.arguments
+            );
+        }
+    }
+    Message::user().with_text(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::ToolCall;
+
+    fn request(id: &str, tool: &str) -> ToolRequest {
+        ToolRequest {
+            id: id.to_string(),
+            tool_call: Ok(ToolCall::new(tool, json!({}))),
+        }
+    }
+
+    fn ids(requests: &[ToolRequest]) -> Vec<&str> {
+        requests.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_capability_classes() {
+        assert_eq!(
+            CapabilityClass::of_tool("developer__shell"),
+            CapabilityClass::Shell
+        );
+        assert_eq!(
+            CapabilityClass::of_tool("developer__text_editor"),
+            CapabilityClass::FileWrite
+        );
+        assert_eq!(
+            CapabilityClass::of_tool("slack__send_message"),
+            CapabilityClass::Network
+        );
+        assert_eq!(
+            CapabilityClass::of_tool("jira__create_issue"),
+            CapabilityClass::Other
+        );
+    }
+
+    #[test]
+    fn test_low_confidence_is_escalated_per_class() {
+        let check = ConfidenceCheck::default()
+            .enabled(true)
+            .with_threshold(CapabilityClass::Other, 0.2);
+        let assessment = json!({"assessments": [
+            {"id": "1", "confidence": 0.7, "reason": "rm -rf was not asked for"},
+            {"id": "2", "confidence": 0.7},
+            {"id": "3", "confidence": 0.3}
+        ]});
+        let requests = vec![
+            request("1", "developer__shell"),
+            request("2", "developer__text_editor"),
+            request("3", "jira__create_issue"),
+            request("4", "developer__shell"),
+        ];
+
+        let (confident, escalated) = check.apply(&assessment, requests);
+        assert_eq!(ids(&confident), vec!["2", "3"]);
+        // Below the shell threshold, and left out of the assessment
+        assert_eq!(ids(&escalated), vec!["1", "4"]);
+    }
+
+    #[test]
+    fn test_failed_assessment_escalates_everything() {
+        let check = ConfidenceCheck::default().enabled(true);
+        let (confident, escalated) =
+            check.apply(&Value::Null, vec![request("1", "developer__shell")]);
+        assert!(confident.is_empty());
+        assert_eq!(ids(&escalated), vec!["1"]);
+    }
+}
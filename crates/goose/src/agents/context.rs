@@ -1,37 +1,50 @@
 use anyhow::Ok;
+use rmcp::model::Tool;
+use std::sync::Arc;
 
+use crate::config::Config;
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
-use crate::token_counter::create_async_token_counter;
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::model_registry::{configured_provider, ModelRegistry};
+use crate::token_counter::create_async_token_counter_for_model;
 
+use crate::context_mgmt::budget::TokenBudget;
 use crate::context_mgmt::summarize::summarize_messages_async;
-use crate::context_mgmt::truncate::{truncate_messages, OldestFirstTruncation};
+use crate::context_mgmt::truncate::{truncate_messages, TruncationStrategyKind};
+use crate::context_mgmt::usage::ContextUsage;
 use crate::context_mgmt::{estimate_target_context_limit, get_messages_token_counts_async};
 
 use super::super::agents::Agent;
 
 impl Agent {
-    /// Public API to truncate oldest messages so that the conversation's token count is within the allowed context limit.
+    /// Public API to truncate messages so that the conversation's token count is within the allowed context limit.
+    /// Which messages are dropped is chosen by `GOOSE_TRUNCATION_STRATEGY`.
     pub async fn truncate_context(
         &self,
         messages: &[Message], // last message is a user msg that led to assistant message with_context_length_exceeded
     ) -> Result<(Conversation, Vec<usize>), anyhow::Error> {
         let provider = self.provider().await?;
-        let token_counter = create_async_token_counter()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+        let token_counter =
+            create_async_token_counter_for_model(&provider.get_model_config().model_name)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
         let target_context_limit = estimate_target_context_limit(provider);
         let token_counts = get_messages_token_counts_async(&token_counter, messages);
+        let strategy = TruncationStrategyKind::from_config(Config::global());
 
         let (mut new_messages, mut new_token_counts) = truncate_messages(
             messages,
             &token_counts,
             target_context_limit,
-            &OldestFirstTruncation,
+            strategy.strategy(),
         )?;
 
         // Only add an assistant message if we have room for it and it won't cause another overflow
-        let assistant_message = Message::assistant().with_text("I had run into a context length exceeded error so I truncated some of the oldest messages in our conversation.");
+        let assistant_message = Message::assistant().with_text(format!(
+            "I had run into a context length exceeded error so I truncated {} in our conversation.",
+            strategy.description()
+        ));
         let assistant_tokens =
             token_counter.count_chat_tokens("", &[assistant_message.clone()], &[]);
 
@@ -49,14 +62,16 @@ impl Agent {
     }
 
     /// Public API to summarize the conversation so that its token count is within the allowed context limit.
+    /// Pinned messages are kept verbatim ahead of the summary.
     pub async fn summarize_context(
         &self,
         messages: &[Message], // last message is a user msg that led to assistant message with_context_length_exceeded
     ) -> Result<(Conversation, Vec<usize>), anyhow::Error> {
         let provider = self.provider().await?;
-        let token_counter = create_async_token_counter()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+        let token_counter =
+            create_async_token_counter_for_model(&provider.get_model_config().model_name)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
         let target_context_limit = estimate_target_context_limit(provider.clone());
 
         let (mut new_messages, mut new_token_counts) =
@@ -83,6 +98,144 @@ impl Agent {
             }
         }
 
+        let pinned: Vec<Message> = messages.iter().filter(|m| m.pinned).cloned().collect();
+        if !pinned.is_empty() {
+            let pinned_token_counts = get_messages_token_counts_async(&token_counter, &pinned);
+            new_messages = Conversation::new_unvalidated(
+                pinned
+                    .into_iter()
+                    .chain(new_messages.messages().iter().cloned()),
+            );
+            new_token_counts = pinned_token_counts
+                .into_iter()
+                .chain(new_token_counts)
+                .collect();
+        }
+
         Ok((new_messages, new_token_counts))
     }
+
+    /// Public API to report how much of the model's context window a request with this
+    /// conversation would use, broken down by system prompt, tools, history and last response.
+    pub async fn context_usage(&self, messages: &[Message]) -> Result<ContextUsage, anyhow::Error> {
+        let provider = self.provider().await?;
+        let (tools, _toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+        Self::measure_context_usage(&provider, &system_prompt, &tools, messages).await
+    }
+
+    /// The history to send with the next request, within the configured history budget
+    pub(super) async fn fit_history_to_budget(
+        provider: &Arc<dyn Provider>,
+        messages: &[Message],
+    ) -> Vec<Message> {
+        let budget = TokenBudget::from_config();
+        if budget.history.is_none() {
+            return messages.to_vec();
+        }
+        match create_async_token_counter_for_model(&provider.get_model_config().model_name).await {
+            Ok(token_counter) => budget.fit_history(&token_counter, messages),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to create token counter for the history budget: {}",
+                    e
+                );
+                messages.to_vec()
+            }
+        }
+    }
+
+    pub(super) async fn measure_context_usage(
+        provider: &Arc<dyn Provider>,
+        system_prompt: &str,
+        tools: &[Tool],
+        messages: &[Message],
+    ) -> Result<ContextUsage, anyhow::Error> {
+        let model_config = provider.get_model_config();
+        let token_counter = create_async_token_counter_for_model(&model_config.model_name)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+        Ok(ContextUsage::measure(
+            &token_counter,
+            system_prompt,
+            tools,
+            messages,
+            model_config.context_limit(),
+        ))
+    }
+
+    /// Remember a prompt the provider accepted when it goes beyond what we know of the model's
+    /// context window, so later sessions can use the larger limit
+    pub(super) async fn record_accepted_prompt(
+        &self,
+        provider: &Arc<dyn Provider>,
+        usage: &ProviderUsage,
+    ) {
+        let (Some((endpoint, model_name)), Some(input_tokens)) =
+            (context_key(provider), usage.usage.input_tokens)
+        else {
+            return;
+        };
+        let prompt_tokens = input_tokens.max(0) as usize;
+        let registry = ModelRegistry::global();
+
+        let known_limit = provider.get_model_config().context_limit();
+        if prompt_tokens <= known_limit
+            && registry
+                .observed_context_limit(&endpoint, &model_name)
+                .is_none()
+        {
+            return;
+        }
+
+        if let Err(e) = registry
+            .record_accepted(&endpoint, &model_name, prompt_tokens)
+            .await
+        {
+            tracing::debug!("Failed to record accepted prompt size: {}", e);
+        }
+    }
+
+    /// Remember the size of a prompt that overflowed the provider's context window, so later
+    /// sessions start from a limit the endpoint actually honours. `messages` are the ones sent,
+    /// after fitting the history to its budget.
+    pub(super) async fn record_rejected_prompt(
+        &self,
+        provider: &Arc<dyn Provider>,
+        system_prompt: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) {
+        let Some((endpoint, model_name)) = context_key(provider) else {
+            return;
+        };
+        let token_counter = match create_async_token_counter_for_model(&model_name).await {
+            std::result::Result::Ok(counter) => counter,
+            Err(e) => {
+                tracing::debug!("Failed to create token counter: {}", e);
+                return;
+            }
+        };
+        let prompt_tokens = token_counter.count_everything(system_prompt, messages, tools, &[]);
+
+        tracing::info!(
+            "{}/{} rejected a prompt of ~{} tokens as too long",
+            endpoint,
+            model_name,
+            prompt_tokens
+        );
+        if let Err(e) = ModelRegistry::global()
+            .record_rejected(&endpoint, &model_name, prompt_tokens)
+            .await
+        {
+            tracing::debug!("Failed to record rejected prompt size: {}", e);
+        }
+    }
+}
+
+/// The provider and model the latest request went to, which context bounds are learned for.
+/// Wrapping providers report the member that handled it; otherwise it's the configured provider.
+fn context_key(provider: &Arc<dyn Provider>) -> Option<(String, String)> {
+    let target = provider.last_request_target();
+    let endpoint = target.provider.or_else(configured_provider)?;
+    Some((endpoint, target.model))
 }
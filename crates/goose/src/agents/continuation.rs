@@ -0,0 +1,190 @@
+use anyhow::Result;
+use rmcp::model::Tool;
+use tracing::debug;
+
+use super::Agent;
+use crate::agents::types::SessionConfig;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+
+/// Maximum number of follow-up requests issued to complete a single truncated response
+pub const MAX_TRUNCATION_CONTINUATIONS: u32 = 3;
+
+pub const TRUNCATION_CONTINUATION_MESSAGE: &str =
+    "Your previous response was cut off by the output token limit. Continue exactly where you left off, without repeating anything.";
+
+/// Longest overlap between the end of one part and the start of the next that we look for
+const MAX_OVERLAP_CHARS: usize = 500;
+
+/// Shorter overlaps are likely coincidental (a shared word or space) rather than a repeat
+const MIN_OVERLAP_CHARS: usize = 8;
+
+const CODE_FENCE: &str = "```";
+
+fn count_code_fences(text: &str) -> usize {
+    text.lines()
+        .filter(|line| line.trim_start().starts_with(CODE_FENCE))
+        .count()
+}
+
+/// Whether the text ends inside an unterminated ``` code block
+pub fn has_open_code_fence(text: &str) -> bool {
+    count_code_fences(text) % 2 == 1
+}
+
+/// Terminate a dangling code block so the final answer renders correctly
+pub fn close_open_code_fence(text: &str) -> String {
+    if !has_open_code_fence(text) {
+        return text.to_string();
+    }
+    if text.ends_with('\n') {
+        format!("{}{}", text, CODE_FENCE)
+    } else {
+        format!("{}\n{}", text, CODE_FENCE)
+    }
+}
+
+/// Length in bytes of the longest suffix of `previous` that the continuation starts with
+fn find_overlap(previous: &str, continuation: &str) -> usize {
+    let max = MAX_OVERLAP_CHARS
+        .min(previous.len())
+        .min(continuation.len());
+
+    (MIN_OVERLAP_CHARS..=max)
+        .rev()
+        .find(|&len| {
+            continuation.is_char_boundary(len)
+                && previous.is_char_boundary(previous.len() - len)
+                && previous.ends_with(&continuation[..len])
+        })
+        .unwrap_or(0)
+}
+
+/// Join a truncated response with its continuation.
+///
+/// Models often restate the last few words they produced, or reopen the code block they were in
+/// the middle of; both are dropped so the stitched text reads as a single answer.
+pub fn stitch_continuation(previous: &str, continuation: &str) -> String {
+    let mut continuation = continuation;
+
+    if has_open_code_fence(previous) {
+        let trimmed = continuation.trim_start();
+        if trimmed.starts_with(CODE_FENCE) {
+            continuation = trimmed
+                .split_once('\n')
+                .map(|(_, rest)| rest)
+                .unwrap_or_default();
+        }
+    }
+
+    let overlap = find_overlap(previous, continuation);
+    format!("{}{}", previous, &continuation[overlap..])
+}
+
+impl Agent {
+    /// Ask the model to continue a response that was cut off by the output token limit.
+    ///
+    /// Returns only the text that completes `partial`, so callers can append it to what has
+    /// already been streamed to the user.
+    pub(super) async fn complete_truncated_response(
+        &self,
+        system_prompt: &str,
+        messages: &Conversation,
+        tools: &[Tool],
+        toolshim_tools: &[Tool],
+        partial: &str,
+        session: &Option<SessionConfig>,
+    ) -> Result<String> {
+        let provider = self.provider().await?;
+        let mut stitched = partial.to_string();
+
+        for attempt in 1..=MAX_TRUNCATION_CONTINUATIONS {
+            let mut conversation = messages.clone();
+            conversation.push(Message::assistant().with_text(&stitched));
+            conversation.push(Message::user().with_text(TRUNCATION_CONTINUATION_MESSAGE));
+
+            let (response, usage) = Self::generate_response_from_provider(
+                provider.clone(),
+                system_prompt,
+                conversation.messages(),
+                tools,
+                toolshim_tools,
+            )
+            .await?;
+
+            if let Some(session_config) = session {
+                Self::update_session_metrics(session_config, &usage, conversation.len()).await?;
+            }
+
+            stitched = stitch_continuation(&stitched, &response.as_concat_text());
+
+            if !response.stop_reason.is_some_and(|r| r.is_truncated()) {
+                break;
+            }
+            debug!(
+                "Continuation {} of {} was also truncated",
+                attempt, MAX_TRUNCATION_CONTINUATIONS
+            );
+        }
+
+        let completed = close_open_code_fence(&stitched);
+        Ok(completed[partial.len()..].to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stitch_without_overlap() {
+        let stitched = stitch_continuation("The quick brown fox", " jumps over the lazy dog.");
+        assert_eq!(stitched, "The quick brown fox jumps over the lazy dog.");
+    }
+
+    #[test]
+    fn test_stitch_removes_repeated_overlap() {
+        let stitched = stitch_continuation(
+            "The quick brown fox jumps",
+            "brown fox jumps over the lazy dog.",
+        );
+        assert_eq!(stitched, "The quick brown fox jumps over the lazy dog.");
+    }
+
+    #[test]
+    fn test_stitch_ignores_short_coincidental_overlap() {
+        let stitched = stitch_continuation("I like it", "it is good");
+        assert_eq!(stitched, "I like itit is good");
+    }
+
+    #[test]
+    fn test_stitch_drops_reopened_code_fence() {
+        let previous = "Here is the code:\n```rust\nfn main() {\n";
+        let continuation = "```rust\n    println!(\"hi\");\n}\n```\n";
+        let stitched = stitch_continuation(previous, continuation);
+        assert_eq!(
+            stitched,
+            "Here is the code:\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n"
+        );
+        assert!(!has_open_code_fence(&stitched));
+    }
+
+    #[test]
+    fn test_close_open_code_fence() {
+        assert_eq!(
+            close_open_code_fence("```\nlet x = 1;"),
+            "```\nlet x = 1;\n```"
+        );
+        assert_eq!(
+            close_open_code_fence("```\nlet x = 1;\n"),
+            "```\nlet x = 1;\n```"
+        );
+        assert_eq!(close_open_code_fence("no code here"), "no code here");
+    }
+
+    #[test]
+    fn test_stitch_handles_multibyte_text() {
+        let stitched = stitch_continuation("Réponse très longue é", "très longue é terminée");
+        assert_eq!(stitched, "Réponse très longue é terminée");
+    }
+}
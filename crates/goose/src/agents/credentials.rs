@@ -0,0 +1,141 @@
+//! Secrets the user hands to tools without the model ever seeing them.
+//!
+//! The model asks for a credential with `platform__request_credential`, naming the environment
+//! variable it should be exposed as. The client prompts the user (masked input, no clipboard)
+//! and answers with [`Agent::provide_credential`](super::Agent::provide_credential). The value
+//! is kept in memory by the agent and passed to shell commands as an environment variable when
+//! they are dispatched; neither the value nor any message containing it is added to the
+//! conversation, and the developer extension redacts it from command output.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use mcp_core::tool::ToolCall;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// The tool that receives stored credentials as environment variables
+const SHELL_TOOL_NAME: &str = "developer__shell";
+
+/// Argument of the shell tool carrying the credentials, added at dispatch time only
+pub const SECRET_ENV_ARGUMENT: &str = "secret_env";
+
+/// Sent to the client when the model asks for a credential
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CredentialRequest {
+    /// Id to answer with in `provide_credential`
+    pub id: String,
+    /// The environment variable the credential will be exposed as
+    pub name: String,
+    /// What the credential is for, shown to the user
+    pub prompt: String,
+}
+
+/// Whether `name` can be used as an environment variable
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_uppercase() || c == '_')
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Tool response once the user has provided a credential
+pub fn provided_response(name: &str) -> String {
+    format!(
+        "The user provided the credential. It is available to shell commands as the environment \
+        variable ${name}. Refer to it only through the variable; never print or echo its value."
+    )
+}
+
+/// Tool response when the user declines to provide a credential
+pub const DECLINED_CREDENTIAL_RESPONSE: &str = "The user declined to provide the credential. \
+    Do not ask for it again; explain what you would need it for and continue without it if you can.";
+
+/// Credentials provided by the user in this session, by environment variable name
+#[derive(Default)]
+pub struct CredentialStore {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl CredentialStore {
+    pub fn insert(&self, name: impl Into<String>, value: impl Into<String>) {
+        if let Ok(mut values) = self.values.lock() {
+            values.insert(name.into(), value.into());
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.values
+            .lock()
+            .map(|values| values.contains_key(name))
+            .unwrap_or(false)
+    }
+
+    /// Names of the stored credentials whose value appears in `text`
+    pub fn names_in(&self, text: &str) -> Vec<String> {
+        let Ok(values) = self.values.lock() else {
+            return Vec::new();
+        };
+        values
+            .iter()
+            .filter(|(_, value)| !value.is_empty() && text.contains(value.as_str()))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Add the stored credentials to a shell tool call about to be sent to its extension.
+    /// Other tools are returned unchanged.
+    pub fn inject(&self, mut tool_call: ToolCall) -> ToolCall {
+        if tool_call.name != SHELL_TOOL_NAME {
+            return tool_call;
+        }
+        let Ok(values) = self.values.lock() else {
+            return tool_call;
+        };
+        if values.is_empty() {
+            return tool_call;
+        }
+        if let Value::Object(arguments) = &mut tool_call.arguments {
+            let env: Map<String, Value> = values
+                .iter()
+                .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+                .collect();
+            arguments.insert(SECRET_ENV_ARGUMENT.to_string(), Value::Object(env));
+        }
+        tool_call
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_valid_names() {
+        assert!(is_valid_name("GITHUB_TOKEN"));
+        assert!(is_valid_name("_PRIVATE2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2FA_CODE"));
+        assert!(!is_valid_name("db-password"));
+        assert!(!is_valid_name("PATH; rm -rf /"));
+    }
+
+    #[test]
+    fn test_inject_only_into_shell() {
+        let store = CredentialStore::default();
+        let call = ToolCall::new(SHELL_TOOL_NAME, json!({"command": "psql"}));
+        assert_eq!(store.inject(call.clone()), call);
+
+        store.insert("PGPASSWORD", "hunter22");
+        let injected = store.inject(call);
+        assert_eq!(
+            injected.arguments[SECRET_ENV_ARGUMENT],
+            json!({"PGPASSWORD": "hunter22"})
+        );
+        assert_eq!(injected.arguments["command"], "psql");
+
+        let other = ToolCall::new("developer__text_editor", json!({"command": "view"}));
+        assert_eq!(store.inject(other.clone()), other);
+        assert!(store.contains("PGPASSWORD"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Fanning independent subtasks out to concurrent subagents.
+//!
+//! The `subagent__dispatch` tool gives each subtask its own subagent, optionally on another model
+//! and with only some of the enabled extensions, runs them concurrently and returns their answers
+//! together. Every subagent is held to a token and cost budget, from `GOOSE_SUBAGENT_MAX_TOKENS`
+//! and `GOOSE_SUBAGENT_MAX_COST` unless the call sets its own.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::{stream, StreamExt};
+use mcp_core::ToolError;
+use rmcp::model::{Content, Role, Tool, ToolAnnotations};
+use rmcp::object;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::subagent::{SubAgent, BUDGET_EXCEEDED_MESSAGE};
+use crate::agents::subagent_task_config::{SubagentBudget, SubagentSpend, TaskConfig};
+use crate::agents::SubAgentStatus;
+use crate::config::Config;
+use crate::model::ModelConfig;
+use crate::providers::base::Provider;
+
+pub const DISPATCH_TOOL_NAME: &str = "subagent__dispatch";
+/// Config key for how many subagents one dispatch runs at a time
+pub const SUBAGENT_MAX_CONCURRENCY_KEY: &str = "GOOSE_SUBAGENT_MAX_CONCURRENCY";
+
+const DEFAULT_MAX_CONCURRENCY: usize = 4;
+
+pub fn create_dispatch_tool() -> Tool {
+    Tool::new(
+        DISPATCH_TOOL_NAME,
+        "Split work into independent subtasks and run each in its own subagent, all at the same \
+         time, then get their answers back together. Use it when parts of a request don't depend \
+         on each other, such as researching several topics or reviewing several files. Each \
+         subagent starts with no knowledge of this conversation, so give it complete \
+         instructions. A subagent can be put on a different model or given only some of the \
+         extensions, and is stopped once it spends its token or cost budget.",
+        object!({
+            "type": "object",
+            "required": ["tasks"],
+            "properties": {
+                "tasks": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "required": ["instructions"],
+                        "properties": {
+                            "instructions": {
+                                "type": "string",
+                                "description": "Everything the subagent needs to do the subtask"
+                            },
+                            "model": {
+                                "type": "string",
+                                "description": "Model for the subagent, from the current provider; defaults to the current model"
+                            },
+                            "extensions": {
+                                "type": "array",
+                                "items": {"type": "string"},
+                                "description": "Names of the enabled extensions the subagent may use; defaults to all of them"
+                            }
+                        }
+                    }
+                },
+                "max_tokens": {
+                    "type": "integer",
+                    "description": "Most tokens each subagent may use"
+                },
+                "max_cost": {
+                    "type": "number",
+                    "description": "Most each subagent may cost, in dollars"
+                }
+            }
+        }),
+    )
+    .annotate(ToolAnnotations {
+        title: Some("Dispatch subagents".to_string()),
+        read_only_hint: Some(false),
+        destructive_hint: Some(true),
+        idempotent_hint: Some(false),
+        open_world_hint: Some(true),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct DispatchRequest {
+    tasks: Vec<Subtask>,
+    max_tokens: Option<i64>,
+    max_cost: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subtask {
+    instructions: String,
+    model: Option<String>,
+    extensions: Option<Vec<String>>,
+}
+
+/// How a subagent's run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubagentOutcome {
+    Completed,
+    /// Stopped after spending its budget; the output is what it had by then
+    BudgetExceeded,
+    Failed,
+}
+
+/// What one subagent produced and spent
+#[derive(Debug, Clone, Serialize)]
+pub struct SubagentReport {
+    pub task: String,
+    pub model: String,
+    pub outcome: SubagentOutcome,
+    /// The subagent's final answer, or the error it failed with
+    pub output: String,
+    pub spend: SubagentSpend,
+}
+
+/// A provider for a subagent on `model`, from the configured provider
+pub fn subagent_provider(model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let provider_name: String = Config::global().get_param("GOOSE_PROVIDER")?;
+    crate::providers::create(&provider_name, model)
+}
+
+/// Run `task` in a new subagent and report how it went
+pub async fn run_subagent(task: String, task_config: TaskConfig) -> SubagentReport {
+    let model = task_config
+        .provider()
+        .map(|provider| provider.get_model_config().model_name)
+        .unwrap_or_default();
+    let failed = |output: String, spend: SubagentSpend| SubagentReport {
+        task: task.clone(),
+        model: model.clone(),
+        outcome: SubagentOutcome::Failed,
+        output,
+        spend,
+    };
+
+    let subagent = match SubAgent::new(task_config.clone()).await {
+        Ok(subagent) => subagent,
+        Err(e) => return failed(e.to_string(), SubagentSpend::default()),
+    };
+    let result = subagent.reply_subagent(task.clone(), task_config).await;
+    let spend = *subagent.spend.lock().await;
+    let conversation = match result {
+        Ok(conversation) => conversation,
+        Err(e) => return failed(e.to_string(), spend),
+    };
+
+    let outcome = match &*subagent.status.read().await {
+        SubAgentStatus::Completed(message) if message == BUDGET_EXCEEDED_MESSAGE => {
+            SubagentOutcome::BudgetExceeded
+        }
+        _ => SubagentOutcome::Completed,
+    };
+    let output = conversation
+        .messages()
+        .iter()
+        .rev()
+        .find(|message| message.role == Role::Assistant)
+        .map(|message| message.as_concat_text())
+        .unwrap_or_default();
+    SubagentReport {
+        task,
+        model,
+        outcome,
+        output,
+        spend,
+    }
+}
+
+/// The reports as one result for the model, in the order the subtasks were given
+pub fn aggregate(reports: &[SubagentReport]) -> String {
+    let mut sections = Vec::with_capacity(reports.len() + 1);
+    let completed = reports
+        .iter()
+        .filter(|report| report.outcome == SubagentOutcome::Completed)
+        .count();
+    let tokens: i64 = reports.iter().map(|report| report.spend.tokens).sum();
+    let cost: f64 = reports.iter().filter_map(|report| report.spend.cost).sum();
+    sections.push(format!(
+        "{} of {} subtasks completed, using {} tokens (${:.4}).",
+        completed,
+        reports.len(),
+        tokens,
+        cost
+    ));
+    for (index, report) in reports.iter().enumerate() {
+        let status = match report.outcome {
+            SubagentOutcome::Completed => "completed",
+            SubagentOutcome::BudgetExceeded => "stopped at its budget, answer may be incomplete",
+            SubagentOutcome::Failed => "failed",
+        };
+        let summary = report.task.lines().next().unwrap_or_default();
+        sections.push(format!(
+            "## Subtask {}: {}\nModel {}, {}, {} tokens.\n\n{}",
+            index + 1,
+            summary,
+            report.model,
+            status,
+            report.spend.tokens,
+            report.output.trim()
+        ));
+    }
+    sections.join("\n\n")
+}
+
+/// Run the dispatch tool: every subtask in its own subagent, a few at a time
+pub async fn dispatch(
+    arguments: Value,
+    parent_provider: Option<Arc<dyn Provider>>,
+    cancellation_token: Option<CancellationToken>,
+) -> Result<Vec<Content>, ToolError> {
+    let request: DispatchRequest = serde_json::from_value(arguments)
+        .map_err(|e| ToolError::InvalidParameters(format!("Invalid dispatch request: {}", e)))?;
+    if request.tasks.is_empty() {
+        return Err(ToolError::InvalidParameters(
+            "At least one task is required".to_string(),
+        ));
+    }
+
+    let config = Config::global();
+    let defaults = SubagentBudget::from_config(config);
+    let budget = SubagentBudget {
+        max_tokens: request.max_tokens.or(defaults.max_tokens),
+        max_cost: request.max_cost.or(defaults.max_cost),
+    };
+    let concurrency = config
+        .get_param::<usize>(SUBAGENT_MAX_CONCURRENCY_KEY)
+        .unwrap_or(DEFAULT_MAX_CONCURRENCY)
+        .max(1);
+
+    let mut runs = Vec::with_capacity(request.tasks.len());
+    for subtask in request.tasks {
+        let provider = match &subtask.model {
+            Some(model) => ModelConfig::new(model)
+                .map_err(anyhow::Error::from)
+                .and_then(subagent_provider)
+                .map_err(|e| {
+                    ToolError::InvalidParameters(format!("Model {} is unavailable: {}", model, e))
+                })?,
+            None => parent_provider.clone().ok_or_else(|| {
+                ToolError::ExecutionError("No provider is configured".to_string())
+            })?,
+        };
+        let task_config = TaskConfig::new(Some(provider))
+            .with_extensions(subtask.extensions)
+            .with_budget(budget);
+        runs.push((subtask.instructions, task_config));
+    }
+
+    let reports = stream::iter(runs)
+        .map(|(task, task_config)| run_subagent(task, task_config))
+        .buffered(concurrency)
+        .collect::<Vec<_>>();
+    let reports = match cancellation_token {
+        Some(token) => tokio::select! {
+            reports = reports => reports,
+            _ = token.cancelled() => {
+                return Err(ToolError::ExecutionError("Dispatch cancelled".to_string()))
+            }
+        },
+        None => reports.await,
+    };
+    Ok(vec![Content::text(aggregate(&reports))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(task: &str, outcome: SubagentOutcome, tokens: i64, cost: f64) -> SubagentReport {
+        SubagentReport {
+            task: task.to_string(),
+            model: "gpt-4o-mini".to_string(),
+            outcome,
+            output: format!("answer to {}\n", task),
+            spend: SubagentSpend {
+                tokens,
+                cost: Some(cost),
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate_keeps_task_order_and_totals() {
+        let reports = vec![
+            report(
+                "Summarize README.md",
+                SubagentOutcome::Completed,
+                1200,
+                0.01,
+            ),
+            report(
+                "Review src/lib.rs",
+                SubagentOutcome::BudgetExceeded,
+                5000,
+                0.02,
+            ),
+        ];
+        let result = aggregate(&reports);
+        assert!(result.starts_with("1 of 2 subtasks completed, using 6200 tokens ($0.0300)."));
+        let first = result.find("## Subtask 1: Summarize README.md").unwrap();
+        let second = result.find("## Subtask 2: Review src/lib.rs").unwrap();
+        assert!(first < second);
+        assert!(result.contains("stopped at its budget"));
+        assert!(result.contains("answer to Review src/lib.rs"));
+
+        let budget = SubagentBudget {
+            max_tokens: Some(5000),
+            max_cost: None,
+        };
+        assert!(budget.exceeded(&reports[1].spend));
+        assert!(!budget.exceeded(&reports[0].spend));
+    }
+}
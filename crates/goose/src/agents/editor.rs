@@ -0,0 +1,630 @@
+//! Editor integration.
+//!
+//! An editor connected to the daemon keeps the agent up to date with its open buffers and the
+//! user's selection, and subscribes to the edit requests the agent sends it. While one is
+//! connected, the developer extension's `text_editor` edits to files open in it are applied by
+//! the editor instead of written to disk, so they land in the editor's undo history, and viewing
+//! a buffer with unsaved changes shows what is in the editor rather than on disk. Calls on files
+//! that `.gooseignore` restricts are refused here just as the developer extension would.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use etcetera::{choose_app_strategy, AppStrategy};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use mcp_core::tool::ToolCall;
+use mcp_core::{ToolError, ToolResult};
+use rmcp::model::{Content, Role};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast, oneshot};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The developer extension's file tool, whose edits are routed to the editor
+const TEXT_EDITOR_TOOL_NAME: &str = "developer__text_editor";
+/// How long the editor has to apply an edit
+const EDIT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest selection included in the system prompt, in bytes
+const MAX_SELECTION_BYTES: usize = 8 * 1024;
+
+/// A place in a buffer: zero-based line, and byte offset within the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Replace `range` with `new_text`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+/// What the agent asks the editor to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EditorRequest {
+    /// Apply the edits, in order, as one undoable change, then save the buffer if `save` is set
+    ApplyEdit {
+        id: String,
+        #[schema(value_type = String)]
+        path: PathBuf,
+        edits: Vec<TextEdit>,
+        save: bool,
+    },
+    /// Undo the last change to the buffer
+    Undo {
+        id: String,
+        #[schema(value_type = String)]
+        path: PathBuf,
+    },
+}
+
+impl EditorRequest {
+    pub fn id(&self) -> &str {
+        match self {
+            EditorRequest::ApplyEdit { id, .. } | EditorRequest::Undo { id, .. } => id,
+        }
+    }
+}
+
+/// A buffer opened, changed or closed in the editor
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BufferUpdate {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    /// The buffer's full text, or null once it is closed
+    pub text: Option<String>,
+    /// Whether the buffer has changes that aren't saved
+    #[serde(default)]
+    pub dirty: bool,
+}
+
+/// Text the user has selected, with one-based inclusive line numbers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Selection {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+struct Buffer {
+    text: String,
+    dirty: bool,
+}
+
+struct EditorState {
+    buffers: Mutex<HashMap<PathBuf, Buffer>>,
+    selection: Mutex<Option<Selection>>,
+    requests: broadcast::Sender<EditorRequest>,
+    pending: Mutex<HashMap<String, oneshot::Sender<Result<(), String>>>>,
+    /// The session's working directory, whose `.gooseignore` applies
+    working_dir: Mutex<Option<PathBuf>>,
+}
+
+/// The agent's side of the editor connection
+#[derive(Clone)]
+pub struct EditorBridge {
+    state: Arc<EditorState>,
+}
+
+impl Default for EditorBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditorBridge {
+    pub fn new() -> Self {
+        let (requests, _) = broadcast::channel(32);
+        Self {
+            state: Arc::new(EditorState {
+                buffers: Mutex::new(HashMap::new()),
+                selection: Mutex::new(None),
+                requests,
+                pending: Mutex::new(HashMap::new()),
+                working_dir: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Connect an editor. What earlier editors reported is forgotten; the new one is expected
+    /// to send its open buffers.
+    pub fn subscribe(&self) -> broadcast::Receiver<EditorRequest> {
+        self.state.buffers.lock().unwrap().clear();
+        *self.state.selection.lock().unwrap() = None;
+        self.state.requests.subscribe()
+    }
+
+    pub fn set_working_dir(&self, working_dir: PathBuf) {
+        *self.state.working_dir.lock().unwrap() = Some(working_dir);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.requests.receiver_count() > 0
+    }
+
+    pub fn update_buffer(&self, update: BufferUpdate) {
+        let mut buffers = self.state.buffers.lock().unwrap();
+        match update.text {
+            Some(text) => {
+                buffers.insert(
+                    update.path,
+                    Buffer {
+                        text,
+                        dirty: update.dirty,
+                    },
+                );
+            }
+            None => {
+                buffers.remove(&update.path);
+            }
+        }
+    }
+
+    pub fn set_selection(&self, selection: Option<Selection>) {
+        *self.state.selection.lock().unwrap() = selection.filter(|s| !s.text.is_empty());
+    }
+
+    /// Report how the editor handled a request, returning false for unknown ids
+    pub fn complete(&self, id: &str, result: Result<(), String>) -> bool {
+        match self.state.pending.lock().unwrap().remove(id) {
+            Some(tx) => tx.send(result).is_ok(),
+            None => false,
+        }
+    }
+
+    /// What the editor shows, for the system prompt
+    pub fn context_note(&self) -> Option<String> {
+        if !self.is_connected() {
+            return None;
+        }
+        let buffers = self.state.buffers.lock().unwrap();
+        let mut paths: Vec<String> = buffers
+            .iter()
+            .map(|(path, buffer)| {
+                let unsaved = if buffer.dirty {
+                    " (unsaved changes)"
+                } else {
+                    ""
+                };
+                format!("- {}{}", path.display(), unsaved)
+            })
+            .collect();
+        paths.sort();
+
+        let mut note = "# Editor\n\nThe user is working in an editor connected to this session. \
+                        Edits you make with the text editor tool to files open in it are applied \
+                        in the editor, where the user can undo them."
+            .to_string();
+        if !paths.is_empty() {
+            note.push_str(&format!("\n\nOpen files:\n{}", paths.join("\n")));
+        }
+        if let Some(selection) = self.state.selection.lock().unwrap().as_ref() {
+            let mut end = selection.text.len().min(MAX_SELECTION_BYTES);
+            while !selection.text.is_char_boundary(end) {
+                end -= 1;
+            }
+            let truncated = if end < selection.text.len() {
+                "\n(selection truncated)"
+            } else {
+                ""
+            };
+            note.push_str(&format!(
+                "\n\nThe user has selected lines {}-{} of {}. Questions about \"this\" or \"the \
+                 selection\" refer to it:\n```\n{}\n```{}",
+                selection.start_line,
+                selection.end_line,
+                selection.path.display(),
+                &selection.text[..end],
+                truncated
+            ));
+        }
+        Some(note)
+    }
+
+    /// Handle a text editor call in the editor, if one is connected and has the file open
+    pub fn route(
+        &self,
+        tool_call: &ToolCall,
+    ) -> Option<BoxFuture<'static, ToolResult<Vec<Content>>>> {
+        if tool_call.name != TEXT_EDITOR_TOOL_NAME || !self.is_connected() {
+            return None;
+        }
+        let arguments = &tool_call.arguments;
+        let command = arguments.get("command")?.as_str()?;
+        let path = PathBuf::from(arguments.get("path")?.as_str()?);
+        // Relative paths are left to the developer extension, which explains what it expects
+        if !path.is_absolute() {
+            return None;
+        }
+        let buffer = self.state.buffers.lock().unwrap().get(&path).cloned()?;
+        let working_dir = self.state.working_dir.lock().unwrap().clone();
+        if let Some(cwd) = working_dir.or_else(|| std::env::current_dir().ok()) {
+            if ignore_patterns(&cwd).matched(&path, false).is_ignore() {
+                return Some(
+                    futures::future::ready(Err(ToolError::ExecutionError(format!(
+                        "Access to '{}' is restricted by .gooseignore",
+                        path.display()
+                    ))))
+                    .boxed(),
+                );
+            }
+        }
+
+        let request = match command {
+            "view" if buffer.dirty => {
+                return Some(futures::future::ready(Ok(view(&path, &buffer.text))).boxed())
+            }
+            "write" | "str_replace" | "insert" => match edits_for(command, &buffer.text, arguments)
+            {
+                Ok(edits) => EditorRequest::ApplyEdit {
+                    id: Uuid::new_v4().to_string(),
+                    path: path.clone(),
+                    edits,
+                    save: true,
+                },
+                Err(e) => return Some(futures::future::ready(Err(e)).boxed()),
+            },
+            "undo_edit" => EditorRequest::Undo {
+                id: Uuid::new_v4().to_string(),
+                path: path.clone(),
+            },
+            _ => return None,
+        };
+        let bridge = self.clone();
+        Some(async move { bridge.send(request).await }.boxed())
+    }
+
+    async fn send(&self, request: EditorRequest) -> ToolResult<Vec<Content>> {
+        let (tx, rx) = oneshot::channel();
+        let id = request.id().to_string();
+        self.state.pending.lock().unwrap().insert(id.clone(), tx);
+        let path = match &request {
+            EditorRequest::ApplyEdit { path, .. } | EditorRequest::Undo { path, .. } => {
+                path.clone()
+            }
+        };
+        if self.state.requests.send(request.clone()).is_err() {
+            self.state.pending.lock().unwrap().remove(&id);
+            return Err(ToolError::ExecutionError(
+                "The editor disconnected".to_string(),
+            ));
+        }
+
+        let result = tokio::time::timeout(EDIT_TIMEOUT, rx).await;
+        self.state.pending.lock().unwrap().remove(&id);
+        match result {
+            Ok(Ok(Ok(()))) => {
+                let message = match &request {
+                    EditorRequest::ApplyEdit { edits, .. } => {
+                        self.apply_locally(&path, edits);
+                        format!(
+                            "Edited {} in the user's editor and saved it; the user can undo the \
+                             change there.",
+                            path.display()
+                        )
+                    }
+                    EditorRequest::Undo { .. } => {
+                        format!("Undid the last change to {} in the editor.", path.display())
+                    }
+                };
+                Ok(vec![
+                    Content::text(message).with_audience(vec![Role::Assistant])
+                ])
+            }
+            Ok(Ok(Err(e))) => Err(ToolError::ExecutionError(format!(
+                "The editor could not change {}: {}",
+                path.display(),
+                e
+            ))),
+            Ok(Err(_)) => Err(ToolError::ExecutionError(
+                "The editor disconnected".to_string(),
+            )),
+            Err(_) => Err(ToolError::ExecutionError(format!(
+                "The editor did not respond within {} seconds",
+                EDIT_TIMEOUT.as_secs()
+            ))),
+        }
+    }
+
+    /// Keep our copy of the buffer in step until the editor reports it again
+    fn apply_locally(&self, path: &Path, edits: &[TextEdit]) {
+        let mut buffers = self.state.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get_mut(path) {
+            for edit in edits {
+                let start = offset(&buffer.text, edit.range.start);
+                let end = offset(&buffer.text, edit.range.end);
+                buffer.text.replace_range(start..end, &edit.new_text);
+            }
+            buffer.dirty = false;
+        }
+    }
+}
+
+/// The files the developer extension keeps the agent away from: the global and the project's
+/// `.gooseignore`, the project's `.gitignore` when it has none, or else env and secret files
+fn ignore_patterns(cwd: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(cwd);
+    let mut has_ignore_file = false;
+    if let Ok(strategy) = choose_app_strategy(crate::config::APP_STRATEGY.clone()) {
+        let global = strategy.in_config_dir(".gooseignore");
+        if global.is_file() {
+            let _ = builder.add(global);
+            has_ignore_file = true;
+        }
+    }
+    let local = [cwd.join(".gooseignore"), cwd.join(".gitignore")]
+        .into_iter()
+        .find(|path| path.is_file());
+    if let Some(local) = local {
+        let _ = builder.add(local);
+        has_ignore_file = true;
+    }
+    if !has_ignore_file {
+        for pattern in ["**/.env", "**/.env.*", "**/secrets.*"] {
+            let _ = builder.add_line(None, pattern);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// The position of a byte offset in `text`
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Position {
+        line: before.matches('\n').count(),
+        character: offset - line_start,
+    }
+}
+
+/// The byte offset of a position in `text`, clamped to its end
+fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line)
+        .map(str::len)
+        .sum::<usize>();
+    (line_start + position.character).min(text.len())
+}
+
+/// The edits a text editor command makes to a buffer holding `text`
+fn edits_for(command: &str, text: &str, arguments: &Value) -> Result<Vec<TextEdit>, ToolError> {
+    let argument = |name: &str| {
+        arguments
+            .get(name)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters(format!("Missing '{}' parameter", name)))
+    };
+    let edit = |start: usize, end: usize, new_text: String| TextEdit {
+        range: Range {
+            start: position(text, start),
+            end: position(text, end),
+        },
+        new_text,
+    };
+
+    match command {
+        "write" => {
+            let mut file_text = argument("file_text")?.to_string();
+            if !file_text.ends_with('\n') {
+                file_text.push('\n');
+            }
+            Ok(vec![edit(0, text.len(), file_text)])
+        }
+        "str_replace" => {
+            let old_str = argument("old_str")?;
+            let new_str = argument("new_str")?;
+            let mut matches = text.match_indices(old_str);
+            match (matches.next(), matches.next()) {
+                (Some((start, _)), None) => Ok(vec![edit(
+                    start,
+                    start + old_str.len(),
+                    new_str.to_string(),
+                )]),
+                (None, _) => Err(ToolError::InvalidParameters(
+                    "'old_str' must match exactly one location in the file, but it was not \
+                     found in the editor's buffer"
+                        .to_string(),
+                )),
+                (Some(_), Some(_)) => Err(ToolError::InvalidParameters(
+                    "'old_str' must match exactly one location in the file, but it appears \
+                     more than once in the editor's buffer"
+                        .to_string(),
+                )),
+            }
+        }
+        "insert" => {
+            let insert_line = arguments
+                .get("insert_line")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| {
+                    ToolError::InvalidParameters("Missing 'insert_line' parameter".to_string())
+                })? as usize;
+            let new_str = argument("new_str")?;
+            let lines = text.lines().count();
+            if insert_line > lines {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Insert line {} is beyond the end of the file ({} lines)",
+                    insert_line, lines
+                )));
+            }
+            let at = offset(
+                text,
+                Position {
+                    line: insert_line,
+                    character: 0,
+                },
+            );
+            let new_text = if at == text.len() && !text.is_empty() && !text.ends_with('\n') {
+                format!("\n{}", new_str)
+            } else {
+                format!("{}\n", new_str)
+            };
+            Ok(vec![edit(at, at, new_text)])
+        }
+        _ => Err(ToolError::InvalidParameters(format!(
+            "Unknown command '{}'",
+            command
+        ))),
+    }
+}
+
+/// A buffer's contents shown the way the developer extension shows files
+fn view(path: &Path, text: &str) -> Vec<Content> {
+    let numbered: Vec<String> = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{}: {}", i + 1, line))
+        .collect();
+    let formatted = format!(
+        "### {} (unsaved, from the editor)\n```\n{}\n```\n",
+        path.display(),
+        numbered.join("\n")
+    );
+    vec![
+        Content::text(text.to_string()).with_audience(vec![Role::Assistant]),
+        Content::text(formatted)
+            .with_audience(vec![Role::User])
+            .with_priority(0.0),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_edits_for_text_editor_commands() {
+        let text = "fn main() {\n    println!(\"hi\");\n}\n";
+
+        let edits = edits_for(
+            "str_replace",
+            text,
+            &json!({"old_str": "\"hi\"", "new_str": "\"hello\""}),
+        )
+        .unwrap();
+        assert_eq!(
+            edits,
+            vec![TextEdit {
+                range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 13
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 17
+                    },
+                },
+                new_text: "\"hello\"".to_string(),
+            }]
+        );
+
+        let edits = edits_for(
+            "insert",
+            text,
+            &json!({"insert_line": 1, "new_str": "    let x = 1;"}),
+        )
+        .unwrap();
+        assert_eq!(
+            edits[0].range.start,
+            Position {
+                line: 1,
+                character: 0
+            }
+        );
+        assert_eq!(edits[0].new_text, "    let x = 1;\n");
+
+        let edits = edits_for("write", text, &json!({"file_text": "fn main() {}"})).unwrap();
+        assert_eq!(
+            edits[0].range.end,
+            Position {
+                line: 3,
+                character: 0
+            }
+        );
+        assert_eq!(edits[0].new_text, "fn main() {}\n");
+
+        assert!(edits_for(
+            "str_replace",
+            text,
+            &json!({"old_str": "x", "new_str": "y"})
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_route_applies_edits_through_the_editor() {
+        let bridge = EditorBridge::new();
+        let call = ToolCall::new(
+            TEXT_EDITOR_TOOL_NAME,
+            json!({"command": "str_replace", "path": "/src/lib.rs", "old_str": "a", "new_str": "b"}),
+        );
+        assert!(bridge.route(&call).is_none());
+
+        let mut requests = bridge.subscribe();
+        bridge.update_buffer(BufferUpdate {
+            path: PathBuf::from("/src/lib.rs"),
+            text: Some("a\n".to_string()),
+            dirty: true,
+        });
+        let result = tokio::spawn(bridge.route(&call).unwrap());
+
+        let request = requests.recv().await.unwrap();
+        assert!(bridge.complete(request.id(), Ok(())));
+        assert!(result.await.unwrap().is_ok());
+        assert_eq!(
+            bridge.state.buffers.lock().unwrap()[Path::new("/src/lib.rs")].text,
+            "b\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_route_refuses_ignored_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".gooseignore"), "*.secret\n").unwrap();
+        assert!(ignore_patterns(dir.path())
+            .matched(dir.path().join("keys.secret"), false)
+            .is_ignore());
+        assert!(!ignore_patterns(dir.path())
+            .matched(dir.path().join("lib.rs"), false)
+            .is_ignore());
+
+        let bridge = EditorBridge::new();
+        bridge.set_working_dir(dir.path().to_path_buf());
+        let _requests = bridge.subscribe();
+        let path = dir.path().join("keys.secret");
+        bridge.update_buffer(BufferUpdate {
+            path: path.clone(),
+            text: Some("KEY=value\n".to_string()),
+            dirty: true,
+        });
+        for call in [
+            json!({"command": "view", "path": path}),
+            json!({"command": "write", "path": path, "file_text": "KEY=other"}),
+            json!({"command": "undo_edit", "path": path}),
+        ] {
+            let call = ToolCall::new(TEXT_EDITOR_TOOL_NAME, call);
+            let result = bridge.route(&call).unwrap().await;
+            assert!(result.unwrap_err().to_string().contains(".gooseignore"));
+        }
+    }
+}
@@ -11,6 +11,7 @@ use rmcp::transport::{
     ConfigureCommandExt, SseClientTransport, StreamableHttpClientTransport, TokioChildProcess,
 };
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
@@ -26,7 +27,7 @@ use tracing::{error, warn};
 use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ToolInfo};
 use super::tool_execution::ToolCallResult;
 use crate::agents::extension::{Envs, ProcessExit};
-use crate::config::{Config, ExtensionConfigManager};
+use crate::config::{extension_lock, Config, ExtensionConfigManager};
 use crate::oauth::oauth_flow;
 use crate::prompt_template;
 use mcp_client::client::{McpClient, McpClientTrait};
@@ -42,6 +43,8 @@ pub struct ExtensionManager {
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
     temp_dirs: HashMap<String, tempfile::TempDir>,
+    /// The session's working directory, whose extension lockfile command extensions are checked against
+    working_dir: Option<PathBuf>,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -113,6 +116,7 @@ impl ExtensionManager {
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
             temp_dirs: HashMap::new(),
+            working_dir: None,
         }
     }
 
@@ -120,6 +124,11 @@ impl ExtensionManager {
         !self.resource_capable_extensions.is_empty()
     }
 
+    /// Set the working directory of the session, for extensions added from now on
+    pub fn set_working_dir(&mut self, working_dir: PathBuf) {
+        self.working_dir = Some(working_dir);
+    }
+
     /// Add a new MCP extension based on the provided client type
     // TODO IMPORTANT need to ensure this times out if the extension command is broken!
     pub async fn add_extension(&mut self, config: ExtensionConfig) -> ExtensionResult<()> {
@@ -269,6 +278,7 @@ impl ExtensionManager {
                 Box::new(client)
             }
             ExtensionConfig::Stdio {
+                name,
                 cmd,
                 args,
                 envs,
@@ -276,6 +286,14 @@ impl ExtensionManager {
                 timeout,
                 ..
             } => {
+                let working_dir = match &self.working_dir {
+                    Some(dir) => dir.clone(),
+                    None => std::env::current_dir()
+                        .map_err(|e| ExtensionError::ConfigError(e.to_string()))?,
+                };
+                let (cmd, args) = extension_lock::locked_command(&working_dir, name, cmd, args)
+                    .await
+                    .map_err(|e| ExtensionError::ConfigError(e.to_string()))?;
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
                 let command = Command::new(cmd).configure(|command| {
                     command.args(args).envs(all_envs);
@@ -137,6 +137,13 @@ impl FinalOutputTool {
         }
     }
 
+    /// Collect a final output the provider returned directly rather than through the tool
+    pub async fn collect_output(&mut self, output: Value) -> Result<(), String> {
+        let parsed_value = self.validate_json_output(&output).await?;
+        self.final_output = Some(Self::parsed_final_output_string(parsed_value));
+        Ok(())
+    }
+
     // Formats the parsed JSON as a single line string so its easy to extract from the output
     fn parsed_final_output_string(parsed_json: Value) -> String {
         serde_json::to_string(&parsed_json).unwrap()
@@ -202,6 +209,26 @@ mod tests {
         FinalOutputTool::new(response);
     }
 
+    #[tokio::test]
+    async fn test_collect_output() {
+        let response = Response {
+            json_schema: Some(json!({
+                "type": "object",
+                "properties": {"message": {"type": "string"}},
+                "required": ["message"]
+            })),
+        };
+        let mut tool = FinalOutputTool::new(response);
+
+        assert!(tool.collect_output(json!({"message": 1})).await.is_err());
+        assert!(tool.final_output.is_none());
+
+        tool.collect_output(json!({"message": "done"}))
+            .await
+            .unwrap();
+        assert_eq!(tool.final_output.as_deref(), Some(r#"{"message":"done"}"#));
+    }
+
     #[tokio::test]
     async fn test_execute_tool_call_schema_validation_failure() {
         let response = Response {
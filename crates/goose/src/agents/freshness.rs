@@ -0,0 +1,250 @@
+//! Documentation freshness checks for fast-moving libraries.
+//!
+//! Models write code against the APIs they saw in training, and for libraries that change every
+//! few months that code is often out of date. When the user's latest message mentions one of
+//! them, the agent either points the model at a documentation or web search tool for the turn
+//! (`suggest`), or has the turn start with a call to that tool (`fetch`).
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rmcp::model::{Role, Tool};
+
+use crate::agents::injection_guard::matches_pattern;
+use crate::config::Config;
+use crate::conversation::message::Message;
+
+/// Config key for how the agent reacts to fast-moving libraries: `off`, `suggest` or `fetch`
+pub const DOC_FRESHNESS_KEY: &str = "GOOSE_DOC_FRESHNESS";
+/// Config key for more library names to treat as fast-moving
+pub const FAST_MOVING_LIBRARIES_KEY: &str = "GOOSE_FAST_MOVING_LIBRARIES";
+
+/// Libraries whose APIs have changed substantially within a typical training cutoff
+const FAST_MOVING_LIBRARIES: &[&str] = &[
+    // AI and LLM tooling
+    "langchain",
+    "langgraph",
+    "llamaindex",
+    "llama_index",
+    "llama-index",
+    "openai-agents",
+    "ai sdk",
+    "pydantic-ai",
+    "transformers",
+    "diffusers",
+    "vllm",
+    "mcp sdk",
+    "rmcp",
+    // web frameworks and tooling
+    "next.js",
+    "nextjs",
+    "react router",
+    "react-router",
+    "remix",
+    "sveltekit",
+    "svelte 5",
+    "astro",
+    "tailwind",
+    "tailwindcss",
+    "shadcn",
+    "tanstack",
+    "vite",
+    "expo",
+    "prisma",
+    "drizzle",
+    "supabase",
+    // rust
+    "axum",
+    "bevy",
+    "leptos",
+    "tauri",
+];
+
+/// Tools, matched by name, that can look up current documentation
+const DOC_TOOL_PATTERNS: &[&str] = &[
+    "*web_search*",
+    "*search_web*",
+    "*docs*",
+    "*documentation*",
+    "*web_scrape*",
+    "*fetch*",
+    "*browse*",
+];
+
+static DEFAULT_PATTERN: Lazy<Regex> = Lazy::new(|| library_pattern(&[]));
+
+fn library_pattern(extra: &[String]) -> Regex {
+    let names: Vec<String> = FAST_MOVING_LIBRARIES
+        .iter()
+        .map(|name| name.to_string())
+        .chain(extra.iter().map(|name| name.trim().to_lowercase()))
+        .filter(|name| !name.is_empty())
+        .map(|name| regex::escape(&name))
+        .collect();
+    Regex::new(&format!(r"(?i)\b(?:{})\b", names.join("|")))
+        .expect("escaped library names form a valid pattern")
+}
+
+/// The fast-moving libraries `text` mentions, lower case and in order of first mention
+pub fn fast_moving_libraries(text: &str, extra: &[String]) -> Vec<String> {
+    let custom;
+    let pattern = if extra.is_empty() {
+        &*DEFAULT_PATTERN
+    } else {
+        custom = library_pattern(extra);
+        &custom
+    };
+    let mut found: Vec<String> = Vec::new();
+    for name in pattern.find_iter(text) {
+        let name = name.as_str().to_lowercase();
+        if !found.contains(&name) {
+            found.push(name);
+        }
+    }
+    found
+}
+
+/// The tool best suited to looking up documentation, by the order of [`DOC_TOOL_PATTERNS`]
+pub fn doc_tool<'a>(tools: impl IntoIterator<Item = &'a Tool> + Clone) -> Option<String> {
+    DOC_TOOL_PATTERNS.iter().find_map(|pattern| {
+        tools
+            .clone()
+            .into_iter()
+            .find(|tool| matches_pattern(pattern, &tool.name))
+            .map(|tool| tool.name.to_string())
+    })
+}
+
+/// How the agent reacts when the user mentions a fast-moving library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocFreshness {
+    Off,
+    /// Tell the model to check current documentation before relying on what it remembers
+    #[default]
+    Suggest,
+    /// Also make the turn's first call go to the documentation tool
+    Fetch,
+}
+
+/// A turn that mentions fast-moving libraries
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreshnessCheck {
+    pub libraries: Vec<String>,
+    /// Tool to look the documentation up with, if one is available
+    pub tool: Option<String>,
+    /// Whether the turn should start by calling `tool`
+    pub fetch: bool,
+}
+
+impl DocFreshness {
+    pub fn from_config(config: &Config) -> Self {
+        let mode: String = config
+            .get_param(DOC_FRESHNESS_KEY)
+            .unwrap_or_else(|_| "suggest".to_string());
+        match mode.to_lowercase().as_str() {
+            "off" | "false" => DocFreshness::Off,
+            "fetch" => DocFreshness::Fetch,
+            _ => DocFreshness::Suggest,
+        }
+    }
+
+    /// Check the user's latest message, if the conversation ends with one
+    pub fn check<'a>(
+        &self,
+        messages: &[Message],
+        tools: impl IntoIterator<Item = &'a Tool> + Clone,
+        extra_libraries: &[String],
+    ) -> Option<FreshnessCheck> {
+        if *self == DocFreshness::Off {
+            return None;
+        }
+        let last = messages.last()?;
+        if last.role != Role::User || last.is_tool_response() {
+            return None;
+        }
+        let libraries = fast_moving_libraries(&last.as_concat_text(), extra_libraries);
+        if libraries.is_empty() {
+            return None;
+        }
+        let tool = doc_tool(tools);
+        Some(FreshnessCheck {
+            fetch: *self == DocFreshness::Fetch && tool.is_some(),
+            libraries,
+            tool,
+        })
+    }
+}
+
+impl FreshnessCheck {
+    /// Instructions for the turn, mentioning the model's knowledge cutoff when known
+    pub fn note(&self, knowledge_cutoff: Option<&str>) -> String {
+        let cutoff = knowledge_cutoff
+            .map(|cutoff| format!(" and your training data ends around {}", cutoff))
+            .unwrap_or_default();
+        let intro = format!(
+            "# Documentation freshness\n\nThe user's request involves {}, which change often{}.",
+            self.libraries.join(", "),
+            cutoff
+        );
+        match &self.tool {
+            Some(tool) => format!(
+                "{} Before writing code against their APIs, check the current documentation with \
+                 the {} tool, and tell the user when it differs from what you remember.",
+                intro, tool
+            ),
+            None => format!(
+                "{} No documentation tool is available, so point out that their APIs may have \
+                 changed since your training and suggest the user confirm them against the \
+                 current documentation.",
+                intro
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name.to_string(), "", object!({"type": "object"}))
+    }
+
+    #[test]
+    fn test_check_finds_libraries_and_doc_tool() {
+        let tools = vec![
+            tool("developer__shell"),
+            tool("computercontroller__web_scrape"),
+            tool("brave__brave_web_search"),
+        ];
+        let messages = vec![Message::user().with_text(
+            "Port the LangChain agent to LangGraph and style it with Tailwind. Bevy too?",
+        )];
+
+        let check = DocFreshness::Fetch
+            .check(
+                &messages,
+                &tools,
+                &["Bevy".to_string(), "polars".to_string()],
+            )
+            .unwrap();
+        assert_eq!(
+            check.libraries,
+            vec!["langchain", "langgraph", "tailwind", "bevy"]
+        );
+        assert_eq!(check.tool.as_deref(), Some("brave__brave_web_search"));
+        assert!(check.fetch);
+        assert!(check.note(Some("2024-04")).contains("ends around 2024-04"));
+
+        // Library names inside other words don't count
+        let messages =
+            vec![Message::user().with_text("Use the taxonomy and the remixed astronaut")];
+        assert_eq!(DocFreshness::Suggest.check(&messages, &tools, &[]), None);
+
+        let messages = vec![Message::user().with_text("Upgrade next.js")];
+        assert_eq!(DocFreshness::Off.check(&messages, &tools, &[]), None);
+        let check = DocFreshness::Fetch.check(&messages, &[], &[]).unwrap();
+        assert_eq!(check.tool, None);
+        assert!(!check.fetch);
+    }
+}
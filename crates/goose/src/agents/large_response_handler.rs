@@ -1,79 +1,304 @@
-use chrono::Utc;
 use mcp_core::ToolError;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
 use rmcp::model::Content;
-use std::fs::File;
-use std::io::Write;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::agents::platform_tools::PLATFORM_READ_MORE_TOOL_NAME;
+use crate::config::Config;
+use crate::token_counter::TokenCounter;
+
+/// Tool output above this many tokens is stored on disk when `GOOSE_TOOL_OUTPUT_TOKEN_BUDGET`
+/// is not set
+pub const DEFAULT_TOOL_OUTPUT_TOKEN_BUDGET: usize = 20_000;
+
+/// Most tokens of a stored output shown to the model up front
+const PREVIEW_TOKENS: usize = 1_000;
+
+/// Rough characters per token, for cutting single lines too long to tokenize piecewise
+const CHARS_PER_TOKEN: usize = 4;
+
+const REFERENCE_PREFIX: &str = "output_";
+
+/// Where large outputs are stored; the directory name is part of every stored output's path
+const STORE_DIR_NAME: &str = "goose_mcp_responses";
+
+/// A stored output's path, on whichever machine it was written
+static STORED_PATH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"[^\s]*{}[/\\]({}[A-Za-z0-9]+)\.txt",
+        STORE_DIR_NAME, REFERENCE_PREFIX
+    ))
+    .expect("valid stored output pattern")
+});
+
+/// The configured budget; 0 disables storing large outputs
+fn token_budget() -> usize {
+    Config::global()
+        .get_param::<usize>("GOOSE_TOOL_OUTPUT_TOKEN_BUDGET")
+        .unwrap_or(DEFAULT_TOOL_OUTPUT_TOKEN_BUDGET)
+}
+
+pub(crate) fn store_dir() -> PathBuf {
+    std::env::temp_dir().join(STORE_DIR_NAME)
+}
 
-const LARGE_TEXT_THRESHOLD: usize = 200_000;
+/// Whether `reference` names a stored output. References name files in the store, so they can't
+/// be allowed to point anywhere else.
+fn is_reference(reference: &str) -> bool {
+    reference
+        .strip_prefix(REFERENCE_PREFIX)
+        .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// The path a stored output is kept at, if `reference` is one
+pub(crate) fn stored_output_path(reference: &str) -> Option<PathBuf> {
+    is_reference(reference).then(|| store_dir().join(format!("{}.txt", reference)))
+}
+
+/// The references to stored outputs that `text` mentions
+pub(crate) fn output_references(text: &str) -> Vec<String> {
+    let mut references = Vec::new();
+    for (start, _) in text.match_indices(REFERENCE_PREFIX) {
+        let reference: String = text[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        if is_reference(&reference) && !references.contains(&reference) {
+            references.push(reference);
+        }
+    }
+    references
+}
+
+/// `text` with the paths of stored outputs pointing into this machine's store, for messages
+/// written where the store was somewhere else
+pub(crate) fn relocate_output_paths(text: &str) -> String {
+    STORED_PATH
+        .replace_all(text, |captures: &Captures| {
+            store_dir()
+                .join(format!("{}.txt", &captures[1]))
+                .display()
+                .to_string()
+        })
+        .into_owned()
+}
 
 /// Process tool response and handle large text content
 pub fn process_tool_response(
     response: Result<Vec<Content>, ToolError>,
 ) -> Result<Vec<Content>, ToolError> {
-    match response {
-        Ok(contents) => {
-            let mut processed_contents = Vec::new();
-
-            for content in contents {
-                match content.as_text() {
-                    Some(text_content) => {
-                        // Check if text exceeds threshold
-                        if text_content.text.chars().count() > LARGE_TEXT_THRESHOLD {
-                            // Write to temp file
-                            match write_large_text_to_file(&text_content.text) {
-                                Ok(file_path) => {
-                                    // Create a new text content with reference to the file
-                                    let message = format!(
-                                        "The response returned from the tool call was larger ({} characters) and is stored in the file which you can use other tools to examine or search in: {}",
-                                        text_content.text.chars().count(),
-                                        file_path
-                                    );
-                                    processed_contents.push(Content::text(message));
-                                }
-                                Err(e) => {
-                                    // If file writing fails, include original content with warning
-                                    let warning = format!(
-                                        "Warning: Failed to write large response to file: {}. Showing full content instead.\n\n{}",
-                                        e,
-                                        text_content.text
-                                    );
-                                    processed_contents.push(Content::text(warning));
-                                }
-                            }
-                        } else {
-                            // Keep original content for smaller texts
-                            processed_contents.push(content);
-                        }
-                    }
-                    None => {
-                        // Pass through other content types unchanged
-                        processed_contents.push(content);
-                    }
-                }
+    process_with_budget(response, token_budget(), &store_dir())
+}
+
+/// Replace text over `budget` tokens with a preview and a reference to the full output, which
+/// is written to `dir` and can be read with the read_more tool
+fn process_with_budget(
+    response: Result<Vec<Content>, ToolError>,
+    budget: usize,
+    dir: &Path,
+) -> Result<Vec<Content>, ToolError> {
+    let contents = response?;
+    if budget == 0 {
+        return Ok(contents);
+    }
+
+    let counter = TokenCounter::new();
+    let mut processed_contents = Vec::new();
+    for content in contents {
+        let Some(text_content) = content.as_text() else {
+            // Pass through other content types unchanged
+            processed_contents.push(content);
+            continue;
+        };
+        let text = &text_content.text;
+        // A token is at least one byte, so shorter text can't be over budget
+        if text.len() <= budget {
+            processed_contents.push(content);
+            continue;
+        }
+        let tokens = counter.count_tokens(text);
+        if tokens <= budget {
+            processed_contents.push(content);
+            continue;
+        }
+
+        match store_output(text, dir) {
+            Ok((reference, path)) => {
+                let preview = take_lines(&counter, text, 0, 0, PREVIEW_TOKENS.min(budget / 2));
+                processed_contents.push(Content::text(format!(
+                    "{}\n\n[The output was too large to show in full ({} lines, ~{} tokens); showing lines 1-{}. \
+                    To read further, call {} with reference \"{}\" and {}. \
+                    The full output is also saved at {} for searching with other tools.]",
+                    preview.text,
+                    preview.total_lines,
+                    tokens,
+                    preview.end,
+                    PLATFORM_READ_MORE_TOOL_NAME,
+                    reference,
+                    preview.next_position(),
+                    path.display()
+                )));
+            }
+            Err(e) => {
+                // If file writing fails, include original content with warning
+                let warning = format!(
+                    "Warning: Failed to write large response to file: {}. Showing full content instead.\n\n{}",
+                    e, text
+                );
+                processed_contents.push(Content::text(warning));
             }
+        }
+    }
+
+    Ok(processed_contents)
+}
+
+/// Write a large output to the store, returning its reference and path
+fn store_output(content: &str, dir: &Path) -> Result<(String, PathBuf), std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+    let reference = format!("{}{}", REFERENCE_PREFIX, uuid::Uuid::new_v4().simple());
+    let path = dir.join(format!("{}.txt", reference));
+    std::fs::write(&path, content)?;
+    Ok((reference, path))
+}
+
+/// A run of lines taken from a stored output
+struct Chunk {
+    text: String,
+    /// 1-based number of the last line included, or of the line before `start` if none were
+    end: usize,
+    /// When the last line was cut short, the 1-based character of it to continue from
+    cut_at: Option<usize>,
+    total_lines: usize,
+}
 
-            Ok(processed_contents)
+impl Chunk {
+    /// Where the next read starts, as read_more arguments
+    fn next_position(&self) -> String {
+        match self.cut_at {
+            Some(column) => format!("offset {} and column {}", self.end, column),
+            None => format!("offset {}", self.end + 1),
         }
-        Err(e) => Err(e),
     }
 }
 
-/// Write large text content to a temporary file
-fn write_large_text_to_file(content: &str) -> Result<String, std::io::Error> {
-    // Create temp directory if it doesn't exist
-    let temp_dir = std::env::temp_dir().join("goose_mcp_responses");
-    std::fs::create_dir_all(&temp_dir)?;
+/// Take whole lines starting at the 0-based line `start`, from its 0-based character `column`,
+/// until `budget` tokens are used. A first line that is over budget on its own is cut short
+/// rather than skipped, and the rest of it can be read by continuing from `cut_at`.
+fn take_lines(
+    counter: &TokenCounter,
+    text: &str,
+    start: usize,
+    column: usize,
+    budget: usize,
+) -> Chunk {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut taken = Vec::new();
+    let mut cut_at = None;
+    let mut used = 0;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let (line, line_column) = if index == start {
+            let skipped = line
+                .char_indices()
+                .nth(column)
+                .map_or(line.len(), |(i, _)| i);
+            (&line[skipped..], column)
+        } else {
+            (*line, 0)
+        };
+        let tokens = counter.count_tokens(line) + 1;
+        if used + tokens > budget {
+            if taken.is_empty() {
+                let max_chars = budget.saturating_mul(CHARS_PER_TOKEN).max(1);
+                let cut: String = line.chars().take(max_chars).collect();
+                if cut.len() < line.len() {
+                    let next = line_column + max_chars + 1;
+                    taken.push(format!("{}[... line cut at character {} ...]", cut, next));
+                    cut_at = Some(next);
+                } else {
+                    taken.push(cut);
+                }
+            }
+            break;
+        }
+        used += tokens;
+        taken.push(line.to_string());
+    }
 
-    // Generate a unique filename with timestamp
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("mcp_response_{}.txt", timestamp);
-    let file_path = temp_dir.join(&filename);
+    Chunk {
+        end: (start + taken.len()).min(lines.len()),
+        text: taken.join("\n"),
+        cut_at,
+        total_lines: lines.len(),
+    }
+}
 
-    // Write content to file
-    let mut file = File::create(&file_path)?;
-    file.write_all(content.as_bytes())?;
+/// Handle the read_more tool: return the next lines of a stored output
+pub fn read_more(arguments: Value) -> Result<Vec<Content>, ToolError> {
+    read_more_from(arguments, token_budget(), &store_dir())
+}
+
+fn read_more_from(arguments: Value, budget: usize, dir: &Path) -> Result<Vec<Content>, ToolError> {
+    let reference = arguments
+        .get("reference")
+        .and_then(Value::as_str)
+        .ok_or_else(|| ToolError::InvalidParameters("Missing 'reference' parameter".into()))?;
+    if !is_reference(reference) {
+        return Err(ToolError::InvalidParameters(format!(
+            "Unknown output reference '{}'",
+            reference
+        )));
+    }
+    let offset = arguments
+        .get("offset")
+        .and_then(Value::as_u64)
+        .unwrap_or(1)
+        .max(1) as usize;
+    let column = arguments
+        .get("column")
+        .and_then(Value::as_u64)
+        .unwrap_or(1)
+        .max(1) as usize;
+
+    let text = std::fs::read_to_string(dir.join(format!("{}.txt", reference))).map_err(|e| {
+        ToolError::ExecutionError(format!(
+            "Could not read stored output '{}': {}",
+            reference, e
+        ))
+    })?;
+
+    let budget = if budget == 0 {
+        DEFAULT_TOOL_OUTPUT_TOKEN_BUDGET
+    } else {
+        budget
+    };
+    let counter = TokenCounter::new();
+    let chunk = take_lines(&counter, &text, offset - 1, column - 1, budget / 2);
+    if offset > chunk.total_lines {
+        return Ok(vec![Content::text(format!(
+            "Offset {} is past the end of the output, which has {} lines.",
+            offset, chunk.total_lines
+        ))]);
+    }
 
-    Ok(file_path.to_string_lossy().to_string())
+    let footer = if chunk.end < chunk.total_lines || chunk.cut_at.is_some() {
+        format!(
+            "[Lines {}-{} of {}. Call {} with {} to continue.]",
+            offset,
+            chunk.end,
+            chunk.total_lines,
+            PLATFORM_READ_MORE_TOOL_NAME,
+            chunk.next_position()
+        )
+    } else {
+        format!(
+            "[Lines {}-{} of {}; this is the end of the output.]",
+            offset, chunk.end, chunk.total_lines
+        )
+    };
+    Ok(vec![Content::text(format!("{}\n\n{}", chunk.text, footer))])
 }
 
 #[cfg(test)]
@@ -81,150 +306,176 @@ mod tests {
     use super::*;
     use mcp_core::ToolError;
     use rmcp::model::Content;
-    use std::fs;
-    use std::path::Path;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const BUDGET: usize = 1_000;
+
+    fn numbered_lines(count: usize) -> String {
+        (1..=count)
+            .map(|i| format!("line {} of the build log", i))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn reference_in(text: &str) -> String {
+        let start = text.find(REFERENCE_PREFIX).expect("reference in message");
+        text[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect()
+    }
 
     #[test]
     fn test_small_text_response_passes_through() {
-        // Create a small text response
+        let dir = TempDir::new().unwrap();
         let small_text = "This is a small text response";
-        let content = Content::text(small_text.to_string());
+        let response = Ok(vec![Content::text(small_text.to_string())]);
 
-        let response = Ok(vec![content]);
+        let processed = process_with_budget(response, BUDGET, dir.path()).unwrap();
 
-        // Process the response
-        let processed = process_tool_response(response).unwrap();
-
-        // Verify the response is unchanged
         assert_eq!(processed.len(), 1);
-        if let Some(text_content) = processed[0].as_text() {
-            assert_eq!(text_content.text, small_text);
-        } else {
-            panic!("Expected text content");
-        }
+        assert_eq!(processed[0].as_text().unwrap().text, small_text);
     }
 
     #[test]
-    fn test_large_text_response_redirected_to_file() {
-        // Create a text larger than the threshold
-        let large_text = "a".repeat(LARGE_TEXT_THRESHOLD + 1000);
-        let content = Content::text(large_text.clone());
+    fn test_large_text_response_is_previewed_and_stored() {
+        let dir = TempDir::new().unwrap();
+        let large_text = numbered_lines(2_000);
+        let response = Ok(vec![Content::text(large_text.clone())]);
 
-        let response = Ok(vec![content]);
+        let processed = process_with_budget(response, BUDGET, dir.path()).unwrap();
 
-        // Process the response
-        let processed = process_tool_response(response).unwrap();
-
-        // Verify the response contains a message about the file
         assert_eq!(processed.len(), 1);
-        if let Some(text_content) = processed[0].as_text() {
-            assert!(text_content
-                .text
-                .contains("The response returned from the tool call was larger"));
-            assert!(text_content.text.contains("characters"));
-
-            // Extract the file path from the message
-            if let Some(file_path) = text_content.text.split("stored in the file: ").nth(1) {
-                // Verify the file exists and contains the original text
-                let path = Path::new(file_path.trim());
-                if path.exists() {
-                    // Only check content if file exists (may not exist in CI environments)
-                    if let Ok(file_content) = fs::read_to_string(path) {
-                        assert_eq!(file_content, large_text);
-                    }
-
-                    // Clean up the file
-                    let _ = fs::remove_file(path); // Ignore errors on cleanup
-                }
-            }
-        } else {
-            panic!("Expected text content");
-        }
+        let text = &processed[0].as_text().unwrap().text;
+        assert!(text.starts_with("line 1 of the build log\nline 2 of the build log"));
+        assert!(!text.contains("line 2000 of"));
+        assert!(text.contains(PLATFORM_READ_MORE_TOOL_NAME));
+        assert!(TokenCounter::new().count_tokens(text) < BUDGET);
+
+        let reference = reference_in(text);
+        let stored = std::fs::read_to_string(dir.path().join(format!("{}.txt", reference)));
+        assert_eq!(stored.unwrap(), large_text);
     }
 
     #[test]
-    fn test_image_content_passes_through() {
-        // Create an image content
-        let image_content = Content::image("base64data".to_string(), "image/png".to_string());
+    fn test_read_more_pages_through_output() {
+        let dir = TempDir::new().unwrap();
+        let response = Ok(vec![Content::text(numbered_lines(2_000))]);
+        let processed = process_with_budget(response, BUDGET, dir.path()).unwrap();
+        let reference = reference_in(&processed[0].as_text().unwrap().text);
+
+        let page = read_more_from(
+            json!({"reference": reference, "offset": 100}),
+            BUDGET,
+            dir.path(),
+        )
+        .unwrap();
+        let text = &page[0].as_text().unwrap().text;
+        assert!(text.starts_with("line 100 of the build log\n"));
+        assert!(text.contains("to continue"));
+
+        let end = read_more_from(
+            json!({"reference": reference, "offset": 1_999}),
+            BUDGET,
+            dir.path(),
+        )
+        .unwrap();
+        let text = &end[0].as_text().unwrap().text;
+        assert!(text.contains("line 2000 of the build log"));
+        assert!(text.contains("end of the output"));
+    }
 
-        let response = Ok(vec![image_content]);
+    #[test]
+    fn test_read_more_rejects_paths() {
+        let dir = TempDir::new().unwrap();
+        for reference in ["../../etc/passwd", "output_../secret", "notes"] {
+            let result = read_more_from(json!({ "reference": reference }), BUDGET, dir.path());
+            assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+        }
+    }
 
-        // Process the response
-        let processed = process_tool_response(response).unwrap();
+    #[test]
+    fn test_single_long_line_is_cut() {
+        let dir = TempDir::new().unwrap();
+        let minified = "{\"key\": \"value\"}, ".repeat(2_000);
+        let response = Ok(vec![Content::text(minified)]);
+
+        let processed = process_with_budget(response, BUDGET, dir.path()).unwrap();
+        let text = &processed[0].as_text().unwrap().text;
+        assert!(text.contains("line cut at"));
+        assert!(text.len() < 4 * BUDGET);
+        assert!(text.contains("offset 1 and column 2001"));
+    }
 
-        // Verify the response is unchanged
-        assert_eq!(processed.len(), 1);
-        if let Some(img) = processed[0].as_image() {
-            assert_eq!(img.data, "base64data");
-            assert_eq!(img.mime_type, "image/png");
-        } else {
-            panic!("Expected image content");
+    #[test]
+    fn test_read_more_pages_within_a_long_line() {
+        let dir = TempDir::new().unwrap();
+        let long_line: String = (0..20_000)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+        let response = Ok(vec![Content::text(format!("{}\nlast line", long_line))]);
+        let processed = process_with_budget(response, BUDGET, dir.path()).unwrap();
+        let reference = reference_in(&processed[0].as_text().unwrap().text);
+
+        let mut column = 1;
+        let mut read = String::new();
+        loop {
+            let page = read_more_from(
+                json!({"reference": reference, "offset": 1, "column": column}),
+                BUDGET,
+                dir.path(),
+            )
+            .unwrap();
+            let text = &page[0].as_text().unwrap().text;
+            let Some(start) = text.find("column ") else {
+                read.push_str(text.split('\n').next().unwrap());
+                break;
+            };
+            read.push_str(&text[..text.find("[... line cut").unwrap()]);
+            column = text[start + "column ".len()..]
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap();
         }
+        assert_eq!(read, long_line);
     }
 
     #[test]
-    fn test_mixed_content_handled_correctly() {
-        // Create a response with mixed content types
-        let small_text = Content::text("Small text");
-        let large_text = Content::text("a".repeat(LARGE_TEXT_THRESHOLD + 1000));
-        let image = Content::image("image_data".to_string(), "image/jpeg".to_string());
-
-        let response = Ok(vec![small_text, large_text, image]);
+    fn test_image_content_passes_through() {
+        let dir = TempDir::new().unwrap();
+        let image_content = Content::image("base64data".to_string(), "image/png".to_string());
 
-        // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_with_budget(Ok(vec![image_content]), BUDGET, dir.path()).unwrap();
 
-        // Verify each item is handled correctly
-        assert_eq!(processed.len(), 3);
+        assert_eq!(processed.len(), 1);
+        let img = processed[0].as_image().unwrap();
+        assert_eq!(img.data, "base64data");
+        assert_eq!(img.mime_type, "image/png");
+    }
 
-        // First item should be unchanged small text
-        if let Some(text_content) = processed[0].as_text() {
-            assert_eq!(text_content.text, "Small text");
-        } else {
-            panic!("Expected text content");
-        }
+    #[test]
+    fn test_zero_budget_disables_storing() {
+        let dir = TempDir::new().unwrap();
+        let large_text = numbered_lines(2_000);
 
-        // Second item should be a message about the file
-        if let Some(text_content) = processed[1].as_text() {
-            assert!(text_content
-                .text
-                .contains("The response returned from the tool call was larger"));
-
-            // Extract the file path and clean up
-            if let Some(file_path) = text_content.text.split("stored in the file: ").nth(1) {
-                let path = Path::new(file_path.trim());
-                if path.exists() {
-                    let _ = fs::remove_file(path); // Ignore errors on cleanup
-                }
-            }
-        } else {
-            panic!("Expected text content");
-        }
+        let processed =
+            process_with_budget(Ok(vec![Content::text(large_text.clone())]), 0, dir.path())
+                .unwrap();
 
-        // Third item should be unchanged image
-        if let Some(img) = processed[2].as_image() {
-            assert_eq!(img.data, "image_data");
-            assert_eq!(img.mime_type, "image/jpeg");
-        } else {
-            panic!("Expected image content");
-        }
+        assert_eq!(processed[0].as_text().unwrap().text, large_text);
     }
 
     #[test]
     fn test_error_response_passes_through() {
-        // Create an error response
-        let error = ToolError::ExecutionError("Test error".to_string());
-        let response: Result<Vec<Content>, ToolError> = Err(error);
-
-        // Process the response
-        let processed = process_tool_response(response);
-
-        // Verify the error is passed through unchanged
-        assert!(processed.is_err());
-        match processed {
-            Err(ToolError::ExecutionError(msg)) => {
-                assert_eq!(msg, "Test error");
-            }
+        let dir = TempDir::new().unwrap();
+        let response: Result<Vec<Content>, ToolError> =
+            Err(ToolError::ExecutionError("Test error".to_string()));
+
+        match process_with_budget(response, BUDGET, dir.path()) {
+            Err(ToolError::ExecutionError(msg)) => assert_eq!(msg, "Test error"),
             _ => panic!("Expected execution error"),
         }
     }
@@ -1,17 +1,28 @@
 mod agent;
+pub mod confidence;
 mod context;
+mod continuation;
+pub mod credentials;
+pub mod dispatch;
+pub mod editor;
 pub mod extension;
 pub mod extension_manager;
 pub mod final_output_tool;
-mod large_response_handler;
+pub mod freshness;
+pub mod injection_guard;
+pub(crate) mod large_response_handler;
+pub mod plan;
 pub mod platform_tools;
 pub mod prompt_manager;
 mod recipe_tools;
+pub mod refusal;
 mod reply_parts;
 pub mod retry;
+pub mod room;
 mod router_tool_selector;
 mod router_tools;
 mod schedule_tool;
+pub mod secret_guard;
 pub mod sub_recipe_manager;
 pub mod subagent;
 pub mod subagent_execution_tool;
@@ -21,7 +32,9 @@ mod tool_execution;
 mod tool_route_manager;
 mod tool_router_index_manager;
 pub(crate) mod tool_vectordb;
+pub mod trust_policy;
 pub mod types;
+mod warm_up;
 
 pub use agent::{Agent, AgentEvent};
 pub use extension::ExtensionConfig;
@@ -29,4 +42,5 @@ pub use extension_manager::ExtensionManager;
 pub use prompt_manager::PromptManager;
 pub use subagent::{SubAgent, SubAgentProgress, SubAgentStatus};
 pub use subagent_task_config::TaskConfig;
-pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck};
+pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck, ToolResultOrdering};
+pub use warm_up::WarmUpReport;
@@ -0,0 +1,125 @@
+//! Structured plans for plan-then-execute mode.
+//!
+//! In plan mode the planner model answers with either clarifying questions or a plan as a JSON
+//! block: the goal, the context the executor needs, and numbered steps naming the tools each is
+//! expected to use and the files it will touch. The user approves or edits the plan before the
+//! agent carries it out. With `GOOSE_PLAN_FIRST` set, every request is planned this way.
+
+use serde::{Deserialize, Serialize};
+
+/// Config key that puts new sessions in plan mode
+pub const PLAN_FIRST_KEY: &str = "GOOSE_PLAN_FIRST";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub description: String,
+    /// Tools the step is expected to call
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Files the step will create, change or delete
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub goal: String,
+    /// Background the executor needs, since it won't see the planning conversation
+    #[serde(default)]
+    pub context: String,
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    /// The plan in a planner response, from its first JSON block or the whole text
+    pub fn parse(text: &str) -> Option<Plan> {
+        let candidates = text
+            .split("```")
+            .skip(1)
+            .step_by(2)
+            .map(|block| block.strip_prefix("json").unwrap_or(block))
+            .chain(std::iter::once(text));
+        candidates
+            .filter_map(|candidate| serde_json::from_str::<Plan>(candidate.trim()).ok())
+            .find(|plan| !plan.steps.is_empty())
+    }
+
+    /// Every file the plan touches, in order of first mention
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = Vec::new();
+        for file in self.steps.iter().flat_map(|step| &step.files) {
+            if !files.contains(&file.as_str()) {
+                files.push(file);
+            }
+        }
+        files
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Plan: {}\n", self.goal);
+        if !self.context.trim().is_empty() {
+            out.push_str(&format!("\n{}\n", self.context.trim()));
+        }
+        out.push_str("\n## Steps\n");
+        for (index, step) in self.steps.iter().enumerate() {
+            out.push_str(&format!("\n{}. {}\n", index + 1, step.description));
+            if !step.tools.is_empty() {
+                out.push_str(&format!("   - Tools: {}\n", step.tools.join(", ")));
+            }
+            if !step.files.is_empty() {
+                out.push_str(&format!("   - Files: {}\n", step.files.join(", ")));
+            }
+        }
+        let files = self.files();
+        if !files.is_empty() {
+            out.push_str(&format!("\n## Files to touch\n\n{}\n", files.join("\n")));
+        }
+        out
+    }
+}
+
+/// The message that starts carrying out a plan the user approved, possibly after editing it
+pub fn execution_message(plan: &str) -> String {
+    format!(
+        "Carry out this plan, which I have reviewed and approved. Follow the steps in order, and \
+         stop to ask me before doing anything the plan doesn't cover, such as touching files it \
+         doesn't list.\n\n{}",
+        plan.trim()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plan_from_planner_response() {
+        let response = r#"Here is the plan:
+
+```json
+{
+  "goal": "Add a --json flag to the list command",
+  "context": "The CLI uses clap; output goes through src/output.rs.",
+  "steps": [
+    {"description": "Add the flag", "tools": ["developer__text_editor"], "files": ["src/cli.rs"]},
+    {"description": "Print JSON when it is set", "files": ["src/output.rs", "src/cli.rs"]},
+    {"description": "Run the tests", "tools": ["developer__shell"]}
+  ]
+}
+```"#;
+        let plan = Plan::parse(response).unwrap();
+        assert_eq!(plan.steps.len(), 3);
+        assert!(plan.steps[1].tools.is_empty());
+        assert_eq!(plan.files(), vec!["src/cli.rs", "src/output.rs"]);
+
+        let markdown = plan.to_markdown();
+        assert!(markdown.starts_with("# Plan: Add a --json flag to the list command"));
+        assert!(markdown.contains("1. Add the flag\n   - Tools: developer__text_editor\n"));
+        assert!(markdown.contains("## Files to touch\n\nsrc/cli.rs\nsrc/output.rs\n"));
+
+        assert_eq!(
+            Plan::parse("1. What database should this use?\n2. Is auth needed?"),
+            None
+        );
+    }
+}
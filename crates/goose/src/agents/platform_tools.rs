@@ -8,6 +8,11 @@ pub const PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME: &str =
     "platform__search_available_extensions";
 pub const PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME: &str = "platform__manage_extensions";
 pub const PLATFORM_MANAGE_SCHEDULE_TOOL_NAME: &str = "platform__manage_schedule";
+pub const PLATFORM_REQUEST_CREDENTIAL_TOOL_NAME: &str = "platform__request_credential";
+pub const PLATFORM_READ_MORE_TOOL_NAME: &str = "platform__read_more";
+pub const PLATFORM_GET_CURRENT_TIME_TOOL_NAME: &str = "platform__get_current_time";
+pub const PLATFORM_CONVERT_TIMEZONE_TOOL_NAME: &str = "platform__convert_timezone";
+pub const PLATFORM_LOAD_TOOL_TOOL_NAME: &str = "platform__load_tool";
 
 pub fn read_resource_tool() -> Tool {
     Tool::new(
@@ -154,3 +159,139 @@ pub fn manage_schedule_tool() -> Tool {
         open_world_hint: Some(false),
     })
 }
+
+pub fn request_credential_tool() -> Tool {
+    Tool::new(
+        PLATFORM_REQUEST_CREDENTIAL_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Ask the user for a secret such as a password, API key or token.
+
+            The user enters it privately: you never see the value, and it is not added to the
+            conversation. It becomes available to shell commands as the environment variable
+            you name, so refer to it as $NAME in commands. Never ask the user to paste secrets
+            into the chat; use this tool instead.
+        "#}
+        .to_string(),
+        object!({
+            "type": "object",
+            "required": ["name", "prompt"],
+            "properties": {
+                "name": {"type": "string", "description": "Environment variable to expose the secret as, e.g. GITHUB_TOKEN", "pattern": "^[A-Z_][A-Z0-9_]*$"},
+                "prompt": {"type": "string", "description": "What the secret is for, shown to the user"}
+            }
+        }),
+    ).annotate(ToolAnnotations {
+        title: Some("Request a credential".to_string()),
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(false),
+        open_world_hint: Some(false),
+    })
+}
+
+pub fn read_more_tool() -> Tool {
+    Tool::new(
+        PLATFORM_READ_MORE_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Read more of a tool output that was too large to show in full.
+
+            Large outputs are replaced by their first lines and a reference. Pass that reference
+            and the line to start from to get the next part of the output; a line too long to
+            show at once is continued from the column given. Prefer narrowing the original
+            command (e.g. with grep or head) when you only need part of the output.
+        "#}
+        .to_string(),
+        object!({
+            "type": "object",
+            "required": ["reference"],
+            "properties": {
+                "reference": {"type": "string", "description": "The reference given in the truncated output, e.g. output_1a2b3c"},
+                "offset": {"type": "integer", "description": "Line to start reading from, 1-based", "minimum": 1},
+                "column": {"type": "integer", "description": "Character of that line to start from, 1-based, for continuing a line that was cut short", "minimum": 1}
+            }
+        }),
+    ).annotate(ToolAnnotations {
+        title: Some("Read more of a large output".to_string()),
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    })
+}
+
+pub fn get_current_time_tool() -> Tool {
+    Tool::new(
+        PLATFORM_GET_CURRENT_TIME_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Get the current date and time, in the user's timezone or another one.
+
+            The system prompt gives the time at the start of the turn; use this tool when you
+            need the exact time now or the time somewhere else.
+        "#}
+        .to_string(),
+        object!({
+            "type": "object",
+            "properties": {
+                "timezone": {"type": "string", "description": "IANA timezone such as America/New_York; defaults to the user's timezone"}
+            }
+        }),
+    ).annotate(ToolAnnotations {
+        title: Some("Get the current time".to_string()),
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(false),
+        open_world_hint: Some(false),
+    })
+}
+
+pub fn convert_timezone_tool() -> Tool {
+    Tool::new(
+        PLATFORM_CONVERT_TIMEZONE_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Convert a date and time from one timezone to another, accounting for daylight saving.
+
+            Accepts "2026-03-01 09:30", a time of day such as "09:30" (meaning today), or an
+            RFC 3339 timestamp, which carries its own offset.
+        "#}
+        .to_string(),
+        object!({
+            "type": "object",
+            "required": ["time", "to"],
+            "properties": {
+                "time": {"type": "string", "description": "The date and time to convert"},
+                "from": {"type": "string", "description": "IANA timezone the time is in; defaults to the user's timezone"},
+                "to": {"type": "string", "description": "IANA timezone to convert to, e.g. Asia/Tokyo"}
+            }
+        }),
+    ).annotate(ToolAnnotations {
+        title: Some("Convert between timezones".to_string()),
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    })
+}
+
+pub fn load_tool_tool() -> Tool {
+    Tool::new(
+        PLATFORM_LOAD_TOOL_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Load a tool so you can call it. Only one tool is loaded at a time; loading another
+            replaces it. Pick the tool from the list in the system prompt.
+        "#}
+        .to_string(),
+        object!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string", "description": "The tool's full name, e.g. developer__shell"}
+            }
+        }),
+    ).annotate(ToolAnnotations {
+        title: Some("Load a tool".to_string()),
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    })
+}
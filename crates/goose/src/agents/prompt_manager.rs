@@ -1,17 +1,17 @@
-use chrono::Utc;
 use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::agents::extension::ExtensionInfo;
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
 use crate::agents::router_tools::{llm_search_tool_prompt, vector_search_tool_prompt};
+use crate::clock;
+use crate::model::ModelConfig;
 use crate::providers::base::get_current_model;
 use crate::{config::Config, prompt_template};
 
 pub struct PromptManager {
     system_prompt_override: Option<String>,
     system_prompt_extras: Vec<String>,
-    current_date_timestamp: String,
 }
 
 impl Default for PromptManager {
@@ -25,8 +25,6 @@ impl PromptManager {
         PromptManager {
             system_prompt_override: None,
             system_prompt_extras: Vec::new(),
-            // Use the fixed current date time so that prompt cache can be used.
-            current_date_timestamp: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         }
     }
 
@@ -101,10 +99,12 @@ impl PromptManager {
             None => {}
         }
 
-        context.insert(
-            "current_date_time",
-            Value::String(self.current_date_timestamp.clone()),
-        );
+        // The prompt is built once per turn, so tool calls within a turn still share the
+        // prompt cache while the model's sense of time stays current
+        context.insert("current_date_time", Value::String(clock::now_for_prompt()));
+        if let Some(locale) = clock::user_locale() {
+            context.insert("user_locale", Value::String(locale));
+        }
 
         // Add the suggestion about disabling extensions if flag is true
         context.insert(
@@ -115,6 +115,12 @@ impl PromptManager {
         // First check the global store, and only if it's not available, fall back to the provided model_name
         let model_to_use: Option<String> =
             get_current_model().or_else(|| model_name.map(|s| s.to_string()));
+        if let Some(cutoff) = model_to_use
+            .as_deref()
+            .and_then(ModelConfig::knowledge_cutoff)
+        {
+            context.insert("knowledge_cutoff", Value::String(cutoff));
+        }
 
         // Conditionally load the override prompt or the global system prompt
         let base_prompt = if let Some(override_prompt) = &self.system_prompt_override {
@@ -160,6 +166,34 @@ impl PromptManager {
         }
     }
 
+    /// Build the compact system prompt used in constrained mode, which lists tools by name
+    /// instead of describing extensions. An override prompt is still used in full.
+    pub fn build_compact_system_prompt(&self, tool_index: String) -> String {
+        let mut context: HashMap<&str, Value> = HashMap::new();
+        context.insert("current_date_time", Value::String(clock::now_for_prompt()));
+        if let Some(locale) = clock::user_locale() {
+            context.insert("user_locale", Value::String(locale));
+        }
+        context.insert("tool_index", Value::String(tool_index));
+
+        let base_prompt = match &self.system_prompt_override {
+            Some(override_prompt) => prompt_template::render_inline_once(override_prompt, &context)
+                .expect("Prompt should render"),
+            None => prompt_template::render_global_file("system_compact.md", &context)
+                .expect("Prompt should render"),
+        };
+
+        if self.system_prompt_extras.is_empty() {
+            base_prompt
+        } else {
+            format!(
+                "{}\n\n{}",
+                base_prompt,
+                self.system_prompt_extras.join("\n\n")
+            )
+        }
+    }
+
     pub async fn get_recipe_prompt(&self) -> String {
         let context: HashMap<&str, Value> = HashMap::new();
         prompt_template::render_global_file("recipe.md", &context).expect("Prompt should render")
@@ -170,6 +204,16 @@ impl PromptManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_compact_system_prompt_lists_tools() {
+        let manager = PromptManager::new();
+        let prompt = manager
+            .build_compact_system_prompt("- developer__shell: Execute a command".to_string());
+        assert!(prompt.contains("- developer__shell: Execute a command"));
+        assert!(prompt.contains("platform__load_tool"));
+        assert!(!prompt.contains("# Extensions"));
+    }
+
     #[test]
     fn test_normalize_model_name() {
         assert_eq!(PromptManager::normalize_model_name("gpt-4.1"), "gpt_4_1");
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::conversation::message::{Message, StopReason};
+use crate::model::ModelConfig;
+use crate::prompt_template::render_inline_once;
+use crate::providers::base::Provider;
+
+/// Template used to rephrase a refused request when no custom template is configured. It only
+/// asks the model to look again; it makes no claims about the user that nothing has verified.
+pub const DEFAULT_REPHRASE_TEMPLATE: &str = "Your previous reply declined this request. In case it was misread, here it is again. If you still won't do some or all of it, say which part and why, so I can clarify or change the request.\n\nOriginal request:\n{{ request }}";
+
+/// Shown to the user when a refusal is not (or can no longer be) recovered automatically
+pub const REFUSAL_MESSAGE: &str =
+    "The model declined to respond to this request. You can rephrase it or switch to a different model.";
+
+/// How many automatic recoveries are attempted for a single reply
+pub const MAX_REFUSAL_RECOVERIES: u32 = 1;
+
+/// Phrases that open a refusal when the provider does not report one as a finish reason
+const REFUSAL_PREFIXES: &[&str] = &[
+    "i can't help with",
+    "i cannot help with",
+    "i can't assist with",
+    "i cannot assist with",
+    "i'm sorry, but i can't",
+    "i'm sorry, but i cannot",
+    "i am sorry, but i cannot",
+    "i won't be able to help with",
+    "i'm not able to help with",
+    "i must decline",
+];
+
+/// Refusals are short; longer text starting with an apology is usually a real answer
+const MAX_REFUSAL_TEXT_LEN: usize = 400;
+
+/// What the agent does after the model declines a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "action")]
+pub enum RefusalPolicy {
+    /// Tell the user and end the reply
+    Surface,
+    /// Re-send the request rendered through a template (`{{ request }}` is the original text)
+    Rephrase { template: String },
+    /// Retry the request against another model of the same provider
+    Fallback { model: String },
+}
+
+impl RefusalPolicy {
+    /// Read the policy from `GOOSE_REFUSAL_POLICY` (`surface`, `rephrase` or `fallback`)
+    ///
+    /// `rephrase` uses `GOOSE_REFUSAL_REPHRASE_TEMPLATE` when set, and `fallback` requires
+    /// `GOOSE_REFUSAL_FALLBACK_MODEL`; anything missing or unrecognised falls back to `surface`.
+    pub fn from_config(config: &Config) -> Self {
+        let policy: String = config
+            .get_param("GOOSE_REFUSAL_POLICY")
+            .unwrap_or_else(|_| "surface".to_string());
+
+        match policy.to_lowercase().as_str() {
+            "rephrase" => RefusalPolicy::Rephrase {
+                template: config
+                    .get_param("GOOSE_REFUSAL_REPHRASE_TEMPLATE")
+                    .unwrap_or_else(|_| DEFAULT_REPHRASE_TEMPLATE.to_string()),
+            },
+            "fallback" => match config.get_param::<String>("GOOSE_REFUSAL_FALLBACK_MODEL") {
+                Ok(model) => RefusalPolicy::Fallback { model },
+                Err(_) => {
+                    tracing::warn!(
+                        "GOOSE_REFUSAL_POLICY is 'fallback' but GOOSE_REFUSAL_FALLBACK_MODEL is not set"
+                    );
+                    RefusalPolicy::Surface
+                }
+            },
+            _ => RefusalPolicy::Surface,
+        }
+    }
+
+    /// Build the follow-up user message for the rephrase policy
+    pub fn rephrase(template: &str, request: &str) -> String {
+        let context = serde_json::json!({ "request": request });
+        render_inline_once(template, &context).unwrap_or_else(|e| {
+            tracing::warn!("Failed to render refusal rephrase template: {}", e);
+            request.to_string()
+        })
+    }
+}
+
+/// Emitted whenever the model declines a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefusalEvent {
+    /// The model that declined
+    pub model: String,
+    /// The normalized finish reason, if the provider reported one
+    pub stop_reason: Option<StopReason>,
+    /// The refusal text returned by the model
+    pub text: String,
+    /// The recovery the agent is attempting, if any
+    pub recovery: Option<RefusalPolicy>,
+}
+
+/// Whether a model response declines the request, either by finish reason or by its wording
+pub fn is_refusal(stop_reason: Option<StopReason>, text: &str) -> bool {
+    if stop_reason.is_some_and(|r| r.is_refusal()) {
+        return true;
+    }
+
+    let text = text.trim();
+    if text.is_empty() || text.len() > MAX_REFUSAL_TEXT_LEN {
+        return false;
+    }
+
+    let lowered = text.to_lowercase().replace('’', "'");
+    REFUSAL_PREFIXES
+        .iter()
+        .any(|prefix| lowered.starts_with(prefix))
+}
+
+/// The text of the most recent user message, which is what gets rephrased
+pub fn last_user_request(messages: &[Message]) -> Option<String> {
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == rmcp::model::Role::User && !m.as_concat_text().is_empty())
+        .map(|m| m.as_concat_text())
+}
+
+/// Create a provider for the refusal fallback model, served by the configured provider
+pub fn create_fallback_provider(model: &str) -> Result<Arc<dyn Provider>> {
+    let provider_name: String = Config::global().get_param("GOOSE_PROVIDER")?;
+    let model_config = ModelConfig::new(model)?;
+    crate::providers::create(&provider_name, model_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refusal_from_stop_reason() {
+        assert!(is_refusal(Some(StopReason::Refusal), "anything"));
+        assert!(is_refusal(Some(StopReason::ContentFilter), ""));
+        assert!(!is_refusal(Some(StopReason::Stop), "Here is the answer."));
+    }
+
+    #[test]
+    fn test_refusal_from_text() {
+        assert!(is_refusal(
+            Some(StopReason::Stop),
+            "I’m sorry, but I can’t help with that."
+        ));
+        assert!(is_refusal(None, "I cannot assist with this request."));
+        assert!(!is_refusal(None, "I can help with that! First, ..."));
+
+        let long_answer = format!("I'm sorry, but I cannot stress enough {}", "x".repeat(500));
+        assert!(!is_refusal(None, &long_answer));
+    }
+
+    #[test]
+    fn test_rephrase_template() {
+        let rephrased = RefusalPolicy::rephrase("Please retry: {{ request }}", "delete temp files");
+        assert_eq!(rephrased, "Please retry: delete temp files");
+
+        let rephrased = RefusalPolicy::rephrase(DEFAULT_REPHRASE_TEMPLATE, "delete temp files");
+        assert!(rephrased.ends_with("delete temp files"));
+    }
+
+    #[test]
+    fn test_last_user_request() {
+        let messages = vec![
+            Message::user().with_text("first"),
+            Message::assistant().with_text("reply"),
+            Message::user().with_text("second"),
+            Message::assistant().with_text("I can't help with that."),
+        ];
+        assert_eq!(last_user_request(&messages), Some("second".to_string()));
+    }
+}
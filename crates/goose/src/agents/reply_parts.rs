@@ -6,27 +6,36 @@ use async_stream::try_stream;
 use futures::stream::StreamExt;
 
 use super::super::agents::Agent;
+use crate::agents::extension::ExtensionInfo;
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
+use crate::context_mgmt::budget::{tools_left_out_note, TokenBudget};
+use crate::context_mgmt::constrained;
 use crate::conversation::message::{Message, MessageContent, ToolRequest};
 use crate::conversation::Conversation;
-use crate::providers::base::{stream_from_single_message, MessageStream, Provider, ProviderUsage};
+use crate::model::ModelConfig;
+use crate::providers::base::{MessageStream, Provider, ProviderUsage, ToolChoice};
+use crate::providers::delta::{messages_from_deltas, DeltaAccumulator};
 use crate::providers::errors::ProviderError;
 use crate::providers::toolshim::{
-    augment_message_with_tool_calls, convert_tool_messages_to_text,
-    modify_system_prompt_for_tool_json, OllamaInterpreter,
+    augment_message_with_tool_calls, convert_tool_messages_to_text, create_interpreter,
+    modify_system_prompt_for_tool_json,
 };
+use crate::providers::vision::prepare_messages;
 use crate::session;
+use crate::session::diff::ContextSnapshot;
+use crate::token_counter::create_async_token_counter_for_model;
 use rmcp::model::Tool;
 
 async fn toolshim_postprocess(
     response: Message,
     toolshim_tools: &[Tool],
+    model_config: &ModelConfig,
 ) -> Result<Message, ProviderError> {
-    let interpreter = OllamaInterpreter::new().map_err(|e| {
-        ProviderError::ExecutionError(format!("Failed to create OllamaInterpreter: {}", e))
+    let interpreter = create_interpreter(model_config).map_err(|e| {
+        ProviderError::ExecutionError(format!("Failed to create tool interpreter: {}", e))
     })?;
 
-    augment_message_with_tool_calls(&interpreter, response, toolshim_tools)
+    augment_message_with_tool_calls(interpreter.as_ref(), response, toolshim_tools)
         .await
         .map_err(|e| ProviderError::ExecutionError(format!("Failed to augment message: {}", e)))
 }
@@ -60,21 +69,65 @@ impl Agent {
 
         // Prepare system prompt
         let extension_manager = self.extension_manager.read().await;
-        let extensions_info = extension_manager.get_extensions_info().await;
+        let mut extensions_info = extension_manager.get_extensions_info().await;
 
         // Get model name from provider
         let provider = self.provider().await?;
         let model_config = provider.get_model_config();
         let model_name = &model_config.model_name;
 
-        let prompt_manager = self.prompt_manager.lock().await;
-        let mut system_prompt = prompt_manager.build_system_prompt(
-            extensions_info,
-            self.frontend_instructions.lock().await.clone(),
-            extension_manager.suggest_disable_extensions_prompt().await,
-            Some(model_name),
-            tool_selection_strategy,
-        );
+        let mut system_prompt = if constrained::is_constrained(&model_config) {
+            // Small models get a compact prompt and see one tool schema at a time
+            let tool_index = constrained::tool_index(&tools);
+            let loaded_tool = self.loaded_tool.lock().await.clone();
+            tools = constrained::constrained_tools(tools, loaded_tool.as_deref());
+            self.prompt_manager
+                .lock()
+                .await
+                .build_compact_system_prompt(tool_index)
+        } else {
+            // Keep each part of the request within the configured token budget
+            let budget = TokenBudget::from_config();
+            let mut tools_left_out = Vec::new();
+            let counter = if budget.is_unlimited() {
+                None
+            } else {
+                Some(
+                    create_async_token_counter_for_model(model_name)
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?,
+                )
+            };
+            if let Some(counter) = &counter {
+                budget.fit_memory(counter, &mut extensions_info);
+                (tools, tools_left_out) = budget.fit_tools(counter, tools);
+            }
+
+            let prompt_manager = self.prompt_manager.lock().await;
+            let frontend_instructions = self.frontend_instructions.lock().await.clone();
+            let suggest_disable = extension_manager.suggest_disable_extensions_prompt().await;
+            let build = |extensions_info: Vec<ExtensionInfo>| {
+                prompt_manager.build_system_prompt(
+                    extensions_info,
+                    frontend_instructions.clone(),
+                    suggest_disable.clone(),
+                    Some(model_name),
+                    tool_selection_strategy.clone(),
+                )
+            };
+            let mut system_prompt = match &counter {
+                Some(counter) => budget.fit_system_prompt(counter, extensions_info, build),
+                None => build(extensions_info),
+            };
+            if !tools_left_out.is_empty() {
+                system_prompt = format!(
+                    "{}\n\n{}",
+                    system_prompt,
+                    tools_left_out_note(&tools_left_out)
+                );
+            }
+            system_prompt
+        };
 
         // Handle toolshim if enabled
         let mut toolshim_tools = vec![];
@@ -138,13 +191,13 @@ impl Agent {
         crate::providers::base::set_current_model(&usage.model);
 
         if config.toolshim {
-            response = toolshim_postprocess(response, toolshim_tools).await?;
+            response = toolshim_postprocess(response, toolshim_tools, &config).await?;
         }
 
         Ok((response, usage))
     }
 
-    /// Stream a response from the LLM provider.
+    /// Stream a response from the LLM provider, constrained to the turn's tool choice.
     /// Handles toolshim transformations if needed
     pub(crate) async fn stream_response_from_provider(
         provider: Arc<dyn Provider>,
@@ -152,6 +205,7 @@ impl Agent {
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
+        tool_choice: &ToolChoice,
     ) -> Result<MessageStream, ProviderError> {
         let config = provider.get_model_config();
 
@@ -161,44 +215,46 @@ impl Agent {
         } else {
             Conversation::new_unvalidated(messages.to_vec())
         };
+        // Fit images to the provider, or explain why it can't take them
+        let messages_for_provider = Conversation::new_unvalidated(prepare_messages(
+            provider.as_ref(),
+            messages_for_provider.messages(),
+        )?);
+
+        let mut deltas = provider
+            .stream_deltas(
+                system_prompt,
+                messages_for_provider.messages(),
+                tools,
+                tool_choice,
+            )
+            .await?;
 
-        // Clone owned data to move into the async stream
-        let system_prompt = system_prompt.to_owned();
-        let tools = tools.to_owned();
-        let toolshim_tools = toolshim_tools.to_owned();
-        let provider = provider.clone();
-
-        let mut stream = if provider.supports_streaming() {
-            provider
-                .stream(
-                    system_prompt.as_str(),
-                    messages_for_provider.messages(),
-                    &tools,
-                )
-                .await?
-        } else {
-            let (message, usage) = provider
-                .complete(
-                    system_prompt.as_str(),
-                    messages_for_provider.messages(),
-                    &tools,
-                )
-                .await?;
-            stream_from_single_message(message, usage)
-        };
+        // Tool calls are only interpreted from the complete response text
+        if config.toolshim {
+            let toolshim_tools = toolshim_tools.to_owned();
+            return Ok(Box::pin(try_stream! {
+                let mut accumulator = DeltaAccumulator::new();
+                while let Some(delta) = deltas.next().await {
+                    accumulator.push(delta?);
+                }
+                let (message, usage) = accumulator.finish();
+                if let Some(usage) = usage.as_ref() {
+                    crate::providers::base::set_current_model(&usage.model);
+                }
+                let message = toolshim_postprocess(message, &toolshim_tools, &config).await?;
+                yield (Some(message), usage);
+            }));
+        }
 
+        let mut stream = messages_from_deltas(deltas);
         Ok(Box::pin(try_stream! {
-            while let Some(Ok((mut message, usage))) = stream.next().await {
+            while let Some(item) = stream.next().await {
+                let (message, usage) = item?;
                 // Store the model information in the global store
                 if let Some(usage) = usage.as_ref() {
                     crate::providers::base::set_current_model(&usage.model);
                 }
-
-                // Post-process / structure the response only if tool interpretation is enabled
-                if message.is_some() && config.toolshim {
-                    message = Some(toolshim_postprocess(message.unwrap(), &toolshim_tools).await?);
-                }
-
                 yield (message, usage);
             }
         }))
@@ -252,6 +308,10 @@ impl Agent {
             role: response.role.clone(),
             created: response.created,
             content: filtered_content,
+            stop_reason: response.stop_reason,
+            pinned: response.pinned,
+            origin: response.origin.clone(),
+            logprobs: response.logprobs.clone(),
         };
 
         // Categorize tool requests
@@ -309,9 +369,36 @@ impl Agent {
             metadata.accumulated_output_tokens,
             usage.usage.output_tokens,
         );
+        metadata
+            .model_usage
+            .entry(usage.model.clone())
+            .or_default()
+            .add(&usage.usage);
+        metadata.usage_ledger.record(usage);
 
         session::storage::update_metadata(&session_file_path, &metadata).await?;
 
         Ok(())
     }
+
+    /// Record the context the model was given, so sessions can later be compared. Only rewrites
+    /// the session metadata when the context changed.
+    pub(crate) async fn record_context_snapshot(
+        session_config: &crate::agents::types::SessionConfig,
+        snapshot: ContextSnapshot,
+    ) {
+        let result = async {
+            let session_file_path = session::storage::get_path(session_config.id.clone())?;
+            let mut metadata = session::storage::read_metadata(&session_file_path)?;
+            if metadata.context.as_ref() != Some(&snapshot) {
+                metadata.context = Some(snapshot);
+                session::storage::update_metadata(&session_file_path, &metadata).await?;
+            }
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to record the session context: {}", e);
+        }
+    }
 }
@@ -9,6 +9,7 @@ use tracing::{debug, info, warn};
 use crate::agents::types::SessionConfig;
 use crate::agents::types::{
     RetryConfig, SuccessCheck, DEFAULT_ON_FAILURE_TIMEOUT_SECONDS, DEFAULT_RETRY_TIMEOUT_SECONDS,
+    MAX_RETRIES_PREFIX,
 };
 use crate::config::Config;
 use crate::conversation::message::Message;
@@ -132,8 +133,8 @@ impl RetryManager {
         let current_attempts = self.get_attempts().await;
         if current_attempts >= retry_config.max_retries {
             let error_msg = Message::assistant().with_text(format!(
-                "Maximum retry attempts ({}) exceeded. Unable to complete the task successfully.",
-                retry_config.max_retries
+                "{} ({}) exceeded. Unable to complete the task successfully.",
+                MAX_RETRIES_PREFIX, retry_config.max_retries
             ));
             messages.push(error_msg);
             warn!(
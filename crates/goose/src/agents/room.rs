@@ -0,0 +1,624 @@
+//! Rooms where several agents work on one task together (experimental).
+//!
+//! Each member is a full agent with its own role, model and extensions. They take turns
+//! writing to a shared thread: a coordinator model picks who speaks next, or members go in a
+//! fixed order. Members publish shared artifacts (a design, a patch, a review) with
+//! `<artifact name="...">` blocks, and every member sees the latest version of each. The room
+//! stops when the coordinator calls the task done, after the turn limit, or once the members
+//! together have used up the token budget.
+//!
+//! Nobody is there to answer a member's questions mid-turn, so tools that need confirmation are
+//! denied and credential requests are declined.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::{Agent, AgentEvent};
+use crate::config::{Config, ExtensionConfigManager};
+use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::origin::Origin;
+use crate::conversation::Conversation;
+use crate::model::ModelConfig;
+use crate::permission::permission_confirmation::PrincipalType;
+use crate::permission::{Permission, PermissionConfirmation};
+use crate::providers::base::Provider;
+
+const DEFAULT_MAX_TURNS: usize = 12;
+
+/// The speaker name of the task that opens the thread
+const USER_SPEAKER: &str = "user";
+
+static ARTIFACT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<artifact\s+name\s*=\s*"([^"]+)"\s*>\n?(.*?)\n?</artifact>"#).unwrap()
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomMember {
+    pub name: String,
+    /// Instructions describing the member's part in the work
+    pub role: String,
+    /// Defaults to the configured provider
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Defaults to the configured model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Names of configured extensions the member may use, none by default
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnOrder {
+    /// The coordinator picks each next speaker and decides when the task is done
+    #[default]
+    Coordinator,
+    /// Members speak in the order they are listed until the turn limit
+    RoundRobin,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoordinatorConfig {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomConfig {
+    pub members: Vec<RoomMember>,
+    #[serde(default)]
+    pub turns: TurnOrder,
+    #[serde(default)]
+    pub coordinator: CoordinatorConfig,
+    #[serde(default = "default_max_turns")]
+    pub max_turns: usize,
+    /// Tokens all members and the coordinator may use together
+    #[serde(default)]
+    pub token_budget: Option<usize>,
+}
+
+fn default_max_turns() -> usize {
+    DEFAULT_MAX_TURNS
+}
+
+impl RoomConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.members.len() < 2 {
+            bail!("A room needs at least two members");
+        }
+        for (i, member) in self.members.iter().enumerate() {
+            if member.name.eq_ignore_ascii_case(USER_SPEAKER) {
+                bail!("'{}' is reserved for the task", USER_SPEAKER);
+            }
+            if self.members[..i]
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&member.name))
+            {
+                bail!("Two members are named {}", member.name);
+            }
+        }
+        if self.max_turns == 0 {
+            bail!("max_turns must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+/// One contribution to the shared thread
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomTurn {
+    pub speaker: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub content: String,
+    /// The member who wrote the latest version
+    pub author: String,
+    pub revision: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RoomOutcome {
+    /// The coordinator considered the task complete
+    Done,
+    TurnLimit,
+    BudgetExhausted,
+}
+
+#[derive(Debug, Clone)]
+pub enum RoomEvent {
+    Turn(RoomTurn),
+    Artifact { name: String, author: String },
+    Finished(RoomOutcome),
+}
+
+pub struct Room {
+    config: RoomConfig,
+    members: Vec<Agent>,
+    coordinator: Arc<dyn Provider>,
+    thread: Vec<RoomTurn>,
+    artifacts: BTreeMap<String, Artifact>,
+    tokens_used: usize,
+}
+
+impl Room {
+    /// Set up an agent per member with its own model, extensions and role
+    pub async fn new(config: RoomConfig) -> Result<Self> {
+        config.validate()?;
+        let configured = ExtensionConfigManager::get_all()?;
+
+        let mut members = Vec::with_capacity(config.members.len());
+        for member in &config.members {
+            let agent = Agent::new();
+            agent
+                .update_provider(create_provider(
+                    member.provider.as_deref(),
+                    member.model.as_deref(),
+                )?)
+                .await?;
+            for name in &member.extensions {
+                let entry = configured
+                    .iter()
+                    .find(|entry| &entry.config.name() == name)
+                    .ok_or_else(|| anyhow!("No extension named {} is configured", name))?;
+                agent
+                    .add_extension(entry.config.clone())
+                    .await
+                    .map_err(|e| anyhow!("Failed to add {} for {}: {}", name, member.name, e))?;
+            }
+            agent
+                .extend_system_prompt(member_instructions(member, &config.members))
+                .await;
+            members.push(agent);
+        }
+
+        let coordinator = create_provider(
+            config.coordinator.provider.as_deref(),
+            config.coordinator.model.as_deref(),
+        )?;
+
+        Ok(Self {
+            config,
+            members,
+            coordinator,
+            thread: Vec::new(),
+            artifacts: BTreeMap::new(),
+            tokens_used: 0,
+        })
+    }
+
+    pub fn thread(&self) -> &[RoomTurn] {
+        &self.thread
+    }
+
+    pub fn artifacts(&self) -> &BTreeMap<String, Artifact> {
+        &self.artifacts
+    }
+
+    pub fn tokens_used(&self) -> usize {
+        self.tokens_used
+    }
+
+    /// Work on the task until the room finishes, reporting each turn as it happens
+    pub async fn run(
+        &mut self,
+        task: &str,
+        mut on_event: impl FnMut(&RoomEvent),
+    ) -> Result<RoomOutcome> {
+        self.thread.push(RoomTurn {
+            speaker: USER_SPEAKER.to_string(),
+            text: task.to_string(),
+        });
+
+        let mut last: Option<usize> = None;
+        let mut outcome = RoomOutcome::TurnLimit;
+        for turn in 0..self.config.max_turns {
+            if self.over_budget() {
+                outcome = RoomOutcome::BudgetExhausted;
+                break;
+            }
+
+            let next = match self.config.turns {
+                TurnOrder::RoundRobin => turn % self.members.len(),
+                TurnOrder::Coordinator => match self.choose_next(last).await? {
+                    Some(next) => next,
+                    None => {
+                        outcome = RoomOutcome::Done;
+                        break;
+                    }
+                },
+            };
+
+            let speaker = self.config.members[next].name.clone();
+            let reply = self.speak(next).await?;
+            let (text, updated) = extract_artifacts(&reply);
+            for (name, content) in updated {
+                let revision = self.artifacts.get(&name).map_or(1, |a| a.revision + 1);
+                self.artifacts.insert(
+                    name.clone(),
+                    Artifact {
+                        content,
+                        author: speaker.clone(),
+                        revision,
+                    },
+                );
+                on_event(&RoomEvent::Artifact {
+                    name,
+                    author: speaker.clone(),
+                });
+            }
+
+            let turn = RoomTurn { speaker, text };
+            on_event(&RoomEvent::Turn(turn.clone()));
+            self.thread.push(turn);
+            last = Some(next);
+        }
+
+        if outcome == RoomOutcome::TurnLimit && self.over_budget() {
+            outcome = RoomOutcome::BudgetExhausted;
+        }
+        on_event(&RoomEvent::Finished(outcome.clone()));
+        Ok(outcome)
+    }
+
+    fn over_budget(&self) -> bool {
+        self.config
+            .token_budget
+            .is_some_and(|budget| self.tokens_used >= budget)
+    }
+
+    /// Ask the coordinator who should speak next, or None once the task is done
+    async fn choose_next(&mut self, last: Option<usize>) -> Result<Option<usize>> {
+        let roster = self
+            .config
+            .members
+            .iter()
+            .map(|member| format!("- {}: {}", member.name, member.role))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let system = format!(
+            "You moderate a discussion between agents working on a task together.\n\
+             Participants:\n{}\n\n\
+             Read the discussion and reply with only the name of the participant who should \
+             speak next. Nobody speaks twice in a row. Reply DONE once every participant has \
+             spoken and the task is complete.",
+            roster
+        );
+        let transcript = Message::user().with_text(self.transcript());
+        let (response, usage) = self
+            .coordinator
+            .complete(&system, &[transcript], &[])
+            .await?;
+        self.tokens_used += usage.usage.total_tokens.unwrap_or(0).max(0) as usize;
+
+        let everyone_spoke = self
+            .config
+            .members
+            .iter()
+            .all(|member| self.thread.iter().any(|turn| turn.speaker == member.name));
+        Ok(next_speaker(
+            &response.as_concat_text(),
+            &self.config.members,
+            last,
+            everyone_spoke,
+        ))
+    }
+
+    /// Run one member's turn on the thread so far and return what it said
+    async fn speak(&mut self, index: usize) -> Result<String> {
+        let member = &self.config.members[index];
+        let messages = member_view(&member.name, &self.thread, &self.artifacts);
+        let mut stream = self.members[index]
+            .reply(Conversation::new_unvalidated(messages), None, None)
+            .await?;
+
+        let agent = &self.members[index];
+        let mut reply = String::new();
+        // Each report covers the whole context so far, so only the last one counts
+        let mut tokens = 0;
+        while let Some(event) = stream.next().await {
+            match event? {
+                AgentEvent::Message(message) => {
+                    for content in &message.content {
+                        if let MessageContent::ToolConfirmationRequest(confirmation) = content {
+                            tracing::warn!(
+                                "Denying {} for {}: it needs the user's confirmation",
+                                confirmation.tool_name,
+                                member.name
+                            );
+                            agent
+                                .handle_confirmation(
+                                    confirmation.id.clone(),
+                                    PermissionConfirmation {
+                                        principal_type: PrincipalType::Tool,
+                                        permission: Permission::DenyOnce,
+                                    },
+                                )
+                                .await;
+                        }
+                    }
+                    if message.role == Role::Assistant {
+                        reply.push_str(&message.as_concat_text());
+                    }
+                }
+                AgentEvent::CredentialRequest(request) => {
+                    tracing::warn!(
+                        "Declining request for credential {} from {}",
+                        request.name,
+                        member.name
+                    );
+                    agent.provide_credential(request.id, None).await;
+                }
+                AgentEvent::ContextUsage(usage) => tokens = usage.used(),
+                _ => {}
+            }
+        }
+        self.tokens_used += tokens;
+        Ok(reply.trim().to_string())
+    }
+
+    fn transcript(&self) -> String {
+        let mut transcript = self
+            .thread
+            .iter()
+            .map(|turn| format!("[{}]: {}", turn.speaker, turn.text))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if !self.artifacts.is_empty() {
+            let names = self.artifacts.keys().cloned().collect::<Vec<_>>();
+            transcript.push_str(&format!("\n\nShared artifacts: {}", names.join(", ")));
+        }
+        transcript
+    }
+}
+
+fn create_provider(provider: Option<&str>, model: Option<&str>) -> Result<Arc<dyn Provider>> {
+    let config = Config::global();
+    let provider = match provider {
+        Some(provider) => provider.to_string(),
+        None => config.get_param::<String>("GOOSE_PROVIDER")?,
+    };
+    let model = match model {
+        Some(model) => model.to_string(),
+        None => config.get_param::<String>("GOOSE_MODEL")?,
+    };
+    crate::providers::create(&provider, ModelConfig::new(&model)?)
+}
+
+fn member_instructions(member: &RoomMember, members: &[RoomMember]) -> String {
+    let others = members
+        .iter()
+        .filter(|other| other.name != member.name)
+        .map(|other| format!("- {}: {}", other.name, other.role))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "You are {name}, one of several agents working on a task together in a shared thread.\n\
+         Your role: {role}\n\n\
+         The other participants:\n{others}\n\n\
+         Messages from others appear as \"[name]: ...\". Speak only as {name} and contribute \
+         your part for this turn, building on what has been said. To create or replace a shared \
+         artifact that everyone can read, write its full content as \
+         <artifact name=\"NAME\">CONTENT</artifact>.",
+        name = member.name,
+        role = member.role,
+        others = others,
+    )
+}
+
+/// The thread as one member sees it: its own turns as the assistant, everyone else's as
+/// user messages naming the speaker, ending with the current artifacts
+fn member_view(
+    name: &str,
+    thread: &[RoomTurn],
+    artifacts: &BTreeMap<String, Artifact>,
+) -> Vec<Message> {
+    let mut messages: Vec<Message> = Vec::new();
+    let mut pending: Vec<String> = Vec::new();
+    for turn in thread {
+        if turn.speaker == name {
+            if !pending.is_empty() {
+                messages.push(from_others(pending.join("\n\n")));
+                pending.clear();
+            }
+            messages.push(Message::assistant().with_text(&turn.text));
+        } else {
+            pending.push(format!("[{}]: {}", turn.speaker, turn.text));
+        }
+    }
+
+    if !artifacts.is_empty() {
+        let listing = artifacts
+            .iter()
+            .map(|(artifact, a)| {
+                format!(
+                    "<artifact name=\"{}\" author=\"{}\" revision=\"{}\">\n{}\n</artifact>",
+                    artifact, a.author, a.revision, a.content
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        pending.push(format!("Shared artifacts:\n{}", listing));
+    }
+    pending.push(format!("It is your turn, {}.", name));
+    messages.push(from_others(pending.join("\n\n")));
+    messages
+}
+
+/// Other members' turns are model output, so they count as coming from outside the user
+fn from_others(text: String) -> Message {
+    Message::user()
+        .with_text(text)
+        .with_origin(Origin::Extension("room".to_string()))
+}
+
+/// Pull the artifacts out of a reply, leaving a note where each one was
+fn extract_artifacts(reply: &str) -> (String, Vec<(String, String)>) {
+    let mut artifacts = Vec::new();
+    let text = ARTIFACT.replace_all(reply, |caps: &regex::Captures| {
+        let name = caps[1].trim().to_string();
+        artifacts.push((name.clone(), caps[2].to_string()));
+        format!("[updated artifact {}]", name)
+    });
+    (text.trim().to_string(), artifacts)
+}
+
+/// Read the coordinator's pick, keeping the turn-taking rules: nobody speaks twice in a row,
+/// and the room isn't done before every member has spoken
+fn next_speaker(
+    reply: &str,
+    members: &[RoomMember],
+    last: Option<usize>,
+    everyone_spoke: bool,
+) -> Option<usize> {
+    let reply = reply.trim().trim_matches(|c: char| !c.is_alphanumeric());
+    if reply.eq_ignore_ascii_case("done") && everyone_spoke {
+        return None;
+    }
+
+    let after_last = last.map_or(0, |last| (last + 1) % members.len());
+    let picked = members
+        .iter()
+        .position(|member| member.name.eq_ignore_ascii_case(reply))
+        .or_else(|| {
+            let reply = reply.to_lowercase();
+            members
+                .iter()
+                .position(|member| reply.contains(&member.name.to_lowercase()))
+        })
+        .unwrap_or(after_last);
+
+    if Some(picked) == last {
+        Some(after_last)
+    } else {
+        Some(picked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str) -> RoomMember {
+        RoomMember {
+            name: name.to_string(),
+            role: format!("the {}", name),
+            provider: None,
+            model: None,
+            extensions: vec![],
+        }
+    }
+
+    fn turn(speaker: &str, text: &str) -> RoomTurn {
+        RoomTurn {
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_config_from_yaml() {
+        let config: RoomConfig = serde_yaml::from_str(
+            r#"
+members:
+  - name: architect
+    role: Design the change
+    model: gpt-4o
+  - name: reviewer
+    role: Review the design
+    extensions: [developer]
+turns: round_robin
+token_budget: 50000
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.turns, TurnOrder::RoundRobin);
+        assert_eq!(config.max_turns, DEFAULT_MAX_TURNS);
+        assert_eq!(config.members[1].extensions, vec!["developer"]);
+        assert!(config.validate().is_ok());
+
+        let duplicate = RoomConfig {
+            members: vec![member("a"), member("A")],
+            ..config
+        };
+        assert!(duplicate.validate().is_err());
+    }
+
+    #[test]
+    fn test_member_view() {
+        let thread = vec![
+            turn("user", "Add a cache"),
+            turn("architect", "Use an LRU"),
+            turn("implementer", "Done"),
+            turn("reviewer", "Looks good"),
+        ];
+        let messages = member_view("implementer", &thread, &BTreeMap::new());
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].role, Role::User);
+        assert_eq!(
+            messages[0].as_concat_text(),
+            "[user]: Add a cache\n\n[architect]: Use an LRU"
+        );
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(
+            messages[2].as_concat_text(),
+            "[reviewer]: Looks good\n\nIt is your turn, implementer."
+        );
+    }
+
+    #[test]
+    fn test_extract_artifacts() {
+        let (text, artifacts) = extract_artifacts(
+            "Here is the plan.\n<artifact name=\"design.md\">\n# Cache\nLRU\n</artifact>\nThoughts?",
+        );
+        assert_eq!(
+            text,
+            "Here is the plan.\n[updated artifact design.md]\nThoughts?"
+        );
+        assert_eq!(
+            artifacts,
+            vec![("design.md".to_string(), "# Cache\nLRU".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_next_speaker_rules() {
+        let members = vec![
+            member("architect"),
+            member("implementer"),
+            member("reviewer"),
+        ];
+        assert_eq!(next_speaker("reviewer", &members, Some(0), false), Some(2));
+        assert_eq!(
+            next_speaker("I think the Implementer.", &members, None, false),
+            Some(1)
+        );
+        // Nobody speaks twice in a row
+        assert_eq!(next_speaker("architect", &members, Some(0), false), Some(1));
+        // Done only counts once everyone has spoken
+        assert_eq!(next_speaker("DONE", &members, Some(2), false), Some(0));
+        assert_eq!(next_speaker("DONE", &members, Some(2), true), None);
+    }
+}
@@ -14,6 +14,7 @@ use tokio::sync::RwLock;
 use crate::agents::tool_vectordb::ToolVectorDB;
 use crate::conversation::message::Message;
 use crate::model::ModelConfig;
+use crate::providers::embedding::{EmbeddingCache, EmbeddingPipeline};
 use crate::providers::{self, base::Provider};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -35,9 +36,26 @@ pub trait RouterToolSelector: Send + Sync {
 pub struct VectorToolSelector {
     vector_db: Arc<RwLock<ToolVectorDB>>,
     embedding_provider: Arc<dyn Provider>,
+    embedding_pipeline: EmbeddingPipeline,
     recent_tool_calls: Arc<RwLock<VecDeque<String>>>,
 }
 
+/// The in-process embedding model, when goose was built with it
+#[cfg(feature = "local-embeddings")]
+fn local_embedder() -> Result<Option<Arc<dyn Provider>>> {
+    use crate::providers::local_embedding::{
+        LocalEmbeddingProvider, LOCAL_EMBEDDING_DEFAULT_MODEL,
+    };
+    let model = ModelConfig::new(LOCAL_EMBEDDING_DEFAULT_MODEL)
+        .context("Failed to create model config for local embeddings")?;
+    Ok(Some(Arc::new(LocalEmbeddingProvider::from_env(model)?)))
+}
+
+#[cfg(not(feature = "local-embeddings"))]
+fn local_embedder() -> Result<Option<Arc<dyn Provider>>> {
+    Ok(None)
+}
+
 impl VectorToolSelector {
     pub async fn new(provider: Arc<dyn Provider>, table_name: String) -> Result<Self> {
         let vector_db = ToolVectorDB::new(Some(table_name)).await?;
@@ -57,14 +75,24 @@ impl VectorToolSelector {
                 "Failed to create {} provider for embeddings. If using OpenAI, make sure OPENAI_API_KEY env var is set or that you have configured the OpenAI provider via Goose before.",
                 embedding_provider_name
             ))?
-        } else {
+        } else if provider.supports_embeddings() {
             // Otherwise fall back to using the same provider instance as used for base goose model
             provider.clone()
+        } else {
+            // The base model can't embed, so embed offline when goose was built to
+            local_embedder()?.unwrap_or_else(|| provider.clone())
         };
 
+        let mut embedding_pipeline = EmbeddingPipeline::new(embedding_provider.clone());
+        match EmbeddingCache::default_location() {
+            Ok(cache) => embedding_pipeline = embedding_pipeline.with_cache(cache),
+            Err(e) => tracing::warn!("Embedding cache disabled: {}", e),
+        }
+
         Ok(Self {
             vector_db: Arc::new(RwLock::new(vector_db)),
             embedding_provider,
+            embedding_pipeline,
             recent_tool_calls: Arc::new(RwLock::new(VecDeque::with_capacity(100))),
         })
     }
@@ -148,8 +176,8 @@ impl RouterToolSelector for VectorToolSelector {
         }
 
         let embeddings = self
-            .embedding_provider
-            .create_embeddings(texts_to_embed)
+            .embedding_pipeline
+            .embed(&texts_to_embed)
             .await
             .map_err(|e| {
                 ToolError::ExecutionError(format!("Failed to generate tool embeddings: {}", e))
@@ -151,6 +151,7 @@ impl Agent {
             current_session_id: None,
             process_start_time: None,
             execution_mode: Some(execution_mode.to_string()),
+            timezone: None,
         };
 
         match scheduler.add_scheduled_job(job).await {
@@ -1,10 +1,13 @@
-use crate::agents::subagent_task_config::DEFAULT_SUBAGENT_MAX_TURNS;
+use crate::agents::subagent_task_config::{SubagentSpend, DEFAULT_SUBAGENT_MAX_TURNS};
+use crate::permission::permission_judge::always_needs_confirmation;
 use crate::{
     agents::extension::ExtensionConfig,
     agents::{extension_manager::ExtensionManager, Agent, TaskConfig},
-    config::ExtensionConfigManager,
+    config::{Config, ExtensionConfigManager},
     prompt_template::render_global_file,
+    providers::base::Usage,
     providers::errors::ProviderError,
+    providers::pricing::{get_model_pricing, parse_model_id},
 };
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
@@ -19,6 +22,9 @@ use tokio::sync::{Mutex, RwLock};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, instrument};
 
+/// Status message of a subagent stopped by its budget
+pub const BUDGET_EXCEEDED_MESSAGE: &str = "Budget exceeded";
+
 /// Status of a subagent
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SubAgentStatus {
@@ -48,6 +54,7 @@ pub struct SubAgent {
     pub turn_count: Arc<Mutex<usize>>,
     pub created_at: DateTime<Utc>,
     pub extension_manager: Arc<RwLock<ExtensionManager>>,
+    pub spend: Arc<Mutex<SubagentSpend>>,
 }
 
 impl SubAgent {
@@ -63,13 +70,29 @@ impl SubAgent {
         // 1. If executing dynamic task (task_type = 'text_instruction'), default to using all enabled extensions
         // 2. (TODO) If executing a sub-recipe task, only use recipe extensions
 
-        // Get all enabled extensions from config
+        // Get all enabled extensions from config, or the ones the task is limited to
         let enabled_extensions = ExtensionConfigManager::get_all()
             .unwrap_or_default()
             .into_iter()
             .filter(|ext| ext.enabled)
             .map(|ext| ext.config)
+            .filter(|ext| {
+                task_config
+                    .extensions
+                    .as_ref()
+                    .is_none_or(|names| names.contains(&ext.name()))
+            })
             .collect::<Vec<ExtensionConfig>>();
+        if let Some(names) = &task_config.extensions {
+            let missing: Vec<&str> = names
+                .iter()
+                .filter(|name| !enabled_extensions.iter().any(|ext| ext.name() == **name))
+                .map(String::as_str)
+                .collect();
+            if !missing.is_empty() {
+                return Err(anyhow!("Extensions not enabled: {}", missing.join(", ")));
+            }
+        }
 
         // Add enabled extensions to the subagent's extension manager
         for extension in enabled_extensions {
@@ -87,6 +110,7 @@ impl SubAgent {
             turn_count: Arc::new(Mutex::new(0)),
             created_at: Utc::now(),
             extension_manager: Arc::new(RwLock::new(extension_manager)),
+            spend: Arc::new(Mutex::new(SubagentSpend::default())),
         });
 
         debug!("Subagent {} created successfully", subagent.id);
@@ -166,7 +190,9 @@ impl SubAgent {
             )
             .await
             {
-                Ok((response, _usage)) => {
+                Ok((response, usage)) => {
+                    let over_budget = self.record_usage(&usage.model, &usage.usage).await;
+
                     // Process any tool calls in the response
                     let tool_requests: Vec<ToolRequest> = response
                         .content
@@ -180,13 +206,17 @@ impl SubAgent {
                         })
                         .collect();
 
-                    // If there are no tool requests, we're done
-                    if tool_requests.is_empty() || loop_count >= max_turns {
+                    // If there are no tool requests, or the budget is spent, we're done
+                    if tool_requests.is_empty() || loop_count >= max_turns || over_budget {
                         self.add_message(response.clone()).await;
                         messages.push(response.clone());
 
-                        // Set status back to ready
-                        self.set_status(SubAgentStatus::Completed("Completed!".to_string()))
+                        let message = if over_budget {
+                            BUDGET_EXCEEDED_MESSAGE
+                        } else {
+                            "Completed!"
+                        };
+                        self.set_status(SubAgentStatus::Completed(message.to_string()))
                             .await;
                         break;
                     }
@@ -197,16 +227,27 @@ impl SubAgent {
                     // Process each tool request and create user response messages
                     for request in &tool_requests {
                         if let Ok(tool_call) = &request.tool_call {
-                            // Handle platform tools or dispatch to extension manager
-                            let tool_result = match self
-                                .extension_manager
-                                .read()
-                                .await
-                                .dispatch_tool_call(tool_call.clone(), CancellationToken::default())
-                                .await
-                            {
-                                Ok(result) => result.result.await,
-                                Err(e) => Err(ToolError::ExecutionError(e.to_string())),
+                            // Nobody is there to confirm these, so they are left to the parent
+                            let tool_result = if always_needs_confirmation(&tool_call.name) {
+                                Err(ToolError::ExecutionError(format!(
+                                    "{} needs the user's confirmation and can't be called by a subagent; \
+                                     report what you would send instead",
+                                    tool_call.name
+                                )))
+                            } else {
+                                match self
+                                    .extension_manager
+                                    .read()
+                                    .await
+                                    .dispatch_tool_call(
+                                        tool_call.clone(),
+                                        CancellationToken::default(),
+                                    )
+                                    .await
+                                {
+                                    Ok(result) => result.result.await,
+                                    Err(e) => Err(ToolError::ExecutionError(e.to_string())),
+                                }
                             };
 
                             match tool_result {
@@ -238,7 +279,7 @@ impl SubAgent {
                     last_error = Some(anyhow::anyhow!("Context length exceeded"));
                     break;
                 }
-                Err(ProviderError::RateLimitExceeded(_)) => {
+                Err(ProviderError::RateLimitExceeded { .. }) => {
                     self.set_status(SubAgentStatus::Completed("Rate limit exceeded".to_string()))
                         .await;
                     last_error = Some(anyhow::anyhow!("Rate limit exceeded"));
@@ -262,6 +303,18 @@ impl SubAgent {
         }
     }
 
+    /// Add a response's usage to what the subagent has spent, returning whether that is over
+    /// its budget
+    async fn record_usage(&self, model: &str, usage: &Usage) -> bool {
+        let cost = usage_cost(model, usage).await;
+        let mut spend = self.spend.lock().await;
+        spend.tokens += usage.total_tokens.unwrap_or_default() as i64;
+        if let Some(cost) = cost {
+            spend.cost = Some(spend.cost.unwrap_or_default() + cost);
+        }
+        self.config.budget.exceeded(&spend)
+    }
+
     /// Add a message to the conversation (for tracking agent responses)
     async fn add_message(&self, message: Message) {
         let mut conversation = self.conversation.lock().await;
@@ -275,7 +328,7 @@ impl SubAgent {
         // Add basic context
         context.insert(
             "current_date_time",
-            serde_json::Value::String(Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+            serde_json::Value::String(crate::clock::now_for_prompt()),
         );
         context.insert("subagent_id", serde_json::Value::String(self.id.clone()));
 
@@ -287,6 +340,13 @@ impl SubAgent {
             );
         }
 
+        if let Some(max_tokens) = self.config.budget.max_tokens {
+            context.insert(
+                "max_tokens",
+                serde_json::Value::Number(serde_json::Number::from(max_tokens)),
+            );
+        }
+
         // Add available tools with descriptions for better context
         let tools_with_descriptions: Vec<String> = available_tools
             .iter()
@@ -321,3 +381,15 @@ impl SubAgent {
         Ok(system_prompt)
     }
 }
+
+/// The cost of one response, when pricing is known for its model
+async fn usage_cost(model: &str, usage: &Usage) -> Option<f64> {
+    let provider: String = Config::global().get_param("GOOSE_PROVIDER").ok()?;
+    // Models served through a router carry their real provider in the name
+    let (billed_by, model) = parse_model_id(model).unwrap_or((provider, model.to_string()));
+    let pricing = get_model_pricing(&billed_by, &model).await?;
+    Some(
+        pricing.input_cost * usage.input_tokens.unwrap_or_default() as f64
+            + pricing.output_cost * usage.output_tokens.unwrap_or_default() as f64,
+    )
+}
@@ -1,4 +1,6 @@
+use crate::config::Config;
 use crate::providers::base::Provider;
+use serde::Serialize;
 use std::env;
 use std::fmt;
 use std::sync::Arc;
@@ -10,12 +12,52 @@ pub const DEFAULT_SUBAGENT_MAX_TURNS: usize = 5;
 /// Environment variable name for configuring max turns
 pub const GOOSE_SUBAGENT_MAX_TURNS_ENV_VAR: &str = "GOOSE_SUBAGENT_MAX_TURNS";
 
+/// Config key for the most tokens one subagent may use
+pub const SUBAGENT_MAX_TOKENS_KEY: &str = "GOOSE_SUBAGENT_MAX_TOKENS";
+/// Config key for the most one subagent may cost, in dollars
+pub const SUBAGENT_MAX_COST_KEY: &str = "GOOSE_SUBAGENT_MAX_COST";
+
+/// Caps on what one subagent may spend; unset caps don't limit anything
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SubagentBudget {
+    pub max_tokens: Option<i64>,
+    pub max_cost: Option<f64>,
+}
+
+/// What a subagent has spent so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct SubagentSpend {
+    pub tokens: i64,
+    /// Dollars, when pricing is known for the model
+    pub cost: Option<f64>,
+}
+
+impl SubagentBudget {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_tokens: config.get_param(SUBAGENT_MAX_TOKENS_KEY).ok(),
+            max_cost: config.get_param(SUBAGENT_MAX_COST_KEY).ok(),
+        }
+    }
+
+    pub fn exceeded(&self, spend: &SubagentSpend) -> bool {
+        self.max_tokens.is_some_and(|max| spend.tokens >= max)
+            || self
+                .max_cost
+                .zip(spend.cost)
+                .is_some_and(|(max, cost)| cost >= max)
+    }
+}
+
 /// Configuration for task execution with all necessary dependencies
 #[derive(Clone)]
 pub struct TaskConfig {
     pub id: String,
     pub provider: Option<Arc<dyn Provider>>,
     pub max_turns: Option<usize>,
+    /// Names of the enabled extensions the subagent gets; all of them when unset
+    pub extensions: Option<Vec<String>>,
+    pub budget: SubagentBudget,
 }
 
 impl fmt::Debug for TaskConfig {
@@ -24,6 +66,8 @@ impl fmt::Debug for TaskConfig {
             .field("id", &self.id)
             .field("provider", &"<dyn Provider>")
             .field("max_turns", &self.max_turns)
+            .field("extensions", &self.extensions)
+            .field("budget", &self.budget)
             .finish()
     }
 }
@@ -40,9 +84,22 @@ impl TaskConfig {
                     .and_then(|val| val.parse::<usize>().ok())
                     .unwrap_or(DEFAULT_SUBAGENT_MAX_TURNS),
             ),
+            extensions: None,
+            budget: SubagentBudget::from_config(Config::global()),
         }
     }
 
+    /// Limit the subagent to some of the enabled extensions
+    pub fn with_extensions(mut self, extensions: Option<Vec<String>>) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: SubagentBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
     /// Get a reference to the provider
     pub fn provider(&self) -> Option<&Arc<dyn Provider>> {
         self.provider.as_ref()
@@ -10,7 +10,7 @@ use tokio_util::sync::CancellationToken;
 use crate::config::permission::PermissionLevel;
 use crate::config::PermissionManager;
 use crate::permission::Permission;
-use mcp_core::ToolResult;
+use mcp_core::{ToolError, ToolResult};
 use rmcp::model::{Content, ServerNotification};
 
 // ToolCallResult combines the result of a tool call with an optional notification stream that
@@ -30,6 +30,7 @@ impl From<ToolResult<Vec<Content>>> for ToolCallResult {
 }
 
 use super::agent::{tool_stream, ToolStream};
+use crate::agents::credentials::{self, CredentialRequest, DECLINED_CREDENTIAL_RESPONSE};
 use crate::agents::Agent;
 use crate::conversation::message::{Message, ToolRequest};
 
@@ -37,6 +38,10 @@ pub const DECLINED_RESPONSE: &str = "The user has declined to run this tool. \
     DO NOT attempt to call this tool again. \
     If there are no alternative methods to proceed, clearly explain the situation and STOP.";
 
+pub const READ_ONLY_TOOL_SKIPPED_RESPONSE: &str = "This tool was not run: another goose session \
+    is working in this workspace, so this session is attached read-only and may only run \
+    read-only tools. Tell the user what the tool call would have done.";
+
 pub const CHAT_MODE_TOOL_SKIPPED_RESPONSE: &str = "Let the user know the tool call was skipped in Goose chat mode. \
                                         DO NOT apologize for skipping the tool call. DO NOT say sorry. \
                                         Provide an explanation of what the tool call would do, structured as a \
@@ -128,4 +133,61 @@ impl Agent {
         }
         .boxed()
     }
+
+    /// Ask the user for each requested credential and store the ones they provide. Yields the
+    /// requests for the client to prompt with; answers arrive through `provide_credential`.
+    pub(crate) fn handle_credential_requests<'a>(
+        &'a self,
+        tool_requests: &'a [ToolRequest],
+        message_tool_response: Arc<Mutex<Message>>,
+    ) -> BoxStream<'a, anyhow::Result<CredentialRequest>> {
+        try_stream! {
+            for request in tool_requests {
+                let Ok(tool_call) = request.tool_call.clone() else {
+                    continue;
+                };
+                let name = tool_call.arguments.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                let prompt = tool_call.arguments.get("prompt").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+
+                if !credentials::is_valid_name(&name) {
+                    let mut response = message_tool_response.lock().await;
+                    *response = response.clone().with_tool_response(
+                        request.id.clone(),
+                        Err(ToolError::InvalidParameters(format!(
+                            "'{}' is not a valid environment variable name; use uppercase letters, digits and underscores",
+                            name
+                        ))),
+                    );
+                    continue;
+                }
+
+                yield CredentialRequest {
+                    id: request.id.clone(),
+                    name: name.clone(),
+                    prompt,
+                };
+
+                let mut answer = None;
+                let mut rx = self.credential_rx.lock().await;
+                while let Some((req_id, value)) = rx.recv().await {
+                    if req_id == request.id {
+                        answer = value.filter(|value| !value.is_empty());
+                        break;
+                    }
+                }
+
+                let result = match answer {
+                    Some(value) => {
+                        self.credentials.insert(name.clone(), value);
+                        Ok(vec![Content::text(credentials::provided_response(&name))])
+                    }
+                    None => Ok(vec![Content::text(DECLINED_CREDENTIAL_RESPONSE)]),
+                };
+
+                let mut response = message_tool_response.lock().await;
+                *response = response.clone().with_tool_response(request.id.clone(), result);
+            }
+        }
+        .boxed()
+    }
 }
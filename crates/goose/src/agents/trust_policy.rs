@@ -0,0 +1,240 @@
+//! Trust levels for the content in the agent's context.
+//!
+//! Each message records where its content came from: the user, a local file, an extension or
+//! the web. The policy gives every origin a trust level and names tools that may only run
+//! without approval while everything that entered the context since the user last spoke is
+//! trusted enough, so a shell command prompted by a web page waits for the user.
+
+use std::collections::HashMap;
+
+use rmcp::model::Role;
+use serde::Deserialize;
+
+use crate::agents::injection_guard::{matches_pattern, InjectionGuard};
+use crate::config::{Config, ConfigError};
+use crate::conversation::message::Message;
+use crate::conversation::origin::{Origin, TrustLevel};
+
+/// Config key for trust levels per extension, e.g. `{"fetch": "untrusted"}`
+pub const EXTENSION_TRUST_KEY: &str = "GOOSE_EXTENSION_TRUST";
+/// Config key for the least trusted context each tool may run on, e.g. `{"*shell*": "trusted"}`
+pub const TRUST_POLICY_KEY: &str = "GOOSE_TRUST_POLICY";
+
+/// Tools that read local files
+const LOCAL_FILE_TOOLS: &[&str] = &["*text_editor", "*read_file*", "*list_directory*"];
+
+/// Tools that act on the machine and shouldn't follow untrusted content unattended
+const DEFAULT_GUARDED_TOOLS: &[&str] = &["*shell*", "*automation_script*"];
+
+#[derive(Debug, Clone)]
+pub struct TrustPolicy {
+    extension_trust: HashMap<String, TrustLevel>,
+    /// Tool patterns with the least trusted context they may run on without approval
+    rules: Vec<(String, TrustLevel)>,
+}
+
+impl Default for TrustPolicy {
+    fn default() -> Self {
+        Self {
+            // Goose's own tools are as trusted as goose
+            extension_trust: HashMap::from([("platform".to_string(), TrustLevel::Trusted)]),
+            rules: DEFAULT_GUARDED_TOOLS
+                .iter()
+                .map(|tool| (tool.to_string(), TrustLevel::Limited))
+                .collect(),
+        }
+    }
+}
+
+impl TrustPolicy {
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        let mut policy = Self::default();
+        if let Some(extension_trust) =
+            optional::<HashMap<String, TrustLevel>>(config, EXTENSION_TRUST_KEY)
+        {
+            policy.extension_trust.extend(extension_trust);
+        }
+        if let Some(rules) = optional::<HashMap<String, TrustLevel>>(config, TRUST_POLICY_KEY) {
+            policy = policy.with_rules(rules);
+        }
+        policy
+    }
+
+    pub fn with_extension_trust(mut self, extension: &str, trust: TrustLevel) -> Self {
+        self.extension_trust.insert(extension.to_string(), trust);
+        self
+    }
+
+    pub fn with_rules(mut self, rules: HashMap<String, TrustLevel>) -> Self {
+        self.rules = rules.into_iter().collect();
+        self.rules.sort();
+        self
+    }
+
+    pub fn trust_of(&self, origin: &Origin) -> TrustLevel {
+        match origin {
+            Origin::Extension(name) => self
+                .extension_trust
+                .get(name)
+                .copied()
+                .unwrap_or_else(|| origin.default_trust()),
+            _ => origin.default_trust(),
+        }
+    }
+
+    /// Where a tool's output comes from
+    pub fn origin_for_tool(&self, tool_name: &str, guard: &InjectionGuard) -> Origin {
+        if guard.is_untrusted(tool_name) {
+            return Origin::Web;
+        }
+        if LOCAL_FILE_TOOLS
+            .iter()
+            .any(|pattern| matches_pattern(pattern, tool_name))
+        {
+            return Origin::LocalFile;
+        }
+        let extension = tool_name
+            .split_once("__")
+            .map_or(tool_name, |(extension, _)| extension);
+        Origin::Extension(extension.to_string())
+    }
+
+    /// The least trusted origin among the tools that produced a set of results
+    pub fn least_trusted_origin<'a>(
+        &self,
+        tool_names: impl IntoIterator<Item = &'a str>,
+        guard: &InjectionGuard,
+    ) -> Option<Origin> {
+        tool_names
+            .into_iter()
+            .map(|name| self.origin_for_tool(name, guard))
+            .min_by_key(|origin| self.trust_of(origin))
+    }
+
+    /// The trust of the least trusted content added since the user last wrote
+    pub fn context_trust(&self, messages: &[Message]) -> TrustLevel {
+        let mut trust = TrustLevel::Trusted;
+        for message in messages.iter().rev() {
+            match message_origin(message) {
+                Some(Origin::User) => break,
+                Some(origin) => trust = trust.min(self.trust_of(&origin)),
+                None => {}
+            }
+        }
+        trust
+    }
+
+    /// Whether a tool needs approval to run on a context of the given trust
+    pub fn requires_approval(&self, tool_name: &str, context_trust: TrustLevel) -> bool {
+        self.rules
+            .iter()
+            .any(|(pattern, least)| context_trust < *least && matches_pattern(pattern, tool_name))
+    }
+}
+
+/// A message's origin, taking untagged text from the user as the user's own
+pub fn message_origin(message: &Message) -> Option<Origin> {
+    if message.origin.is_some() {
+        return message.origin.clone();
+    }
+    let from_user = message.role == Role::User
+        && message
+            .content
+            .iter()
+            .any(|content| content.as_text().is_some())
+        && !message
+            .content
+            .iter()
+            .any(|content| content.as_tool_response().is_some());
+    from_user.then_some(Origin::User)
+}
+
+fn optional<T: for<'de> Deserialize<'de>>(config: &Config, key: &str) -> Option<T> {
+    match config.get_param::<T>(key) {
+        Ok(value) => Some(value),
+        Err(ConfigError::NotFound(_)) => None,
+        Err(e) => {
+            tracing::warn!("Ignoring {}: {}", key, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    fn tool_result(origin: Origin) -> Message {
+        Message::user()
+            .with_tool_response("1", Ok(vec![Content::text("output")]))
+            .with_origin(origin)
+    }
+
+    #[test]
+    fn test_origin_for_tool() {
+        let policy = TrustPolicy::default();
+        let guard = InjectionGuard::default();
+        assert_eq!(
+            policy.origin_for_tool("computercontroller__web_scrape", &guard),
+            Origin::Web
+        );
+        assert_eq!(
+            policy.origin_for_tool("developer__text_editor", &guard),
+            Origin::LocalFile
+        );
+        assert_eq!(
+            policy.origin_for_tool("developer__shell", &guard),
+            Origin::Extension("developer".to_string())
+        );
+        assert_eq!(
+            policy.least_trusted_origin(["developer__shell", "fetch__fetch"], &guard),
+            Some(Origin::Web)
+        );
+    }
+
+    #[test]
+    fn test_shell_after_web_content_needs_approval() {
+        let policy = TrustPolicy::default();
+        let messages = vec![
+            Message::user().with_text("Summarize this page"),
+            Message::assistant().with_text("Fetching"),
+            tool_result(Origin::Web),
+        ];
+        let trust = policy.context_trust(&messages);
+        assert_eq!(trust, TrustLevel::Untrusted);
+        assert!(policy.requires_approval("developer__shell", trust));
+        assert!(!policy.requires_approval("developer__text_editor", trust));
+
+        // The user speaking again resets what the next command derives from
+        let mut messages = messages;
+        messages.push(Message::user().with_text("Now list the files"));
+        messages.push(tool_result(Origin::LocalFile));
+        let trust = policy.context_trust(&messages);
+        assert_eq!(trust, TrustLevel::Trusted);
+        assert!(!policy.requires_approval("developer__shell", trust));
+    }
+
+    #[test]
+    fn test_configured_trust() {
+        let policy = TrustPolicy::default()
+            .with_extension_trust("slack", TrustLevel::Untrusted)
+            .with_rules(HashMap::from([(
+                "*__shell".to_string(),
+                TrustLevel::Trusted,
+            )]));
+        let trust =
+            policy.context_trust(&[tool_result(Origin::Extension("developer".to_string()))]);
+        assert_eq!(trust, TrustLevel::Limited);
+        assert!(policy.requires_approval("developer__shell", trust));
+        assert_eq!(
+            policy.trust_of(&Origin::Extension("slack".to_string())),
+            TrustLevel::Untrusted
+        );
+        assert_eq!(
+            policy.trust_of(&Origin::Extension("platform".to_string())),
+            TrustLevel::Trusted
+        );
+    }
+}
@@ -16,6 +16,27 @@ pub const DEFAULT_RETRY_TIMEOUT_SECONDS: u64 = 300;
 /// Default timeout for on_failure operations (10 minutes - longer for on_failure tasks)
 pub const DEFAULT_ON_FAILURE_TIMEOUT_SECONDS: u64 = 600;
 
+/// What the agent says when it stops after `max_turns` without hearing from the user
+pub const MAX_TURNS_MESSAGE: &str =
+    "I've reached the maximum number of actions I can do without user input. Would you like me to continue?";
+
+/// Start of what the agent says when a provider request fails and the reply is abandoned
+pub const PROVIDER_ERROR_PREFIX: &str = "Ran into this error: ";
+
+/// Start of what the agent says when a recipe's success checks still fail after every retry
+pub const MAX_RETRIES_PREFIX: &str = "Maximum retry attempts";
+
+/// Order in which the results of parallel tool calls are appended to the conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolResultOrdering {
+    /// The order the model requested the tools in, which every provider accepts
+    #[default]
+    RequestOrder,
+    /// The order the tool calls finished in
+    CompletionOrder,
+}
+
 /// Configuration for retry logic in recipe execution
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RetryConfig {
@@ -0,0 +1,47 @@
+//! Warming caches ahead of a run.
+//!
+//! The opening request of a recipe run is the same every time: the system prompt, the tools and
+//! the rendered prompt. Sending it once shortly before a scheduled run lets providers with prompt
+//! caching store that prefix, and builds the local state the request needs (the tool router's
+//! index, tokenizers), so the real run starts without paying for either on its critical path.
+
+use anyhow::Result;
+
+use crate::agents::Agent;
+use crate::conversation::message::Message;
+use crate::providers::base::ProviderUsage;
+
+/// What warming sent to the provider
+#[derive(Debug, Clone)]
+pub struct WarmUpReport {
+    pub tools: usize,
+    pub system_prompt_chars: usize,
+    pub usage: ProviderUsage,
+}
+
+impl Agent {
+    /// Send the opening request of a run without acting on the answer.
+    ///
+    /// The request goes through the same preparation as a reply, so the prefix the provider caches
+    /// is the one the run will send. Keep the provider's max tokens low; only the prefix matters.
+    pub async fn warm_up(&self, message: Message) -> Result<WarmUpReport> {
+        self.update_router_tool_selector(None, Some(true)).await?;
+
+        let (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+        let provider = self.provider().await?;
+        let (_, usage) = Self::generate_response_from_provider(
+            provider,
+            &system_prompt,
+            &[message],
+            &tools,
+            &toolshim_tools,
+        )
+        .await?;
+
+        Ok(WarmUpReport {
+            tools: tools.len() + toolshim_tools.len(),
+            system_prompt_chars: system_prompt.len(),
+            usage,
+        })
+    }
+}
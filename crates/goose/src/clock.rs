@@ -0,0 +1,213 @@
+//! The user's time, timezone and locale.
+//!
+//! goose often runs somewhere other than where the user is — a server, a container, a
+//! scheduled job — so the timezone can be set with `GOOSE_TIMEZONE` and otherwise comes from
+//! the system. The system prompt carries the current time in that timezone, refreshed every
+//! turn, and the platform time tools read and convert times relative to it.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::config::Config;
+
+/// Config key for the user's IANA timezone, e.g. `Europe/Berlin`
+pub const TIMEZONE_KEY: &str = "GOOSE_TIMEZONE";
+
+/// The user's timezone: `GOOSE_TIMEZONE` if set and valid, else the system's, else UTC
+pub fn user_timezone() -> Tz {
+    if let Ok(name) = Config::global().get_param::<String>(TIMEZONE_KEY) {
+        match parse_timezone_name(&name) {
+            Ok(tz) => return tz,
+            Err(e) => tracing::warn!("Ignoring {}: {}", TIMEZONE_KEY, e),
+        }
+    }
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Parse an IANA timezone name such as `America/New_York`; `local` is the user's timezone
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    if name.trim().eq_ignore_ascii_case("local") {
+        return Ok(user_timezone());
+    }
+    parse_timezone_name(name)
+}
+
+fn parse_timezone_name(name: &str) -> Result<Tz> {
+    let name = name.trim();
+    if name.eq_ignore_ascii_case("utc") || name == "Z" {
+        return Ok(Tz::UTC);
+    }
+    name.parse::<Tz>().map_err(|_| {
+        anyhow!(
+            "Unknown timezone '{}'; use an IANA name such as Europe/Berlin",
+            name
+        )
+    })
+}
+
+/// The user's locale from the environment, e.g. `en_US`, if one is set
+pub fn user_locale() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| value.split(['.', '@']).next().map(str::to_string))
+        .filter(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+}
+
+/// A point in time as shown to the model, e.g. `Friday, 2026-10-16 14:03 Europe/Berlin (UTC+02:00)`
+pub fn describe(time: DateTime<Utc>, tz: Tz) -> String {
+    let local = time.with_timezone(&tz);
+    format!(
+        "{} {} (UTC{})",
+        local.format("%A, %Y-%m-%d %H:%M"),
+        tz.name(),
+        local.format("%:z")
+    )
+}
+
+/// The current time in the user's timezone, for the system prompt
+pub fn now_for_prompt() -> String {
+    describe(Utc::now(), user_timezone())
+}
+
+/// The current time in `timezone` (the user's if not given), for the get_current_time tool
+pub fn current_time(timezone: Option<&str>) -> Result<String> {
+    let tz = match timezone {
+        Some(name) => parse_timezone(name)?,
+        None => user_timezone(),
+    };
+    let now = Utc::now();
+    Ok(format!(
+        "{}\nISO 8601: {}\nUnix timestamp: {}",
+        describe(now, tz),
+        now.with_timezone(&tz).to_rfc3339(),
+        now.timestamp()
+    ))
+}
+
+/// Read `time` in the `from` timezone (the user's if not given) and show it in `to`,
+/// for the convert_timezone tool
+pub fn convert_timezone(time: &str, from: Option<&str>, to: &str) -> Result<String> {
+    let from = match from {
+        Some(name) => parse_timezone(name)?,
+        None => user_timezone(),
+    };
+    let to = parse_timezone(to)?;
+    let instant = parse_time(time, from, Utc::now())?;
+    Ok(format!(
+        "{} is {}",
+        describe(instant, from),
+        describe(instant, to)
+    ))
+}
+
+/// Parse RFC 3339, which carries its own offset, or a date and time or just a time of day
+/// (taken as today) in `tz`
+fn parse_time(input: &str, tz: Tz, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let naive = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    .or_else(|| {
+        ["%H:%M:%S", "%H:%M"]
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(input, format).ok())
+            .map(|time| now.with_timezone(&tz).date_naive().and_time(time))
+    })
+    .ok_or_else(|| {
+        anyhow!(
+            "Could not read '{}' as a time; use e.g. 2026-03-01 09:30, 09:30 or RFC 3339",
+            input
+        )
+    })?;
+
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| {
+            anyhow!(
+                "{} does not exist in {}: it falls in a daylight saving gap",
+                input,
+                tz.name()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("UTC").unwrap(), Tz::UTC);
+        assert_eq!(
+            parse_timezone(" Europe/Berlin ").unwrap(),
+            Tz::Europe__Berlin
+        );
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        let time = at("2026-10-16T12:03:00Z");
+        assert_eq!(
+            describe(time, Tz::Europe__Berlin),
+            "Friday, 2026-10-16 14:03 Europe/Berlin (UTC+02:00)"
+        );
+        assert_eq!(
+            describe(time, Tz::America__New_York),
+            "Friday, 2026-10-16 08:03 America/New_York (UTC-04:00)"
+        );
+    }
+
+    #[test]
+    fn test_parse_time_in_timezone() {
+        let now = at("2026-01-10T08:00:00Z");
+        assert_eq!(
+            parse_time("2026-07-01 09:30", Tz::Europe__Berlin, now).unwrap(),
+            at("2026-07-01T07:30:00Z")
+        );
+        assert_eq!(
+            parse_time("09:30", Tz::Asia__Tokyo, now).unwrap(),
+            at("2026-01-10T00:30:00Z")
+        );
+        assert_eq!(
+            parse_time("2026-07-01T09:30:00-07:00", Tz::Europe__Berlin, now).unwrap(),
+            at("2026-07-01T16:30:00Z")
+        );
+        // Clocks skip from 02:00 to 03:00 in Berlin on this day
+        assert!(parse_time("2026-03-29 02:30", Tz::Europe__Berlin, now).is_err());
+        assert!(parse_time("next tuesday", Tz::UTC, now).is_err());
+    }
+
+    #[test]
+    fn test_convert_timezone() {
+        let converted =
+            convert_timezone("2026-07-01 09:30", Some("Europe/London"), "Asia/Kolkata").unwrap();
+        assert_eq!(
+            converted,
+            "Wednesday, 2026-07-01 09:30 Europe/London (UTC+01:00) is \
+            Wednesday, 2026-07-01 14:00 Asia/Kolkata (UTC+05:30)"
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! A lockfile for the extensions a project runs.
+//!
+//! `.goose/extensions.lock` records, for every command-line extension, the exact package
+//! version its runner resolved (`npx`, `uvx`) with the registry's hash, or the hash of the
+//! executable it starts. Extensions are checked against it before they are spawned, so a team
+//! sharing a configuration runs the same servers until someone deliberately refreshes the
+//! lock with `goose extension update`. Locked packages are checked against the registry's hash
+//! again at spawn.
+//!
+//! The file is signed with `GOOSE_EXTENSION_LOCK_KEY` when that secret is set. Without the key
+//! the lock only pins versions and catches accidental drift: anyone who can edit the file can
+//! also change what it pins, so teams that rely on it for security should share a key.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::agents::ExtensionConfig;
+use crate::config::Config;
+
+pub const LOCKFILE_PATH: &str = ".goose/extensions.lock";
+/// Secret shared by a team to sign and verify the lockfile
+pub const LOCK_KEY: &str = "GOOSE_EXTENSION_LOCK_KEY";
+
+const LOCKFILE_VERSION: u32 = 1;
+const HMAC_PREFIX: &str = "hmac-sha256:";
+/// Prefix of registry hashes of PyPI packages
+const DIGEST_PREFIX: &str = "sha256:";
+const PYPI_URL: &str = "https://pypi.org/pypi";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Registry {
+    Npm,
+    Pypi,
+}
+
+/// The exact package an extension's runner installs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub registry: Registry,
+    pub name: String,
+    pub version: String,
+    /// The registry's hash of the package, e.g. `sha512-...` or `sha256:...`
+    pub integrity: Option<String>,
+}
+
+impl LockedPackage {
+    /// The package spec the runner is given to install exactly this version
+    pub fn pinned_spec(&self) -> String {
+        match self.registry {
+            Registry::Npm => format!("{}@{}", self.name, self.version),
+            Registry::Pypi => format!("{}=={}", self.name, self.version),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedExtension {
+    /// The command and arguments as configured, to notice when the configuration drifts
+    pub cmd: String,
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<LockedPackage>,
+    /// Hash of the executable, for extensions that run a local program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionLock {
+    pub version: u32,
+    pub extensions: BTreeMap<String, LockedExtension>,
+    /// `hmac-sha256:` of the content, empty when the lock is unsigned
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl Default for ExtensionLock {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            extensions: BTreeMap::new(),
+            signature: String::new(),
+        }
+    }
+}
+
+impl ExtensionLock {
+    pub fn path(project_dir: &Path) -> PathBuf {
+        project_dir.join(LOCKFILE_PATH)
+    }
+
+    /// Load and verify the lockfile of a project, None when it has none. Unsigned lockfiles
+    /// load with a warning, since nothing stops them from being rewritten.
+    pub fn load(project_dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(project_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let lock: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let key = lock_key();
+        lock.verify(key.as_deref())?;
+        if key.is_none() {
+            tracing::warn!(
+                "{} is not signed; set {} to protect it against tampering",
+                path.display(),
+                LOCK_KEY
+            );
+        }
+        Ok(Some(lock))
+    }
+
+    /// Sign the lockfile and write it to the project
+    pub fn save(&mut self, project_dir: &Path) -> Result<PathBuf> {
+        self.signature = self.sign(lock_key().as_deref())?;
+        let path = Self::path(project_dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    fn signed_content(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(self.version, &self.extensions))?)
+    }
+
+    /// The signature of the lock, empty without a key: a plain digest could be recomputed
+    /// by whoever edits the file, so it would protect nothing
+    fn sign(&self, key: Option<&str>) -> Result<String> {
+        let Some(key) = key else {
+            return Ok(String::new());
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes());
+        let tag = hmac::sign(&key, &self.signed_content()?);
+        Ok(format!("{}{}", HMAC_PREFIX, to_hex(tag.as_ref())))
+    }
+
+    fn verify(&self, key: Option<&str>) -> Result<()> {
+        if self.version != LOCKFILE_VERSION {
+            bail!(
+                "{} has version {}, expected {}",
+                LOCKFILE_PATH,
+                self.version,
+                LOCKFILE_VERSION
+            );
+        }
+        let tag = self.signature.strip_prefix(HMAC_PREFIX);
+        let key = match (tag, key) {
+            (Some(_), None) => {
+                bail!("{} is signed; set {} to verify it", LOCKFILE_PATH, LOCK_KEY)
+            }
+            // With a key configured, an unsigned lockfile could have been rewritten by anyone
+            (None, Some(_)) => bail!("{} is not signed", LOCKFILE_PATH),
+            (None, None) => return Ok(()),
+            (Some(_), Some(key)) => key,
+        };
+        let tag = tag.and_then(from_hex).unwrap_or_default();
+        let key = hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes());
+        hmac::verify(&key, &self.signed_content()?, &tag).map_err(|_| {
+            anyhow!(
+                "{} does not match its signature; it was changed by hand or with a different key",
+                LOCKFILE_PATH
+            )
+        })
+    }
+
+    /// Check an extension against the lock before it is spawned. Returns the command and
+    /// arguments to run, with package specs pinned to the locked versions.
+    pub fn check(&self, name: &str, cmd: &str, args: &[String]) -> Result<(String, Vec<String>)> {
+        let Some(locked) = self.extensions.get(name) else {
+            tracing::warn!("Extension {} is not in {}", name, LOCKFILE_PATH);
+            return Ok((cmd.to_string(), args.to_vec()));
+        };
+        if locked.cmd != cmd || locked.args != args {
+            bail!(
+                "Extension {} is configured differently than in {}; run `goose extension update {}` to lock the new configuration",
+                name,
+                LOCKFILE_PATH,
+                name
+            );
+        }
+        if let Some(expected) = &locked.sha256 {
+            let actual = hash_executable(cmd)?;
+            if &actual != expected {
+                bail!(
+                    "The executable of extension {} changed since it was locked (expected sha256 {}, found {})",
+                    name,
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        let mut args = args.to_vec();
+        if let Some(package) = &locked.package {
+            if let Some((index, _)) = package_spec(cmd, &args) {
+                args[index] = package.pinned_spec();
+            }
+        }
+        Ok((cmd.to_string(), args))
+    }
+}
+
+/// Check that the registry still serves the locked package with the hash it had when it was
+/// locked, so a republished or substituted package is not installed under the same version
+pub async fn verify_package(name: &str, package: &LockedPackage) -> Result<()> {
+    let Some(expected) = &package.integrity else {
+        return Ok(());
+    };
+    let resolved = match package.registry {
+        Registry::Npm => resolve_npm(&package.name, Some(&package.version)).await?,
+        Registry::Pypi => resolve_pypi(&package.name, Some(&package.version)).await?,
+    };
+    if resolved.integrity.as_ref() != Some(expected) {
+        bail!(
+            "The package of extension {} changed since it was locked (expected {}, found {})",
+            name,
+            expected,
+            resolved.integrity.as_deref().unwrap_or("no hash")
+        );
+    }
+    Ok(())
+}
+
+/// The command and arguments to spawn an extension with, checked against the lockfile of the
+/// session's project if it has one
+pub async fn locked_command(
+    project_dir: &Path,
+    name: &str,
+    cmd: &str,
+    args: &[String],
+) -> Result<(String, Vec<String>)> {
+    let Some(lock) = ExtensionLock::load(project_dir)? else {
+        return Ok((cmd.to_string(), args.to_vec()));
+    };
+    let command = lock.check(name, cmd, args)?;
+    if let Some(package) = lock
+        .extensions
+        .get(name)
+        .and_then(|locked| locked.package.as_ref())
+    {
+        verify_package(name, package).await?;
+    }
+    Ok(command)
+}
+
+fn lock_key() -> Option<String> {
+    Config::global().get_secret::<String>(LOCK_KEY).ok()
+}
+
+/// The position and value of the package spec in the arguments of a package runner
+fn package_spec(cmd: &str, args: &[String]) -> Option<(usize, Registry)> {
+    let runner = Path::new(cmd).file_name()?.to_str()?;
+    let registry = match runner {
+        "npx" => Registry::Npm,
+        "uvx" => Registry::Pypi,
+        _ => return None,
+    };
+    let mut skip_next = false;
+    for (index, arg) in args.iter().enumerate() {
+        if skip_next {
+            skip_next = false;
+            continue;
+        }
+        if arg.starts_with('-') {
+            // Options of uvx that take a value
+            skip_next = registry == Registry::Pypi
+                && matches!(
+                    arg.as_str(),
+                    "--python" | "--with" | "--index-url" | "--from"
+                );
+            continue;
+        }
+        return Some((index, registry));
+    }
+    None
+}
+
+/// Split a package spec into its name and version requirement
+fn split_spec(spec: &str, registry: Registry) -> (String, Option<String>) {
+    match registry {
+        Registry::Npm => match spec.rfind('@') {
+            // A leading @ starts a scope, not a version
+            Some(index) if index > 0 => (
+                spec[..index].to_string(),
+                Some(spec[index + 1..].to_string()),
+            ),
+            _ => (spec.to_string(), None),
+        },
+        Registry::Pypi => {
+            let index = spec.find(['=', '<', '>', '~', '!', '@']);
+            match index {
+                Some(index) => (
+                    spec[..index].trim().to_string(),
+                    Some(
+                        spec[index..]
+                            .trim_start_matches(['=', '@'])
+                            .trim()
+                            .to_string(),
+                    ),
+                ),
+                None => (spec.to_string(), None),
+            }
+        }
+    }
+}
+
+/// Resolve what an extension would run right now, None for extensions that don't run a command
+pub async fn resolve(config: &ExtensionConfig) -> Result<Option<LockedExtension>> {
+    let ExtensionConfig::Stdio { cmd, args, .. } = config else {
+        return Ok(None);
+    };
+    let (package, sha256) = match package_spec(cmd, args) {
+        Some((index, registry)) => {
+            let (name, version) = split_spec(&args[index], registry);
+            let package = match registry {
+                Registry::Npm => resolve_npm(&name, version.as_deref()).await?,
+                Registry::Pypi => resolve_pypi(&name, version.as_deref()).await?,
+            };
+            (Some(package), None)
+        }
+        None => (None, Some(hash_executable(cmd)?)),
+    };
+    Ok(Some(LockedExtension {
+        cmd: cmd.clone(),
+        args: args.clone(),
+        package,
+        sha256,
+    }))
+}
+
+async fn resolve_npm(name: &str, version: Option<&str>) -> Result<LockedPackage> {
+    let spec = match version {
+        Some(version) => format!("{}@{}", name, version),
+        None => name.to_string(),
+    };
+    let output = tokio::process::Command::new("npm")
+        .args(["view", &spec, "version", "dist.integrity", "--json"])
+        .output()
+        .await
+        .context("Failed to run npm to resolve the package version")?;
+    if !output.status.success() {
+        bail!(
+            "npm could not resolve {}: {}",
+            spec,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let view: Value = serde_json::from_slice(&output.stdout)?;
+    // A version range matching several versions lists each of them, the newest last
+    let view = match view {
+        Value::Array(versions) => versions
+            .into_iter()
+            .last()
+            .ok_or_else(|| anyhow!("No version of {} matches", spec))?,
+        view => view,
+    };
+    Ok(LockedPackage {
+        registry: Registry::Npm,
+        name: name.to_string(),
+        version: view["version"]
+            .as_str()
+            .ok_or_else(|| anyhow!("npm did not report a version for {}", spec))?
+            .to_string(),
+        integrity: view["dist.integrity"].as_str().map(String::from),
+    })
+}
+
+async fn resolve_pypi(name: &str, version: Option<&str>) -> Result<LockedPackage> {
+    // Only exact versions can be looked up directly; anything else locks the latest
+    let url = match version.filter(|v| !v.contains(['<', '>', '~', '!', ',', '*'])) {
+        Some(version) => format!("{}/{}/{}/json", PYPI_URL, name, version),
+        None => format!("{}/{}/json", PYPI_URL, name),
+    };
+    let release: Value = reqwest::get(&url)
+        .await?
+        .error_for_status()
+        .with_context(|| format!("PyPI could not resolve {}", name))?
+        .json()
+        .await?;
+    let integrity = release["urls"].as_array().and_then(|files| {
+        files
+            .iter()
+            .find(|file| file["packagetype"] == "sdist")
+            .or_else(|| files.first())
+            .and_then(|file| file["digests"]["sha256"].as_str())
+            .map(|digest| format!("{}{}", DIGEST_PREFIX, digest))
+    });
+    Ok(LockedPackage {
+        registry: Registry::Pypi,
+        name: name.to_string(),
+        version: release["info"]["version"]
+            .as_str()
+            .ok_or_else(|| anyhow!("PyPI did not report a version for {}", name))?
+            .to_string(),
+        integrity,
+    })
+}
+
+/// The sha256 of the program a command runs, looked up on PATH like the shell would
+fn hash_executable(cmd: &str) -> Result<String> {
+    let path = find_executable(cmd).ok_or_else(|| anyhow!("Could not find {} on PATH", cmd))?;
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(to_hex(&Sha256::digest(&bytes)))
+}
+
+/// Where a command's program is, looked up on PATH like the shell would
+pub(crate) fn find_executable(cmd: &str) -> Option<PathBuf> {
+    let path = Path::new(cmd);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(cmd);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let candidate = dir.join(format!("{}.exe", cmd));
+        candidate.is_file().then_some(candidate)
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn lock() -> ExtensionLock {
+        let mut lock = ExtensionLock::default();
+        lock.extensions.insert(
+            "github".to_string(),
+            LockedExtension {
+                cmd: "npx".to_string(),
+                args: args(&["-y", "@modelcontextprotocol/server-github"]),
+                package: Some(LockedPackage {
+                    registry: Registry::Npm,
+                    name: "@modelcontextprotocol/server-github".to_string(),
+                    version: "2025.4.8".to_string(),
+                    integrity: Some("sha512-abc".to_string()),
+                }),
+                sha256: None,
+            },
+        );
+        lock
+    }
+
+    #[test]
+    fn test_package_specs() {
+        assert_eq!(
+            package_spec("npx", &args(&["-y", "@scope/server@1.2.0"])),
+            Some((1, Registry::Npm))
+        );
+        assert_eq!(
+            package_spec(
+                "/usr/bin/uvx",
+                &args(&["--python", "3.12", "mcp-server-fetch"])
+            ),
+            Some((2, Registry::Pypi))
+        );
+        assert_eq!(package_spec("node", &args(&["server.js"])), None);
+
+        assert_eq!(
+            split_spec("@scope/server@^1.2", Registry::Npm),
+            ("@scope/server".to_string(), Some("^1.2".to_string()))
+        );
+        assert_eq!(
+            split_spec("@scope/server", Registry::Npm),
+            ("@scope/server".to_string(), None)
+        );
+        assert_eq!(
+            split_spec("mcp-server-fetch==0.6.2", Registry::Pypi),
+            ("mcp-server-fetch".to_string(), Some("0.6.2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_check_pins_and_detects_drift() {
+        let lock = lock();
+        let (cmd, pinned) = lock
+            .check(
+                "github",
+                "npx",
+                &args(&["-y", "@modelcontextprotocol/server-github"]),
+            )
+            .unwrap();
+        assert_eq!(cmd, "npx");
+        assert_eq!(
+            pinned,
+            args(&["-y", "@modelcontextprotocol/server-github@2025.4.8"])
+        );
+
+        let drifted = lock.check("github", "npx", &args(&["-y", "@evil/server-github"]));
+        assert!(drifted
+            .unwrap_err()
+            .to_string()
+            .contains("goose extension update github"));
+
+        // Extensions the lock doesn't know run as configured
+        assert_eq!(
+            lock.check("other", "node", &args(&["a.js"])).unwrap().1,
+            args(&["a.js"])
+        );
+    }
+
+    #[test]
+    fn test_signatures() {
+        let mut lock = lock();
+        lock.signature = lock.sign(Some("team secret")).unwrap();
+        assert!(lock.signature.starts_with(HMAC_PREFIX));
+        assert!(lock.verify(Some("team secret")).is_ok());
+        assert!(lock.verify(Some("other secret")).is_err());
+        assert!(lock.verify(None).is_err());
+
+        lock.extensions.get_mut("github").unwrap().package = None;
+        assert!(lock.verify(Some("team secret")).is_err());
+
+        let mut unsigned = ExtensionLock::default();
+        unsigned.signature = unsigned.sign(None).unwrap();
+        assert!(unsigned.signature.is_empty());
+        assert!(unsigned.verify(None).is_ok());
+        assert!(unsigned.verify(Some("team secret")).is_err());
+
+        // A signature that isn't hex doesn't verify, and doesn't panic
+        let mut garbled = lock.clone();
+        garbled.signature = format!("{}zz", HMAC_PREFIX);
+        assert!(garbled.verify(Some("team secret")).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(ExtensionLock::load(dir.path()).unwrap().is_none());
+        let mut lock = lock();
+        let path = lock.save(dir.path()).unwrap();
+        assert!(path.ends_with(LOCKFILE_PATH));
+        assert_eq!(ExtensionLock::load(dir.path()).unwrap(), Some(lock));
+    }
+}
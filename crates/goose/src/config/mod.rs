@@ -1,5 +1,6 @@
 pub mod base;
 mod experiments;
+pub mod extension_lock;
 pub mod extensions;
 pub mod permission;
 pub mod signup_openrouter;
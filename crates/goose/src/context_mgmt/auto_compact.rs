@@ -7,11 +7,15 @@ use crate::{
         common::{SYSTEM_PROMPT_TOKEN_OVERHEAD, TOOLS_TOKEN_OVERHEAD},
         get_messages_token_counts_async,
     },
-    token_counter::create_async_token_counter,
+    token_counter::{create_async_token_counter_for_model, AsyncTokenCounter},
 };
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
+/// Fraction of the context window used when `GOOSE_AUTO_COMPACT_THRESHOLD` is not set
+pub const DEFAULT_COMPACTION_THRESHOLD: f64 = 0.3;
+
 /// Result of auto-compaction check
 #[derive(Debug)]
 pub struct AutoCompactResult {
@@ -23,6 +27,34 @@ pub struct AutoCompactResult {
     pub tokens_before: Option<usize>,
     /// Token count after compaction (if compaction occurred)
     pub tokens_after: Option<usize>,
+    /// Number of messages folded into the summary
+    pub messages_summarized: usize,
+}
+
+/// Emitted when the history is compacted, so clients can tell the user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactionEvent {
+    /// Token count of the history before compaction
+    pub tokens_before: usize,
+    /// Token count of the history after compaction
+    pub tokens_after: usize,
+    /// Number of messages folded into the summary
+    pub messages_summarized: usize,
+}
+
+impl CompactionEvent {
+    /// Notification shown in the conversation when compaction happens
+    pub fn notice(&self) -> String {
+        if self.tokens_before == 0 {
+            return "Auto-compacted context to reduce token usage\n\n".to_string();
+        }
+        format!(
+            "Auto-compacted context: {} → {} tokens ({:.0}% reduction)\n\n",
+            self.tokens_before,
+            self.tokens_after,
+            (1.0 - (self.tokens_after as f64 / self.tokens_before as f64)) * 100.0
+        )
+    }
 }
 
 /// Result of checking if compaction is needed
@@ -42,6 +74,93 @@ pub struct CompactionCheckResult {
     pub percentage_until_compaction: f64,
 }
 
+/// The configured compaction threshold, as a fraction of the context window
+pub fn compaction_threshold() -> f64 {
+    Config::global()
+        .get_param::<f64>("GOOSE_AUTO_COMPACT_THRESHOLD")
+        .unwrap_or(DEFAULT_COMPACTION_THRESHOLD)
+}
+
+/// Whether `tokens` goes past `threshold` of the context window.
+/// Thresholds outside (0, 1) disable compaction.
+pub fn exceeds_threshold(tokens: usize, context_limit: usize, threshold: f64) -> bool {
+    if threshold <= 0.0 || threshold >= 1.0 || context_limit == 0 {
+        return false;
+    }
+    tokens as f64 / context_limit as f64 > threshold
+}
+
+/// Whether usage is above the compaction threshold, so that a reply compacts once each time
+/// usage crosses it instead of after every request while it stays above
+#[derive(Debug, Default)]
+pub struct ThresholdCrossing {
+    above: bool,
+}
+
+impl ThresholdCrossing {
+    /// Record whether the latest usage is over the threshold; true when it has just crossed it
+    pub fn update(&mut self, over: bool) -> bool {
+        let crossed = over && !self.above;
+        self.above = over;
+        crossed
+    }
+}
+
+/// Index of the message that opens the current turn: the last user message with text,
+/// as opposed to one that only carries tool results
+pub fn current_turn_start(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .rposition(|m| {
+            m.role == rmcp::model::Role::User && m.content.iter().any(|c| c.as_text().is_some())
+        })
+        .unwrap_or(0)
+}
+
+/// Summarize the turns before the current one and splice the summary back in front of it.
+///
+/// The current turn (the user's request and any tool calls made for it so far) is kept
+/// verbatim so the model can carry on where it is, as are any pinned messages. Returns `None` when there are no earlier
+/// turns to summarize.
+pub async fn compact_older_turns(
+    agent: &Agent,
+    messages: &[Message],
+) -> Result<Option<(Conversation, CompactionEvent)>> {
+    let split = current_turn_start(messages);
+    if split == 0 {
+        debug!("No earlier turns to compact");
+        return Ok(None);
+    }
+
+    let (older, current) = messages.split_at(split);
+    let token_counter = model_token_counter(agent).await?;
+    let current_tokens: usize = get_messages_token_counts_async(&token_counter, current)
+        .iter()
+        .sum();
+
+    let (mut compacted, older_tokens, summary_tokens) = perform_compaction(agent, older).await?;
+    for message in current {
+        compacted.push(message.clone());
+    }
+
+    Ok(Some((
+        compacted,
+        CompactionEvent {
+            tokens_before: older_tokens + current_tokens,
+            tokens_after: summary_tokens + current_tokens,
+            messages_summarized: older.iter().filter(|m| !m.pinned).count(),
+        },
+    )))
+}
+
+/// A token counter that uses the tokenizer of the agent's current model
+async fn model_token_counter(agent: &Agent) -> Result<AsyncTokenCounter> {
+    let provider = agent.provider().await?;
+    create_async_token_counter_for_model(&provider.get_model_config().model_name)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))
+}
+
 /// Check if messages need compaction without performing the compaction
 ///
 /// This function analyzes the current token usage and returns detailed information
@@ -64,12 +183,7 @@ pub async fn check_compaction_needed(
     session_metadata: Option<&crate::session::storage::SessionMetadata>,
 ) -> Result<CompactionCheckResult> {
     // Get threshold from config or use override
-    let config = Config::global();
-    let threshold = threshold_override.unwrap_or_else(|| {
-        config
-            .get_param::<f64>("GOOSE_AUTO_COMPACT_THRESHOLD")
-            .unwrap_or(0.3) // Default to 30%
-    });
+    let threshold = threshold_override.unwrap_or_else(compaction_threshold);
 
     let provider = agent.provider().await?;
     let context_limit = provider.get_model_config().context_limit();
@@ -77,9 +191,7 @@ pub async fn check_compaction_needed(
     let (current_tokens, token_source) = match session_metadata.and_then(|m| m.total_tokens) {
         Some(tokens) => (tokens as usize, "session metadata"),
         None => {
-            let token_counter = create_async_token_counter()
-                .await
-                .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+            let token_counter = model_token_counter(agent).await?;
             let token_counts = get_messages_token_counts_async(&token_counter, messages);
             (token_counts.iter().sum(), "estimated")
         }
@@ -99,12 +211,7 @@ pub async fn check_compaction_needed(
         0.0
     };
 
-    // Check if compaction is needed (disabled if threshold is invalid)
-    let needs_compaction = if threshold <= 0.0 || threshold >= 1.0 {
-        false
-    } else {
-        usage_ratio > threshold
-    };
+    let needs_compaction = exceeds_threshold(current_tokens, context_limit, threshold);
 
     debug!(
         "Compaction check: {} / {} tokens ({:.1}%), threshold: {:.1}%, needs compaction: {}, source: {}",
@@ -143,9 +250,7 @@ pub async fn perform_compaction(
     messages: &[Message],
 ) -> Result<(Conversation, usize, usize)> {
     // Get token counter to measure before/after
-    let token_counter = create_async_token_counter()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+    let token_counter = model_token_counter(agent).await?;
 
     // Calculate tokens before compaction
     let token_counts_before = get_messages_token_counts_async(&token_counter, messages);
@@ -203,6 +308,7 @@ pub async fn check_and_compact_messages(
             messages: Conversation::new_unvalidated(messages.to_vec()),
             tokens_before: None,
             tokens_after: None,
+            messages_summarized: 0,
         });
     }
 
@@ -235,6 +341,7 @@ pub async fn check_and_compact_messages(
 
     Ok(AutoCompactResult {
         compacted: true,
+        messages_summarized: messages_to_compact.len(),
         messages: compacted_messages,
         tokens_before: Some(tokens_before + SYSTEM_PROMPT_TOKEN_OVERHEAD + TOOLS_TOKEN_OVERHEAD),
         tokens_after: Some(tokens_after + SYSTEM_PROMPT_TOKEN_OVERHEAD + TOOLS_TOKEN_OVERHEAD),
@@ -310,6 +417,7 @@ mod tests {
             message_count,
             working_dir: PathBuf::from(working_dir),
             description: "Test session".to_string(),
+            title: String::new(),
             schedule_id: Some("test_job".to_string()),
             project_id: None,
             total_tokens: Some(100),
@@ -318,6 +426,11 @@ mod tests {
             accumulated_total_tokens: Some(100),
             accumulated_input_tokens: Some(50),
             accumulated_output_tokens: Some(50),
+            model_usage: Default::default(),
+            usage_ledger: Default::default(),
+            context: None,
+            tags: Vec::new(),
+            properties: Default::default(),
         }
     }
 
@@ -554,7 +667,7 @@ mod tests {
         // Debug info if not compacted
         if !result.compacted {
             let provider = agent.provider().await.unwrap();
-            let token_counter = create_async_token_counter().await.unwrap();
+            let token_counter = model_token_counter(&agent).await.unwrap();
             let token_counts = get_messages_token_counts_async(&token_counter, &messages);
             let total_tokens: usize = token_counts.iter().sum();
             let context_limit = provider.get_model_config().context_limit();
@@ -800,4 +913,71 @@ mod tests {
         // With estimation, likely won't trigger compaction
         assert!(!result_edge_case.needs_compaction);
     }
+
+    #[test]
+    fn test_exceeds_threshold() {
+        assert!(exceeds_threshold(81_000, 100_000, 0.8));
+        assert!(!exceeds_threshold(79_000, 100_000, 0.8));
+        assert!(!exceeds_threshold(99_000, 100_000, 0.0));
+        assert!(!exceeds_threshold(99_000, 100_000, 1.0));
+        assert!(!exceeds_threshold(99_000, 0, 0.8));
+    }
+
+    #[test]
+    fn test_threshold_crossing_fires_once_per_crossing() {
+        let mut crossing = ThresholdCrossing::default();
+        assert!(!crossing.update(false));
+        assert!(crossing.update(true));
+        // Still above after compacting: no second compaction
+        assert!(!crossing.update(true));
+        assert!(!crossing.update(false));
+        assert!(crossing.update(true));
+    }
+
+    #[test]
+    fn test_current_turn_start_skips_tool_responses() {
+        let messages = vec![
+            create_test_message("first request"),
+            Message::assistant().with_text("first answer"),
+            create_test_message("second request"),
+            Message::assistant().with_text("calling a tool"),
+            Message::user().with_tool_response("call_1", Ok(vec![])),
+        ];
+        assert_eq!(current_turn_start(&messages), 2);
+        assert_eq!(current_turn_start(&messages[..2]), 0);
+    }
+
+    #[tokio::test]
+    async fn test_compact_older_turns_keeps_current_turn() {
+        let mock_provider = Arc::new(MockProvider {
+            model_config: ModelConfig::new("test-model")
+                .unwrap()
+                .with_context_limit(50_000.into()),
+        });
+
+        let agent = Agent::new();
+        let _ = agent.update_provider(mock_provider).await;
+
+        let messages = vec![
+            create_test_message("first request"),
+            Message::assistant().with_text("first answer"),
+            create_test_message("second request"),
+            Message::assistant().with_text("working on it"),
+        ];
+
+        let (compacted, event) = compact_older_turns(&agent, &messages)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(event.messages_summarized, 2);
+        assert!(event.tokens_before > 0);
+        let tail = &compacted.messages()[compacted.len() - 2..];
+        assert_eq!(tail[0].as_concat_text(), "second request");
+        assert_eq!(tail[1].as_concat_text(), "working on it");
+
+        // A single turn has nothing older to summarize
+        let result = compact_older_turns(&agent, &messages[2..]).await.unwrap();
+        assert!(result.is_none());
+    }
 }
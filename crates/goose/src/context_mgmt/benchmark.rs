@@ -0,0 +1,532 @@
+//! How well each compaction strategy keeps what matters for the task.
+//!
+//! A long generated session hides facts ("needles") in user messages, assistant replies and
+//! tool output at different depths. Each strategy compacts it to a fraction of its size, and
+//! the result is scored by how many needles survive in the text and, with a provider, how
+//! many questions about them the model can still answer. `goose bench compaction` runs it.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use mcp_core::tool::ToolCall;
+use rmcp::model::Content;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::context_mgmt::get_messages_token_counts;
+use crate::context_mgmt::summarize::summarize_messages;
+use crate::context_mgmt::truncate::{truncate_messages, TruncationStrategyKind};
+use crate::conversation::message::{Message, MessageContent};
+use crate::providers::base::Provider;
+use crate::token_counter::TokenCounter;
+
+/// Facts to hide, as what they are, the question that asks for them and a value prefix
+const FACTS: &[(&str, &str, &str)] = &[
+    (
+        "staging deploy key",
+        "What is the staging deploy key?",
+        "DK",
+    ),
+    (
+        "on-call badge number",
+        "What is the on-call badge number?",
+        "BN",
+    ),
+    (
+        "database migration id",
+        "What is the database migration id?",
+        "MG",
+    ),
+    ("release codename", "What is the release codename?", "RC"),
+    ("feature flag id", "What is the feature flag id?", "FF"),
+    ("incident ticket", "What is the incident ticket?", "IT"),
+    (
+        "vendor account number",
+        "What is the vendor account number?",
+        "VA",
+    ),
+    (
+        "backup bucket suffix",
+        "What is the backup bucket suffix?",
+        "BB",
+    ),
+];
+
+/// Lines of output each generated tool call returns
+const TOOL_OUTPUT_LINES: usize = 24;
+
+/// Where a needle is hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    UserMessage,
+    AssistantMessage,
+    ToolOutput,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Needle {
+    pub fact: String,
+    pub value: String,
+    pub question: String,
+    /// Index of the message holding it
+    pub message: usize,
+    pub placement: Placement,
+}
+
+/// A generated session and the needles hidden in it
+#[derive(Debug, Clone)]
+pub struct Haystack {
+    pub messages: Vec<Message>,
+    pub needles: Vec<Needle>,
+}
+
+/// Build a session of `turns` tool-using turns with up to one needle per fact. The first
+/// needle is in the task itself and the last in the final turn; the rest are spread evenly
+/// and rotate through user messages, tool output and assistant replies.
+pub fn build_haystack(turns: usize, seed: u64) -> Haystack {
+    let turns = turns.max(2);
+    let mut rng = seed.max(1);
+    let count = FACTS.len().min(turns + 1);
+    let needle_turns: Vec<usize> = (0..count)
+        .map(|i| {
+            if count == 1 {
+                0
+            } else {
+                i * turns / (count - 1)
+            }
+        })
+        .collect();
+
+    let mut messages = Vec::new();
+    let mut needles = Vec::new();
+    let mut plant = |message: usize, index: usize, placement: Placement| {
+        let (fact, question, prefix) = FACTS[index];
+        rng = next_random(rng);
+        let needle = Needle {
+            fact: fact.to_string(),
+            value: format!("{}-{:05}", prefix, rng % 100_000),
+            question: question.to_string(),
+            message,
+            placement,
+        };
+        let statement = format!("Note: the {} is {}.", needle.fact, needle.value);
+        needles.push(needle);
+        statement
+    };
+
+    let task = plant(messages.len(), 0, Placement::UserMessage);
+    messages.push(Message::user().with_text(format!(
+        "We're cleaning up the build scripts in this repository. {}",
+        task
+    )));
+
+    for turn in 1..=turns {
+        let needle = needle_turns.iter().position(|&t| t == turn && t != 0);
+        // The last turn's needle goes in the user's words, so it is the freshest fact
+        let placement = needle.map(|index| {
+            if turn == turns {
+                Placement::UserMessage
+            } else {
+                [
+                    Placement::ToolOutput,
+                    Placement::AssistantMessage,
+                    Placement::UserMessage,
+                ][index % 3]
+            }
+        });
+
+        let mut ask = format!("Now look at step {} of the build.", turn);
+        if placement == Some(Placement::UserMessage) {
+            ask = format!(
+                "{} {}",
+                ask,
+                plant(messages.len(), needle.unwrap(), Placement::UserMessage)
+            );
+        }
+        messages.push(Message::user().with_text(ask));
+
+        let id = format!("call_{}", turn);
+        messages.push(Message::assistant().with_tool_request(
+            &id,
+            Ok(ToolCall::new(
+                "developer__shell",
+                json!({"command": format!("cat scripts/step{}.sh", turn)}),
+            )),
+        ));
+
+        let mut output = filler_output(turn);
+        if placement == Some(Placement::ToolOutput) {
+            let line = TOOL_OUTPUT_LINES / 2;
+            output.insert(
+                line,
+                format!(
+                    "# {}",
+                    plant(messages.len(), needle.unwrap(), Placement::ToolOutput)
+                ),
+            );
+        }
+        messages.push(
+            Message::user().with_tool_response(&id, Ok(vec![Content::text(output.join("\n"))])),
+        );
+
+        // Nothing here repeats the tool output, which would make it look referenced
+        let mut reply = format!("Step {} looks fine.", turn);
+        if placement == Some(Placement::AssistantMessage) {
+            reply = format!(
+                "{} {}",
+                reply,
+                plant(messages.len(), needle.unwrap(), Placement::AssistantMessage)
+            );
+        }
+        messages.push(Message::assistant().with_text(reply));
+    }
+
+    messages.push(Message::user().with_text("Continue with the next step."));
+    Haystack { messages, needles }
+}
+
+fn next_random(state: u64) -> u64 {
+    // xorshift64, enough to vary the values between seeds
+    let mut x = state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+fn filler_output(turn: usize) -> Vec<String> {
+    (0..TOOL_OUTPUT_LINES)
+        .map(|line| {
+            format!(
+                "cc -O2 -c src/unit{}_{}.c -o out/unit{}_{}.o",
+                turn, line, turn, line
+            )
+        })
+        .collect()
+}
+
+/// A compaction strategy under test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionStrategy {
+    Truncate(TruncationStrategyKind),
+    /// Summarize with the model, as auto-compaction does
+    Summarize,
+}
+
+impl CompactionStrategy {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Truncate(TruncationStrategyKind::OldestFirst),
+            Self::Truncate(TruncationStrategyKind::MiddleOut),
+            Self::Truncate(TruncationStrategyKind::ToolOutputFirst),
+            Self::Truncate(TruncationStrategyKind::Importance),
+            Self::Summarize,
+        ]
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Truncate(kind) => serde_json::to_value(kind)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            Self::Summarize => "summarize".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.trim().eq_ignore_ascii_case("summarize") {
+            return Some(Self::Summarize);
+        }
+        TruncationStrategyKind::from_name(name).map(Self::Truncate)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyResult {
+    pub strategy: String,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    pub needles: usize,
+    /// Needles whose value is still somewhere in the compacted conversation
+    pub retained: usize,
+    /// Questions the model answered correctly from the compacted conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answered: Option<usize>,
+    /// The facts that were lost
+    pub lost: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StrategyResult {
+    pub fn recall(&self) -> f64 {
+        ratio(self.retained, self.needles)
+    }
+
+    pub fn answer_recall(&self) -> Option<f64> {
+        self.answered.map(|answered| ratio(answered, self.needles))
+    }
+
+    pub fn compression(&self) -> f64 {
+        ratio(self.tokens_after, self.tokens_before)
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 / whole as f64
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionReport {
+    /// Messages in the session before compaction
+    pub messages: usize,
+    /// The size each strategy compacts to, as a fraction of the session
+    pub target: f64,
+    pub results: Vec<StrategyResult>,
+}
+
+/// Compact the haystack with each strategy and score what survives. Summarizing and asking
+/// questions need a provider; without one, summarizing is reported as skipped.
+pub async fn evaluate(
+    haystack: &Haystack,
+    strategies: &[CompactionStrategy],
+    target: f64,
+    provider: Option<Arc<dyn Provider>>,
+) -> CompactionReport {
+    let token_counter = TokenCounter::new();
+    let counts = get_messages_token_counts(&token_counter, &haystack.messages);
+    let tokens_before: usize = counts.iter().sum();
+    let limit = (tokens_before as f64 * target) as usize;
+
+    let mut results = Vec::with_capacity(strategies.len());
+    for strategy in strategies {
+        let compacted = match strategy {
+            CompactionStrategy::Truncate(kind) => {
+                truncate(&haystack.messages, &counts, limit, *kind)
+            }
+            CompactionStrategy::Summarize => match &provider {
+                Some(provider) => {
+                    summarize_messages(provider.clone(), &haystack.messages, &token_counter, limit)
+                        .await
+                        .map(|(conversation, _)| conversation.messages().to_vec())
+                }
+                None => Err(anyhow!("needs a provider")),
+            },
+        };
+
+        let mut result = match compacted {
+            Ok(messages) => {
+                let after: usize = get_messages_token_counts(&token_counter, &messages)
+                    .iter()
+                    .sum();
+                let mut result = score(&strategy.name(), haystack, &messages, tokens_before, after);
+                if let Some(provider) = &provider {
+                    result.answered = Some(ask(provider.as_ref(), haystack, &messages).await);
+                }
+                result
+            }
+            Err(e) => StrategyResult {
+                strategy: strategy.name(),
+                tokens_before,
+                tokens_after: tokens_before,
+                needles: haystack.needles.len(),
+                retained: 0,
+                answered: None,
+                lost: Vec::new(),
+                error: Some(e.to_string()),
+            },
+        };
+        result.tokens_before = tokens_before;
+        results.push(result);
+    }
+
+    CompactionReport {
+        messages: haystack.messages.len(),
+        target,
+        results,
+    }
+}
+
+fn truncate(
+    messages: &[Message],
+    counts: &[usize],
+    limit: usize,
+    kind: TruncationStrategyKind,
+) -> Result<Vec<Message>> {
+    let (conversation, _) = truncate_messages(messages, counts, limit, kind.strategy())?;
+    Ok(conversation.messages().to_vec())
+}
+
+/// Count the needles whose values are still in the compacted messages
+fn score(
+    strategy: &str,
+    haystack: &Haystack,
+    compacted: &[Message],
+    tokens_before: usize,
+    tokens_after: usize,
+) -> StrategyResult {
+    let text = compacted
+        .iter()
+        .map(searchable_text)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let lost: Vec<String> = haystack
+        .needles
+        .iter()
+        .filter(|needle| !text.contains(&needle.value))
+        .map(|needle| needle.fact.clone())
+        .collect();
+    StrategyResult {
+        strategy: strategy.to_string(),
+        tokens_before,
+        tokens_after,
+        needles: haystack.needles.len(),
+        retained: haystack.needles.len() - lost.len(),
+        answered: None,
+        lost,
+        error: None,
+    }
+}
+
+/// Everything a model would read in a message: text, tool arguments and tool output
+fn searchable_text(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::Text(text) => Some(text.text.clone()),
+            MessageContent::ToolRequest(request) => request
+                .tool_call
+                .as_ref()
+                .ok()
+                .map(|call| call.arguments.to_string()),
+            MessageContent::ToolResponse(response) => {
+                response.tool_result.as_ref().ok().map(|contents| {
+                    contents
+                        .iter()
+                        .filter_map(|content| content.as_text().map(|text| text.text.clone()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Ask the model about each needle and count the right answers
+async fn ask(provider: &dyn Provider, haystack: &Haystack, compacted: &[Message]) -> usize {
+    let system = "Answer from the conversation only. Reply with just the value asked for, \
+                  or UNKNOWN if the conversation doesn't say.";
+    let mut answered = 0;
+    for needle in &haystack.needles {
+        let mut messages = compacted.to_vec();
+        messages.push(Message::user().with_text(&needle.question));
+        match provider.complete(system, &messages, &[]).await {
+            Ok((response, _)) if response.as_concat_text().contains(&needle.value) => answered += 1,
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Probe for {} failed: {}", needle.fact, e),
+        }
+    }
+    answered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Roughly four characters a token, so the tests don't need the tokenizer
+    fn approximate_counts(messages: &[Message]) -> Vec<usize> {
+        messages
+            .iter()
+            .map(|message| searchable_text(message).len() / 4 + 4)
+            .collect()
+    }
+
+    fn run(haystack: &Haystack, kind: TruncationStrategyKind) -> StrategyResult {
+        let counts = approximate_counts(&haystack.messages);
+        let before: usize = counts.iter().sum();
+        let limit = before / 2;
+        let compacted = truncate(&haystack.messages, &counts, limit, kind).unwrap();
+        let after: usize = approximate_counts(&compacted).iter().sum();
+        assert!(after <= limit, "{:?} left {} of {}", kind, after, limit);
+        score(&format!("{:?}", kind), haystack, &compacted, before, after)
+    }
+
+    #[test]
+    fn test_haystack_is_deterministic() {
+        let haystack = build_haystack(20, 7);
+        assert_eq!(haystack.needles.len(), FACTS.len());
+        assert_eq!(haystack.needles[0].message, 0);
+        let text: Vec<String> = haystack.messages.iter().map(searchable_text).collect();
+        for needle in &haystack.needles {
+            assert_eq!(
+                text.iter().filter(|t| t.contains(&needle.value)).count(),
+                1,
+                "{} should appear once",
+                needle.value
+            );
+            assert!(text[needle.message].contains(&needle.value));
+        }
+        let again = build_haystack(20, 7);
+        assert_eq!(
+            haystack.needles[3].value, again.needles[3].value,
+            "the same seed builds the same session"
+        );
+    }
+
+    #[test]
+    fn test_truncation_keeps_the_freshest_fact() {
+        let haystack = build_haystack(20, 7);
+        let last = haystack.needles.last().unwrap().value.clone();
+        for kind in [
+            TruncationStrategyKind::OldestFirst,
+            TruncationStrategyKind::MiddleOut,
+            TruncationStrategyKind::Importance,
+        ] {
+            let counts = approximate_counts(&haystack.messages);
+            let limit = counts.iter().sum::<usize>() / 2;
+            let compacted = truncate(&haystack.messages, &counts, limit, kind).unwrap();
+            let text: String = compacted.iter().map(searchable_text).collect();
+            assert!(text.contains(&last), "{:?} lost the latest fact", kind);
+        }
+    }
+
+    #[test]
+    fn test_strategies_that_keep_the_task() {
+        let haystack = build_haystack(20, 7);
+        let oldest_first = run(&haystack, TruncationStrategyKind::OldestFirst);
+        let middle_out = run(&haystack, TruncationStrategyKind::MiddleOut);
+        let importance = run(&haystack, TruncationStrategyKind::Importance);
+        run(&haystack, TruncationStrategyKind::ToolOutputFirst);
+
+        // The fact in the task description is the first thing oldest-first drops
+        let task = &haystack.needles[0].fact;
+        assert!(oldest_first.lost.contains(task));
+        assert!(!middle_out.lost.contains(task));
+        // Importance keeps everything the user said, so it never does worse
+        for needle in &haystack.needles {
+            if needle.placement == Placement::UserMessage {
+                assert!(!importance.lost.contains(&needle.fact), "{}", needle.fact);
+            }
+        }
+        assert!(importance.retained >= oldest_first.retained);
+    }
+
+    #[test]
+    fn test_strategy_names_round_trip() {
+        for strategy in CompactionStrategy::all() {
+            assert_eq!(
+                CompactionStrategy::from_name(&strategy.name()),
+                Some(strategy)
+            );
+        }
+    }
+}
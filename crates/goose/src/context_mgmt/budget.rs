@@ -0,0 +1,325 @@
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::extension::ExtensionInfo;
+use crate::config::{Config, ConfigError};
+use crate::context_mgmt::get_messages_token_counts_async;
+use crate::context_mgmt::truncate::{truncate_messages, TruncationStrategyKind};
+use crate::conversation::message::Message;
+use crate::token_counter::AsyncTokenCounter;
+
+/// Config key for the token budget, e.g. in config.yaml:
+///
+/// ```yaml
+/// GOOSE_TOKEN_BUDGET:
+///   system_prompt: 1500
+///   tools: 2000
+///   memory: 500
+///   history: 3000
+/// ```
+pub const TOKEN_BUDGET_KEY: &str = "GOOSE_TOKEN_BUDGET";
+
+/// The extension whose instructions carry the user's saved memories
+const MEMORY_EXTENSION: &str = "memory";
+
+/// Tools that are kept ahead of extension tools when the tool budget is tight
+const PLATFORM_TOOL_PREFIX: &str = "platform__";
+
+/// The most tokens each part of a request may use. Parts without a limit are sent in full.
+///
+/// Small local models can have context windows of 8K tokens or less, which the tool schemas
+/// of a few extensions can fill on their own. The budget is applied before every request:
+/// memories are cut to the memory limit, extension instructions are left out, largest first,
+/// to fit the system prompt limit, tools past the tool limit are left out (platform tools
+/// first in line), and the oldest history is left out of the request — though not the
+/// conversation — to fit the history limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenBudget {
+    /// Tokens for the system prompt, including extension instructions
+    pub system_prompt: Option<usize>,
+    /// Tokens for the tool schemas
+    pub tools: Option<usize>,
+    /// Tokens for the memory extension's instructions, which hold the saved memories
+    pub memory: Option<usize>,
+    /// Tokens for the conversation history
+    pub history: Option<usize>,
+}
+
+impl TokenBudget {
+    /// The configured budget; no limits if it is not set or can't be read
+    pub fn from_config() -> Self {
+        match Config::global().get_param::<TokenBudget>(TOKEN_BUDGET_KEY) {
+            Ok(budget) => budget,
+            Err(ConfigError::NotFound(_)) => Self::default(),
+            Err(e) => {
+                tracing::warn!("Ignoring {}: {}", TOKEN_BUDGET_KEY, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Cut the memory extension's instructions to the memory limit
+    pub fn fit_memory(&self, counter: &AsyncTokenCounter, extensions: &mut [ExtensionInfo]) {
+        let Some(limit) = self.memory else {
+            return;
+        };
+        for extension in extensions
+            .iter_mut()
+            .filter(|extension| extension.name == MEMORY_EXTENSION)
+        {
+            if let Some(cut) = cut_to_tokens(counter, &extension.instructions, limit) {
+                tracing::info!("Cut memories to the {} token memory budget", limit);
+                extension.instructions = cut;
+            }
+        }
+    }
+
+    /// Build the system prompt within its limit, leaving out extension instructions, largest
+    /// first, until it fits. A prompt that is still over the limit without any is sent anyway.
+    pub fn fit_system_prompt(
+        &self,
+        counter: &AsyncTokenCounter,
+        mut extensions: Vec<ExtensionInfo>,
+        build: impl Fn(Vec<ExtensionInfo>) -> String,
+    ) -> String {
+        let mut prompt = build(extensions.clone());
+        let Some(limit) = self.system_prompt else {
+            return prompt;
+        };
+
+        while counter.count_tokens(&prompt) > limit {
+            let Some(largest) = extensions
+                .iter_mut()
+                .filter(|extension| !extension.instructions.is_empty())
+                .max_by_key(|extension| extension.instructions.len())
+            else {
+                tracing::warn!(
+                    "The system prompt is over its {} token budget even without extension instructions",
+                    limit
+                );
+                break;
+            };
+            tracing::info!(
+                "Leaving out the instructions of extension '{}' to fit the system prompt budget",
+                largest.name
+            );
+            largest.instructions.clear();
+            prompt = build(extensions.clone());
+        }
+        prompt
+    }
+
+    /// Keep the tools that fit the tool limit, platform tools first and then in order.
+    /// Returns the tools to send and the names of those left out.
+    pub fn fit_tools(
+        &self,
+        counter: &AsyncTokenCounter,
+        tools: Vec<Tool>,
+    ) -> (Vec<Tool>, Vec<String>) {
+        let Some(limit) = self.tools else {
+            return (tools, Vec::new());
+        };
+        if counter.count_tokens_for_tools(&tools) <= limit {
+            return (tools, Vec::new());
+        }
+
+        let (platform, others): (Vec<Tool>, Vec<Tool>) = tools
+            .into_iter()
+            .partition(|tool| tool.name.starts_with(PLATFORM_TOOL_PREFIX));
+        let mut kept = Vec::new();
+        let mut left_out = Vec::new();
+        for tool in platform.into_iter().chain(others) {
+            kept.push(tool);
+            if counter.count_tokens_for_tools(&kept) > limit {
+                if let Some(tool) = kept.pop() {
+                    left_out.push(tool.name.to_string());
+                }
+            }
+        }
+        (kept, left_out)
+    }
+
+    /// The messages to send, without the oldest ones if the history is over its limit.
+    /// Pinned messages are always sent.
+    pub fn fit_history(&self, counter: &AsyncTokenCounter, messages: &[Message]) -> Vec<Message> {
+        let Some(limit) = self.history else {
+            return messages.to_vec();
+        };
+        let token_counts = get_messages_token_counts_async(counter, messages);
+        if token_counts.iter().sum::<usize>() <= limit {
+            return messages.to_vec();
+        }
+
+        let strategy = TruncationStrategyKind::from_config(Config::global());
+        match truncate_messages(messages, &token_counts, limit, strategy.strategy()) {
+            Ok((conversation, _)) => conversation.messages().clone(),
+            Err(e) => {
+                tracing::warn!(
+                    "Could not fit the history into its {} token budget, sending all of it: {}",
+                    limit,
+                    e
+                );
+                messages.to_vec()
+            }
+        }
+    }
+}
+
+/// Note for the system prompt naming the tools left out to fit the budget
+pub fn tools_left_out_note(left_out: &[String]) -> String {
+    format!(
+        "To fit the token budget, these tools are not available in this session: {}. \
+        Tell the user if one of them is needed.",
+        left_out.join(", ")
+    )
+}
+
+/// The whole lines of `text` from the start that fit in `limit` tokens, or None if all of it fits
+fn cut_to_tokens(counter: &AsyncTokenCounter, text: &str, limit: usize) -> Option<String> {
+    if counter.count_tokens(text) <= limit {
+        return None;
+    }
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for line in text.lines() {
+        let tokens = counter.count_tokens(line) + 1;
+        if used + tokens > limit {
+            break;
+        }
+        used += tokens;
+        kept.push(line);
+    }
+    kept.push("[... cut to fit the token budget ...]");
+    Some(kept.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_counter::create_async_token_counter;
+    use rmcp::object;
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(
+            name.to_string(),
+            "A tool with a description long enough to take up some tokens in the request",
+            object!({
+                "type": "object",
+                "properties": {"path": {"type": "string", "description": "Where to look"}}
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_budget_changes_nothing() {
+        let counter = create_async_token_counter().await.unwrap();
+        let budget = TokenBudget::default();
+        assert!(budget.is_unlimited());
+
+        let tools = vec![tool("developer__shell"), tool("platform__read_more")];
+        let (kept, left_out) = budget.fit_tools(&counter, tools.clone());
+        assert_eq!(kept, tools);
+        assert!(left_out.is_empty());
+
+        let messages = vec![Message::user().with_text("hello")];
+        assert_eq!(budget.fit_history(&counter, &messages), messages);
+    }
+
+    #[tokio::test]
+    async fn test_fit_tools_keeps_platform_tools() {
+        let counter = create_async_token_counter().await.unwrap();
+        let tools = vec![
+            tool("developer__shell"),
+            tool("developer__text_editor"),
+            tool("platform__read_more"),
+        ];
+        let two_tools = counter.count_tokens_for_tools(&tools[..2]);
+        let budget = TokenBudget {
+            tools: Some(two_tools),
+            ..Default::default()
+        };
+
+        let (kept, left_out) = budget.fit_tools(&counter, tools);
+
+        let kept: Vec<&str> = kept.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(kept, vec!["platform__read_more", "developer__shell"]);
+        assert_eq!(left_out, vec!["developer__text_editor".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_fit_memory_and_system_prompt() {
+        let counter = create_async_token_counter().await.unwrap();
+        let memories = (1..=200)
+            .map(|i| format!("- remembered fact number {}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut extensions = vec![
+            ExtensionInfo::new("memory", &memories, false),
+            ExtensionInfo::new("developer", &"Use the shell. ".repeat(200), false),
+        ];
+        let budget = TokenBudget {
+            memory: Some(100),
+            system_prompt: Some(300),
+            ..Default::default()
+        };
+
+        budget.fit_memory(&counter, &mut extensions);
+        assert!(counter.count_tokens(&extensions[0].instructions) <= 120);
+        assert!(extensions[0]
+            .instructions
+            .starts_with("- remembered fact number 1\n"));
+
+        let build = |extensions: Vec<ExtensionInfo>| {
+            extensions
+                .iter()
+                .map(|extension| format!("## {}\n{}", extension.name, extension.instructions))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let prompt = budget.fit_system_prompt(&counter, extensions, build);
+        assert!(counter.count_tokens(&prompt) <= 300);
+        assert!(prompt.contains("remembered fact number 1"));
+        assert!(!prompt.contains("Use the shell."));
+    }
+
+    #[tokio::test]
+    async fn test_fit_history_leaves_out_oldest() {
+        let counter = create_async_token_counter().await.unwrap();
+        let mut messages = Vec::new();
+        for i in 0..20 {
+            messages.push(Message::user().with_text(format!("Question {} about the build", i)));
+            messages.push(Message::assistant().with_text(format!("Answer {} about the build", i)));
+        }
+        messages.push(Message::user().with_text("What now?"));
+        let budget = TokenBudget {
+            history: Some(100),
+            ..Default::default()
+        };
+
+        let fitted = budget.fit_history(&counter, &messages);
+
+        assert!(fitted.len() < messages.len());
+        assert_eq!(fitted.last(), messages.last());
+        let tokens: usize = get_messages_token_counts_async(&counter, &fitted)
+            .iter()
+            .sum();
+        assert!(tokens <= 100);
+    }
+
+    #[test]
+    fn test_budget_deserializes_partially() {
+        let budget: TokenBudget = serde_json::from_str(r#"{"tools": 2000}"#).unwrap();
+        assert_eq!(
+            budget,
+            TokenBudget {
+                tools: Some(2000),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use rmcp::model::Tool;
+
+use crate::agents::platform_tools::{load_tool_tool, PLATFORM_LOAD_TOOL_TOOL_NAME};
+use crate::config::Config;
+use crate::model::ModelConfig;
+
+/// Config key to force constrained mode on (`true`) or off (`false`); when not set it is on
+/// for models with a context window of [`CONSTRAINED_CONTEXT_LIMIT`] tokens or less
+pub const CONSTRAINED_MODE_KEY: &str = "GOOSE_CONSTRAINED_MODE";
+
+/// The largest context window that gets constrained mode by default
+pub const CONSTRAINED_CONTEXT_LIMIT: usize = 8_192;
+
+/// Fraction of the context window the history may use before earlier turns are summarized at
+/// the start of the next turn, in place of `GOOSE_AUTO_COMPACT_THRESHOLD`
+pub const CONSTRAINED_COMPACTION_THRESHOLD: f64 = 0.15;
+
+/// Longest tool description shown in the tool index
+const INDEX_DESCRIPTION_CHARS: usize = 80;
+
+/// Whether to run in constrained mode for this model.
+///
+/// Small local models (gemma2-9b and friends have 8K tokens) can't fit the full system prompt
+/// and every tool schema and still leave room for a conversation. In constrained mode the
+/// system prompt is a compact variant listing tools by name only, the model sees the schema of
+/// one tool at a time (loading it with platform__load_tool), and earlier turns are summarized
+/// as soon as they take a small share of the window.
+pub fn is_constrained(model_config: &ModelConfig) -> bool {
+    constrained_for(
+        model_config.context_limit(),
+        Config::global()
+            .get_param::<bool>(CONSTRAINED_MODE_KEY)
+            .ok(),
+    )
+}
+
+fn constrained_for(context_limit: usize, setting: Option<bool>) -> bool {
+    setting.unwrap_or(context_limit <= CONSTRAINED_CONTEXT_LIMIT)
+}
+
+/// The compaction threshold to use at the start of a turn, if constrained mode changes it
+pub fn compaction_threshold(model_config: &ModelConfig) -> Option<f64> {
+    is_constrained(model_config).then_some(CONSTRAINED_COMPACTION_THRESHOLD)
+}
+
+/// One line per tool with the start of its description, for the compact system prompt
+pub fn tool_index(tools: &[Tool]) -> String {
+    tools
+        .iter()
+        .filter(|tool| tool.name != PLATFORM_LOAD_TOOL_TOOL_NAME)
+        .map(|tool| {
+            let description = tool
+                .description
+                .as_deref()
+                .and_then(|description| description.lines().find(|line| !line.trim().is_empty()))
+                .unwrap_or_default()
+                .trim();
+            if description.chars().count() > INDEX_DESCRIPTION_CHARS {
+                let cut: String = description.chars().take(INDEX_DESCRIPTION_CHARS).collect();
+                format!("- {}: {}...", tool.name, cut.trim_end())
+            } else if description.is_empty() {
+                format!("- {}", tool.name)
+            } else {
+                format!("- {}: {}", tool.name, description)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The tools sent in constrained mode: the load tool and the loaded tool, if it still exists
+pub fn constrained_tools(tools: Vec<Tool>, loaded: Option<&str>) -> Vec<Tool> {
+    let mut constrained = vec![load_tool_tool()];
+    if let Some(loaded) = loaded {
+        constrained.extend(tools.into_iter().find(|tool| tool.name == loaded));
+    }
+    constrained
+}
+
+/// Answer a platform__load_tool call: the loaded tool's name and the message for the model
+pub fn load_tool(tools: &[Tool], name: &str) -> Result<(String, String), String> {
+    let name = name.trim();
+    if let Some(tool) = tools.iter().find(|tool| tool.name == name) {
+        return Ok((
+            tool.name.to_string(),
+            format!(
+                "{} is loaded; call it now. {}",
+                tool.name,
+                tool.description.as_deref().unwrap_or_default().trim()
+            ),
+        ));
+    }
+
+    // Small models often drop the extension prefix
+    let suffix = format!("__{}", name);
+    let matches: Vec<&Tool> = tools
+        .iter()
+        .filter(|tool| tool.name.ends_with(&suffix))
+        .collect();
+    match matches.as_slice() {
+        [tool] => load_tool(tools, &tool.name),
+        [] => Err(format!(
+            "There is no tool named '{}'. Pick one from the list in the system prompt.",
+            name
+        )),
+        _ => Err(format!(
+            "'{}' is ambiguous; use one of: {}",
+            name,
+            matches
+                .iter()
+                .map(|tool| tool.name.as_ref())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+
+    fn tool(name: &str, description: &str) -> Tool {
+        Tool::new(
+            name.to_string(),
+            description.to_string(),
+            object!({"type": "object", "properties": {}}),
+        )
+    }
+
+    fn sample() -> Vec<Tool> {
+        vec![
+            tool(
+                "developer__shell",
+                "\nExecute a command in the shell.\n\nThis will return the output and error concatenated.",
+            ),
+            tool(
+                "developer__text_editor",
+                "Perform text editing operations on files, such as viewing, creating and replacing text in them",
+            ),
+            tool("memory__shell", "Run a memory command"),
+        ]
+    }
+
+    #[test]
+    fn test_constrained_for_small_models() {
+        assert!(constrained_for(8_192, None));
+        assert!(!constrained_for(128_000, None));
+        assert!(constrained_for(128_000, Some(true)));
+        assert!(!constrained_for(8_192, Some(false)));
+    }
+
+    #[test]
+    fn test_tool_index_is_one_line_per_tool() {
+        let index = tool_index(&sample());
+        let lines: Vec<&str> = index.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "- developer__shell: Execute a command in the shell."
+        );
+        assert!(lines[1].ends_with("..."));
+        assert!(lines[1].len() < 120);
+    }
+
+    #[test]
+    fn test_constrained_tools_sends_one_schema() {
+        let tools = constrained_tools(sample(), None);
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(names, vec![PLATFORM_LOAD_TOOL_TOOL_NAME]);
+
+        let tools = constrained_tools(sample(), Some("developer__text_editor"));
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            vec![PLATFORM_LOAD_TOOL_TOOL_NAME, "developer__text_editor"]
+        );
+    }
+
+    #[test]
+    fn test_load_tool_by_full_or_short_name() {
+        let tools = sample();
+        let (name, message) = load_tool(&tools, "developer__shell").unwrap();
+        assert_eq!(name, "developer__shell");
+        assert!(message.contains("Execute a command"));
+
+        let (name, _) = load_tool(&tools, "text_editor").unwrap();
+        assert_eq!(name, "developer__text_editor");
+
+        assert!(load_tool(&tools, "shell")
+            .unwrap_err()
+            .contains("ambiguous"));
+        assert!(load_tool(&tools, "browser").is_err());
+    }
+}
@@ -1,6 +1,10 @@
 pub mod auto_compact;
+pub mod benchmark;
+pub mod budget;
 mod common;
+pub mod constrained;
 pub mod summarize;
 pub mod truncate;
+pub mod usage;
 
 pub use common::*;
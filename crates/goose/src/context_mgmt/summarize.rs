@@ -509,7 +509,7 @@ mod tests {
 
             // Fail if this looks like a one-shot request
             if system.contains("reasoning in `<analysis>` tags") {
-                return Err(ProviderError::RateLimitExceeded(
+                return Err(ProviderError::rate_limited(
                     "Simulated one-shot failure".to_string(),
                 ));
             }
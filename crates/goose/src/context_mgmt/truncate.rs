@@ -1,9 +1,11 @@
+use crate::config::Config;
 use crate::conversation::message::{Message, MessageContent};
 use crate::conversation::Conversation;
 use crate::utils::safe_truncate;
 use anyhow::{anyhow, Result};
 use rmcp::model::{RawContent, ResourceContents, Role};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
 use tracing::{debug, warn};
 
@@ -178,7 +180,7 @@ fn estimate_message_tokens(message: &Message, estimate_fn: &dyn Fn(&str) -> usiz
 /// - messages: The vector of messages in the conversation.
 /// - token_counts: A parallel vector containing the token count for each message.
 /// - context_limit: The maximum allowed context length in tokens.
-/// - strategy: The truncation strategy to use. Only option is OldestFirstTruncation.
+/// - strategy: The truncation strategy to use, see [`TruncationStrategyKind`].
 pub fn truncate_messages(
     messages: &[Message],
     token_counts: &[usize],
@@ -231,9 +233,11 @@ pub fn truncate_messages(
         )); // No truncation needed
     }
 
-    // Step 2: Determine indices to remove based on strategy
-    let indices_to_remove =
+    // Step 2: Determine indices to remove based on strategy, never removing pinned messages
+    let protected = protected_indices(&messages);
+    let mut indices_to_remove =
         strategy.determine_indices_to_remove(&messages, &token_counts, context_limit)?;
+    indices_to_remove.retain(|i| !protected.contains(i));
 
     // Circuit breaker: if we can't remove enough messages, fail gracefully
     let tokens_to_remove: usize = indices_to_remove
@@ -272,7 +276,7 @@ pub fn truncate_messages(
 
     // Step 4: Ensure the last message is a user message with TextContent only
     while let Some(last_msg) = messages.last() {
-        if last_msg.role != Role::User || !last_msg.has_only_text_content() {
+        if !last_msg.pinned && (last_msg.role != Role::User || !last_msg.has_only_text_content()) {
             let _ = messages.pop().ok_or(anyhow!("Failed to pop message"))?;
             let removed_tokens = token_counts
                 .pop()
@@ -285,7 +289,8 @@ pub fn truncate_messages(
 
     // Step 5: Check first msg is a User message with TextContent only
     while let Some(first_msg) = messages.first() {
-        if first_msg.role != Role::User || !first_msg.has_only_text_content() {
+        if !first_msg.pinned && (first_msg.role != Role::User || !first_msg.has_only_text_content())
+        {
             let _ = messages.remove(0);
             let removed_tokens = token_counts.remove(0);
             total_tokens -= removed_tokens;
@@ -316,6 +321,28 @@ pub fn truncate_messages(
     ))
 }
 
+/// Indices of pinned messages, and of the other half of any tool request/response pair a
+/// pinned message belongs to, which truncation must keep
+fn protected_indices(messages: &[Message]) -> HashSet<usize> {
+    let pinned_tool_ids: HashSet<String> = messages
+        .iter()
+        .filter(|m| m.pinned)
+        .flat_map(|m| m.get_tool_ids().into_iter().map(str::to_string))
+        .collect();
+
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| {
+            m.pinned
+                || m.get_tool_ids()
+                    .iter()
+                    .any(|id| pinned_tool_ids.contains(*id))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
 /// Trait representing a truncation strategy
 pub trait TruncationStrategy {
     /// Determines the indices of messages to remove to fit within the context limit.
@@ -346,11 +373,15 @@ impl TruncationStrategy for OldestFirstTruncation {
         let mut indices_to_remove = HashSet::new();
         let mut total_tokens: usize = token_counts.iter().sum();
         let mut tool_ids_to_remove = HashSet::new();
+        let protected = protected_indices(messages);
 
         for (i, message) in messages.iter().enumerate() {
             if total_tokens <= context_limit {
                 break;
             }
+            if protected.contains(&i) {
+                continue;
+            }
 
             // Remove the message
             indices_to_remove.insert(i);
@@ -385,6 +416,284 @@ impl TruncationStrategy for OldestFirstTruncation {
     }
 }
 
+/// Remove messages in the given order until the conversation fits, taking the other half of
+/// any tool request/response pair along with each removed message
+fn remove_in_order(
+    messages: &[Message],
+    token_counts: &[usize],
+    context_limit: usize,
+    order: impl IntoIterator<Item = usize>,
+) -> HashSet<usize> {
+    let mut indices_to_remove = HashSet::new();
+    let mut total_tokens: usize = token_counts.iter().sum();
+    let protected = protected_indices(messages);
+
+    for i in order {
+        if total_tokens <= context_limit {
+            break;
+        }
+        if protected.contains(&i) || !indices_to_remove.insert(i) {
+            continue;
+        }
+        total_tokens = total_tokens.saturating_sub(token_counts[i]);
+
+        let tool_ids = messages[i].get_tool_ids();
+        if tool_ids.is_empty() {
+            continue;
+        }
+        for (j, other) in messages.iter().enumerate() {
+            if j != i
+                && !indices_to_remove.contains(&j)
+                && other.get_tool_ids().iter().any(|id| tool_ids.contains(id))
+            {
+                indices_to_remove.insert(j);
+                total_tokens = total_tokens.saturating_sub(token_counts[j]);
+            }
+        }
+    }
+
+    indices_to_remove
+}
+
+/// Strategy that keeps the start of the conversation (usually the task description) and the
+/// most recent messages, removing from the middle outward
+pub struct MiddleOutTruncation;
+
+impl TruncationStrategy for MiddleOutTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        if messages.len() < 3 {
+            return OldestFirstTruncation.determine_indices_to_remove(
+                messages,
+                token_counts,
+                context_limit,
+            );
+        }
+
+        // The first and last messages are kept; everything between is ordered by its distance
+        // from the middle
+        let middle = messages.len() / 2;
+        let mut order: Vec<usize> = (1..messages.len() - 1).collect();
+        order.sort_by_key(|&i| i.abs_diff(middle));
+        debug!("MiddleOut: removal order {:?}", order);
+
+        Ok(remove_in_order(
+            messages,
+            token_counts,
+            context_limit,
+            order,
+        ))
+    }
+}
+
+/// Strategy that drops tool output (with the requests that produced it) before any of the
+/// conversation itself, oldest first, then falls back to removing the oldest messages
+pub struct ToolOutputFirstTruncation;
+
+impl TruncationStrategy for ToolOutputFirstTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let (tool_output, rest): (Vec<usize>, Vec<usize>) =
+            (0..messages.len()).partition(|&i| messages[i].is_tool_response());
+        debug!(
+            "ToolOutputFirst: {} tool output messages are removed first",
+            tool_output.len()
+        );
+
+        Ok(remove_in_order(
+            messages,
+            token_counts,
+            context_limit,
+            tool_output.into_iter().chain(rest),
+        ))
+    }
+}
+
+/// Terms shorter than this are too common to show that a tool result was used later
+const MIN_REFERENCE_TERM_LEN: usize = 6;
+
+/// Most distinctive terms taken from each tool result when looking for later references
+const MAX_REFERENCE_TERMS: usize = 50;
+
+/// Strategy that scores each message by how much it is likely to matter for the rest of the
+/// task and removes the lowest scoring first. Newer messages score higher, as do the user's
+/// own words and tool results that later messages refer back to; pinned messages are never
+/// removed.
+pub struct ImportanceTruncation;
+
+impl ImportanceTruncation {
+    /// Score each message; higher is more worth keeping. Both halves of a tool request and
+    /// response pair get the higher of their scores, since they are removed together.
+    fn scores(messages: &[Message]) -> Vec<f64> {
+        let count = messages.len().max(1) as f64;
+        let mut scores: Vec<f64> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                let mut score = (i + 1) as f64 / count;
+                if message.role == Role::User && message.has_only_text_content() {
+                    score += 1.0;
+                }
+                if message.is_tool_response() && Self::referenced_later(message, &messages[i + 1..])
+                {
+                    score += 1.0;
+                }
+                score
+            })
+            .collect();
+
+        let mut pair_scores: HashMap<&str, f64> = HashMap::new();
+        for (message, &score) in messages.iter().zip(&scores) {
+            for id in message.get_tool_ids() {
+                let pair_score = pair_scores.entry(id).or_insert(score);
+                *pair_score = pair_score.max(score);
+            }
+        }
+        for (message, score) in messages.iter().zip(scores.iter_mut()) {
+            for id in message.get_tool_ids() {
+                *score = score.max(pair_scores[id]);
+            }
+        }
+        scores
+    }
+
+    /// Whether a distinctive term from a tool result shows up in what the assistant said or
+    /// asked for afterwards, such as a path from a listing passed to the next tool call
+    fn referenced_later(message: &Message, later: &[Message]) -> bool {
+        let result_text = tool_result_text(message);
+        let terms: Vec<&str> = result_text
+            .split(|c: char| !(c.is_alphanumeric() || "_-./:".contains(c)))
+            .map(|term| term.trim_matches(|c: char| ".:".contains(c)))
+            .filter(|term| term.len() >= MIN_REFERENCE_TERM_LEN)
+            .take(MAX_REFERENCE_TERMS)
+            .collect();
+        if terms.is_empty() {
+            return false;
+        }
+
+        later.iter().filter(|m| m.role == Role::Assistant).any(|m| {
+            let text = assistant_text_and_arguments(m);
+            terms.iter().any(|term| text.contains(term))
+        })
+    }
+}
+
+/// The text of the tool results in a message
+fn tool_result_text(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::ToolResponse(response) => response.tool_result.as_ref().ok(),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|content| content.as_text().map(|text| text.text.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// What an assistant message says and the arguments of the tools it calls
+fn assistant_text_and_arguments(message: &Message) -> String {
+    let mut text = message.as_concat_text();
+    for content in &message.content {
+        if let MessageContent::ToolRequest(request) = content {
+            if let Ok(call) = &request.tool_call {
+                text.push('\n');
+                text.push_str(&call.arguments.to_string());
+            }
+        }
+    }
+    text
+}
+
+impl TruncationStrategy for ImportanceTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let scores = Self::scores(messages);
+        // The last message is what the model is about to respond to
+        let mut order: Vec<usize> = (0..messages.len().saturating_sub(1)).collect();
+        order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]).then(a.cmp(&b)));
+        debug!("Importance: removal order {:?}", order);
+
+        Ok(remove_in_order(
+            messages,
+            token_counts,
+            context_limit,
+            order,
+        ))
+    }
+}
+
+/// The truncation strategies that can be selected with `GOOSE_TRUNCATION_STRATEGY`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategyKind {
+    #[default]
+    OldestFirst,
+    MiddleOut,
+    ToolOutputFirst,
+    Importance,
+}
+
+impl TruncationStrategyKind {
+    /// Read the strategy from `GOOSE_TRUNCATION_STRATEGY`, defaulting to oldest-first
+    pub fn from_config(config: &Config) -> Self {
+        match config.get_param::<String>("GOOSE_TRUNCATION_STRATEGY") {
+            Ok(name) => Self::from_name(&name).unwrap_or_else(|| {
+                warn!(
+                    "Unknown GOOSE_TRUNCATION_STRATEGY '{}', using oldest_first",
+                    name
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parse a strategy name; dashes and underscores are interchangeable
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "oldest_first" => Some(Self::OldestFirst),
+            "middle_out" => Some(Self::MiddleOut),
+            "tool_output_first" => Some(Self::ToolOutputFirst),
+            "importance" => Some(Self::Importance),
+            _ => None,
+        }
+    }
+
+    pub fn strategy(&self) -> &'static dyn TruncationStrategy {
+        match self {
+            Self::OldestFirst => &OldestFirstTruncation,
+            Self::MiddleOut => &MiddleOutTruncation,
+            Self::ToolOutputFirst => &ToolOutputFirstTruncation,
+            Self::Importance => &ImportanceTruncation,
+        }
+    }
+
+    /// Describes what was dropped, for the notice added after truncating
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::OldestFirst => "some of the oldest messages",
+            Self::MiddleOut => "messages from the middle",
+            Self::ToolOutputFirst => "older tool output",
+            Self::Importance => "the earlier messages least likely to matter",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -725,4 +1034,136 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_strategy_names() {
+        assert_eq!(
+            TruncationStrategyKind::from_name("middle-out"),
+            Some(TruncationStrategyKind::MiddleOut)
+        );
+        assert_eq!(
+            TruncationStrategyKind::from_name("Tool_Output_First"),
+            Some(TruncationStrategyKind::ToolOutputFirst)
+        );
+        assert_eq!(TruncationStrategyKind::from_name("newest_first"), None);
+    }
+
+    #[test]
+    fn test_middle_out_keeps_first_and_recent_messages() -> Result<()> {
+        let (messages, token_counts) = create_messages_with_counts(5, 10, true);
+        let context_limit = 50;
+
+        let (truncated, counts) = truncate_messages(
+            messages.messages(),
+            &token_counts,
+            context_limit,
+            &MiddleOutTruncation,
+        )?;
+
+        assert!(counts.iter().sum::<usize>() <= context_limit);
+        assert_eq!(truncated.messages()[0].as_concat_text(), "User message 0");
+        assert_eq!(
+            truncated.messages().last().unwrap().as_concat_text(),
+            "User message 8"
+        );
+        assert!(!truncated
+            .iter()
+            .any(|m| m.as_concat_text() == "User message 4"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_output_first_removes_tool_pairs_before_text() -> Result<()> {
+        let tool_call = ToolCall::new("read_file", json!({"path": "a.txt"}));
+        let messages = vec![
+            user_text(0, 10),
+            assistant_text(1, 10),
+            user_text(2, 10),
+            assistant_tool_request("tool1", tool_call, 10),
+            user_tool_response("tool1", vec![Content::text("big file")], 60),
+            assistant_text(5, 10),
+            user_text(6, 10),
+        ];
+        let (messages, token_counts): (Vec<_>, Vec<_>) = messages.into_iter().unzip();
+
+        let (truncated, counts) =
+            truncate_messages(&messages, &token_counts, 60, &ToolOutputFirstTruncation)?;
+
+        assert_eq!(counts.iter().sum::<usize>(), 50);
+        assert_eq!(truncated.len(), 5);
+        assert!(!truncated
+            .iter()
+            .any(|m| m.is_tool_call() || m.is_tool_response()));
+        assert_eq!(truncated.messages()[0].as_concat_text(), "User message 0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_importance_keeps_referenced_tool_results() -> Result<()> {
+        let listing = ToolCall::new("shell", json!({"command": "ls"}));
+        let weather = ToolCall::new("shell", json!({"command": "curl wttr.in"}));
+        let messages = vec![
+            user_text(0, 10),
+            assistant_tool_request("list", listing, 10),
+            user_tool_response(
+                "list",
+                vec![Content::text("Cargo.toml\nsrc/scheduler.rs\nREADME.md")],
+                20,
+            ),
+            assistant_tool_request("weather", weather, 10),
+            user_tool_response("weather", vec![Content::text("Sunny, +21 degrees")], 20),
+            (
+                Message::assistant().with_text("The scheduler lives in src/scheduler.rs."),
+                10,
+            ),
+            user_text(6, 10),
+        ];
+        let (messages, token_counts): (Vec<_>, Vec<_>) = messages.into_iter().unzip();
+
+        let (truncated, counts) =
+            truncate_messages(&messages, &token_counts, 60, &ImportanceTruncation)?;
+
+        assert!(counts.iter().sum::<usize>() <= 60);
+        let texts: Vec<String> = truncated.iter().map(tool_result_text).collect();
+        assert!(texts.iter().any(|t| t.contains("src/scheduler.rs")));
+        assert!(!texts.iter().any(|t| t.contains("Sunny")));
+        assert_eq!(truncated.messages()[0].as_concat_text(), "User message 0");
+        assert_eq!(
+            truncated.messages().last().unwrap().as_concat_text(),
+            "User message 6"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_messages_are_never_removed() -> Result<()> {
+        let (mut messages, token_counts): (Vec<_>, Vec<_>) = vec![
+            user_text(0, 10),
+            assistant_text(1, 10),
+            user_text(2, 10),
+            assistant_text(3, 10),
+            user_text(4, 10),
+        ]
+        .into_iter()
+        .unzip();
+        messages[0].pinned = true;
+
+        for strategy in [
+            TruncationStrategyKind::OldestFirst,
+            TruncationStrategyKind::MiddleOut,
+            TruncationStrategyKind::ToolOutputFirst,
+            TruncationStrategyKind::Importance,
+        ] {
+            let (truncated, counts) =
+                truncate_messages(&messages, &token_counts, 30, strategy.strategy())?;
+            assert!(counts.iter().sum::<usize>() <= 30);
+            assert_eq!(truncated.messages()[0].as_concat_text(), "User message 0");
+            assert!(truncated.messages()[0].pinned);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,101 @@
+use rmcp::model::{Role, Tool};
+use serde::{Deserialize, Serialize};
+
+use crate::context_mgmt::get_messages_token_counts_async;
+use crate::conversation::message::Message;
+use crate::token_counter::AsyncTokenCounter;
+
+/// How much of the model's context window the next request will use, by where the tokens
+/// come from. Emitted after every turn so clients can render a context meter.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextUsage {
+    /// Tokens in the system prompt
+    pub system_prompt: usize,
+    /// Tokens in the tool schemas sent with each request
+    pub tools: usize,
+    /// Tokens in the conversation before the last response
+    pub history: usize,
+    /// Tokens in the model's most recent response and anything sent back with it
+    pub last_response: usize,
+    /// The model's context window
+    pub context_limit: usize,
+}
+
+impl ContextUsage {
+    /// Count the tokens the next request would send for this prompt, tools and history
+    pub fn measure(
+        token_counter: &AsyncTokenCounter,
+        system_prompt: &str,
+        tools: &[Tool],
+        messages: &[Message],
+        context_limit: usize,
+    ) -> Self {
+        let counts = get_messages_token_counts_async(token_counter, messages);
+        let split = messages
+            .iter()
+            .rposition(|message| message.role == Role::Assistant)
+            .unwrap_or(messages.len());
+
+        Self {
+            system_prompt: token_counter.count_tokens(system_prompt),
+            tools: token_counter.count_tokens_for_tools(tools),
+            history: counts[..split].iter().sum(),
+            last_response: counts[split..].iter().sum(),
+            context_limit,
+        }
+    }
+
+    /// Total tokens in use
+    pub fn used(&self) -> usize {
+        self.system_prompt + self.tools + self.history + self.last_response
+    }
+
+    /// Fraction of the context window in use
+    pub fn fraction(&self) -> f64 {
+        if self.context_limit == 0 {
+            return 0.0;
+        }
+        self.used() as f64 / self.context_limit as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_counter::create_async_token_counter;
+
+    #[tokio::test]
+    async fn test_measure_splits_last_response() {
+        let counter = create_async_token_counter().await.unwrap();
+        let messages = vec![
+            Message::user().with_text("What is the capital of France?"),
+            Message::assistant().with_text("Paris."),
+            Message::user().with_text("And of Italy?"),
+            Message::assistant().with_text("Rome is the capital of Italy."),
+        ];
+
+        let usage = ContextUsage::measure(&counter, "You are helpful.", &[], &messages, 1_000);
+        let counts = get_messages_token_counts_async(&counter, &messages);
+
+        assert_eq!(usage.history, counts[..3].iter().sum::<usize>());
+        assert_eq!(usage.last_response, counts[3]);
+        assert_eq!(usage.tools, 0);
+        assert!(usage.system_prompt > 0);
+        assert_eq!(
+            usage.used(),
+            usage.system_prompt + usage.history + usage.last_response
+        );
+        assert!(usage.fraction() > 0.0 && usage.fraction() < 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_measure_without_response() {
+        let counter = create_async_token_counter().await.unwrap();
+        let messages = vec![Message::user().with_text("hello")];
+
+        let usage = ContextUsage::measure(&counter, "", &[], &messages, 0);
+        assert_eq!(usage.last_response, 0);
+        assert!(usage.history > 0);
+        assert_eq!(usage.fraction(), 0.0);
+    }
+}
@@ -10,6 +10,7 @@ use std::collections::HashSet;
 use std::fmt;
 use utoipa::ToSchema;
 
+use crate::conversation::origin::Origin;
 use crate::conversation::tool_result_serde;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -88,6 +89,129 @@ pub struct SummarizationRequested {
     pub msg: String,
 }
 
+/// A source backing the model's answer, normalized from the citation and web search result
+/// annotations providers return
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The passage of the source the answer relies on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cited_text: Option<String>,
+    /// The annotation as the provider returned it
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    #[schema(value_type = Object)]
+    pub metadata: Value,
+}
+
+impl Citation {
+    /// A one line description of the source, for footnotes
+    pub fn label(&self) -> String {
+        match (&self.title, &self.url) {
+            (Some(title), Some(url)) => format!("{} - {}", title, url),
+            (Some(title), None) => title.clone(),
+            (None, Some(url)) => url.clone(),
+            (None, None) => self
+                .cited_text
+                .clone()
+                .unwrap_or_else(|| "Unknown source".to_string()),
+        }
+    }
+}
+
+/// The log probability of one generated token, with the likeliest alternatives at its position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// A token the model could have generated instead
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
+}
+
+impl TokenLogprob {
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+/// Why the model stopped generating, normalized across providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum StopReason {
+    /// The model finished its turn naturally or hit a stop sequence
+    Stop,
+    /// Generation was cut off by the output token limit
+    Length,
+    /// The model stopped to call one or more tools
+    ToolUse,
+    /// The provider filtered the output (safety, recitation, guardrails)
+    ContentFilter,
+    /// The model explicitly declined to answer
+    Refusal,
+    /// A finish reason we do not recognize
+    Unknown,
+}
+
+impl StopReason {
+    /// Normalize a provider-specific finish reason string.
+    ///
+    /// Covers the vocabularies used by OpenAI-compatible APIs (`stop`, `length`, `tool_calls`),
+    /// Anthropic (`end_turn`, `max_tokens`, `tool_use`), Google (`STOP`, `MAX_TOKENS`, `SAFETY`)
+    /// and Bedrock (`guardrail_intervened`).
+    pub fn from_provider_str(reason: &str) -> Self {
+        match reason.to_ascii_lowercase().as_str() {
+            "stop" | "end_turn" | "stop_sequence" | "eos" | "pause_turn" => StopReason::Stop,
+            "length" | "max_tokens" | "model_length" => StopReason::Length,
+            "tool_calls" | "tool_use" | "function_call" => StopReason::ToolUse,
+            "content_filter"
+            | "safety"
+            | "recitation"
+            | "blocklist"
+            | "prohibited_content"
+            | "spii"
+            | "guardrail_intervened"
+            | "content_filtered" => StopReason::ContentFilter,
+            "refusal" => StopReason::Refusal,
+            _ => StopReason::Unknown,
+        }
+    }
+
+    /// Whether the model's output was truncated before it finished
+    pub fn is_truncated(&self) -> bool {
+        matches!(self, StopReason::Length)
+    }
+
+    /// Whether the model (or the provider on its behalf) declined to answer
+    pub fn is_refusal(&self) -> bool {
+        matches!(self, StopReason::Refusal | StopReason::ContentFilter)
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StopReason::Stop => "stop",
+            StopReason::Length => "length",
+            StopReason::ToolUse => "tool_use",
+            StopReason::ContentFilter => "content_filter",
+            StopReason::Refusal => "refusal",
+            StopReason::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 /// Content passed inside a message, which can be both simple content and tool content
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -102,6 +226,7 @@ pub enum MessageContent {
     RedactedThinking(RedactedThinkingContent),
     ContextLengthExceeded(ContextLengthExceeded),
     SummarizationRequested(SummarizationRequested),
+    Citation(Citation),
 }
 
 impl fmt::Display for MessageContent {
@@ -135,6 +260,7 @@ impl fmt::Display for MessageContent {
             MessageContent::SummarizationRequested(r) => {
                 write!(f, "[SummarizationRequested: {}]", r.msg)
             }
+            MessageContent::Citation(c) => write!(f, "[Citation: {}]", c.label()),
         }
     }
 }
@@ -208,6 +334,10 @@ impl MessageContent {
         MessageContent::SummarizationRequested(SummarizationRequested { msg: msg.into() })
     }
 
+    pub fn citation(citation: Citation) -> Self {
+        MessageContent::Citation(citation)
+    }
+
     // Add this new method to check for summarization requested content
     pub fn as_summarization_requested(&self) -> Option<&SummarizationRequested> {
         if let MessageContent::SummarizationRequested(ref summarization_requested) = self {
@@ -279,6 +409,14 @@ impl MessageContent {
             _ => None,
         }
     }
+
+    /// Get the citation if this is a Citation variant
+    pub fn as_citation(&self) -> Option<&Citation> {
+        match self {
+            MessageContent::Citation(citation) => Some(citation),
+            _ => None,
+        }
+    }
 }
 
 impl From<Content> for MessageContent {
@@ -346,6 +484,18 @@ pub struct Message {
     #[serde(default = "default_created")]
     pub created: i64,
     pub content: Vec<MessageContent>,
+    /// Why the model stopped generating; only set on assistant messages from a provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+    /// Pinned messages are never dropped when the context is truncated or compacted
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Where the content came from; for tool results, the least trusted of the tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+    /// Per-token log probabilities of the generated text, when they were asked for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logprobs: Vec<TokenLogprob>,
 }
 
 impl fmt::Debug for Message {
@@ -372,6 +522,10 @@ impl Message {
             role,
             created,
             content,
+            stop_reason: None,
+            pinned: false,
+            origin: None,
+            logprobs: Vec::new(),
         }
     }
     pub fn debug(&self) -> String {
@@ -385,6 +539,10 @@ impl Message {
             role: Role::User,
             created: Utc::now().timestamp(),
             content: Vec::new(),
+            stop_reason: None,
+            pinned: false,
+            origin: None,
+            logprobs: Vec::new(),
         }
     }
 
@@ -395,6 +553,10 @@ impl Message {
             role: Role::Assistant,
             created: Utc::now().timestamp(),
             content: Vec::new(),
+            stop_reason: None,
+            pinned: false,
+            origin: None,
+            logprobs: Vec::new(),
         }
     }
 
@@ -403,6 +565,29 @@ impl Message {
         self
     }
 
+    /// Record why the model stopped generating this message
+    pub fn with_stop_reason(mut self, stop_reason: Option<StopReason>) -> Self {
+        self.stop_reason = stop_reason;
+        self
+    }
+
+    /// Pin the message so truncation and compaction keep it
+    pub fn with_pinned(mut self, pinned: bool) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// Record where the message's content came from
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    pub fn with_logprobs(mut self, logprobs: Vec<TokenLogprob>) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+
     /// Add any MessageContent to the message
     pub fn with_content(mut self, content: MessageContent) -> Self {
         self.content.push(content);
@@ -472,6 +657,11 @@ impl Message {
         self.with_content(MessageContent::redacted_thinking(data))
     }
 
+    /// Add a citation to the message
+    pub fn with_citation(self, citation: Citation) -> Self {
+        self.with_content(MessageContent::citation(citation))
+    }
+
     /// Add context length exceeded content to the message
     pub fn with_context_length_exceeded<S: Into<String>>(self, msg: S) -> Self {
         self.with_content(MessageContent::context_length_exceeded(msg))
@@ -540,6 +730,19 @@ impl Message {
             .collect()
     }
 
+    /// Reorder ToolResponse content to follow `request_ids`, the order the tools were requested in.
+    /// Responses for unknown ids and any other content keep their relative order at the end.
+    pub fn with_tool_responses_in_order(mut self, request_ids: &[String]) -> Self {
+        self.content.sort_by_key(|content| match content {
+            MessageContent::ToolResponse(res) => request_ids
+                .iter()
+                .position(|id| id == &res.id)
+                .unwrap_or(usize::MAX),
+            _ => usize::MAX,
+        });
+        self
+    }
+
     /// Check if the message has only TextContent
     pub fn has_only_text_content(&self) -> bool {
         self.content
@@ -555,7 +758,7 @@ impl Message {
 
 #[cfg(test)]
 mod tests {
-    use crate::conversation::message::{Message, MessageContent};
+    use crate::conversation::message::{Message, MessageContent, StopReason};
     use crate::conversation::*;
     use mcp_core::handler::ToolError;
     use mcp_core::ToolCall;
@@ -825,4 +1028,69 @@ mod tests {
         assert_eq!(ids.len(), 1);
         assert!(ids.contains("req1"));
     }
+
+    #[test]
+    fn test_stop_reason_normalization() {
+        assert_eq!(StopReason::from_provider_str("stop"), StopReason::Stop);
+        assert_eq!(StopReason::from_provider_str("end_turn"), StopReason::Stop);
+        assert_eq!(StopReason::from_provider_str("length"), StopReason::Length);
+        assert_eq!(
+            StopReason::from_provider_str("MAX_TOKENS"),
+            StopReason::Length
+        );
+        assert_eq!(
+            StopReason::from_provider_str("tool_calls"),
+            StopReason::ToolUse
+        );
+        assert_eq!(
+            StopReason::from_provider_str("tool_use"),
+            StopReason::ToolUse
+        );
+        assert_eq!(
+            StopReason::from_provider_str("SAFETY"),
+            StopReason::ContentFilter
+        );
+        assert_eq!(
+            StopReason::from_provider_str("refusal"),
+            StopReason::Refusal
+        );
+        assert_eq!(StopReason::from_provider_str("banana"), StopReason::Unknown);
+
+        assert!(StopReason::Length.is_truncated());
+        assert!(StopReason::ContentFilter.is_refusal());
+        assert!(!StopReason::Stop.is_refusal());
+    }
+
+    #[test]
+    fn test_stop_reason_serialization() {
+        let message = Message::assistant().with_text("Hello");
+        let value = serde_json::to_value(&message).unwrap();
+        assert!(value.get("stopReason").is_none());
+
+        let message = message.with_stop_reason(Some(StopReason::ToolUse));
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["stopReason"], "toolUse");
+
+        let deserialized: Message = serde_json::from_value(value).unwrap();
+        assert_eq!(deserialized.stop_reason, Some(StopReason::ToolUse));
+    }
+
+    #[test]
+    fn test_tool_responses_in_order() {
+        let request_ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let message = Message::user()
+            .with_tool_response("c", Ok(vec![]))
+            .with_tool_response("unknown", Ok(vec![]))
+            .with_tool_response("a", Ok(vec![]))
+            .with_tool_response("b", Ok(vec![]))
+            .with_tool_responses_in_order(&request_ids);
+
+        let ids: Vec<&str> = message
+            .content
+            .iter()
+            .filter_map(|c| c.as_tool_response())
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a", "b", "c", "unknown"]);
+    }
 }
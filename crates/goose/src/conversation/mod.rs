@@ -5,6 +5,7 @@ use std::collections::HashSet;
 use thiserror::Error;
 
 pub mod message;
+pub mod origin;
 mod tool_result_serde;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,12 +47,23 @@ impl Conversation {
             .last_mut()
             .filter(|m| m.id.is_some() && m.id == message.id)
         {
+            if message.stop_reason.is_some() {
+                last.stop_reason = message.stop_reason;
+            }
+            last.logprobs.extend(message.logprobs);
             match (last.content.last_mut(), message.content.last()) {
                 (Some(MessageContent::Text(ref mut last)), Some(MessageContent::Text(new)))
                     if message.content.len() == 1 =>
                 {
                     last.text.push_str(&new.text);
                 }
+                // Streamed reasoning arrives in pieces, like text
+                (
+                    Some(MessageContent::Thinking(ref mut last)),
+                    Some(MessageContent::Thinking(new)),
+                ) if message.content.len() == 1 => {
+                    last.thinking.push_str(&new.thinking);
+                }
                 (_, _) => {
                     last.content.extend(message.content);
                 }
@@ -102,6 +114,43 @@ impl Conversation {
         self.0.clear();
     }
 
+    /// Pin the message with `id` so truncation and compaction never drop it.
+    /// Only text messages can be pinned: tool calls can't be kept apart from their results.
+    pub fn pin_message(&mut self, id: &str) -> anyhow::Result<()> {
+        let message = self
+            .0
+            .iter_mut()
+            .find(|m| m.id.as_deref() == Some(id))
+            .ok_or_else(|| anyhow::anyhow!("No message with id {}", id))?;
+        if !message.has_only_text_content() {
+            anyhow::bail!("Only text messages can be pinned");
+        }
+        message.pinned = true;
+        Ok(())
+    }
+
+    /// Pin the most recent message the user typed, giving it an id if it has none.
+    /// Returns the id of the pinned message.
+    pub fn pin_last_user_message(&mut self) -> anyhow::Result<String> {
+        let message = self
+            .0
+            .iter_mut()
+            .rev()
+            .find(|m| m.role == Role::User && m.has_only_text_content())
+            .ok_or_else(|| anyhow::anyhow!("There is no message to pin yet"))?;
+        let id = message
+            .id
+            .get_or_insert_with(|| format!("msg_{}", uuid::Uuid::new_v4()))
+            .clone();
+        message.pinned = true;
+        Ok(id)
+    }
+
+    /// The messages that truncation and compaction keep
+    pub fn pinned(&self) -> impl Iterator<Item = &Message> {
+        self.0.iter().filter(|m| m.pinned)
+    }
+
     fn validate(self) -> Result<Self, InvalidConversation> {
         let (_messages, issues) = fix_messages(self.0.clone());
         if !issues.is_empty() {
@@ -531,4 +580,29 @@ mod tests {
         let (_fixed, issues) = run_verify(messages);
         assert_eq!(issues.len(), 0);
     }
+
+    #[test]
+    fn test_pin_messages() {
+        let mut conversation = Conversation::new_unvalidated(vec![
+            Message::user()
+                .with_text("Refactor the parser")
+                .with_id("task"),
+            Message::assistant()
+                .with_tool_request("tool_1", Ok(ToolCall::new("read_file", json!({}))))
+                .with_id("call"),
+            Message::user().with_text("Keep the public API unchanged"),
+        ]);
+
+        conversation.pin_message("task").unwrap();
+        assert!(conversation.pin_message("call").is_err());
+        assert!(conversation.pin_message("missing").is_err());
+
+        let id = conversation.pin_last_user_message().unwrap();
+        assert!(id.starts_with("msg_"));
+        let pinned: Vec<_> = conversation.pinned().map(|m| m.as_concat_text()).collect();
+        assert_eq!(
+            pinned,
+            vec!["Refactor the parser", "Keep the public API unchanged"]
+        );
+    }
 }
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Where the content of a message came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", content = "name", rename_all = "camelCase")]
+pub enum Origin {
+    /// Typed or sent by the user
+    User,
+    /// Read from a file on this machine
+    LocalFile,
+    /// Returned by the named extension
+    Extension(String),
+    /// Fetched from the web or another untrusted remote source
+    Web,
+}
+
+impl Origin {
+    /// How far content from this origin is trusted unless configured otherwise
+    pub fn default_trust(&self) -> TrustLevel {
+        match self {
+            Origin::User | Origin::LocalFile => TrustLevel::Trusted,
+            Origin::Extension(_) => TrustLevel::Limited,
+            Origin::Web => TrustLevel::Untrusted,
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::User => write!(f, "user"),
+            Origin::LocalFile => write!(f, "local file"),
+            Origin::Extension(name) => write!(f, "extension {}", name),
+            Origin::Web => write!(f, "web"),
+        }
+    }
+}
+
+/// How far the agent may act on content without asking, from least to most trusted
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustLevel {
+    Untrusted,
+    Limited,
+    Trusted,
+}
+
+impl fmt::Display for TrustLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrustLevel::Untrusted => write!(f, "untrusted"),
+            TrustLevel::Limited => write!(f, "limited"),
+            TrustLevel::Trusted => write!(f, "trusted"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_origin_serialization() {
+        assert_eq!(
+            serde_json::to_value(Origin::Extension("fetch".to_string())).unwrap(),
+            json!({"type": "extension", "name": "fetch"})
+        );
+        assert_eq!(
+            serde_json::from_value::<Origin>(json!({"type": "web"})).unwrap(),
+            Origin::Web
+        );
+    }
+
+    #[test]
+    fn test_trust_levels_are_ordered() {
+        assert!(TrustLevel::Untrusted < TrustLevel::Limited);
+        assert!(TrustLevel::Limited < TrustLevel::Trusted);
+        assert_eq!(Origin::Web.default_trust(), TrustLevel::Untrusted);
+        assert_eq!(
+            serde_json::from_value::<TrustLevel>(json!("limited")).unwrap(),
+            TrustLevel::Limited
+        );
+    }
+}
@@ -1,9 +1,22 @@
 use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use thiserror::Error;
 
+use crate::providers::model_registry::ModelRegistry;
+
 const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 
+/// Config key holding a map of model aliases, e.g. `{"fast": "gpt-4o-mini"}`
+pub const MODEL_ALIASES_KEY: &str = "GOOSE_MODEL_ALIASES";
+
+/// Config key overriding the knowledge cutoff of the configured model, as `YYYY-MM`
+pub const KNOWLEDGE_CUTOFF_KEY: &str = "GOOSE_KNOWLEDGE_CUTOFF";
+
+/// Aliases may point at other aliases; this bounds the chain to catch cycles
+const MAX_ALIAS_DEPTH: usize = 8;
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("Environment variable '{0}' not found")]
@@ -28,6 +41,8 @@ static MODEL_SPECIFIC_LIMITS: Lazy<Vec<(&'static str, usize)>> = Lazy::new(|| {
         ("claude", 200_000),
         // google
         ("gemini-1", 128_000),
+        ("gemini-1.5-flash", 1_000_000),
+        ("gemini-1.5-pro", 2_000_000),
         ("gemini-2", 1_000_000),
         ("gemma-3-27b", 128_000),
         ("gemma-3-12b", 128_000),
@@ -56,27 +71,182 @@ static MODEL_SPECIFIC_LIMITS: Lazy<Vec<(&'static str, usize)>> = Lazy::new(|| {
         ("qwen2-70b", 262_144),
         ("qwen2", 128_000),
         ("qwen3-32b", 131_072),
+        // deepseek
+        ("deepseek-chat", 131_072),
+        ("deepseek-reasoner", 131_072),
+        ("deepseek-r1", 131_072),
+        ("deepseek-v3", 131_072),
+        // mistral
+        ("mistral-large", 131_072),
+        ("mistral-medium", 131_072),
+        ("mistral-small", 131_072),
+        ("open-mistral-nemo", 131_072),
+        ("codestral", 256_000),
+        ("devstral", 131_072),
+        ("magistral", 40_000),
+        ("ministral", 131_072),
+        ("pixtral", 131_072),
         // other
         ("kimi-k2", 131_072),
         ("grok-4", 256_000),
         ("grok", 131_072),
+        // aws bedrock ids; claude models match "claude" above
+        ("anthropic.claude-instant", 100_000),
+        ("amazon.nova-micro", 128_000),
+        ("amazon.nova", 300_000),
+        ("amazon.titan-text-premier", 32_000),
+        ("amazon.titan-text-express", 8_192),
+        ("amazon.titan-text-lite", 4_096),
+        ("meta.llama3-8b", 8_192),
+        ("meta.llama3-70b", 8_192),
+        ("mistral.mistral-large", 128_000),
+        ("mistral.mistral-7b", 32_000),
+        ("mistral.mixtral", 32_000),
+        ("cohere.command-r", 128_000),
+        ("ai21.jamba", 256_000),
     ]
 });
 
+/// Month each model's training data ends, matched like the limit table by the longest
+/// substring of the model name. Providers that report a cutoff take precedence.
+static MODEL_KNOWLEDGE_CUTOFFS: &[(&str, &str)] = &[
+    // openai
+    ("gpt-4-turbo", "2023-12"),
+    ("gpt-4o", "2023-10"),
+    ("gpt-4.1", "2024-06"),
+    ("gpt-4-1", "2024-06"),
+    ("gpt-5", "2024-09"),
+    ("o3-mini", "2023-10"),
+    ("o3", "2024-06"),
+    ("o4-mini", "2024-06"),
+    // anthropic
+    ("claude-3-opus", "2023-08"),
+    ("claude-3-haiku", "2023-08"),
+    ("claude-3-5-sonnet", "2024-04"),
+    ("claude-3-5-haiku", "2024-07"),
+    ("claude-3-7-sonnet", "2024-10"),
+    ("claude-sonnet-4", "2025-03"),
+    ("claude-opus-4", "2025-03"),
+    // google
+    ("gemini-1.5", "2023-11"),
+    ("gemini-2.0", "2024-08"),
+    ("gemini-2.5", "2025-01"),
+    // others
+    ("llama-3.1", "2023-12"),
+    ("llama-3.3", "2023-12"),
+    ("deepseek-chat", "2024-07"),
+    ("deepseek-reasoner", "2024-07"),
+    ("deepseek-v3", "2024-07"),
+    ("deepseek-r1", "2024-07"),
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
     pub model_name: String,
     pub context_limit: Option<usize>,
+    /// Whether the user set `context_limit`, in which case context windows reported by the
+    /// provider or learned at runtime don't replace it
+    #[serde(default)]
+    pub context_limit_explicit: bool,
     pub temperature: Option<f32>,
     pub max_tokens: Option<i32>,
+    pub top_p: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub seed: Option<i64>,
+    /// Capture per-token logprobs with this many likeliest alternatives per token, for providers
+    /// that support it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u8>,
     pub toolshim: bool,
     pub toolshim_model: Option<String>,
+    /// Provider that runs the toolshim interpreter model, Ollama when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolshim_provider: Option<String>,
+    /// Models to try in order when this one is rate limited or overloaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<ModelConfig>,
+}
+
+/// Most alternatives per token that OpenAI compatible APIs return with logprobs
+const MAX_TOP_LOGPROBS: u8 = 20;
+
+/// Patterns with this prefix in the limit table are regular expressions rather than substrings
+const REGEX_PATTERN_PREFIX: &str = "re:";
+
+/// A compiled entry of the model limit table
+struct LimitRule {
+    pattern: &'static str,
+    regex: Option<Regex>,
+    context_limit: usize,
+}
+
+impl LimitRule {
+    fn new(pattern: &'static str, context_limit: usize) -> Self {
+        let regex = pattern.strip_prefix(REGEX_PATTERN_PREFIX).map(|re| {
+            Regex::new(re).unwrap_or_else(|e| panic!("Invalid model limit pattern {}: {}", re, e))
+        });
+        Self {
+            pattern,
+            regex,
+            context_limit,
+        }
+    }
+
+    /// Length of the part of the model name this rule matches, if it matches at all
+    fn match_len(&self, model_name: &str) -> Option<usize> {
+        match &self.regex {
+            Some(regex) => regex.find(model_name).map(|m| m.len()),
+            None => model_name
+                .contains(self.pattern)
+                .then_some(self.pattern.len()),
+        }
+    }
+
+    fn to_config(&self) -> ModelLimitConfig {
+        ModelLimitConfig {
+            pattern: self
+                .pattern
+                .strip_prefix(REGEX_PATTERN_PREFIX)
+                .unwrap_or(self.pattern)
+                .to_string(),
+            context_limit: self.context_limit,
+            regex: self.regex.is_some(),
+        }
+    }
+}
+
+static LIMIT_RULES: Lazy<Vec<LimitRule>> = Lazy::new(|| {
+    MODEL_SPECIFIC_LIMITS
+        .iter()
+        .map(|(pattern, limit)| LimitRule::new(pattern, *limit))
+        .collect()
+});
+
+/// The rule that applies to a model: the one matching the longest part of its name, with
+/// earlier table entries winning ties
+fn find_limit_rule<'a>(rules: &'a [LimitRule], model_name: &str) -> Option<&'a LimitRule> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.match_len(model_name).map(|len| (len, rule)))
+        .fold(
+            None,
+            |best: Option<(usize, &LimitRule)>, (len, rule)| match best {
+                Some((best_len, _)) if best_len >= len => best,
+                _ => Some((len, rule)),
+            },
+        )
+        .map(|(_, rule)| rule)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelLimitConfig {
     pub pattern: String,
     pub context_limit: usize,
+    /// Whether `pattern` is a regular expression rather than a substring
+    #[serde(default)]
+    pub regex: bool,
 }
 
 impl ModelConfig {
@@ -88,34 +258,99 @@ impl ModelConfig {
         model_name: String,
         context_env_var: Option<&str>,
     ) -> Result<Self, ConfigError> {
-        let context_limit = Self::parse_context_limit(&model_name, context_env_var)?;
+        let model_name = Self::resolve_alias(&model_name)?;
+        let (context_limit, context_limit_explicit) =
+            Self::parse_context_limit(&model_name, context_env_var)?;
         let temperature = Self::parse_temperature()?;
+        let top_p = Self::parse_top_p()?;
+        let stop = Self::parse_stop();
+        let frequency_penalty = Self::parse_penalty("GOOSE_FREQUENCY_PENALTY")?;
+        let presence_penalty = Self::parse_penalty("GOOSE_PRESENCE_PENALTY")?;
+        let seed = Self::parse_seed()?;
+        let logprobs = Self::parse_logprobs()?;
         let toolshim = Self::parse_toolshim()?;
         let toolshim_model = Self::parse_toolshim_model()?;
+        let toolshim_provider = Self::parse_toolshim_provider()?;
 
         Ok(Self {
             model_name,
             context_limit,
+            context_limit_explicit,
             temperature,
             max_tokens: None,
+            top_p,
+            stop,
+            frequency_penalty,
+            presence_penalty,
+            seed,
+            logprobs,
             toolshim,
             toolshim_model,
+            toolshim_provider,
+            fallbacks: Vec::new(),
         })
     }
 
+    /// Resolve a model alias defined in `GOOSE_MODEL_ALIASES` to the model it stands for.
+    ///
+    /// Names that are not aliases are returned unchanged, so this is safe to call on any model name.
+    pub fn resolve_alias(model_name: &str) -> Result<String, ConfigError> {
+        let aliases: HashMap<String, String> = crate::config::Config::global()
+            .get_param(MODEL_ALIASES_KEY)
+            .unwrap_or_default();
+        Self::resolve_alias_with(model_name, &aliases)
+    }
+
+    fn resolve_alias_with(
+        model_name: &str,
+        aliases: &HashMap<String, String>,
+    ) -> Result<String, ConfigError> {
+        let mut resolved = model_name;
+        for _ in 0..MAX_ALIAS_DEPTH {
+            match aliases.get(resolved) {
+                Some(target) if target != resolved => resolved = target,
+                _ => {
+                    if resolved != model_name {
+                        tracing::debug!("Resolved model alias {} to {}", model_name, resolved);
+                    }
+                    return Ok(resolved.to_string());
+                }
+            }
+        }
+        Err(ConfigError::InvalidValue(
+            MODEL_ALIASES_KEY.to_string(),
+            model_name.to_string(),
+            "alias chain is too long or contains a cycle".to_string(),
+        ))
+    }
+
+    /// The context limit for the model, and whether the user set it in the environment or
+    /// the config file rather than it coming from the built-in table
     fn parse_context_limit(
         model_name: &str,
         custom_env_var: Option<&str>,
-    ) -> Result<Option<usize>, ConfigError> {
-        if let Some(env_var) = custom_env_var {
-            if let Ok(val) = std::env::var(env_var) {
-                return Self::validate_context_limit(&val, env_var).map(Some);
+    ) -> Result<(Option<usize>, bool), ConfigError> {
+        let keys: Vec<&str> = custom_env_var
+            .into_iter()
+            .chain(std::iter::once("GOOSE_CONTEXT_LIMIT"))
+            .collect();
+        for key in &keys {
+            if let Ok(val) = std::env::var(key) {
+                return Self::validate_context_limit(&val, key).map(|limit| (Some(limit), true));
             }
         }
-        if let Ok(val) = std::env::var("GOOSE_CONTEXT_LIMIT") {
-            return Self::validate_context_limit(&val, "GOOSE_CONTEXT_LIMIT").map(Some);
+        let config = crate::config::Config::global();
+        for key in &keys {
+            if let Ok(val) = config.get_param::<serde_json::Value>(key) {
+                let val = match val {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                return Self::validate_context_limit(&val, key).map(|limit| (Some(limit), true));
+            }
         }
-        Ok(Self::get_model_specific_limit(model_name))
+        // Context windows reported by the provider API replace this when the provider is built
+        Ok((Self::get_model_specific_limit(model_name), false))
     }
 
     fn validate_context_limit(val: &str, env_var: &str) -> Result<usize, ConfigError> {
@@ -158,6 +393,87 @@ impl ModelConfig {
         }
     }
 
+    fn parse_top_p() -> Result<Option<f32>, ConfigError> {
+        if let Ok(val) = std::env::var("GOOSE_TOP_P") {
+            let top_p = val.parse::<f32>().map_err(|_| {
+                ConfigError::InvalidValue(
+                    "GOOSE_TOP_P".to_string(),
+                    val.clone(),
+                    "must be a valid number".to_string(),
+                )
+            })?;
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(ConfigError::InvalidRange(
+                    "GOOSE_TOP_P".to_string(),
+                    "must be between 0.0 and 1.0".to_string(),
+                ));
+            }
+            Ok(Some(top_p))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Stop sequences are given as a comma separated list in `GOOSE_STOP`
+    fn parse_stop() -> Option<Vec<String>> {
+        let val = std::env::var("GOOSE_STOP").ok()?;
+        let stop: Vec<String> = val
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+        (!stop.is_empty()).then_some(stop)
+    }
+
+    fn parse_penalty(env_var: &str) -> Result<Option<f32>, ConfigError> {
+        if let Ok(val) = std::env::var(env_var) {
+            let penalty = val.parse::<f32>().map_err(|_| {
+                ConfigError::InvalidValue(
+                    env_var.to_string(),
+                    val.clone(),
+                    "must be a valid number".to_string(),
+                )
+            })?;
+            if !(-2.0..=2.0).contains(&penalty) {
+                return Err(ConfigError::InvalidRange(
+                    env_var.to_string(),
+                    "must be between -2.0 and 2.0".to_string(),
+                ));
+            }
+            Ok(Some(penalty))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_seed() -> Result<Option<i64>, ConfigError> {
+        match std::env::var("GOOSE_SEED") {
+            Ok(val) => val.parse::<i64>().map(Some).map_err(|_| {
+                ConfigError::InvalidValue(
+                    "GOOSE_SEED".to_string(),
+                    val,
+                    "must be an integer".to_string(),
+                )
+            }),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn parse_logprobs() -> Result<Option<u8>, ConfigError> {
+        match std::env::var("GOOSE_LOGPROBS") {
+            Ok(val) => match val.parse::<u8>() {
+                Ok(top) if top <= MAX_TOP_LOGPROBS => Ok(Some(top)),
+                _ => Err(ConfigError::InvalidValue(
+                    "GOOSE_LOGPROBS".to_string(),
+                    val,
+                    format!("must be an integer between 0 and {}", MAX_TOP_LOGPROBS),
+                )),
+            },
+            Err(_) => Ok(None),
+        }
+    }
+
     fn parse_toolshim() -> Result<bool, ConfigError> {
         if let Ok(val) = std::env::var("GOOSE_TOOLSHIM") {
             match val.to_lowercase().as_str() {
@@ -174,38 +490,89 @@ impl ModelConfig {
         }
     }
 
+    /// The interpreter model, from GOOSE_TOOLSHIM_MODEL or the older GOOSE_TOOLSHIM_OLLAMA_MODEL
     fn parse_toolshim_model() -> Result<Option<String>, ConfigError> {
-        match std::env::var("GOOSE_TOOLSHIM_OLLAMA_MODEL") {
+        let var = ["GOOSE_TOOLSHIM_MODEL", "GOOSE_TOOLSHIM_OLLAMA_MODEL"]
+            .into_iter()
+            .find_map(|name| std::env::var(name).ok().map(|val| (name, val)));
+        match var {
+            Some((name, val)) if val.trim().is_empty() => Err(ConfigError::InvalidValue(
+                name.to_string(),
+                val,
+                "cannot be empty if set".to_string(),
+            )),
+            Some((_, val)) => Ok(Some(val)),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_toolshim_provider() -> Result<Option<String>, ConfigError> {
+        match std::env::var("GOOSE_TOOLSHIM_PROVIDER") {
             Ok(val) if val.trim().is_empty() => Err(ConfigError::InvalidValue(
-                "GOOSE_TOOLSHIM_OLLAMA_MODEL".to_string(),
+                "GOOSE_TOOLSHIM_PROVIDER".to_string(),
                 val,
                 "cannot be empty if set".to_string(),
             )),
-            Ok(val) => Ok(Some(val)),
+            Ok(val) => Ok(Some(val.trim().to_string())),
             Err(_) => Ok(None),
         }
     }
 
     fn get_model_specific_limit(model_name: &str) -> Option<usize> {
-        MODEL_SPECIFIC_LIMITS
+        find_limit_rule(&LIMIT_RULES, model_name).map(|rule| rule.context_limit)
+    }
+
+    /// The limit table entry that determines the default context limit for a model
+    pub fn get_model_limit_rule(model_name: &str) -> Option<ModelLimitConfig> {
+        find_limit_rule(&LIMIT_RULES, model_name).map(LimitRule::to_config)
+    }
+
+    fn get_model_specific_cutoff(model_name: &str) -> Option<&'static str> {
+        MODEL_KNOWLEDGE_CUTOFFS
             .iter()
-            .find(|(pattern, _)| model_name.contains(pattern))
-            .map(|(_, limit)| *limit)
+            .filter(|(pattern, _)| model_name.contains(pattern))
+            // Earlier entries win ties, as in the limit table
+            .fold(
+                None,
+                |best: Option<(&str, &str)>, &(pattern, cutoff)| match best {
+                    Some((best_pattern, _)) if best_pattern.len() >= pattern.len() => best,
+                    _ => Some((pattern, cutoff)),
+                },
+            )
+            .map(|(_, cutoff)| cutoff)
+    }
+
+    /// Month a model's training data ends, as `YYYY-MM`: from `GOOSE_KNOWLEDGE_CUTOFF`, then
+    /// what the provider reported, then the built-in table
+    pub fn knowledge_cutoff(model_name: &str) -> Option<String> {
+        if let Ok(cutoff) =
+            crate::config::Config::global().get_param::<String>(KNOWLEDGE_CUTOFF_KEY)
+        {
+            return Some(cutoff);
+        }
+        ModelRegistry::global()
+            .get_configured(model_name)
+            .and_then(|info| info.knowledge_cutoff)
+            .or_else(|| Self::get_model_specific_cutoff(model_name).map(String::from))
     }
 
     pub fn get_all_model_limits() -> Vec<ModelLimitConfig> {
-        MODEL_SPECIFIC_LIMITS
-            .iter()
-            .map(|(pattern, context_limit)| ModelLimitConfig {
-                pattern: pattern.to_string(),
-                context_limit: *context_limit,
-            })
-            .collect()
+        LIMIT_RULES.iter().map(LimitRule::to_config).collect()
     }
 
     pub fn with_context_limit(mut self, limit: Option<usize>) -> Self {
         if limit.is_some() {
             self.context_limit = limit;
+            self.context_limit_explicit = true;
+        }
+        self
+    }
+
+    /// Use a context window reported by the provider or learned at runtime, unless the user
+    /// set one
+    pub fn with_reported_context_limit(mut self, limit: usize) -> Self {
+        if !self.context_limit_explicit {
+            self.context_limit = Some(limit);
         }
         self
     }
@@ -220,6 +587,36 @@ impl ModelConfig {
         self
     }
 
+    pub fn with_top_p(mut self, top_p: Option<f32>) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    pub fn with_stop(mut self, stop: Option<Vec<String>>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn with_frequency_penalty(mut self, penalty: Option<f32>) -> Self {
+        self.frequency_penalty = penalty;
+        self
+    }
+
+    pub fn with_presence_penalty(mut self, penalty: Option<f32>) -> Self {
+        self.presence_penalty = penalty;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: Option<i64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_logprobs(mut self, top_logprobs: Option<u8>) -> Self {
+        self.logprobs = top_logprobs;
+        self
+    }
+
     pub fn with_toolshim(mut self, toolshim: bool) -> Self {
         self.toolshim = toolshim;
         self
@@ -230,6 +627,16 @@ impl ModelConfig {
         self
     }
 
+    pub fn with_toolshim_provider(mut self, provider: Option<String>) -> Self {
+        self.toolshim_provider = provider;
+        self
+    }
+
+    pub fn with_fallbacks(mut self, fallbacks: Vec<ModelConfig>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
     pub fn context_limit(&self) -> usize {
         self.context_limit.unwrap_or(DEFAULT_CONTEXT_LIMIT)
     }
@@ -342,6 +749,24 @@ mod tests {
         });
     }
 
+    #[test]
+    #[serial]
+    fn test_toolshim_interpreter_from_env() {
+        with_var("GOOSE_TOOLSHIM_PROVIDER", Some("openai"), || {
+            with_var("GOOSE_TOOLSHIM_MODEL", Some("gpt-4o-mini"), || {
+                with_var("GOOSE_TOOLSHIM_OLLAMA_MODEL", Some("llama3"), || {
+                    let config = ModelConfig::new("test-model").unwrap();
+                    assert_eq!(config.toolshim_provider, Some("openai".to_string()));
+                    assert_eq!(config.toolshim_model, Some("gpt-4o-mini".to_string()));
+                });
+            });
+        });
+
+        with_var("GOOSE_TOOLSHIM_PROVIDER", Some(" "), || {
+            assert!(ModelConfig::new("test-model").is_err());
+        });
+    }
+
     #[test]
     #[serial]
     fn test_valid_configurations() {
@@ -360,4 +785,291 @@ mod tests {
             });
         });
     }
+
+    #[test]
+    #[serial]
+    fn test_sampling_parameters_from_env() {
+        with_var("GOOSE_TOP_P", Some("0.9"), || {
+            with_var("GOOSE_STOP", Some("END, ###"), || {
+                with_var("GOOSE_FREQUENCY_PENALTY", Some("0.5"), || {
+                    with_var("GOOSE_PRESENCE_PENALTY", Some("-1"), || {
+                        with_var("GOOSE_SEED", Some("42"), || {
+                            let config = ModelConfig::new("test-model").unwrap();
+                            assert_eq!(config.top_p, Some(0.9));
+                            assert_eq!(
+                                config.stop,
+                                Some(vec!["END".to_string(), "###".to_string()])
+                            );
+                            assert_eq!(config.frequency_penalty, Some(0.5));
+                            assert_eq!(config.presence_penalty, Some(-1.0));
+                            assert_eq!(config.seed, Some(42));
+                        });
+                    });
+                });
+            });
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_sampling_parameters() {
+        with_var("GOOSE_TOP_P", Some("1.5"), || {
+            assert!(matches!(
+                ModelConfig::new("test-model").unwrap_err(),
+                ConfigError::InvalidRange(_, _)
+            ));
+        });
+
+        with_var("GOOSE_PRESENCE_PENALTY", Some("3"), || {
+            assert!(matches!(
+                ModelConfig::new("test-model").unwrap_err(),
+                ConfigError::InvalidRange(_, _)
+            ));
+        });
+
+        with_var("GOOSE_SEED", Some("random"), || {
+            assert!(ModelConfig::new("test-model").is_err());
+        });
+
+        with_var("GOOSE_LOGPROBS", Some("21"), || {
+            assert!(ModelConfig::new("test-model").is_err());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_logprobs_from_env() {
+        with_var("GOOSE_LOGPROBS", Some("5"), || {
+            let config = ModelConfig::new("test-model").unwrap();
+            assert_eq!(config.logprobs, Some(5));
+        });
+        with_var("GOOSE_LOGPROBS", None::<&str>, || {
+            let config = ModelConfig::new("test-model").unwrap();
+            assert_eq!(config.logprobs, None);
+        });
+    }
+
+    #[test]
+    fn test_longest_pattern_wins() {
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("grok-4-0709"),
+            Some(256_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("grok-3"),
+            Some(131_072)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("llama-2-1b"),
+            Some(32_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("o3-mini"),
+            Some(200_000)
+        );
+
+        let rule = ModelConfig::get_model_limit_rule("qwen3-coder-480b").unwrap();
+        assert_eq!(rule.pattern, "qwen3-coder");
+        assert_eq!(rule.context_limit, 262_144);
+        assert!(!rule.regex);
+    }
+
+    #[test]
+    fn test_model_specific_cutoffs() {
+        assert_eq!(
+            ModelConfig::get_model_specific_cutoff("gpt-4o-mini"),
+            Some("2023-10")
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_cutoff("o3-mini-2025-01-31"),
+            Some("2023-10")
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_cutoff("anthropic.claude-3-5-sonnet-20241022-v2:0"),
+            Some("2024-04")
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_cutoff("claude-sonnet-4@20250514"),
+            Some("2025-03")
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_cutoff("unknown-model"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_vertex_gemini_model_ids() {
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("gemini-1.5-pro-002"),
+            Some(2_000_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("gemini-2.5-flash"),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("gemini-1.0-pro-002"),
+            Some(128_000)
+        );
+    }
+
+    #[test]
+    fn test_deepseek_model_ids() {
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("deepseek-reasoner"),
+            Some(131_072)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("deepseek/deepseek-r1-0528"),
+            Some(131_072)
+        );
+    }
+
+    #[test]
+    fn test_mistral_model_ids() {
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("codestral-latest"),
+            Some(256_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("mistral-large-latest"),
+            Some(131_072)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("mistral.mistral-large-2407-v1:0"),
+            Some(128_000)
+        );
+    }
+
+    #[test]
+    fn test_bedrock_model_ids() {
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("us.anthropic.claude-sonnet-4-20250514-v1:0"),
+            Some(200_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("anthropic.claude-instant-v1"),
+            Some(100_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("meta.llama3-8b-instruct-v1:0"),
+            Some(8_192)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("meta.llama3-1-70b-instruct-v1:0"),
+            Some(128_000)
+        );
+        assert_eq!(
+            ModelConfig::get_model_specific_limit("amazon.nova-micro-v1:0"),
+            Some(128_000)
+        );
+    }
+
+    #[test]
+    fn test_regex_limit_patterns() {
+        let rules = vec![
+            LimitRule::new("gpt", 8_000),
+            LimitRule::new("re:^gpt-\\d+o", 128_000),
+            LimitRule::new("gpt-4o-mini", 64_000),
+        ];
+
+        let rule = find_limit_rule(&rules, "gpt-4o").unwrap();
+        assert_eq!(rule.context_limit, 128_000);
+        assert_eq!(rule.to_config().pattern, "^gpt-\\d+o");
+        assert!(rule.to_config().regex);
+
+        assert_eq!(
+            find_limit_rule(&rules, "gpt-4o-mini")
+                .unwrap()
+                .context_limit,
+            64_000
+        );
+        assert_eq!(
+            find_limit_rule(&rules, "gpt-3.5").unwrap().context_limit,
+            8_000
+        );
+        assert!(find_limit_rule(&rules, "claude").is_none());
+    }
+
+    #[test]
+    fn test_resolve_alias_with() {
+        let aliases: HashMap<String, String> = [
+            ("fast", "gpt-4o-mini"),
+            ("smart", "claude-sonnet-4"),
+            ("default", "smart"),
+            ("loop-a", "loop-b"),
+            ("loop-b", "loop-a"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let resolve = |name| ModelConfig::resolve_alias_with(name, &aliases);
+        assert_eq!(resolve("fast").unwrap(), "gpt-4o-mini");
+        assert_eq!(resolve("default").unwrap(), "claude-sonnet-4");
+        assert_eq!(resolve("gpt-4o").unwrap(), "gpt-4o");
+        assert!(matches!(
+            resolve("loop-a"),
+            Err(ConfigError::InvalidValue(_, _, _))
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_model_config_resolves_alias() {
+        with_var(
+            MODEL_ALIASES_KEY,
+            Some(r#"{"smart": "claude-sonnet-4"}"#),
+            || {
+                let config = ModelConfig::new("smart").unwrap();
+                assert_eq!(config.model_name, "claude-sonnet-4");
+                assert_eq!(config.context_limit(), 200_000);
+            },
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_reported_context_limit_keeps_explicit_limits() {
+        with_var("GOOSE_CONTEXT_LIMIT", None::<&str>, || {
+            let config = ModelConfig::new_or_fail("gpt-4o");
+            assert!(!config.context_limit_explicit);
+            assert_eq!(
+                config.with_reported_context_limit(64_000).context_limit(),
+                64_000
+            );
+        });
+        with_var("GOOSE_CONTEXT_LIMIT", Some("50000"), || {
+            let config = ModelConfig::new_or_fail("gpt-4o");
+            assert!(config.context_limit_explicit);
+            assert_eq!(
+                config.with_reported_context_limit(64_000).context_limit(),
+                50_000
+            );
+        });
+        let config = ModelConfig::new_or_fail("gpt-4o").with_context_limit(Some(20_000));
+        assert_eq!(
+            config.with_reported_context_limit(64_000).context_limit(),
+            20_000
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_with_fallbacks() {
+        let config = ModelConfig::new_or_fail("gpt-4o").with_fallbacks(vec![
+            ModelConfig::new_or_fail("gpt-4o-mini"),
+            ModelConfig::new_or_fail("gpt-3.5-turbo"),
+        ]);
+        let names: Vec<&str> = config
+            .fallbacks
+            .iter()
+            .map(|m| m.model_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["gpt-4o-mini", "gpt-3.5-turbo"]);
+
+        let serialized = serde_json::to_value(ModelConfig::new_or_fail("gpt-4o")).unwrap();
+        assert!(serialized.get("fallbacks").is_none());
+    }
 }
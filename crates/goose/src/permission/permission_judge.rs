@@ -13,6 +13,17 @@ use serde_json::Value;
 use std::collections::HashSet;
 use std::sync::Arc;
 
+/// Tools that act for the user outside this machine, such as sending mail or invitations. They
+/// wait for the user every time, whatever the mode or saved permissions say.
+const ALWAYS_CONFIRM_TOOLS: &[&str] = &["send_email", "create_event"];
+
+/// Whether a tool must be confirmed by the user on every call; matched on the name without
+/// its extension prefix
+pub fn always_needs_confirmation(tool_name: &str) -> bool {
+    let name = tool_name.rsplit("__").next().unwrap_or(tool_name);
+    ALWAYS_CONFIRM_TOOLS.contains(&name)
+}
+
 /// Creates the tool definition for checking read-only permissions.
 fn create_read_only_tool() -> Tool {
     Tool::new(
@@ -175,6 +186,11 @@ pub async fn check_tool_permissions(
         if let Ok(tool_call) = request.tool_call.clone() {
             if mode == "chat" {
                 continue;
+            } else if always_needs_confirmation(&tool_call.name) {
+                match permission_manager.get_user_permission(&tool_call.name) {
+                    Some(PermissionLevel::NeverAllow) => denied.push(request.clone()),
+                    _ => needs_approval.push(request.clone()),
+                }
             } else if mode == "auto" {
                 approved.push(request.clone());
             } else {
@@ -515,4 +531,37 @@ mod tests {
         assert_eq!(result.needs_approval.len(), 0); // data_fetcher should need approval
         assert_eq!(result.denied.len(), 0); // No tool should be denied in this test
     }
+
+    #[tokio::test]
+    async fn test_outbound_tools_always_need_confirmation() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut permission_manager = PermissionManager::new(temp_file.path());
+        permission_manager
+            .update_user_permission("email__send_email", PermissionLevel::AlwaysAllow);
+
+        let candidate_requests: Vec<ToolRequest> = ["email__send_email", "calendar__create_event"]
+            .iter()
+            .map(|name| ToolRequest {
+                id: name.to_string(),
+                tool_call: ToolResult::Ok(ToolCall {
+                    name: name.to_string(),
+                    arguments: serde_json::json!({}),
+                }),
+            })
+            .collect();
+
+        for mode in ["auto", "smart_approve"] {
+            let (result, _) = check_tool_permissions(
+                &candidate_requests,
+                mode,
+                HashSet::new(),
+                HashSet::new(),
+                &mut permission_manager,
+                create_mock_provider(),
+            )
+            .await;
+            assert!(result.approved.is_empty());
+            assert_eq!(result.needs_approval.len(), candidate_requests.len());
+        }
+    }
 }
@@ -1,4 +1,6 @@
 pub mod storage;
+pub mod toolchain;
+pub mod workspace_lock;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
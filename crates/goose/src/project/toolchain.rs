@@ -0,0 +1,326 @@
+//! Detect how a repository is built and tested, so the model starts a session knowing the
+//! commands instead of discovering them by trial and error.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::extension_lock::find_executable;
+use crate::config::Config;
+
+/// Config key for turning the toolchain note off
+pub const PROJECT_BOOTSTRAP_KEY: &str = "GOOSE_PROJECT_BOOTSTRAP";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Toolchain {
+    Cargo,
+    Npm,
+    Pnpm,
+    Yarn,
+    Poetry,
+    Go,
+}
+
+impl Toolchain {
+    /// The program its commands run
+    pub fn program(&self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Npm => "npm",
+            Self::Pnpm => "pnpm",
+            Self::Yarn => "yarn",
+            Self::Poetry => "poetry",
+            Self::Go => "go",
+        }
+    }
+
+    pub fn language(&self) -> &'static str {
+        match self {
+            Self::Cargo => "Rust",
+            Self::Npm | Self::Pnpm | Self::Yarn => "JavaScript/TypeScript",
+            Self::Poetry => "Python",
+            Self::Go => "Go",
+        }
+    }
+}
+
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectCommand {
+    /// What the command is for: build, test or lint
+    pub purpose: &'static str,
+    pub command: String,
+}
+
+impl ProjectCommand {
+    fn new(purpose: &'static str, command: impl Into<String>) -> Self {
+        Self {
+            purpose,
+            command: command.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedToolchain {
+    pub toolchain: Toolchain,
+    /// The manifest it was detected from
+    pub manifest: PathBuf,
+    pub commands: Vec<ProjectCommand>,
+    /// Whether the program was found on PATH
+    pub installed: bool,
+}
+
+/// The repository root for a directory: the nearest ancestor with a .git entry
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Every toolchain with a manifest at the root of the repository
+pub fn detect(root: &Path) -> Vec<DetectedToolchain> {
+    let detectors: [fn(&Path) -> Option<(Toolchain, PathBuf, Vec<ProjectCommand>)>; 4] =
+        [detect_cargo, detect_node, detect_poetry, detect_go];
+    detectors
+        .iter()
+        .filter_map(|detector| detector(root))
+        .map(|(toolchain, manifest, commands)| DetectedToolchain {
+            installed: find_executable(toolchain.program()).is_some(),
+            toolchain,
+            manifest,
+            commands,
+        })
+        .collect()
+}
+
+fn detect_cargo(root: &Path) -> Option<(Toolchain, PathBuf, Vec<ProjectCommand>)> {
+    let manifest = root.join("Cargo.toml");
+    let contents = std::fs::read_to_string(&manifest).ok()?;
+    let scope = if contents.contains("[workspace]") {
+        " --workspace"
+    } else {
+        ""
+    };
+    Some((
+        Toolchain::Cargo,
+        manifest,
+        vec![
+            ProjectCommand::new("build", format!("cargo build{}", scope)),
+            ProjectCommand::new("test", format!("cargo test{}", scope)),
+            ProjectCommand::new("lint", format!("cargo clippy{}", scope)),
+        ],
+    ))
+}
+
+fn detect_node(root: &Path) -> Option<(Toolchain, PathBuf, Vec<ProjectCommand>)> {
+    let manifest = root.join("package.json");
+    let package: Value = serde_json::from_str(&std::fs::read_to_string(&manifest).ok()?).ok()?;
+    let toolchain = if root.join("pnpm-lock.yaml").exists() {
+        Toolchain::Pnpm
+    } else if root.join("yarn.lock").exists() {
+        Toolchain::Yarn
+    } else {
+        Toolchain::Npm
+    };
+
+    // Only the scripts the project defines; a bare `npm test` fails without one
+    let scripts = package["scripts"].as_object();
+    let commands = ["build", "test", "lint"]
+        .into_iter()
+        .filter(|script| scripts.is_some_and(|scripts| scripts.contains_key(*script)))
+        .map(|script| ProjectCommand::new(script, format!("{} run {}", toolchain, script)))
+        .collect();
+    Some((toolchain, manifest, commands))
+}
+
+fn detect_poetry(root: &Path) -> Option<(Toolchain, PathBuf, Vec<ProjectCommand>)> {
+    let manifest = root.join("pyproject.toml");
+    let contents = std::fs::read_to_string(&manifest).ok()?;
+    if !contents.contains("[tool.poetry") {
+        return None;
+    }
+    let mut commands = vec![ProjectCommand::new("build", "poetry install")];
+    if contents.contains("pytest") || root.join("tests").is_dir() {
+        commands.push(ProjectCommand::new("test", "poetry run pytest"));
+    }
+    let linter = [
+        ("ruff", "poetry run ruff check ."),
+        ("flake8", "poetry run flake8"),
+    ]
+    .into_iter()
+    .find(|(name, _)| contents.contains(name));
+    if let Some((_, command)) = linter {
+        commands.push(ProjectCommand::new("lint", command));
+    }
+    Some((Toolchain::Poetry, manifest, commands))
+}
+
+fn detect_go(root: &Path) -> Option<(Toolchain, PathBuf, Vec<ProjectCommand>)> {
+    let manifest = root.join("go.mod");
+    manifest.is_file().then(|| {
+        (
+            Toolchain::Go,
+            manifest,
+            vec![
+                ProjectCommand::new("build", "go build ./..."),
+                ProjectCommand::new("test", "go test ./..."),
+                ProjectCommand::new("lint", "go vet ./..."),
+            ],
+        )
+    })
+}
+
+/// A short note on the repository's toolchains and commands for the system prompt
+pub fn capability_note(root: &Path, toolchains: &[DetectedToolchain]) -> Option<String> {
+    if toolchains.is_empty() {
+        return None;
+    }
+    let mut lines = vec![format!(
+        "This session is in a repository at {}, detected from its manifests:",
+        root.display()
+    )];
+    for detected in toolchains {
+        let commands = detected
+            .commands
+            .iter()
+            .map(|command| format!("{} `{}`", command.purpose, command.command))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let commands = if commands.is_empty() {
+            "no build or test scripts defined".to_string()
+        } else {
+            commands
+        };
+        let status = if detected.installed {
+            String::new()
+        } else {
+            format!(
+                " ({} is not installed, so these commands will fail; tell the user rather than retrying them)",
+                detected.toolchain
+            )
+        };
+        lines.push(format!(
+            "- {} via {}: {}{}",
+            detected.toolchain.language(),
+            detected.toolchain,
+            commands,
+            status
+        ));
+    }
+    lines.push("Use these commands to build and check your changes.".to_string());
+    Some(lines.join("\n"))
+}
+
+/// The note for a session started in `dir`, unless it isn't in a repository, no toolchain is
+/// recognised, or GOOSE_PROJECT_BOOTSTRAP is false
+pub fn bootstrap_note(dir: &Path) -> Option<String> {
+    if !Config::global()
+        .get_param::<bool>(PROJECT_BOOTSTRAP_KEY)
+        .unwrap_or(true)
+    {
+        return None;
+    }
+    let root = repo_root(dir)?;
+    capability_note(&root, &detect(&root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, name: &str, contents: &str) {
+        std::fs::write(root.join(name), contents).unwrap();
+    }
+
+    #[test]
+    fn test_detects_each_toolchain() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        write(
+            root,
+            "package.json",
+            r#"{"scripts": {"build": "tsc", "lint": "eslint ."}}"#,
+        );
+        write(root, "pnpm-lock.yaml", "");
+        write(root, "go.mod", "module example.com/app\n");
+
+        let detected = detect(root);
+        let toolchains: Vec<Toolchain> = detected.iter().map(|d| d.toolchain).collect();
+        assert_eq!(
+            toolchains,
+            vec![Toolchain::Cargo, Toolchain::Pnpm, Toolchain::Go]
+        );
+        assert_eq!(detected[0].commands[1].command, "cargo test --workspace");
+        let node: Vec<&str> = detected[1]
+            .commands
+            .iter()
+            .map(|c| c.command.as_str())
+            .collect();
+        assert_eq!(node, vec!["pnpm run build", "pnpm run lint"]);
+    }
+
+    #[test]
+    fn test_poetry_needs_a_poetry_section() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "pyproject.toml", "[project]\nname = \"app\"\n");
+        assert!(detect(dir.path()).is_empty());
+
+        write(
+            dir.path(),
+            "pyproject.toml",
+            "[tool.poetry]\nname = \"app\"\n[tool.poetry.group.dev.dependencies]\npytest = \"^8\"\nruff = \"^0.5\"\n",
+        );
+        let detected = detect(dir.path());
+        let commands: Vec<&str> = detected[0]
+            .commands
+            .iter()
+            .map(|c| c.command.as_str())
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                "poetry install",
+                "poetry run pytest",
+                "poetry run ruff check ."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_note_flags_missing_programs() {
+        let root = Path::new("/work/app");
+        let toolchains = vec![DetectedToolchain {
+            toolchain: Toolchain::Go,
+            manifest: root.join("go.mod"),
+            commands: vec![ProjectCommand::new("test", "go test ./...")],
+            installed: false,
+        }];
+        let note = capability_note(root, &toolchains).unwrap();
+        assert!(note.contains("Go via go: test `go test ./...`"));
+        assert!(note.contains("go is not installed"));
+        assert!(capability_note(root, &[]).is_none());
+    }
+
+    #[test]
+    fn test_repo_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        let nested = dir.path().join("src/module");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(repo_root(&nested).unwrap(), dir.path());
+    }
+}
@@ -0,0 +1,252 @@
+//! Advisory locks on workspaces, so two goose sessions or scheduled jobs working in the same
+//! repository don't interleave conflicting edits.
+//!
+//! The lock files live in goose's state directory rather than the repository, one per
+//! repository root, and are held with an OS file lock so a crashed session never leaves a
+//! stale lock behind.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::toolchain::repo_root;
+use crate::config::Config;
+
+/// Config key for what a session does when another one holds its workspace
+pub const WORKSPACE_LOCK_KEY: &str = "GOOSE_WORKSPACE_LOCK";
+/// Config key for the seconds a queued session waits before giving up
+pub const WORKSPACE_LOCK_TIMEOUT_KEY: &str = "GOOSE_WORKSPACE_LOCK_TIMEOUT";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What to do when the workspace is already locked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceLockPolicy {
+    /// Ask the user, or queue when nobody is there to ask
+    #[default]
+    Prompt,
+    /// Wait for the other session to finish
+    Queue,
+    /// Go ahead without the lock, running only read-only tools
+    ReadOnly,
+    /// Don't lock workspaces at all
+    Off,
+}
+
+impl WorkspaceLockPolicy {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get_param::<WorkspaceLockPolicy>(WORKSPACE_LOCK_KEY)
+            .unwrap_or_default()
+    }
+}
+
+/// How long a queued session waits, from GOOSE_WORKSPACE_LOCK_TIMEOUT
+pub fn queue_timeout() -> Duration {
+    Config::global()
+        .get_param::<u64>(WORKSPACE_LOCK_TIMEOUT_KEY)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT)
+}
+
+/// Who holds a workspace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    /// The session, or `schedule:<job id>` for a scheduled job
+    pub owner: String,
+    pub pid: u32,
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (pid {}, since {})",
+            self.owner,
+            self.pid,
+            self.acquired_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+}
+
+/// A held workspace lock, released when dropped
+#[derive(Debug)]
+pub struct WorkspaceLock {
+    file: File,
+    root: PathBuf,
+}
+
+pub enum LockAttempt {
+    Acquired(WorkspaceLock),
+    /// Someone else has it; the holder is None if its lock file couldn't be read
+    Held(Option<LockHolder>),
+}
+
+/// The directory a session in `dir` locks: its repository root, or `dir` outside a repository
+pub fn workspace_root(dir: &Path) -> PathBuf {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    repo_root(&dir).unwrap_or(dir)
+}
+
+fn lock_dir() -> Result<PathBuf> {
+    // Windows has no convention for state_dir, use data_dir instead
+    let strategy = choose_app_strategy(crate::config::APP_STRATEGY.clone())?;
+    Ok(strategy
+        .in_state_dir("workspace-locks")
+        .unwrap_or_else(|| strategy.in_data_dir("workspace-locks")))
+}
+
+fn lock_path(lock_dir: &Path, root: &Path) -> PathBuf {
+    let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+    let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    lock_dir.join(format!("{}.lock", name))
+}
+
+impl WorkspaceLock {
+    /// Take the lock on the workspace containing `dir` if nobody holds it
+    pub fn try_acquire(dir: &Path, owner: &str) -> Result<LockAttempt> {
+        Self::try_acquire_in(&lock_dir()?, dir, owner)
+    }
+
+    fn try_acquire_in(lock_dir: &Path, dir: &Path, owner: &str) -> Result<LockAttempt> {
+        let root = workspace_root(dir);
+        std::fs::create_dir_all(lock_dir)?;
+        let path = lock_path(lock_dir, &root);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open workspace lock {}", path.display()))?;
+
+        if file.try_lock_exclusive().is_err() {
+            let mut contents = String::new();
+            let holder = file
+                .read_to_string(&mut contents)
+                .ok()
+                .and_then(|_| serde_json::from_str(&contents).ok());
+            return Ok(LockAttempt::Held(holder));
+        }
+
+        let holder = LockHolder {
+            owner: owner.to_string(),
+            pid: std::process::id(),
+            acquired_at: Utc::now(),
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+        file.flush()?;
+        Ok(LockAttempt::Acquired(Self { file, root }))
+    }
+
+    /// Wait up to `timeout` for the lock on the workspace containing `dir`
+    pub async fn acquire(dir: &Path, owner: &str, timeout: Duration) -> Result<Self> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut announced = false;
+        loop {
+            match Self::try_acquire(dir, owner)? {
+                LockAttempt::Acquired(lock) => return Ok(lock),
+                LockAttempt::Held(holder) => {
+                    let holder = holder
+                        .map(|holder| holder.to_string())
+                        .unwrap_or_else(|| "another session".to_string());
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(anyhow!(
+                            "Gave up waiting for {} to release {}",
+                            holder,
+                            workspace_root(dir).display()
+                        ));
+                    }
+                    if !announced {
+                        tracing::info!(
+                            "Waiting for {} to release {}",
+                            holder,
+                            workspace_root(dir).display()
+                        );
+                        announced = true;
+                    }
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for WorkspaceLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_session_sees_the_holder() {
+        let locks = tempfile::tempdir().unwrap();
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join(".git")).unwrap();
+        let nested = repo.path().join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let first = match WorkspaceLock::try_acquire_in(locks.path(), repo.path(), "first") {
+            Ok(LockAttempt::Acquired(lock)) => lock,
+            _ => panic!("expected to acquire the lock"),
+        };
+
+        // A session in a subdirectory of the same repository contends for the same lock
+        match WorkspaceLock::try_acquire_in(locks.path(), &nested, "second").unwrap() {
+            LockAttempt::Held(Some(holder)) => {
+                assert_eq!(holder.owner, "first");
+                assert_eq!(holder.pid, std::process::id());
+            }
+            _ => panic!("expected the lock to be held"),
+        }
+
+        drop(first);
+        assert!(matches!(
+            WorkspaceLock::try_acquire_in(locks.path(), &nested, "second").unwrap(),
+            LockAttempt::Acquired(_)
+        ));
+    }
+
+    #[test]
+    fn test_separate_workspaces_lock_separately() {
+        let locks = tempfile::tempdir().unwrap();
+        let one = tempfile::tempdir().unwrap();
+        let two = tempfile::tempdir().unwrap();
+
+        let _first = WorkspaceLock::try_acquire_in(locks.path(), one.path(), "first").unwrap();
+        assert!(matches!(
+            WorkspaceLock::try_acquire_in(locks.path(), two.path(), "second").unwrap(),
+            LockAttempt::Acquired(_)
+        ));
+    }
+
+    #[test]
+    fn test_policy_names() {
+        let policy: WorkspaceLockPolicy = serde_json::from_str("\"read_only\"").unwrap();
+        assert_eq!(policy, WorkspaceLockPolicy::ReadOnly);
+        assert_eq!(WorkspaceLockPolicy::default(), WorkspaceLockPolicy::Prompt);
+    }
+}
@@ -9,16 +9,21 @@ use tokio::pin;
 use tokio_util::io::StreamReader;
 
 use super::api_client::{ApiClient, ApiResponse, AuthMethod};
-use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage, ToolChoice,
+};
 use super::errors::ProviderError;
 use super::formats::anthropic::{
-    create_request, get_usage, response_to_message, response_to_streaming_message,
+    apply_tool_choice, create_request, get_usage, response_to_message,
+    response_to_streaming_message,
 };
-use super::utils::{emit_debug_trace, get_model, map_http_error_to_provider_error};
+use super::prompt_cache;
+use super::utils::{emit_debug_trace, get_model, map_http_error_to_provider_error, retry_after};
+use super::vision::ImageLimits;
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
-use crate::providers::retry::ProviderRetry;
+use crate::providers::retry::{ProviderRetry, RetryPolicy};
 use rmcp::model::Tool;
 
 const ANTHROPIC_DEFAULT_MODEL: &str = "claude-sonnet-4-0";
@@ -42,6 +47,8 @@ pub struct AnthropicProvider {
     #[serde(skip)]
     api_client: ApiClient,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(AnthropicProvider);
@@ -59,10 +66,15 @@ impl AnthropicProvider {
             key: api_key,
         };
 
-        let api_client =
-            ApiClient::new(host, auth)?.with_header("anthropic-version", ANTHROPIC_API_VERSION)?;
+        let api_client = ApiClient::new(host, auth)?
+            .with_header("anthropic-version", ANTHROPIC_API_VERSION)?
+            .with_configured_middleware("anthropic")?;
 
-        Ok(Self { api_client, model })
+        Ok(Self {
+            api_client,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "ANTHROPIC"),
+        })
     }
 
     fn get_conditional_headers(&self) -> Vec<(&str, &str)> {
@@ -111,10 +123,10 @@ impl AnthropicProvider {
                         }
                     }
                 }
-                Err(map_http_error_to_provider_error(
-                    response.status,
-                    response.payload,
-                ))
+                Err(
+                    map_http_error_to_provider_error(response.status, response.payload)
+                        .with_retry_delay(response.retry_after),
+                )
             }
         }
     }
@@ -147,21 +159,52 @@ impl Provider for AnthropicProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
 
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        true
+    }
+
+    /// Images over 1568px on the long edge are scaled down by the API before the model sees them
+    fn image_limits(&self) -> ImageLimits {
+        ImageLimits {
+            max_bytes: 5 * 1024 * 1024,
+            max_dimension: 1568,
+        }
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_tool_choice(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+        tool_choice: &ToolChoice,
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools)?;
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        apply_tool_choice(&mut payload, tool_choice);
+        if !prompt_cache::is_enabled() {
+            prompt_cache::strip_cache_control(&mut payload);
+        }
 
         let response = self
             .with_retry(|| async { self.post(&payload).await })
@@ -221,26 +264,47 @@ impl Provider for AnthropicProvider {
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
     ) -> Result<MessageStream, ProviderError> {
         let mut payload = create_request(&self.model, system, messages, tools)?;
+        apply_tool_choice(&mut payload, tool_choice);
+        if !prompt_cache::is_enabled() {
+            prompt_cache::strip_cache_control(&mut payload);
+        }
         payload
             .as_object_mut()
             .unwrap()
             .insert("stream".to_string(), Value::Bool(true));
 
-        let mut request = self.api_client.request("v1/messages");
-
-        for (key, value) in self.get_conditional_headers() {
-            request = request.header(key, value)?;
-        }
+        let response = self
+            .with_retry(|| async {
+                let mut request = self.api_client.request("v1/messages");
+                for (key, value) in self.get_conditional_headers() {
+                    request = request.header(key, value)?;
+                }
 
-        let response = request.response_post(&payload).await?;
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            let error_json = serde_json::from_str::<Value>(&error_text).ok();
-            return Err(map_http_error_to_provider_error(status, error_json));
-        }
+                let response = request.response_post(&payload).await?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let retry_after = retry_after(response.headers());
+                    let error_text = response.text().await.unwrap_or_default();
+                    let error_json = serde_json::from_str::<Value>(&error_text).ok();
+                    return Err(map_http_error_to_provider_error(status, error_json)
+                        .with_retry_delay(retry_after));
+                }
+                Ok(response)
+            })
+            .await?;
 
         let stream = response.bytes_stream().map_err(io::Error::other);
 
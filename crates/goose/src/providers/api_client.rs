@@ -1,11 +1,14 @@
+use super::middleware::{self, MiddlewareRequest, ProviderMiddleware};
 use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::{
     header::{HeaderMap, HeaderName, HeaderValue},
-    Client, Response, StatusCode,
+    multipart::Form,
+    Client, Method, Response, StatusCode,
 };
 use serde_json::Value;
 use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub struct ApiClient {
@@ -14,6 +17,8 @@ pub struct ApiClient {
     auth: AuthMethod,
     default_headers: HeaderMap,
     timeout: Duration,
+    provider: String,
+    middleware: Vec<Arc<dyn ProviderMiddleware>>,
 }
 
 pub enum AuthMethod {
@@ -42,6 +47,8 @@ pub trait AuthProvider: Send + Sync {
 pub struct ApiResponse {
     pub status: StatusCode,
     pub payload: Option<Value>,
+    /// The wait the server asked for before trying again, if it said
+    pub retry_after: Option<Duration>,
 }
 
 impl fmt::Debug for AuthMethod {
@@ -62,11 +69,22 @@ impl fmt::Debug for AuthMethod {
 impl ApiResponse {
     pub async fn from_response(response: Response) -> Result<Self> {
         let status = response.status();
+        let retry_after = super::utils::retry_after(response.headers());
         let payload = response.json().await.ok();
-        Ok(Self { status, payload })
+        Ok(Self {
+            status,
+            payload,
+            retry_after,
+        })
     }
 }
 
+enum Body<'a> {
+    Empty,
+    Json(&'a Value),
+    Multipart(Form),
+}
+
 pub struct ApiRequestBuilder<'a> {
     client: &'a ApiClient,
     path: &'a str,
@@ -85,9 +103,24 @@ impl ApiClient {
             auth,
             default_headers: HeaderMap::new(),
             timeout,
+            provider: String::new(),
+            middleware: Vec::new(),
         })
     }
 
+    /// Run the middleware registered in config for this provider around each request
+    pub fn with_configured_middleware(mut self, provider: &str) -> Result<Self> {
+        self.provider = provider.to_string();
+        self.middleware = middleware::configured(provider)?;
+        Ok(self)
+    }
+
+    #[allow(dead_code)]
+    pub fn with_middleware(mut self, middleware: Arc<dyn ProviderMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
     pub fn with_headers(mut self, headers: HeaderMap) -> Result<Self> {
         self.default_headers = headers;
         self.client = Client::builder()
@@ -124,6 +157,10 @@ impl ApiClient {
         self.request(path).response_post(payload).await
     }
 
+    pub async fn response_post_multipart(&self, path: &str, form: Form) -> Result<Response> {
+        self.request(path).response_post_multipart(form).await
+    }
+
     pub async fn api_get(&self, path: &str) -> Result<ApiResponse> {
         self.request(path).api_get().await
     }
@@ -172,8 +209,11 @@ impl<'a> ApiRequestBuilder<'a> {
     }
 
     pub async fn response_post(self, payload: &Value) -> Result<Response> {
-        let request = self.send_request(|url, client| client.post(url)).await?;
-        Ok(request.json(payload).send().await?)
+        self.send(Method::POST, Body::Json(payload)).await
+    }
+
+    pub async fn response_post_multipart(self, form: Form) -> Result<Response> {
+        self.send(Method::POST, Body::Multipart(form)).await
     }
 
     pub async fn api_get(self) -> Result<ApiResponse> {
@@ -182,17 +222,54 @@ impl<'a> ApiRequestBuilder<'a> {
     }
 
     pub async fn response_get(self) -> Result<Response> {
-        let request = self.send_request(|url, client| client.get(url)).await?;
-        Ok(request.send().await?)
+        self.send(Method::GET, Body::Empty).await
     }
 
-    async fn send_request<F>(&self, request_builder: F) -> Result<reqwest::RequestBuilder>
-    where
-        F: FnOnce(url::Url, &Client) -> reqwest::RequestBuilder,
-    {
+    async fn send(&self, method: Method, body: Body<'_>) -> Result<Response> {
         let url = self.client.build_url(self.path)?;
-        let mut request = request_builder(url, &self.client.client);
-        request = request.headers(self.headers.clone());
+        let middleware = &self.client.middleware;
+        if middleware.is_empty() {
+            return self.dispatch(method, url, self.headers.clone(), body).await;
+        }
+
+        // Only JSON payloads are shown to the middleware; other bodies go out as they are
+        let mut outgoing = MiddlewareRequest {
+            provider: self.client.provider.clone(),
+            method,
+            url,
+            headers: self.headers.clone(),
+            payload: match &body {
+                Body::Json(payload) => Some((*payload).clone()),
+                _ => None,
+            },
+        };
+        for hook in middleware {
+            hook.on_request(&mut outgoing).await?;
+        }
+        let body = match (body, &outgoing.payload) {
+            (Body::Json(_), Some(payload)) => Body::Json(payload),
+            (Body::Json(_), None) => Body::Empty,
+            (body, _) => body,
+        };
+        let response = self
+            .dispatch(
+                outgoing.method.clone(),
+                outgoing.url.clone(),
+                outgoing.headers.clone(),
+                body,
+            )
+            .await?;
+        middleware::handle_response(middleware, &outgoing, response).await
+    }
+
+    async fn dispatch(
+        &self,
+        method: Method,
+        url: url::Url,
+        headers: HeaderMap,
+        body: Body<'_>,
+    ) -> Result<Response> {
+        let mut request = self.client.client.request(method, url).headers(headers);
 
         request = match &self.client.auth {
             AuthMethod::BearerToken(token) => {
@@ -209,7 +286,12 @@ impl<'a> ApiRequestBuilder<'a> {
             }
         };
 
-        Ok(request)
+        request = match body {
+            Body::Empty => request,
+            Body::Json(payload) => request.json(payload),
+            Body::Multipart(form) => request.multipart(form),
+        };
+        Ok(request.send().await?)
     }
 }
 
@@ -220,6 +302,7 @@ impl fmt::Debug for ApiClient {
             .field("auth", &"[auth method]")
             .field("timeout", &self.timeout)
             .field("default_headers", &self.default_headers)
+            .field("middleware", &self.middleware.len())
             .finish_non_exhaustive()
     }
 }
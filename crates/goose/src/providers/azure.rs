@@ -2,13 +2,14 @@ use anyhow::Result;
 use async_trait::async_trait;
 use serde::Serialize;
 use serde_json::Value;
+use std::collections::HashMap;
 
 use super::api_client::{ApiClient, AuthMethod, AuthProvider};
 use super::azureauth::{AuthError, AzureAuth};
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
@@ -21,12 +22,77 @@ pub const AZURE_DOC_URL: &str =
 pub const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
 pub const AZURE_OPENAI_KNOWN_MODELS: &[&str] = &["gpt-4o", "gpt-4o-mini", "gpt-4"];
 
+/// Config key mapping deployment names to the models they serve, e.g.
+/// `{"prod-chat": "gpt-4o", "cheap": "gpt-4o-mini"}`
+pub const AZURE_DEPLOYMENTS_KEY: &str = "AZURE_OPENAI_DEPLOYMENTS";
+
+/// Pick the deployment to call for `model_name` and the model it serves.
+///
+/// The model name may be a deployment from `deployments` or a model one of them serves;
+/// otherwise the default deployment is used and assumed to serve the model as named.
+fn resolve_deployment(
+    model_name: &str,
+    default_deployment: Option<&str>,
+    deployments: &HashMap<String, String>,
+) -> Result<(String, String)> {
+    if let Some(model) = deployments.get(model_name) {
+        return Ok((model_name.to_string(), model.clone()));
+    }
+    let mut serving: Vec<&String> = deployments
+        .iter()
+        .filter(|(_, model)| *model == model_name)
+        .map(|(deployment, _)| deployment)
+        .collect();
+    serving.sort();
+    if let Some(deployment) = serving.first() {
+        return Ok((deployment.to_string(), model_name.to_string()));
+    }
+    match default_deployment {
+        Some(deployment) => Ok((
+            deployment.to_string(),
+            deployments
+                .get(deployment)
+                .cloned()
+                .unwrap_or_else(|| model_name.to_string()),
+        )),
+        None => Err(anyhow::anyhow!(
+            "No Azure OpenAI deployment for model {}; set AZURE_OPENAI_DEPLOYMENT_NAME or add it to {}",
+            model_name,
+            AZURE_DEPLOYMENTS_KEY
+        )),
+    }
+}
+
+/// The config for the model a deployment serves, so context limits, pricing and tokenizers
+/// follow the model rather than the deployment's name
+fn config_for_model(model: ModelConfig, model_name: &str) -> Result<ModelConfig> {
+    if model.model_name == model_name {
+        return Ok(model);
+    }
+    let resolved = ModelConfig::new(model_name)?;
+    let context_limit = resolved.context_limit.or(model.context_limit);
+    Ok(resolved
+        .with_context_limit(context_limit)
+        .with_temperature(model.temperature)
+        .with_max_tokens(model.max_tokens)
+        .with_top_p(model.top_p)
+        .with_stop(model.stop)
+        .with_frequency_penalty(model.frequency_penalty)
+        .with_presence_penalty(model.presence_penalty)
+        .with_seed(model.seed)
+        .with_toolshim(model.toolshim)
+        .with_toolshim_model(model.toolshim_model)
+        .with_toolshim_provider(model.toolshim_provider)
+        .with_fallbacks(model.fallbacks))
+}
+
 #[derive(Debug)]
 pub struct AzureProvider {
     api_client: ApiClient,
     deployment_name: String,
     api_version: String,
     model: ModelConfig,
+    retry_policy: RetryPolicy,
 }
 
 impl Serialize for AzureProvider {
@@ -74,7 +140,18 @@ impl AzureProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
         let config = crate::config::Config::global();
         let endpoint: String = config.get_param("AZURE_OPENAI_ENDPOINT")?;
-        let deployment_name: String = config.get_param("AZURE_OPENAI_DEPLOYMENT_NAME")?;
+        let default_deployment: Option<String> = config
+            .get_param("AZURE_OPENAI_DEPLOYMENT_NAME")
+            .ok()
+            .filter(|name: &String| !name.is_empty());
+        let deployments: HashMap<String, String> =
+            config.get_param(AZURE_DEPLOYMENTS_KEY).unwrap_or_default();
+        let (deployment_name, model_name) = resolve_deployment(
+            &model.model_name,
+            default_deployment.as_deref(),
+            &deployments,
+        )?;
+        let model = config_for_model(model, &model_name)?;
         let api_version: String = config
             .get_param("AZURE_OPENAI_API_VERSION")
             .unwrap_or_else(|_| AZURE_DEFAULT_API_VERSION.to_string());
@@ -89,13 +166,15 @@ impl AzureProvider {
         })?;
 
         let auth_provider = AzureAuthProvider { auth };
-        let api_client = ApiClient::new(endpoint, AuthMethod::Custom(Box::new(auth_provider)))?;
+        let api_client = ApiClient::new(endpoint, AuthMethod::Custom(Box::new(auth_provider)))?
+            .with_configured_middleware("azure_openai")?;
 
         Ok(Self {
             api_client,
             deployment_name,
             api_version,
             model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
         })
     }
 
@@ -123,13 +202,18 @@ impl Provider for AzureProvider {
             AZURE_DOC_URL,
             vec![
                 ConfigKey::new("AZURE_OPENAI_ENDPOINT", true, false, None),
-                ConfigKey::new("AZURE_OPENAI_DEPLOYMENT_NAME", true, false, None),
+                ConfigKey::new("AZURE_OPENAI_DEPLOYMENT_NAME", false, false, None),
+                ConfigKey::new(AZURE_DEPLOYMENTS_KEY, false, false, None),
                 ConfigKey::new("AZURE_OPENAI_API_VERSION", true, false, Some("2024-10-21")),
                 ConfigKey::new("AZURE_OPENAI_API_KEY", true, true, Some("")),
             ],
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -162,3 +246,48 @@ impl Provider for AzureProvider {
         Ok((message, ProviderUsage::new(model, usage)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployments() -> HashMap<String, String> {
+        HashMap::from([
+            ("prod-chat".to_string(), "gpt-4o".to_string()),
+            ("cheap".to_string(), "gpt-4o-mini".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_deployment() {
+        let deployments = deployments();
+        assert_eq!(
+            resolve_deployment("prod-chat", None, &deployments).unwrap(),
+            ("prod-chat".to_string(), "gpt-4o".to_string())
+        );
+        assert_eq!(
+            resolve_deployment("gpt-4o-mini", None, &deployments).unwrap(),
+            ("cheap".to_string(), "gpt-4o-mini".to_string())
+        );
+        assert_eq!(
+            resolve_deployment("gpt-4.1", Some("cheap"), &deployments).unwrap(),
+            ("cheap".to_string(), "gpt-4o-mini".to_string())
+        );
+        assert_eq!(
+            resolve_deployment("gpt-4.1", Some("legacy"), &deployments).unwrap(),
+            ("legacy".to_string(), "gpt-4.1".to_string())
+        );
+        assert!(resolve_deployment("gpt-4.1", None, &deployments).is_err());
+    }
+
+    #[test]
+    fn test_limits_follow_the_deployed_model() {
+        let model = ModelConfig::new_or_fail("prod-chat").with_temperature(Some(0.2));
+        assert_eq!(model.context_limit, None);
+
+        let model = config_for_model(model, "gpt-4.1").unwrap();
+        assert_eq!(model.model_name, "gpt-4.1");
+        assert_eq!(model.context_limit(), 1_000_000);
+        assert_eq!(model.temperature, Some(0.2));
+    }
+}
@@ -0,0 +1,515 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::base::{
+    stream_from_single_message, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+    RequestTarget, ToolChoice,
+};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+/// Config key listing the providers and models to balance across
+pub const BALANCED_PROVIDERS_KEY: &str = "GOOSE_BALANCED_PROVIDERS";
+/// Config key for the seconds a member sits out after failing
+pub const BALANCED_COOLDOWN_KEY: &str = "GOOSE_BALANCED_COOLDOWN";
+
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// One provider and model requests can be sent to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalancedMember {
+    pub provider: String,
+    pub model: String,
+    /// Its share of requests relative to the other members
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone)]
+pub struct BalancedConfig {
+    pub members: Vec<BalancedMember>,
+    pub cooldown: Duration,
+}
+
+impl BalancedConfig {
+    /// Read the members from GOOSE_BALANCED_PROVIDERS, e.g.
+    ///
+    /// ```yaml
+    /// GOOSE_BALANCED_PROVIDERS:
+    ///   - provider: openai
+    ///     model: gpt-4o
+    ///     weight: 3
+    ///   - provider: azure_openai
+    ///     model: gpt-4o
+    /// ```
+    pub fn from_config() -> Result<Self> {
+        let config = Config::global();
+        let members: Vec<BalancedMember> =
+            config.get_param(BALANCED_PROVIDERS_KEY).map_err(|e| {
+                anyhow::anyhow!(
+                    "The balanced provider needs {} to list its members: {}",
+                    BALANCED_PROVIDERS_KEY,
+                    e
+                )
+            })?;
+        let cooldown = config
+            .get_param::<u64>(BALANCED_COOLDOWN_KEY)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_COOLDOWN);
+        let config = Self { members, cooldown };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.members.is_empty() {
+            bail!("{} lists no providers", BALANCED_PROVIDERS_KEY);
+        }
+        for member in &self.members {
+            if member.provider == "balanced" {
+                bail!("The balanced provider can't be one of its own members");
+            }
+            if member.weight == 0 {
+                bail!(
+                    "{}/{} has a weight of 0; remove it instead",
+                    member.provider,
+                    member.model
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Member {
+    provider: Arc<dyn Provider>,
+    provider_name: String,
+    weight: i64,
+    label: String,
+}
+
+#[derive(Default)]
+struct Schedule {
+    /// Smooth weighted round robin credit per member
+    current: Vec<i64>,
+    /// When each member may be used again after failing
+    resting_until: Vec<Option<Instant>>,
+}
+
+/// A provider that spreads requests across a weighted set of providers and models, moving on
+/// to the next member when one is rate limited, overloaded or unreachable.
+///
+/// Members are picked by smooth weighted round robin, so a member with weight 3 answers three
+/// of every four requests next to one with weight 1, interleaved rather than in runs. A member
+/// that fails sits out for the cooldown, or for as long as its Retry-After asked.
+pub struct BalancedProvider {
+    model: ModelConfig,
+    members: Vec<Member>,
+    cooldown: Duration,
+    schedule: Mutex<Schedule>,
+    /// The member the latest request was sent to
+    attempted: AtomicUsize,
+}
+
+impl BalancedProvider {
+    /// `providers` holds one provider per member, in the same order
+    pub fn new(config: &BalancedConfig, providers: Vec<Arc<dyn Provider>>) -> Result<Self> {
+        config.validate()?;
+        if providers.len() != config.members.len() {
+            bail!(
+                "Expected {} providers, got {}",
+                config.members.len(),
+                providers.len()
+            );
+        }
+
+        let members: Vec<Member> = config
+            .members
+            .iter()
+            .zip(providers)
+            .map(|(member, provider)| Member {
+                provider,
+                provider_name: member.provider.clone(),
+                weight: member.weight as i64,
+                label: format!("{}/{}", member.provider, member.model),
+            })
+            .collect();
+
+        // Any member may answer, so plan for the smallest context window among them
+        let context_limit = members
+            .iter()
+            .map(|member| member.provider.get_model_config().context_limit())
+            .min();
+        let model = members[0]
+            .provider
+            .get_model_config()
+            .with_context_limit(context_limit);
+
+        Ok(Self {
+            model,
+            schedule: Mutex::new(Schedule {
+                current: vec![0; members.len()],
+                resting_until: vec![None; members.len()],
+            }),
+            members,
+            cooldown: config.cooldown,
+            attempted: AtomicUsize::new(0),
+        })
+    }
+
+    /// The members in the order to try them for the next request: the round robin's pick,
+    /// then the other available members by weight, then any that are resting
+    fn order(&self) -> Vec<usize> {
+        let mut schedule = self.schedule.lock().unwrap();
+        let now = Instant::now();
+        let available: Vec<usize> = (0..self.members.len())
+            .filter(|&i| schedule.resting_until[i].is_none_or(|until| until <= now))
+            .collect();
+
+        let mut order = Vec::with_capacity(self.members.len());
+        if !available.is_empty() {
+            let total: i64 = available.iter().map(|&i| self.members[i].weight).sum();
+            for &i in &available {
+                schedule.current[i] += self.members[i].weight;
+            }
+            let pick = *available
+                .iter()
+                .max_by_key(|&&i| (schedule.current[i], std::cmp::Reverse(i)))
+                .expect("available is not empty");
+            schedule.current[pick] -= total;
+            order.push(pick);
+
+            let mut rest: Vec<usize> = available.into_iter().filter(|&i| i != pick).collect();
+            rest.sort_by_key(|&i| std::cmp::Reverse(self.members[i].weight));
+            order.extend(rest);
+        }
+        let mut resting: Vec<usize> = (0..self.members.len())
+            .filter(|i| !order.contains(i))
+            .collect();
+        resting.sort_by_key(|&i| schedule.resting_until[i]);
+        order.extend(resting);
+        order
+    }
+
+    fn rest(&self, index: usize, error: &ProviderError) {
+        let wait = match error {
+            ProviderError::RateLimitExceeded {
+                retry_delay: Some(delay),
+                ..
+            } => *delay,
+            _ => self.cooldown,
+        };
+        self.schedule.lock().unwrap().resting_until[index] = Some(Instant::now() + wait);
+    }
+
+    fn record_answer(&self, index: usize) {
+        self.schedule.lock().unwrap().resting_until[index] = None;
+        super::base::set_current_model(&self.members[index].provider.get_model_config().model_name);
+    }
+
+    /// Whether an error means another member may do better
+    fn should_fail_over(error: &ProviderError) -> bool {
+        matches!(
+            error,
+            ProviderError::RateLimitExceeded { .. }
+                | ProviderError::ServerError(_)
+                | ProviderError::RequestFailed(_)
+        )
+    }
+
+    fn failed_over(&self, index: usize, error: &ProviderError, remaining: bool) -> bool {
+        if !Self::should_fail_over(error) {
+            return false;
+        }
+        self.rest(index, error);
+        if remaining {
+            tracing::warn!(
+                "{} unavailable ({}), trying the next provider",
+                self.members[index].label,
+                error
+            );
+        }
+        remaining
+    }
+}
+
+#[async_trait]
+impl Provider for BalancedProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "balanced",
+            "Balanced",
+            "Spread requests across several providers and models by weight, failing over between them",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn complete_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let order = self.order();
+        let mut last_error = None;
+        for (position, &index) in order.iter().enumerate() {
+            self.attempted.store(index, Ordering::Relaxed);
+            let provider = &self.members[index].provider;
+            match provider
+                .complete_with_tool_choice(system, messages, tools, tool_choice)
+                .await
+            {
+                Ok(result) => {
+                    self.record_answer(index);
+                    return Ok(result);
+                }
+                Err(e) if self.failed_over(index, &e, position + 1 < order.len()) => {
+                    last_error = Some(e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("balanced provider always has a member"))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<MessageStream, ProviderError> {
+        let order = self.order();
+        let mut last_error = None;
+        for (position, &index) in order.iter().enumerate() {
+            self.attempted.store(index, Ordering::Relaxed);
+            let provider = &self.members[index].provider;
+            let result = if provider.supports_streaming() {
+                provider
+                    .stream_with_tool_choice(system, messages, tools, tool_choice)
+                    .await
+            } else {
+                provider
+                    .complete_with_tool_choice(system, messages, tools, tool_choice)
+                    .await
+                    .map(|(message, usage)| stream_from_single_message(message, usage))
+            };
+            match result {
+                Ok(stream) => {
+                    self.record_answer(index);
+                    return Ok(stream);
+                }
+                Err(e) if self.failed_over(index, &e, position + 1 < order.len()) => {
+                    last_error = Some(e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("balanced provider always has a member"))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.members
+            .iter()
+            .any(|member| member.provider.supports_streaming())
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        self.members
+            .iter()
+            .all(|member| member.provider.supports_tool_choice())
+    }
+
+    fn last_request_target(&self) -> RequestTarget {
+        let member = &self.members[self.attempted.load(Ordering::Relaxed)];
+        member
+            .provider
+            .last_request_target()
+            .with_provider(Some(&member.provider_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    struct MockProvider {
+        model_config: ModelConfig,
+        error: Option<fn(String) -> ProviderError>,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let name = self.model_config.model_name.clone();
+            match self.error {
+                Some(error) => Err(error(format!("{} failed", name))),
+                None => Ok((
+                    Message::assistant().with_text(format!("Response from {}", name)),
+                    ProviderUsage::new(name, Usage::default()),
+                )),
+            }
+        }
+    }
+
+    fn rate_limited(details: String) -> ProviderError {
+        ProviderError::rate_limited(details)
+    }
+
+    fn mock(name: &str, error: Option<fn(String) -> ProviderError>) -> Arc<MockProvider> {
+        Arc::new(MockProvider {
+            model_config: ModelConfig::new_or_fail(name),
+            error,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    fn balanced(members: &[(&Arc<MockProvider>, u32)]) -> BalancedProvider {
+        let config = BalancedConfig {
+            members: members
+                .iter()
+                .map(|(provider, weight)| BalancedMember {
+                    provider: "mock".to_string(),
+                    model: provider.model_config.model_name.clone(),
+                    weight: *weight,
+                })
+                .collect(),
+            cooldown: Duration::from_secs(60),
+        };
+        let providers = members
+            .iter()
+            .map(|(provider, _)| (*provider).clone() as Arc<dyn Provider>)
+            .collect();
+        BalancedProvider::new(&config, providers).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_follow_the_weights() {
+        let east = mock("east", None);
+        let west = mock("west", None);
+        let provider = balanced(&[(&east, 3), (&west, 1)]);
+
+        let mut answered = Vec::new();
+        for _ in 0..8 {
+            let (_message, usage) = provider.complete("system", &[], &[]).await.unwrap();
+            answered.push(usage.model);
+        }
+
+        assert_eq!(east.calls.load(Ordering::SeqCst), 6);
+        assert_eq!(west.calls.load(Ordering::SeqCst), 2);
+        // Interleaved rather than in runs
+        assert_eq!(answered[..4], ["east", "east", "west", "east"]);
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_and_rests_the_failing_member() {
+        let limited = mock("limited", Some(rate_limited));
+        let healthy = mock("healthy", None);
+        let provider = balanced(&[(&limited, 5), (&healthy, 1)]);
+
+        for _ in 0..3 {
+            let (_message, usage) = provider.complete("system", &[], &[]).await.unwrap();
+            assert_eq!(usage.model, "healthy");
+        }
+        // Tried once, then left alone while it cools down
+        assert_eq!(limited.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_returned() {
+        let broken = mock("broken", Some(ProviderError::Authentication));
+        let healthy = mock("healthy", None);
+        let provider = balanced(&[(&broken, 1), (&healthy, 1)]);
+
+        let result = provider.complete("system", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::Authentication(_))));
+        assert_eq!(healthy.calls.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            provider.last_request_target(),
+            RequestTarget {
+                provider: Some("mock".to_string()),
+                model: "broken".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let members: Vec<BalancedMember> = serde_yaml::from_str(
+            "- provider: openai\n  model: gpt-4o\n  weight: 2\n- provider: azure_openai\n  model: gpt-4o\n",
+        )
+        .unwrap();
+        assert_eq!(members[1].weight, 1);
+        let config = BalancedConfig {
+            members,
+            cooldown: DEFAULT_COOLDOWN,
+        };
+        assert!(config.validate().is_ok());
+
+        let nested = BalancedConfig {
+            members: vec![BalancedMember {
+                provider: "balanced".to_string(),
+                model: "x".to_string(),
+                weight: 1,
+            }],
+            cooldown: DEFAULT_COOLDOWN,
+        };
+        assert!(nested.validate().is_err());
+    }
+}
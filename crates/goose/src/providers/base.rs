@@ -2,8 +2,11 @@ use anyhow::Result;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 
+use super::delta::{deltas_from_messages, DeltaStream};
 use super::errors::ProviderError;
-use super::retry::RetryConfig;
+use super::health::Diagnostic;
+use super::retry::RetryPolicy;
+use super::vision::ImageLimits;
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
 use crate::model::ModelConfig;
@@ -46,6 +49,19 @@ pub struct ModelInfo {
     pub currency: Option<String>,
     /// Whether this model supports cache control
     pub supports_cache_control: Option<bool>,
+    /// Whether this model supports native tool calling, if the provider reports it
+    #[serde(default)]
+    pub supports_tools: Option<bool>,
+    /// Whether this model reads images, if the provider reports it
+    #[serde(default)]
+    pub supports_vision: Option<bool>,
+    /// Whether this is a reasoning model, which takes `developer` instructions and rejects
+    /// sampling parameters, if the provider reports it
+    #[serde(default)]
+    pub reasoning: Option<bool>,
+    /// Month the model's training data ends, as `YYYY-MM`, if the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub knowledge_cutoff: Option<String>,
 }
 
 impl ModelInfo {
@@ -58,6 +74,10 @@ impl ModelInfo {
             output_token_cost: None,
             currency: None,
             supports_cache_control: None,
+            supports_tools: None,
+            supports_vision: None,
+            reasoning: None,
+            knowledge_cutoff: None,
         }
     }
 
@@ -75,6 +95,10 @@ impl ModelInfo {
             output_token_cost: Some(output_cost),
             currency: Some("$".to_string()),
             supports_cache_control: None,
+            supports_tools: None,
+            supports_vision: None,
+            reasoning: None,
+            knowledge_cutoff: None,
         }
     }
 }
@@ -123,6 +147,10 @@ impl ProviderMetadata {
                     output_token_cost: None,
                     currency: None,
                     supports_cache_control: None,
+                    supports_tools: None,
+                    supports_vision: None,
+                    reasoning: None,
+                    knowledge_cutoff: None,
                 })
                 .collect(),
             model_doc_link: model_doc_link.to_string(),
@@ -219,11 +247,48 @@ impl ProviderUsage {
     }
 }
 
+/// Whether and which tool the model has to call on a turn
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides
+    #[default]
+    Auto,
+    /// The model must answer without calling a tool
+    None,
+    /// The model must call at least one tool
+    Required,
+    /// The model must call the named tool
+    Tool(String),
+}
+
+impl ToolChoice {
+    /// The tools a provider that can't enforce the choice should offer: none, only the
+    /// named one, or all of them
+    pub fn filter_tools(&self, tools: &[Tool]) -> Vec<Tool> {
+        match self {
+            ToolChoice::None => Vec::new(),
+            ToolChoice::Tool(name) => tools
+                .iter()
+                .filter(|tool| tool.name == name.as_str())
+                .cloned()
+                .collect(),
+            ToolChoice::Auto | ToolChoice::Required => tools.to_vec(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, Copy)]
 pub struct Usage {
     pub input_tokens: Option<i32>,
     pub output_tokens: Option<i32>,
     pub total_tokens: Option<i32>,
+    /// Input tokens read from the provider's prompt cache, included in `input_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_tokens: Option<i32>,
+    /// Input tokens written to the provider's prompt cache, included in `input_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_tokens: Option<i32>,
 }
 
 fn sum_optionals<T>(a: Option<T>, b: Option<T>) -> Option<T>
@@ -246,6 +311,8 @@ impl Add for Usage {
             input_tokens: sum_optionals(self.input_tokens, other.input_tokens),
             output_tokens: sum_optionals(self.output_tokens, other.output_tokens),
             total_tokens: sum_optionals(self.total_tokens, other.total_tokens),
+            cache_read_tokens: sum_optionals(self.cache_read_tokens, other.cache_read_tokens),
+            cache_write_tokens: sum_optionals(self.cache_write_tokens, other.cache_write_tokens),
         }
     }
 }
@@ -266,8 +333,21 @@ impl Usage {
             input_tokens,
             output_tokens,
             total_tokens,
+            cache_read_tokens: None,
+            cache_write_tokens: None,
         }
     }
+
+    /// Record how many of the input tokens were read from and written to the prompt cache
+    pub fn with_cache_tokens(
+        mut self,
+        cache_read_tokens: Option<i32>,
+        cache_write_tokens: Option<i32>,
+    ) -> Self {
+        self.cache_read_tokens = cache_read_tokens;
+        self.cache_write_tokens = cache_write_tokens;
+        self
+    }
 }
 
 use async_trait::async_trait;
@@ -281,6 +361,34 @@ pub trait LeadWorkerProviderTrait {
     fn get_active_model(&self) -> String;
 }
 
+/// Trait for FallbackProvider-specific functionality
+pub trait FallbackProviderTrait {
+    /// Get the name of the primary model in the chain
+    fn get_primary_model(&self) -> String;
+
+    /// Get the name of the model that produced the most recent response
+    fn get_active_model(&self) -> String;
+}
+
+/// The provider and model a request was sent to, which is what the model registry keys
+/// learned context bounds by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTarget {
+    /// The provider's name in the factory, or `None` for the configured provider
+    pub provider: Option<String>,
+    pub model: String,
+}
+
+impl RequestTarget {
+    /// Name the provider, unless a provider further down already did
+    pub fn with_provider(mut self, provider: Option<&str>) -> Self {
+        if self.provider.is_none() {
+            self.provider = provider.map(str::to_string);
+        }
+        self
+    }
+}
+
 /// Base trait for AI providers (OpenAI, Anthropic, etc)
 #[async_trait]
 pub trait Provider: Send + Sync {
@@ -312,8 +420,11 @@ pub trait Provider: Send + Sync {
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
 
-    fn retry_config(&self) -> RetryConfig {
-        RetryConfig::default()
+    /// How failed requests are retried. Providers that retry load their policy from the
+    /// settings described on [`RetryPolicy`] when they are created, so it can't change partway
+    /// through a request.
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
     }
 
     /// Optional hook to fetch supported models.
@@ -321,6 +432,18 @@ pub trait Provider: Send + Sync {
         Ok(None)
     }
 
+    /// Check the credentials, endpoint and model before the first real request. The default
+    /// lists the provider's models, or sends a tiny request when it can't list them.
+    async fn health_check(&self) -> Vec<Diagnostic> {
+        super::health::probe(self).await
+    }
+
+    /// Optional hook to fetch model metadata, including context windows, from the provider API.
+    /// Used by the model registry to refine the built-in context limits.
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        Ok(None)
+    }
+
     /// Check if this provider supports embeddings
     fn supports_embeddings(&self) -> bool {
         false
@@ -331,6 +454,16 @@ pub trait Provider: Send + Sync {
         false
     }
 
+    /// Whether the configured model reads images
+    fn supports_vision(&self) -> bool {
+        super::vision::model_supports_vision(&self.get_model_config().model_name)
+    }
+
+    /// The largest image this provider accepts
+    fn image_limits(&self) -> ImageLimits {
+        ImageLimits::default()
+    }
+
     /// Create embeddings if supported. Default implementation returns an error.
     async fn create_embeddings(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
         Err(ProviderError::ExecutionError(
@@ -344,6 +477,12 @@ pub trait Provider: Send + Sync {
         None
     }
 
+    /// Check if this provider is a FallbackProvider
+    /// This is used to report which model in the chain actually answered
+    fn as_fallback(&self) -> Option<&dyn FallbackProviderTrait> {
+        None
+    }
+
     async fn stream(
         &self,
         _system: &str,
@@ -359,17 +498,97 @@ pub trait Provider: Send + Sync {
         false
     }
 
+    /// Whether the provider sends [`ToolChoice`] to the model rather than only narrowing the
+    /// tools it offers
+    fn supports_tool_choice(&self) -> bool {
+        false
+    }
+
+    /// Like [`Provider::complete`], constraining which tool the model calls on this turn.
+    /// The default offers only the tools the choice allows, which can't force a call.
+    async fn complete_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete(system, messages, &tool_choice.filter_tools(tools))
+            .await
+    }
+
+    /// Like [`Provider::complete`], answering with a JSON value that matches `schema` rather
+    /// than a message. The default forces a call to a tool taking the schema as its input when
+    /// the provider supports tool choice, and otherwise asks for JSON and retries with the
+    /// validation errors until it matches.
+    async fn complete_with_response_schema(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &serde_json::Value,
+    ) -> Result<(serde_json::Value, ProviderUsage), ProviderError> {
+        super::structured::complete(self, system, messages, schema).await
+    }
+
+    /// Like [`Provider::stream`], constraining which tool the model calls on this turn
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream(system, messages, &tool_choice.filter_tools(tools))
+            .await
+    }
+
+    /// Stream a completion as [`CompletionDelta`](super::delta::CompletionDelta)s, whatever
+    /// the backend's own format. This is the path the agent loop consumes.
+    ///
+    /// Providers that stream are adapted from [`Provider::stream_with_tool_choice`]; the rest
+    /// complete the request and replay the finished message as deltas, so callers can consume
+    /// every provider the same way.
+    async fn stream_deltas(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<DeltaStream, ProviderError> {
+        let stream = if self.supports_streaming() {
+            self.stream_with_tool_choice(system, messages, tools, tool_choice)
+                .await?
+        } else {
+            let (message, usage) = self
+                .complete_with_tool_choice(system, messages, tools, tool_choice)
+                .await?;
+            stream_from_single_message(message, usage)
+        };
+        Ok(deltas_from_messages(stream))
+    }
+
     /// Get the currently active model name
     /// For regular providers, this returns the configured model
     /// For LeadWorkerProvider, this returns the currently active model (lead or worker)
     fn get_active_model_name(&self) -> String {
         if let Some(lead_worker) = self.as_lead_worker() {
             lead_worker.get_active_model()
+        } else if let Some(fallback) = self.as_fallback() {
+            fallback.get_active_model()
         } else {
             self.get_model_config().model_name
         }
     }
 
+    /// Where the most recent request went. Providers that hand requests to other providers
+    /// report the one that handled it, whether it answered or failed.
+    fn last_request_target(&self) -> RequestTarget {
+        RequestTarget {
+            provider: None,
+            model: self.get_model_config().model_name,
+        }
+    }
+
     /// Returns the first 3 user messages as strings for session naming
     fn get_initial_user_messages(&self, messages: &Conversation) -> Vec<String> {
         messages
@@ -540,6 +759,10 @@ mod tests {
             output_token_cost: None,
             currency: None,
             supports_cache_control: None,
+            supports_tools: None,
+            supports_vision: None,
+            reasoning: None,
+            knowledge_cutoff: None,
         };
         assert_eq!(info.context_limit, 1000);
 
@@ -551,6 +774,10 @@ mod tests {
             output_token_cost: None,
             currency: None,
             supports_cache_control: None,
+            supports_tools: None,
+            supports_vision: None,
+            reasoning: None,
+            knowledge_cutoff: None,
         };
         assert_eq!(info, info2);
 
@@ -562,6 +789,10 @@ mod tests {
             output_token_cost: None,
             currency: None,
             supports_cache_control: None,
+            supports_tools: None,
+            supports_vision: None,
+            reasoning: None,
+            knowledge_cutoff: None,
         };
         assert_ne!(info, info3);
     }
@@ -575,4 +806,21 @@ mod tests {
         assert_eq!(info.output_token_cost, Some(0.00001));
         assert_eq!(info.currency, Some("$".to_string()));
     }
+
+    #[test]
+    fn test_tool_choice_filters_tools() {
+        let tools = vec![
+            Tool::new("shell", "Run a command", serde_json::Map::new()),
+            Tool::new("answer", "Give the answer", serde_json::Map::new()),
+        ];
+        assert!(ToolChoice::None.filter_tools(&tools).is_empty());
+        assert_eq!(ToolChoice::Required.filter_tools(&tools).len(), 2);
+        let only = ToolChoice::Tool("answer".to_string()).filter_tools(&tools);
+        assert_eq!(only.len(), 1);
+        assert_eq!(only[0].name, "answer");
+        assert_eq!(
+            serde_json::to_value(ToolChoice::Tool("answer".to_string())).unwrap(),
+            json!({"type": "tool", "name": "answer"})
+        );
+    }
 }
@@ -1,16 +1,19 @@
 use std::collections::HashMap;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
-use crate::conversation::message::Message;
+use super::retry::{ProviderRetry, RetryPolicy};
+use crate::conversation::message::{Message, StopReason};
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
 use crate::providers::utils::emit_debug_trace;
 use anyhow::Result;
+use async_stream::try_stream;
 use async_trait::async_trait;
 use aws_sdk_bedrockruntime::config::ProvideCredentials;
 use aws_sdk_bedrockruntime::operation::converse::ConverseError;
+use aws_sdk_bedrockruntime::operation::converse_stream::ConverseStreamError;
+use aws_sdk_bedrockruntime::types::error::ConverseStreamOutputError;
 use aws_sdk_bedrockruntime::{types as bedrock, Client};
 use rmcp::model::Tool;
 use serde_json::Value;
@@ -18,6 +21,7 @@ use serde_json::Value;
 // Import the migrated helper functions from providers/formats/bedrock.rs
 use super::formats::bedrock::{
     from_bedrock_message, from_bedrock_usage, to_bedrock_message, to_bedrock_tool_config,
+    BedrockStreamState,
 };
 
 pub const BEDROCK_DOC_LINK: &str =
@@ -27,6 +31,13 @@ pub const BEDROCK_DEFAULT_MODEL: &str = "anthropic.claude-3-5-sonnet-20240620-v1
 pub const BEDROCK_KNOWN_MODELS: &[&str] = &[
     "anthropic.claude-3-5-sonnet-20240620-v1:0",
     "anthropic.claude-3-5-sonnet-20241022-v2:0",
+    "us.anthropic.claude-3-7-sonnet-20250219-v1:0",
+    "us.anthropic.claude-sonnet-4-20250514-v1:0",
+    "us.anthropic.claude-opus-4-20250514-v1:0",
+    "amazon.nova-pro-v1:0",
+    "amazon.nova-lite-v1:0",
+    "meta.llama3-1-70b-instruct-v1:0",
+    "mistral.mistral-large-2407-v1:0",
 ];
 
 #[derive(Debug, serde::Serialize)]
@@ -34,6 +45,27 @@ pub struct BedrockProvider {
     #[serde(skip)]
     client: Client,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
+}
+
+fn to_bedrock_messages(messages: &[Message]) -> Result<Vec<bedrock::Message>> {
+    messages.iter().map(to_bedrock_message).collect()
+}
+
+/// Map an error that arrives partway through a response stream
+fn from_stream_error(
+    err: aws_sdk_bedrockruntime::error::SdkError<
+        ConverseStreamOutputError,
+        aws_smithy_types::event_stream::RawMessage,
+    >,
+) -> ProviderError {
+    match err.into_service_error() {
+        ConverseStreamOutputError::ThrottlingException(throttle_err) => {
+            ProviderError::rate_limited(format!("Bedrock throttling error: {:?}", throttle_err))
+        }
+        err => ProviderError::ServerError(format!("Bedrock stream error: {:?}", err)),
+    }
 }
 
 impl BedrockProvider {
@@ -65,7 +97,89 @@ impl BedrockProvider {
         )?;
         let client = Client::new(&sdk_config);
 
-        Ok(Self { client, model })
+        Ok(Self {
+            client,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
+        })
+    }
+
+    /// Sampling settings for the request; Converse has no penalty or seed parameters
+    fn inference_config(&self) -> Option<bedrock::InferenceConfiguration> {
+        let model = &self.model;
+        if model.max_tokens.is_none()
+            && model.temperature.is_none()
+            && model.top_p.is_none()
+            && model.stop.is_none()
+        {
+            return None;
+        }
+        Some(
+            bedrock::InferenceConfiguration::builder()
+                .set_max_tokens(model.max_tokens)
+                .set_temperature(model.temperature)
+                .set_top_p(model.top_p)
+                .set_stop_sequences(model.stop.clone())
+                .build(),
+        )
+    }
+
+    /// Start a ConverseStream request, returning the stream of response events
+    async fn converse_stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<
+        aws_sdk_bedrockruntime::primitives::event_stream::EventReceiver<
+            bedrock::ConverseStreamOutput,
+            ConverseStreamOutputError,
+        >,
+        ProviderError,
+    > {
+        let mut request = self
+            .client
+            .converse_stream()
+            .system(bedrock::SystemContentBlock::Text(system.to_string()))
+            .model_id(self.model.model_name.to_string())
+            .set_messages(Some(to_bedrock_messages(messages)?))
+            .set_inference_config(self.inference_config());
+
+        if !tools.is_empty() {
+            request = request.tool_config(to_bedrock_tool_config(tools)?);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| match err.into_service_error() {
+                ConverseStreamError::ThrottlingException(throttle_err) => {
+                    ProviderError::rate_limited(format!(
+                        "Bedrock throttling error: {:?}",
+                        throttle_err
+                    ))
+                }
+                ConverseStreamError::AccessDeniedException(err) => {
+                    ProviderError::Authentication(format!("Failed to call Bedrock: {:?}", err))
+                }
+                ConverseStreamError::ValidationException(err)
+                    if err
+                        .message()
+                        .unwrap_or_default()
+                        .contains("Input is too long for requested model.") =>
+                {
+                    ProviderError::ContextLengthExceeded(format!(
+                        "Failed to call Bedrock: {:?}",
+                        err
+                    ))
+                }
+                ConverseStreamError::ModelErrorException(err) => {
+                    ProviderError::ExecutionError(format!("Failed to call Bedrock: {:?}", err))
+                }
+                err => ProviderError::ServerError(format!("Failed to call Bedrock: {:?}", err)),
+            })?;
+
+        Ok(response.stream)
     }
 
     async fn converse(
@@ -73,7 +187,14 @@ impl BedrockProvider {
         system: &str,
         messages: &[Message],
         tools: &[Tool],
-    ) -> Result<(bedrock::Message, Option<bedrock::TokenUsage>), ProviderError> {
+    ) -> Result<
+        (
+            bedrock::Message,
+            Option<bedrock::TokenUsage>,
+            bedrock::StopReason,
+        ),
+        ProviderError,
+    > {
         let model_name = &self.model.model_name;
 
         let mut request = self
@@ -81,12 +202,8 @@ impl BedrockProvider {
             .converse()
             .system(bedrock::SystemContentBlock::Text(system.to_string()))
             .model_id(model_name.to_string())
-            .set_messages(Some(
-                messages
-                    .iter()
-                    .map(to_bedrock_message)
-                    .collect::<Result<_>>()?,
-            ));
+            .set_messages(Some(to_bedrock_messages(messages)?))
+            .set_inference_config(self.inference_config());
 
         if !tools.is_empty() {
             request = request.tool_config(to_bedrock_tool_config(tools)?);
@@ -96,12 +213,9 @@ impl BedrockProvider {
             .send()
             .await
             .map_err(|err| match err.into_service_error() {
-                ConverseError::ThrottlingException(throttle_err) => {
-                    ProviderError::RateLimitExceeded(format!(
-                        "Bedrock throttling error: {:?}",
-                        throttle_err
-                    ))
-                }
+                ConverseError::ThrottlingException(throttle_err) => ProviderError::rate_limited(
+                    format!("Bedrock throttling error: {:?}", throttle_err),
+                ),
                 ConverseError::AccessDeniedException(err) => {
                     ProviderError::Authentication(format!("Failed to call Bedrock: {:?}", err))
                 }
@@ -123,7 +237,9 @@ impl BedrockProvider {
             })?;
 
         match response.output {
-            Some(bedrock::ConverseOutput::Message(message)) => Ok((message, response.usage)),
+            Some(bedrock::ConverseOutput::Message(message)) => {
+                Ok((message, response.usage, response.stop_reason))
+            }
             _ => Err(ProviderError::RequestFailed(
                 "No output from Bedrock".to_string(),
             )),
@@ -143,10 +259,17 @@ impl Provider for BedrockProvider {
             BEDROCK_DEFAULT_MODEL,
             BEDROCK_KNOWN_MODELS.to_vec(),
             BEDROCK_DOC_LINK,
-            vec![ConfigKey::new("AWS_PROFILE", true, false, Some("default"))],
+            vec![
+                ConfigKey::new("AWS_PROFILE", true, false, Some("default")),
+                ConfigKey::new("AWS_REGION", false, false, None),
+            ],
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -163,7 +286,7 @@ impl Provider for BedrockProvider {
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let model_name = &self.model.model_name;
 
-        let (bedrock_message, bedrock_usage) = self
+        let (bedrock_message, bedrock_usage, bedrock_stop_reason) = self
             .with_retry(|| self.converse(system, messages, tools))
             .await?;
 
@@ -172,7 +295,9 @@ impl Provider for BedrockProvider {
             .map(from_bedrock_usage)
             .unwrap_or_default();
 
-        let message = from_bedrock_message(&bedrock_message)?;
+        let message = from_bedrock_message(&bedrock_message)?.with_stop_reason(Some(
+            StopReason::from_provider_str(bedrock_stop_reason.as_str()),
+        ));
 
         // Add debug trace with input context
         let debug_payload = serde_json::json!({
@@ -190,4 +315,43 @@ impl Provider for BedrockProvider {
         let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
         Ok((message, provider_usage))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let mut events = self
+            .with_retry(|| self.converse_stream(system, messages, tools))
+            .await?;
+
+        let model_config = self.model.clone();
+        let debug_payload = serde_json::json!({
+            "system": system,
+            "messages": messages,
+            "tools": tools
+        });
+        Ok(Box::pin(try_stream! {
+            let mut state = BedrockStreamState::default();
+            while let Some(event) = events.recv().await.map_err(from_stream_error)? {
+                if let Some(message) = state.handle(event) {
+                    yield (Some(message), None);
+                }
+            }
+
+            let (message, usage) = state.finish();
+            emit_debug_trace(
+                &model_config,
+                &debug_payload,
+                &serde_json::to_value(&message).unwrap_or_default(),
+                &usage,
+            );
+            yield (message, Some(ProviderUsage::new(model_config.model_name.clone(), usage)));
+        }))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 }
@@ -287,6 +287,10 @@ impl ClaudeCodeProvider {
             role: Role::Assistant,
             created: chrono::Utc::now().timestamp(),
             content: message_content,
+            stop_reason: None,
+            pinned: false,
+            origin: None,
+            logprobs: Vec::new(),
         };
 
         Ok((response_message, usage))
@@ -438,6 +442,10 @@ impl ClaudeCodeProvider {
             role: Role::Assistant,
             created: chrono::Utc::now().timestamp(),
             content: vec![MessageContent::text(description.clone())],
+            stop_reason: None,
+            pinned: false,
+            origin: None,
+            logprobs: Vec::new(),
         };
 
         let usage = Usage::default();
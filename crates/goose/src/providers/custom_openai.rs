@@ -0,0 +1,469 @@
+//! A provider for self-hosted gateways that speak the OpenAI API.
+//!
+//! vLLM, LiteLLM, TGI and similar servers accept OpenAI requests but differ in where they
+//! serve each endpoint, how they authenticate and which parts of the API they implement.
+//! Everything about the gateway is configured rather than assumed: the base URL, extra
+//! headers, the auth scheme, a path per endpoint, and switches for features it lacks.
+
+use anyhow::{anyhow, Result};
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use tokio::pin;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{FramedRead, LinesCodec};
+use tokio_util::io::StreamReader;
+
+use super::api_client::{ApiClient, AuthMethod, AuthProvider};
+use super::base::{
+    ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage, ToolChoice, Usage,
+};
+use super::errors::ProviderError;
+use super::formats::openai::{
+    apply_tool_choice, create_request, get_usage, response_to_message,
+    response_to_streaming_message,
+};
+use super::openai::parse_custom_headers;
+use super::retry::{ProviderRetry, RetryPolicy};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
+    ImageFormat,
+};
+use crate::config::{Config, ConfigError};
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+pub const CUSTOM_OPENAI_DEFAULT_HOST: &str = "http://localhost:8000/v1/";
+pub const CUSTOM_OPENAI_DEFAULT_MODEL: &str = "default";
+pub const CUSTOM_OPENAI_DOC_URL: &str = "https://platform.openai.com/docs/api-reference/chat";
+
+/// Fields only OpenAI's own tool calling understands
+const TOOL_FIELDS: &[&str] = &["tools", "tool_choice", "parallel_tool_calls"];
+
+/// How requests authenticate with the gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`, the OpenAI default
+    Bearer,
+    /// The key as the value of the named header, e.g. `header:X-Api-Key`
+    Header(String),
+    /// No credentials, for gateways on a trusted network
+    None,
+}
+
+impl AuthScheme {
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        match value.to_lowercase().as_str() {
+            "" | "bearer" => Ok(AuthScheme::Bearer),
+            "none" => Ok(AuthScheme::None),
+            _ => match value.split_once(':') {
+                Some((scheme, header)) if scheme.eq_ignore_ascii_case("header") => {
+                    let header = header.trim();
+                    if header.is_empty() {
+                        return Err(anyhow!("CUSTOM_OPENAI_AUTH_SCHEME needs a header name"));
+                    }
+                    Ok(AuthScheme::Header(header.to_string()))
+                }
+                _ => Err(anyhow!(
+                    "Unknown CUSTOM_OPENAI_AUTH_SCHEME '{}', expected bearer, none or header:<name>",
+                    value
+                )),
+            },
+        }
+    }
+
+    fn auth_method(&self, api_key: Option<String>) -> Result<AuthMethod> {
+        let require_key = || {
+            api_key
+                .clone()
+                .filter(|key| !key.is_empty())
+                .ok_or_else(|| anyhow!("CUSTOM_OPENAI_API_KEY is required by the auth scheme"))
+        };
+        Ok(match self {
+            AuthScheme::Bearer => AuthMethod::BearerToken(require_key()?),
+            AuthScheme::Header(header_name) => AuthMethod::ApiKey {
+                header_name: header_name.clone(),
+                key: require_key()?,
+            },
+            AuthScheme::None => AuthMethod::Custom(Box::new(NoAuth)),
+        })
+    }
+}
+
+/// Where the gateway serves each endpoint, relative to the base URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointPaths {
+    pub chat: String,
+    pub models: String,
+}
+
+impl Default for EndpointPaths {
+    fn default() -> Self {
+        Self {
+            chat: "chat/completions".to_string(),
+            models: "models".to_string(),
+        }
+    }
+}
+
+/// Parts of the OpenAI API the gateway implements, all on by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GatewayFeatures {
+    /// Accepts the `tools` field; without it tool definitions are left out of requests
+    pub tools: bool,
+    /// Streams responses as server-sent events
+    pub streaming: bool,
+    /// Reports usage at the end of a stream when asked with `stream_options`
+    pub stream_usage: bool,
+    /// Lists its models at the models endpoint
+    pub list_models: bool,
+}
+
+impl Default for GatewayFeatures {
+    fn default() -> Self {
+        Self {
+            tools: true,
+            streaming: true,
+            stream_usage: true,
+            list_models: true,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CustomOpenAiProvider {
+    #[serde(skip)]
+    api_client: ApiClient,
+    model: ModelConfig,
+    paths: EndpointPaths,
+    features: GatewayFeatures,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
+}
+
+impl_provider_default!(CustomOpenAiProvider);
+
+impl CustomOpenAiProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let host: String = config
+            .get_param("CUSTOM_OPENAI_BASE_URL")
+            .unwrap_or_else(|_| CUSTOM_OPENAI_DEFAULT_HOST.to_string());
+        let api_key: Option<String> = config.get_secret("CUSTOM_OPENAI_API_KEY").ok();
+        let auth_scheme = match config.get_param::<String>("CUSTOM_OPENAI_AUTH_SCHEME") {
+            Ok(scheme) => AuthScheme::parse(&scheme)?,
+            // Without a key there is nothing to send
+            Err(_) if api_key.is_none() => AuthScheme::None,
+            Err(_) => AuthScheme::Bearer,
+        };
+        let custom_headers: Option<HashMap<String, String>> = config
+            .get_secret("CUSTOM_OPENAI_HEADERS")
+            .or_else(|_| config.get_param("CUSTOM_OPENAI_HEADERS"))
+            .ok()
+            .map(parse_custom_headers);
+        let paths: EndpointPaths = optional(config, "CUSTOM_OPENAI_PATHS")?.unwrap_or_default();
+        let features: GatewayFeatures =
+            optional(config, "CUSTOM_OPENAI_FEATURES")?.unwrap_or_default();
+        let timeout_secs: u64 = config.get_param("CUSTOM_OPENAI_TIMEOUT").unwrap_or(600);
+
+        let mut api_client = ApiClient::with_timeout(
+            base_url(&host),
+            auth_scheme.auth_method(api_key)?,
+            std::time::Duration::from_secs(timeout_secs),
+        )?
+        .with_configured_middleware("custom_openai")?;
+
+        if let Some(headers) = custom_headers {
+            let mut header_map = reqwest::header::HeaderMap::new();
+            for (key, value) in headers {
+                let header_name = reqwest::header::HeaderName::from_bytes(key.as_bytes())?;
+                let header_value = reqwest::header::HeaderValue::from_str(&value)?;
+                header_map.insert(header_name, header_value);
+            }
+            api_client = api_client.with_headers(header_map)?;
+        }
+
+        if !features.tools && !model.toolshim {
+            tracing::warn!(
+                "CUSTOM_OPENAI_FEATURES turns tools off; enable GOOSE_TOOLSHIM for goose to use extensions"
+            );
+        }
+
+        Ok(Self {
+            api_client,
+            model,
+            paths,
+            features,
+            retry_policy: RetryPolicy::from_config(config, "CUSTOM_OPENAI"),
+        })
+    }
+
+    fn create_request(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<Value> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        if self.features.tools {
+            apply_tool_choice(&mut payload, tool_choice);
+        } else {
+            strip_tool_fields(&mut payload);
+        }
+        Ok(payload)
+    }
+
+    async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
+        let response = self
+            .api_client
+            .response_post(&self.paths.chat, payload)
+            .await?;
+        handle_response_openai_compat(response).await
+    }
+}
+
+/// Make relative endpoint paths extend the base URL instead of replacing its last segment
+fn base_url(host: &str) -> String {
+    if host.ends_with('/') {
+        host.to_string()
+    } else {
+        format!("{}/", host)
+    }
+}
+
+fn strip_tool_fields(payload: &mut Value) {
+    if let Some(object) = payload.as_object_mut() {
+        for field in TOOL_FIELDS {
+            object.remove(*field);
+        }
+    }
+}
+
+/// A JSON setting that is fine to leave out but has to parse when given
+fn optional<T: for<'de> Deserialize<'de>>(config: &Config, key: &str) -> Result<Option<T>> {
+    match config.get_param::<T>(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(ConfigError::NotFound(_)) => Ok(None),
+        Err(e) => Err(anyhow!("Invalid {}: {}", key, e)),
+    }
+}
+
+struct NoAuth;
+
+#[async_trait]
+impl AuthProvider for NoAuth {
+    async fn get_auth_header(&self) -> Result<(String, String)> {
+        Ok(("X-No-Auth".to_string(), "true".to_string()))
+    }
+}
+
+#[async_trait]
+impl Provider for CustomOpenAiProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "custom_openai",
+            "OpenAI Compatible Gateway",
+            "Self-hosted gateways such as vLLM, LiteLLM or TGI that speak the OpenAI API",
+            CUSTOM_OPENAI_DEFAULT_MODEL,
+            vec![],
+            CUSTOM_OPENAI_DOC_URL,
+            vec![
+                ConfigKey::new(
+                    "CUSTOM_OPENAI_BASE_URL",
+                    true,
+                    false,
+                    Some(CUSTOM_OPENAI_DEFAULT_HOST),
+                ),
+                ConfigKey::new("CUSTOM_OPENAI_API_KEY", false, true, None),
+                ConfigKey::new("CUSTOM_OPENAI_AUTH_SCHEME", false, false, Some("bearer")),
+                ConfigKey::new("CUSTOM_OPENAI_HEADERS", false, true, None),
+                ConfigKey::new("CUSTOM_OPENAI_PATHS", false, false, None),
+                ConfigKey::new("CUSTOM_OPENAI_FEATURES", false, false, None),
+                ConfigKey::new("CUSTOM_OPENAI_TIMEOUT", false, false, Some("600")),
+            ],
+        )
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        self.features.tools
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self.create_request(system, messages, tools, tool_choice)?;
+
+        let json_response = self.with_retry(|| self.post(&payload)).await?;
+
+        let message = response_to_message(&json_response)?;
+        let usage = json_response
+            .get("usage")
+            .map(get_usage)
+            .unwrap_or_else(|| {
+                tracing::debug!("Failed to get usage data");
+                Usage::default()
+            });
+        let model = get_model(&json_response);
+        emit_debug_trace(&self.model, &payload, &json_response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        if !self.features.list_models {
+            return Ok(None);
+        }
+        let response = self.api_client.response_get(&self.paths.models).await?;
+        let json = handle_response_openai_compat(response).await?;
+        let data = json.get("data").and_then(|v| v.as_array()).ok_or_else(|| {
+            ProviderError::UsageError("Missing data field in JSON response".into())
+        })?;
+        let mut models: Vec<String> = data
+            .iter()
+            .filter_map(|m| m.get("id").and_then(|v| v.as_str()).map(str::to_string))
+            .collect();
+        models.sort();
+        Ok(Some(models))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.features.streaming
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<MessageStream, ProviderError> {
+        if !self.features.streaming {
+            return Err(ProviderError::NotImplemented(
+                "the gateway doesn't stream".to_string(),
+            ));
+        }
+        let mut payload = self.create_request(system, messages, tools, tool_choice)?;
+        payload["stream"] = Value::Bool(true);
+        if self.features.stream_usage {
+            payload["stream_options"] = json!({
+                "include_usage": true,
+            });
+        }
+
+        let response = self
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post(&self.paths.chat, &payload)
+                    .await?;
+                handle_status_openai_compat(response).await
+            })
+            .await?;
+
+        let stream = response.bytes_stream().map_err(io::Error::other);
+
+        let model_config = self.model.clone();
+
+        Ok(Box::pin(try_stream! {
+            let stream_reader = StreamReader::new(stream);
+            let framed = FramedRead::new(stream_reader, LinesCodec::new()).map_err(anyhow::Error::from);
+
+            let message_stream = response_to_streaming_message(framed);
+            pin!(message_stream);
+            while let Some(message) = message_stream.next().await {
+                let (message, usage) = message.map_err(|e| ProviderError::RequestFailed(format!("Stream decode error: {}", e)))?;
+                emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
+                yield (message, usage);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_scheme_parse() {
+        assert_eq!(AuthScheme::parse("Bearer").unwrap(), AuthScheme::Bearer);
+        assert_eq!(AuthScheme::parse("none").unwrap(), AuthScheme::None);
+        assert_eq!(
+            AuthScheme::parse("header:X-Api-Key").unwrap(),
+            AuthScheme::Header("X-Api-Key".to_string())
+        );
+        assert!(AuthScheme::parse("header:").is_err());
+        assert!(AuthScheme::parse("digest").is_err());
+        assert!(AuthScheme::Bearer.auth_method(None).is_err());
+    }
+
+    #[test]
+    fn test_partial_overrides_keep_defaults() {
+        let paths: EndpointPaths =
+            serde_json::from_value(json!({"chat": "openai/v1/chat"})).unwrap();
+        assert_eq!(paths.chat, "openai/v1/chat");
+        assert_eq!(paths.models, "models");
+
+        let features: GatewayFeatures = serde_json::from_value(json!({"tools": false})).unwrap();
+        assert!(!features.tools);
+        assert!(features.streaming);
+    }
+
+    #[test]
+    fn test_strip_tool_fields() {
+        let mut payload = json!({
+            "model": "llama",
+            "messages": [],
+            "tools": [{"type": "function"}],
+            "tool_choice": "auto"
+        });
+        strip_tool_fields(&mut payload);
+        assert_eq!(payload, json!({"model": "llama", "messages": []}));
+        assert_eq!(base_url("http://gpu:8000/v1"), "http://gpu:8000/v1/");
+    }
+}
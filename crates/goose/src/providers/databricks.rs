@@ -22,10 +22,7 @@ use crate::conversation::message::Message;
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::{get_usage, response_to_streaming_message};
-use crate::providers::retry::{
-    RetryConfig, DEFAULT_BACKOFF_MULTIPLIER, DEFAULT_INITIAL_RETRY_INTERVAL_MS,
-    DEFAULT_MAX_RETRIES, DEFAULT_MAX_RETRY_INTERVAL_MS,
-};
+use crate::providers::retry::RetryPolicy;
 use rmcp::model::Tool;
 use serde_json::json;
 use tokio_stream::StreamExt;
@@ -101,7 +98,7 @@ pub struct DatabricksProvider {
     model: ModelConfig,
     image_format: ImageFormat,
     #[serde(skip)]
-    retry_config: RetryConfig,
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(DatabricksProvider);
@@ -123,7 +120,7 @@ impl DatabricksProvider {
         }
 
         let host = host?;
-        let retry_config = Self::load_retry_config(config);
+        let retry_policy = Self::load_retry_policy(config);
 
         let auth = if let Ok(api_key) = config.get_secret("DATABRICKS_TOKEN") {
             DatabricksAuth::token(api_key)
@@ -135,48 +132,20 @@ impl DatabricksProvider {
             AuthMethod::Custom(Box::new(DatabricksAuthProvider { auth: auth.clone() }));
 
         let api_client =
-            ApiClient::with_timeout(host, auth_method, Duration::from_secs(DEFAULT_TIMEOUT_SECS))?;
+            ApiClient::with_timeout(host, auth_method, Duration::from_secs(DEFAULT_TIMEOUT_SECS))?
+                .with_configured_middleware("databricks")?;
 
         Ok(Self {
             api_client,
             auth,
             model,
             image_format: ImageFormat::OpenAi,
-            retry_config,
+            retry_policy,
         })
     }
 
-    fn load_retry_config(config: &crate::config::Config) -> RetryConfig {
-        let max_retries = config
-            .get_param("DATABRICKS_MAX_RETRIES")
-            .ok()
-            .and_then(|v: String| v.parse::<usize>().ok())
-            .unwrap_or(DEFAULT_MAX_RETRIES);
-
-        let initial_interval_ms = config
-            .get_param("DATABRICKS_INITIAL_RETRY_INTERVAL_MS")
-            .ok()
-            .and_then(|v: String| v.parse::<u64>().ok())
-            .unwrap_or(DEFAULT_INITIAL_RETRY_INTERVAL_MS);
-
-        let backoff_multiplier = config
-            .get_param("DATABRICKS_BACKOFF_MULTIPLIER")
-            .ok()
-            .and_then(|v: String| v.parse::<f64>().ok())
-            .unwrap_or(DEFAULT_BACKOFF_MULTIPLIER);
-
-        let max_interval_ms = config
-            .get_param("DATABRICKS_MAX_RETRY_INTERVAL_MS")
-            .ok()
-            .and_then(|v: String| v.parse::<u64>().ok())
-            .unwrap_or(DEFAULT_MAX_RETRY_INTERVAL_MS);
-
-        RetryConfig {
-            max_retries,
-            initial_interval_ms,
-            backoff_multiplier,
-            max_interval_ms,
-        }
+    fn load_retry_policy(config: &crate::config::Config) -> RetryPolicy {
+        RetryPolicy::from_config(config, "DATABRICKS")
     }
 
     pub fn from_params(host: String, api_key: String, model: ModelConfig) -> Result<Self> {
@@ -184,14 +153,15 @@ impl DatabricksProvider {
         let auth_method =
             AuthMethod::Custom(Box::new(DatabricksAuthProvider { auth: auth.clone() }));
 
-        let api_client = ApiClient::with_timeout(host, auth_method, Duration::from_secs(600))?;
+        let api_client = ApiClient::with_timeout(host, auth_method, Duration::from_secs(600))?
+            .with_configured_middleware("databricks")?;
 
         Ok(Self {
             api_client,
             auth,
             model,
             image_format: ImageFormat::OpenAi,
-            retry_config: RetryConfig::default(),
+            retry_policy: RetryPolicy::default(),
         })
     }
 
@@ -229,8 +199,8 @@ impl Provider for DatabricksProvider {
         )
     }
 
-    fn retry_config(&self) -> RetryConfig {
-        self.retry_config.clone()
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
     }
 
     fn get_model_config(&self) -> ModelConfig {
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use serde_json::{json, Value};
+use std::io;
+use tokio::pin;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{FramedRead, LinesCodec};
+use tokio_util::io::StreamReader;
+
+use super::api_client::{ApiClient, AuthMethod};
+use super::base::{
+    ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage, ToolChoice,
+    Usage,
+};
+use super::errors::ProviderError;
+use super::formats::openai::{
+    apply_tool_choice, create_request, get_usage, response_to_message,
+    response_to_streaming_message,
+};
+use super::retry::{ProviderRetry, RetryPolicy};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
+    ImageFormat,
+};
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+pub const DEEPSEEK_API_HOST: &str = "https://api.deepseek.com";
+pub const DEEPSEEK_DEFAULT_MODEL: &str = "deepseek-chat";
+/// Known models with their context window and USD cost per input (cache miss) and output
+/// token; cache hits are billed at a tenth of the input price
+pub const DEEPSEEK_KNOWN_MODELS: &[(&str, usize, f64, f64)] = &[
+    ("deepseek-chat", 131_072, 0.00000028, 0.00000042),
+    ("deepseek-reasoner", 131_072, 0.00000028, 0.00000042),
+];
+
+pub const DEEPSEEK_DOC_URL: &str = "https://api-docs.deepseek.com/quick_start/pricing";
+
+/// DeepSeek's OpenAI-compatible API. deepseek-reasoner returns its chain of thought as
+/// `reasoning_content`, which becomes thinking content on the message and is left out when the
+/// conversation is sent back, as DeepSeek requires.
+#[derive(serde::Serialize)]
+pub struct DeepSeekProvider {
+    #[serde(skip)]
+    api_client: ApiClient,
+    model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
+}
+
+impl_provider_default!(DeepSeekProvider);
+
+impl DeepSeekProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("DEEPSEEK_API_KEY")?;
+        let host: String = config
+            .get_param("DEEPSEEK_HOST")
+            .unwrap_or_else(|_| DEEPSEEK_API_HOST.to_string());
+
+        let auth = AuthMethod::BearerToken(api_key);
+        let api_client = ApiClient::new(host, auth)?.with_configured_middleware("deepseek")?;
+
+        Ok(Self {
+            api_client,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "DEEPSEEK"),
+        })
+    }
+
+    fn chat_payload(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<Value, ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        apply_tool_choice(&mut payload, tool_choice);
+        Ok(payload)
+    }
+
+    async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
+        tracing::debug!("DeepSeek request model: {:?}", self.model.model_name);
+
+        let response = self
+            .api_client
+            .response_post("chat/completions", payload)
+            .await?;
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for DeepSeekProvider {
+    fn metadata() -> ProviderMetadata {
+        let models = DEEPSEEK_KNOWN_MODELS
+            .iter()
+            .map(|(name, limit, input_cost, output_cost)| {
+                ModelInfo::with_cost(*name, *limit, *input_cost, *output_cost)
+            })
+            .collect();
+        ProviderMetadata::with_models(
+            "deepseek",
+            "DeepSeek",
+            "DeepSeek chat and reasoning models",
+            DEEPSEEK_DEFAULT_MODEL,
+            models,
+            DEEPSEEK_DOC_URL,
+            vec![
+                ConfigKey::new("DEEPSEEK_API_KEY", true, true, None),
+                ConfigKey::new("DEEPSEEK_HOST", false, false, Some(DEEPSEEK_API_HOST)),
+            ],
+        )
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self.chat_payload(system, messages, tools, tool_choice)?;
+
+        let response = self.with_retry(|| self.post(&payload)).await?;
+
+        let message = response_to_message(&response)?;
+        let usage = response.get("usage").map(get_usage).unwrap_or_else(|| {
+            tracing::debug!("Failed to get usage data");
+            Usage::default()
+        });
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        let response = self.api_client.response_get("models").await?;
+        let json = handle_response_openai_compat(response).await?;
+        let data = json.get("data").and_then(|v| v.as_array()).ok_or_else(|| {
+            ProviderError::UsageError("Missing data field in JSON response".into())
+        })?;
+        let mut models: Vec<String> = data
+            .iter()
+            .filter_map(|m| m.get("id").and_then(|v| v.as_str()).map(str::to_string))
+            .collect();
+        models.sort();
+        Ok(Some(models))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<MessageStream, ProviderError> {
+        let mut payload = self.chat_payload(system, messages, tools, tool_choice)?;
+        payload["stream"] = Value::Bool(true);
+        payload["stream_options"] = json!({
+            "include_usage": true,
+        });
+
+        let response = self
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post("chat/completions", &payload)
+                    .await?;
+                handle_status_openai_compat(response).await
+            })
+            .await?;
+
+        let stream = response.bytes_stream().map_err(io::Error::other);
+
+        let model_config = self.model.clone();
+
+        Ok(Box::pin(try_stream! {
+            let stream_reader = StreamReader::new(stream);
+            let framed = FramedRead::new(stream_reader, LinesCodec::new()).map_err(anyhow::Error::from);
+
+            let message_stream = response_to_streaming_message(framed);
+            pin!(message_stream);
+            while let Some(message) = message_stream.next().await {
+                let (message, usage) = message.map_err(|e| ProviderError::RequestFailed(format!("Stream decode error: {}", e)))?;
+                emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
+                yield (message, usage);
+            }
+        }))
+    }
+}
@@ -0,0 +1,406 @@
+//! A single streaming vocabulary for every provider.
+//!
+//! Providers stream in their own shapes: SSE events, chunked JSON, partial messages. A
+//! [`DeltaStream`] normalizes them into [`CompletionDelta`]s, so the agent and the UIs can
+//! render a response as it arrives without knowing which backend produced it.
+//! [`messages_from_deltas`] turns them into the partial messages the agent loop yields, and a
+//! [`DeltaAccumulator`] turns them back into the complete message.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+
+use async_stream::try_stream;
+use futures::{Stream, StreamExt};
+use mcp_core::{ToolCall, ToolError};
+use serde_json::Value;
+
+use super::base::{MessageStream, ProviderUsage};
+use super::errors::ProviderError;
+use crate::conversation::message::{Message, MessageContent, StopReason, TokenLogprob};
+
+/// One increment of a streamed completion
+#[derive(Debug, Clone)]
+pub enum CompletionDelta {
+    /// The provider's id for the message the following deltas belong to
+    Id(String),
+    /// More of the response text
+    Text(String),
+    /// More of the model's reasoning
+    Thinking(String),
+    /// Part of a tool call. The first fragment for an id carries the tool name; the argument
+    /// fragments of one id concatenate to the JSON arguments.
+    ToolCall {
+        id: String,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// Log probabilities of the text that follows
+    Logprobs(Vec<TokenLogprob>),
+    /// Content that only ever arrives whole, like images or signed reasoning
+    Content(MessageContent),
+    /// Why the model stopped
+    Stop(StopReason),
+    /// Token usage, usually once at the end
+    Usage(ProviderUsage),
+}
+
+pub type DeltaStream = Pin<Box<dyn Stream<Item = Result<CompletionDelta, ProviderError>> + Send>>;
+
+/// The deltas that make up a message, in order
+pub fn message_to_deltas(message: Message) -> Vec<CompletionDelta> {
+    let mut deltas = Vec::new();
+    if let Some(id) = message.id {
+        deltas.push(CompletionDelta::Id(id));
+    }
+    if !message.logprobs.is_empty() {
+        deltas.push(CompletionDelta::Logprobs(message.logprobs));
+    }
+    for content in message.content {
+        match content {
+            MessageContent::Text(text) => deltas.push(CompletionDelta::Text(text.text)),
+            MessageContent::Thinking(thinking) if thinking.signature.is_empty() => {
+                deltas.push(CompletionDelta::Thinking(thinking.thinking))
+            }
+            MessageContent::ToolRequest(request) => match request.tool_call {
+                Ok(call) => deltas.push(CompletionDelta::ToolCall {
+                    id: request.id,
+                    name: Some(call.name),
+                    arguments: call.arguments.to_string(),
+                }),
+                Err(e) => deltas.push(CompletionDelta::Content(MessageContent::tool_request(
+                    request.id,
+                    Err(e),
+                ))),
+            },
+            other => deltas.push(CompletionDelta::Content(other)),
+        }
+    }
+    if let Some(stop_reason) = message.stop_reason {
+        deltas.push(CompletionDelta::Stop(stop_reason));
+    }
+    deltas
+}
+
+/// Adapt a stream of partial messages into deltas
+pub fn deltas_from_messages(stream: MessageStream) -> DeltaStream {
+    Box::pin(stream.flat_map(|item| {
+        let deltas: Vec<Result<CompletionDelta, ProviderError>> = match item {
+            Ok((message, usage)) => message
+                .map(message_to_deltas)
+                .unwrap_or_default()
+                .into_iter()
+                .chain(usage.map(CompletionDelta::Usage))
+                .map(Ok)
+                .collect(),
+            Err(e) => vec![Err(e)],
+        };
+        futures::stream::iter(deltas)
+    }))
+}
+
+/// Adapt deltas into the partial messages the agent loop yields: text and reasoning as they
+/// arrive, tool calls once the response is complete. The stop reason goes on that last message,
+/// and usage is passed on as soon as it is reported.
+pub fn messages_from_deltas(mut deltas: DeltaStream) -> MessageStream {
+    Box::pin(try_stream! {
+        let mut id = None;
+        let mut logprobs = Vec::new();
+        let mut tool_calls = DeltaAccumulator::new();
+        let mut stop_reason = None;
+        while let Some(delta) = deltas.next().await {
+            let content = match delta? {
+                CompletionDelta::Id(message_id) => {
+                    id = Some(message_id);
+                    continue;
+                }
+                CompletionDelta::Logprobs(more) => {
+                    logprobs.extend(more);
+                    continue;
+                }
+                CompletionDelta::Text(text) => MessageContent::text(text),
+                CompletionDelta::Thinking(thinking) => MessageContent::thinking(thinking, ""),
+                CompletionDelta::Content(content) => content,
+                delta @ CompletionDelta::ToolCall { .. } => {
+                    tool_calls.push(delta);
+                    continue;
+                }
+                CompletionDelta::Stop(reason) => {
+                    stop_reason = Some(reason);
+                    continue;
+                }
+                CompletionDelta::Usage(usage) => {
+                    yield (None, Some(usage));
+                    continue;
+                }
+            };
+            let mut message = Message::assistant()
+                .with_content(content)
+                .with_logprobs(std::mem::take(&mut logprobs));
+            message.id = id.clone();
+            yield (Some(message), None);
+        }
+
+        let (mut message, _) = tool_calls.finish();
+        if !message.content.is_empty() || stop_reason.is_some() {
+            message.id = id;
+            message.stop_reason = stop_reason;
+            message.logprobs = logprobs;
+            yield (Some(message), None);
+        }
+    })
+}
+
+/// Add content to a message's, extending the last part when both are text or unsigned reasoning
+pub fn merge_content(content: &mut Vec<MessageContent>, next: MessageContent) {
+    match (content.last_mut(), next) {
+        (Some(MessageContent::Text(last)), MessageContent::Text(text)) => {
+            last.text.push_str(&text.text)
+        }
+        (Some(MessageContent::Thinking(last)), MessageContent::Thinking(thinking))
+            if last.signature.is_empty() && thinking.signature.is_empty() =>
+        {
+            last.thinking.push_str(&thinking.thinking)
+        }
+        (_, next) => content.push(next),
+    }
+}
+
+#[derive(Default)]
+struct PendingToolCall {
+    name: Option<String>,
+    arguments: String,
+}
+
+/// Rebuilds the complete message from a stream of deltas
+#[derive(Default)]
+pub struct DeltaAccumulator {
+    id: Option<String>,
+    logprobs: Vec<TokenLogprob>,
+    content: Vec<MessageContent>,
+    tool_calls: HashMap<String, PendingToolCall>,
+    stop_reason: Option<StopReason>,
+    usage: Option<ProviderUsage>,
+}
+
+impl DeltaAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, delta: CompletionDelta) {
+        match delta {
+            CompletionDelta::Id(id) => self.id = Some(id),
+            CompletionDelta::Logprobs(logprobs) => self.logprobs.extend(logprobs),
+            CompletionDelta::Text(text) => {
+                merge_content(&mut self.content, MessageContent::text(text))
+            }
+            CompletionDelta::Thinking(thinking) => {
+                merge_content(&mut self.content, MessageContent::thinking(thinking, ""))
+            }
+            CompletionDelta::ToolCall {
+                id,
+                name,
+                arguments,
+            } => {
+                if !self.tool_calls.contains_key(&id) {
+                    // Hold the call's place among the other content until it is complete
+                    self.content.push(MessageContent::tool_request(
+                        id.clone(),
+                        Ok(ToolCall::new("", Value::Null)),
+                    ));
+                }
+                let pending = self.tool_calls.entry(id).or_default();
+                if name.is_some() {
+                    pending.name = name;
+                }
+                pending.arguments.push_str(&arguments);
+            }
+            CompletionDelta::Content(content) => self.content.push(content),
+            CompletionDelta::Stop(stop_reason) => self.stop_reason = Some(stop_reason),
+            CompletionDelta::Usage(usage) => self.usage = Some(usage),
+        }
+    }
+
+    /// The message the deltas add up to, with the last usage reported
+    pub fn finish(mut self) -> (Message, Option<ProviderUsage>) {
+        for content in &mut self.content {
+            let MessageContent::ToolRequest(request) = content else {
+                continue;
+            };
+            let Some(pending) = self.tool_calls.remove(&request.id) else {
+                continue;
+            };
+            request.tool_call = match pending.name {
+                Some(name) => parse_arguments(&pending.arguments)
+                    .map(|arguments| ToolCall::new(name, arguments)),
+                None => Err(ToolError::InvalidParameters(format!(
+                    "Tool call {} streamed without a name",
+                    request.id
+                ))),
+            };
+        }
+
+        let mut message = Message::assistant()
+            .with_stop_reason(self.stop_reason)
+            .with_logprobs(self.logprobs);
+        message.id = self.id;
+        message.content = self.content;
+        (message, self.usage)
+    }
+}
+
+fn parse_arguments(arguments: &str) -> Result<Value, ToolError> {
+    if arguments.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+    serde_json::from_str(arguments).map_err(|e| {
+        ToolError::InvalidParameters(format!(
+            "Could not parse tool arguments {}: {}",
+            arguments, e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{stream_from_single_message, Usage};
+    use serde_json::json;
+
+    #[test]
+    fn test_fragments_accumulate_into_a_message() {
+        let mut accumulator = DeltaAccumulator::new();
+        for delta in [
+            CompletionDelta::Text("Let me ".to_string()),
+            CompletionDelta::Text("check.".to_string()),
+            CompletionDelta::ToolCall {
+                id: "call_1".to_string(),
+                name: Some("developer__shell".to_string()),
+                arguments: "{\"comm".to_string(),
+            },
+            CompletionDelta::ToolCall {
+                id: "call_1".to_string(),
+                name: None,
+                arguments: "and\": \"ls\"}".to_string(),
+            },
+            CompletionDelta::Stop(StopReason::ToolUse),
+            CompletionDelta::Usage(ProviderUsage::new(
+                "gpt-4o".to_string(),
+                Usage::new(Some(10), Some(5), Some(15)),
+            )),
+        ] {
+            accumulator.push(delta);
+        }
+
+        let (message, usage) = accumulator.finish();
+        assert_eq!(message.as_concat_text(), "Let me check.");
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(usage.unwrap().usage.total_tokens, Some(15));
+        let MessageContent::ToolRequest(request) = &message.content[1] else {
+            panic!("expected a tool request");
+        };
+        let call = request.tool_call.as_ref().unwrap();
+        assert_eq!(call.name, "developer__shell");
+        assert_eq!(call.arguments, json!({"command": "ls"}));
+    }
+
+    #[test]
+    fn test_broken_tool_calls_become_errors() {
+        let mut accumulator = DeltaAccumulator::new();
+        accumulator.push(CompletionDelta::ToolCall {
+            id: "a".to_string(),
+            name: Some("shell".to_string()),
+            arguments: "{\"unterminated".to_string(),
+        });
+        accumulator.push(CompletionDelta::ToolCall {
+            id: "b".to_string(),
+            name: None,
+            arguments: "{}".to_string(),
+        });
+        let (message, _) = accumulator.finish();
+        assert!(message.content.iter().all(|content| matches!(
+            content,
+            MessageContent::ToolRequest(request) if request.tool_call.is_err()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_messages_round_trip_through_deltas() {
+        let original = Message::assistant()
+            .with_text("Done")
+            .with_tool_request("1", Ok(ToolCall::new("read", json!({"path": "a.rs"}))))
+            .with_stop_reason(Some(StopReason::Stop));
+        let usage = ProviderUsage::new("m".to_string(), Usage::default());
+
+        let mut stream = deltas_from_messages(stream_from_single_message(original.clone(), usage));
+        let mut accumulator = DeltaAccumulator::new();
+        while let Some(delta) = stream.next().await {
+            accumulator.push(delta.unwrap());
+        }
+        let (message, usage) = accumulator.finish();
+        assert_eq!(message.content, original.content);
+        assert_eq!(message.stop_reason, original.stop_reason);
+        assert_eq!(usage.unwrap().model, "m");
+    }
+
+    #[tokio::test]
+    async fn test_text_streams_and_tool_calls_arrive_complete() {
+        let deltas: DeltaStream = Box::pin(futures::stream::iter(vec![
+            Ok(CompletionDelta::Id("msg_1".to_string())),
+            Ok(CompletionDelta::Text("Let me ".to_string())),
+            Ok(CompletionDelta::ToolCall {
+                id: "call_1".to_string(),
+                name: Some("developer__shell".to_string()),
+                arguments: "{\"command\"".to_string(),
+            }),
+            Ok(CompletionDelta::Text("check.".to_string())),
+            Ok(CompletionDelta::ToolCall {
+                id: "call_1".to_string(),
+                name: None,
+                arguments: ": \"ls\"}".to_string(),
+            }),
+            Ok(CompletionDelta::Stop(StopReason::ToolUse)),
+            Ok(CompletionDelta::Usage(ProviderUsage::new(
+                "gpt-4o".to_string(),
+                Usage::new(Some(10), Some(5), Some(15)),
+            ))),
+        ]));
+
+        let items: Vec<_> = messages_from_deltas(deltas)
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(items.len(), 4);
+        let texts: Vec<String> = items[..2]
+            .iter()
+            .map(|(message, _)| message.as_ref().unwrap().as_concat_text())
+            .collect();
+        assert_eq!(texts, ["Let me ", "check."]);
+        assert_eq!(items[2].1.as_ref().unwrap().usage.total_tokens, Some(15));
+
+        let (Some(last), None) = &items[3] else {
+            panic!("expected the tool calls last");
+        };
+        assert_eq!(last.id.as_deref(), Some("msg_1"));
+        assert_eq!(last.stop_reason, Some(StopReason::ToolUse));
+        let call = last.content[0]
+            .as_tool_request()
+            .unwrap()
+            .tool_call
+            .as_ref()
+            .unwrap();
+        assert_eq!(call.arguments, json!({"command": "ls"}));
+    }
+
+    #[tokio::test]
+    async fn test_stream_errors_are_passed_on() {
+        let deltas: DeltaStream = Box::pin(futures::stream::iter(vec![
+            Ok(CompletionDelta::Text("Partial".to_string())),
+            Err(ProviderError::RequestFailed("connection reset".to_string())),
+        ]));
+
+        let items: Vec<_> = messages_from_deltas(deltas).collect().await;
+        assert!(items[0].is_ok());
+        assert!(matches!(items[1], Err(ProviderError::RequestFailed(_))));
+    }
+}
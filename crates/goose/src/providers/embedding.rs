@@ -1,6 +1,15 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use etcetera::{choose_app_strategy, AppStrategy};
+use futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::base::Provider;
+use crate::config::Config;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingRequest {
@@ -22,3 +31,294 @@ pub struct EmbeddingData {
 pub trait EmbeddingCapable {
     async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
 }
+
+/// Config key for the number of texts sent in one embedding request
+pub const EMBEDDING_BATCH_SIZE_KEY: &str = "GOOSE_EMBEDDING_BATCH_SIZE";
+/// Config key for the number of embedding requests in flight at once
+pub const EMBEDDING_CONCURRENCY_KEY: &str = "GOOSE_EMBEDDING_CONCURRENCY";
+
+const DEFAULT_BATCH_SIZE: usize = 64;
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Embeddings stored on disk, one file per text, keyed by a hash of the model and the text.
+///
+/// Unchanged content hashes to the same key, so indexing it again reads every vector from
+/// here and never reaches the provider.
+#[derive(Debug, Clone)]
+pub struct EmbeddingCache {
+    dir: PathBuf,
+}
+
+impl EmbeddingCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The cache under goose's cache directory
+    pub fn default_location() -> Result<Self> {
+        let dir =
+            choose_app_strategy(crate::config::APP_STRATEGY.clone())?.in_cache_dir("embeddings");
+        Ok(Self::new(dir))
+    }
+
+    pub fn key(model: &str, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.json", key))
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Vec<f32>> {
+        let contents = tokio::fs::read(self.path(key)).await.ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    pub async fn put(&self, key: &str, embedding: &[f32]) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_vec(embedding)?).await?;
+        Ok(())
+    }
+}
+
+/// Embeds texts through a provider in batches, with a bounded number of requests in flight,
+/// and only for texts the cache doesn't already hold.
+pub struct EmbeddingPipeline {
+    provider: Arc<dyn Provider>,
+    cache: Option<EmbeddingCache>,
+    batch_size: usize,
+    concurrency: usize,
+}
+
+impl EmbeddingPipeline {
+    pub fn new(provider: Arc<dyn Provider>) -> Self {
+        let config = Config::global();
+        Self {
+            provider,
+            cache: None,
+            batch_size: config
+                .get_param::<usize>(EMBEDDING_BATCH_SIZE_KEY)
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            concurrency: config
+                .get_param::<usize>(EMBEDDING_CONCURRENCY_KEY)
+                .unwrap_or(DEFAULT_CONCURRENCY),
+        }
+    }
+
+    pub fn with_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// One embedding per text, in the order given
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = self.provider.get_model_config().model_name;
+        let keys: Vec<String> = texts
+            .iter()
+            .map(|text| EmbeddingCache::key(&model, text))
+            .collect();
+
+        let mut found: HashMap<String, Vec<f32>> = HashMap::new();
+        let mut missing: Vec<(String, String)> = Vec::new();
+        let mut seen = HashSet::new();
+        for (key, text) in keys.iter().zip(texts) {
+            if !seen.insert(key) {
+                continue;
+            }
+            match self.cached(key).await {
+                Some(embedding) => {
+                    found.insert(key.clone(), embedding);
+                }
+                None => missing.push((key.clone(), text.clone())),
+            }
+        }
+
+        if !missing.is_empty() {
+            tracing::debug!(
+                "Embedding {} of {} texts with {} ({} cached)",
+                missing.len(),
+                texts.len(),
+                model,
+                found.len()
+            );
+            let batches: Vec<Vec<(String, String)>> = missing
+                .chunks(self.batch_size.max(1))
+                .map(|chunk| chunk.to_vec())
+                .collect();
+            let mut results = stream::iter(batches)
+                .map(|batch| self.embed_batch(batch))
+                .buffer_unordered(self.concurrency.max(1));
+            while let Some(batch) = results.next().await {
+                for (key, embedding) in batch? {
+                    if let Some(cache) = &self.cache {
+                        if let Err(e) = cache.put(&key, &embedding).await {
+                            tracing::warn!("Failed to cache embedding: {}", e);
+                        }
+                    }
+                    found.insert(key, embedding);
+                }
+            }
+        }
+
+        keys.iter()
+            .map(|key| {
+                found
+                    .get(key)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No embedding returned for a text"))
+            })
+            .collect()
+    }
+
+    async fn cached(&self, key: &str) -> Option<Vec<f32>> {
+        match &self.cache {
+            Some(cache) => cache.get(key).await,
+            None => None,
+        }
+    }
+
+    async fn embed_batch(&self, batch: Vec<(String, String)>) -> Result<Vec<(String, Vec<f32>)>> {
+        let (keys, texts): (Vec<String>, Vec<String>) = batch.into_iter().unzip();
+        let embeddings = self.provider.create_embeddings(texts).await?;
+        if embeddings.len() != keys.len() {
+            return Err(anyhow!(
+                "Expected {} embeddings, got {}",
+                keys.len(),
+                embeddings.len()
+            ));
+        }
+        Ok(keys.into_iter().zip(embeddings).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::Message;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage};
+    use crate::providers::errors::ProviderError;
+    use rmcp::model::Tool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockEmbedder {
+        model_config: ModelConfig,
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for MockEmbedder {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            unimplemented!()
+        }
+
+        fn supports_embeddings(&self) -> bool {
+            true
+        }
+
+        async fn create_embeddings(
+            &self,
+            texts: Vec<String>,
+        ) -> Result<Vec<Vec<f32>>, ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+        }
+    }
+
+    fn embedder() -> Arc<MockEmbedder> {
+        Arc::new(MockEmbedder {
+            model_config: ModelConfig::new_or_fail("text-embedding-3-small"),
+            calls: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        })
+    }
+
+    fn texts(n: usize) -> Vec<String> {
+        (0..n).map(|i| "x".repeat(i + 1)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_batches_with_bounded_concurrency() {
+        let provider = embedder();
+        let pipeline = EmbeddingPipeline::new(provider.clone())
+            .with_batch_size(3)
+            .with_concurrency(2);
+
+        let embeddings = pipeline.embed(&texts(10)).await.unwrap();
+
+        assert_eq!(embeddings.len(), 10);
+        assert_eq!(embeddings[4], vec![5.0]);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+        assert!(provider.max_in_flight.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_texts_are_not_embedded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = embedder();
+        let pipeline = EmbeddingPipeline::new(provider.clone())
+            .with_cache(EmbeddingCache::new(dir.path().to_path_buf()))
+            .with_batch_size(4);
+
+        let first = pipeline.embed(&texts(10)).await.unwrap();
+        let calls = provider.calls.load(Ordering::SeqCst);
+        assert_eq!(calls, 3);
+
+        let second = pipeline.embed(&texts(10)).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), calls);
+
+        // Only the new text is embedded
+        pipeline.embed(&texts(11)).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), calls + 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_texts_are_embedded_once() {
+        let provider = embedder();
+        let pipeline = EmbeddingPipeline::new(provider.clone()).with_batch_size(1);
+        let texts = vec!["a".to_string(), "b".to_string(), "a".to_string()];
+
+        let embeddings = pipeline.embed(&texts).await.unwrap();
+
+        assert_eq!(embeddings.len(), 3);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+}
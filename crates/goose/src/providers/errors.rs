@@ -1,4 +1,5 @@
 use reqwest::StatusCode;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,8 +10,12 @@ pub enum ProviderError {
     #[error("Context length exceeded: {0}")]
     ContextLengthExceeded(String),
 
-    #[error("Rate limit exceeded: {0}")]
-    RateLimitExceeded(String),
+    #[error("Rate limit exceeded: {details}")]
+    RateLimitExceeded {
+        details: String,
+        /// How long the server asked us to wait, from its Retry-After header
+        retry_delay: Option<Duration>,
+    },
 
     #[error("Server error: {0}")]
     ServerError(String),
@@ -28,6 +33,60 @@ pub enum ProviderError {
     NotImplemented(String),
 }
 
+impl ProviderError {
+    pub fn rate_limited(details: impl Into<String>) -> Self {
+        ProviderError::RateLimitExceeded {
+            details: details.into(),
+            retry_delay: None,
+        }
+    }
+
+    /// Attach the wait the server asked for to a rate limit error
+    pub fn with_retry_delay(self, delay: Option<Duration>) -> Self {
+        match self {
+            ProviderError::RateLimitExceeded {
+                details,
+                retry_delay,
+            } => ProviderError::RateLimitExceeded {
+                details,
+                retry_delay: delay.or(retry_delay),
+            },
+            other => other,
+        }
+    }
+
+    pub fn retry_delay(&self) -> Option<Duration> {
+        match self {
+            ProviderError::RateLimitExceeded { retry_delay, .. } => *retry_delay,
+            _ => None,
+        }
+    }
+
+    /// The HTTP status behind the error, where there is one. Server errors without a status in
+    /// their message count as 500.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ProviderError::RateLimitExceeded { .. } => Some(429),
+            ProviderError::ServerError(message) => Some(status_in(message).unwrap_or(500)),
+            ProviderError::RequestFailed(message) => status_in(message),
+            _ => None,
+        }
+    }
+}
+
+/// The status code in messages like "Request failed with status: 502 Bad Gateway"
+fn status_in(message: &str) -> Option<u16> {
+    let lower = message.to_lowercase();
+    let start = lower.find("status")? + "status".len();
+    let rest = lower[start..].trim_start_matches([':', ' ', '(']);
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() == 3 {
+        digits.parse().ok()
+    } else {
+        None
+    }
+}
+
 impl From<anyhow::Error> for ProviderError {
     fn from(error: anyhow::Error) -> Self {
         if let Some(reqwest_err) = error.downcast_ref::<reqwest::Error>() {
@@ -3,16 +3,24 @@ use std::sync::Arc;
 use super::{
     anthropic::AnthropicProvider,
     azure::AzureProvider,
+    balanced::{BalancedConfig, BalancedProvider},
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
     claude_code::ClaudeCodeProvider,
+    custom_openai::CustomOpenAiProvider,
     databricks::DatabricksProvider,
+    deepseek::DeepSeekProvider,
+    fallback::FallbackProvider,
     gcpvertexai::GcpVertexAIProvider,
     gemini_cli::GeminiCliProvider,
     google::GoogleProvider,
     groq::GroqProvider,
-    lead_worker::LeadWorkerProvider,
+    huggingface::HuggingFaceProvider,
+    hybrid::{DataClassifier, DataRoutingConfig, HybridProvider},
+    lead_worker::{EscalationPolicy, LeadWorkerProvider},
     litellm::LiteLLMProvider,
+    mistral::MistralProvider,
+    model_registry::ModelRegistry,
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
@@ -32,26 +40,25 @@ use rmcp::model::Tool;
 fn default_lead_turns() -> usize {
     3
 }
-fn default_failure_threshold() -> usize {
-    2
-}
-fn default_fallback_turns() -> usize {
-    2
-}
 
 pub fn providers() -> Vec<ProviderMetadata> {
-    vec![
+    #[allow(unused_mut)]
+    let mut providers = vec![
         AnthropicProvider::metadata(),
         AzureProvider::metadata(),
         BedrockProvider::metadata(),
         ClaudeCodeProvider::metadata(),
+        CustomOpenAiProvider::metadata(),
         DatabricksProvider::metadata(),
+        DeepSeekProvider::metadata(),
         GcpVertexAIProvider::metadata(),
         GeminiCliProvider::metadata(),
         // GithubCopilotProvider::metadata(),
         GoogleProvider::metadata(),
         GroqProvider::metadata(),
+        HuggingFaceProvider::metadata(),
         LiteLLMProvider::metadata(),
+        MistralProvider::metadata(),
         OllamaProvider::metadata(),
         OpenAiProvider::metadata(),
         OpenRouterProvider::metadata(),
@@ -59,19 +66,85 @@ pub fn providers() -> Vec<ProviderMetadata> {
         VeniceProvider::metadata(),
         SnowflakeProvider::metadata(),
         XaiProvider::metadata(),
-    ]
+    ];
+    #[cfg(feature = "llama-cpp")]
+    providers.push(super::llamacpp::LlamaCppProvider::metadata());
+    providers
 }
 
 pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     let config = crate::config::Config::global();
 
+    // Check for a comma separated fallback chain, unless the caller already supplied one
+    let model = match config.get_param::<String>("GOOSE_FALLBACK_MODELS") {
+        Ok(fallback_models) if model.fallbacks.is_empty() => {
+            let fallbacks = fallback_models
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(ModelConfig::new)
+                .collect::<Result<Vec<_>, _>>()?;
+            model.with_fallbacks(fallbacks)
+        }
+        _ => model,
+    };
+
     // Check for lead model environment variables
-    if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
+    let provider = if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
         tracing::info!("Creating lead/worker provider from environment variables");
 
-        return create_lead_worker_from_env(name, &model, &lead_model_name);
+        create_lead_worker_from_env(name, &model, &lead_model_name)?
+    } else {
+        create_provider(name, model)?
+    };
+    refresh_model_registry(name, &provider);
+
+    // Keep requests that touch sensitive data on a local model
+    match DataRoutingConfig::from_config() {
+        Some(routing) => create_hybrid(name, provider, &routing),
+        None => Ok(provider),
     }
-    create_provider(name, model)
+}
+
+/// Fetch the provider's model metadata in the background, so reported context windows apply to
+/// providers created from then on. At most one fetch per provider is spawned per cache TTL.
+fn refresh_model_registry(name: &str, provider: &Arc<dyn Provider>) {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    let registry = ModelRegistry::global();
+    if !registry.begin_refresh(name, &provider.get_model_config().model_name) {
+        return;
+    }
+    let name = name.to_string();
+    let provider = provider.clone();
+    handle.spawn(async move {
+        if let Err(e) = registry.fetch_from_provider(&name, provider.as_ref()).await {
+            tracing::debug!("Failed to refresh model registry: {}", e);
+        }
+    });
+}
+
+/// Wrap the cloud provider so requests carrying sensitive data go to the local model instead
+fn create_hybrid(
+    name: &str,
+    cloud: Arc<dyn Provider>,
+    routing: &DataRoutingConfig,
+) -> Result<Arc<dyn Provider>> {
+    tracing::info!(
+        "Routing sensitive requests to {}/{}",
+        routing.local_provider,
+        routing.local_model
+    );
+    let classifier = DataClassifier::new(routing)?;
+    let local = create_provider(
+        &routing.local_provider,
+        ModelConfig::new(&routing.local_model)?,
+    )?;
+    Ok(Arc::new(
+        HybridProvider::new(cloud, local, classifier)
+            .with_provider_names(name, &routing.local_provider),
+    ))
 }
 
 /// Create a lead/worker provider from environment variables
@@ -91,12 +164,7 @@ fn create_lead_worker_from_env(
     let lead_turns = config
         .get_param::<usize>("GOOSE_LEAD_TURNS")
         .unwrap_or(default_lead_turns());
-    let failure_threshold = config
-        .get_param::<usize>("GOOSE_LEAD_FAILURE_THRESHOLD")
-        .unwrap_or(default_failure_threshold());
-    let fallback_turns = config
-        .get_param::<usize>("GOOSE_LEAD_FALLBACK_TURNS")
-        .unwrap_or(default_fallback_turns());
+    let policy = EscalationPolicy::from_config(config);
 
     let lead_model_config = ModelConfig::new_with_context_env(
         lead_model_name.to_string(),
@@ -108,11 +176,19 @@ fn create_lead_worker_from_env(
     let worker_model_config = {
         // Start with a clone of the original model to preserve user-specified settings
         let mut worker_config = ModelConfig::new_or_fail(default_model.model_name.as_str())
-            .with_context_limit(default_model.context_limit)
             .with_temperature(default_model.temperature)
             .with_max_tokens(default_model.max_tokens)
+            .with_top_p(default_model.top_p)
+            .with_stop(default_model.stop.clone())
+            .with_frequency_penalty(default_model.frequency_penalty)
+            .with_presence_penalty(default_model.presence_penalty)
+            .with_seed(default_model.seed)
             .with_toolshim(default_model.toolshim)
-            .with_toolshim_model(default_model.toolshim_model.clone());
+            .with_toolshim_model(default_model.toolshim_model.clone())
+            .with_toolshim_provider(default_model.toolshim_provider.clone());
+        if default_model.context_limit_explicit {
+            worker_config = worker_config.with_context_limit(default_model.context_limit);
+        }
 
         // Apply environment variable overrides with proper precedence
         let global_config = crate::config::Config::global();
@@ -137,29 +213,80 @@ fn create_lead_worker_from_env(
     let worker_provider = create_provider(default_provider_name, worker_model_config)?;
 
     // Create the lead/worker provider with configured settings
-    Ok(Arc::new(LeadWorkerProvider::new_with_settings(
-        lead_provider,
-        worker_provider,
-        lead_turns,
-        failure_threshold,
-        fallback_turns,
-    )))
+    Ok(Arc::new(
+        LeadWorkerProvider::new_with_policy(lead_provider, worker_provider, lead_turns, policy)
+            .with_provider_names(&lead_provider_name, default_provider_name),
+    ))
+}
+
+/// Use the context window the provider reported for the model, narrowed by the bounds this
+/// endpoint has shown in earlier sessions, unless the user set a limit
+fn apply_registry_context_limit(
+    registry: &ModelRegistry,
+    name: &str,
+    model: ModelConfig,
+) -> ModelConfig {
+    if model.context_limit_explicit {
+        return model;
+    }
+
+    // Context windows reported by the provider API are more accurate than the built-in patterns
+    let model = match registry.context_limit(name, &model.model_name) {
+        Some(limit) => model.with_reported_context_limit(limit),
+        None => model,
+    };
+    match registry.observed_context_limit(name, &model.model_name) {
+        Some(observed) => {
+            let limit = observed.effective_limit(model.context_limit());
+            if limit != model.context_limit() {
+                tracing::debug!(
+                    "Using learned context limit {} for {}/{}",
+                    limit,
+                    name,
+                    model.model_name
+                );
+            }
+            model.with_reported_context_limit(limit)
+        }
+        None => model,
+    }
 }
 
 fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    if name == "balanced" {
+        // The members name their own models
+        return create_balanced_provider();
+    }
+    if !model.fallbacks.is_empty() {
+        return create_fallback_provider(name, model);
+    }
+    let model = apply_registry_context_limit(ModelRegistry::global(), name, model);
+
     // We use Arc instead of Box to be able to clone for multiple async tasks
     match name {
         "anthropic" => Ok(Arc::new(AnthropicProvider::from_env(model)?)),
-        "aws_bedrock" => Ok(Arc::new(BedrockProvider::from_env(model)?)),
+        "aws_bedrock" | "bedrock" => Ok(Arc::new(BedrockProvider::from_env(model)?)),
         "azure_openai" => Ok(Arc::new(AzureProvider::from_env(model)?)),
         "claude-code" => Ok(Arc::new(ClaudeCodeProvider::from_env(model)?)),
+        "custom_openai" => Ok(Arc::new(CustomOpenAiProvider::from_env(model)?)),
         "databricks" => Ok(Arc::new(DatabricksProvider::from_env(model)?)),
-        "gcp_vertex_ai" => Ok(Arc::new(GcpVertexAIProvider::from_env(model)?)),
+        "deepseek" => Ok(Arc::new(DeepSeekProvider::from_env(model)?)),
+        "gcp_vertex_ai" | "vertex_ai" => Ok(Arc::new(GcpVertexAIProvider::from_env(model)?)),
         "gemini-cli" => Ok(Arc::new(GeminiCliProvider::from_env(model)?)),
         // "github_copilot" => Ok(Arc::new(GithubCopilotProvider::from_env(model)?)),
         "google" => Ok(Arc::new(GoogleProvider::from_env(model)?)),
         "groq" => Ok(Arc::new(GroqProvider::from_env(model)?)),
+        "huggingface" => Ok(Arc::new(HuggingFaceProvider::from_env(model)?)),
         "litellm" => Ok(Arc::new(LiteLLMProvider::from_env(model)?)),
+        #[cfg(feature = "llama-cpp")]
+        "llama_cpp" => Ok(Arc::new(super::llamacpp::LlamaCppProvider::from_env(
+            model,
+        )?)),
+        #[cfg(feature = "local-embeddings")]
+        "local_embedding" => Ok(Arc::new(
+            super::local_embedding::LocalEmbeddingProvider::from_env(model)?,
+        )),
+        "mistral" => Ok(Arc::new(MistralProvider::from_env(model)?)),
         "ollama" => Ok(Arc::new(OllamaProvider::from_env(model)?)),
         "openai" => Ok(Arc::new(OpenAiProvider::from_env(model)?)),
         "openrouter" => Ok(Arc::new(OpenRouterProvider::from_env(model)?)),
@@ -171,6 +298,32 @@ fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>>
     }
 }
 
+/// Create a provider that walks the model's fallback chain, all served by the same provider
+fn create_fallback_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let mut providers = Vec::with_capacity(model.fallbacks.len() + 1);
+    providers.push(create_provider(
+        name,
+        model.clone().with_fallbacks(Vec::new()),
+    )?);
+    for fallback in &model.fallbacks {
+        providers.push(create_provider(name, fallback.clone())?);
+    }
+
+    Ok(Arc::new(FallbackProvider::new(model, providers)))
+}
+
+/// Create a provider that spreads requests across the members in GOOSE_BALANCED_PROVIDERS
+fn create_balanced_provider() -> Result<Arc<dyn Provider>> {
+    let config = BalancedConfig::from_config()?;
+    let providers = config
+        .members
+        .iter()
+        .map(|member| create_provider(&member.provider, ModelConfig::new(&member.model)?))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Arc::new(BalancedProvider::new(&config, providers)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +331,7 @@ mod tests {
     use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
     use chrono::Utc;
     use rmcp::model::{AnnotateAble, RawTextContent, Role};
+    use serial_test::serial;
     use std::env;
 
     #[allow(dead_code)]
@@ -231,6 +385,7 @@ mod tests {
     }
 
     #[test]
+    #[serial]
     fn test_create_lead_worker_provider() {
         // Save current env vars
         let saved_lead = env::var("GOOSE_LEAD_MODEL").ok();
@@ -280,6 +435,7 @@ mod tests {
     }
 
     #[test]
+    #[serial]
     fn test_lead_model_env_vars_with_defaults() {
         // Save current env vars
         let saved_vars = [
@@ -337,6 +493,7 @@ mod tests {
     }
 
     #[test]
+    #[serial]
     fn test_create_regular_provider_without_lead_config() {
         // Save current env vars
         let saved_lead = env::var("GOOSE_LEAD_MODEL").ok();
@@ -386,6 +543,7 @@ mod tests {
     }
 
     #[test]
+    #[serial]
     fn test_worker_model_preserves_original_context_limit() {
         use std::env;
 
@@ -448,4 +606,39 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_registry_context_limit_applies_per_provider() {
+        let saved = env::var("GOOSE_CONTEXT_LIMIT").ok();
+        env::remove_var("GOOSE_CONTEXT_LIMIT");
+
+        let registry = ModelRegistry::new(None);
+        registry
+            .insert(
+                "openrouter",
+                vec![crate::providers::base::ModelInfo::new("qwen3", 128_000)],
+            )
+            .await
+            .unwrap();
+        registry
+            .record_rejected("openrouter", "qwen3", 100_000)
+            .await
+            .unwrap();
+
+        let model = ModelConfig::new_or_fail("qwen3");
+        let reported = apply_registry_context_limit(&registry, "openrouter", model.clone());
+        assert_eq!(reported.context_limit(), 99_999);
+        let other = apply_registry_context_limit(&registry, "ollama", model.clone());
+        assert_eq!(other.context_limit(), model.context_limit());
+
+        // A limit the user set wins over anything reported or learned
+        let explicit = model.with_context_limit(Some(32_000));
+        let kept = apply_registry_context_limit(&registry, "openrouter", explicit);
+        assert_eq!(kept.context_limit(), 32_000);
+
+        if let Some(value) = saved {
+            env::set_var("GOOSE_CONTEXT_LIMIT", value);
+        }
+    }
 }
@@ -0,0 +1,317 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::base::{
+    stream_from_single_message, FallbackProviderTrait, MessageStream, ModelInfo, Provider,
+    ProviderMetadata, ProviderUsage, RequestTarget,
+};
+use super::errors::ProviderError;
+use super::retry::RetryPolicy;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+/// A provider that walks a chain of models, moving on to the next one when the current
+/// model is rate limited or overloaded
+pub struct FallbackProvider {
+    model: ModelConfig,
+    providers: Vec<Arc<dyn Provider>>,
+    active_index: AtomicUsize,
+    /// The model the latest request was sent to, which differs from the active one when it failed
+    attempted_index: AtomicUsize,
+}
+
+impl FallbackProvider {
+    /// Create a new FallbackProvider
+    ///
+    /// # Arguments
+    /// * `model` - The primary model config, including its fallback chain
+    /// * `providers` - One provider per model in the chain, primary first
+    pub fn new(model: ModelConfig, providers: Vec<Arc<dyn Provider>>) -> Self {
+        Self {
+            model,
+            providers,
+            active_index: AtomicUsize::new(0),
+            attempted_index: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether an error means the next model in the chain should be tried
+    fn should_fall_back(error: &ProviderError) -> bool {
+        matches!(
+            error,
+            ProviderError::RateLimitExceeded { .. } | ProviderError::ServerError(_)
+        )
+    }
+
+    fn primary(&self) -> &Arc<dyn Provider> {
+        &self.providers[0]
+    }
+
+    fn record_answer(&self, index: usize) {
+        self.active_index.store(index, Ordering::Relaxed);
+        let model_name = self.providers[index].get_model_config().model_name;
+        super::base::set_current_model(&model_name);
+        if index > 0 {
+            tracing::info!("Fallback model {} answered the request", model_name);
+        }
+    }
+
+    async fn stream_with(
+        provider: &Arc<dyn Provider>,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        if provider.supports_streaming() {
+            provider.stream(system, messages, tools).await
+        } else {
+            let (message, usage) = provider.complete(system, messages, tools).await?;
+            Ok(stream_from_single_message(message, usage))
+        }
+    }
+}
+
+impl FallbackProviderTrait for FallbackProvider {
+    fn get_primary_model(&self) -> String {
+        self.model.model_name.clone()
+    }
+
+    fn get_active_model(&self) -> String {
+        let index = self.active_index.load(Ordering::Relaxed);
+        self.providers[index].get_model_config().model_name
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "fallback",
+            "Fallback Provider",
+            "A provider that falls back to other models when the primary is rate limited or overloaded",
+            "",     // No default model as this is determined by the wrapped providers
+            vec![], // No known models as this depends on wrapped providers
+            "",     // No doc link
+            vec![], // No config keys as configuration is done through wrapped providers
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.primary().retry_policy()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let last = self.providers.len() - 1;
+        for (index, provider) in self.providers.iter().enumerate() {
+            self.attempted_index.store(index, Ordering::Relaxed);
+            match provider.complete(system, messages, tools).await {
+                Ok(result) => {
+                    self.record_answer(index);
+                    return Ok(result);
+                }
+                Err(e) if index < last && Self::should_fall_back(&e) => {
+                    tracing::warn!(
+                        "Model {} unavailable ({}), falling back to {}",
+                        provider.get_model_config().model_name,
+                        e,
+                        self.providers[index + 1].get_model_config().model_name
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("fallback chain always contains the primary provider")
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let last = self.providers.len() - 1;
+        for (index, provider) in self.providers.iter().enumerate() {
+            self.attempted_index.store(index, Ordering::Relaxed);
+            match Self::stream_with(provider, system, messages, tools).await {
+                Ok(stream) => {
+                    self.record_answer(index);
+                    return Ok(stream);
+                }
+                Err(e) if index < last && Self::should_fall_back(&e) => {
+                    tracing::warn!(
+                        "Model {} unavailable ({}), falling back to {}",
+                        provider.get_model_config().model_name,
+                        e,
+                        self.providers[index + 1].get_model_config().model_name
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("fallback chain always contains the primary provider")
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.primary().supports_streaming()
+    }
+
+    fn last_request_target(&self) -> RequestTarget {
+        let index = self.attempted_index.load(Ordering::Relaxed);
+        self.providers[index].last_request_target()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.primary().fetch_supported_models().await
+    }
+
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        self.primary().fetch_model_info().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.primary().supports_embeddings()
+    }
+
+    fn supports_cache_control(&self) -> bool {
+        self.primary().supports_cache_control()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.primary().create_embeddings(texts).await
+    }
+
+    fn as_fallback(&self) -> Option<&dyn FallbackProviderTrait> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    struct MockProvider {
+        model_config: ModelConfig,
+        error: Option<fn(String) -> ProviderError>,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let name = self.model_config.model_name.clone();
+            match self.error {
+                Some(error) => Err(error(format!("{} failed", name))),
+                None => Ok((
+                    Message::assistant().with_text(format!("Response from {}", name)),
+                    ProviderUsage::new(name, Usage::default()),
+                )),
+            }
+        }
+    }
+
+    fn mock(name: &str, error: Option<fn(String) -> ProviderError>) -> Arc<dyn Provider> {
+        Arc::new(MockProvider {
+            model_config: ModelConfig::new_or_fail(name),
+            error,
+        })
+    }
+
+    fn chain(providers: Vec<Arc<dyn Provider>>) -> FallbackProvider {
+        let fallbacks = providers[1..]
+            .iter()
+            .map(|p| p.get_model_config())
+            .collect();
+        let model = providers[0].get_model_config().with_fallbacks(fallbacks);
+        FallbackProvider::new(model, providers)
+    }
+
+    #[tokio::test]
+    async fn test_primary_answers_when_available() {
+        let provider = chain(vec![mock("primary", None), mock("secondary", None)]);
+
+        let (_message, usage) = provider.complete("system", &[], &[]).await.unwrap();
+        assert_eq!(usage.model, "primary");
+        assert_eq!(provider.get_active_model(), "primary");
+        assert_eq!(provider.get_primary_model(), "primary");
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_rate_limit_and_overload() {
+        let provider = chain(vec![
+            mock("primary", Some(ProviderError::rate_limited::<String>)),
+            mock("secondary", Some(ProviderError::ServerError)),
+            mock("tertiary", None),
+        ]);
+
+        let (_message, usage) = provider.complete("system", &[], &[]).await.unwrap();
+        assert_eq!(usage.model, "tertiary");
+        assert_eq!(provider.get_active_model(), "tertiary");
+        assert_eq!(provider.get_active_model_name(), "tertiary");
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_do_not_fall_back() {
+        let provider = chain(vec![
+            mock("primary", Some(ProviderError::Authentication)),
+            mock("secondary", None),
+        ]);
+
+        let result = provider.complete("system", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::Authentication(_))));
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_target_is_the_model_that_failed() {
+        let provider = chain(vec![
+            mock("primary", Some(ProviderError::rate_limited::<String>)),
+            mock("secondary", Some(ProviderError::ContextLengthExceeded)),
+        ]);
+
+        let result = provider.complete("system", &[], &[]).await;
+        assert!(matches!(
+            result,
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+        assert_eq!(provider.last_request_target().model, "secondary");
+        assert_eq!(provider.get_active_model(), "primary");
+    }
+
+    #[tokio::test]
+    async fn test_last_error_is_returned_when_chain_is_exhausted() {
+        let provider = chain(vec![
+            mock("primary", Some(ProviderError::rate_limited::<String>)),
+            mock("secondary", Some(ProviderError::rate_limited::<String>)),
+        ]);
+
+        let result = provider.stream("system", &[], &[]).await;
+        assert!(
+            matches!(result, Err(ProviderError::RateLimitExceeded { details, .. }) if details.contains("secondary"))
+        );
+    }
+}
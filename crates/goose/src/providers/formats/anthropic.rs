@@ -1,6 +1,6 @@
-use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::message::{Citation, Message, MessageContent, StopReason};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{ToolChoice, Usage};
 use crate::providers::errors::ProviderError;
 use anyhow::{anyhow, Result};
 use mcp_core::tool::ToolCall;
@@ -27,6 +27,9 @@ const TOOL_USE_ID_FIELD: &str = "tool_use_id";
 const IS_ERROR_FIELD: &str = "is_error";
 const SIGNATURE_FIELD: &str = "signature";
 const DATA_FIELD: &str = "data";
+const STOP_REASON_FIELD: &str = "stop_reason";
+const CITATIONS_FIELD: &str = "citations";
+const WEB_SEARCH_TOOL_RESULT_TYPE: &str = "web_search_tool_result";
 
 /// Convert internal Message format to Anthropic's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -96,6 +99,11 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                MessageContent::Citation(_) => {
+                    // Skip, the citations were returned for this answer and are not sent back
+                }
+                // Thinking from other providers has no signature, which Anthropic rejects
+                MessageContent::Thinking(thinking) if thinking.signature.is_empty() => {}
                 MessageContent::Thinking(thinking) => {
                     content.push(json!({
                         TYPE_FIELD: THINKING_TYPE,
@@ -221,6 +229,17 @@ pub fn response_to_message(response: &Value) -> Result<Message> {
                 if let Some(text) = block.get(TEXT_TYPE).and_then(|t| t.as_str()) {
                     message = message.with_text(text.to_string());
                 }
+                let citations = block.get(CITATIONS_FIELD).and_then(|c| c.as_array());
+                for citation in citations.into_iter().flatten() {
+                    message = message.with_citation(citation_from_anthropic(citation));
+                }
+            }
+            Some(WEB_SEARCH_TOOL_RESULT_TYPE) => {
+                // An error result is an object rather than a list of results
+                let results = block.get(CONTENT_FIELD).and_then(|c| c.as_array());
+                for result in results.into_iter().flatten() {
+                    message = message.with_citation(citation_from_anthropic(result));
+                }
             }
             Some(TOOL_USE_TYPE) => {
                 let id = block
@@ -260,7 +279,24 @@ pub fn response_to_message(response: &Value) -> Result<Message> {
         }
     }
 
-    Ok(message)
+    let stop_reason = response
+        .get(STOP_REASON_FIELD)
+        .and_then(|r| r.as_str())
+        .map(StopReason::from_provider_str);
+
+    Ok(message.with_stop_reason(stop_reason))
+}
+
+/// Normalize a text block citation or web search result. Document citations carry a
+/// `document_title`, search result citations their `source`.
+fn citation_from_anthropic(value: &Value) -> Citation {
+    let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    Citation {
+        title: field("title").or_else(|| field("document_title")),
+        url: field("url").or_else(|| field("source")),
+        cited_text: field("cited_text"),
+        metadata: value.clone(),
+    }
 }
 
 /// Extract usage information from Anthropic's API response
@@ -302,6 +338,10 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             Some(total_input_i32),
             Some(output_tokens_i32),
             Some(total_tokens_i32),
+        )
+        .with_cache_tokens(
+            Some(clamp_tokens(cache_read_tokens)),
+            Some(clamp_tokens(cache_creation_tokens)),
         ))
     } else if data.as_object().is_some() {
         // Check if the data itself is the usage object (for message_delta events that might have usage at top level)
@@ -345,6 +385,10 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
                 Some(total_input_i32),
                 Some(output_tokens_i32),
                 Some(total_tokens_i32),
+            )
+            .with_cache_tokens(
+                Some(clamp_tokens(cache_read_tokens)),
+                Some(clamp_tokens(cache_creation_tokens)),
             ))
         } else {
             tracing::debug!("🔍 Anthropic no token data found in object");
@@ -360,6 +404,10 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+fn clamp_tokens(tokens: u64) -> i32 {
+    tokens.min(i32::MAX as u64) as i32
+}
+
 /// Create a complete request payload for Anthropic's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -412,9 +460,27 @@ pub fn create_request(
         }
     }
 
+    let is_thinking_enabled = model_config.model_name.starts_with("claude-3-7-sonnet-")
+        && std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
+
+    // Anthropic has no penalty or seed parameters; top_p is not allowed alongside thinking
+    if let Some(stop) = &model_config.stop {
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stop_sequences".to_string(), json!(stop));
+    }
+    if let Some(top_p) = model_config.top_p {
+        if !is_thinking_enabled {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("top_p".to_string(), json!(top_p));
+        }
+    }
+
     // Add thinking parameters for claude-3-7-sonnet model
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if model_config.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
+    if is_thinking_enabled {
         // Minimum budget_tokens is 1024
         let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
             .unwrap_or_else(|_| "16000".to_string())
@@ -468,6 +534,7 @@ where
         let mut current_tool_id: Option<String> = None;
         let mut final_usage: Option<crate::providers::base::ProviderUsage> = None;
         let mut message_id: Option<String> = None;
+        let mut stop_reason: Option<StopReason> = None;
 
         while let Some(line_result) = stream.next().await {
             let line = line_result?;
@@ -547,6 +614,16 @@ where
                                 message.id = message_id.clone();
                                 yield (Some(message), None);
                             }
+                        } else if delta.get("type") == Some(&json!("citations_delta")) {
+                            if let Some(citation) = delta.get("citation") {
+                                let mut message = Message::new(
+                                    Role::Assistant,
+                                    chrono::Utc::now().timestamp(),
+                                    vec![MessageContent::citation(citation_from_anthropic(citation))],
+                                );
+                                message.id = message_id.clone();
+                                yield (Some(message), None);
+                            }
                         } else if delta.get("type") == Some(&json!("input_json_delta")) {
                             // Tool input delta
                             if let Some(tool_id) = &current_tool_id {
@@ -602,6 +679,13 @@ where
                 "message_delta" => {
                     // Message metadata delta (like stop_reason) and cumulative usage
                     tracing::debug!("🔍 Anthropic message_delta event data: {}", serde_json::to_string_pretty(&event.data).unwrap_or_else(|_| format!("{:?}", event.data)));
+                    if let Some(reason) = event.data
+                        .get("delta")
+                        .and_then(|d| d.get(STOP_REASON_FIELD))
+                        .and_then(|r| r.as_str())
+                    {
+                        stop_reason = Some(StopReason::from_provider_str(reason));
+                    }
                     if let Some(usage_data) = event.data.get("usage") {
                         tracing::debug!("🔍 Anthropic message_delta usage data (cumulative): {}", serde_json::to_string_pretty(usage_data).unwrap_or_else(|_| format!("{:?}", usage_data)));
                         let delta_usage = get_usage(usage_data).unwrap_or_default();
@@ -620,7 +704,11 @@ where
                                 (None, None) => None,
                             };
 
-                            let merged_usage = crate::providers::base::Usage::new(merged_input, merged_output, merged_total);
+                            let merged_usage = crate::providers::base::Usage::new(merged_input, merged_output, merged_total)
+                                .with_cache_tokens(
+                                    existing_usage.usage.cache_read_tokens.or(delta_usage.cache_read_tokens),
+                                    existing_usage.usage.cache_write_tokens.or(delta_usage.cache_write_tokens),
+                                );
                             final_usage = Some(crate::providers::base::ProviderUsage::new(existing_usage.model.clone(), merged_usage));
                             tracing::debug!("🔍 Anthropic MERGED usage: input_tokens={:?}, output_tokens={:?}, total_tokens={:?}",
                                     merged_input, merged_output, merged_total);
@@ -664,15 +752,41 @@ where
             }
         }
 
-        // Yield final usage information if available
-        if let Some(usage) = final_usage {
-            yield (None, Some(usage));
-        } else {
+        // Yield the stop reason on an empty message so it reaches the agent alongside final usage
+        let final_message = stop_reason.map(|reason| {
+            let mut message = Message::new(Role::Assistant, chrono::Utc::now().timestamp(), vec![])
+                .with_stop_reason(Some(reason));
+            message.id = message_id.clone();
+            message
+        });
+
+        if final_usage.is_none() {
             tracing::debug!("🔍 Anthropic no final usage to yield");
         }
+        if final_message.is_some() || final_usage.is_some() {
+            yield (final_message, final_usage);
+        }
     }
 }
 
+/// Tell the model which tool it has to call, when the request offers tools. Extended
+/// thinking doesn't allow forcing a tool, so those requests keep the default.
+pub fn apply_tool_choice(payload: &mut Value, tool_choice: &ToolChoice) {
+    if payload.get("tools").is_none() {
+        return;
+    }
+    let forced = matches!(tool_choice, ToolChoice::Required | ToolChoice::Tool(_));
+    if forced && payload.get("thinking").is_some() {
+        return;
+    }
+    payload["tool_choice"] = match tool_choice {
+        ToolChoice::Auto => return,
+        ToolChoice::None => json!({"type": "none"}),
+        ToolChoice::Required => json!({"type": "any"}),
+        ToolChoice::Tool(name) => json!({"type": "tool", "name": name}),
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -710,6 +824,7 @@ mod tests {
             panic!("Expected Text content");
         }
 
+        assert_eq!(message.stop_reason, Some(StopReason::Stop));
         assert_eq!(usage.input_tokens, Some(24)); // 12 + 12 = 24 actual tokens
         assert_eq!(usage.output_tokens, Some(15));
         assert_eq!(usage.total_tokens, Some(39)); // 24 + 15
@@ -834,6 +949,68 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_citations_and_search_results() -> Result<()> {
+        let response = json!({
+            "id": "msg_789",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {
+                    "type": "web_search_tool_result",
+                    "tool_use_id": "srvtoolu_1",
+                    "content": [{
+                        "type": "web_search_result",
+                        "url": "https://www.rust-lang.org",
+                        "title": "Rust Programming Language",
+                        "page_age": "1 day ago"
+                    }]
+                },
+                {
+                    "type": "text",
+                    "text": "Rust is memory safe.",
+                    "citations": [{
+                        "type": "web_search_result_location",
+                        "url": "https://www.rust-lang.org",
+                        "title": "Rust Programming Language",
+                        "cited_text": "Rust's rich type system and ownership model guarantee memory-safety"
+                    }, {
+                        "type": "char_location",
+                        "document_title": "notes.txt",
+                        "cited_text": "memory safe",
+                        "start_char_index": 0,
+                        "end_char_index": 11
+                    }]
+                }
+            ],
+            "model": "claude-sonnet-4-20250514",
+            "stop_reason": "end_turn"
+        });
+
+        let message = response_to_message(&response)?;
+        let citations: Vec<&Citation> = message
+            .content
+            .iter()
+            .filter_map(|c| c.as_citation())
+            .collect();
+
+        assert_eq!(message.as_concat_text(), "Rust is memory safe.");
+        assert_eq!(citations.len(), 3);
+        assert_eq!(
+            citations[0].url.as_deref(),
+            Some("https://www.rust-lang.org")
+        );
+        assert_eq!(citations[0].metadata["page_age"], "1 day ago");
+        assert_eq!(
+            citations[1].label(),
+            "Rust Programming Language - https://www.rust-lang.org"
+        );
+        assert_eq!(citations[2].title.as_deref(), Some("notes.txt"));
+        assert_eq!(citations[2].cited_text.as_deref(), Some("memory safe"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_message_to_anthropic_spec() {
         let messages = vec![
@@ -916,7 +1093,8 @@ mod tests {
         std::env::set_var("CLAUDE_THINKING_ENABLED", "true");
 
         let result = (|| {
-            let model_config = ModelConfig::new_or_fail("claude-3-7-sonnet-20250219");
+            let model_config =
+                ModelConfig::new_or_fail("claude-3-7-sonnet-20250219").with_top_p(Some(0.9));
             let system = "You are a helpful assistant.";
             let messages = vec![Message::user().with_text("Hello")];
             let tools = vec![];
@@ -935,6 +1113,12 @@ mod tests {
 
             // Temperature should not be present for 3.7 models with thinking
             assert!(payload.get("temperature").is_none());
+            assert!(payload.get("top_p").is_none());
+
+            std::env::remove_var("CLAUDE_THINKING_ENABLED");
+            let payload = create_request(&model_config, system, &messages, &tools)?;
+            assert!(payload.get("thinking").is_none());
+            assert_eq!(payload["top_p"], json!(0.9f32));
 
             Ok(())
         })();
@@ -978,6 +1162,8 @@ mod tests {
         assert_eq!(usage.input_tokens, Some(15007));
         assert_eq!(usage.output_tokens, Some(50));
         assert_eq!(usage.total_tokens, Some(15057)); // 15007 + 50
+        assert_eq!(usage.cache_read_tokens, Some(5000));
+        assert_eq!(usage.cache_write_tokens, Some(10000));
 
         Ok(())
     }
@@ -1016,4 +1202,20 @@ mod tests {
         );
         assert_eq!(spec[1]["content"][0]["is_error"], true);
     }
+
+    #[test]
+    fn test_apply_tool_choice() {
+        let mut payload = json!({"model": "claude-sonnet-4-0", "tools": [{"name": "answer"}]});
+        apply_tool_choice(&mut payload, &ToolChoice::Tool("answer".to_string()));
+        assert_eq!(
+            payload["tool_choice"],
+            json!({"type": "tool", "name": "answer"})
+        );
+        apply_tool_choice(&mut payload, &ToolChoice::Required);
+        assert_eq!(payload["tool_choice"], json!({"type": "any"}));
+
+        let mut thinking = json!({"tools": [{"name": "answer"}], "thinking": {"type": "enabled"}});
+        apply_tool_choice(&mut thinking, &ToolChoice::Required);
+        assert!(thinking.get("tool_choice").is_none());
+    }
 }
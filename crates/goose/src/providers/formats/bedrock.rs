@@ -11,7 +11,7 @@ use rmcp::model::{Content, RawContent, ResourceContents, Role, Tool};
 use serde_json::Value;
 
 use super::super::base::Usage;
-use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::message::{Message, MessageContent, StopReason};
 
 pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
     bedrock::Message::builder()
@@ -44,6 +44,10 @@ pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::C
             // Redacted thinking blocks are not supported in Bedrock - skip
             bedrock::ContentBlock::Text("".to_string())
         }
+        MessageContent::Citation(_) => {
+            // Citations are only kept for display - skip
+            bedrock::ContentBlock::Text("".to_string())
+        }
         MessageContent::ContextLengthExceeded(_) => {
             bail!("ContextLengthExceeded should not get passed to the provider")
         }
@@ -323,10 +327,121 @@ pub fn from_bedrock_role(role: &bedrock::ConversationRole) -> Result<Role> {
 }
 
 pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
-    Usage {
-        input_tokens: Some(usage.input_tokens),
-        output_tokens: Some(usage.output_tokens),
-        total_tokens: Some(usage.total_tokens),
+    Usage::new(
+        Some(usage.input_tokens),
+        Some(usage.output_tokens),
+        Some(usage.total_tokens),
+    )
+}
+
+/// A tool call whose input is still arriving
+struct PendingToolUse {
+    id: String,
+    name: String,
+    input: String,
+}
+
+impl PendingToolUse {
+    fn into_content(self) -> MessageContent {
+        let arguments = if self.input.trim().is_empty() {
+            Ok(Value::Object(Default::default()))
+        } else {
+            serde_json::from_str::<Value>(&self.input)
+        };
+        match arguments {
+            Ok(arguments) => {
+                MessageContent::tool_request(self.id, Ok(ToolCall::new(self.name, arguments)))
+            }
+            Err(_) => MessageContent::tool_request(
+                self.id,
+                Err(ToolError::InvalidParameters(format!(
+                    "Could not parse tool arguments: {}",
+                    self.input
+                ))),
+            ),
+        }
+    }
+}
+
+/// Turns the events of a ConverseStream response into messages: text as it arrives, each
+/// tool call once its input is complete, and the stop reason and usage at the end
+pub struct BedrockStreamState {
+    message_id: String,
+    tool_uses: HashMap<i32, PendingToolUse>,
+    stop_reason: Option<StopReason>,
+    usage: Option<Usage>,
+}
+
+impl Default for BedrockStreamState {
+    fn default() -> Self {
+        Self {
+            // Bedrock doesn't identify responses, but the parts of one need a shared id
+            message_id: format!("msg_{}", uuid::Uuid::new_v4().simple()),
+            tool_uses: HashMap::new(),
+            stop_reason: None,
+            usage: None,
+        }
+    }
+}
+
+impl BedrockStreamState {
+    /// Take in one stream event, returning a message if it completes one
+    pub fn handle(&mut self, event: bedrock::ConverseStreamOutput) -> Option<Message> {
+        match event {
+            bedrock::ConverseStreamOutput::ContentBlockStart(start) => {
+                if let Some(bedrock::ContentBlockStart::ToolUse(tool_use)) = start.start {
+                    self.tool_uses.insert(
+                        start.content_block_index,
+                        PendingToolUse {
+                            id: tool_use.tool_use_id,
+                            name: tool_use.name,
+                            input: String::new(),
+                        },
+                    );
+                }
+                None
+            }
+            bedrock::ConverseStreamOutput::ContentBlockDelta(delta) => match delta.delta {
+                Some(bedrock::ContentBlockDelta::Text(text)) if !text.is_empty() => {
+                    Some(self.message(MessageContent::text(text)))
+                }
+                Some(bedrock::ContentBlockDelta::ToolUse(tool_delta)) => {
+                    if let Some(tool_use) = self.tool_uses.get_mut(&delta.content_block_index) {
+                        tool_use.input.push_str(&tool_delta.input);
+                    }
+                    None
+                }
+                _ => None,
+            },
+            bedrock::ConverseStreamOutput::ContentBlockStop(stop) => self
+                .tool_uses
+                .remove(&stop.content_block_index)
+                .map(|tool_use| self.message(tool_use.into_content())),
+            bedrock::ConverseStreamOutput::MessageStop(stop) => {
+                self.stop_reason = Some(StopReason::from_provider_str(stop.stop_reason.as_str()));
+                None
+            }
+            bedrock::ConverseStreamOutput::Metadata(metadata) => {
+                self.usage = metadata.usage.as_ref().map(from_bedrock_usage);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// The empty closing message carrying the stop reason, if there was one, and the usage
+    pub fn finish(self) -> (Option<Message>, Usage) {
+        let message = self.stop_reason.map(|reason| {
+            Message::new(Role::Assistant, Utc::now().timestamp(), vec![])
+                .with_id(self.message_id.clone())
+                .with_stop_reason(Some(reason))
+        });
+        (message, self.usage.unwrap_or_default())
+    }
+
+    fn message(&self, content: MessageContent) -> Message {
+        Message::new(Role::Assistant, Utc::now().timestamp(), vec![content])
+            .with_id(self.message_id.clone())
     }
 }
 
@@ -442,4 +557,71 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_stream_state_assembles_text_and_tool_calls() -> Result<()> {
+        let mut state = BedrockStreamState::default();
+
+        let text = state.handle(bedrock::ConverseStreamOutput::ContentBlockDelta(
+            bedrock::ContentBlockDeltaEvent::builder()
+                .content_block_index(0)
+                .delta(bedrock::ContentBlockDelta::Text("Let me look.".to_string()))
+                .build()?,
+        ));
+        assert_eq!(text.unwrap().as_concat_text(), "Let me look.");
+
+        assert!(state
+            .handle(bedrock::ConverseStreamOutput::ContentBlockStart(
+                bedrock::ContentBlockStartEvent::builder()
+                    .content_block_index(1)
+                    .start(bedrock::ContentBlockStart::ToolUse(
+                        bedrock::ToolUseBlockStart::builder()
+                            .tool_use_id("tooluse_1")
+                            .name("developer__shell")
+                            .build()?,
+                    ))
+                    .build()?,
+            ))
+            .is_none());
+        for part in [r#"{"command": "#, r#""ls"}"#] {
+            assert!(state
+                .handle(bedrock::ConverseStreamOutput::ContentBlockDelta(
+                    bedrock::ContentBlockDeltaEvent::builder()
+                        .content_block_index(1)
+                        .delta(bedrock::ContentBlockDelta::ToolUse(
+                            bedrock::ToolUseBlockDelta::builder().input(part).build()?,
+                        ))
+                        .build()?,
+                ))
+                .is_none());
+        }
+        let tool_message = state
+            .handle(bedrock::ConverseStreamOutput::ContentBlockStop(
+                bedrock::ContentBlockStopEvent::builder()
+                    .content_block_index(1)
+                    .build()?,
+            ))
+            .unwrap();
+        match &tool_message.content[0] {
+            MessageContent::ToolRequest(request) => {
+                let call = request.tool_call.as_ref().unwrap();
+                assert_eq!(request.id, "tooluse_1");
+                assert_eq!(call.name, "developer__shell");
+                assert_eq!(call.arguments, serde_json::json!({"command": "ls"}));
+            }
+            other => panic!("Expected a tool request, got {:?}", other),
+        }
+
+        state.handle(bedrock::ConverseStreamOutput::MessageStop(
+            bedrock::MessageStopEvent::builder()
+                .stop_reason(bedrock::StopReason::ToolUse)
+                .build()?,
+        ));
+        let (closing, _usage) = state.finish();
+        let closing = closing.unwrap();
+        assert_eq!(closing.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(closing.id, tool_message.id);
+
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::message::{Message, MessageContent, StopReason};
 use crate::model::ModelConfig;
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file, safely_parse_json,
@@ -124,6 +124,9 @@ fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<Data
                 MessageContent::SummarizationRequested(_) => {
                     continue;
                 }
+                MessageContent::Citation(_) => {
+                    continue;
+                }
                 MessageContent::ToolResponse(response) => {
                     match &response.tool_result {
                         Ok(contents) => {
@@ -382,11 +385,14 @@ pub fn response_to_message(response: &Value) -> anyhow::Result<Message> {
         }
     }
 
-    Ok(Message::new(
-        Role::Assistant,
-        chrono::Utc::now().timestamp(),
-        content,
-    ))
+    let stop_reason = response["choices"][0]["finish_reason"]
+        .as_str()
+        .map(StopReason::from_provider_str);
+
+    Ok(
+        Message::new(Role::Assistant, chrono::Utc::now().timestamp(), content)
+            .with_stop_reason(stop_reason),
+    )
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -487,13 +493,12 @@ pub fn create_request(
     }
 
     let model_name = model_config.model_name.to_string();
-    let is_o1 = model_name.starts_with("o1") || model_name.starts_with("goose-o1");
-    let is_o3 = model_name.starts_with("o3") || model_name.starts_with("goose-o3");
+    let is_reasoning = super::openai::is_reasoning_model(&model_name);
     let is_claude_sonnet =
         model_name.contains("claude-3-7-sonnet") || model_name.contains("claude-4-sonnet"); // can be goose- or databricks-
 
-    // Only extract reasoning effort for O1/O3 models
-    let (model_name, reasoning_effort) = if is_o1 || is_o3 {
+    // Only extract reasoning effort for reasoning models
+    let (model_name, reasoning_effort) = if is_reasoning {
         let parts: Vec<&str> = model_config.model_name.split('-').collect();
         let last_part = parts.last().unwrap();
 
@@ -513,12 +518,7 @@ pub fn create_request(
     };
 
     let system_message = DatabricksMessage {
-        role: if is_o1 || is_o3 {
-            "developer"
-        } else {
-            "system"
-        }
-        .to_string(),
+        role: if is_reasoning { "developer" } else { "system" }.to_string(),
         content: system.into(),
         tool_calls: None,
         tool_call_id: None,
@@ -587,19 +587,20 @@ pub fn create_request(
             .unwrap()
             .insert("temperature".to_string(), json!(2));
     } else {
-        // o1, o3 models currently don't support temperature
-        if !is_o1 && !is_o3 {
+        // Reasoning models reject temperature and the other sampling parameters
+        if !is_reasoning {
             if let Some(temp) = model_config.temperature {
                 payload
                     .as_object_mut()
                     .unwrap()
                     .insert("temperature".to_string(), json!(temp));
             }
+            super::openai::add_sampling_params(&mut payload, model_config);
         }
 
-        // o1 models use max_completion_tokens instead of max_tokens
+        // Reasoning models use max_completion_tokens instead of max_tokens
         if let Some(tokens) = model_config.max_tokens {
-            let key = if is_o1 || is_o3 {
+            let key = if is_reasoning {
                 "max_completion_tokens"
             } else {
                 "max_tokens"
@@ -1022,10 +1023,19 @@ mod tests {
         let model_config = ModelConfig {
             model_name: "gpt-4o".to_string(),
             context_limit: Some(4096),
+            context_limit_explicit: true,
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            logprobs: None,
             toolshim: false,
             toolshim_model: None,
+            toolshim_provider: None,
+            fallbacks: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1053,10 +1063,18 @@ mod tests {
         let model_config = ModelConfig {
             model_name: "o1".to_string(),
             context_limit: Some(4096),
+            context_limit_explicit: true,
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            logprobs: None,
             toolshim: false,
             toolshim_model: None,
+            fallbacks: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1085,10 +1103,18 @@ mod tests {
         let model_config = ModelConfig {
             model_name: "o3-mini-high".to_string(),
             context_limit: Some(4096),
+            context_limit_explicit: true,
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            logprobs: None,
             toolshim: false,
             toolshim_model: None,
+            fallbacks: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
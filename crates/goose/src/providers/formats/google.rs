@@ -7,7 +7,7 @@ use mcp_core::tool::ToolCall;
 use rand::{distributions::Alphanumeric, Rng};
 use rmcp::model::{AnnotateAble, RawContent, Role, Tool};
 
-use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::message::{Citation, Message, MessageContent, StopReason};
 use serde_json::{json, Map, Value};
 use std::ops::Deref;
 
@@ -270,7 +270,61 @@ pub fn response_to_message(response: Value) -> Result<Message> {
             }
         }
     }
-    Ok(Message::new(role, created, content))
+    if let Some(grounding) = candidate.get("groundingMetadata") {
+        content.extend(
+            grounding_citations(grounding)
+                .into_iter()
+                .map(MessageContent::citation),
+        );
+    }
+    let stop_reason = candidate
+        .get("finishReason")
+        .and_then(|r| r.as_str())
+        .map(StopReason::from_provider_str);
+    Ok(Message::new(role, created, content).with_stop_reason(stop_reason))
+}
+
+/// The sources in a candidate's grounding metadata, from Google Search or a retrieval store,
+/// each with the first segment of the answer it supports
+fn grounding_citations(grounding: &Value) -> Vec<Citation> {
+    let supports: &[Value] = grounding
+        .get("groundingSupports")
+        .and_then(|s| s.as_array())
+        .map_or(&[], |s| s.as_slice());
+    let chunks = grounding.get("groundingChunks").and_then(|c| c.as_array());
+
+    chunks
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let source = chunk
+                .get("web")
+                .or_else(|| chunk.get("retrievedContext"))
+                .unwrap_or(chunk);
+            let field = |name: &str| {
+                source
+                    .get(name)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            };
+            let cited_text = supports
+                .iter()
+                .find(|support| {
+                    support["groundingChunkIndices"]
+                        .as_array()
+                        .is_some_and(|indices| indices.contains(&json!(index)))
+                })
+                .and_then(|support| support["segment"]["text"].as_str())
+                .map(str::to_string);
+            Citation {
+                title: field("title"),
+                url: field("uri"),
+                cited_text,
+                metadata: chunk.clone(),
+            }
+        })
+        .collect()
 }
 
 /// Extract usage information from Google's API response
@@ -288,7 +342,12 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             .get("totalTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        let cache_read_tokens = usage_meta_data
+            .get("cachedContentTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
+        Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cache_read_tokens, None))
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -325,6 +384,21 @@ pub fn create_request(
     if let Some(tokens) = model_config.max_tokens {
         generation_config.insert("maxOutputTokens".to_string(), json!(tokens));
     }
+    if let Some(top_p) = model_config.top_p {
+        generation_config.insert("topP".to_string(), json!(top_p as f64));
+    }
+    if let Some(stop) = &model_config.stop {
+        generation_config.insert("stopSequences".to_string(), json!(stop));
+    }
+    if let Some(penalty) = model_config.frequency_penalty {
+        generation_config.insert("frequencyPenalty".to_string(), json!(penalty as f64));
+    }
+    if let Some(penalty) = model_config.presence_penalty {
+        generation_config.insert("presencePenalty".to_string(), json!(penalty as f64));
+    }
+    if let Some(seed) = model_config.seed {
+        generation_config.insert("seed".to_string(), json!(seed));
+    }
     if !generation_config.is_empty() {
         payload.insert("generationConfig".to_string(), json!(generation_config));
     }
@@ -723,6 +797,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_response_to_message_with_grounding_metadata() {
+        let response = json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "Spain won Euro 2024."}]},
+                "groundingMetadata": {
+                    "webSearchQueries": ["who won euro 2024"],
+                    "groundingChunks": [
+                        {"web": {"uri": "https://example.com/a", "title": "example.com"}},
+                        {"web": {"uri": "https://example.com/b", "title": "Match report"}}
+                    ],
+                    "groundingSupports": [{
+                        "segment": {"startIndex": 0, "endIndex": 20, "text": "Spain won Euro 2024."},
+                        "groundingChunkIndices": [1]
+                    }]
+                }
+            }]
+        });
+        let message = response_to_message(response).unwrap();
+        let citations: Vec<&Citation> = message
+            .content
+            .iter()
+            .filter_map(|c| c.as_citation())
+            .collect();
+
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[0].url.as_deref(), Some("https://example.com/a"));
+        assert_eq!(citations[0].cited_text, None);
+        assert_eq!(citations[1].title.as_deref(), Some("Match report"));
+        assert_eq!(
+            citations[1].cited_text.as_deref(),
+            Some("Spain won Euro 2024.")
+        );
+    }
+
     #[test]
     fn test_response_to_message_with_invalid_function_name() {
         let response = json!({
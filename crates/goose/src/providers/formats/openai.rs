@@ -1,6 +1,7 @@
-use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::message::{Message, MessageContent, StopReason, TokenLogprob, TopLogprob};
 use crate::model::ModelConfig;
-use crate::providers::base::{ProviderUsage, Usage};
+use crate::providers::base::{ProviderUsage, ToolChoice, Usage};
+use crate::providers::model_registry::ModelRegistry;
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file, safely_parse_json,
     sanitize_function_name, ImageFormat,
@@ -31,6 +32,8 @@ struct DeltaToolCall {
 #[derive(Serialize, Deserialize, Debug)]
 struct Delta {
     content: Option<String>,
+    /// Chain of thought, sent by reasoning models such as deepseek-reasoner
+    reasoning_content: Option<String>,
     role: Option<String>,
     tool_calls: Option<Vec<DeltaToolCall>>,
 }
@@ -40,6 +43,8 @@ struct StreamingChoice {
     delta: Delta,
     index: Option<i32>,
     finish_reason: Option<String>,
+    #[serde(default)]
+    logprobs: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -98,6 +103,10 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                 MessageContent::SummarizationRequested(_) => {
                     continue;
                 }
+                MessageContent::Citation(_) => {
+                    // Citations describe the answer's sources, they are not sent back
+                    continue;
+                }
                 MessageContent::ToolRequest(request) => match &request.tool_call {
                     Ok(tool_call) => {
                         let sanitized_name = sanitize_function_name(&tool_call.name);
@@ -269,6 +278,16 @@ pub fn format_tools(tools: &[Tool]) -> anyhow::Result<Vec<Value>> {
 pub fn response_to_message(response: &Value) -> anyhow::Result<Message> {
     let original = &response["choices"][0]["message"];
     let mut content = Vec::new();
+    let mut stop_reason = response["choices"][0]["finish_reason"]
+        .as_str()
+        .map(StopReason::from_provider_str);
+
+    // Reasoning models such as deepseek-reasoner return their chain of thought separately
+    if let Some(reasoning) = original.get("reasoning_content").and_then(|r| r.as_str()) {
+        if !reasoning.is_empty() {
+            content.push(MessageContent::thinking(reasoning, ""));
+        }
+    }
 
     if let Some(text) = original.get("content") {
         if let Some(text_str) = text.as_str() {
@@ -276,6 +295,12 @@ pub fn response_to_message(response: &Value) -> anyhow::Result<Message> {
         }
     }
 
+    // Structured-output capable models report a refusal in its own field rather than content
+    if let Some(refusal) = original.get("refusal").and_then(|r| r.as_str()) {
+        content.push(MessageContent::text(refusal));
+        stop_reason = Some(StopReason::Refusal);
+    }
+
     if let Some(tool_calls) = original.get("tool_calls") {
         if let Some(tool_calls_array) = tool_calls.as_array() {
             for tool_call in tool_calls_array {
@@ -325,11 +350,43 @@ pub fn response_to_message(response: &Value) -> anyhow::Result<Message> {
         }
     }
 
-    Ok(Message::new(
-        Role::Assistant,
-        chrono::Utc::now().timestamp(),
-        content,
-    ))
+    Ok(
+        Message::new(Role::Assistant, chrono::Utc::now().timestamp(), content)
+            .with_stop_reason(stop_reason)
+            .with_logprobs(get_logprobs(&response["choices"][0]["logprobs"])),
+    )
+}
+
+/// Read the per-token log probabilities of a choice's `logprobs`, empty when there are none
+pub fn get_logprobs(logprobs: &Value) -> Vec<TokenLogprob> {
+    let entry = |value: &Value| -> Option<(String, f64)> {
+        Some((
+            value.get("token")?.as_str()?.to_string(),
+            value.get("logprob")?.as_f64()?,
+        ))
+    };
+    logprobs
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|token| {
+            let (text, logprob) = entry(token)?;
+            let top_logprobs = token
+                .get("top_logprobs")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(entry)
+                .map(|(token, logprob)| TopLogprob { token, logprob })
+                .collect();
+            Some(TokenLogprob {
+                token: text,
+                logprob,
+                top_logprobs,
+            })
+        })
+        .collect()
 }
 
 pub fn get_usage(usage: &Value) -> Usage {
@@ -352,7 +409,16 @@ pub fn get_usage(usage: &Value) -> Usage {
             _ => None,
         });
 
-    Usage::new(input_tokens, output_tokens, total_tokens)
+    // OpenAI caches long prompts on its own and reports the hits here; DeepSeek reports them
+    // at the top level
+    let cache_read_tokens = usage
+        .get("prompt_tokens_details")
+        .and_then(|details| details.get("cached_tokens"))
+        .or_else(|| usage.get("prompt_cache_hit_tokens"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    Usage::new(input_tokens, output_tokens, total_tokens).with_cache_tokens(cache_read_tokens, None)
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -518,6 +584,10 @@ where
                         role: Role::Assistant,
                         created: chrono::Utc::now().timestamp(),
                         content: contents,
+                        stop_reason: Some(StopReason::ToolUse),
+                        pinned: false,
+                        origin: None,
+                        logprobs: Vec::new(),
                     }),
                     usage,
                 )
@@ -528,6 +598,13 @@ where
                         role: Role::Assistant,
                         created: chrono::Utc::now().timestamp(),
                         content: vec![MessageContent::text(text)],
+                        stop_reason: chunk.choices[0]
+                            .finish_reason
+                            .as_deref()
+                            .map(StopReason::from_provider_str),
+                        pinned: false,
+                        origin: None,
+                        logprobs: chunk.choices[0].logprobs.as_ref().map(get_logprobs).unwrap_or_default(),
                     }),
                     if chunk.choices[0].finish_reason.is_some() {
                         usage
@@ -535,6 +612,34 @@ where
                         None
                     },
                 )
+            } else if let Some(reasoning) = chunk.choices[0].delta.reasoning_content.as_ref().filter(|r| !r.is_empty()) {
+                yield (
+                    Some(Message {
+                        id: chunk.id,
+                        role: Role::Assistant,
+                        created: chrono::Utc::now().timestamp(),
+                        content: vec![MessageContent::thinking(reasoning, "")],
+                        stop_reason: None,
+                        pinned: false,
+                        origin: None,
+                        logprobs: Vec::new(),
+                    }),
+                    None,
+                )
+            } else if let Some(reason) = &chunk.choices[0].finish_reason {
+                yield (
+                    Some(Message {
+                        id: chunk.id.clone(),
+                        role: Role::Assistant,
+                        created: chrono::Utc::now().timestamp(),
+                        content: vec![],
+                        stop_reason: Some(StopReason::from_provider_str(reason)),
+                        pinned: false,
+                        origin: None,
+                        logprobs: Vec::new(),
+                    }),
+                    usage,
+                )
             } else if usage.is_some() {
                 yield (None, usage)
             }
@@ -542,6 +647,67 @@ where
     }
 }
 
+/// Add top_p, stop sequences, penalties, seed and logprobs from the model config to an OpenAI
+/// compatible request payload
+pub fn add_sampling_params(payload: &mut Value, model_config: &ModelConfig) {
+    let obj = payload.as_object_mut().unwrap();
+    if let Some(top_p) = model_config.top_p {
+        obj.insert("top_p".to_string(), json!(top_p));
+    }
+    if let Some(stop) = &model_config.stop {
+        obj.insert("stop".to_string(), json!(stop));
+    }
+    if let Some(penalty) = model_config.frequency_penalty {
+        obj.insert("frequency_penalty".to_string(), json!(penalty));
+    }
+    if let Some(penalty) = model_config.presence_penalty {
+        obj.insert("presence_penalty".to_string(), json!(penalty));
+    }
+    if let Some(seed) = model_config.seed {
+        obj.insert("seed".to_string(), json!(seed));
+    }
+    if let Some(top_logprobs) = model_config.logprobs {
+        obj.insert("logprobs".to_string(), json!(true));
+        if top_logprobs > 0 {
+            obj.insert("top_logprobs".to_string(), json!(top_logprobs));
+        }
+    }
+}
+
+/// Effort levels that can end a reasoning model's name, as in `o3-mini-high`
+const REASONING_EFFORTS: &[&str] = &["low", "medium", "high"];
+
+fn without_reasoning_effort(model_name: &str) -> (&str, Option<&str>) {
+    match model_name.rsplit_once('-') {
+        Some((base, effort)) if REASONING_EFFORTS.contains(&effort) => (base, Some(effort)),
+        _ => (model_name, None),
+    }
+}
+
+/// Whether a name belongs to an OpenAI reasoning family, ignoring router and gateway prefixes
+fn reasoning_model_family(model_name: &str) -> bool {
+    let name = model_name.rsplit('/').next().unwrap_or(model_name);
+    let name = name.to_lowercase();
+    let name = name
+        .trim_start_matches("databricks-")
+        .trim_start_matches("goose-");
+    let mut chars = name.chars();
+    let o_series = chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit());
+    // gpt-5-chat is the non-reasoning variant
+    o_series || (name.starts_with("gpt-5") && !name.contains("chat"))
+}
+
+/// Whether a model is a reasoning model, which takes `developer` instructions and rejects
+/// sampling parameters. What its provider reported in the model registry wins over its name.
+pub fn is_reasoning_model(model_name: &str) -> bool {
+    let registry = ModelRegistry::global();
+    registry
+        .get_configured(model_name)
+        .or_else(|| registry.get_configured(without_reasoning_effort(model_name).0))
+        .and_then(|info| info.reasoning)
+        .unwrap_or_else(|| reasoning_model_family(model_name))
+}
+
 pub fn create_request(
     model_config: &ModelConfig,
     system: &str,
@@ -555,30 +721,22 @@ pub fn create_request(
         ));
     }
 
-    let is_ox_model = model_config.model_name.starts_with("o");
+    let is_reasoning = is_reasoning_model(&model_config.model_name);
 
-    // Only extract reasoning effort for O1/O3 models
-    let (model_name, reasoning_effort) = if is_ox_model {
-        let parts: Vec<&str> = model_config.model_name.split('-').collect();
-        let last_part = parts.last().unwrap();
-
-        match *last_part {
-            "low" | "medium" | "high" => {
-                let base_name = parts[..parts.len() - 1].join("-");
-                (base_name, Some(last_part.to_string()))
-            }
-            _ => (
-                model_config.model_name.to_string(),
-                Some("medium".to_string()),
-            ),
-        }
+    // Only extract reasoning effort for reasoning models
+    let (model_name, reasoning_effort) = if is_reasoning {
+        let (base_name, effort) = without_reasoning_effort(&model_config.model_name);
+        (
+            base_name.to_string(),
+            Some(effort.unwrap_or("medium").to_string()),
+        )
     } else {
-        // For non-O family models, use the model name as is and no reasoning effort
+        // For other models, use the model name as is and no reasoning effort
         (model_config.model_name.to_string(), None)
     };
 
     let system_message = json!({
-        "role": if is_ox_model { "developer" } else { "system" },
+        "role": if is_reasoning { "developer" } else { "system" },
         "content": system
     });
 
@@ -613,19 +771,25 @@ pub fn create_request(
             .unwrap()
             .insert("tools".to_string(), json!(tools_spec));
     }
-    // o1, o3 models currently don't support temperature
-    if !is_ox_model {
+    // Reasoning models reject temperature and the other sampling parameters
+    if !is_reasoning {
         if let Some(temp) = model_config.temperature {
             payload
                 .as_object_mut()
                 .unwrap()
                 .insert("temperature".to_string(), json!(temp));
         }
+        add_sampling_params(&mut payload, model_config);
+    } else if model_config.temperature.is_some() || model_config.top_p.is_some() {
+        tracing::debug!(
+            "Leaving out sampling parameters, which {} doesn't accept",
+            model_name
+        );
     }
 
-    // o1 models use max_completion_tokens instead of max_tokens
+    // Reasoning models use max_completion_tokens instead of max_tokens
     if let Some(tokens) = model_config.max_tokens {
-        let key = if is_ox_model {
+        let key = if is_reasoning {
             "max_completion_tokens"
         } else {
             "max_tokens"
@@ -638,6 +802,19 @@ pub fn create_request(
     Ok(payload)
 }
 
+/// Tell the model which tool it has to call, when the request offers tools
+pub fn apply_tool_choice(payload: &mut Value, tool_choice: &ToolChoice) {
+    if payload.get("tools").is_none() {
+        return;
+    }
+    payload["tool_choice"] = match tool_choice {
+        ToolChoice::Auto => return,
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool(name) => json!({"type": "function", "function": {"name": name}}),
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -647,6 +824,29 @@ mod tests {
     use tokio::pin;
     use tokio_stream::{self, StreamExt};
 
+    #[test]
+    fn test_get_usage_reports_cached_tokens() {
+        let usage = get_usage(&json!({
+            "prompt_tokens": 2000,
+            "completion_tokens": 100,
+            "total_tokens": 2100,
+            "prompt_tokens_details": {"cached_tokens": 1536}
+        }));
+        assert_eq!(usage.input_tokens, Some(2000));
+        assert_eq!(usage.cache_read_tokens, Some(1536));
+        assert_eq!(
+            get_usage(&json!({"prompt_tokens": 10})).cache_read_tokens,
+            None
+        );
+        let deepseek = get_usage(&json!({
+            "prompt_tokens": 900,
+            "completion_tokens": 50,
+            "prompt_cache_hit_tokens": 640,
+            "prompt_cache_miss_tokens": 260
+        }));
+        assert_eq!(deepseek.cache_read_tokens, Some(640));
+    }
+
     #[test]
     fn test_validate_tool_schemas() {
         // Test case 1: Empty parameters object
@@ -956,6 +1156,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_response_to_message_reasoning_content() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "The user wants a greeting.",
+                    "content": "Hello!"
+                },
+                "finish_reason": "stop"
+            }]
+        });
+        let message = response_to_message(&response)?;
+
+        assert_eq!(message.content.len(), 2);
+        match &message.content[0] {
+            MessageContent::Thinking(thinking) => {
+                assert_eq!(thinking.thinking, "The user wants a greeting.")
+            }
+            other => panic!("Expected thinking first, got {:?}", other),
+        }
+        assert_eq!(message.as_concat_text(), "Hello!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message_stop_reason() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "finish_reason": "length",
+                "message": {
+                    "content": "Truncated answ"
+                }
+            }]
+        });
+        let message = response_to_message(&response)?;
+        assert_eq!(message.stop_reason, Some(StopReason::Length));
+
+        let response = json!({
+            "choices": [{
+                "finish_reason": "stop",
+                "message": {
+                    "content": null,
+                    "refusal": "I can't help with that."
+                }
+            }]
+        });
+        let message = response_to_message(&response)?;
+        assert_eq!(message.stop_reason, Some(StopReason::Refusal));
+        assert_eq!(message.as_concat_text(), "I can't help with that.");
+
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_valid_toolrequest() -> anyhow::Result<()> {
         let response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -1042,10 +1296,19 @@ mod tests {
         let model_config = ModelConfig {
             model_name: "gpt-4o".to_string(),
             context_limit: Some(4096),
+            context_limit_explicit: true,
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            logprobs: None,
             toolshim: false,
             toolshim_model: None,
+            toolshim_provider: None,
+            fallbacks: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1067,16 +1330,136 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_create_request_sampling_params() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new_or_fail("gpt-4o")
+            .with_top_p(Some(0.5))
+            .with_stop(Some(vec!["END".to_string()]))
+            .with_frequency_penalty(Some(0.25))
+            .with_presence_penalty(Some(-0.5))
+            .with_seed(Some(7))
+            .with_logprobs(Some(3));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["top_p"], json!(0.5));
+        assert_eq!(request["stop"], json!(["END"]));
+        assert_eq!(request["frequency_penalty"], json!(0.25));
+        assert_eq!(request["presence_penalty"], json!(-0.5));
+        assert_eq!(request["seed"], json!(7));
+        assert_eq!(request["logprobs"], json!(true));
+        assert_eq!(request["top_logprobs"], json!(3));
+
+        // Reasoning models reject sampling parameters
+        let model_config = ModelConfig::new_or_fail("o3").with_top_p(Some(0.5));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert!(request.get("top_p").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message_logprobs() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": "stop",
+                "logprobs": {
+                    "content": [{
+                        "token": "Hi",
+                        "logprob": -0.01,
+                        "top_logprobs": [
+                            {"token": "Hi", "logprob": -0.01},
+                            {"token": "Hello", "logprob": -4.6}
+                        ]
+                    }]
+                }
+            }]
+        });
+        let message = response_to_message(&response)?;
+        assert_eq!(message.logprobs.len(), 1);
+        assert_eq!(message.logprobs[0].token, "Hi");
+        assert_eq!(message.logprobs[0].top_logprobs[1].token, "Hello");
+        assert!(message.logprobs[0].probability() > 0.98);
+
+        let without = json!({"choices": [{"message": {"content": "Hi"}}]});
+        assert!(response_to_message(&without)?.logprobs.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reasoning_model_family() {
+        for model in [
+            "o1",
+            "o3-mini-high",
+            "o4-mini",
+            "gpt-5",
+            "gpt-5-mini",
+            "openai/o3",
+            "goose-o3",
+        ] {
+            assert!(
+                reasoning_model_family(model),
+                "{} is a reasoning model",
+                model
+            );
+        }
+        for model in [
+            "gpt-4o",
+            "gpt-5-chat-latest",
+            "openchat-3.5",
+            "ollama3",
+            "claude-sonnet-4",
+        ] {
+            assert!(
+                !reasoning_model_family(model),
+                "{} is not a reasoning model",
+                model
+            );
+        }
+        assert_eq!(
+            without_reasoning_effort("o3-mini-high"),
+            ("o3-mini", Some("high"))
+        );
+        assert_eq!(without_reasoning_effort("gpt-5"), ("gpt-5", None));
+    }
+
+    #[test]
+    fn test_create_request_reasoning_model_drops_sampling() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new_or_fail("gpt-5")
+            .with_temperature(Some(0.2))
+            .with_top_p(Some(0.9));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["messages"][0]["role"], "developer");
+        assert_eq!(request["reasoning_effort"], "medium");
+        assert!(request.get("temperature").is_none());
+        assert!(request.get("top_p").is_none());
+
+        let model_config =
+            ModelConfig::new_or_fail("gpt-5-chat-latest").with_temperature(Some(0.5));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["messages"][0]["role"], "system");
+        assert_eq!(request["temperature"], json!(0.5));
+        Ok(())
+    }
+
     #[test]
     fn test_create_request_o1_default() -> anyhow::Result<()> {
         // Test default medium reasoning effort for O1 model
         let model_config = ModelConfig {
             model_name: "o1".to_string(),
             context_limit: Some(4096),
+            context_limit_explicit: true,
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            logprobs: None,
             toolshim: false,
             toolshim_model: None,
+            toolshim_provider: None,
+            fallbacks: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1105,10 +1488,19 @@ mod tests {
         let model_config = ModelConfig {
             model_name: "o3-mini-high".to_string(),
             context_limit: Some(4096),
+            context_limit_explicit: true,
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            logprobs: None,
             toolshim: false,
             toolshim_model: None,
+            toolshim_provider: None,
+            fallbacks: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1180,4 +1572,22 @@ data: [DONE]
 
         panic!("Expected tool call message with two calls, but did not see it");
     }
+
+    #[test]
+    fn test_apply_tool_choice() {
+        let mut payload = json!({"model": "gpt-4o", "tools": [{"type": "function"}]});
+        apply_tool_choice(&mut payload, &ToolChoice::Auto);
+        assert!(payload.get("tool_choice").is_none());
+        apply_tool_choice(&mut payload, &ToolChoice::Required);
+        assert_eq!(payload["tool_choice"], "required");
+        apply_tool_choice(&mut payload, &ToolChoice::Tool("answer".to_string()));
+        assert_eq!(
+            payload["tool_choice"],
+            json!({"type": "function", "function": {"name": "answer"}})
+        );
+
+        let mut without_tools = json!({"model": "gpt-4o"});
+        apply_tool_choice(&mut without_tools, &ToolChoice::None);
+        assert!(without_tools.get("tool_choice").is_none());
+    }
 }
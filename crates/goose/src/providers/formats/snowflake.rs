@@ -59,6 +59,9 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                MessageContent::Citation(_) => {
+                    // Skip
+                }
                 MessageContent::Thinking(_thinking) => {
                     // Skip thinking for now
                 }
@@ -353,6 +356,13 @@ pub fn create_request(
         }
     }
 
+    // Cortex only supports top_p among the additional sampling parameters
+    if let Some(top_p) = model_config.top_p {
+        if let Some(obj) = payload.as_object_mut() {
+            obj.insert("top_p".to_string(), json!(top_p));
+        }
+    }
+
     Ok(payload)
 }
 
@@ -0,0 +1,238 @@
+//! Convert JSON schemas into GBNF grammars, the format llama.cpp uses to constrain decoding.
+//!
+//! A grammar built from a schema only lets the model produce JSON the schema accepts, so local
+//! models answer with valid structured output by construction. The common subset of JSON schema
+//! is covered: objects with properties, arrays, primitives, `enum`, `const`, `anyOf` and `oneOf`.
+//! Anything else, `$ref` included, falls back to accepting any JSON value.
+
+use serde_json::Value;
+
+/// Rules every grammar starts with
+const PRIMITIVE_RULES: &[(&str, &str)] = &[
+    ("ws", r#"([ \t\n] ws)?"#),
+    (
+        "string",
+        r#""\"" ([^"\\\x7F\x00-\x1F] | "\\" (["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F]))* "\"""#,
+    ),
+    (
+        "number",
+        r#""-"? ([0-9] | [1-9] [0-9]*) ("." [0-9]+)? ([eE] [-+]? [0-9]+)?"#,
+    ),
+    ("integer", r#""-"? ([0-9] | [1-9] [0-9]*)"#),
+    ("boolean", r#""true" | "false""#),
+    ("null", r#""null""#),
+    ("value", "object | array | string | number | boolean | null"),
+    (
+        "object",
+        r#""{" ws (string ws ":" ws value (ws "," ws string ws ":" ws value)*)? ws "}""#,
+    ),
+    ("array", r#""[" ws (value (ws "," ws value)*)? ws "]""#),
+];
+
+/// Build a grammar whose `root` rule accepts exactly the JSON values matching `schema`
+pub fn from_json_schema(schema: &Value) -> String {
+    let mut builder = GrammarBuilder::default();
+    let root = builder.visit(schema, "root");
+    builder.rules.insert(0, ("root".to_string(), root));
+
+    builder
+        .rules
+        .iter()
+        .map(|(name, body)| (name.as_str(), body.as_str()))
+        .chain(PRIMITIVE_RULES.iter().copied())
+        .map(|(name, body)| format!("{} ::= {}\n", name, body))
+        .collect()
+}
+
+/// A GBNF string literal matching the JSON encoding of `value`
+fn literal(value: &Value) -> String {
+    let json = serde_json::to_string(value).unwrap_or_default();
+    format!("\"{}\"", json.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Default)]
+struct GrammarBuilder {
+    rules: Vec<(String, String)>,
+}
+
+impl GrammarBuilder {
+    /// Add a rule, named after `hint` but made unique, and return its name
+    fn add_rule(&mut self, hint: &str, body: String) -> String {
+        let base: String = hint
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let taken = |name: &str| {
+            self.rules.iter().any(|(rule, _)| rule == name)
+                || PRIMITIVE_RULES.iter().any(|(rule, _)| *rule == name)
+                || name == "root"
+        };
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while taken(&name) {
+            name = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        self.rules.push((name.clone(), body));
+        name
+    }
+
+    /// The grammar expression for a schema, adding whatever rules it needs
+    fn visit(&mut self, schema: &Value, hint: &str) -> String {
+        if let Some(value) = schema.get("const") {
+            return literal(value);
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            let alternatives: Vec<String> = values.iter().map(literal).collect();
+            return format!("({})", alternatives.join(" | "));
+        }
+        if let Some(options) = schema
+            .get("anyOf")
+            .or_else(|| schema.get("oneOf"))
+            .and_then(Value::as_array)
+        {
+            let alternatives: Vec<String> = options
+                .iter()
+                .enumerate()
+                .map(|(i, option)| self.visit(option, &format!("{}-{}", hint, i)))
+                .collect();
+            return format!("({})", alternatives.join(" | "));
+        }
+
+        match schema.get("type") {
+            Some(Value::String(kind)) => self.visit_type(schema, kind, hint),
+            Some(Value::Array(kinds)) => {
+                let alternatives: Vec<String> = kinds
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|kind| self.visit_type(schema, kind, hint))
+                    .collect();
+                format!("({})", alternatives.join(" | "))
+            }
+            _ if schema.get("properties").is_some() => self.visit_type(schema, "object", hint),
+            _ => "value".to_string(),
+        }
+    }
+
+    fn visit_type(&mut self, schema: &Value, kind: &str, hint: &str) -> String {
+        match kind {
+            "object" => self.visit_object(schema, hint),
+            "array" => match schema.get("items") {
+                Some(items) => {
+                    let item = self.visit(items, &format!("{}-item", hint));
+                    let body = format!(r#""[" ws ({item} (ws "," ws {item})*)? ws "]""#);
+                    self.add_rule(hint, body)
+                }
+                None => "array".to_string(),
+            },
+            "string" | "number" | "integer" | "boolean" | "null" => kind.to_string(),
+            _ => "value".to_string(),
+        }
+    }
+
+    /// Required properties in the order given, then each optional one at most once
+    fn visit_object(&mut self, schema: &Value, hint: &str) -> String {
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            return "object".to_string();
+        };
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut required_pairs = Vec::new();
+        let mut optional_pairs = Vec::new();
+        for (name, property) in properties {
+            let value = self.visit(property, &format!("{}-{}", hint, name));
+            let pair = format!(
+                r#"{} ws ":" ws {}"#,
+                literal(&Value::from(name.as_str())),
+                value
+            );
+            if required.contains(&name.as_str()) {
+                required_pairs.push((name.as_str(), pair));
+            } else {
+                optional_pairs.push(pair);
+            }
+        }
+        required_pairs.sort_by_key(|(name, _)| required.iter().position(|r| r == name));
+        let required_pairs: Vec<String> = required_pairs.into_iter().map(|(_, p)| p).collect();
+
+        let then_optional = |pairs: &[String]| -> String {
+            pairs
+                .iter()
+                .map(|pair| format!(r#" (ws "," ws {})?"#, pair))
+                .collect()
+        };
+        let mut body = required_pairs.join(r#" ws "," ws "#);
+        if body.is_empty() {
+            // With nothing before them, the first optional property present has no comma
+            let chains: Vec<String> = (0..optional_pairs.len())
+                .map(|first| {
+                    let rest = then_optional(&optional_pairs[first + 1..]);
+                    format!("{}{}", optional_pairs[first], rest)
+                })
+                .collect();
+            if !chains.is_empty() {
+                body = format!("({})?", chains.join(" | "));
+            }
+        } else {
+            body.push_str(&then_optional(&optional_pairs));
+        }
+
+        self.add_rule(hint, format!(r#""{{" ws {} ws "}}""#, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule<'a>(grammar: &'a str, name: &str) -> &'a str {
+        grammar
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{} ::= ", name)))
+            .unwrap_or_else(|| panic!("no rule {} in:\n{}", name, grammar))
+    }
+
+    #[test]
+    fn test_required_properties_come_first_in_order() {
+        let grammar = from_json_schema(&json!({
+            "type": "object",
+            "properties": {
+                "a": {"type": "string"},
+                "b": {"type": "integer"},
+                "c": {"type": "boolean"}
+            },
+            "required": ["b", "a"]
+        }));
+
+        assert_eq!(rule(&grammar, "root"), "root-1");
+        assert_eq!(
+            rule(&grammar, "root-1"),
+            r#""{" ws "\"b\"" ws ":" ws integer ws "," ws "\"a\"" ws ":" ws string (ws "," ws "\"c\"" ws ":" ws boolean)? ws "}""#
+        );
+    }
+
+    #[test]
+    fn test_const_and_any_of_become_alternatives() {
+        let grammar = from_json_schema(&json!({
+            "anyOf": [
+                {"type": "object", "properties": {"name": {"const": "shell"}}, "required": ["name"]},
+                {"enum": ["noop", 1]}
+            ]
+        }));
+
+        assert_eq!(rule(&grammar, "root"), r#"(root-0 | ("\"noop\"" | "1"))"#);
+        assert!(rule(&grammar, "root-0").contains(r#""\"name\"" ws ":" ws "\"shell\"""#));
+    }
+
+    #[test]
+    fn test_unsupported_schemas_accept_any_value() {
+        let grammar = from_json_schema(&json!({"$ref": "#/definitions/thing"}));
+        assert_eq!(rule(&grammar, "root"), "value");
+        assert!(grammar.contains("value ::= object | array"));
+    }
+}
@@ -21,14 +21,16 @@ use crate::providers::formats::gcpvertexai::{
 use crate::impl_provider_default;
 use crate::providers::formats::gcpvertexai::GcpLocation::Iowa;
 use crate::providers::gcpauth::GcpAuth;
-use crate::providers::retry::RetryConfig;
-use crate::providers::utils::emit_debug_trace;
+use crate::providers::retry::{notify_retry, RetryEvent, RetryPolicy};
+use crate::providers::utils::{emit_debug_trace, retry_after};
 use rmcp::model::Tool;
 
 /// Base URL for GCP Vertex AI documentation
 const GCP_VERTEX_AI_DOC_URL: &str = "https://cloud.google.com/vertex-ai";
 /// Default timeout for API requests in seconds
 const DEFAULT_TIMEOUT_SECS: u64 = 600;
+/// Location served by the global endpoint, which has no region in its host name
+const GLOBAL_LOCATION: &str = "global";
 /// Default initial interval for retry (in milliseconds)
 const DEFAULT_INITIAL_RETRY_INTERVAL_MS: u64 = 5000;
 /// Default maximum number of retries
@@ -76,7 +78,17 @@ pub struct GcpVertexAIProvider {
     model: ModelConfig,
     /// Retry configuration for handling rate limit errors
     #[serde(skip)]
-    retry_config: RetryConfig,
+    retry_policy: RetryPolicy,
+}
+
+/// The Vertex AI API host for a location: regional endpoints carry the region in the host
+/// name, while the global endpoint (which spreads load across regions) has none
+fn host_for_location(location: &str) -> String {
+    if location == GLOBAL_LOCATION {
+        "https://aiplatform.googleapis.com".to_string()
+    } else {
+        format!("https://{}-aiplatform.googleapis.com", location)
+    }
 }
 
 impl GcpVertexAIProvider {
@@ -107,7 +119,7 @@ impl GcpVertexAIProvider {
         let config = crate::config::Config::global();
         let project_id = config.get_param("GCP_PROJECT_ID")?;
         let location = Self::determine_location(config)?;
-        let host = format!("https://{}-aiplatform.googleapis.com", location);
+        let host = host_for_location(&location);
 
         let client = Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
@@ -116,7 +128,7 @@ impl GcpVertexAIProvider {
         let auth = GcpAuth::new().await?;
 
         // Load optional retry configuration from environment
-        let retry_config = Self::load_retry_config(config);
+        let retry_policy = Self::load_retry_policy(config);
 
         Ok(Self {
             client,
@@ -125,43 +137,19 @@ impl GcpVertexAIProvider {
             project_id,
             location,
             model,
-            retry_config,
+            retry_policy,
         })
     }
 
     /// Loads retry configuration from environment variables or uses defaults.
-    fn load_retry_config(config: &crate::config::Config) -> RetryConfig {
-        // Load max retries for 429 rate limit errors
-        let max_retries = config
-            .get_param("GCP_MAX_RETRIES")
-            .ok()
-            .and_then(|v: String| v.parse::<usize>().ok())
-            .unwrap_or(DEFAULT_MAX_RETRIES);
-
-        let initial_interval_ms = config
-            .get_param("GCP_INITIAL_RETRY_INTERVAL_MS")
-            .ok()
-            .and_then(|v: String| v.parse::<u64>().ok())
-            .unwrap_or(DEFAULT_INITIAL_RETRY_INTERVAL_MS);
-
-        let backoff_multiplier = config
-            .get_param("GCP_BACKOFF_MULTIPLIER")
-            .ok()
-            .and_then(|v: String| v.parse::<f64>().ok())
-            .unwrap_or(DEFAULT_BACKOFF_MULTIPLIER);
-
-        let max_interval_ms = config
-            .get_param("GCP_MAX_RETRY_INTERVAL_MS")
-            .ok()
-            .and_then(|v: String| v.parse::<u64>().ok())
-            .unwrap_or(DEFAULT_MAX_RETRY_INTERVAL_MS);
-
-        RetryConfig::new(
-            max_retries,
-            initial_interval_ms,
-            backoff_multiplier,
-            max_interval_ms,
+    fn load_retry_policy(config: &crate::config::Config) -> RetryPolicy {
+        RetryPolicy::new(
+            DEFAULT_MAX_RETRIES,
+            DEFAULT_INITIAL_RETRY_INTERVAL_MS,
+            DEFAULT_BACKOFF_MULTIPLIER,
+            DEFAULT_MAX_RETRY_INTERVAL_MS,
         )
+        .with_config(config, "GCP")
     }
 
     /// Determines the appropriate GCP location for model deployment.
@@ -198,14 +186,13 @@ impl GcpVertexAIProvider {
     ) -> Result<Url, GcpVertexAIError> {
         // Create host URL for the specified location
         let host_url = if self.location == location {
-            &self.host
+            self.host.clone()
         } else {
-            // Only allocate a new string if location differs
-            &self.host.replace(&self.location, location)
+            host_for_location(location)
         };
 
         let base_url =
-            Url::parse(host_url).map_err(|e| GcpVertexAIError::InvalidUrl(e.to_string()))?;
+            Url::parse(&host_url).map_err(|e| GcpVertexAIError::InvalidUrl(e.to_string()))?;
 
         // Determine endpoint based on provider type
         let endpoint = match provider {
@@ -252,15 +239,15 @@ impl GcpVertexAIProvider {
 
         loop {
             // Check if we've exceeded max retries
-            if rate_limit_attempts > self.retry_config.max_retries
-                && overloaded_attempts > self.retry_config.max_retries
+            if rate_limit_attempts > self.retry_policy.max_retries
+                && overloaded_attempts > self.retry_policy.max_retries
             {
                 let error_msg = format!(
                     "Exceeded maximum retry attempts ({}) for rate limiting errors",
-                    self.retry_config.max_retries
+                    self.retry_policy.max_retries
                 );
                 tracing::error!("{}", error_msg);
-                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg)));
+                return Err(last_error.unwrap_or(ProviderError::rate_limited(error_msg)));
             }
 
             // Get a fresh auth token for each attempt
@@ -286,20 +273,19 @@ impl GcpVertexAIProvider {
                 status if status == StatusCode::TOO_MANY_REQUESTS => {
                     rate_limit_attempts += 1;
 
-                    if rate_limit_attempts > self.retry_config.max_retries {
+                    if rate_limit_attempts > self.retry_policy.max_retries {
                         let error_msg = format!(
                             "Exceeded maximum retry attempts ({}) for rate limiting (429) errors",
-                            self.retry_config.max_retries
+                            self.retry_policy.max_retries
                         );
                         tracing::error!("{}", error_msg);
-                        return Err(
-                            last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg))
-                        );
+                        return Err(last_error.unwrap_or(ProviderError::rate_limited(error_msg)));
                     }
 
                     // Try to parse response for more detailed error info
                     let cite_gcp_vertex_429 =
                         "See https://cloud.google.com/vertex-ai/generative-ai/docs/error-code-429";
+                    let server_delay = retry_after(response.headers());
                     let response_text = response.text().await.unwrap_or_default();
 
                     let error_message =
@@ -314,30 +300,40 @@ impl GcpVertexAIProvider {
                     tracing::warn!(
                         "Rate limit exceeded error (429) (attempt {}/{}): {}. Retrying after backoff...",
                         rate_limit_attempts,
-                        self.retry_config.max_retries,
+                        self.retry_policy.max_retries,
                         error_message
                     );
 
                     // Store the error in case we need to return it after max retries
-                    last_error = Some(ProviderError::RateLimitExceeded(error_message));
+                    let error =
+                        ProviderError::rate_limited(error_message).with_retry_delay(server_delay);
 
                     // Calculate and apply the backoff delay
-                    let delay = self.retry_config.delay_for_attempt(rate_limit_attempts);
+                    let Some(delay) = self.retry_policy.delay_for(rate_limit_attempts, &error)
+                    else {
+                        return Err(error);
+                    };
                     tracing::info!("Backing off for {:?} before retry (rate limit 429)", delay);
+                    notify_retry(RetryEvent {
+                        attempt: rate_limit_attempts,
+                        max_retries: self.retry_policy.max_retries,
+                        delay,
+                        rate_limited: true,
+                        error: error.to_string(),
+                    });
+                    last_error = Some(error);
                     sleep(delay).await;
                 }
                 status if status == *STATUS_API_OVERLOADED => {
                     overloaded_attempts += 1;
 
-                    if overloaded_attempts > self.retry_config.max_retries {
+                    if overloaded_attempts > self.retry_policy.max_retries {
                         let error_msg = format!(
                             "Exceeded maximum retry attempts ({}) for API overloaded (529) errors",
-                            self.retry_config.max_retries
+                            self.retry_policy.max_retries
                         );
                         tracing::error!("{}", error_msg);
-                        return Err(
-                            last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg))
-                        );
+                        return Err(last_error.unwrap_or(ProviderError::rate_limited(error_msg)));
                     }
 
                     // Handle 529 Overloaded error (https://docs.anthropic.com/en/api/errors)
@@ -349,19 +345,26 @@ impl GcpVertexAIProvider {
                     tracing::warn!(
                         "API overloaded error (529) (attempt {}/{}): {}. Retrying after backoff...",
                         overloaded_attempts,
-                        self.retry_config.max_retries,
+                        self.retry_policy.max_retries,
                         error_message
                     );
 
                     // Store the error in case we need to return it after max retries
-                    last_error = Some(ProviderError::RateLimitExceeded(error_message));
+                    last_error = Some(ProviderError::rate_limited(error_message));
 
                     // Calculate and apply the backoff delay
-                    let delay = self.retry_config.delay_for_attempt(overloaded_attempts);
+                    let delay = self.retry_policy.delay_for_attempt(overloaded_attempts);
                     tracing::info!(
                         "Backing off for {:?} before retry (API overloaded 529)",
                         delay
                     );
+                    notify_retry(RetryEvent {
+                        attempt: overloaded_attempts,
+                        max_retries: self.retry_policy.max_retries,
+                        delay,
+                        rate_limited: true,
+                        error: "API overloaded".to_string(),
+                    });
                     sleep(delay).await;
                 }
                 // For any other status codes, process normally
@@ -550,7 +553,7 @@ mod tests {
 
     #[test]
     fn test_retry_config_delay_calculation() {
-        let config = RetryConfig::new(5, 1000, 2.0, 32000);
+        let config = RetryPolicy::new(5, 1000, 2.0, 32000);
 
         // First attempt has no delay
         let delay0 = config.delay_for_attempt(0);
@@ -620,6 +623,18 @@ mod tests {
         assert!(url.as_str().contains("locations/us-east5"));
     }
 
+    #[test]
+    fn test_host_for_location() {
+        assert_eq!(
+            host_for_location("europe-west4"),
+            "https://europe-west4-aiplatform.googleapis.com"
+        );
+        assert_eq!(
+            host_for_location("global"),
+            "https://aiplatform.googleapis.com"
+        );
+    }
+
     #[test]
     fn test_provider_metadata() {
         let metadata = GcpVertexAIProvider::metadata();
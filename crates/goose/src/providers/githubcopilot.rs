@@ -14,7 +14,7 @@ use std::time::Duration;
 use super::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
 
 use crate::config::{Config, ConfigError};
@@ -115,6 +115,8 @@ pub struct GithubCopilotProvider {
     #[serde(skip)]
     mu: tokio::sync::Mutex<RefCell<Option<CopilotState>>>,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(GithubCopilotProvider);
@@ -131,6 +133,7 @@ impl GithubCopilotProvider {
             cache,
             mu,
             model,
+            retry_policy: RetryPolicy::from_config(Config::global(), "GOOSE"),
         })
     }
 
@@ -396,6 +399,10 @@ impl Provider for GithubCopilotProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -1,11 +1,11 @@
 use super::api_client::{ApiClient, AuthMethod};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{emit_debug_trace, handle_response_google_compat, unescape_json_values};
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use crate::providers::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
 use crate::providers::formats::google::{create_request, get_usage, response_to_message};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -49,6 +49,8 @@ pub struct GoogleProvider {
     #[serde(skip)]
     api_client: ApiClient,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(GoogleProvider);
@@ -66,10 +68,15 @@ impl GoogleProvider {
             key: api_key,
         };
 
-        let api_client =
-            ApiClient::new(host, auth)?.with_header("Content-Type", "application/json")?;
+        let api_client = ApiClient::new(host, auth)?
+            .with_header("Content-Type", "application/json")?
+            .with_configured_middleware("google")?;
 
-        Ok(Self { api_client, model })
+        Ok(Self {
+            api_client,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
+        })
     }
 
     async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
@@ -96,6 +103,10 @@ impl Provider for GoogleProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -148,4 +159,24 @@ impl Provider for GoogleProvider {
         models.sort();
         Ok(Some(models))
     }
+
+    /// Fetch context windows from the Google Generative Language API
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let response = self.api_client.response_get("v1beta/models").await?;
+        let json: Value = response.json().await?;
+        let arr = match json.get("models").and_then(|v| v.as_array()) {
+            Some(arr) => arr,
+            None => return Ok(None),
+        };
+        let models = arr
+            .iter()
+            .filter_map(|m| {
+                let name = m.get("name").and_then(|v| v.as_str())?;
+                let name = name.split('/').next_back().unwrap_or(name);
+                let limit = m.get("inputTokenLimit").and_then(|v| v.as_u64())?;
+                Some(ModelInfo::new(name, limit as usize))
+            })
+            .collect();
+        Ok(Some(models))
+    }
 }
@@ -0,0 +1,336 @@
+//! Up-front checks that a provider is usable.
+//!
+//! A bad API key, an unreachable endpoint or a misspelled model otherwise only shows up as an
+//! error on the first real request. A health check walks through them in order and reports
+//! each as a [`Diagnostic`], with a hint on how to fix what failed.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::Provider;
+use crate::providers::errors::ProviderError;
+
+/// How long each request of a health check may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many similarly named models to suggest when the configured one isn't offered
+const MAX_SUGGESTIONS: usize = 5;
+
+/// What a diagnostic is about, in the order they are checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheck {
+    /// The provider and model are set and the provider can be created
+    Configuration,
+    /// The API accepts the credentials
+    Credentials,
+    /// The endpoint answers
+    Reachability,
+    /// The model exists and answers
+    Model,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub check: HealthCheck,
+    pub status: HealthStatus,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn pass(check: HealthCheck, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: HealthStatus::Pass,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn warn(check: HealthCheck, message: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Warn,
+            ..Self::pass(check, message)
+        }
+    }
+
+    pub fn fail(check: HealthCheck, message: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Fail,
+            ..Self::pass(check, message)
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub provider: String,
+    pub model: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.status != HealthStatus::Fail)
+    }
+}
+
+/// Check the configured provider and model
+pub async fn check_configured() -> HealthReport {
+    let config = Config::global();
+    let provider = config.get_param::<String>("GOOSE_PROVIDER").ok();
+    let model = config.get_param::<String>("GOOSE_MODEL").ok();
+    match (provider, model) {
+        (Some(provider), Some(model)) => check(&provider, &model).await,
+        (provider, model) => HealthReport {
+            provider: provider.unwrap_or_default(),
+            model: model.unwrap_or_default(),
+            diagnostics: vec![Diagnostic::fail(
+                HealthCheck::Configuration,
+                "No provider and model are configured",
+            )
+            .with_hint("Run 'goose configure' or set GOOSE_PROVIDER and GOOSE_MODEL")],
+        },
+    }
+}
+
+/// Create a provider for the model and check it
+pub async fn check(provider_name: &str, model_name: &str) -> HealthReport {
+    let mut report = HealthReport {
+        provider: provider_name.to_string(),
+        model: model_name.to_string(),
+        diagnostics: Vec::new(),
+    };
+
+    let provider = ModelConfig::new(model_name)
+        .map_err(anyhow::Error::from)
+        .and_then(|model_config| crate::providers::create(provider_name, model_config));
+    match provider {
+        Ok(provider) => {
+            report.diagnostics.push(Diagnostic::pass(
+                HealthCheck::Configuration,
+                format!("{} is configured", provider_name),
+            ));
+            report.diagnostics.extend(provider.health_check().await);
+        }
+        Err(e) => report.diagnostics.push(
+            Diagnostic::fail(HealthCheck::Configuration, e.to_string())
+                .with_hint(format!("Run 'goose configure' to set up {}", provider_name)),
+        ),
+    }
+    report
+}
+
+/// The default health check: list the provider's models if it can, and otherwise send it a
+/// tiny request, turning whatever goes wrong into a diagnostic
+pub async fn probe<P: Provider + ?Sized>(provider: &P) -> Vec<Diagnostic> {
+    let model = provider.get_model_config().model_name;
+    let mut diagnostics = Vec::new();
+
+    match tokio::time::timeout(PROBE_TIMEOUT, provider.fetch_supported_models()).await {
+        Ok(Ok(Some(models))) => {
+            diagnostics.push(Diagnostic::pass(
+                HealthCheck::Credentials,
+                "The API accepted the credentials",
+            ));
+            diagnostics.push(Diagnostic::pass(
+                HealthCheck::Reachability,
+                format!("Listed {} models", models.len()),
+            ));
+            if models.contains(&model) {
+                diagnostics.push(Diagnostic::pass(
+                    HealthCheck::Model,
+                    format!("{} is available", model),
+                ));
+                return diagnostics;
+            }
+            let suggestions = similar_models(&model, &models);
+            let missing = Diagnostic::fail(
+                HealthCheck::Model,
+                format!("{} is not among the provider's models", model),
+            );
+            diagnostics.push(if suggestions.is_empty() {
+                missing.with_hint("Run 'goose configure' to pick an available model")
+            } else {
+                missing.with_hint(format!("Did you mean {}?", suggestions.join(", ")))
+            });
+            return diagnostics;
+        }
+        Ok(Ok(None)) => {}
+        Ok(Err(e)) => {
+            let diagnostic = diagnose(&e, &model);
+            if diagnostic.check != HealthCheck::Model {
+                return vec![diagnostic];
+            }
+        }
+        Err(_) => return vec![timed_out()],
+    }
+
+    // Nothing to list the models with, so ask the model itself
+    let messages = [Message::user().with_text("Reply with OK.")];
+    match tokio::time::timeout(
+        PROBE_TIMEOUT,
+        provider.complete("You are a health check.", &messages, &[]),
+    )
+    .await
+    {
+        Ok(Ok(_)) => vec![
+            Diagnostic::pass(HealthCheck::Credentials, "The API accepted the credentials"),
+            Diagnostic::pass(HealthCheck::Reachability, "The endpoint answered"),
+            Diagnostic::pass(HealthCheck::Model, format!("{} answered", model)),
+        ],
+        Ok(Err(e)) => {
+            let failure = diagnose(&e, &model);
+            // Everything before the failing check worked
+            let mut diagnostics: Vec<Diagnostic> =
+                [HealthCheck::Credentials, HealthCheck::Reachability]
+                    .into_iter()
+                    .filter(|check| *check < failure.check)
+                    .map(|check| Diagnostic::pass(check, "OK"))
+                    .collect();
+            diagnostics.push(failure);
+            diagnostics
+        }
+        Err(_) => vec![timed_out()],
+    }
+}
+
+/// Which check a provider error fails, with a hint for fixing it
+pub fn diagnose(error: &ProviderError, model: &str) -> Diagnostic {
+    match error {
+        ProviderError::Authentication(message) => {
+            Diagnostic::fail(HealthCheck::Credentials, message.clone())
+                .with_hint("Check the API key with 'goose configure'")
+        }
+        ProviderError::RateLimitExceeded { details, .. } => Diagnostic::warn(
+            HealthCheck::Reachability,
+            format!("Reachable, but rate limited: {}", details),
+        ),
+        ProviderError::ContextLengthExceeded(message) => Diagnostic::warn(
+            HealthCheck::Model,
+            format!("The model answered with an error: {}", message),
+        ),
+        other => match other.status() {
+            Some(401) | Some(403) => Diagnostic::fail(HealthCheck::Credentials, other.to_string())
+                .with_hint("Check the API key with 'goose configure'"),
+            Some(404) => Diagnostic::fail(
+                HealthCheck::Model,
+                format!("{} was not found: {}", model, other),
+            )
+            .with_hint("Run 'goose configure' to pick an available model"),
+            Some(status) if status >= 500 => {
+                Diagnostic::fail(HealthCheck::Reachability, other.to_string())
+                    .with_hint("The provider is having trouble; try again later")
+            }
+            Some(_) => Diagnostic::fail(HealthCheck::Model, other.to_string()),
+            None => match other {
+                ProviderError::RequestFailed(_) | ProviderError::ServerError(_) => {
+                    Diagnostic::fail(HealthCheck::Reachability, other.to_string())
+                        .with_hint("Check the host setting and your network connection")
+                }
+                _ => Diagnostic::fail(HealthCheck::Model, other.to_string()),
+            },
+        },
+    }
+}
+
+fn timed_out() -> Diagnostic {
+    Diagnostic::fail(
+        HealthCheck::Reachability,
+        format!("No answer within {} seconds", PROBE_TIMEOUT.as_secs()),
+    )
+    .with_hint("Check the host setting and your network connection")
+}
+
+/// Models whose names share a part with the configured one
+fn similar_models(model: &str, models: &[String]) -> Vec<String> {
+    let model = model.to_lowercase();
+    let parts: Vec<&str> = model
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| part.len() > 2)
+        .collect();
+    models
+        .iter()
+        .filter(|candidate| {
+            let candidate = candidate.to_lowercase();
+            candidate.contains(&model) || parts.iter().any(|part| candidate.contains(part))
+        })
+        .take(MAX_SUGGESTIONS)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let auth = diagnose(&ProviderError::Authentication("bad key".into()), "gpt-4o");
+        assert_eq!(auth.check, HealthCheck::Credentials);
+        assert_eq!(auth.status, HealthStatus::Fail);
+
+        let missing = diagnose(
+            &ProviderError::RequestFailed("Request failed with status: 404 Not Found".into()),
+            "gpt-5o",
+        );
+        assert_eq!(missing.check, HealthCheck::Model);
+
+        let offline = diagnose(
+            &ProviderError::RequestFailed("error sending request".into()),
+            "gpt-4o",
+        );
+        assert_eq!(offline.check, HealthCheck::Reachability);
+
+        let limited = diagnose(&ProviderError::rate_limited("slow down"), "gpt-4o");
+        assert_eq!(limited.status, HealthStatus::Warn);
+    }
+
+    #[test]
+    fn test_similar_models() {
+        let models = vec![
+            "gpt-4o".to_string(),
+            "gpt-4o-mini".to_string(),
+            "o3".to_string(),
+        ];
+        assert_eq!(similar_models("gpt4o-mini", &models), vec!["gpt-4o-mini"]);
+        assert!(similar_models("claude", &models).is_empty());
+    }
+
+    #[test]
+    fn test_report_health() {
+        let report = HealthReport {
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            diagnostics: vec![
+                Diagnostic::pass(HealthCheck::Configuration, "ok"),
+                Diagnostic::warn(HealthCheck::Reachability, "rate limited"),
+            ],
+        };
+        assert!(report.is_healthy());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["diagnostics"][1]["check"], "reachability");
+        assert!(json["diagnostics"][0].get("hint").is_none());
+    }
+}
@@ -0,0 +1,507 @@
+use std::io;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use rmcp::model::{Role, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::pin;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{FramedRead, LinesCodec};
+use tokio_util::io::StreamReader;
+
+use super::api_client::{ApiClient, AuthMethod, AuthProvider};
+use super::base::{
+    ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use super::errors::ProviderError;
+use super::formats::openai::{
+    create_request, get_usage, response_to_message, response_to_streaming_message,
+};
+use super::retry::{ProviderRetry, RetryPolicy};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
+    ImageFormat,
+};
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+
+/// TGI serves one model and ignores the name in chat requests
+pub const HUGGINGFACE_DEFAULT_MODEL: &str = "tgi";
+pub const HUGGINGFACE_DOC_URL: &str = "https://huggingface.co/docs/inference-endpoints/index";
+/// Scaled-to-zero endpoints can take minutes to wake up
+pub const HUGGINGFACE_DEFAULT_TIMEOUT: u64 = 600;
+/// Tokens generated per request on the native API when no max_tokens is configured
+const DEFAULT_MAX_NEW_TOKENS: i32 = 2048;
+
+const CHAT_PATH: &str = "v1/chat/completions";
+const GENERATE_PATH: &str = "generate";
+const GENERATE_STREAM_PATH: &str = "generate_stream";
+const INFO_PATH: &str = "info";
+
+/// Which of the server's APIs requests go to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HuggingFaceApi {
+    /// The OpenAI compatible Messages API, with native tool calling
+    #[default]
+    Chat,
+    /// TGI's own generate API, for servers without a chat template
+    Generate,
+}
+
+/// Hugging Face Inference Endpoints and self-hosted Text Generation Inference servers
+#[derive(serde::Serialize)]
+pub struct HuggingFaceProvider {
+    #[serde(skip)]
+    api_client: ApiClient,
+    api: HuggingFaceApi,
+    model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
+}
+
+impl_provider_default!(HuggingFaceProvider);
+
+/// Self-hosted TGI servers usually run without a token
+struct NoAuth;
+
+#[async_trait]
+impl AuthProvider for NoAuth {
+    async fn get_auth_header(&self) -> Result<(String, String)> {
+        Ok(("X-No-Auth".to_string(), "true".to_string()))
+    }
+}
+
+impl HuggingFaceProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let host: String = config.get_param("HF_ENDPOINT_URL").map_err(|_| {
+            anyhow::anyhow!("HF_ENDPOINT_URL is required for the Hugging Face provider")
+        })?;
+        let auth = match config.get_secret::<String>("HF_TOKEN") {
+            Ok(token) if !token.is_empty() => AuthMethod::BearerToken(token),
+            _ => AuthMethod::Custom(Box::new(NoAuth)),
+        };
+        let api = config
+            .get_param::<HuggingFaceApi>("HF_API")
+            .unwrap_or_default();
+        let timeout_secs: u64 = config
+            .get_param("HF_TIMEOUT")
+            .unwrap_or(HUGGINGFACE_DEFAULT_TIMEOUT);
+
+        let api_client = ApiClient::with_timeout(host, auth, Duration::from_secs(timeout_secs))?
+            .with_configured_middleware("huggingface")?;
+
+        Ok(Self {
+            api_client,
+            api,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
+        })
+    }
+
+    async fn post(&self, path: &str, payload: &Value) -> Result<Value, ProviderError> {
+        let response = self.api_client.response_post(path, payload).await?;
+        handle_response_openai_compat(response).await
+    }
+
+    /// A request to the native generate API
+    fn generate_request(&self, system: &str, messages: &[Message]) -> Value {
+        let mut parameters = json!({
+            "max_new_tokens": self.model.max_tokens.unwrap_or(DEFAULT_MAX_NEW_TOKENS),
+            "return_full_text": false,
+            "details": true,
+            // Keep the model from writing the user's next turn
+            "stop": ["\nUser:"],
+        });
+        let params = parameters.as_object_mut().unwrap();
+        if let Some(temperature) = self.model.temperature.filter(|t| *t > 0.0) {
+            params.insert("temperature".to_string(), json!(temperature));
+            params.insert("do_sample".to_string(), json!(true));
+        }
+        if let Some(top_p) = self.model.top_p {
+            params.insert("top_p".to_string(), json!(top_p));
+        }
+        if let Some(stop) = &self.model.stop {
+            params["stop"]
+                .as_array_mut()
+                .unwrap()
+                .extend(stop.iter().map(|s| json!(s)));
+        }
+        if let Some(penalty) = self.model.frequency_penalty {
+            params.insert("frequency_penalty".to_string(), json!(penalty));
+        }
+        if let Some(seed) = self.model.seed {
+            params.insert("seed".to_string(), json!(seed));
+        }
+
+        json!({
+            "inputs": render_prompt(system, messages),
+            "parameters": parameters,
+        })
+    }
+
+    async fn complete_chat(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        let response = self.with_retry(|| self.post(CHAT_PATH, &payload)).await?;
+
+        let message = response_to_message(&response)?;
+        let usage = response.get("usage").map(get_usage).unwrap_or_else(|| {
+            tracing::debug!("Failed to get usage data");
+            Usage::default()
+        });
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    async fn complete_generate(
+        &self,
+        system: &str,
+        messages: &[Message],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self.generate_request(system, messages);
+        let response = self
+            .with_retry(|| self.post(GENERATE_PATH, &payload))
+            .await?;
+
+        // Servers answer with an object, or a single element list on older versions
+        let result = match &response {
+            Value::Array(results) => results.first().cloned().unwrap_or_default(),
+            _ => response.clone(),
+        };
+        let text = result
+            .get("generated_text")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                ProviderError::RequestFailed("No generated_text in response".to_string())
+            })?;
+        let usage = generate_usage(result.get("details"));
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((
+            Message::assistant().with_text(text.trim()),
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+
+    async fn stream_chat(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        payload["stream"] = Value::Bool(true);
+        payload["stream_options"] = json!({ "include_usage": true });
+
+        let response = self
+            .with_retry(|| async {
+                let response = self.api_client.response_post(CHAT_PATH, &payload).await?;
+                handle_status_openai_compat(response).await
+            })
+            .await?;
+        let stream = response.bytes_stream().map_err(io::Error::other);
+        let model_config = self.model.clone();
+
+        Ok(Box::pin(try_stream! {
+            let stream_reader = StreamReader::new(stream);
+            let framed = FramedRead::new(stream_reader, LinesCodec::new()).map_err(anyhow::Error::from);
+
+            let message_stream = response_to_streaming_message(framed);
+            pin!(message_stream);
+            while let Some(message) = message_stream.next().await {
+                let (message, usage) = message.map_err(|e| ProviderError::RequestFailed(format!("Stream decode error: {}", e)))?;
+                emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
+                yield (message, usage);
+            }
+        }))
+    }
+
+    async fn stream_generate(
+        &self,
+        system: &str,
+        messages: &[Message],
+    ) -> Result<MessageStream, ProviderError> {
+        let payload = self.generate_request(system, messages);
+        let response = self
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post(GENERATE_STREAM_PATH, &payload)
+                    .await?;
+                handle_status_openai_compat(response).await
+            })
+            .await?;
+        let stream = response.bytes_stream().map_err(io::Error::other);
+        let model_config = self.model.clone();
+        let message_id = format!("msg_{}", uuid::Uuid::new_v4().simple());
+
+        Ok(Box::pin(try_stream! {
+            let stream_reader = StreamReader::new(stream);
+            let mut lines = FramedRead::new(stream_reader, LinesCodec::new());
+            let mut text = String::new();
+            while let Some(line) = lines.next().await {
+                let line = line.map_err(|e| ProviderError::RequestFailed(format!("Stream decode error: {}", e)))?;
+                let Some(event) = parse_generate_event(&line)? else {
+                    continue;
+                };
+                if !event.token.is_empty() {
+                    text.push_str(&event.token);
+                    let message = Message::assistant()
+                        .with_text(event.token)
+                        .with_id(message_id.clone());
+                    yield (Some(message), None);
+                }
+                if let Some(usage) = event.usage {
+                    emit_debug_trace(&model_config, &payload, &json!({ "generated_text": text }), &usage);
+                    yield (None, Some(ProviderUsage::new(model_config.model_name.clone(), usage)));
+                }
+            }
+        }))
+    }
+}
+
+/// Render the conversation as a plain transcript for servers that don't apply a chat template
+fn render_prompt(system: &str, messages: &[Message]) -> String {
+    let mut prompt = String::new();
+    if !system.is_empty() {
+        prompt.push_str(&format!("System: {}\n\n", system));
+    }
+    for message in messages {
+        let text = message.as_concat_text();
+        if text.is_empty() {
+            continue;
+        }
+        let role = match message.role {
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+        };
+        prompt.push_str(&format!("{}: {}\n\n", role, text));
+    }
+    prompt.push_str("Assistant:");
+    prompt
+}
+
+/// Token counts from a generate response's details
+fn generate_usage(details: Option<&Value>) -> Usage {
+    let count = |key: &str| {
+        details
+            .and_then(|d| d.get(key))
+            .and_then(|v| v.as_u64())
+            .map(|n| n as i32)
+    };
+    let output = count("generated_tokens");
+    // The prompt's tokens are only listed when the server was asked for decoder details
+    let input = details
+        .and_then(|d| d.get("prefill"))
+        .and_then(|v| v.as_array())
+        .filter(|prefill| !prefill.is_empty())
+        .map(|prefill| prefill.len() as i32);
+    let total = input.zip(output).map(|(input, output)| input + output);
+    Usage::new(input, output, total.or(output))
+}
+
+struct GenerateEvent {
+    token: String,
+    /// Set on the last event
+    usage: Option<Usage>,
+}
+
+/// One server-sent event from generate_stream; None for keep-alives and blank lines
+fn parse_generate_event(line: &str) -> Result<Option<GenerateEvent>, ProviderError> {
+    let Some(data) = line.strip_prefix("data:") else {
+        return Ok(None);
+    };
+    let event: Value = serde_json::from_str(data.trim()).map_err(|e| {
+        ProviderError::RequestFailed(format!("Failed to parse stream event: {}", e))
+    })?;
+    if let Some(error) = event.get("error").and_then(|v| v.as_str()) {
+        return Err(ProviderError::ServerError(error.to_string()));
+    }
+
+    let token = event.get("token");
+    let special = token
+        .and_then(|t| t.get("special"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let token = token
+        .and_then(|t| t.get("text"))
+        .and_then(|v| v.as_str())
+        .filter(|_| !special)
+        .unwrap_or_default()
+        .to_string();
+    let usage = event
+        .get("details")
+        .filter(|details| !details.is_null())
+        .map(|details| generate_usage(Some(details)));
+    Ok(Some(GenerateEvent { token, usage }))
+}
+
+/// The model and context window from a TGI server's /info
+fn parse_info(info: &Value) -> Option<(String, usize)> {
+    let model_id = info.get("model_id").and_then(|v| v.as_str())?;
+    // Newer servers report max_total_tokens as the whole window; older ones only the input
+    let limit = ["max_total_tokens", "max_input_tokens", "max_input_length"]
+        .iter()
+        .find_map(|key| info.get(*key).and_then(|v| v.as_u64()))?;
+    Some((model_id.to_string(), limit as usize))
+}
+
+#[async_trait]
+impl Provider for HuggingFaceProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "huggingface",
+            "Hugging Face",
+            "Models on Hugging Face Inference Endpoints or any Text Generation Inference server",
+            HUGGINGFACE_DEFAULT_MODEL,
+            vec![HUGGINGFACE_DEFAULT_MODEL],
+            HUGGINGFACE_DOC_URL,
+            vec![
+                ConfigKey::new("HF_ENDPOINT_URL", true, false, None),
+                ConfigKey::new("HF_TOKEN", false, true, None),
+                ConfigKey::new("HF_API", false, false, Some("chat")),
+                ConfigKey::new(
+                    "HF_TIMEOUT",
+                    false,
+                    false,
+                    Some(&HUGGINGFACE_DEFAULT_TIMEOUT.to_string()),
+                ),
+            ],
+        )
+    }
+
+    /// The configured model, with toolshim on for the generate API, which has no tool calling,
+    /// unless GOOSE_TOOLSHIM says otherwise
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        let mut model = self.model.clone();
+        if self.api == HuggingFaceApi::Generate && std::env::var("GOOSE_TOOLSHIM").is_err() {
+            model.toolshim = true;
+        }
+        model
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        match self.api {
+            HuggingFaceApi::Chat => self.complete_chat(system, messages, tools).await,
+            HuggingFaceApi::Generate => self.complete_generate(system, messages).await,
+        }
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        match self.api {
+            HuggingFaceApi::Chat => self.stream_chat(system, messages, tools).await,
+            HuggingFaceApi::Generate => self.stream_generate(system, messages).await,
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    /// The served model's context window, from the server's /info
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let response = self.api_client.response_get(INFO_PATH).await?;
+        let info = handle_response_openai_compat(response).await?;
+        let Some((model_id, context_limit)) = parse_info(&info) else {
+            return Ok(None);
+        };
+        // The server answers to any name, so the configured one gets the limit too
+        let mut models = vec![ModelInfo::new(model_id.clone(), context_limit)];
+        if self.model.model_name != model_id {
+            models.push(ModelInfo::new(self.model.model_name.clone(), context_limit));
+        }
+        Ok(Some(models))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prompt() {
+        let messages = vec![
+            Message::user().with_text("What is 2 + 2?"),
+            Message::assistant().with_text("4"),
+            Message::user().with_text("And 3 + 3?"),
+        ];
+        assert_eq!(
+            render_prompt("Be brief.", &messages),
+            "System: Be brief.\n\nUser: What is 2 + 2?\n\nAssistant: 4\n\nUser: And 3 + 3?\n\nAssistant:"
+        );
+    }
+
+    #[test]
+    fn test_parse_generate_events() {
+        let event = parse_generate_event(
+            r#"data:{"index":1,"token":{"id":415,"text":" Hello","logprob":-0.1,"special":false},"generated_text":null,"details":null}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.token, " Hello");
+        assert!(event.usage.is_none());
+
+        let event = parse_generate_event(
+            r#"data: {"index":2,"token":{"id":2,"text":"</s>","logprob":0.0,"special":true},"generated_text":" Hello","details":{"finish_reason":"eos_token","generated_tokens":2,"seed":null}}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.token, "");
+        assert_eq!(event.usage.unwrap().output_tokens, Some(2));
+
+        assert!(parse_generate_event(":keep-alive").unwrap().is_none());
+        assert!(matches!(
+            parse_generate_event(
+                r#"data: {"error":"Input validation error","error_type":"validation"}"#
+            ),
+            Err(ProviderError::ServerError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let info = json!({
+            "model_id": "meta-llama/Llama-3.1-8B-Instruct",
+            "max_input_tokens": 8191,
+            "max_total_tokens": 8192
+        });
+        assert_eq!(
+            parse_info(&info),
+            Some(("meta-llama/Llama-3.1-8B-Instruct".to_string(), 8192))
+        );
+
+        let older = json!({"model_id": "bigcode/starcoder", "max_input_length": 4096});
+        assert_eq!(parse_info(&older).unwrap().1, 4096);
+        assert!(parse_info(&json!({"model_id": "x"})).is_none());
+    }
+}
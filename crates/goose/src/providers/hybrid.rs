@@ -0,0 +1,454 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::base::{
+    stream_from_single_message, FallbackProviderTrait, LeadWorkerProviderTrait, MessageStream,
+    ModelInfo, Provider, ProviderMetadata, ProviderUsage, RequestTarget,
+};
+use super::errors::ProviderError;
+use super::retry::RetryPolicy;
+use crate::config::{Config, ConfigError};
+use crate::conversation::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use rmcp::model::{Role, Tool};
+
+/// Config key for routing sensitive data to a local model, e.g. in config.yaml:
+///
+/// ```yaml
+/// GOOSE_DATA_ROUTING:
+///   local_provider: ollama
+///   local_model: qwen2.5-coder:14b
+///   sensitive_paths: ["**/.env", "*.pem", "secrets/**"]
+///   sensitive_tags: ["confidential"]
+/// ```
+pub const DATA_ROUTING_KEY: &str = "GOOSE_DATA_ROUTING";
+
+/// Tracing target of the per-request routing decisions, for auditing where data was sent
+pub const ROUTING_LOG_TARGET: &str = "goose::data_routing";
+
+/// Which requests must stay on a local model
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataRoutingConfig {
+    /// Provider of the local model, e.g. `ollama`
+    pub local_provider: String,
+    /// The local model that sensitive requests go to
+    pub local_model: String,
+    /// Globs of sensitive files. Patterns without a `/` match file names anywhere, like
+    /// `*.pem`; others match the end of a path, like `secrets/**`
+    #[serde(default)]
+    pub sensitive_paths: Vec<String>,
+    /// Words that mark a request as sensitive when the user writes them as `#tag`
+    #[serde(default)]
+    pub sensitive_tags: Vec<String>,
+}
+
+impl DataRoutingConfig {
+    /// The configured routing, if any
+    pub fn from_config() -> Option<Self> {
+        match Config::global().get_param::<DataRoutingConfig>(DATA_ROUTING_KEY) {
+            Ok(routing) => Some(routing),
+            Err(ConfigError::NotFound(_)) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring {}: {}", DATA_ROUTING_KEY, e);
+                None
+            }
+        }
+    }
+}
+
+/// Decides whether a request carries sensitive data
+#[derive(Debug, Clone)]
+pub struct DataClassifier {
+    paths: Vec<Pattern>,
+    tags: Vec<String>,
+}
+
+impl DataClassifier {
+    pub fn new(config: &DataRoutingConfig) -> Result<Self> {
+        let paths = config
+            .sensitive_paths
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern.trim())
+                    .map_err(|e| anyhow::anyhow!("Invalid sensitive path '{}': {}", pattern, e))
+            })
+            .collect::<Result<_>>()?;
+        let tags = config
+            .sensitive_tags
+            .iter()
+            .map(|tag| format!("#{}", tag.trim().trim_start_matches('#').to_lowercase()))
+            .collect();
+        Ok(Self { paths, tags })
+    }
+
+    /// Why the messages are sensitive, or None if they can go to the cloud model.
+    ///
+    /// The whole history is checked, not just the latest turn, since every request carries
+    /// it: once a sensitive file has been read, later requests stay local until it has been
+    /// compacted away.
+    pub fn classify(&self, messages: &[Message]) -> Option<String> {
+        messages.iter().rev().find_map(|message| {
+            message
+                .content
+                .iter()
+                .find_map(|content| self.classify_content(&message.role, content))
+        })
+    }
+
+    fn classify_content(&self, role: &Role, content: &MessageContent) -> Option<String> {
+        match content {
+            MessageContent::Text(text) => {
+                if *role == Role::User {
+                    let lower = text.text.to_lowercase();
+                    if let Some(tag) = self.tags.iter().find(|tag| has_tag(&lower, tag)) {
+                        return Some(format!("the user tagged the request {}", tag));
+                    }
+                }
+                self.find_path(&text.text)
+            }
+            MessageContent::ToolRequest(request) => {
+                let call = request.tool_call.as_ref().ok()?;
+                let mut strings = Vec::new();
+                collect_strings(&call.arguments, &mut strings);
+                strings
+                    .iter()
+                    .find_map(|s| self.find_path(s))
+                    .map(|reason| format!("{} (in a {} call)", reason, call.name))
+            }
+            MessageContent::ToolResponse(response) => response
+                .tool_result
+                .as_ref()
+                .ok()?
+                .iter()
+                .filter_map(|content| content.as_text())
+                .find_map(|text| self.find_path(&text.text)),
+            _ => None,
+        }
+    }
+
+    /// The first word of `text` that names a sensitive path
+    fn find_path(&self, text: &str) -> Option<String> {
+        if self.paths.is_empty() {
+            return None;
+        }
+        text.split(|c: char| c.is_whitespace() || "\"'`,;:()[]{}<>|=".contains(c))
+            .filter(|word| !word.is_empty())
+            .find_map(|word| {
+                self.paths
+                    .iter()
+                    .find(|pattern| path_matches(pattern, word))
+                    .map(|pattern| format!("{} matches {}", word, pattern.as_str()))
+            })
+    }
+}
+
+fn has_tag(text: &str, tag: &str) -> bool {
+    text.match_indices(tag).any(|(start, _)| {
+        text[start + tag.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !c.is_alphanumeric() && c != '_' && c != '-')
+    })
+}
+
+/// Match a file name pattern against the last component of the path, and any other pattern
+/// against the path and each of its trailing parts
+fn path_matches(pattern: &Pattern, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    if !pattern.as_str().contains('/') {
+        return path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| pattern.matches(name));
+    }
+    pattern.matches(path)
+        || path
+            .match_indices('/')
+            .any(|(index, _)| pattern.matches(&path[index + 1..]))
+}
+
+fn collect_strings<'a>(value: &'a Value, strings: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => strings.push(s),
+        Value::Array(values) => values.iter().for_each(|v| collect_strings(v, strings)),
+        Value::Object(map) => map.values().for_each(|v| collect_strings(v, strings)),
+        _ => {}
+    }
+}
+
+/// A provider that keeps sensitive data on a local model: requests whose history touches a
+/// sensitive file or carries a sensitive tag go to the local provider, everything else to the
+/// cloud provider. Each decision is logged under [`ROUTING_LOG_TARGET`].
+pub struct HybridProvider {
+    cloud: Arc<dyn Provider>,
+    local: Arc<dyn Provider>,
+    classifier: DataClassifier,
+    /// Factory names of the cloud and local providers, for [`Provider::last_request_target`]
+    provider_names: Option<(String, String)>,
+    /// Whether the latest request went to the local provider
+    routed_local: AtomicBool,
+}
+
+impl HybridProvider {
+    pub fn new(
+        cloud: Arc<dyn Provider>,
+        local: Arc<dyn Provider>,
+        classifier: DataClassifier,
+    ) -> Self {
+        Self {
+            cloud,
+            local,
+            classifier,
+            provider_names: None,
+            routed_local: AtomicBool::new(false),
+        }
+    }
+
+    /// Name the cloud and local providers as the factory knows them
+    pub fn with_provider_names(mut self, cloud: &str, local: &str) -> Self {
+        self.provider_names = Some((cloud.to_string(), local.to_string()));
+        self
+    }
+
+    /// The provider for these messages, logging the decision
+    fn route(&self, messages: &[Message]) -> &Arc<dyn Provider> {
+        let (provider, destination, reason) = match self.classifier.classify(messages) {
+            Some(reason) => (&self.local, "local", reason),
+            None => (&self.cloud, "cloud", "no sensitive data".to_string()),
+        };
+        let model = provider.get_model_config().model_name;
+        tracing::info!(
+            target: ROUTING_LOG_TARGET,
+            destination,
+            model = model.as_str(),
+            reason = reason.as_str(),
+            "Routing request to the {} model {}: {}",
+            destination,
+            model,
+            reason
+        );
+        super::base::set_current_model(&model);
+        self.routed_local
+            .store(destination == "local", Ordering::Relaxed);
+        provider
+    }
+}
+
+#[async_trait]
+impl Provider for HybridProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "hybrid",
+            "Hybrid Provider",
+            "A provider that sends requests touching sensitive data to a local model",
+            "",     // No default model as this is determined by the wrapped providers
+            vec![], // No known models as this depends on wrapped providers
+            "",     // No doc link
+            vec![], // Configured through GOOSE_DATA_ROUTING
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.cloud.get_model_config()
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.cloud.retry_policy()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.route(messages).complete(system, messages, tools).await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let provider = self.route(messages);
+        if provider.supports_streaming() {
+            provider.stream(system, messages, tools).await
+        } else {
+            let (message, usage) = provider.complete(system, messages, tools).await?;
+            Ok(stream_from_single_message(message, usage))
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.cloud.supports_streaming() || self.local.supports_streaming()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.cloud.fetch_supported_models().await
+    }
+
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        self.cloud.fetch_model_info().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.cloud.supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.cloud.create_embeddings(texts).await
+    }
+
+    fn last_request_target(&self) -> RequestTarget {
+        let names = self.provider_names.as_ref();
+        if self.routed_local.load(Ordering::Relaxed) {
+            self.local
+                .last_request_target()
+                .with_provider(names.map(|(_, local)| local.as_str()))
+        } else {
+            self.cloud
+                .last_request_target()
+                .with_provider(names.map(|(cloud, _)| cloud.as_str()))
+        }
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.cloud.as_lead_worker()
+    }
+
+    fn as_fallback(&self) -> Option<&dyn FallbackProviderTrait> {
+        self.cloud.as_fallback()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use mcp_core::ToolCall;
+    use rmcp::model::Content;
+    use serde_json::json;
+
+    struct MockProvider {
+        name: &'static str,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail(self.name)
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(self.name),
+                ProviderUsage::new(self.name.to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn classifier() -> DataClassifier {
+        DataClassifier::new(&DataRoutingConfig {
+            local_provider: "ollama".to_string(),
+            local_model: "qwen2.5".to_string(),
+            sensitive_paths: vec![
+                "**/.env".to_string(),
+                "*.pem".to_string(),
+                "secrets/**".to_string(),
+            ],
+            sensitive_tags: vec!["confidential".to_string()],
+        })
+        .unwrap()
+    }
+
+    fn shell(command: &str) -> Message {
+        Message::assistant().with_tool_request(
+            "call_1",
+            Ok(ToolCall::new(
+                "developer__shell",
+                json!({ "command": command }),
+            )),
+        )
+    }
+
+    #[test]
+    fn test_classify_paths_and_tags() {
+        let classifier = classifier();
+        let routine = vec![
+            Message::user().with_text("Fix the failing test in src/lib.rs"),
+            shell("cargo test"),
+        ];
+        assert_eq!(classifier.classify(&routine), None);
+
+        let reason = classifier
+            .classify(&[shell("cat /home/me/project/.env")])
+            .unwrap();
+        assert!(reason.contains(".env"));
+        assert!(reason.contains("developer__shell"));
+
+        assert!(classifier
+            .classify(&[shell("openssl x509 -in certs/server.pem")])
+            .is_some());
+        assert!(classifier
+            .classify(&[shell("ls config/secrets/api.json")])
+            .is_some());
+        assert!(classifier
+            .classify(&[shell("ls mysecrets/api.json")])
+            .is_none());
+
+        let tagged = Message::user().with_text("Summarize the #Confidential roadmap");
+        assert!(classifier.classify(&[tagged]).is_some());
+        let untagged = Message::user().with_text("Summarize the #confidentiality policy");
+        assert!(classifier.classify(&[untagged]).is_none());
+
+        let output = Message::user().with_tool_response(
+            "call_1",
+            Ok(vec![Content::text(
+                "deploy/secrets/db.yaml:password: hunter2",
+            )]),
+        );
+        assert!(classifier.classify(&[output]).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_routes_sensitive_requests_locally() {
+        let provider = HybridProvider::new(
+            Arc::new(MockProvider { name: "cloud" }),
+            Arc::new(MockProvider { name: "local" }),
+            classifier(),
+        )
+        .with_provider_names("openai", "ollama");
+
+        let routine = vec![Message::user().with_text("What does this repo do?")];
+        let (message, _) = provider.complete("system", &routine, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "cloud");
+
+        let sensitive = vec![
+            Message::user().with_text("Check my settings"),
+            shell("cat .env"),
+        ];
+        let (message, _) = provider.complete("system", &sensitive, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "local");
+        assert_eq!(provider.get_model_config().model_name, "cloud");
+        assert_eq!(
+            provider.last_request_target().provider.as_deref(),
+            Some("ollama")
+        );
+    }
+}
@@ -1,15 +1,109 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use super::base::{LeadWorkerProviderTrait, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    LeadWorkerProviderTrait, ModelInfo, Provider, ProviderMetadata, ProviderUsage, RequestTarget,
+};
 use super::errors::ProviderError;
+use crate::config::Config;
 use crate::conversation::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use rmcp::model::Tool;
-use rmcp::model::{Content, RawContent};
+use rmcp::model::{Content, RawContent, Role};
+
+/// Phrases with which a model admits it is unsure of its answer
+const LOW_CONFIDENCE_PHRASES: &[&str] = &[
+    "i'm not sure",
+    "i am not sure",
+    "i'm not certain",
+    "i am not certain",
+    "i'm unsure",
+    "i don't know",
+    "i do not know",
+    "i cannot determine",
+    "i can't determine",
+    "i'm unable to determine",
+    "it's unclear",
+    "it is unclear",
+    "i'm not confident",
+    "this might not work",
+    "i may be wrong",
+];
+
+/// When the worker model hands work to the lead model
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscalationPolicy {
+    /// Start every user turn with the worker instead of giving the lead the first turns
+    pub worker_first: bool,
+    /// Consecutive task failures, such as failed tool calls, before escalating
+    pub failure_threshold: usize,
+    /// Completions the lead model handles once escalated
+    pub escalation_turns: usize,
+    /// Redo worker answers below this confidence with the lead model. Confidence is the mean
+    /// token probability when logprobs were captured, and otherwise 0 for answers that hedge.
+    pub min_confidence: Option<f64>,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            worker_first: false,
+            failure_threshold: 2,
+            escalation_turns: 2,
+            min_confidence: None,
+        }
+    }
+}
+
+impl EscalationPolicy {
+    /// Read the policy from `GOOSE_LEAD_WORKER_FIRST`, `GOOSE_LEAD_FAILURE_THRESHOLD`,
+    /// `GOOSE_LEAD_FALLBACK_TURNS` and `GOOSE_LEAD_MIN_CONFIDENCE`
+    pub fn from_config(config: &Config) -> Self {
+        let defaults = Self::default();
+        Self {
+            worker_first: config
+                .get_param::<bool>("GOOSE_LEAD_WORKER_FIRST")
+                .unwrap_or(defaults.worker_first),
+            failure_threshold: config
+                .get_param::<usize>("GOOSE_LEAD_FAILURE_THRESHOLD")
+                .unwrap_or(defaults.failure_threshold),
+            escalation_turns: config
+                .get_param::<usize>("GOOSE_LEAD_FALLBACK_TURNS")
+                .unwrap_or(defaults.escalation_turns),
+            min_confidence: config
+                .get_param::<f64>("GOOSE_LEAD_MIN_CONFIDENCE")
+                .ok()
+                .filter(|confidence| *confidence > 0.0),
+        }
+    }
+
+    /// How confident a response looks, from 0 to 1
+    pub fn confidence(message: &Message) -> f64 {
+        if !message.logprobs.is_empty() {
+            let mean = message.logprobs.iter().map(|t| t.logprob).sum::<f64>()
+                / message.logprobs.len() as f64;
+            return mean.exp();
+        }
+        let text = message.as_concat_text().to_lowercase();
+        if LOW_CONFIDENCE_PHRASES
+            .iter()
+            .any(|phrase| text.contains(phrase))
+        {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    fn is_low_confidence(&self, message: &Message) -> bool {
+        self.min_confidence
+            .is_some_and(|min| Self::confidence(message) < min)
+    }
+}
 
 /// A provider that switches between a lead model and a worker model based on turn count
 /// and can fallback to lead model on consecutive failures
@@ -19,10 +113,13 @@ pub struct LeadWorkerProvider {
     lead_turns: usize,
     turn_count: Arc<Mutex<usize>>,
     failure_count: Arc<Mutex<usize>>,
-    max_failures_before_fallback: usize,
-    fallback_turns: usize,
+    policy: EscalationPolicy,
     in_fallback_mode: Arc<Mutex<bool>>,
     fallback_remaining: Arc<Mutex<usize>>,
+    /// Factory names of the lead and worker providers, for [`Provider::last_request_target`]
+    provider_names: Option<(String, String)>,
+    /// Whether the latest request ended with the lead provider
+    last_used_lead: AtomicBool,
 }
 
 impl LeadWorkerProvider {
@@ -37,17 +134,12 @@ impl LeadWorkerProvider {
         worker_provider: Arc<dyn Provider>,
         lead_turns: Option<usize>,
     ) -> Self {
-        Self {
+        Self::new_with_policy(
             lead_provider,
             worker_provider,
-            lead_turns: lead_turns.unwrap_or(3),
-            turn_count: Arc::new(Mutex::new(0)),
-            failure_count: Arc::new(Mutex::new(0)),
-            max_failures_before_fallback: 2, // Fallback after 2 consecutive failures
-            fallback_turns: 2,               // Use lead model for 2 turns when in fallback mode
-            in_fallback_mode: Arc::new(Mutex::new(false)),
-            fallback_remaining: Arc::new(Mutex::new(0)),
-        }
+            lead_turns.unwrap_or(3),
+            EscalationPolicy::default(),
+        )
     }
 
     /// Create a new LeadWorkerProvider with custom settings
@@ -65,16 +157,68 @@ impl LeadWorkerProvider {
         failure_threshold: usize,
         fallback_turns: usize,
     ) -> Self {
-        Self {
+        Self::new_with_policy(
             lead_provider,
             worker_provider,
             lead_turns,
+            EscalationPolicy {
+                failure_threshold,
+                escalation_turns: fallback_turns,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a new LeadWorkerProvider that escalates to the lead model as `policy` says.
+    /// With `worker_first` set, `lead_turns` is ignored.
+    pub fn new_with_policy(
+        lead_provider: Arc<dyn Provider>,
+        worker_provider: Arc<dyn Provider>,
+        lead_turns: usize,
+        policy: EscalationPolicy,
+    ) -> Self {
+        Self {
+            lead_provider,
+            worker_provider,
+            lead_turns: if policy.worker_first { 0 } else { lead_turns },
             turn_count: Arc::new(Mutex::new(0)),
             failure_count: Arc::new(Mutex::new(0)),
-            max_failures_before_fallback: failure_threshold,
-            fallback_turns,
+            policy,
             in_fallback_mode: Arc::new(Mutex::new(false)),
             fallback_remaining: Arc::new(Mutex::new(0)),
+            provider_names: None,
+            last_used_lead: AtomicBool::new(true),
+        }
+    }
+
+    /// Name the lead and worker providers as the factory knows them
+    pub fn with_provider_names(mut self, lead: &str, worker: &str) -> Self {
+        self.provider_names = Some((lead.to_string(), worker.to_string()));
+        self
+    }
+
+    /// Hand the next completions to the lead model
+    async fn escalate(&self, reason: &str) {
+        let mut in_fallback = self.in_fallback_mode.lock().await;
+        let mut fallback_remaining = self.fallback_remaining.lock().await;
+        *in_fallback = true;
+        *fallback_remaining = self.policy.escalation_turns.max(1);
+        *self.failure_count.lock().await = 0;
+        tracing::warn!(
+            "🔄 SWITCHING TO LEAD MODEL: {} - using lead model for {} turns",
+            reason,
+            *fallback_remaining
+        );
+    }
+
+    /// Go back to the worker when the user starts a new turn, if the worker starts turns
+    async fn start_turn_with_worker(&self, messages: &[Message]) {
+        let new_turn = messages
+            .last()
+            .is_some_and(|message| message.role == Role::User && !message.is_tool_response());
+        if self.policy.worker_first && new_turn {
+            *self.in_fallback_mode.lock().await = false;
+            *self.fallback_remaining.lock().await = 0;
         }
     }
 
@@ -144,20 +288,14 @@ impl LeadWorkerProvider {
                     // Check if we should trigger fallback
                     if turn_count >= self.lead_turns
                         && !*self.in_fallback_mode.lock().await
-                        && failure_count >= self.max_failures_before_fallback
+                        && failure_count >= self.policy.failure_threshold
                     {
-                        let mut in_fallback = self.in_fallback_mode.lock().await;
-                        let mut fallback_remaining = self.fallback_remaining.lock().await;
-
-                        *in_fallback = true;
-                        *fallback_remaining = self.fallback_turns;
-                        *failures = 0; // Reset failure count when entering fallback
-
-                        tracing::warn!(
-                            "🔄 SWITCHING TO LEAD MODEL: Entering fallback mode after {} consecutive task failures - using lead model for {} turns",
-                            self.max_failures_before_fallback,
-                            self.fallback_turns
-                        );
+                        drop(failures);
+                        self.escalate(&format!(
+                            "Entering fallback mode after {} consecutive task failures",
+                            failure_count
+                        ))
+                        .await;
                     }
                 } else {
                     // Success - reset failure count and handle fallback mode
@@ -168,7 +306,7 @@ impl LeadWorkerProvider {
                     let mut fallback_remaining = self.fallback_remaining.lock().await;
 
                     if *in_fallback {
-                        *fallback_remaining -= 1;
+                        *fallback_remaining = fallback_remaining.saturating_sub(1);
                         if *fallback_remaining == 0 {
                             *in_fallback = false;
                             tracing::info!("✅ SWITCHING BACK TO WORKER MODEL: Exiting fallback mode - worker model resumed");
@@ -194,6 +332,30 @@ impl LeadWorkerProvider {
         }
     }
 
+    /// Failed tool results at the end of the conversation since the user last wrote, which the
+    /// model has to recover from in this completion
+    fn trailing_tool_failures(&self, messages: &[Message]) -> usize {
+        let mut failures = 0;
+        for message in messages.iter().rev() {
+            if message.role == Role::User && !message.is_tool_response() {
+                break;
+            }
+            for content in message.content.iter().rev() {
+                if let MessageContent::ToolResponse(tool_response) = content {
+                    let failed = match &tool_response.tool_result {
+                        Err(_) => true,
+                        Ok(contents) => self.contains_error_indicators(contents),
+                    };
+                    if !failed {
+                        return failures;
+                    }
+                    failures += 1;
+                }
+            }
+        }
+        failures
+    }
+
     /// Detect task-level failures in the model's response
     async fn detect_task_failures(&self, message: &Message) -> bool {
         let mut failure_indicators = 0;
@@ -332,6 +494,19 @@ impl Provider for LeadWorkerProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.start_turn_with_worker(messages).await;
+
+        // Repeated failed tool calls are a sign the worker is out of its depth
+        let tool_failures = self.trailing_tool_failures(messages);
+        if self.policy.failure_threshold > 0
+            && tool_failures >= self.policy.failure_threshold
+            && !*self.in_fallback_mode.lock().await
+            && *self.turn_count.lock().await >= self.lead_turns
+        {
+            self.escalate(&format!("{} consecutive failed tool calls", tool_failures))
+                .await;
+        }
+
         // Get the active provider
         let provider = self.get_active_provider().await;
 
@@ -349,7 +524,8 @@ impl Provider for LeadWorkerProvider {
         };
 
         // Get the active model name and update the global store
-        let active_model_name = if turn_count < self.lead_turns || in_fallback {
+        let using_lead = turn_count < self.lead_turns || in_fallback;
+        let active_model_name = if using_lead {
             self.lead_provider.get_model_config().model_name.clone()
         } else {
             self.worker_provider.get_model_config().model_name.clone()
@@ -377,6 +553,7 @@ impl Provider for LeadWorkerProvider {
         }
 
         // Make the completion request
+        self.last_used_lead.store(using_lead, Ordering::Relaxed);
         let result = provider.complete(system, messages, tools).await;
 
         // For technical failures, try with default model (lead provider) instead
@@ -392,6 +569,7 @@ impl Provider for LeadWorkerProvider {
                         tracing::info!(
                             "✅ Default model (lead provider) succeeded after technical failure"
                         );
+                        self.last_used_lead.store(true, Ordering::Relaxed);
                         default_result
                     }
                     Err(_) => {
@@ -400,6 +578,29 @@ impl Provider for LeadWorkerProvider {
                     }
                 }
             }
+            // The worker answered but isn't sure of it, so the lead answers instead
+            Ok((message, _))
+                if provider_type == "worker" && self.policy.is_low_confidence(message) =>
+            {
+                self.escalate("Low-confidence answer from the worker model")
+                    .await;
+                let lead_model_name = self.lead_provider.get_model_config().model_name;
+                super::base::set_current_model(&lead_model_name);
+                match self.lead_provider.complete(system, messages, tools).await {
+                    Ok(lead_result) => {
+                        self.last_used_lead.store(true, Ordering::Relaxed);
+                        Ok(lead_result)
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "Lead model failed after escalation, keeping the worker's answer: {}",
+                            e
+                        );
+                        super::base::set_current_model(&active_model_name);
+                        result
+                    }
+                }
+            }
             Ok(_) => result, // Success with original provider
         };
 
@@ -427,6 +628,38 @@ impl Provider for LeadWorkerProvider {
         }
     }
 
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        // Combine model metadata from both providers, preferring the lead's entries
+        let lead_models = self.lead_provider.fetch_model_info().await?;
+        let worker_models = self.worker_provider.fetch_model_info().await?;
+
+        match (lead_models, worker_models) {
+            (Some(mut lead), Some(worker)) => {
+                for model in worker {
+                    if !lead.iter().any(|m| m.name == model.name) {
+                        lead.push(model);
+                    }
+                }
+                Ok(Some(lead))
+            }
+            (Some(models), None) | (None, Some(models)) => Ok(Some(models)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn last_request_target(&self) -> RequestTarget {
+        let names = self.provider_names.as_ref();
+        if self.last_used_lead.load(Ordering::Relaxed) {
+            self.lead_provider
+                .last_request_target()
+                .with_provider(names.map(|(lead, _)| lead.as_str()))
+        } else {
+            self.worker_provider
+                .last_request_target()
+                .with_provider(names.map(|(_, worker)| worker.as_str()))
+        }
+    }
+
     fn supports_embeddings(&self) -> bool {
         // Support embeddings if either provider supports them
         self.lead_provider.supports_embeddings() || self.worker_provider.supports_embeddings()
@@ -457,6 +690,7 @@ mod tests {
     use crate::conversation::message::{Message, MessageContent};
     use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
     use chrono::Utc;
+    use mcp_core::ToolError;
     use rmcp::model::{AnnotateAble, RawTextContent, Role};
 
     #[derive(Clone)]
@@ -509,7 +743,8 @@ mod tests {
             model_config: ModelConfig::new_or_fail("worker-model"),
         });
 
-        let provider = LeadWorkerProvider::new(lead_provider, worker_provider, Some(3));
+        let provider = LeadWorkerProvider::new(lead_provider, worker_provider, Some(3))
+            .with_provider_names("anthropic", "openai");
 
         // First three turns should use lead provider
         for i in 0..3 {
@@ -526,6 +761,13 @@ mod tests {
             assert_eq!(provider.get_turn_count().await, i + 1);
             assert!(!provider.is_in_fallback_mode().await);
         }
+        assert_eq!(
+            provider.last_request_target(),
+            RequestTarget {
+                provider: Some("openai".to_string()),
+                model: "worker-model".to_string(),
+            }
+        );
 
         // Reset and verify it goes back to lead
         provider.reset_turn_count().await;
@@ -618,6 +860,95 @@ mod tests {
         assert!(!provider.is_in_fallback_mode().await); // Should exit fallback mode
     }
 
+    struct MockTextProvider {
+        name: String,
+        model_config: ModelConfig,
+        text: std::sync::Mutex<String>,
+    }
+
+    #[async_trait]
+    impl Provider for MockTextProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let text = self.text.lock().unwrap().clone();
+            Ok((
+                Message::assistant().with_text(text),
+                ProviderUsage::new(self.name.clone(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_first_escalation() {
+        let lead_provider = Arc::new(MockProvider {
+            name: "lead".to_string(),
+            model_config: ModelConfig::new_or_fail("lead-model"),
+        });
+        let worker_provider = Arc::new(MockTextProvider {
+            name: "worker".to_string(),
+            model_config: ModelConfig::new_or_fail("worker-model"),
+            text: std::sync::Mutex::new("Done.".to_string()),
+        });
+        let provider = LeadWorkerProvider::new_with_policy(
+            lead_provider,
+            worker_provider.clone(),
+            3,
+            EscalationPolicy {
+                worker_first: true,
+                failure_threshold: 2,
+                escalation_turns: 1,
+                min_confidence: Some(0.5),
+            },
+        );
+
+        let user = Message::user().with_text("Fix the build");
+        let (_, usage) = provider
+            .complete("system", &[user.clone()], &[])
+            .await
+            .unwrap();
+        assert_eq!(usage.model, "worker");
+
+        // Two failed tool calls in a row hand the next completion to the lead
+        let failed = |id: &str| {
+            Message::user().with_tool_response(id, Err(ToolError::ExecutionError("boom".into())))
+        };
+        let messages = vec![
+            user.clone(),
+            Message::assistant().with_text("Trying"),
+            failed("1"),
+            Message::assistant().with_text("Trying again"),
+            failed("2"),
+        ];
+        let (_, usage) = provider.complete("system", &messages, &[]).await.unwrap();
+        assert_eq!(usage.model, "lead");
+
+        // A new user turn starts with the worker again
+        let (_, usage) = provider
+            .complete("system", &[user.clone()], &[])
+            .await
+            .unwrap();
+        assert_eq!(usage.model, "worker");
+
+        // An answer the worker hedges on is redone by the lead
+        *worker_provider.text.lock().unwrap() = "I'm not sure, but maybe this works".to_string();
+        let (message, usage) = provider.complete("system", &[user], &[]).await.unwrap();
+        assert_eq!(usage.model, "lead");
+        assert_eq!(message.as_concat_text(), "Response from lead");
+        assert!(!provider.is_in_fallback_mode().await);
+    }
+
     #[derive(Clone)]
     struct MockFailureProvider {
         name: String,
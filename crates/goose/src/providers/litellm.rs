@@ -7,7 +7,7 @@ use super::api_client::{ApiClient, AuthMethod};
 use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
 use super::embedding::EmbeddingCapable;
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
@@ -23,6 +23,8 @@ pub struct LiteLLMProvider {
     api_client: ApiClient,
     base_path: String,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(LiteLLMProvider);
@@ -53,7 +55,8 @@ impl LiteLLMProvider {
         };
 
         let mut api_client =
-            ApiClient::with_timeout(host, auth, std::time::Duration::from_secs(timeout_secs))?;
+            ApiClient::with_timeout(host, auth, std::time::Duration::from_secs(timeout_secs))?
+                .with_configured_middleware("litellm")?;
 
         if let Some(headers) = custom_headers {
             let mut header_map = reqwest::header::HeaderMap::new();
@@ -69,6 +72,7 @@ impl LiteLLMProvider {
             api_client,
             base_path,
             model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
         })
     }
 
@@ -102,8 +106,17 @@ impl LiteLLMProvider {
                     model_info["max_input_tokens"].as_u64().unwrap_or(128000) as usize;
                 let supports_cache_control = model_info["supports_prompt_caching"].as_bool();
 
+                // Reasoning models that also take a temperature need no translation
+                let reasoning = model_info["supported_openai_params"]
+                    .as_array()
+                    .map(|params| {
+                        model_info["supports_reasoning"].as_bool() == Some(true)
+                            && !params.iter().any(|p| p.as_str() == Some("temperature"))
+                    });
+
                 let mut model_info_obj = ModelInfo::new(model_name, context_length);
                 model_info_obj.supports_cache_control = supports_cache_control;
+                model_info_obj.reasoning = reasoning;
                 models.push(model_info_obj);
             }
         }
@@ -156,6 +169,10 @@ impl Provider for LiteLLMProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -175,7 +192,7 @@ impl Provider for LiteLLMProvider {
             &ImageFormat::OpenAi,
         )?;
 
-        if self.supports_cache_control() {
+        if self.supports_cache_control() && super::prompt_cache::is_enabled() {
             payload = update_request_for_cache_control(&payload);
         }
 
@@ -221,6 +238,10 @@ impl Provider for LiteLLMProvider {
             }
         }
     }
+
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        self.fetch_models().await.map(Some)
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,459 @@
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Result;
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+use llama_cpp_2::sampling::LlamaSampler;
+use rmcp::model::{Role, Tool};
+use tokio::sync::{mpsc, OnceCell};
+
+use super::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::gbnf;
+use super::structured;
+use super::utils::emit_debug_trace;
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+
+pub const LLAMA_CPP_DEFAULT_MODEL: &str = "local-gguf";
+pub const LLAMA_CPP_DOC_URL: &str = "https://huggingface.co/models?library=gguf";
+/// Tokens decoded per batch while reading the prompt
+const PROMPT_BATCH_SIZE: usize = 512;
+
+/// llama.cpp can only be initialized once per process
+static BACKEND: OnceLock<std::result::Result<LlamaBackend, String>> = OnceLock::new();
+
+fn backend() -> Result<&'static LlamaBackend, ProviderError> {
+    BACKEND
+        .get_or_init(|| LlamaBackend::init().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| ProviderError::ExecutionError(format!("Failed to start llama.cpp: {}", e)))
+}
+
+/// Runs a GGUF model in-process through llama.cpp, for fully offline use.
+///
+/// The weights are loaded on the first request and kept for the life of the provider. Models
+/// run this way have no native tool calling, so toolshim is on unless GOOSE_TOOLSHIM says
+/// otherwise.
+#[derive(serde::Serialize)]
+pub struct LlamaCppProvider {
+    #[serde(skip)]
+    weights: Arc<OnceCell<Arc<LlamaModel>>>,
+    model_path: PathBuf,
+    context_size: Option<u32>,
+    gpu_layers: u32,
+    threads: Option<i32>,
+    model: ModelConfig,
+}
+
+impl_provider_default!(LlamaCppProvider);
+
+impl LlamaCppProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        // The model name doubles as the path when it points at a GGUF file
+        let model_path: PathBuf = match config.get_param::<String>("LLAMA_CPP_MODEL_PATH") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) if model.model_name.ends_with(".gguf") => PathBuf::from(&model.model_name),
+            Err(_) => {
+                return Err(anyhow::anyhow!(
+                    "Set LLAMA_CPP_MODEL_PATH to a .gguf file to use the llama.cpp provider"
+                ))
+            }
+        };
+
+        Ok(Self {
+            weights: Arc::new(OnceCell::new()),
+            model_path,
+            context_size: config.get_param("LLAMA_CPP_CONTEXT_SIZE").ok(),
+            gpu_layers: config.get_param("LLAMA_CPP_GPU_LAYERS").unwrap_or(0),
+            threads: config.get_param("LLAMA_CPP_THREADS").ok(),
+            model,
+        })
+    }
+
+    async fn weights(&self) -> Result<Arc<LlamaModel>, ProviderError> {
+        self.weights
+            .get_or_try_init(|| async {
+                let path = self.model_path.clone();
+                let params = LlamaModelParams::default().with_n_gpu_layers(self.gpu_layers);
+                tokio::task::spawn_blocking(move || {
+                    tracing::info!("Loading GGUF model from {}", path.display());
+                    LlamaModel::load_from_file(backend()?, &path, &params)
+                        .map(Arc::new)
+                        .map_err(|e| {
+                            ProviderError::ExecutionError(format!(
+                                "Failed to load {}: {}",
+                                path.display(),
+                                e
+                            ))
+                        })
+                })
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+            })
+            .await
+            .cloned()
+    }
+
+    /// The context window to run with: the configured size, else the model config's limit,
+    /// never more than the model was trained for
+    fn context_size(&self, weights: &LlamaModel) -> u32 {
+        let trained = weights.n_ctx_train();
+        self.context_size
+            .unwrap_or(self.model.context_limit() as u32)
+            .min(trained)
+    }
+}
+
+/// Render the conversation with the model's own chat template, or a plain transcript for
+/// models that don't ship one
+fn render_prompt(weights: &LlamaModel, system: &str, messages: &[Message]) -> (String, bool) {
+    let turns: Vec<(&str, String)> = std::iter::once(("system", system.to_string()))
+        .chain(messages.iter().map(|message| {
+            let role = match message.role {
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            (role, message.as_concat_text())
+        }))
+        .filter(|(_, text)| !text.is_empty())
+        .collect();
+
+    let templated = weights.chat_template(None).ok().and_then(|template| {
+        let chat = turns
+            .iter()
+            .map(|(role, text)| LlamaChatMessage::new(role.to_string(), text.clone()))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .ok()?;
+        weights.apply_chat_template(&template, &chat, true).ok()
+    });
+    match templated {
+        Some(prompt) => (prompt, false),
+        None => {
+            let mut prompt: String = turns
+                .iter()
+                .map(|(role, text)| format!("{}: {}\n\n", role, text))
+                .collect();
+            prompt.push_str("assistant: ");
+            (prompt, true)
+        }
+    }
+}
+
+/// Remove and return the longest valid UTF-8 prefix; a token can end partway through a
+/// character, so the rest waits for the next token
+fn take_utf8(bytes: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(text) => text.len(),
+        Err(e) => e.valid_up_to(),
+    };
+    let rest = bytes.split_off(valid);
+    String::from_utf8(std::mem::replace(bytes, rest)).unwrap_or_default()
+}
+
+struct Generation {
+    weights: Arc<LlamaModel>,
+    prompt: String,
+    add_bos: bool,
+    context_size: u32,
+    threads: Option<i32>,
+    max_tokens: Option<i32>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    seed: Option<i64>,
+    /// GBNF grammar the output must match
+    grammar: Option<String>,
+}
+
+enum Event {
+    Text(String),
+    Done(Usage),
+}
+
+impl Generation {
+    /// Decode until end of generation, sending text as it comes; stops early if the receiver
+    /// goes away
+    fn run(self, tx: mpsc::UnboundedSender<Result<Event, ProviderError>>) {
+        let result = self.generate(&tx);
+        if let Err(e) = result {
+            let _ = tx.send(Err(e));
+        }
+    }
+
+    fn generate(
+        &self,
+        tx: &mpsc::UnboundedSender<Result<Event, ProviderError>>,
+    ) -> Result<(), ProviderError> {
+        let fail = |e: &dyn std::fmt::Display| ProviderError::ExecutionError(e.to_string());
+
+        let mut params =
+            LlamaContextParams::default().with_n_ctx(NonZeroU32::new(self.context_size));
+        if let Some(threads) = self.threads {
+            params = params.with_n_threads(threads);
+        }
+        let mut ctx = self
+            .weights
+            .new_context(backend()?, params)
+            .map_err(|e| fail(&e))?;
+
+        let add_bos = if self.add_bos {
+            AddBos::Always
+        } else {
+            AddBos::Never
+        };
+        let tokens = self
+            .weights
+            .str_to_token(&self.prompt, add_bos)
+            .map_err(|e| fail(&e))?;
+        let prompt_tokens = tokens.len() as i32;
+        if prompt_tokens >= self.context_size as i32 {
+            return Err(ProviderError::ContextLengthExceeded(format!(
+                "Prompt of {} tokens doesn't fit the context of {}",
+                prompt_tokens, self.context_size
+            )));
+        }
+
+        let mut batch = LlamaBatch::new(PROMPT_BATCH_SIZE, 1);
+        for (chunk_start, chunk) in tokens.chunks(PROMPT_BATCH_SIZE).enumerate() {
+            batch.clear();
+            for (i, token) in chunk.iter().enumerate() {
+                let pos = (chunk_start * PROMPT_BATCH_SIZE + i) as i32;
+                batch
+                    .add(*token, pos, &[0], pos == prompt_tokens - 1)
+                    .map_err(|e| fail(&e))?;
+            }
+            ctx.decode(&mut batch).map_err(|e| fail(&e))?;
+        }
+
+        let sampler = match self.temperature.filter(|t| *t > 0.0) {
+            Some(temperature) => LlamaSampler::chain_simple([
+                LlamaSampler::top_p(self.top_p.unwrap_or(1.0), 1),
+                LlamaSampler::temp(temperature),
+                LlamaSampler::dist(self.seed.map(|s| s as u32).unwrap_or_else(rand::random)),
+            ]),
+            None => LlamaSampler::greedy(),
+        };
+        // The grammar goes first so only tokens it allows are left to sample from
+        let mut sampler = match &self.grammar {
+            Some(grammar) => {
+                let grammar = LlamaSampler::grammar(&self.weights, grammar, "root")
+                    .ok_or_else(|| fail(&"Invalid grammar for constrained decoding"))?;
+                LlamaSampler::chain_simple([grammar, sampler])
+            }
+            None => sampler,
+        };
+
+        let limit = self
+            .max_tokens
+            .map_or(self.context_size as i32, |max| prompt_tokens + max)
+            .min(self.context_size as i32);
+        let mut pos = prompt_tokens;
+        let mut pending = Vec::new();
+        while pos < limit {
+            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if self.weights.is_eog_token(token) {
+                break;
+            }
+
+            pending.extend(
+                self.weights
+                    .token_to_bytes(token, Special::Tokenize)
+                    .map_err(|e| fail(&e))?,
+            );
+            let text = take_utf8(&mut pending);
+            if !text.is_empty() && tx.send(Ok(Event::Text(text))).is_err() {
+                return Ok(());
+            }
+
+            batch.clear();
+            batch.add(token, pos, &[0], true).map_err(|e| fail(&e))?;
+            ctx.decode(&mut batch).map_err(|e| fail(&e))?;
+            pos += 1;
+        }
+
+        let output_tokens = pos - prompt_tokens;
+        let _ = tx.send(Ok(Event::Done(Usage::new(
+            Some(prompt_tokens),
+            Some(output_tokens),
+            Some(pos),
+        ))));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for LlamaCppProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "llama_cpp",
+            "llama.cpp",
+            "Run a local GGUF model in-process, fully offline",
+            LLAMA_CPP_DEFAULT_MODEL,
+            vec![],
+            LLAMA_CPP_DOC_URL,
+            vec![
+                ConfigKey::new("LLAMA_CPP_MODEL_PATH", true, false, None),
+                ConfigKey::new("LLAMA_CPP_CONTEXT_SIZE", false, false, None),
+                ConfigKey::new("LLAMA_CPP_GPU_LAYERS", false, false, Some("0")),
+                ConfigKey::new("LLAMA_CPP_THREADS", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        let mut model = self.model.clone();
+        if std::env::var("GOOSE_TOOLSHIM").is_err() {
+            model.toolshim = true;
+        }
+        model
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let stream = self.stream(system, messages, tools).await?;
+        let (text, usage) = self.collect(stream).await?;
+        Ok((Message::assistant().with_text(text), usage))
+    }
+
+    /// Decodes with a grammar built from the schema, so the answer matches by construction
+    async fn complete_with_response_schema(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &serde_json::Value,
+    ) -> Result<(serde_json::Value, ProviderUsage), ProviderError> {
+        let grammar = gbnf::from_json_schema(schema);
+        let stream = self.generate(system, messages, Some(grammar)).await?;
+        let (text, usage) = self.collect(stream).await?;
+
+        match structured::extract_json(&text) {
+            Some(value) if structured::validate(schema, &value).is_ok() => Ok((value, usage)),
+            _ => {
+                tracing::debug!(
+                    "Grammar-constrained output didn't match the schema: {}",
+                    text
+                );
+                structured::complete_with_retries(self, system, messages, schema, Some(usage)).await
+            }
+        }
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.generate(system, messages, None).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+}
+
+impl LlamaCppProvider {
+    /// Start generating on a blocking thread, streaming the text as it's decoded
+    async fn generate(
+        &self,
+        system: &str,
+        messages: &[Message],
+        grammar: Option<String>,
+    ) -> Result<MessageStream, ProviderError> {
+        let weights = self.weights().await?;
+        let (prompt, add_bos) = render_prompt(&weights, system, messages);
+        let generation = Generation {
+            context_size: self.context_size(&weights),
+            weights,
+            prompt,
+            add_bos,
+            threads: self.threads,
+            max_tokens: self.model.max_tokens,
+            temperature: self.model.temperature,
+            top_p: self.model.top_p,
+            seed: self.model.seed,
+            grammar,
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || generation.run(tx));
+
+        let model_config = self.model.clone();
+        let debug_payload = serde_json::json!({ "system": system, "messages": messages });
+        let message_id = format!("msg_{}", uuid::Uuid::new_v4().simple());
+        Ok(Box::pin(try_stream! {
+            let mut text = String::new();
+            while let Some(event) = rx.recv().await {
+                match event? {
+                    Event::Text(delta) => {
+                        text.push_str(&delta);
+                        let message = Message::assistant()
+                            .with_text(delta)
+                            .with_id(message_id.clone());
+                        yield (Some(message), None);
+                    }
+                    Event::Done(usage) => {
+                        emit_debug_trace(
+                            &model_config,
+                            &debug_payload,
+                            &serde_json::json!({ "text": text }),
+                            &usage,
+                        );
+                        yield (None, Some(ProviderUsage::new(model_config.model_name.clone(), usage)));
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Read a stream to the end, returning its text and usage
+    async fn collect(
+        &self,
+        mut stream: MessageStream,
+    ) -> Result<(String, ProviderUsage), ProviderError> {
+        let mut text = String::new();
+        let mut usage = None;
+        while let Some(item) = stream.next().await {
+            let (message, item_usage) = item?;
+            if let Some(message) = message {
+                text.push_str(&message.as_concat_text());
+            }
+            usage = item_usage.or(usage);
+        }
+        let usage = usage
+            .unwrap_or_else(|| ProviderUsage::new(self.model.model_name.clone(), Usage::default()));
+        Ok((text, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_utf8_holds_back_split_characters() {
+        let mut bytes = "héllo".as_bytes().to_vec();
+        let tail = bytes.split_off(2);
+        assert_eq!(take_utf8(&mut bytes), "h");
+        assert_eq!(bytes, vec![0xc3]);
+
+        bytes.extend(tail);
+        assert_eq!(take_utf8(&mut bytes), "éllo");
+        assert!(bytes.is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use candle_core::{DType, Device, IndexOp, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use rmcp::model::Tool;
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use tokio::sync::OnceCell;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+
+pub const LOCAL_EMBEDDING_DEFAULT_MODEL: &str = "BAAI/bge-small-en-v1.5";
+pub const LOCAL_EMBEDDING_DOC_URL: &str = "https://huggingface.co/BAAI/bge-small-en-v1.5";
+
+const HUGGINGFACE_URL: &str = "https://huggingface.co";
+/// The files a BERT-style embedding model is loaded from
+const MODEL_FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+/// BERT models only have position embeddings this far
+const MAX_SEQUENCE_LENGTH: usize = 512;
+
+struct Encoder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl Encoder {
+    fn load(dir: &Path) -> Result<Self> {
+        let device = Device::Cpu;
+        let config: BertConfig = serde_json::from_slice(&std::fs::read(dir.join("config.json"))?)
+            .context("Failed to read the model config")?;
+
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| anyhow::anyhow!("Failed to read the tokenizer: {}", e))?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_SEQUENCE_LENGTH,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow::anyhow!("Failed to configure the tokenizer: {}", e))?;
+
+        // Safety: the weights file is only read, and is not changed while mapped
+        let weights = unsafe {
+            VarBuilder::from_mmaped_safetensors(
+                &[dir.join("model.safetensors")],
+                DType::F32,
+                &device,
+            )?
+        };
+        let model = BertModel::load(weights, &config)?;
+
+        Ok(Self {
+            model,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// The normalized [CLS] embedding of each text, which is what bge models are trained to
+    /// produce
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts, true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize: {}", e))?;
+
+        let rows = |get: fn(&tokenizers::Encoding) -> &[u32]| -> Result<Tensor> {
+            let rows = encodings
+                .iter()
+                .map(|encoding| Tensor::new(get(encoding), &self.device))
+                .collect::<candle_core::Result<Vec<_>>>()?;
+            Ok(Tensor::stack(&rows, 0)?)
+        };
+        let input_ids = rows(tokenizers::Encoding::get_ids)?;
+        let token_type_ids = rows(tokenizers::Encoding::get_type_ids)?;
+        let attention_mask = rows(tokenizers::Encoding::get_attention_mask)?;
+
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+        let cls = hidden.i((.., 0))?;
+        let norms = cls.sqr()?.sum_keepdim(1)?.sqrt()?;
+        Ok(cls.broadcast_div(&norms)?.to_vec2::<f32>()?)
+    }
+}
+
+/// Creates embeddings with a small BERT-style model run in-process through candle, so
+/// retrieval works offline without configuring an embedding provider.
+///
+/// The model is read from LOCAL_EMBEDDING_MODEL_PATH when set. Otherwise it is downloaded
+/// from the HuggingFace hub on first use and cached, after which no network is needed.
+/// This provider only creates embeddings; it can't be used for chat.
+#[derive(serde::Serialize)]
+pub struct LocalEmbeddingProvider {
+    #[serde(skip)]
+    encoder: Arc<OnceCell<Arc<Encoder>>>,
+    model_path: Option<PathBuf>,
+    model: ModelConfig,
+}
+
+impl_provider_default!(LocalEmbeddingProvider);
+
+impl LocalEmbeddingProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        Ok(Self {
+            encoder: Arc::new(OnceCell::new()),
+            model_path: config
+                .get_param::<String>("LOCAL_EMBEDDING_MODEL_PATH")
+                .ok()
+                .map(PathBuf::from),
+            model,
+        })
+    }
+
+    async fn encoder(&self) -> Result<Arc<Encoder>, ProviderError> {
+        self.encoder
+            .get_or_try_init(|| async {
+                let dir = match &self.model_path {
+                    Some(path) => path.clone(),
+                    None => download(&self.model.model_name)
+                        .await
+                        .map_err(|e| ProviderError::ExecutionError(e.to_string()))?,
+                };
+                tokio::task::spawn_blocking(move || {
+                    tracing::info!("Loading embedding model from {}", dir.display());
+                    Encoder::load(&dir).map(Arc::new).map_err(|e| {
+                        ProviderError::ExecutionError(format!(
+                            "Failed to load the embedding model from {}: {}",
+                            dir.display(),
+                            e
+                        ))
+                    })
+                })
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+            })
+            .await
+            .cloned()
+    }
+}
+
+/// Where a model from the hub is kept
+fn model_cache_dir(repo: &str) -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine cache directory"))?
+        .join("goose");
+    Ok(cache_dir
+        .join("embedding-models")
+        .join(repo.replace('/', "--")))
+}
+
+/// Fetch whichever of the model's files aren't cached yet
+async fn download(repo: &str) -> Result<PathBuf> {
+    let dir = model_cache_dir(repo)?;
+    tokio::fs::create_dir_all(&dir).await?;
+    for file in MODEL_FILES {
+        let path = dir.join(file);
+        if path.exists() {
+            continue;
+        }
+        let url = format!("{}/{}/resolve/main/{}", HUGGINGFACE_URL, repo, file);
+        tracing::info!("Downloading {}", url);
+        let mut request = reqwest::Client::new().get(&url);
+        if let Ok(token) = std::env::var("HF_TOKEN") {
+            request = request.bearer_auth(token);
+        }
+        let bytes = request
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to download {}", url))?
+            .bytes()
+            .await?;
+        // Written aside first so an interrupted download isn't mistaken for a cached file
+        let partial = path.with_extension("part");
+        tokio::fs::write(&partial, &bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+    }
+    Ok(dir)
+}
+
+#[async_trait]
+impl Provider for LocalEmbeddingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "local_embedding",
+            "Local embeddings",
+            "Create embeddings in-process with a small model, fully offline once downloaded",
+            LOCAL_EMBEDDING_DEFAULT_MODEL,
+            vec![LOCAL_EMBEDDING_DEFAULT_MODEL],
+            LOCAL_EMBEDDING_DOC_URL,
+            vec![ConfigKey::new(
+                "LOCAL_EMBEDDING_MODEL_PATH",
+                false,
+                false,
+                None,
+            )],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        _system: &str,
+        _messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        Err(ProviderError::ExecutionError(
+            "The local embedding model can only create embeddings".to_string(),
+        ))
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        true
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let encoder = self.encoder().await?;
+        tokio::task::spawn_blocking(move || encoder.embed(texts))
+            .await
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+            .map_err(|e| ProviderError::ExecutionError(format!("Failed to embed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_cache_dir_is_per_repo() {
+        let dir = model_cache_dir(LOCAL_EMBEDDING_DEFAULT_MODEL).unwrap();
+        assert!(dir.ends_with("embedding-models/BAAI--bge-small-en-v1.5"));
+    }
+}
@@ -0,0 +1,416 @@
+//! Hooks around the HTTP requests providers send.
+//!
+//! A [`ProviderMiddleware`] sees each request before it goes out and each response before the
+//! provider parses it, and may change either: log them, add headers, scrub secrets out of the
+//! payload. Middleware is registered per provider under `GOOSE_PROVIDER_MIDDLEWARE`, keyed by
+//! provider name, with `*` applying to every provider:
+//!
+//! ```yaml
+//! GOOSE_PROVIDER_MIDDLEWARE:
+//!   "*":
+//!     - type: scrub
+//!       patterns: ["sk-[A-Za-z0-9_-]{20,}"]
+//!   openai:
+//!     - type: headers
+//!       headers:
+//!         X-Team: platform
+//!     - type: log
+//!       bodies: true
+//! ```
+//!
+//! The `*` entries run first, then the provider's own, each in the order listed. Credentials are
+//! added after the middleware has run, so it never sees them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use axum::http;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{Method, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::config::{Config, ConfigError};
+
+/// The config key middleware is registered under
+pub const MIDDLEWARE_CONFIG_KEY: &str = "GOOSE_PROVIDER_MIDDLEWARE";
+
+/// Registrations under this name apply to every provider
+const ALL_PROVIDERS: &str = "*";
+
+const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// A request on its way to the provider
+#[derive(Debug, Clone)]
+pub struct MiddlewareRequest {
+    pub provider: String,
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub payload: Option<Value>,
+}
+
+/// A response on its way back to the provider. Streamed responses are passed through as they
+/// are, so only their status and headers can be looked at and `payload` is `None`.
+#[derive(Debug, Clone)]
+pub struct MiddlewareResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub payload: Option<Value>,
+}
+
+#[async_trait]
+pub trait ProviderMiddleware: Send + Sync {
+    async fn on_request(&self, _request: &mut MiddlewareRequest) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_response(
+        &self,
+        _request: &MiddlewareRequest,
+        _response: &mut MiddlewareResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The middleware that can be registered in config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MiddlewareConfig {
+    /// Log each request and response
+    Log {
+        /// Include the payloads, not just the method, URL and status
+        #[serde(default)]
+        bodies: bool,
+    },
+    /// Add headers to each request
+    Headers { headers: HashMap<String, String> },
+    /// Replace whatever matches the patterns in the string values of each request payload
+    Scrub {
+        patterns: Vec<String>,
+        #[serde(default)]
+        replacement: Option<String>,
+    },
+}
+
+impl MiddlewareConfig {
+    pub fn build(&self) -> Result<Arc<dyn ProviderMiddleware>> {
+        Ok(match self {
+            Self::Log { bodies } => Arc::new(LogMiddleware { bodies: *bodies }),
+            Self::Headers { headers } => Arc::new(HeaderMiddleware::new(headers)?),
+            Self::Scrub {
+                patterns,
+                replacement,
+            } => Arc::new(ScrubMiddleware::new(
+                patterns,
+                replacement.as_deref().unwrap_or(DEFAULT_REPLACEMENT),
+            )?),
+        })
+    }
+}
+
+/// The middleware registered for a provider. A mistake in the registration is an error rather
+/// than a warning, since a scrub that silently doesn't run is worse than none at all.
+pub fn configured(provider: &str) -> Result<Vec<Arc<dyn ProviderMiddleware>>> {
+    match Config::global()
+        .get_param::<HashMap<String, Vec<MiddlewareConfig>>>(MIDDLEWARE_CONFIG_KEY)
+    {
+        Ok(registrations) => for_provider(&registrations, provider)
+            .iter()
+            .map(|config| config.build())
+            .collect(),
+        Err(ConfigError::NotFound(_)) => Ok(Vec::new()),
+        Err(e) => Err(anyhow!("Invalid {}: {}", MIDDLEWARE_CONFIG_KEY, e)),
+    }
+}
+
+fn for_provider<'a>(
+    registrations: &'a HashMap<String, Vec<MiddlewareConfig>>,
+    provider: &str,
+) -> Vec<&'a MiddlewareConfig> {
+    [ALL_PROVIDERS, provider]
+        .iter()
+        .filter_map(|name| registrations.get(*name))
+        .flatten()
+        .collect()
+}
+
+/// Run the response hooks. JSON bodies are read so the middleware can change them and the
+/// response is rebuilt from the result; anything else, streams in particular, is handed on
+/// untouched once the middleware has seen its status and headers.
+pub(crate) async fn handle_response(
+    middleware: &[Arc<dyn ProviderMiddleware>],
+    request: &MiddlewareRequest,
+    response: Response,
+) -> Result<Response> {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+    let mut seen = MiddlewareResponse {
+        status: response.status(),
+        headers: response.headers().clone(),
+        payload: None,
+    };
+
+    if !is_json {
+        for hook in middleware {
+            hook.on_response(request, &mut seen).await?;
+        }
+        return Ok(response);
+    }
+
+    let body = response.bytes().await?;
+    seen.payload = serde_json::from_slice(&body).ok();
+    for hook in middleware {
+        hook.on_response(request, &mut seen).await?;
+    }
+
+    let body = match &seen.payload {
+        Some(payload) => serde_json::to_vec(payload)?,
+        None => body.to_vec(),
+    };
+    seen.headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    let mut rebuilt = http::Response::builder().status(seen.status);
+    if let Some(headers) = rebuilt.headers_mut() {
+        *headers = seen.headers;
+    }
+    Ok(Response::from(rebuilt.body(body)?))
+}
+
+pub struct LogMiddleware {
+    bodies: bool,
+}
+
+#[async_trait]
+impl ProviderMiddleware for LogMiddleware {
+    async fn on_request(&self, request: &mut MiddlewareRequest) -> Result<()> {
+        match (&request.payload, self.bodies) {
+            (Some(payload), true) => tracing::info!(
+                provider = %request.provider,
+                "{} {} {}",
+                request.method,
+                request.url,
+                payload
+            ),
+            _ => tracing::info!(provider = %request.provider, "{} {}", request.method, request.url),
+        }
+        Ok(())
+    }
+
+    async fn on_response(
+        &self,
+        request: &MiddlewareRequest,
+        response: &mut MiddlewareResponse,
+    ) -> Result<()> {
+        match (&response.payload, self.bodies) {
+            (Some(payload), true) => tracing::info!(
+                provider = %request.provider,
+                "{} {} -> {} {}",
+                request.method,
+                request.url,
+                response.status,
+                payload
+            ),
+            _ => tracing::info!(
+                provider = %request.provider,
+                "{} {} -> {}",
+                request.method,
+                request.url,
+                response.status
+            ),
+        }
+        Ok(())
+    }
+}
+
+pub struct HeaderMiddleware {
+    headers: HeaderMap,
+}
+
+impl HeaderMiddleware {
+    pub fn new(headers: &HashMap<String, String>) -> Result<Self> {
+        let mut header_map = HeaderMap::new();
+        for (key, value) in headers {
+            header_map.insert(
+                HeaderName::from_bytes(key.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(Self {
+            headers: header_map,
+        })
+    }
+}
+
+#[async_trait]
+impl ProviderMiddleware for HeaderMiddleware {
+    async fn on_request(&self, request: &mut MiddlewareRequest) -> Result<()> {
+        request.headers.extend(self.headers.clone());
+        Ok(())
+    }
+}
+
+pub struct ScrubMiddleware {
+    patterns: Vec<Regex>,
+    replacement: String,
+}
+
+impl ScrubMiddleware {
+    pub fn new(patterns: &[String], replacement: &str) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| anyhow!("Invalid scrub pattern {}: {}", pattern, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            patterns,
+            replacement: replacement.to_string(),
+        })
+    }
+
+    fn scrub(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                for pattern in &self.patterns {
+                    if pattern.is_match(text) {
+                        *text = pattern
+                            .replace_all(text, self.replacement.as_str())
+                            .into_owned();
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.scrub(item)),
+            Value::Object(fields) => fields.values_mut().for_each(|field| self.scrub(field)),
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl ProviderMiddleware for ScrubMiddleware {
+    async fn on_request(&self, request: &mut MiddlewareRequest) -> Result<()> {
+        if let Some(payload) = &mut request.payload {
+            self.scrub(payload);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(payload: Value) -> MiddlewareRequest {
+        MiddlewareRequest {
+            provider: "openai".to_string(),
+            method: Method::POST,
+            url: Url::parse("https://api.openai.com/v1/chat/completions").unwrap(),
+            headers: HeaderMap::new(),
+            payload: Some(payload),
+        }
+    }
+
+    #[test]
+    fn test_registrations_for_provider() {
+        let registrations: HashMap<String, Vec<MiddlewareConfig>> = serde_yaml::from_str(
+            r#"
+"*":
+  - type: scrub
+    patterns: ["secret"]
+openai:
+  - type: log
+  - type: headers
+    headers:
+      X-Team: platform
+anthropic:
+  - type: log
+    bodies: true
+"#,
+        )
+        .unwrap();
+
+        let openai = for_provider(&registrations, "openai");
+        assert_eq!(openai.len(), 3);
+        assert!(matches!(openai[0], MiddlewareConfig::Scrub { .. }));
+        assert_eq!(openai[1], &MiddlewareConfig::Log { bodies: false });
+        assert_eq!(for_provider(&registrations, "groq").len(), 1);
+    }
+
+    #[test]
+    fn test_bad_scrub_pattern_is_an_error() {
+        let config = MiddlewareConfig::Scrub {
+            patterns: vec!["(unclosed".to_string()],
+            replacement: None,
+        };
+        assert!(config.build().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scrub_and_headers() {
+        let scrub = ScrubMiddleware::new(&["sk-[a-z0-9]+".to_string()], "[KEY]").unwrap();
+        let headers = HeaderMiddleware::new(&HashMap::from([(
+            "X-Team".to_string(),
+            "platform".to_string(),
+        )]))
+        .unwrap();
+        let mut request = request(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "my key is sk-abc123, keep it safe"}],
+        }));
+
+        scrub.on_request(&mut request).await.unwrap();
+        headers.on_request(&mut request).await.unwrap();
+
+        let payload = request.payload.unwrap();
+        assert_eq!(
+            payload["messages"][0]["content"],
+            "my key is [KEY], keep it safe"
+        );
+        assert_eq!(payload["model"], "gpt-4o");
+        assert_eq!(request.headers["x-team"], "platform");
+    }
+
+    struct Rename;
+
+    #[async_trait]
+    impl ProviderMiddleware for Rename {
+        async fn on_response(
+            &self,
+            _request: &MiddlewareRequest,
+            response: &mut MiddlewareResponse,
+        ) -> Result<()> {
+            if let Some(payload) = &mut response.payload {
+                payload["model"] = json!("renamed");
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_is_rebuilt_from_the_changed_payload() {
+        let original = http::Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, "application/json")
+            .body(r#"{"model": "gpt-4o", "choices": []}"#)
+            .unwrap();
+        let middleware: Vec<Arc<dyn ProviderMiddleware>> = vec![Arc::new(Rename)];
+
+        let response = handle_response(&middleware, &request(json!({})), Response::from(original))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let payload: Value = response.json().await.unwrap();
+        assert_eq!(payload["model"], "renamed");
+    }
+}
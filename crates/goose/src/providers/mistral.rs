@@ -0,0 +1,358 @@
+use anyhow::Result;
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use serde_json::{json, Value};
+use std::io;
+use tokio::pin;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{FramedRead, LinesCodec};
+use tokio_util::io::StreamReader;
+
+use super::api_client::{ApiClient, AuthMethod};
+use super::base::{
+    ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage, ToolChoice,
+    Usage,
+};
+use super::errors::ProviderError;
+use super::formats::openai::{
+    create_request, get_usage, response_to_message, response_to_streaming_message,
+};
+use super::retry::{ProviderRetry, RetryPolicy};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
+    ImageFormat,
+};
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+pub const MISTRAL_API_HOST: &str = "https://api.mistral.ai";
+pub const MISTRAL_DEFAULT_MODEL: &str = "mistral-large-latest";
+/// Known models with their context window and USD cost per input and output token
+pub const MISTRAL_KNOWN_MODELS: &[(&str, usize, f64, f64)] = &[
+    ("mistral-large-latest", 131_072, 0.000002, 0.000006),
+    ("mistral-medium-latest", 131_072, 0.0000004, 0.000002),
+    ("mistral-small-latest", 131_072, 0.0000001, 0.0000003),
+    ("codestral-latest", 256_000, 0.0000003, 0.0000009),
+    ("devstral-small-latest", 131_072, 0.0000001, 0.0000003),
+    ("magistral-medium-latest", 40_000, 0.000002, 0.000005),
+    ("ministral-8b-latest", 131_072, 0.0000001, 0.0000001),
+    ("pixtral-large-latest", 131_072, 0.000002, 0.000006),
+];
+
+pub const MISTRAL_DOC_URL: &str = "https://docs.mistral.ai/getting-started/models/models_overview/";
+
+/// Length of the tool call ids Mistral accepts, which must also be alphanumeric
+const TOOL_CALL_ID_LEN: usize = 9;
+
+/// Mistral's La Plateforme API, with native function calling
+#[derive(serde::Serialize)]
+pub struct MistralProvider {
+    #[serde(skip)]
+    api_client: ApiClient,
+    model: ModelConfig,
+    /// Whether Mistral prepends its guardrail system prompt to each request
+    safe_prompt: bool,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
+}
+
+impl_provider_default!(MistralProvider);
+
+impl MistralProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("MISTRAL_API_KEY")?;
+        let host: String = config
+            .get_param("MISTRAL_HOST")
+            .unwrap_or_else(|_| MISTRAL_API_HOST.to_string());
+        let safe_prompt = config.get_param("MISTRAL_SAFE_PROMPT").unwrap_or(false);
+
+        let auth = AuthMethod::BearerToken(api_key);
+        let api_client = ApiClient::new(host, auth)?.with_configured_middleware("mistral")?;
+
+        Ok(Self {
+            api_client,
+            model,
+            safe_prompt,
+            retry_policy: RetryPolicy::from_config(config, "MISTRAL"),
+        })
+    }
+
+    /// The chat completion request for a turn, in Mistral's dialect of the OpenAI format
+    fn chat_payload(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<Value, ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        adapt_request(&mut payload, tool_choice, self.safe_prompt);
+        Ok(payload)
+    }
+
+    async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
+        tracing::debug!("Mistral request model: {:?}", self.model.model_name);
+
+        let response = self
+            .api_client
+            .response_post("v1/chat/completions", payload)
+            .await?;
+        handle_response_openai_compat(response).await
+    }
+}
+
+/// A tool call id Mistral accepts: ids it issued pass through, others (from another provider
+/// or the toolshim) are replaced by a stable digest so requests and results still pair up
+fn mistral_tool_call_id(id: &str) -> String {
+    if id.len() == TOOL_CALL_ID_LEN && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return id.to_string();
+    }
+    blake3::hash(id.as_bytes()).to_hex()[..TOOL_CALL_ID_LEN].to_string()
+}
+
+/// Turn an OpenAI chat request into one Mistral accepts
+fn adapt_request(payload: &mut Value, tool_choice: &ToolChoice, safe_prompt: bool) {
+    let Some(obj) = payload.as_object_mut() else {
+        return;
+    };
+
+    // Mistral names the sampling seed differently
+    if let Some(seed) = obj.remove("seed") {
+        obj.insert("random_seed".to_string(), seed);
+    }
+    if safe_prompt {
+        obj.insert("safe_prompt".to_string(), json!(true));
+    }
+    if obj.contains_key("tools") {
+        match tool_choice {
+            ToolChoice::Auto => {}
+            ToolChoice::None => {
+                obj.insert("tool_choice".to_string(), json!("none"));
+            }
+            ToolChoice::Required => {
+                obj.insert("tool_choice".to_string(), json!("any"));
+            }
+            ToolChoice::Tool(name) => {
+                obj.insert(
+                    "tool_choice".to_string(),
+                    json!({"type": "function", "function": {"name": name}}),
+                );
+            }
+        }
+    }
+
+    let Some(messages) = obj.get_mut("messages").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for message in messages {
+        if let Some(calls) = message.get_mut("tool_calls").and_then(Value::as_array_mut) {
+            for call in calls {
+                if let Some(id) = call.get("id").and_then(Value::as_str) {
+                    call["id"] = json!(mistral_tool_call_id(id));
+                }
+            }
+        }
+        if let Some(id) = message.get("tool_call_id").and_then(Value::as_str) {
+            message["tool_call_id"] = json!(mistral_tool_call_id(id));
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for MistralProvider {
+    fn metadata() -> ProviderMetadata {
+        let models = MISTRAL_KNOWN_MODELS
+            .iter()
+            .map(|(name, limit, input_cost, output_cost)| {
+                ModelInfo::with_cost(*name, *limit, *input_cost, *output_cost)
+            })
+            .collect();
+        ProviderMetadata::with_models(
+            "mistral",
+            "Mistral AI",
+            "Mistral Large, Codestral and other models from Mistral's La Plateforme",
+            MISTRAL_DEFAULT_MODEL,
+            models,
+            MISTRAL_DOC_URL,
+            vec![
+                ConfigKey::new("MISTRAL_API_KEY", true, true, None),
+                ConfigKey::new("MISTRAL_HOST", false, false, Some(MISTRAL_API_HOST)),
+                ConfigKey::new("MISTRAL_SAFE_PROMPT", false, false, Some("false")),
+            ],
+        )
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self.chat_payload(system, messages, tools, tool_choice)?;
+
+        let response = self.with_retry(|| self.post(&payload)).await?;
+
+        let message = response_to_message(&response)?;
+        let usage = response.get("usage").map(get_usage).unwrap_or_else(|| {
+            tracing::debug!("Failed to get usage data");
+            Usage::default()
+        });
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    /// Models from the API that support function calling
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        let response = self.api_client.response_get("v1/models").await?;
+        let json = handle_response_openai_compat(response).await?;
+        let data = json.get("data").and_then(|v| v.as_array()).ok_or_else(|| {
+            ProviderError::UsageError("Missing data field in JSON response".into())
+        })?;
+
+        let mut models: Vec<String> = data
+            .iter()
+            .filter(|model| {
+                model
+                    .pointer("/capabilities/function_calling")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true)
+            })
+            .filter_map(|model| model.get("id").and_then(|v| v.as_str()).map(str::to_string))
+            .collect();
+        models.sort();
+        models.dedup();
+        Ok(Some(models))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<MessageStream, ProviderError> {
+        let mut payload = self.chat_payload(system, messages, tools, tool_choice)?;
+        // Mistral reports usage on the last chunk without being asked
+        payload["stream"] = Value::Bool(true);
+
+        let response = self
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post("v1/chat/completions", &payload)
+                    .await?;
+                handle_status_openai_compat(response).await
+            })
+            .await?;
+
+        let stream = response.bytes_stream().map_err(io::Error::other);
+
+        let model_config = self.model.clone();
+
+        Ok(Box::pin(try_stream! {
+            let stream_reader = StreamReader::new(stream);
+            let framed = FramedRead::new(stream_reader, LinesCodec::new()).map_err(anyhow::Error::from);
+
+            let message_stream = response_to_streaming_message(framed);
+            pin!(message_stream);
+            while let Some(message) = message_stream.next().await {
+                let (message, usage) = message.map_err(|e| ProviderError::RequestFailed(format!("Stream decode error: {}", e)))?;
+                emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
+                yield (message, usage);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_call_ids_are_made_mistral_compatible() {
+        assert_eq!(mistral_tool_call_id("D681PevKs"), "D681PevKs");
+
+        let id = mistral_tool_call_id("toolu_01A09q90qw90lq917835lq9");
+        assert_eq!(id.len(), TOOL_CALL_ID_LEN);
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(id, mistral_tool_call_id("toolu_01A09q90qw90lq917835lq9"));
+    }
+
+    #[test]
+    fn test_adapt_request() {
+        let mut payload = json!({
+            "model": "mistral-large-latest",
+            "seed": 7,
+            "messages": [
+                {"role": "assistant", "tool_calls": [{"id": "call_abc-123", "type": "function"}]},
+                {"role": "tool", "tool_call_id": "call_abc-123", "content": "ok"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "shell"}}]
+        });
+
+        adapt_request(&mut payload, &ToolChoice::Required, true);
+
+        assert_eq!(payload["random_seed"], json!(7));
+        assert!(payload.get("seed").is_none());
+        assert_eq!(payload["safe_prompt"], json!(true));
+        assert_eq!(payload["tool_choice"], json!("any"));
+        let call_id = payload["messages"][0]["tool_calls"][0]["id"].clone();
+        assert_eq!(call_id.as_str().unwrap().len(), TOOL_CALL_ID_LEN);
+        assert_eq!(payload["messages"][1]["tool_call_id"], call_id);
+    }
+
+    #[test]
+    fn test_tool_choice_needs_tools() {
+        let mut payload = json!({"model": "codestral-latest", "messages": []});
+        adapt_request(&mut payload, &ToolChoice::Required, false);
+        assert!(payload.get("tool_choice").is_none());
+        assert!(payload.get("safe_prompt").is_none());
+    }
+}
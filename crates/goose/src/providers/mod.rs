@@ -2,35 +2,56 @@ pub mod anthropic;
 mod api_client;
 pub mod azure;
 pub mod azureauth;
+pub mod balanced;
 pub mod base;
 pub mod bedrock;
 pub mod claude_code;
+pub mod custom_openai;
 pub mod databricks;
+pub mod deepseek;
+pub mod delta;
 pub mod embedding;
 pub mod errors;
 mod factory;
+pub mod fallback;
 pub mod formats;
+pub mod gbnf;
 mod gcpauth;
 pub mod gcpvertexai;
 pub mod gemini_cli;
 pub mod githubcopilot;
 pub mod google;
 pub mod groq;
+pub mod health;
+pub mod huggingface;
+pub mod hybrid;
 pub mod lead_worker;
 pub mod litellm;
+#[cfg(feature = "llama-cpp")]
+pub mod llamacpp;
+#[cfg(feature = "local-embeddings")]
+pub mod local_embedding;
+pub mod middleware;
+pub mod mistral;
+pub mod model_registry;
 pub mod oauth;
 pub mod ollama;
 pub mod openai;
+pub mod openai_batch;
 pub mod openrouter;
 pub mod pricing;
-mod retry;
+pub mod prompt_cache;
+pub mod replay;
+pub mod retry;
 pub mod sagemaker_tgi;
 pub mod snowflake;
+pub mod structured;
 pub mod testprovider;
 pub mod toolshim;
 pub mod utils;
 pub mod utils_universal_openai_stream;
 pub mod venice;
+pub mod vision;
 pub mod xai;
 
 pub use factory::{create, providers};
@@ -0,0 +1,523 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::base::{ModelInfo, Provider};
+use crate::config::Config;
+
+/// Disk cache configuration
+const CACHE_FILE_NAME: &str = "model_registry_cache.json";
+const CACHE_TTL_SECS: u64 = 24 * 60 * 60; // Cache for 1 day
+
+/// Get the cache directory path
+fn get_cache_dir() -> Result<PathBuf> {
+    let cache_dir = if let Ok(goose_dir) = std::env::var("GOOSE_CACHE_DIR") {
+        PathBuf::from(goose_dir)
+    } else {
+        dirs::cache_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine cache directory"))?
+            .join("goose")
+    };
+    Ok(cache_dir)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Model metadata fetched from a provider API, with the time it was fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedModelInfo {
+    pub info: ModelInfo,
+    /// Unix timestamp when data was fetched
+    pub fetched_at: u64,
+}
+
+impl CachedModelInfo {
+    fn is_fresh(&self, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) < CACHE_TTL_SECS
+    }
+}
+
+/// Prompt sizes a model has accepted and rejected at one endpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObservedContextLimit {
+    /// Largest prompt, in tokens, that was accepted
+    pub accepted: Option<usize>,
+    /// Smallest prompt, in tokens, that was rejected as too long
+    pub rejected: Option<usize>,
+    /// Unix timestamp of the last observation
+    pub updated_at: u64,
+}
+
+impl ObservedContextLimit {
+    /// Narrow the configured context limit with what the endpoint actually did
+    pub fn effective_limit(&self, configured: usize) -> usize {
+        let mut limit = configured;
+        if let Some(rejected) = self.rejected {
+            limit = limit.min(rejected.saturating_sub(1));
+        }
+        if let Some(accepted) = self.accepted {
+            limit = limit.max(accepted);
+        }
+        limit
+    }
+}
+
+/// Cached model data structure for disk storage. Entries are keyed by `provider/model`, since
+/// the same model name can mean different limits and capabilities at different providers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedModelData {
+    pub models: HashMap<String, CachedModelInfo>,
+    /// Learned context bounds; these do not expire
+    #[serde(default)]
+    pub observed: HashMap<String, ObservedContextLimit>,
+}
+
+fn model_key(provider: &str, model_name: &str) -> String {
+    format!("{}/{}", provider, model_name)
+}
+
+/// The provider goose is configured to use. Lookups made where the serving provider isn't
+/// known are keyed by it.
+pub fn configured_provider() -> Option<String> {
+    Config::global().get_param::<String>("GOOSE_PROVIDER").ok()
+}
+
+/// Registry of model metadata reported by provider APIs.
+///
+/// Context windows fetched here take precedence over the built-in pattern table in
+/// [`ModelConfig`](crate::model::ModelConfig) when the provider factory builds a provider, so
+/// newly released models get accurate limits without a goose release.
+pub struct ModelRegistry {
+    /// In-memory cache, loaded lazily from disk
+    memory_cache: RwLock<Option<CachedModelData>>,
+    cache_path: Option<PathBuf>,
+    /// When each provider was last queried, so it is asked at most once per TTL
+    refreshed_at: Mutex<HashMap<String, u64>>,
+}
+
+static MODEL_REGISTRY: Lazy<ModelRegistry> =
+    Lazy::new(|| ModelRegistry::new(get_cache_dir().ok().map(|d| d.join(CACHE_FILE_NAME))));
+
+impl ModelRegistry {
+    /// Create a registry backed by the given cache file, or memory only when `None`
+    pub fn new(cache_path: Option<PathBuf>) -> Self {
+        Self {
+            memory_cache: RwLock::new(None),
+            cache_path,
+            refreshed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The process-wide registry, persisted in the goose cache directory
+    pub fn global() -> &'static ModelRegistry {
+        &MODEL_REGISTRY
+    }
+
+    fn load_from_disk(&self) -> CachedModelData {
+        let Some(path) = &self.cache_path else {
+            return CachedModelData::default();
+        };
+        match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                tracing::warn!("Failed to parse model registry cache: {}", e);
+                CachedModelData::default()
+            }),
+            Err(_) => CachedModelData::default(),
+        }
+    }
+
+    async fn save_to_disk(&self, data: &CachedModelData) -> Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(path, serde_json::to_vec_pretty(data)?).await?;
+        tracing::debug!("Saved model registry to disk cache");
+        Ok(())
+    }
+
+    /// Run `f` against the cached data, loading it from disk on first use
+    fn with_cache<T>(&self, f: impl FnOnce(&CachedModelData) -> T) -> T {
+        {
+            let cache = self.memory_cache.read().unwrap_or_else(|e| e.into_inner());
+            if let Some(data) = cache.as_ref() {
+                return f(data);
+            }
+        }
+        let mut cache = self.memory_cache.write().unwrap_or_else(|e| e.into_inner());
+        let data = cache.get_or_insert_with(|| self.load_from_disk());
+        f(data)
+    }
+
+    /// Get fresh metadata for a model, if its provider has reported it
+    pub fn get(&self, provider: &str, model_name: &str) -> Option<ModelInfo> {
+        let now = now_secs();
+        let key = model_key(provider, model_name);
+        self.with_cache(|data| {
+            data.models
+                .get(&key)
+                .filter(|cached| cached.is_fresh(now))
+                .map(|cached| cached.info.clone())
+        })
+    }
+
+    /// Get fresh metadata for a model as served by the configured provider
+    pub fn get_configured(&self, model_name: &str) -> Option<ModelInfo> {
+        configured_provider().and_then(|provider| self.get(&provider, model_name))
+    }
+
+    /// Get the fetched context window for a model, if known
+    pub fn context_limit(&self, provider: &str, model_name: &str) -> Option<usize> {
+        self.get(provider, model_name)
+            .map(|info| info.context_limit)
+    }
+
+    /// Get the reported knowledge cutoff for a model, if known
+    pub fn knowledge_cutoff(&self, provider: &str, model_name: &str) -> Option<String> {
+        self.get(provider, model_name)
+            .and_then(|info| info.knowledge_cutoff)
+    }
+
+    /// All models a provider has fresh metadata for, sorted by name
+    pub fn models(&self, provider: &str) -> Vec<ModelInfo> {
+        let now = now_secs();
+        let prefix = model_key(provider, "");
+        let mut models: Vec<ModelInfo> = self.with_cache(|data| {
+            data.models
+                .iter()
+                .filter(|(key, cached)| key.starts_with(&prefix) && cached.is_fresh(now))
+                .map(|(_, cached)| cached.info.clone())
+                .collect()
+        });
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        models
+    }
+
+    /// Apply `f` to the cached data and persist it if `f` reports a change
+    async fn update(&self, f: impl FnOnce(&mut CachedModelData) -> bool) -> Result<()> {
+        let snapshot = {
+            // Make sure entries persisted by earlier runs are merged rather than overwritten
+            self.with_cache(|_| ());
+            let mut cache = self.memory_cache.write().unwrap_or_else(|e| e.into_inner());
+            let data = cache.get_or_insert_with(CachedModelData::default);
+            if !f(data) {
+                return Ok(());
+            }
+            data.clone()
+        };
+        self.save_to_disk(&snapshot).await
+    }
+
+    /// Record metadata a provider reported for its models and persist it to disk
+    pub async fn insert(&self, provider: &str, models: Vec<ModelInfo>) -> Result<()> {
+        let now = now_secs();
+        self.update(|data| {
+            data.models.retain(|_, cached| cached.is_fresh(now));
+            for info in models {
+                data.models.insert(
+                    model_key(provider, &info.name),
+                    CachedModelInfo {
+                        info,
+                        fetched_at: now,
+                    },
+                );
+            }
+            true
+        })
+        .await
+    }
+
+    /// Context bounds learned for a model at an endpoint, if any
+    pub fn observed_context_limit(
+        &self,
+        endpoint: &str,
+        model_name: &str,
+    ) -> Option<ObservedContextLimit> {
+        let key = model_key(endpoint, model_name);
+        self.with_cache(|data| data.observed.get(&key).cloned())
+    }
+
+    /// Record that the endpoint accepted a prompt of `prompt_tokens`
+    pub async fn record_accepted(
+        &self,
+        endpoint: &str,
+        model_name: &str,
+        prompt_tokens: usize,
+    ) -> Result<()> {
+        let key = model_key(endpoint, model_name);
+        self.update(|data| {
+            let observed = data.observed.entry(key).or_default();
+            if observed.accepted.is_some_and(|a| a >= prompt_tokens) {
+                return false;
+            }
+            observed.accepted = Some(prompt_tokens);
+            // A larger prompt went through, so an earlier rejection no longer holds
+            if observed.rejected.is_some_and(|r| r <= prompt_tokens) {
+                observed.rejected = None;
+            }
+            observed.updated_at = now_secs();
+            true
+        })
+        .await
+    }
+
+    /// Record that the endpoint rejected a prompt of `prompt_tokens` as too long
+    pub async fn record_rejected(
+        &self,
+        endpoint: &str,
+        model_name: &str,
+        prompt_tokens: usize,
+    ) -> Result<()> {
+        let key = model_key(endpoint, model_name);
+        self.update(|data| {
+            let observed = data.observed.entry(key).or_default();
+            if observed.rejected.is_some_and(|r| r <= prompt_tokens) {
+                return false;
+            }
+            observed.rejected = Some(prompt_tokens);
+            // The limit has shrunk below what was accepted before
+            if observed.accepted.is_some_and(|a| a >= prompt_tokens) {
+                observed.accepted = None;
+            }
+            observed.updated_at = now_secs();
+            true
+        })
+        .await
+    }
+
+    /// Query the configured provider for model metadata and cache it under `GOOSE_PROVIDER`.
+    ///
+    /// Does nothing when no provider is configured; see [`Self::refresh_provider`].
+    pub async fn refresh_from_provider(&self, provider: &dyn Provider) -> Result<usize> {
+        match configured_provider() {
+            Some(provider_name) => self.refresh_provider(&provider_name, provider).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Query the provider for model metadata and cache it under `provider_name`.
+    ///
+    /// Skips the request while the provider's configured model is still fresh in the cache, or
+    /// when the provider was already queried within the TTL, whatever that query returned.
+    /// Returns the number of models fetched, which is zero when the request was skipped or the
+    /// provider does not expose model metadata.
+    pub async fn refresh_provider(
+        &self,
+        provider_name: &str,
+        provider: &dyn Provider,
+    ) -> Result<usize> {
+        let model_name = provider.get_model_config().model_name;
+        if !self.begin_refresh(provider_name, &model_name) {
+            return Ok(0);
+        }
+        self.fetch_from_provider(provider_name, provider).await
+    }
+
+    /// Claim the refresh of `provider_name` for this TTL period. Returns false when the model is
+    /// already cached or the provider was queried recently, so callers can skip the request
+    /// without spawning it.
+    pub(crate) fn begin_refresh(&self, provider_name: &str, model_name: &str) -> bool {
+        if self.get(provider_name, model_name).is_some() {
+            return false;
+        }
+        let now = now_secs();
+        let mut refreshed_at = self.refreshed_at.lock().unwrap_or_else(|e| e.into_inner());
+        if refreshed_at
+            .get(provider_name)
+            .is_some_and(|at| now.saturating_sub(*at) < CACHE_TTL_SECS)
+        {
+            return false;
+        }
+        refreshed_at.insert(provider_name.to_string(), now);
+        true
+    }
+
+    /// Fetch and cache the provider's model metadata, after [`Self::begin_refresh`] claimed it
+    pub(crate) async fn fetch_from_provider(
+        &self,
+        provider_name: &str,
+        provider: &dyn Provider,
+    ) -> Result<usize> {
+        let Some(models) = provider.fetch_model_info().await? else {
+            return Ok(0);
+        };
+        let count = models.len();
+        tracing::debug!("Fetched metadata for {} models from provider", count);
+        self.insert(provider_name, models).await?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::Message;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage};
+    use crate::providers::errors::ProviderError;
+    use async_trait::async_trait;
+    use rmcp::model::Tool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockProvider {
+        model_config: ModelConfig,
+        fetches: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            unimplemented!()
+        }
+
+        async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(vec![
+                ModelInfo::new("brand-new-model", 400_000),
+                ModelInfo::new("other-model", 32_000),
+            ]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_from_provider_uses_ttl_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_FILE_NAME);
+        let registry = ModelRegistry::new(Some(path.clone()));
+        let provider = MockProvider {
+            model_config: ModelConfig::new_or_fail("brand-new-model"),
+            fetches: AtomicUsize::new(0),
+        };
+
+        assert_eq!(registry.context_limit("mock", "brand-new-model"), None);
+        assert_eq!(
+            registry.refresh_provider("mock", &provider).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            registry.context_limit("mock", "brand-new-model"),
+            Some(400_000)
+        );
+
+        // The configured model is fresh, so no second request is made
+        assert_eq!(
+            registry.refresh_provider("mock", &provider).await.unwrap(),
+            0
+        );
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+
+        // A new registry picks the results up from disk
+        let reloaded = ModelRegistry::new(Some(path));
+        assert_eq!(reloaded.context_limit("mock", "other-model"), Some(32_000));
+        assert_eq!(reloaded.models("mock").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_queries_each_provider_once_per_ttl() {
+        let registry = ModelRegistry::new(None);
+        let provider = MockProvider {
+            model_config: ModelConfig::new_or_fail("unlisted-model"),
+            fetches: AtomicUsize::new(0),
+        };
+
+        // The configured model never shows up in the cache, but the provider is only asked once
+        for _ in 0..3 {
+            registry.refresh_provider("mock", &provider).await.unwrap();
+        }
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+
+        registry.refresh_provider("other", &provider).await.unwrap();
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_models_are_keyed_by_provider() {
+        let registry = ModelRegistry::new(None);
+        registry
+            .insert("openrouter", vec![ModelInfo::new("qwen3", 128_000)])
+            .await
+            .unwrap();
+        registry
+            .insert("ollama", vec![ModelInfo::new("qwen3", 40_000)])
+            .await
+            .unwrap();
+
+        assert_eq!(registry.context_limit("openrouter", "qwen3"), Some(128_000));
+        assert_eq!(registry.context_limit("ollama", "qwen3"), Some(40_000));
+        assert_eq!(registry.context_limit("groq", "qwen3"), None);
+        assert_eq!(registry.models("ollama").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_observed_context_limits() {
+        let registry = ModelRegistry::new(None);
+        assert_eq!(registry.observed_context_limit("ollama", "qwen3"), None);
+
+        registry
+            .record_rejected("ollama", "qwen3", 40_000)
+            .await
+            .unwrap();
+        let observed = registry.observed_context_limit("ollama", "qwen3").unwrap();
+        assert_eq!(observed.effective_limit(128_000), 39_999);
+
+        // Smaller accepted prompts don't change the bound, larger ones lift it
+        registry
+            .record_accepted("ollama", "qwen3", 30_000)
+            .await
+            .unwrap();
+        let observed = registry.observed_context_limit("ollama", "qwen3").unwrap();
+        assert_eq!(observed.effective_limit(128_000), 39_999);
+
+        registry
+            .record_accepted("ollama", "qwen3", 50_000)
+            .await
+            .unwrap();
+        let observed = registry.observed_context_limit("ollama", "qwen3").unwrap();
+        assert_eq!(observed.rejected, None);
+        assert_eq!(observed.effective_limit(32_000), 50_000);
+
+        // Observations are per endpoint
+        assert_eq!(registry.observed_context_limit("openrouter", "qwen3"), None);
+    }
+
+    #[test]
+    fn test_expired_entries_are_ignored() {
+        let registry = ModelRegistry::new(None);
+        let mut data = CachedModelData::default();
+        data.models.insert(
+            model_key("mock", "stale-model"),
+            CachedModelInfo {
+                info: ModelInfo::new("stale-model", 64_000),
+                fetched_at: now_secs() - CACHE_TTL_SECS - 1,
+            },
+        );
+        *registry.memory_cache.write().unwrap() = Some(data);
+
+        assert_eq!(registry.context_limit("mock", "stale-model"), None);
+        assert!(registry.models("mock").is_empty());
+    }
+}
@@ -1,19 +1,21 @@
 use super::api_client::{ApiClient, AuthMethod};
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{get_model, handle_response_openai_compat};
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::model_registry::ModelRegistry;
 use crate::utils::safe_truncate;
 use anyhow::Result;
 use async_trait::async_trait;
 use regex::Regex;
 use rmcp::model::Tool;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::net::{TcpStream, ToSocketAddrs};
 use std::time::Duration;
 use url::Url;
 
@@ -24,12 +26,17 @@ pub const OLLAMA_DEFAULT_MODEL: &str = "qwen2.5";
 // Ollama can run many models, we only provide the default
 pub const OLLAMA_KNOWN_MODELS: &[&str] = &[OLLAMA_DEFAULT_MODEL];
 pub const OLLAMA_DOC_URL: &str = "https://ollama.com/library";
+/// Where to look for a local server when OLLAMA_HOST is not configured
+const OLLAMA_DISCOVERY_HOSTS: &[&str] = &["localhost", "127.0.0.1", "host.docker.internal"];
+const OLLAMA_DISCOVERY_TIMEOUT: Duration = Duration::from_millis(200);
 
 #[derive(serde::Serialize)]
 pub struct OllamaProvider {
     #[serde(skip)]
     api_client: ApiClient,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(OllamaProvider);
@@ -39,7 +46,7 @@ impl OllamaProvider {
         let config = crate::config::Config::global();
         let host: String = config
             .get_param("OLLAMA_HOST")
-            .unwrap_or_else(|_| OLLAMA_HOST.to_string());
+            .unwrap_or_else(|_| Self::discover_host());
 
         let timeout: Duration =
             Duration::from_secs(config.get_param("OLLAMA_TIMEOUT").unwrap_or(OLLAMA_TIMEOUT));
@@ -69,9 +76,59 @@ impl OllamaProvider {
 
         // No authentication for Ollama
         let auth = AuthMethod::Custom(Box::new(NoAuth));
-        let api_client = ApiClient::with_timeout(base_url.to_string(), auth, timeout)?;
+        let api_client = ApiClient::with_timeout(base_url.to_string(), auth, timeout)?
+            .with_configured_middleware("ollama")?;
+
+        Ok(Self {
+            api_client,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
+        })
+    }
 
-        Ok(Self { api_client, model })
+    /// The first of the usual places with a server listening on the default port
+    fn discover_host() -> String {
+        OLLAMA_DISCOVERY_HOSTS
+            .iter()
+            .find(|host| {
+                (**host, OLLAMA_DEFAULT_PORT)
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .is_some_and(|addr| {
+                        TcpStream::connect_timeout(&addr, OLLAMA_DISCOVERY_TIMEOUT).is_ok()
+                    })
+            })
+            .inspect(|host| tracing::debug!("Found Ollama at {}", host))
+            .unwrap_or(&OLLAMA_HOST)
+            .to_string()
+    }
+
+    /// Names of the models installed on the server
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        let response = self.api_client.response_get("api/tags").await?;
+        let json: Value = response.json().await?;
+        let mut models: Vec<String> = json
+            .get("models")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| m.get("name").and_then(|v| v.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        models.sort();
+        Ok(models)
+    }
+
+    async fn show_model(&self, name: &str) -> Result<Option<ModelInfo>, ProviderError> {
+        let response = self
+            .api_client
+            .response_post("api/show", &json!({ "model": name }))
+            .await?;
+        let json: Value = response.json().await?;
+        Ok(parse_show_response(name, &json))
     }
 
     async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
@@ -105,7 +162,7 @@ impl Provider for OllamaProvider {
             OLLAMA_KNOWN_MODELS.to_vec(),
             OLLAMA_DOC_URL,
             vec![
-                ConfigKey::new("OLLAMA_HOST", true, false, Some(OLLAMA_HOST)),
+                ConfigKey::new("OLLAMA_HOST", false, false, Some(OLLAMA_HOST)),
                 ConfigKey::new(
                     "OLLAMA_TIMEOUT",
                     false,
@@ -116,8 +173,21 @@ impl Provider for OllamaProvider {
         )
     }
 
+    /// The configured model, with toolshim turned on when the server reports the model has no
+    /// native tool support and GOOSE_TOOLSHIM is not set
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
-        self.model.clone()
+        let mut model = self.model.clone();
+        if !model.toolshim && std::env::var("GOOSE_TOOLSHIM").is_err() {
+            model.toolshim = ModelRegistry::global()
+                .get("ollama", &model.model_name)
+                .and_then(|info| info.supports_tools)
+                == Some(false);
+        }
+        model
     }
 
     #[tracing::instrument(
@@ -158,6 +228,23 @@ impl Provider for OllamaProvider {
         Ok((message, ProviderUsage::new(model, usage)))
     }
 
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.list_models().await.map(Some)
+    }
+
+    /// Context windows and tool support of the installed models, from `ollama show`
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let mut models = Vec::new();
+        for name in self.list_models().await? {
+            match self.show_model(&name).await {
+                Ok(Some(info)) => models.push(info),
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Failed to show Ollama model {}: {}", name, e),
+            }
+        }
+        Ok(Some(models))
+    }
+
     /// Generate a session name based on the conversation history
     /// This override filters out reasoning tokens that some Ollama models produce
     async fn generate_session_name(
@@ -224,3 +311,97 @@ impl OllamaProvider {
         filtered
     }
 }
+
+/// Read a model's context window and tool support from an `api/show` response.
+///
+/// A `num_ctx` parameter in the Modelfile is what the server actually runs with, so it wins
+/// over the context length the model was trained for.
+fn parse_show_response(name: &str, json: &Value) -> Option<ModelInfo> {
+    let num_ctx = json
+        .get("parameters")
+        .and_then(|v| v.as_str())
+        .and_then(|parameters| {
+            parameters.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                (parts.next() == Some("num_ctx"))
+                    .then(|| parts.next()?.parse::<usize>().ok())
+                    .flatten()
+            })
+        });
+    let trained = json
+        .get("model_info")
+        .and_then(|v| v.as_object())
+        .and_then(|info| {
+            info.iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .and_then(|(_, v)| v.as_u64())
+        })
+        .map(|limit| limit as usize);
+    let context_limit = num_ctx.or(trained)?;
+
+    // Older servers don't report capabilities; their templates mention .Tools when they can
+    let supports_tools = match json.get("capabilities").and_then(|v| v.as_array()) {
+        Some(capabilities) => Some(capabilities.iter().any(|c| c.as_str() == Some("tools"))),
+        None => json
+            .get("template")
+            .and_then(|v| v.as_str())
+            .map(|template| template.contains(".Tools")),
+    };
+
+    let supports_vision = json
+        .get("capabilities")
+        .and_then(|v| v.as_array())
+        .map(|capabilities| capabilities.iter().any(|c| c.as_str() == Some("vision")));
+
+    let mut info = ModelInfo::new(name, context_limit);
+    info.supports_tools = supports_tools;
+    info.supports_vision = supports_vision;
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_show_response() {
+        let json = json!({
+            "parameters": "stop \"<|im_end|>\"\nnum_ctx 16384",
+            "model_info": {"qwen2.context_length": 32768},
+            "capabilities": ["completion", "tools"]
+        });
+        let info = parse_show_response("qwen2.5:latest", &json).unwrap();
+        assert_eq!(info.name, "qwen2.5:latest");
+        assert_eq!(info.context_limit, 16384);
+        assert_eq!(info.supports_tools, Some(true));
+
+        let json = json!({
+            "model_info": {"gemma2.context_length": 8192},
+            "capabilities": ["completion"]
+        });
+        let info = parse_show_response("gemma2:9b", &json).unwrap();
+        assert_eq!(info.context_limit, 8192);
+        assert_eq!(info.supports_tools, Some(false));
+        assert_eq!(info.supports_vision, Some(false));
+
+        let json = json!({
+            "model_info": {"gemma3.context_length": 131072},
+            "capabilities": ["completion", "vision"]
+        });
+        let info = parse_show_response("gemma3:12b", &json).unwrap();
+        assert_eq!(info.supports_vision, Some(true));
+    }
+
+    #[test]
+    fn test_parse_show_response_from_older_servers() {
+        let json = json!({
+            "model_info": {"llama.context_length": 4096},
+            "template": "{{ if .Tools }}{{ .Tools }}{{ end }}{{ .Prompt }}"
+        });
+        let info = parse_show_response("llama3.1", &json).unwrap();
+        assert_eq!(info.supports_tools, Some(true));
+        assert_eq!(info.supports_vision, None);
+
+        assert!(parse_show_response("empty", &json!({})).is_none());
+    }
+}
@@ -12,19 +12,25 @@ use tokio_util::codec::{FramedRead, LinesCodec};
 use tokio_util::io::StreamReader;
 
 use super::api_client::{ApiClient, AuthMethod};
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, ToolChoice, Usage,
+};
 use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::formats::openai::{apply_tool_choice, create_request, get_usage, response_to_message};
+use super::retry::{ProviderRetry, RetryPolicy};
+use super::structured;
 use super::utils::{
     emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
     ImageFormat,
 };
+use super::vision::ImageLimits;
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
 use crate::providers::base::MessageStream;
 use crate::providers::formats::openai::response_to_streaming_message;
+use crate::providers::prompt_cache;
 use rmcp::model::Tool;
 
 pub const OPEN_AI_DEFAULT_MODEL: &str = "gpt-4o";
@@ -51,6 +57,10 @@ pub struct OpenAiProvider {
     project: Option<String>,
     model: ModelConfig,
     custom_headers: Option<HashMap<String, String>>,
+    /// Whether requests carry a prompt cache key, only understood by OpenAI itself
+    prompt_cache_key: bool,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(OpenAiProvider);
@@ -74,9 +84,12 @@ impl OpenAiProvider {
             .map(parse_custom_headers);
         let timeout_secs: u64 = config.get_param("OPENAI_TIMEOUT").unwrap_or(600);
 
+        let prompt_cache_key = host.contains("api.openai.com") && prompt_cache::is_enabled();
+
         let auth = AuthMethod::BearerToken(api_key);
         let mut api_client =
-            ApiClient::with_timeout(host, auth, std::time::Duration::from_secs(timeout_secs))?;
+            ApiClient::with_timeout(host, auth, std::time::Duration::from_secs(timeout_secs))?
+                .with_configured_middleware("openai")?;
 
         if let Some(org) = &organization {
             api_client = api_client.with_header("OpenAI-Organization", org)?;
@@ -103,9 +116,42 @@ impl OpenAiProvider {
             project,
             model,
             custom_headers,
+            prompt_cache_key,
+            retry_policy: RetryPolicy::from_config(config, "OPENAI"),
         })
     }
 
+    /// Route requests that share a system prompt and tools to the same prompt cache
+    fn with_prompt_cache_key(&self, mut payload: Value, system: &str, tools: &[Tool]) -> Value {
+        if self.prompt_cache_key {
+            payload["prompt_cache_key"] = Value::String(prompt_cache::cache_key(system, tools));
+        }
+        payload
+    }
+
+    /// The chat completion request for a turn, as sent to the API
+    pub(crate) fn chat_payload(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<Value, ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        apply_tool_choice(&mut payload, tool_choice);
+        Ok(self.with_prompt_cache_key(payload, system, tools))
+    }
+
+    pub(crate) fn api_client(&self) -> &ApiClient {
+        &self.api_client
+    }
+
+    /// The path chat completions are posted to, e.g. v1/chat/completions
+    pub(crate) fn base_path(&self) -> &str {
+        &self.base_path
+    }
+
     async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
         let response = self
             .api_client
@@ -141,23 +187,49 @@ impl Provider for OpenAiProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
 
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        true
+    }
+
+    fn image_limits(&self) -> ImageLimits {
+        ImageLimits {
+            max_bytes: 20 * 1024 * 1024,
+            max_dimension: 2048,
+        }
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_tool_choice(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+        tool_choice: &ToolChoice,
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        let payload = self.chat_payload(system, messages, tools, tool_choice)?;
 
-        let json_response = self.post(&payload).await?;
+        let json_response = self.with_retry(|| self.post(&payload)).await?;
 
         let message = response_to_message(&json_response)?;
         let usage = json_response
@@ -172,6 +244,39 @@ impl Provider for OpenAiProvider {
         Ok((message, ProviderUsage::new(model, usage)))
     }
 
+    /// Uses the API's JSON schema response format, falling back to asking again with the
+    /// validation errors in the rare case the answer still doesn't match
+    async fn complete_with_response_schema(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let mut payload = self.chat_payload(system, messages, &[], &ToolChoice::Auto)?;
+        payload["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": {"name": "response", "schema": schema},
+        });
+
+        let json_response = self.with_retry(|| self.post(&payload)).await?;
+        let message = response_to_message(&json_response)?;
+        let usage = json_response
+            .get("usage")
+            .map(get_usage)
+            .unwrap_or_default();
+        let provider_usage = ProviderUsage::new(get_model(&json_response), usage);
+        emit_debug_trace(&self.model, &payload, &json_response, &usage);
+
+        if let Some(value) = structured::extract_json(&message.as_concat_text()) {
+            match structured::validate(schema, &value) {
+                Ok(()) => return Ok((value, provider_usage)),
+                Err(errors) => tracing::debug!("Response didn't match the schema:\n{}", errors),
+            }
+        }
+        structured::complete_with_retries(self, system, messages, schema, Some(provider_usage))
+            .await
+    }
+
     async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
         let models_path = self.base_path.replace("v1/chat/completions", "v1/models");
         let response = self.api_client.response_get(&models_path).await?;
@@ -215,18 +320,32 @@ impl Provider for OpenAiProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        let mut payload =
-            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        self.stream_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    async fn stream_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<MessageStream, ProviderError> {
+        let mut payload = self.chat_payload(system, messages, tools, tool_choice)?;
         payload["stream"] = serde_json::Value::Bool(true);
         payload["stream_options"] = json!({
             "include_usage": true,
         });
 
         let response = self
-            .api_client
-            .response_post(&self.base_path, &payload)
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post(&self.base_path, &payload)
+                    .await?;
+                handle_status_openai_compat(response).await
+            })
             .await?;
-        let response = handle_status_openai_compat(response).await?;
 
         let stream = response.bytes_stream().map_err(io::Error::other);
 
@@ -247,7 +366,7 @@ impl Provider for OpenAiProvider {
     }
 }
 
-fn parse_custom_headers(s: String) -> HashMap<String, String> {
+pub(crate) fn parse_custom_headers(s: String) -> HashMap<String, String> {
     s.split(',')
         .filter_map(|header| {
             let mut parts = header.splitn(2, '=');
@@ -0,0 +1,393 @@
+//! Non-interactive runs through the OpenAI Batch API.
+//!
+//! Batched requests cost half as much as regular ones but may take up to a day, which suits
+//! recipes nobody is watching: scheduled jobs and `goose run --recipe ... --batch`. Each turn
+//! is uploaded as a one-request batch and polled until it finishes, and the result is handed
+//! back as an ordinary completion, so the agent loop and the session record it like any other.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
+use rmcp::model::Tool;
+use serde_json::{json, Value};
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, ToolChoice, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{get_usage, response_to_message};
+use super::openai::{OpenAiProvider, OPEN_AI_DEFAULT_MODEL, OPEN_AI_DOC_URL, OPEN_AI_KNOWN_MODELS};
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
+};
+use crate::conversation::message::Message;
+use crate::impl_provider_default;
+use crate::model::ModelConfig;
+
+/// Config key for the seconds between checks on a submitted batch
+pub const BATCH_POLL_INTERVAL_KEY: &str = "OPENAI_BATCH_POLL_INTERVAL";
+/// Config key for the seconds to wait on a batch before cancelling it
+pub const BATCH_TIMEOUT_KEY: &str = "OPENAI_BATCH_TIMEOUT";
+
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 30;
+/// A little over the completion window, which is the longest OpenAI takes
+const DEFAULT_TIMEOUT_SECS: u64 = 25 * 60 * 60;
+const COMPLETION_WINDOW: &str = "24h";
+
+/// The provider for a run in batch mode. Only OpenAI has a batch API goose can use.
+pub fn create(provider_name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    match provider_name {
+        "openai" => Ok(Arc::new(OpenAiBatchProvider::from_env(model)?)),
+        other => bail!(
+            "Batch mode needs the openai provider, but this run uses {}",
+            other
+        ),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum BatchState {
+    Pending,
+    Completed,
+    Failed(String),
+}
+
+impl BatchState {
+    fn of(batch: &Value) -> Self {
+        match batch["status"].as_str().unwrap_or_default() {
+            "completed" => Self::Completed,
+            "failed" => Self::Failed(
+                batch["errors"]["data"][0]["message"]
+                    .as_str()
+                    .unwrap_or("failed")
+                    .to_string(),
+            ),
+            "expired" => Self::Failed("expired before it was processed".to_string()),
+            "cancelling" | "cancelled" => Self::Failed("was cancelled".to_string()),
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// Runs OpenAI completions as batches.
+///
+/// Nothing streams in batch mode, so the agent waits for each whole turn.
+#[derive(Debug, serde::Serialize)]
+pub struct OpenAiBatchProvider {
+    inner: OpenAiProvider,
+    #[serde(skip)]
+    poll_interval: Duration,
+    #[serde(skip)]
+    timeout: Duration,
+}
+
+impl_provider_default!(OpenAiBatchProvider);
+
+impl OpenAiBatchProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        Ok(Self {
+            inner: OpenAiProvider::from_env(model)?,
+            poll_interval: Duration::from_secs(
+                config
+                    .get_param(BATCH_POLL_INTERVAL_KEY)
+                    .unwrap_or(DEFAULT_POLL_INTERVAL_SECS),
+            ),
+            timeout: Duration::from_secs(
+                config
+                    .get_param(BATCH_TIMEOUT_KEY)
+                    .unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ),
+        })
+    }
+
+    fn path(&self, resource: &str) -> String {
+        sibling_path(self.inner.base_path(), resource)
+    }
+
+    /// Upload the request and start a batch for it, returning the batch id
+    async fn submit(&self, custom_id: &str, body: &Value) -> Result<String, ProviderError> {
+        let api_client = self.inner.api_client();
+        let endpoint = format!("/{}", self.inner.base_path().trim_start_matches('/'));
+
+        let part = Part::bytes(batch_line(custom_id, &endpoint, body).into_bytes())
+            .file_name("goose-batch.jsonl")
+            .mime_str("application/jsonl")
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
+        let form = Form::new().text("purpose", "batch").part("file", part);
+        let file = handle_response_openai_compat(
+            api_client
+                .response_post_multipart(&self.path("files"), form)
+                .await?,
+        )
+        .await?;
+
+        let batch = handle_response_openai_compat(
+            api_client
+                .response_post(
+                    &self.path("batches"),
+                    &json!({
+                        "input_file_id": string_field(&file, "id")?,
+                        "endpoint": endpoint,
+                        "completion_window": COMPLETION_WINDOW,
+                        "metadata": {"source": "goose"},
+                    }),
+                )
+                .await?,
+        )
+        .await?;
+        string_field(&batch, "id")
+    }
+
+    /// Poll the batch until it is done one way or another
+    async fn wait(&self, batch_id: &str) -> Result<Value, ProviderError> {
+        let path = format!("{}/{}", self.path("batches"), batch_id);
+        let started = Instant::now();
+        loop {
+            let batch =
+                handle_response_openai_compat(self.inner.api_client().response_get(&path).await?)
+                    .await?;
+            match BatchState::of(&batch) {
+                BatchState::Completed => return Ok(batch),
+                BatchState::Failed(reason) => {
+                    return Err(ProviderError::ExecutionError(format!(
+                        "Batch {} {}",
+                        batch_id, reason
+                    )))
+                }
+                BatchState::Pending => {}
+            }
+
+            if started.elapsed() >= self.timeout {
+                let cancel = format!("{}/cancel", path);
+                if let Err(e) = self
+                    .inner
+                    .api_client()
+                    .response_post(&cancel, &json!({}))
+                    .await
+                {
+                    tracing::warn!("Failed to cancel batch {}: {}", batch_id, e);
+                }
+                return Err(ProviderError::ExecutionError(format!(
+                    "Batch {} did not finish within {} seconds and was cancelled",
+                    batch_id,
+                    self.timeout.as_secs()
+                )));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn file_content(&self, file_id: &str) -> Result<String, ProviderError> {
+        let path = format!("{}/{}/content", self.path("files"), file_id);
+        let response =
+            handle_status_openai_compat(self.inner.api_client().response_get(&path).await?).await?;
+        response
+            .text()
+            .await
+            .map_err(|e| ProviderError::RequestFailed(e.to_string()))
+    }
+
+    /// The completion for the request out of a finished batch's output or error file
+    async fn result(&self, batch: &Value, custom_id: &str) -> Result<Value, ProviderError> {
+        for file in ["output_file_id", "error_file_id"] {
+            if let Some(file_id) = batch[file].as_str() {
+                let content = self.file_content(file_id).await?;
+                if let Some(result) = find_result(&content, custom_id) {
+                    return result.map_err(ProviderError::ExecutionError);
+                }
+            }
+        }
+        Err(ProviderError::ExecutionError(format!(
+            "Batch {} finished without a result for the request",
+            batch["id"].as_str().unwrap_or_default()
+        )))
+    }
+}
+
+/// Another resource under the same API version as chat completions, e.g. v1/files
+fn sibling_path(base_path: &str, resource: &str) -> String {
+    match base_path
+        .trim_start_matches('/')
+        .split_once("chat/completions")
+    {
+        Some((prefix, _)) => format!("{}{}", prefix, resource),
+        None => format!("v1/{}", resource),
+    }
+}
+
+/// One line of a batch input file
+fn batch_line(custom_id: &str, endpoint: &str, body: &Value) -> String {
+    json!({
+        "custom_id": custom_id,
+        "method": "POST",
+        "url": endpoint,
+        "body": body,
+    })
+    .to_string()
+}
+
+/// The response body for a request in a batch result file, or why it failed
+fn find_result(content: &str, custom_id: &str) -> Option<Result<Value, String>> {
+    let line = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|line| line["custom_id"] == custom_id)?;
+
+    let response = &line["response"];
+    let status = response["status_code"].as_u64().unwrap_or_default();
+    if (200..300).contains(&status) {
+        return Some(Ok(response["body"].clone()));
+    }
+    let message = response["body"]["error"]["message"]
+        .as_str()
+        .or_else(|| line["error"]["message"].as_str())
+        .unwrap_or("the request failed");
+    Some(Err(format!("{} (status {})", message, status)))
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String, ProviderError> {
+    value[field].as_str().map(str::to_string).ok_or_else(|| {
+        ProviderError::RequestFailed(format!("Response is missing '{}': {}", field, value))
+    })
+}
+
+#[async_trait]
+impl Provider for OpenAiBatchProvider {
+    fn metadata() -> ProviderMetadata {
+        let models = OPEN_AI_KNOWN_MODELS.iter().map(|(name, _)| *name).collect();
+        ProviderMetadata::new(
+            "openai_batch",
+            "OpenAI Batch",
+            "OpenAI through the Batch API, for non-interactive recipe runs at a lower cost",
+            OPEN_AI_DEFAULT_MODEL,
+            models,
+            OPEN_AI_DOC_URL,
+            vec![
+                ConfigKey::new(BATCH_POLL_INTERVAL_KEY, false, false, Some("30")),
+                ConfigKey::new(BATCH_TIMEOUT_KEY, false, false, Some("90000")),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_tool_choice(system, messages, tools, &ToolChoice::Auto)
+            .await
+    }
+
+    fn supports_tool_choice(&self) -> bool {
+        true
+    }
+
+    async fn complete_with_tool_choice(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_choice: &ToolChoice,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self
+            .inner
+            .chat_payload(system, messages, tools, tool_choice)?;
+        let custom_id = format!("goose-{}", uuid::Uuid::new_v4());
+
+        let batch_id = self.submit(&custom_id, &payload).await?;
+        tracing::info!("Submitted batch {}, waiting for it to finish", batch_id);
+        let batch = self.wait(&batch_id).await?;
+        let json_response = self.result(&batch, &custom_id).await?;
+        tracing::info!("Batch {} finished", batch_id);
+
+        let message = response_to_message(&json_response)?;
+        let usage = json_response
+            .get("usage")
+            .map(get_usage)
+            .unwrap_or_else(Usage::default);
+        let model = get_model(&json_response);
+        emit_debug_trace(
+            &self.inner.get_model_config(),
+            &payload,
+            &json_response,
+            &usage,
+        );
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sibling_path() {
+        assert_eq!(sibling_path("v1/chat/completions", "files"), "v1/files");
+        assert_eq!(
+            sibling_path("/openai/v1/chat/completions", "batches"),
+            "openai/v1/batches"
+        );
+        assert_eq!(sibling_path("v1/responses", "files"), "v1/files");
+    }
+
+    #[test]
+    fn test_batch_state() {
+        assert_eq!(
+            BatchState::of(&json!({"status": "in_progress"})),
+            BatchState::Pending
+        );
+        assert_eq!(
+            BatchState::of(&json!({"status": "completed"})),
+            BatchState::Completed
+        );
+        assert_eq!(
+            BatchState::of(&json!({
+                "status": "failed",
+                "errors": {"data": [{"message": "invalid model"}]}
+            })),
+            BatchState::Failed("invalid model".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_result() {
+        let body = json!({"model": "gpt-4o", "messages": []});
+        let line = batch_line("goose-1", "/v1/chat/completions", &body);
+        let request: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(request["body"], body);
+
+        let content = [
+            json!({"custom_id": "goose-0", "response": {"status_code": 200, "body": {"id": "a"}}}),
+            json!({"custom_id": "goose-1", "response": {"status_code": 200, "body": {"id": "b"}}}),
+            json!({"custom_id": "goose-2", "response": {
+                "status_code": 400,
+                "body": {"error": {"message": "bad request"}}
+            }}),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        assert_eq!(
+            find_result(&content, "goose-1"),
+            Some(Ok(json!({"id": "b"})))
+        );
+        assert_eq!(
+            find_result(&content, "goose-2"),
+            Some(Err("bad request (status 400)".to_string()))
+        );
+        assert_eq!(find_result(&content, "goose-3"), None);
+    }
+}
@@ -3,13 +3,14 @@ use async_trait::async_trait;
 use serde_json::{json, Value};
 
 use super::api_client::{ApiClient, AuthMethod};
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{
     emit_debug_trace, get_model, handle_response_google_compat, handle_response_openai_compat,
     is_google_model,
 };
+use crate::config::{Config, ConfigError};
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
 use crate::model::ModelConfig;
@@ -31,11 +32,21 @@ pub const OPENROUTER_KNOWN_MODELS: &[&str] = &[
 ];
 pub const OPENROUTER_DOC_URL: &str = "https://openrouter.ai/models";
 
+/// Config key for OpenRouter's provider routing preferences, sent as the `provider` field,
+/// e.g. `{"order": ["anthropic", "amazon-bedrock"], "allow_fallbacks": false}`
+pub const OPENROUTER_PROVIDER_PREFERENCES_KEY: &str = "OPENROUTER_PROVIDER_PREFERENCES";
+/// Config key for models OpenRouter tries, in order, when the main model is unavailable
+pub const OPENROUTER_FALLBACK_MODELS_KEY: &str = "OPENROUTER_FALLBACK_MODELS";
+
 #[derive(serde::Serialize)]
 pub struct OpenRouterProvider {
     #[serde(skip)]
     api_client: ApiClient,
     model: ModelConfig,
+    provider_preferences: Option<Value>,
+    fallback_models: Vec<String>,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(OpenRouterProvider);
@@ -51,9 +62,32 @@ impl OpenRouterProvider {
         let auth = AuthMethod::BearerToken(api_key);
         let api_client = ApiClient::new(host, auth)?
             .with_header("HTTP-Referer", "https://block.github.io/goose")?
-            .with_header("X-Title", "Goose")?;
-
-        Ok(Self { api_client, model })
+            .with_header("X-Title", "Goose")?
+            .with_configured_middleware("openrouter")?;
+
+        let provider_preferences =
+            optional_param::<Value>(config, OPENROUTER_PROVIDER_PREFERENCES_KEY).filter(
+                |preferences| {
+                    let is_object = preferences.is_object();
+                    if !is_object {
+                        tracing::warn!(
+                            "Ignoring {}: expected a JSON object",
+                            OPENROUTER_PROVIDER_PREFERENCES_KEY
+                        );
+                    }
+                    is_object
+                },
+            );
+        let fallback_models = optional_param::<Vec<String>>(config, OPENROUTER_FALLBACK_MODELS_KEY)
+            .unwrap_or_default();
+
+        Ok(Self {
+            api_client,
+            model,
+            provider_preferences,
+            fallback_models,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
+        })
     }
 
     async fn post(&self, payload: &Value) -> Result<Value, ProviderError> {
@@ -100,7 +134,7 @@ impl OpenRouterProvider {
             // Return appropriate error based on the OpenRouter error code
             match error_code {
                 401 | 403 => return Err(ProviderError::Authentication(error_message.to_string())),
-                429 => return Err(ProviderError::RateLimitExceeded(error_message.to_string())),
+                429 => return Err(ProviderError::rate_limited(error_message.to_string())),
                 500 | 503 => return Err(ProviderError::ServerError(error_message.to_string())),
                 _ => return Err(ProviderError::RequestFailed(error_message.to_string())),
             }
@@ -184,6 +218,40 @@ fn update_request_for_anthropic(original_payload: &Value) -> Value {
     payload
 }
 
+fn optional_param<T: for<'de> serde::Deserialize<'de>>(config: &Config, key: &str) -> Option<T> {
+    match config.get_param::<T>(key) {
+        Ok(value) => Some(value),
+        Err(ConfigError::NotFound(_)) => None,
+        Err(e) => {
+            tracing::warn!("Ignoring {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Add OpenRouter's routing options to the request: provider preferences, the models to fall
+/// back to, and usage accounting so the response reports the cost of the routed model
+fn add_routing(
+    mut payload: Value,
+    model: &str,
+    provider_preferences: Option<&Value>,
+    fallback_models: &[String],
+) -> Value {
+    if let Some(obj) = payload.as_object_mut() {
+        if let Some(preferences) = provider_preferences {
+            obj.insert("provider".to_string(), preferences.clone());
+        }
+        if !fallback_models.is_empty() {
+            let models: Vec<&str> = std::iter::once(model)
+                .chain(fallback_models.iter().map(String::as_str))
+                .collect();
+            obj.insert("models".to_string(), json!(models));
+        }
+        obj.insert("usage".to_string(), json!({ "include": true }));
+    }
+    payload
+}
+
 fn create_request_based_on_model(
     provider: &OpenRouterProvider,
     system: &str,
@@ -198,11 +266,16 @@ fn create_request_based_on_model(
         &super::utils::ImageFormat::OpenAi,
     )?;
 
-    if provider.supports_cache_control() {
+    if provider.supports_cache_control() && super::prompt_cache::is_enabled() {
         payload = update_request_for_anthropic(&payload);
     }
 
-    Ok(payload)
+    Ok(add_routing(
+        payload,
+        &provider.model.model_name,
+        provider.provider_preferences.as_ref(),
+        &provider.fallback_models,
+    ))
 }
 
 #[async_trait]
@@ -223,10 +296,16 @@ impl Provider for OpenRouterProvider {
                     false,
                     Some("https://openrouter.ai"),
                 ),
+                ConfigKey::new(OPENROUTER_PROVIDER_PREFERENCES_KEY, false, false, None),
+                ConfigKey::new(OPENROUTER_FALLBACK_MODELS_KEY, false, false, None),
             ],
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -258,7 +337,15 @@ impl Provider for OpenRouterProvider {
             tracing::debug!("Failed to get usage data");
             Usage::default()
         });
+        // With openrouter/auto or fallbacks this is the model that actually served the request
         let model = get_model(&response);
+        if let Some(cost) = response
+            .get("usage")
+            .and_then(|usage| usage.get("cost"))
+            .and_then(|cost| cost.as_f64())
+        {
+            tracing::debug!("OpenRouter request to {} cost ${}", model, cost);
+        }
         emit_debug_trace(&self.model, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
@@ -340,9 +427,113 @@ impl Provider for OpenRouterProvider {
         Ok(Some(models))
     }
 
+    /// Fetch context windows and pricing for all models from the OpenRouter API
+    async fn fetch_model_info(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let response = self.api_client.response_get("api/v1/models").await?;
+        let json: Value = response.json().await?;
+
+        let data = match json.get("data").and_then(|v| v.as_array()) {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        Ok(Some(data.iter().filter_map(parse_model_info).collect()))
+    }
+
     fn supports_cache_control(&self) -> bool {
         self.model
             .model_name
             .starts_with(OPENROUTER_MODEL_PREFIX_ANTHROPIC)
     }
 }
+
+/// A model from OpenRouter's model listing
+fn parse_model_info(model: &Value) -> Option<ModelInfo> {
+    let id = model.get("id").and_then(|v| v.as_str())?;
+    let context_length = model.get("context_length").and_then(|v| v.as_u64())?;
+    let mut info = ModelInfo::new(id, context_length as usize);
+
+    let pricing = model.get("pricing");
+    // Routers like openrouter/auto list -1 since the price depends on the model picked
+    let price = |key: &str| {
+        pricing
+            .and_then(|p| p.get(key))
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| *v >= 0.0)
+    };
+    if let (Some(input), Some(output)) = (price("prompt"), price("completion")) {
+        info.input_token_cost = Some(input);
+        info.output_token_cost = Some(output);
+        info.currency = Some("$".to_string());
+    }
+
+    // Reasoning models that also take a temperature, like Claude, need no translation
+    if let Some(params) = model.get("supported_parameters").and_then(|v| v.as_array()) {
+        let supports = |name: &str| params.iter().any(|param| param.as_str() == Some(name));
+        info.reasoning = Some(supports("reasoning") && !supports("temperature"));
+    }
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_model_info() {
+        let info = parse_model_info(&json!({
+            "id": "openai/o3",
+            "context_length": 200000,
+            "pricing": {"prompt": "0.000002", "completion": "0.000008"},
+            "supported_parameters": ["tools", "reasoning", "max_tokens"]
+        }))
+        .unwrap();
+        assert_eq!(info.context_limit, 200_000);
+        assert_eq!(info.input_token_cost, Some(0.000002));
+        assert_eq!(info.reasoning, Some(true));
+
+        let info = parse_model_info(&json!({
+            "id": "anthropic/claude-sonnet-4",
+            "context_length": 200000,
+            "supported_parameters": ["tools", "reasoning", "temperature", "top_p"]
+        }))
+        .unwrap();
+        assert_eq!(info.reasoning, Some(false));
+
+        let info = parse_model_info(&json!({"id": "openrouter/auto", "context_length": 2000000}));
+        assert_eq!(info.unwrap().reasoning, None);
+    }
+
+    #[test]
+    fn test_add_routing() {
+        let payload = json!({"model": "openrouter/auto", "messages": []});
+        let preferences = json!({"order": ["anthropic"], "allow_fallbacks": false});
+        let payload = add_routing(
+            payload,
+            "openrouter/auto",
+            Some(&preferences),
+            &["anthropic/claude-sonnet-4".to_string()],
+        );
+
+        assert_eq!(payload["provider"], preferences);
+        assert_eq!(
+            payload["models"],
+            json!(["openrouter/auto", "anthropic/claude-sonnet-4"])
+        );
+        assert_eq!(payload["usage"], json!({"include": true}));
+    }
+
+    #[test]
+    fn test_add_routing_defaults() {
+        let payload = add_routing(
+            json!({"model": "qwen/qwen3-coder"}),
+            "qwen/qwen3-coder",
+            None,
+            &[],
+        );
+        assert!(payload.get("provider").is_none());
+        assert!(payload.get("models").is_none());
+        assert_eq!(payload["usage"], json!({"include": true}));
+    }
+}
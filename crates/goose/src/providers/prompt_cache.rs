@@ -0,0 +1,111 @@
+//! Prompt caching across providers.
+//!
+//! The system prompt and tool schemas rarely change within a session, so providers that can
+//! cache a request prefix bill the repeats at a fraction of the input price. Each provider
+//! asks for it differently: Anthropic-style APIs take `cache_control` blocks, OpenAI caches
+//! automatically and routes by a `prompt_cache_key`. The usage they report back counts the
+//! tokens read from the cache, which is what the savings are estimated from.
+
+use rmcp::model::Tool;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+
+/// Config key to turn prompt caching off, on by default
+pub const PROMPT_CACHING_KEY: &str = "GOOSE_PROMPT_CACHING";
+
+const CACHE_CONTROL_FIELD: &str = "cache_control";
+
+pub fn is_enabled() -> bool {
+    Config::global()
+        .get_param::<bool>(PROMPT_CACHING_KEY)
+        .unwrap_or(true)
+}
+
+/// Remove every `cache_control` marker from a request payload
+pub fn strip_cache_control(payload: &mut Value) {
+    match payload {
+        Value::Object(map) => {
+            map.remove(CACHE_CONTROL_FIELD);
+            map.values_mut().for_each(strip_cache_control);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_cache_control),
+        _ => {}
+    }
+}
+
+/// A stable key for requests that share a system prompt and tools, so a provider can send
+/// them to the same cache
+pub fn cache_key(system: &str, tools: &[Tool]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(system.as_bytes());
+    for tool in tools {
+        hasher.update(tool.name.as_bytes());
+        hasher.update(serde_json::to_vec(&tool.input_schema).unwrap_or_default());
+    }
+    let digest = hasher.finalize();
+    format!("goose-{}", hex_prefix(&digest, 16))
+}
+
+fn hex_prefix(bytes: &[u8], len: usize) -> String {
+    bytes
+        .iter()
+        .take(len / 2)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The share of the input price a provider charges for tokens read from its cache
+pub fn cached_input_price_factor(provider: &str) -> f64 {
+    match provider {
+        "anthropic" | "aws_bedrock" | "gcp_vertex_ai" | "databricks" | "openrouter"
+        | "deepseek" => 0.1,
+        "openai" | "azure_openai" => 0.5,
+        "google" => 0.25,
+        _ => 1.0,
+    }
+}
+
+/// What reading tokens from the cache saved at the given input price per token
+pub fn cache_savings(provider: &str, input_cost: f64, cache_read_tokens: i64) -> f64 {
+    input_cost * cache_read_tokens as f64 * (1.0 - cached_input_price_factor(provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_strip_cache_control() {
+        let mut payload = json!({
+            "system": [{"type": "text", "text": "hi", "cache_control": {"type": "ephemeral"}}],
+            "tools": [{"name": "a", "cache_control": {"type": "ephemeral"}}],
+        });
+        strip_cache_control(&mut payload);
+        assert_eq!(
+            payload,
+            json!({
+                "system": [{"type": "text", "text": "hi"}],
+                "tools": [{"name": "a"}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_cache_key_follows_the_prefix() {
+        let tool = Tool::new("shell", "Run a command", serde_json::Map::new());
+        let key = cache_key("You are goose", std::slice::from_ref(&tool));
+        assert_eq!(key, cache_key("You are goose", &[tool]));
+        assert_ne!(key, cache_key("You are goose", &[]));
+        assert_eq!(key.len(), "goose-".len() + 16);
+    }
+
+    #[test]
+    fn test_cache_savings() {
+        assert_eq!(cache_savings("anthropic", 0.000003, 1_000_000), 2.7);
+        assert_eq!(cache_savings("ollama", 0.000003, 1_000_000), 0.0);
+        assert!((cache_savings("deepseek", 0.00000028, 1_000_000) - 0.252).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::model::ModelConfig;
+use rmcp::model::{Role, Tool};
+
+/// Answers with the assistant messages of a recorded session, in the order they were recorded,
+/// so a session can be re-driven without calling a model
+pub struct ReplayProvider {
+    responses: Mutex<VecDeque<Message>>,
+    model_config: ModelConfig,
+}
+
+impl ReplayProvider {
+    pub fn new(recorded: &Conversation) -> Self {
+        let responses = recorded
+            .messages()
+            .iter()
+            .filter(|message| message.role == Role::Assistant)
+            .cloned()
+            .collect();
+        Self {
+            responses: Mutex::new(responses),
+            model_config: ModelConfig::new_or_fail("replay"),
+        }
+    }
+
+    /// Recorded responses not served yet
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().map(|r| r.len()).unwrap_or(0)
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "replay",
+            "Replay",
+            "Answers with the responses recorded in a session",
+            "replay",
+            vec!["replay"],
+            "",
+            vec![],
+        )
+    }
+
+    async fn complete(
+        &self,
+        _system: &str,
+        _messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let message = self
+            .responses
+            .lock()
+            .map_err(|_| ProviderError::ExecutionError("Replay lock poisoned".to_string()))?
+            .pop_front()
+            .ok_or_else(|| {
+                ProviderError::ExecutionError(
+                    "The recorded session has no more responses to replay".to_string(),
+                )
+            })?;
+        Ok((
+            message,
+            ProviderUsage::new("replay".to_string(), Usage::default()),
+        ))
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model_config.clone()
+    }
+}
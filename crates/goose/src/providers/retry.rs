@@ -1,17 +1,44 @@
 use super::errors::ProviderError;
+use crate::config::Config;
 use crate::providers::base::Provider;
 use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
 use std::future::Future;
+use std::str::FromStr;
 use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::time::sleep;
 
 pub const DEFAULT_MAX_RETRIES: usize = 3;
 pub const DEFAULT_INITIAL_RETRY_INTERVAL_MS: u64 = 1000;
 pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
 pub const DEFAULT_MAX_RETRY_INTERVAL_MS: u64 = 30_000;
+pub const DEFAULT_RETRY_JITTER: f64 = 0.2;
+/// Rate limits, server errors, unavailable or timed out gateways, and overloaded APIs
+pub const DEFAULT_RETRY_ON_STATUS: &[u16] = &[429, 500, 502, 503, 504, 529];
 
+/// The longest Retry-After we wait out; a server asking for more gets its error returned
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// How a provider retries failed requests.
+///
+/// Each setting is read from `<PREFIX>_<SETTING>`, then `GOOSE_<SETTING>`, then the default,
+/// so retries can be tuned for every provider at once or for one provider alone:
+///
+/// | Setting | Default |
+/// |---|---|
+/// | `MAX_RETRIES` | 3 |
+/// | `INITIAL_RETRY_INTERVAL_MS` | 1000 |
+/// | `BACKOFF_MULTIPLIER` | 2.0 |
+/// | `MAX_RETRY_INTERVAL_MS` | 30000 |
+/// | `RETRY_JITTER` | 0.2 |
+/// | `RETRY_ON_STATUS` | 429,500,502,503,504,529 |
+/// | `HONOR_RETRY_AFTER` | true |
 #[derive(Debug, Clone)]
-pub struct RetryConfig {
+pub struct RetryPolicy {
     /// Maximum number of retry attempts
     pub(crate) max_retries: usize,
     /// Initial interval between retries in milliseconds
@@ -20,20 +47,29 @@ pub struct RetryConfig {
     pub(crate) backoff_multiplier: f64,
     /// Maximum interval between retries in milliseconds
     pub(crate) max_interval_ms: u64,
+    /// Fraction each delay varies by either way, so clients don't retry in lockstep
+    pub(crate) jitter: f64,
+    /// HTTP statuses worth another attempt
+    pub(crate) retry_on: Vec<u16>,
+    /// Wait as long as the server asks in Retry-After instead of the backoff
+    pub(crate) honor_retry_after: bool,
 }
 
-impl Default for RetryConfig {
+impl Default for RetryPolicy {
     fn default() -> Self {
         Self {
             max_retries: DEFAULT_MAX_RETRIES,
             initial_interval_ms: DEFAULT_INITIAL_RETRY_INTERVAL_MS,
             backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
             max_interval_ms: DEFAULT_MAX_RETRY_INTERVAL_MS,
+            jitter: DEFAULT_RETRY_JITTER,
+            retry_on: DEFAULT_RETRY_ON_STATUS.to_vec(),
+            honor_retry_after: true,
         }
     }
 }
 
-impl RetryConfig {
+impl RetryPolicy {
     pub fn new(
         max_retries: usize,
         initial_interval_ms: u64,
@@ -45,6 +81,55 @@ impl RetryConfig {
             initial_interval_ms,
             backoff_multiplier,
             max_interval_ms,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_retry_on(mut self, statuses: Vec<u16>) -> Self {
+        self.retry_on = statuses;
+        self
+    }
+
+    pub fn with_honor_retry_after(mut self, honor_retry_after: bool) -> Self {
+        self.honor_retry_after = honor_retry_after;
+        self
+    }
+
+    /// The policy configured for the provider whose settings start with `prefix`, e.g.
+    /// `DATABRICKS` for `DATABRICKS_MAX_RETRIES`
+    pub fn from_config(config: &Config, prefix: &str) -> Self {
+        Self::default().with_config(config, prefix)
+    }
+
+    /// This policy with any settings configured for `prefix` applied over it
+    pub fn with_config(self, config: &Config, prefix: &str) -> Self {
+        let retry_on =
+            raw_setting(config, prefix, "RETRY_ON_STATUS").and_then(|value| match value {
+                Value::String(list) => list
+                    .split(',')
+                    .map(|status| status.trim().parse().ok())
+                    .collect(),
+                Value::Number(status) => status.as_u64().map(|status| vec![status as u16]),
+                value => serde_json::from_value(value).ok(),
+            });
+        Self {
+            max_retries: setting(config, prefix, "MAX_RETRIES").unwrap_or(self.max_retries),
+            initial_interval_ms: setting(config, prefix, "INITIAL_RETRY_INTERVAL_MS")
+                .unwrap_or(self.initial_interval_ms),
+            backoff_multiplier: setting(config, prefix, "BACKOFF_MULTIPLIER")
+                .unwrap_or(self.backoff_multiplier),
+            max_interval_ms: setting(config, prefix, "MAX_RETRY_INTERVAL_MS")
+                .unwrap_or(self.max_interval_ms),
+            jitter: setting::<f64>(config, prefix, "RETRY_JITTER")
+                .map_or(self.jitter, |jitter| jitter.clamp(0.0, 1.0)),
+            retry_on: retry_on.unwrap_or(self.retry_on),
+            honor_retry_after: setting(config, prefix, "HONOR_RETRY_AFTER")
+                .unwrap_or(self.honor_retry_after),
         }
     }
 
@@ -59,59 +144,203 @@ impl RetryConfig {
 
         let capped_delay_ms = std::cmp::min(base_delay_ms, self.max_interval_ms);
 
-        let jitter_factor_to_avoid_thundering_herd = 0.8 + (rand::random::<f64>() * 0.4);
+        let jitter_factor_to_avoid_thundering_herd =
+            1.0 - self.jitter + (rand::random::<f64>() * 2.0 * self.jitter);
         let jitter_delay_ms =
             (capped_delay_ms as f64 * jitter_factor_to_avoid_thundering_herd) as u64;
 
         Duration::from_millis(jitter_delay_ms)
     }
+
+    /// Whether the error comes from a status this policy retries
+    pub fn should_retry(&self, error: &ProviderError) -> bool {
+        error
+            .status()
+            .is_some_and(|status| self.retry_on.contains(&status))
+    }
+
+    /// How long to wait before retry `attempt`, or None when the server asked for a longer
+    /// wait than [`MAX_RETRY_AFTER`]
+    pub fn delay_for(&self, attempt: usize, error: &ProviderError) -> Option<Duration> {
+        match error.retry_delay() {
+            Some(delay) if self.honor_retry_after => (delay <= MAX_RETRY_AFTER).then_some(delay),
+            _ => Some(self.delay_for_attempt(attempt)),
+        }
+    }
 }
 
-/// Trait for retry functionality to keep Provider dyn-compatible
-#[async_trait]
-pub trait ProviderRetry {
-    fn retry_config(&self) -> RetryConfig {
-        RetryConfig::default()
+/// Read `<prefix>_<name>`, falling back to `GOOSE_<name>`
+fn raw_setting(config: &Config, prefix: &str, name: &str) -> Option<Value> {
+    [format!("{}_{}", prefix, name), format!("GOOSE_{}", name)]
+        .iter()
+        .find_map(|key| config.get_param::<Value>(key).ok())
+}
+
+/// A typed setting, which may also be written as a string in the config file
+fn setting<T>(config: &Config, prefix: &str, name: &str) -> Option<T>
+where
+    T: FromStr + DeserializeOwned,
+{
+    match raw_setting(config, prefix, name)? {
+        Value::String(value) => value.trim().parse().ok(),
+        value => serde_json::from_value(value).ok(),
+    }
+}
+
+/// A failed request about to be tried again
+#[derive(Debug, Clone)]
+pub struct RetryEvent {
+    /// The retry about to be made, starting at 1
+    pub attempt: usize,
+    pub max_retries: usize,
+    pub delay: Duration,
+    pub rate_limited: bool,
+    pub error: String,
+}
+
+impl fmt::Display for RetryEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, retrying in {}s ({}/{})",
+            if self.rate_limited {
+                "Rate limited"
+            } else {
+                "Request failed"
+            },
+            self.delay.as_secs_f64().ceil() as u64,
+            self.attempt,
+            self.max_retries
+        )
     }
+}
+
+static RETRY_EVENTS: Lazy<broadcast::Sender<RetryEvent>> = Lazy::new(|| broadcast::channel(16).0);
+
+/// Receive an event for every provider request that is retried, e.g. to show the user why a
+/// reply is taking longer
+pub fn subscribe_retry_events() -> broadcast::Receiver<RetryEvent> {
+    RETRY_EVENTS.subscribe()
+}
+
+pub(crate) fn notify_retry(event: RetryEvent) {
+    // Nobody may be listening, which is fine
+    let _ = RETRY_EVENTS.send(event);
+}
 
+/// Trait for retry functionality to keep Provider dyn-compatible
+#[async_trait]
+pub trait ProviderRetry: Provider {
     async fn with_retry<F, Fut, T>(&self, operation: F) -> Result<T, ProviderError>
     where
         F: Fn() -> Fut + Send,
         Fut: Future<Output = Result<T, ProviderError>> + Send,
         T: Send,
     {
+        let policy = self.retry_policy();
         let mut attempts = 0;
-        let config = self.retry_config();
 
         loop {
-            return match operation().await {
-                Ok(result) => Ok(result),
-                Err(error) => {
-                    let should_retry = matches!(
-                        error,
-                        ProviderError::RateLimitExceeded(_) | ProviderError::ServerError(_)
-                    );
-
-                    if should_retry && attempts < config.max_retries {
-                        attempts += 1;
-                        tracing::warn!(
-                            "Request failed, retrying ({}/{}): {:?}",
-                            attempts,
-                            config.max_retries,
-                            error
-                        );
-
-                        let delay = config.delay_for_attempt(attempts);
-                        tracing::info!("Backing off for {:?} before retry", delay);
-                        sleep(delay).await;
-                        continue;
-                    }
-
-                    Err(error)
-                }
+            let error = match operation().await {
+                Ok(result) => return Ok(result),
+                Err(error) => error,
             };
+            if attempts >= policy.max_retries || !policy.should_retry(&error) {
+                return Err(error);
+            }
+            attempts += 1;
+            let Some(delay) = policy.delay_for(attempts, &error) else {
+                tracing::warn!("Not retrying, the server asked to wait too long: {}", error);
+                return Err(error);
+            };
+
+            tracing::warn!(
+                "Request failed, retrying ({}/{}) in {:?}: {:?}",
+                attempts,
+                policy.max_retries,
+                delay,
+                error
+            );
+            notify_retry(RetryEvent {
+                attempt: attempts,
+                max_retries: policy.max_retries,
+                delay,
+                rate_limited: matches!(error, ProviderError::RateLimitExceeded { .. }),
+                error: error.to_string(),
+            });
+            sleep(delay).await;
         }
     }
 }
 
 impl<P: Provider> ProviderRetry for P {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limited(retry_delay: Option<Duration>) -> ProviderError {
+        ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_delay,
+        }
+    }
+
+    #[test]
+    fn test_jitter_bounds_delay() {
+        let policy = RetryPolicy::new(5, 1000, 2.0, 4000).with_jitter(0.0);
+        assert_eq!(policy.delay_for_attempt(1), Duration::from_millis(1000));
+        assert_eq!(policy.delay_for_attempt(2), Duration::from_millis(2000));
+        assert_eq!(policy.delay_for_attempt(5), Duration::from_millis(4000));
+
+        let policy = policy.with_jitter(0.5);
+        for _ in 0..20 {
+            let delay = policy.delay_for_attempt(1).as_millis();
+            assert!((500..=1500).contains(&delay));
+        }
+    }
+
+    #[test]
+    fn test_retries_only_configured_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(&rate_limited(None)));
+        assert!(policy.should_retry(&ProviderError::ServerError("oops".to_string())));
+        assert!(policy.should_retry(&ProviderError::RequestFailed(
+            "Request failed with status: 502 Bad Gateway".to_string()
+        )));
+        assert!(!policy.should_retry(&ProviderError::RequestFailed(
+            "Request failed with status: 404 Not Found".to_string()
+        )));
+        assert!(!policy.should_retry(&ProviderError::Authentication("no".to_string())));
+
+        let policy = policy.with_retry_on(vec![503]);
+        assert!(!policy.should_retry(&rate_limited(None)));
+    }
+
+    #[test]
+    fn test_retry_after_is_honored_within_limits() {
+        let policy = RetryPolicy::default().with_jitter(0.0);
+        let error = rate_limited(Some(Duration::from_secs(12)));
+        assert_eq!(policy.delay_for(1, &error), Some(Duration::from_secs(12)));
+        assert_eq!(
+            policy.delay_for(1, &rate_limited(Some(Duration::from_secs(3600)))),
+            None
+        );
+        assert_eq!(
+            policy.with_honor_retry_after(false).delay_for(1, &error),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_retry_event_message() {
+        let event = RetryEvent {
+            attempt: 1,
+            max_retries: 3,
+            delay: Duration::from_millis(11_500),
+            rate_limited: true,
+            error: "slow down".to_string(),
+        };
+        assert_eq!(event.to_string(), "Rate limited, retrying in 12s (1/3)");
+    }
+}
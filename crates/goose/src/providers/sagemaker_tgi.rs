@@ -11,7 +11,7 @@ use serde_json::{json, Value};
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::emit_debug_trace;
 use crate::conversation::message::{Message, MessageContent};
 use crate::impl_provider_default;
@@ -30,6 +30,8 @@ pub struct SageMakerTgiProvider {
     sagemaker_client: SageMakerClient,
     endpoint_name: String,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl SageMakerTgiProvider {
@@ -80,6 +82,7 @@ impl SageMakerTgiProvider {
             sagemaker_client,
             endpoint_name,
             model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
         })
     }
 
@@ -139,7 +142,7 @@ impl SageMakerTgiProvider {
         // causes them to mimic that format in their responses
 
         // Build TGI request with reasonable parameters
-        let request = json!({
+        let mut request = json!({
             "inputs": prompt,
             "parameters": {
                 "max_new_tokens": self.model.max_tokens.unwrap_or(150),
@@ -149,6 +152,20 @@ impl SageMakerTgiProvider {
             }
         });
 
+        let parameters = request["parameters"].as_object_mut().unwrap();
+        if let Some(top_p) = self.model.top_p {
+            parameters.insert("top_p".to_string(), json!(top_p));
+        }
+        if let Some(stop) = &self.model.stop {
+            parameters.insert("stop".to_string(), json!(stop));
+        }
+        if let Some(penalty) = self.model.frequency_penalty {
+            parameters.insert("frequency_penalty".to_string(), json!(penalty));
+        }
+        if let Some(seed) = self.model.seed {
+            parameters.insert("seed".to_string(), json!(seed));
+        }
+
         Ok(request)
     }
 
@@ -275,6 +292,10 @@ impl Provider for SageMakerTgiProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -302,11 +323,11 @@ impl Provider for SageMakerTgiProvider {
         let message = self.parse_tgi_response(response)?;
 
         // TGI doesn't provide usage statistics, so we estimate
-        let usage = Usage {
-            input_tokens: Some(0),  // Would need to tokenize input to get accurate count
-            output_tokens: Some(0), // Would need to tokenize output to get accurate count
-            total_tokens: Some(0),
-        };
+        let usage = Usage::new(
+            Some(0), // Would need to tokenize input to get accurate count
+            Some(0), // Would need to tokenize output to get accurate count
+            Some(0),
+        );
 
         // Add debug trace
         let debug_payload = serde_json::json!({
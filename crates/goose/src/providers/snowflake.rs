@@ -7,7 +7,7 @@ use super::api_client::{ApiClient, AuthMethod};
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
 use super::formats::snowflake::{create_request, get_usage, response_to_message};
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{get_model, map_http_error_to_provider_error, ImageFormat};
 use crate::config::ConfigError;
 use crate::conversation::message::Message;
@@ -38,6 +38,8 @@ pub struct SnowflakeProvider {
     api_client: ApiClient,
     model: ModelConfig,
     image_format: ImageFormat,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(SnowflakeProvider);
@@ -87,12 +89,15 @@ impl SnowflakeProvider {
         };
 
         let auth = AuthMethod::BearerToken(token?);
-        let api_client = ApiClient::new(base_url, auth)?.with_header("User-Agent", "Goose")?;
+        let api_client = ApiClient::new(base_url, auth)?
+            .with_header("User-Agent", "Goose")?
+            .with_configured_middleware("snowflake")?;
 
         Ok(Self {
             api_client,
             model,
             image_format: ImageFormat::OpenAi,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
         })
     }
 
@@ -294,6 +299,10 @@ impl Provider for SnowflakeProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -0,0 +1,314 @@
+//! Structured output: getting a JSON value that matches a schema out of any provider.
+//!
+//! Providers with a native JSON schema mode override
+//! [`Provider::complete_with_response_schema`]. Otherwise a provider that can force a tool
+//! call is made to call a tool whose input is the schema, and the rest are asked for JSON and
+//! given the validation errors until what they answer matches.
+
+use indoc::formatdoc;
+use rmcp::model::Tool;
+use serde_json::Value;
+
+use super::base::{Provider, ProviderUsage, ToolChoice, Usage};
+use super::errors::ProviderError;
+use crate::conversation::message::{Message, MessageContent};
+
+/// The tool a provider is forced to call with the structured response
+pub const RESPONSE_TOOL_NAME: &str = "structured_response";
+
+/// Answers asked for before giving up on getting one that matches the schema
+const MAX_ATTEMPTS: usize = 3;
+
+/// Check a value against a schema, describing every mismatch
+pub fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| format!("The response schema is invalid: {}", e))?;
+    let errors: Vec<String> = validator
+        .iter_errors(value)
+        .map(|error| format!("- {}: {}", error.instance_path, error))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// The JSON in a model's answer, which may be wrapped in a code fence or surrounded by prose
+pub fn extract_json(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    let unfenced = text
+        .split("```")
+        .nth(1)
+        .map(|block| block.trim_start_matches("json").trim());
+    if let Some(value) = unfenced.and_then(|block| serde_json::from_str(block).ok()) {
+        return Some(value);
+    }
+    // The outermost object or array
+    let start = text.find(['{', '['])?;
+    let end = text.rfind(['}', ']'])?;
+    (start < end)
+        .then(|| serde_json::from_str(&text[start..=end]).ok())
+        .flatten()
+}
+
+fn response_tool(schema: &Value) -> Result<Tool, ProviderError> {
+    let input_schema = schema.as_object().cloned().ok_or_else(|| {
+        ProviderError::UsageError("The response schema must be a JSON object".to_string())
+    })?;
+    Ok(Tool::new(
+        RESPONSE_TOOL_NAME.to_string(),
+        "Give your response. Its arguments are the response, matching the requested schema."
+            .to_string(),
+        input_schema,
+    ))
+}
+
+fn total(usage: Option<ProviderUsage>) -> ProviderUsage {
+    usage.unwrap_or_else(|| ProviderUsage::new(String::new(), Usage::default()))
+}
+
+fn combine(total: &mut Option<ProviderUsage>, usage: ProviderUsage) {
+    match total {
+        Some(total) => {
+            total.usage += usage.usage;
+            total.model = usage.model;
+        }
+        None => *total = Some(usage),
+    }
+}
+
+/// Get a response matching `schema`, by forcing the response tool when the provider supports
+/// tool choice and by asking and retrying otherwise
+pub async fn complete<P: Provider + ?Sized>(
+    provider: &P,
+    system: &str,
+    messages: &[Message],
+    schema: &Value,
+) -> Result<(Value, ProviderUsage), ProviderError> {
+    let mut usage = None;
+    if provider.supports_tool_choice() {
+        let tool = response_tool(schema)?;
+        let (message, tool_usage) = provider
+            .complete_with_tool_choice(
+                system,
+                messages,
+                &[tool],
+                &ToolChoice::Tool(RESPONSE_TOOL_NAME.to_string()),
+            )
+            .await?;
+        combine(&mut usage, tool_usage);
+        let arguments = message.content.iter().find_map(|content| match content {
+            MessageContent::ToolRequest(request) => request
+                .tool_call
+                .as_ref()
+                .ok()
+                .filter(|call| call.name == RESPONSE_TOOL_NAME)
+                .map(|call| call.arguments.clone()),
+            _ => None,
+        });
+        match arguments {
+            Some(value) => match validate(schema, &value) {
+                Ok(()) => return Ok((value, total(usage))),
+                Err(errors) => {
+                    tracing::debug!("Forced response didn't match the schema:\n{}", errors)
+                }
+            },
+            None => tracing::debug!("Provider answered without calling the response tool"),
+        }
+    }
+    complete_with_retries(provider, system, messages, schema, usage).await
+}
+
+/// Ask for JSON matching `schema` and feed back what was wrong until it matches
+pub async fn complete_with_retries<P: Provider + ?Sized>(
+    provider: &P,
+    system: &str,
+    messages: &[Message],
+    schema: &Value,
+    mut usage: Option<ProviderUsage>,
+) -> Result<(Value, ProviderUsage), ProviderError> {
+    let system = formatdoc! {r#"
+        {}
+
+        # Response Format
+
+        Respond with only a JSON value matching this schema, with no other text:
+
+        {}
+    "#, system, serde_json::to_string_pretty(schema).unwrap_or_default()};
+
+    let mut conversation = messages.to_vec();
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let (message, attempt_usage) = provider.complete(&system, &conversation, &[]).await?;
+        combine(&mut usage, attempt_usage);
+
+        let text = message.as_concat_text();
+        let problem = match extract_json(&text) {
+            Some(value) => match validate(schema, &value) {
+                Ok(()) => return Ok((value, total(usage))),
+                Err(errors) => format!("The response doesn't match the schema:\n{}", errors),
+            },
+            None => "The response isn't valid JSON.".to_string(),
+        };
+        tracing::debug!(
+            "Structured response attempt {} failed: {}",
+            attempt,
+            problem
+        );
+        conversation.push(message);
+        conversation.push(Message::user().with_text(format!(
+            "{}\n\nRespond again with only the corrected JSON.",
+            problem
+        )));
+        last_error = problem;
+    }
+
+    Err(ProviderError::ExecutionError(format!(
+        "No response matched the schema after {} attempts. {}",
+        MAX_ATTEMPTS, last_error
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use crate::providers::base::ProviderMetadata;
+    use async_trait::async_trait;
+    use mcp_core::ToolCall;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {"title": {"type": "string"}, "count": {"type": "integer"}},
+            "required": ["title", "count"]
+        })
+    }
+
+    /// Answers with each scripted message in turn
+    struct ScriptedProvider {
+        answers: Mutex<Vec<Message>>,
+        tool_choice: bool,
+    }
+
+    impl ScriptedProvider {
+        fn new(answers: Vec<Message>, tool_choice: bool) -> Self {
+            Self {
+                answers: Mutex::new(answers.into_iter().rev().collect()),
+                tool_choice,
+            }
+        }
+
+        fn next(&self) -> (Message, ProviderUsage) {
+            let message = self.answers.lock().unwrap().pop().expect("no answers left");
+            (
+                message,
+                ProviderUsage::new("mock".to_string(), Usage::new(Some(10), Some(5), Some(15))),
+            )
+        }
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("mock")
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok(self.next())
+        }
+
+        fn supports_tool_choice(&self) -> bool {
+            self.tool_choice
+        }
+
+        async fn complete_with_tool_choice(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            tools: &[Tool],
+            tool_choice: &ToolChoice,
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            assert_eq!(tools[0].name, RESPONSE_TOOL_NAME);
+            assert_eq!(
+                tool_choice,
+                &ToolChoice::Tool(RESPONSE_TOOL_NAME.to_string())
+            );
+            Ok(self.next())
+        }
+    }
+
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json(r#"{"a": 1}"#), Some(json!({"a": 1})));
+        assert_eq!(
+            extract_json("Here you go:\n```json\n{\"a\": 1}\n```"),
+            Some(json!({"a": 1}))
+        );
+        assert_eq!(
+            extract_json("The list is [1, 2] as asked"),
+            Some(json!([1, 2]))
+        );
+        assert_eq!(extract_json("no json here"), None);
+    }
+
+    #[tokio::test]
+    async fn test_forces_the_response_tool() {
+        let call = ToolCall::new(RESPONSE_TOOL_NAME, json!({"title": "a", "count": 2}));
+        let provider = ScriptedProvider::new(
+            vec![Message::assistant().with_tool_request("1", Ok(call))],
+            true,
+        );
+        let (value, _usage) = complete(&provider, "system", &[], &schema()).await.unwrap();
+        assert_eq!(value, json!({"title": "a", "count": 2}));
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_the_answer_matches() {
+        let provider = ScriptedProvider::new(
+            vec![
+                Message::assistant().with_text("Sure! The title is a."),
+                Message::assistant().with_text(r#"{"title": "a", "count": "two"}"#),
+                Message::assistant().with_text(
+                    r#"```json
+{"title": "a", "count": 2}
+```"#,
+                ),
+            ],
+            false,
+        );
+        let (value, usage) = complete(&provider, "system", &[], &schema()).await.unwrap();
+        assert_eq!(value, json!({"title": "a", "count": 2}));
+        assert_eq!(usage.usage.input_tokens, Some(30));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_the_last_attempt() {
+        let provider = ScriptedProvider::new(
+            (0..MAX_ATTEMPTS)
+                .map(|_| Message::assistant().with_text(r#"{"title": "a"}"#))
+                .collect(),
+            false,
+        );
+        let error = complete(&provider, "system", &[], &schema())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("count"));
+    }
+}
@@ -21,56 +21,152 @@
 //!
 //! ### Implementations
 //!
-//! The module provides an implementation for Ollama:
+//! The module provides two implementations:
 //!
 //! - `OllamaInterpreter`: Uses Ollama's structured output API to interpret tool calls
+//! - `ProviderInterpreter`: Uses any configured provider's structured output mode
+//!
+//! `create_interpreter` picks one from the `toolshim_provider` and `toolshim_model` of a `ModelConfig`.
 //!
 //! ### Helper Functions
 //!
 //! - `augment_message_with_tool_calls`: A utility function that takes any message, extracts text content, sends it to an interpreter, and adds any detected tool calls back to the message.
+//! - `modify_system_prompt_for_tool_json`: Adds the tool instructions to the system prompt.
+//!   Both it and the interpreter's extraction prompt are templates users can replace with their own files.
+//! - `parse_tool_calls`: Checks an interpreter answer is valid JSON calling known tools with valid arguments.
+//!   Rejected answers are sent back to the interpreter with the reason, a bounded number of times.
 //!
 
+use super::base::Provider;
 use super::errors::ProviderError;
 use super::ollama::OLLAMA_DEFAULT_PORT;
 use super::ollama::OLLAMA_HOST;
+use super::structured::{extract_json, validate};
 use crate::conversation::message::{Message, MessageContent};
 use crate::conversation::Conversation;
 use crate::model::ModelConfig;
+use crate::prompt_template::{render_global_file, render_inline_once};
 use crate::providers::formats::openai::create_request;
 use anyhow::Result;
 use mcp_core::tool::ToolCall;
 use reqwest::Client;
 use rmcp::model::{RawContent, Tool};
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::ops::Deref;
+use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
 /// Default model to use for tool interpretation
 pub const DEFAULT_INTERPRETER_MODEL_OLLAMA: &str = "mistral-nemo";
 
+/// Provider name that selects the built-in Ollama interpreter
+const OLLAMA_PROVIDER: &str = "ollama";
+
+/// Times a rejected interpreter answer is sent back to be corrected, unless configured
+const DEFAULT_MAX_REPAIRS: usize = 2;
+
+/// Local providers that decode structured output with a grammar
+const GRAMMAR_PROVIDERS: &[&str] = &["llama_cpp"];
+
+/// The instructions given to providers interpreting tool calls, ahead of the extraction prompt
+const INTERPRETER_SYSTEM_PROMPT: &str =
+    "You turn tool requests written in text into JSON tool calls.";
+
+/// Built-in prompt templates, in the embedded prompts directory
+const SYSTEM_TEMPLATE: &str = "toolshim_system.md";
+const EXTRACTION_TEMPLATE: &str = "toolshim_extraction.md";
+
 /// Environment variables that affect behavior:
 /// - GOOSE_TOOLSHIM: When set to "true" or "1", enables using the tool shim in the standard OllamaProvider (default: false)
-/// - GOOSE_TOOLSHIM_OLLAMA_MODEL: Ollama model to use as the tool interpreter (default: DEFAULT_INTERPRETER_MODEL)
+/// - GOOSE_TOOLSHIM_PROVIDER: Provider that runs the tool interpreter (default: ollama)
+/// - GOOSE_TOOLSHIM_MODEL: Model to use as the tool interpreter (default: DEFAULT_INTERPRETER_MODEL_OLLAMA
+///   for Ollama, otherwise the provider's default model). GOOSE_TOOLSHIM_OLLAMA_MODEL is still read as a fallback.
+/// - GOOSE_TOOLSHIM_MAX_REPAIRS: Times an invalid interpreter answer is sent back to be corrected (default: 2)
+/// - GOOSE_TOOLSHIM_SYSTEM_TEMPLATE: Template file replacing the tool instructions added to the system prompt,
+///   rendered with `system_prompt` and `tools` (each with `name`, `description` and `schema`)
+/// - GOOSE_TOOLSHIM_EXTRACTION_TEMPLATE: Template file replacing the interpreter's extraction prompt,
+///   rendered with `response`, the model output to extract tool calls from, and `tools`
 /// A trait for models that can interpret text into structured tool call JSON format
 #[async_trait::async_trait]
-pub trait ToolInterpreter {
-    /// Interpret potential tool calls from text and convert them to proper tool call JSON format
+pub trait ToolInterpreter: Send + Sync {
+    /// Ask the interpreter model to write the tool calls in `content` as JSON, returning its raw
+    /// answer. `rejected` carries the previous answer and what was wrong with it when repairing one.
+    async fn interpret(
+        &self,
+        content: &str,
+        tools: &[Tool],
+        rejected: Option<&Rejection>,
+    ) -> Result<String, ProviderError>;
+
+    /// Interpret potential tool calls from text and convert them to proper tool call JSON format.
+    /// Invalid answers are sent back up to `max_repairs` times; if none is valid, no tool calls
+    /// are returned and the text stays as it is.
     async fn interpret_to_tool_calls(
         &self,
         content: &str,
         tools: &[Tool],
-    ) -> Result<Vec<ToolCall>, ProviderError>;
+        max_repairs: usize,
+    ) -> Result<Vec<ToolCall>, ProviderError> {
+        if tools.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut rejected: Option<Rejection> = None;
+        for attempt in 0..=max_repairs {
+            let answer = self.interpret(content, tools, rejected.as_ref()).await?;
+            match parse_tool_calls(&answer, tools) {
+                Ok(tool_calls) => return Ok(tool_calls),
+                Err(error) => {
+                    tracing::warn!(
+                        "Tool interpreter answer rejected on attempt {}: {}",
+                        attempt + 1,
+                        error
+                    );
+                    rejected = Some(Rejection { answer, error });
+                }
+            }
+        }
+
+        tracing::warn!(
+            "No valid tool calls after {} attempts, leaving the message as text",
+            max_repairs + 1
+        );
+        Ok(vec![])
+    }
+}
+
+/// An interpreter answer that did not parse into valid tool calls, and why
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub answer: String,
+    pub error: String,
+}
+
+/// The conversation asking the interpreter for tool calls, continued with the rejected answer
+/// and the reason for rejecting it when repairing one
+fn interpreter_messages(request: String, rejected: Option<&Rejection>) -> Vec<Message> {
+    let mut messages = vec![Message::user().with_text(request)];
+    if let Some(rejected) = rejected {
+        messages.push(Message::assistant().with_text(&rejected.answer));
+        messages.push(Message::user().with_text(format!(
+            "That answer was rejected:\n{}\n\nWrite the tool calls again, fixing these problems.",
+            rejected.error
+        )));
+    }
+    messages
 }
 
 /// Ollama-specific implementation of the ToolInterpreter trait
 pub struct OllamaInterpreter {
     client: Client,
     base_url: String,
+    model: String,
 }
 
 impl OllamaInterpreter {
-    pub fn new() -> Result<Self, ProviderError> {
+    pub fn new(model: &str) -> Result<Self, ProviderError> {
         let client = Client::builder()
             .timeout(Duration::from_secs(600))
             .build()
@@ -78,7 +174,11 @@ impl OllamaInterpreter {
 
         let base_url = Self::get_ollama_base_url()?;
 
-        Ok(Self { client, base_url })
+        Ok(Self {
+            client,
+            base_url,
+            model: model.to_string(),
+        })
     }
 
     /// Get the Ollama base URL from existing config or use default values
@@ -114,53 +214,23 @@ impl OllamaInterpreter {
         Ok(base_url.to_string())
     }
 
-    fn tool_structured_ouput_format_schema() -> Value {
-        json!({
-            "type": "object",
-            "properties": {
-                "tool_calls": {
-                    "type": "array",
-                    "items": {
-                        "type": "object",
-                        "properties": {
-                            "name": {
-                                "type": "string",
-                                "description": "The name of the tool to call"
-                            },
-                            "arguments": {
-                                "type": "object",
-                                "description": "The arguments to pass to the tool"
-                            }
-                        },
-                        "required": ["name", "arguments"]
-                    }
-                }
-            },
-            "required": ["tool_calls"]
-        })
-    }
-
     async fn post_structured(
         &self,
         system_prompt: &str,
-        format_instruction: &str,
+        messages: &[Message],
         format_schema: Value,
         model: &str,
     ) -> Result<Value, ProviderError> {
         let base_url = self.base_url.trim_end_matches('/');
         let url = format!("{}/api/chat", base_url);
 
-        let mut messages = Vec::new();
-        let user_message = Message::user().with_text(format_instruction);
-        messages.push(user_message);
-
         let model_config = ModelConfig::new(model)
             .map_err(|e| ProviderError::RequestFailed(format!("Model config error: {e}")))?;
 
         let mut payload = create_request(
             &model_config,
             system_prompt,
-            &messages,
+            messages,
             &[], // No tools
             &super::utils::ImageFormat::OpenAi,
         )?;
@@ -198,114 +268,305 @@ impl OllamaInterpreter {
         Ok(response_json)
     }
 
-    fn process_interpreter_response(response: &Value) -> Result<Vec<ToolCall>, ProviderError> {
-        let mut tool_calls = Vec::new();
+    fn process_interpreter_response(response: &Value) -> String {
         tracing::info!(
             "Tool interpreter response is {}",
             serde_json::to_string_pretty(&response).unwrap_or_default()
         );
-        // Extract tool_calls array from the response
-        if response.get("message").is_some() && response["message"].get("content").is_some() {
-            let content = response["message"]["content"].as_str().unwrap_or_default();
-
-            // Try to parse the content as JSON
-            if let Ok(content_json) = serde_json::from_str::<Value>(content) {
-                // Check for the format with tool_calls array inside an object
-                if content_json.is_object() && content_json.get("tool_calls").is_some() {
-                    // Process each tool call in the array
-                    if let Some(tool_calls_array) = content_json["tool_calls"].as_array() {
-                        for item in tool_calls_array {
-                            if item.is_object()
-                                && item.get("name").is_some()
-                                && item.get("arguments").is_some()
-                            {
-                                // Create ToolCall directly from the JSON data
-                                let name = item["name"].as_str().unwrap_or_default().to_string();
-                                let arguments = item["arguments"].clone();
-
-                                // Add the tool call to our result vector
-                                tool_calls.push(ToolCall::new(name, arguments));
-                            }
+        response["message"]["content"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// The JSON schema interpreter models answer with
+fn tool_structured_ouput_format_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "tool_calls": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "description": "The name of the tool to call"
+                        },
+                        "arguments": {
+                            "type": "object",
+                            "description": "The arguments to pass to the tool"
                         }
-                    }
+                    },
+                    "required": ["name", "arguments"]
                 }
             }
+        },
+        "required": ["tool_calls"]
+    })
+}
+
+/// A schema only accepting calls to the given tools, with arguments matching each tool's input
+/// schema, or to the no-op tool. Local backends turn it into a grammar, so the tool calls they
+/// interpret are valid by construction.
+pub fn tool_calls_schema(tools: &[Tool]) -> Value {
+    let calls: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"const": tool.name},
+                    "arguments": Value::Object(tool.input_schema.as_ref().clone())
+                },
+                "required": ["name", "arguments"]
+            })
+        })
+        .chain(std::iter::once(json!({
+            "type": "object",
+            "properties": {
+                "name": {"const": "noop"},
+                "arguments": {"type": "object", "properties": {}}
+            },
+            "required": ["name", "arguments"]
+        })))
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": {
+            "tool_calls": {
+                "type": "array",
+                "items": {"anyOf": calls}
+            }
+        },
+        "required": ["tool_calls"]
+    })
+}
+
+/// Parse an interpreter answer into tool calls, checking that each one names the no-op tool or
+/// one of `tools`, with arguments matching that tool's input schema. The error lists every
+/// problem, phrased for the interpreter to correct.
+pub fn parse_tool_calls(answer: &str, tools: &[Tool]) -> Result<Vec<ToolCall>, String> {
+    let value = extract_json(answer).ok_or_else(|| "The answer is not valid JSON".to_string())?;
+    let items = value
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "The answer has no \"tool_calls\" array".to_string())?;
+
+    let mut tool_calls = Vec::new();
+    let mut errors = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let Some(name) = item.get("name").and_then(Value::as_str) else {
+            errors.push(format!("- tool_calls[{}] has no \"name\" string", i));
+            continue;
+        };
+        let Some(arguments) = item.get("arguments").filter(|a| a.is_object()) else {
+            errors.push(format!("- tool_calls[{}] has no \"arguments\" object", i));
+            continue;
+        };
+        if name == "noop" {
+            tool_calls.push(ToolCall::new(name, arguments.clone()));
+            continue;
         }
+        let Some(tool) = tools.iter().find(|tool| tool.name == name) else {
+            let known: Vec<String> = tools.iter().map(|tool| tool.name.to_string()).collect();
+            errors.push(format!(
+                "- tool_calls[{}] calls unknown tool \"{}\", the tools are: {}, noop",
+                i,
+                name,
+                known.join(", ")
+            ));
+            continue;
+        };
+        let schema = Value::Object(tool.input_schema.as_ref().clone());
+        match validate(&schema, arguments) {
+            Ok(()) => tool_calls.push(ToolCall::new(name, arguments.clone())),
+            Err(e) => errors.push(format!(
+                "- tool_calls[{}] has invalid arguments for {}:\n{}",
+                i, name, e
+            )),
+        }
+    }
 
+    if errors.is_empty() {
         Ok(tool_calls)
+    } else {
+        Err(errors.join("\n"))
     }
 }
 
 #[async_trait::async_trait]
 impl ToolInterpreter for OllamaInterpreter {
-    async fn interpret_to_tool_calls(
+    async fn interpret(
         &self,
         last_assistant_msg: &str,
         tools: &[Tool],
-    ) -> Result<Vec<ToolCall>, ProviderError> {
-        if tools.is_empty() {
-            return Ok(vec![]);
-        }
-
-        // Create the system prompt
-        let system_prompt = "If there is detectable JSON-formatted tool requests, write them into valid JSON tool calls in the following format:
-{{
-  \"tool_calls\": [
-    {{
-      \"name\": \"tool_name\",
-      \"arguments\": {{
-        \"param1\": \"value1\",
-        \"param2\": \"value2\"
-      }}
-    }}
-  ]
-}}
-
-Otherwise, if no JSON tool requests are provided, use the no-op tool:
-{{
-  \"tool_calls\": [
-    {{
-    \"name\": \"noop\",
-      \"arguments\": {{
-      }}
-    }}]
-}}
-";
-
+        rejected: Option<&Rejection>,
+    ) -> Result<String, ProviderError> {
         // Create enhanced content with instruction to output tool calls as JSON
-        let format_instruction = format!("{}\nRequest: {}\n\n", system_prompt, last_assistant_msg);
-
-        // Define the JSON schema for tool call format
-        let format_schema = OllamaInterpreter::tool_structured_ouput_format_schema();
+        let format_instruction = extraction_prompt(last_assistant_msg, tools);
+        let messages = interpreter_messages(format_instruction, rejected);
 
-        // Determine which model to use for interpretation (from env var or default)
-        let interpreter_model = std::env::var("GOOSE_TOOLSHIM_OLLAMA_MODEL")
-            .unwrap_or_else(|_| DEFAULT_INTERPRETER_MODEL_OLLAMA.to_string());
+        // Constrain the answer to the available tools, Ollama decodes with a grammar built from it
+        let format_schema = tool_calls_schema(tools);
 
         // Make a call to ollama with structured output
         let interpreter_response = self
-            .post_structured("", &format_instruction, format_schema, &interpreter_model)
+            .post_structured("", &messages, format_schema, &self.model)
             .await?;
 
-        // Process the interpreter response to get tool calls directly
-        let tool_calls = OllamaInterpreter::process_interpreter_response(&interpreter_response)?;
+        Ok(OllamaInterpreter::process_interpreter_response(
+            &interpreter_response,
+        ))
+    }
+}
 
-        Ok(tool_calls)
+/// Interprets tool calls with any provider, through its structured output mode
+pub struct ProviderInterpreter {
+    provider: Arc<dyn Provider>,
+    constrained: bool,
+}
+
+impl ProviderInterpreter {
+    pub fn new(provider: Arc<dyn Provider>) -> Self {
+        Self {
+            provider,
+            constrained: false,
+        }
+    }
+
+    /// Ask for calls matching the tools' own schemas rather than any name and arguments. Only
+    /// worth it for backends that decode with a grammar; hosted APIs reject many tool schemas
+    /// in their strict modes.
+    pub fn with_constrained_decoding(mut self, constrained: bool) -> Self {
+        self.constrained = constrained;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolInterpreter for ProviderInterpreter {
+    async fn interpret(
+        &self,
+        last_assistant_msg: &str,
+        tools: &[Tool],
+        rejected: Option<&Rejection>,
+    ) -> Result<String, ProviderError> {
+        let messages = interpreter_messages(extraction_prompt(last_assistant_msg, tools), rejected);
+        let schema = if self.constrained {
+            tool_calls_schema(tools)
+        } else {
+            tool_structured_ouput_format_schema()
+        };
+        let (response, _usage) = self
+            .provider
+            .complete_with_response_schema(INTERPRETER_SYSTEM_PROMPT, &messages, &schema)
+            .await?;
+
+        tracing::info!(
+            "Tool interpreter response is {}",
+            serde_json::to_string_pretty(&response).unwrap_or_default()
+        );
+        Ok(response.to_string())
     }
 }
 
-/// Creates a string containing formatted tool information
-pub fn format_tool_info(tools: &[Tool]) -> String {
-    let mut tool_info = String::new();
-    for tool in tools {
-        tool_info.push_str(&format!(
-            "Tool Name: {}\nSchema: {}\nDescription: {:?}\n\n",
-            tool.name,
-            serde_json::to_string_pretty(&tool.input_schema).unwrap_or_default(),
-            tool.description
-        ));
+/// Create the interpreter a model config asks for: the Ollama interpreter unless
+/// `toolshim_provider` names another provider, which is then created with `toolshim_model`
+/// or that provider's default model
+pub fn create_interpreter(
+    model_config: &ModelConfig,
+) -> Result<Box<dyn ToolInterpreter>, ProviderError> {
+    let provider_name = model_config
+        .toolshim_provider
+        .as_deref()
+        .unwrap_or(OLLAMA_PROVIDER);
+
+    if provider_name == OLLAMA_PROVIDER {
+        let model = model_config
+            .toolshim_model
+            .as_deref()
+            .unwrap_or(DEFAULT_INTERPRETER_MODEL_OLLAMA);
+        return Ok(Box::new(OllamaInterpreter::new(model)?));
     }
-    tool_info
+
+    let model_name = match &model_config.toolshim_model {
+        Some(model) => model.clone(),
+        None => super::factory::providers()
+            .into_iter()
+            .find(|metadata| metadata.name == provider_name)
+            .map(|metadata| metadata.default_model)
+            .ok_or_else(|| {
+                ProviderError::ExecutionError(format!(
+                    "Unknown toolshim provider: {}",
+                    provider_name
+                ))
+            })?,
+    };
+    // The interpreter answers with structured output, it never needs a shim of its own
+    let interpreter_config = ModelConfig::new(&model_name)
+        .map_err(|e| ProviderError::RequestFailed(format!("Model config error: {e}")))?
+        .with_toolshim(false);
+    let provider = super::factory::create(provider_name, interpreter_config).map_err(|e| {
+        ProviderError::ExecutionError(format!(
+            "Failed to create toolshim provider {}: {}",
+            provider_name, e
+        ))
+    })?;
+
+    Ok(Box::new(
+        ProviderInterpreter::new(provider)
+            .with_constrained_decoding(GRAMMAR_PROVIDERS.contains(&provider_name)),
+    ))
+}
+
+/// A tool as the toolshim prompt templates see it
+#[derive(Serialize)]
+struct TemplateTool {
+    name: String,
+    description: String,
+    schema: String,
+}
+
+fn template_tools(tools: &[Tool]) -> Vec<TemplateTool> {
+    tools
+        .iter()
+        .map(|tool| TemplateTool {
+            name: tool.name.to_string(),
+            description: tool.description.as_deref().unwrap_or_default().to_string(),
+            schema: serde_json::to_string_pretty(&tool.input_schema).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Render a toolshim prompt from the template file configured under `key`, falling back to the
+/// built-in `template` when none is configured or it cannot be read or rendered
+fn render_prompt<T: Serialize>(key: &str, template: &str, context: &T) -> String {
+    if let Ok(path) = crate::config::Config::global().get_param::<String>(key) {
+        let rendered = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| render_inline_once(&source, context).map_err(|e| e.to_string()));
+        match rendered {
+            Ok(prompt) => return prompt,
+            Err(e) => tracing::warn!("Ignoring toolshim template {}: {}", path, e),
+        }
+    }
+    render_global_file(template, context).expect("Prompt should render")
+}
+
+/// The prompt asking the interpreter to extract the tool calls in a model's response
+fn extraction_prompt(response: &str, tools: &[Tool]) -> String {
+    let context = json!({
+        "response": response,
+        "tools": template_tools(tools),
+    });
+    render_prompt(
+        "GOOSE_TOOLSHIM_EXTRACTION_TEMPLATE",
+        EXTRACTION_TEMPLATE,
+        &context,
+    )
 }
 
 /// Convert messages containing ToolRequest/ToolResponse to text messages for toolshim mode
@@ -374,17 +635,15 @@ pub fn convert_tool_messages_to_text(messages: &[Message]) -> Conversation {
 
 /// Modifies the system prompt to include tool usage instructions when tool interpretation is enabled
 pub fn modify_system_prompt_for_tool_json(system_prompt: &str, tools: &[Tool]) -> String {
-    let tool_info = format_tool_info(tools);
-
-    format!(
-        "{}\n\n{}\n\nBreak down your task into smaller steps and do one step and tool call at a time. Do not try to use multiple tools at once. If you want to use a tool, tell the user what tool to use by specifying the tool in this JSON format\n{{\n  \"name\": \"tool_name\",\n  \"arguments\": {{\n    \"parameter1\": \"value1\",\n    \"parameter2\": \"value2\"\n }}\n}}. After you get the tool result back, consider the result and then proceed to do the next step and tool call if required.",
-        system_prompt,
-        tool_info
-    )
+    let context = json!({
+        "system_prompt": system_prompt,
+        "tools": template_tools(tools),
+    });
+    render_prompt("GOOSE_TOOLSHIM_SYSTEM_TEMPLATE", SYSTEM_TEMPLATE, &context)
 }
 
 /// Helper function to augment a message with tool calls if any are detected
-pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
+pub async fn augment_message_with_tool_calls<T: ToolInterpreter + ?Sized>(
     interpreter: &T,
     message: Message,
     tools: &[Tool],
@@ -394,20 +653,24 @@ pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
         return Ok(message);
     }
 
-    // Extract content from the message
-    let content_opt = message.content.iter().find_map(|content| {
-        if let MessageContent::Text(text) = content {
-            Some(text.text.as_str())
-        } else {
-            None
-        }
-    });
+    // Extract content from the message, calls can be spread over several text blocks
+    let texts: Vec<&str> = message
+        .content
+        .iter()
+        .filter_map(|content| {
+            if let MessageContent::Text(text) = content {
+                Some(text.text.as_str())
+            } else {
+                None
+            }
+        })
+        .collect();
 
     // If there's no text content or it's already a tool request, return the original message
-    let content = match content_opt {
-        Some(text) => text,
-        None => return Ok(message),
-    };
+    if texts.is_empty() {
+        return Ok(message);
+    }
+    let content = texts.join("\n");
 
     // Check if there's already a tool request
     if message
@@ -419,14 +682,19 @@ pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
     }
 
     // Use the interpreter to convert the content to tool calls
-    let tool_calls = interpreter.interpret_to_tool_calls(content, tools).await?;
+    let max_repairs = crate::config::Config::global()
+        .get_param("GOOSE_TOOLSHIM_MAX_REPAIRS")
+        .unwrap_or(DEFAULT_MAX_REPAIRS);
+    let tool_calls = interpreter
+        .interpret_to_tool_calls(&content, tools, max_repairs)
+        .await?;
 
     // If no tool calls were detected, return the original message
     if tool_calls.is_empty() {
         return Ok(message);
     }
 
-    // Add each tool call to the message
+    // Add each tool call to the message, in order, as a native model would return parallel calls
     let mut final_message = message;
     for tool_call in tool_calls {
         if tool_call.name != "noop" {
@@ -438,3 +706,185 @@ pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
 
     Ok(final_message)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+    use serial_test::serial;
+    use std::io::Write;
+    use std::sync::Mutex;
+    use temp_env::with_var;
+
+    /// Answers with each scripted answer in turn, recording the rejections it was shown
+    struct ScriptedInterpreter {
+        answers: Mutex<Vec<String>>,
+        rejections: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedInterpreter {
+        fn new(answers: &[&str]) -> Self {
+            Self {
+                answers: Mutex::new(answers.iter().rev().map(|a| a.to_string()).collect()),
+                rejections: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ToolInterpreter for ScriptedInterpreter {
+        async fn interpret(
+            &self,
+            _content: &str,
+            _tools: &[Tool],
+            rejected: Option<&Rejection>,
+        ) -> Result<String, ProviderError> {
+            if let Some(rejected) = rejected {
+                self.rejections.lock().unwrap().push(rejected.error.clone());
+            }
+            Ok(self.answers.lock().unwrap().pop().unwrap_or_default())
+        }
+    }
+
+    fn shell_tool() -> Tool {
+        Tool::new(
+            "shell",
+            "Run a command",
+            object!({
+                "type": "object",
+                "properties": {"command": {"type": "string"}},
+                "required": ["command"]
+            }),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn test_builtin_prompts_list_the_tools() {
+        let system = modify_system_prompt_for_tool_json("You are goose.", &[shell_tool()]);
+        assert!(system.starts_with("You are goose.\n\nTool Name: shell\n"));
+        assert!(system.contains("Description: Run a command\n"));
+        assert!(system.contains("\"arguments\": {\n    \"parameter1\""));
+
+        let extraction = extraction_prompt("call shell", &[shell_tool()]);
+        assert!(extraction.contains("\"tool_calls\": ["));
+        assert!(extraction.ends_with("Request: call shell"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_prompt_templates_can_be_overridden() {
+        let mut template = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            template,
+            "Tools: {{% for tool in tools %}}{{{{ tool.name }}}} {{% endfor %}}\nText: {{{{ response }}}}"
+        )
+        .unwrap();
+        let path = template.path().to_str().unwrap();
+
+        with_var("GOOSE_TOOLSHIM_EXTRACTION_TEMPLATE", Some(path), || {
+            assert_eq!(
+                extraction_prompt("call shell", &[shell_tool()]),
+                "Tools: shell \nText: call shell"
+            );
+        });
+
+        // A missing template falls back to the built-in prompt
+        with_var(
+            "GOOSE_TOOLSHIM_SYSTEM_TEMPLATE",
+            Some("/no/such/template.md"),
+            || {
+                let system = modify_system_prompt_for_tool_json("You are goose.", &[shell_tool()]);
+                assert!(system.contains("Tool Name: shell"));
+            },
+        );
+    }
+
+    #[test]
+    fn test_parse_tool_calls_rejects_unknown_tools_and_bad_arguments() {
+        let tools = [shell_tool()];
+
+        let calls = parse_tool_calls(
+            r#"{"tool_calls": [{"name": "shell", "arguments": {"command": "ls"}}]}"#,
+            &tools,
+        )
+        .unwrap();
+        assert_eq!(
+            calls,
+            vec![ToolCall::new("shell", json!({"command": "ls"}))]
+        );
+
+        assert!(parse_tool_calls("not json", &tools).is_err());
+        let error = parse_tool_calls(
+            r#"{"tool_calls": [{"name": "bash", "arguments": {}}, {"name": "shell", "arguments": {"command": 1}}]}"#,
+            &tools,
+        )
+        .unwrap_err();
+        assert!(error.contains("unknown tool \"bash\""));
+        assert!(error.contains("tool_calls[1] has invalid arguments for shell"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_answers_are_sent_back_for_repair() {
+        let interpreter = ScriptedInterpreter::new(&[
+            "{\"tool_calls\": [",
+            r#"{"tool_calls": [{"name": "shel", "arguments": {"command": "ls"}}]}"#,
+            r#"{"tool_calls": [{"name": "shell", "arguments": {"command": "ls"}}]}"#,
+        ]);
+
+        let calls = interpreter
+            .interpret_to_tool_calls("run ls", &[shell_tool()], 2)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            calls,
+            vec![ToolCall::new("shell", json!({"command": "ls"}))]
+        );
+        let rejections = interpreter.rejections.lock().unwrap();
+        assert_eq!(rejections.len(), 2);
+        assert_eq!(rejections[0], "The answer is not valid JSON");
+        assert!(rejections[1].contains("unknown tool \"shel\""));
+    }
+
+    #[tokio::test]
+    async fn test_several_calls_become_ordered_tool_requests() {
+        let interpreter = ScriptedInterpreter::new(&[r#"{"tool_calls": [
+            {"name": "shell", "arguments": {"command": "ls"}},
+            {"name": "noop", "arguments": {}},
+            {"name": "shell", "arguments": {"command": "pwd"}}
+        ]}"#]);
+        let message = Message::assistant()
+            .with_text(r#"{"name": "shell", "arguments": {"command": "ls"}}"#)
+            .with_text(r#"{"name": "shell", "arguments": {"command": "pwd"}}"#);
+
+        let message = augment_message_with_tool_calls(&interpreter, message, &[shell_tool()])
+            .await
+            .unwrap();
+
+        let commands: Vec<Value> = message
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::ToolRequest(request) => {
+                    Some(request.tool_call.as_ref().unwrap().arguments["command"].clone())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(commands, vec![json!("ls"), json!("pwd")]);
+    }
+
+    #[tokio::test]
+    async fn test_repairs_are_bounded() {
+        let interpreter = ScriptedInterpreter::new(&["nope", "still nope", "never asked"]);
+
+        let calls = interpreter
+            .interpret_to_tool_calls("run ls", &[shell_tool()], 1)
+            .await
+            .unwrap();
+
+        assert!(calls.is_empty());
+        assert_eq!(interpreter.answers.lock().unwrap().len(), 1);
+    }
+}
@@ -4,12 +4,14 @@ use crate::model::ModelConfig;
 use anyhow::Result;
 use base64::Engine;
 use regex::Regex;
+use reqwest::header::HeaderMap;
 use reqwest::{Response, StatusCode};
 use rmcp::model::{AnnotateAble, ImageContent, RawImageContent};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Map, Value};
 use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
 use crate::providers::errors::{OpenAIError, ProviderError};
 
@@ -98,11 +100,14 @@ pub fn map_http_error_to_provider_error(
             ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            ProviderError::RateLimitExceeded(format!("{:?}", payload))
+            ProviderError::rate_limited(format!("{:?}", payload))
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
             ProviderError::ServerError(format!("{:?}", payload))
         }
+        status if status.is_server_error() => {
+            ProviderError::ServerError(format!("Status {}: {:?}", status.as_u16(), payload))
+        }
         _ => {
             tracing::debug!(
                 "Provider request failed with status: {}. Payload: {:?}", status, payload
@@ -117,6 +122,7 @@ pub fn map_http_error_to_provider_error(
 /// Context window exceeded: https://community.openai.com/t/help-needed-tackling-context-length-limits-in-openai-models/617543
 pub async fn handle_status_openai_compat(response: Response) -> Result<Response, ProviderError> {
     let status = response.status();
+    let retry_after = retry_after(response.headers());
 
     match status {
         StatusCode::OK => Ok(response),
@@ -146,7 +152,7 @@ pub async fn handle_status_openai_compat(response: Response) -> Result<Response,
                     } else {
                         map_http_error_to_provider_error(status, Some(body))
                     };
-                    Err(error)
+                    Err(error.with_retry_delay(retry_after))
                 }
             }
         }
@@ -212,6 +218,7 @@ fn get_google_final_status(status: StatusCode, payload: Option<&Value>) -> Statu
 /// - `Err(ProviderError)`: Describes the failure reason.
 pub async fn handle_response_google_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_after = retry_after(response.headers());
     let payload: Option<Value> = response.json().await.ok();
     let final_status = get_google_final_status(status, payload.as_ref());
 
@@ -238,7 +245,7 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", final_status, error_msg)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::rate_limited(format!("{:?}", payload)).with_retry_delay(retry_after))
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
             Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -252,6 +259,23 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
     }
 }
 
+/// How long the server asked us to wait, from `retry-after-ms` or `Retry-After` given in
+/// seconds or as an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(ms) = header("retry-after-ms").and_then(|ms| ms.trim().parse::<f64>().ok()) {
+        return (ms >= 0.0).then(|| Duration::from_secs_f64(ms / 1000.0));
+    }
+    let value = header("retry-after")?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds >= 0.0).then(|| Duration::from_secs_f64(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
 pub fn sanitize_function_name(name: &str) -> String {
     let re = Regex::new(r"[^a-zA-Z0-9_-]").unwrap();
     re.replace_all(name, "_").to_string()
@@ -786,4 +810,27 @@ mod tests {
             "Hello\\u0001World"
         );
     }
+
+    #[test]
+    fn test_retry_after() {
+        use reqwest::header::HeaderValue;
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert("retry-after", HeaderValue::from_static("12"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(12)));
+
+        headers.insert("retry-after-ms", HeaderValue::from_static("1500"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(1500)));
+
+        let mut headers = HeaderMap::new();
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        headers.insert("retry-after", HeaderValue::from_str(&later).unwrap());
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(50) && delay <= Duration::from_secs(60));
+
+        headers.insert("retry-after", HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
 }
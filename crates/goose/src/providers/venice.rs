@@ -7,7 +7,8 @@ use serde_json::{json, Value};
 use super::api_client::{ApiClient, AuthMethod};
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::formats::openai::add_sampling_params;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::map_http_error_to_provider_error;
 use crate::conversation::message::{Message, MessageContent};
 use crate::impl_provider_default;
@@ -78,6 +79,8 @@ pub struct VeniceProvider {
     base_path: String,
     models_path: String,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(VeniceProvider);
@@ -100,13 +103,14 @@ impl VeniceProvider {
         model.model_name = strip_flags(&model.model_name).to_string();
 
         let auth = AuthMethod::BearerToken(api_key);
-        let api_client = ApiClient::new(host, auth)?;
+        let api_client = ApiClient::new(host, auth)?.with_configured_middleware("venice")?;
 
         let instance = Self {
             api_client,
             base_path,
             models_path,
             model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
         };
 
         Ok(instance)
@@ -212,6 +216,10 @@ impl Provider for VeniceProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
@@ -397,6 +405,7 @@ impl Provider for VeniceProvider {
             "temperature": 0.7,
             "max_tokens": 2048,
         });
+        add_sampling_params(&mut payload, &self.model);
 
         if !tools.is_empty() {
             // Format tools specifically for Venice API
@@ -493,11 +502,11 @@ impl Provider for VeniceProvider {
 
         // Extract usage
         let usage_data = &response_json["usage"];
-        let usage = Usage {
-            input_tokens: usage_data["prompt_tokens"].as_i64().map(|v| v as i32),
-            output_tokens: usage_data["completion_tokens"].as_i64().map(|v| v as i32),
-            total_tokens: usage_data["total_tokens"].as_i64().map(|v| v as i32),
-        };
+        let usage = Usage::new(
+            usage_data["prompt_tokens"].as_i64().map(|v| v as i32),
+            usage_data["completion_tokens"].as_i64().map(|v| v as i32),
+            usage_data["total_tokens"].as_i64().map(|v| v as i32),
+        );
 
         Ok((
             Message::new(Role::Assistant, Utc::now().timestamp(), content),
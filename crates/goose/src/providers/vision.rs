@@ -0,0 +1,399 @@
+//! Images in conversations: which models can see them, loading them from files and data URLs,
+//! and fitting them within what a provider accepts before a request is sent.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use rmcp::model::{AnnotateAble, Content, ImageContent, RawContent, RawImageContent};
+
+use super::base::Provider;
+use super::errors::ProviderError;
+use super::model_registry::ModelRegistry;
+use crate::config::Config;
+use crate::conversation::message::{Message, MessageContent};
+
+/// Config key for declaring whether the configured model can read images
+pub const VISION_KEY: &str = "GOOSE_VISION";
+
+/// Image types every vision API accepts as they are
+const SUPPORTED_MIME_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+/// Downscaling stops here even if the image is still too large
+const MIN_DIMENSION: u32 = 256;
+const JPEG_QUALITY: u8 = 85;
+
+/// Model name prefixes of families that read images
+const VISION_PREFIXES: &[&str] = &[
+    "gpt-4o",
+    "gpt-4.1",
+    "gpt-4-turbo",
+    "gpt-5",
+    "o1",
+    "o3",
+    "o4",
+    "chatgpt-4o",
+    "claude-3",
+    "claude-4",
+    "claude-sonnet-4",
+    "claude-opus-4",
+    "gemini",
+    "gemma3",
+    "grok-4",
+    "llama-4",
+    "llama4",
+];
+/// Name fragments that mark a model as reading images
+const VISION_MARKERS: &[&str] = &["vision", "-vl", "vl:", "llava", "pixtral", "moondream"];
+/// Members of vision families that don't read images
+const TEXT_ONLY_PREFIXES: &[&str] = &["o1-mini", "o3-mini", "gpt-4o-audio", "gpt-4o-realtime"];
+
+/// The largest image a provider takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimits {
+    /// Encoded size in bytes
+    pub max_bytes: usize,
+    /// Longest edge in pixels; providers scale anything larger down themselves, so sending
+    /// more only costs tokens
+    pub max_dimension: u32,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 5 * 1024 * 1024,
+            max_dimension: 2048,
+        }
+    }
+}
+
+/// Whether a model is known to read images, judging by its name
+fn known_vision_model(model_name: &str) -> bool {
+    // Routers and gateways put the provider or their own prefix in front of the model name
+    let name = model_name.rsplit('/').next().unwrap_or(model_name);
+    let name = name.to_lowercase();
+    let name = name
+        .trim_start_matches("databricks-")
+        .trim_start_matches("goose-");
+    if TEXT_ONLY_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        return false;
+    }
+    VISION_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || VISION_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// Whether a model reads images: GOOSE_VISION when set, then what the provider reported for it,
+/// then its name
+pub fn model_supports_vision(model_name: &str) -> bool {
+    if let Ok(vision) = Config::global().get_param::<bool>(VISION_KEY) {
+        return vision;
+    }
+    ModelRegistry::global()
+        .get_configured(model_name)
+        .and_then(|info| info.supports_vision)
+        .unwrap_or_else(|| known_vision_model(model_name))
+}
+
+fn mime_type(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn encoded_image(bytes: Vec<u8>) -> anyhow::Result<ImageContent> {
+    let format = image::guess_format(&bytes)?;
+    // Anything else, such as a BMP screenshot, is converted to PNG
+    let (bytes, mime) = match mime_type(format) {
+        Some(mime) => (bytes, mime),
+        None => (
+            encode(&image::load_from_memory(&bytes)?, ImageOutputFormat::Png)?,
+            "image/png",
+        ),
+    };
+    Ok(RawImageContent {
+        data: base64::prelude::BASE64_STANDARD.encode(bytes),
+        mime_type: mime.to_string(),
+    }
+    .no_annotation())
+}
+
+/// Load an image file, recognising its type from its contents
+pub fn load_image(path: &Path) -> anyhow::Result<ImageContent> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    encoded_image(bytes)
+        .map_err(|e| anyhow::anyhow!("{} is not an image goose can read: {}", path.display(), e))
+}
+
+/// Load raw image bytes, such as a clipboard's contents
+pub fn image_from_bytes(bytes: Vec<u8>) -> anyhow::Result<ImageContent> {
+    encoded_image(bytes)
+}
+
+/// Parse a `data:image/...;base64,...` URL
+pub fn image_from_data_url(url: &str) -> Option<ImageContent> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    if !mime_type.starts_with("image/") {
+        return None;
+    }
+    let bytes = base64::prelude::BASE64_STANDARD.decode(data).ok()?;
+    encoded_image(bytes).ok()
+}
+
+fn encode(image: &DynamicImage, format: ImageOutputFormat) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match format {
+        // JPEG has no alpha channel
+        ImageOutputFormat::Jpeg(_) => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut Cursor::new(&mut bytes), format)?,
+        _ => image.write_to(&mut Cursor::new(&mut bytes), format)?,
+    }
+    Ok(bytes)
+}
+
+/// Scale and re-encode an image until it is within `limits`, leaving it untouched if it
+/// already is
+pub fn fit_image(
+    image: &RawImageContent,
+    limits: &ImageLimits,
+) -> Result<RawImageContent, ProviderError> {
+    let bytes = base64::prelude::BASE64_STANDARD
+        .decode(&image.data)
+        .map_err(|e| ProviderError::UsageError(format!("An image isn't valid base64: {}", e)))?;
+    let unreadable =
+        |e: image::ImageError| ProviderError::UsageError(format!("An image can't be read: {}", e));
+
+    let (width, height) = image::io::Reader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|e| ProviderError::UsageError(format!("An image can't be read: {}", e)))?
+        .into_dimensions()
+        .map_err(unreadable)?;
+    if SUPPORTED_MIME_TYPES.contains(&image.mime_type.as_str())
+        && bytes.len() <= limits.max_bytes
+        && width.max(height) <= limits.max_dimension
+    {
+        return Ok(image.clone());
+    }
+
+    let decoded = image::load_from_memory(&bytes).map_err(unreadable)?;
+    // Keep transparency where it fits, otherwise JPEG is far smaller
+    let mut format = if decoded.color().has_alpha() {
+        ImageOutputFormat::Png
+    } else {
+        ImageOutputFormat::Jpeg(JPEG_QUALITY)
+    };
+    let mut edge = width.max(height).min(limits.max_dimension);
+    loop {
+        let resized = if width.max(height) > edge {
+            decoded.resize(edge, edge, FilterType::Triangle)
+        } else {
+            decoded.clone()
+        };
+        let encoded = encode(&resized, format.clone())
+            .map_err(|e| ProviderError::ExecutionError(format!("Failed to encode image: {}", e)))?;
+
+        if encoded.len() <= limits.max_bytes || edge <= MIN_DIMENSION {
+            let (new_width, new_height) = resized.dimensions();
+            tracing::debug!(
+                "Fitted a {}x{} image of {} bytes to {}x{} and {} bytes",
+                width,
+                height,
+                bytes.len(),
+                new_width,
+                new_height,
+                encoded.len()
+            );
+            return Ok(RawImageContent {
+                data: base64::prelude::BASE64_STANDARD.encode(encoded),
+                mime_type: match format {
+                    ImageOutputFormat::Png => "image/png",
+                    _ => "image/jpeg",
+                }
+                .to_string(),
+            });
+        }
+        if matches!(format, ImageOutputFormat::Png) {
+            format = ImageOutputFormat::Jpeg(JPEG_QUALITY);
+        } else {
+            edge = (edge * 3 / 4).max(MIN_DIMENSION);
+        }
+    }
+}
+
+/// The conversation as `provider` should receive it: images fitted within its limits, and an
+/// error explaining what to do when images are sent to a model that can't read them. Images
+/// returned by tools are replaced with a note instead, so a screenshot doesn't end the session.
+pub fn prepare_messages<P: Provider + ?Sized>(
+    provider: &P,
+    messages: &[Message],
+) -> Result<Vec<Message>, ProviderError> {
+    let has_images = messages.iter().any(|message| {
+        message.content.iter().any(|content| match content {
+            MessageContent::Image(_) => true,
+            MessageContent::ToolResponse(response) => response
+                .tool_result
+                .as_ref()
+                .is_ok_and(|contents| contents.iter().any(|c| c.as_image().is_some())),
+            _ => false,
+        })
+    });
+    if !has_images {
+        return Ok(messages.to_vec());
+    }
+
+    let model = provider.get_model_config().model_name;
+    let vision = provider.supports_vision();
+    let limits = provider.image_limits();
+
+    let mut prepared = Vec::with_capacity(messages.len());
+    for message in messages {
+        let mut message = message.clone();
+        for content in message.content.iter_mut() {
+            match content {
+                MessageContent::Image(_) if !vision => {
+                    return Err(ProviderError::UsageError(format!(
+                        "{} can't read images. Remove the image, or switch to a model that \
+                         supports vision. If this model does, set {}=true.",
+                        model, VISION_KEY
+                    )));
+                }
+                MessageContent::Image(image) => {
+                    image.raw = fit_image(&image.raw, &limits)?;
+                }
+                MessageContent::ToolResponse(response) => {
+                    for content in response.tool_result.iter_mut().flatten() {
+                        if content.as_image().is_none() {
+                            continue;
+                        }
+                        if !vision {
+                            *content = Content::text(format!(
+                                "[The tool returned an image, which {} can't read]",
+                                model
+                            ));
+                        } else if let RawContent::Image(image) = &mut content.raw {
+                            *image = fit_image(image, &limits)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        prepared.push(message);
+    }
+    Ok(prepared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> RawImageContent {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+        });
+        let bytes = encode(&DynamicImage::ImageRgb8(image), ImageOutputFormat::Png).unwrap();
+        RawImageContent {
+            data: base64::prelude::BASE64_STANDARD.encode(bytes),
+            mime_type: "image/png".to_string(),
+        }
+    }
+
+    fn dimensions(image: &RawImageContent) -> (u32, u32) {
+        let bytes = base64::prelude::BASE64_STANDARD
+            .decode(&image.data)
+            .unwrap();
+        image::load_from_memory(&bytes).unwrap().dimensions()
+    }
+
+    #[test]
+    fn test_known_vision_models() {
+        for model in [
+            "gpt-4o",
+            "claude-sonnet-4-20250514",
+            "anthropic/claude-3-5-sonnet",
+            "databricks-claude-3-7-sonnet",
+            "gemini-2.5-pro",
+            "llama3.2-vision:11b",
+            "qwen2.5vl:7b",
+            "qwen2.5-vl-72b-instruct",
+        ] {
+            assert!(known_vision_model(model), "{} reads images", model);
+        }
+        for model in [
+            "o3-mini",
+            "gpt-3.5-turbo",
+            "qwen2.5-coder:32b",
+            "deepseek-r1",
+        ] {
+            assert!(!known_vision_model(model), "{} doesn't read images", model);
+        }
+    }
+
+    #[test]
+    fn test_small_images_are_untouched() {
+        let image = png(64, 32);
+        assert_eq!(fit_image(&image, &ImageLimits::default()).unwrap(), image);
+    }
+
+    #[test]
+    fn test_large_images_are_scaled_down() {
+        let limits = ImageLimits {
+            max_bytes: 5 * 1024 * 1024,
+            max_dimension: 512,
+        };
+        let fitted = fit_image(&png(2000, 1000), &limits).unwrap();
+        assert_eq!(fitted.mime_type, "image/jpeg");
+        assert_eq!(dimensions(&fitted), (512, 256));
+    }
+
+    #[test]
+    fn test_heavy_images_are_recompressed() {
+        let image = RgbaImage::from_fn(600, 600, |x, y| {
+            Rgba([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+                200,
+            ])
+        });
+        let bytes = encode(&DynamicImage::ImageRgba8(image), ImageOutputFormat::Png).unwrap();
+        let image = RawImageContent {
+            data: base64::prelude::BASE64_STANDARD.encode(&bytes),
+            mime_type: "image/png".to_string(),
+        };
+        let limits = ImageLimits {
+            max_bytes: bytes.len() / 4,
+            max_dimension: 2048,
+        };
+
+        let fitted = fit_image(&image, &limits).unwrap();
+        let fitted_bytes = base64::prelude::BASE64_STANDARD
+            .decode(&fitted.data)
+            .unwrap();
+        assert!(fitted_bytes.len() <= limits.max_bytes);
+    }
+
+    #[test]
+    fn test_image_from_data_url() {
+        let image = png(8, 8);
+        let url = format!("data:image/png;base64,{}", image.data);
+        let parsed = image_from_data_url(&url).unwrap();
+        assert_eq!(parsed.mime_type, "image/png");
+        assert_eq!(parsed.data, image.data);
+
+        assert!(image_from_data_url("data:text/plain;base64,aGk=").is_none());
+        assert!(image_from_data_url("https://example.com/cat.png").is_none());
+    }
+}
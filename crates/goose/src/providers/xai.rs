@@ -1,6 +1,6 @@
 use super::api_client::{ApiClient, AuthMethod};
 use super::errors::ProviderError;
-use super::retry::ProviderRetry;
+use super::retry::{ProviderRetry, RetryPolicy};
 use super::utils::{get_model, handle_response_openai_compat};
 use crate::conversation::message::Message;
 use crate::impl_provider_default;
@@ -41,6 +41,8 @@ pub struct XaiProvider {
     #[serde(skip)]
     api_client: ApiClient,
     model: ModelConfig,
+    #[serde(skip)]
+    retry_policy: RetryPolicy,
 }
 
 impl_provider_default!(XaiProvider);
@@ -54,9 +56,13 @@ impl XaiProvider {
             .unwrap_or_else(|_| XAI_API_HOST.to_string());
 
         let auth = AuthMethod::BearerToken(api_key);
-        let api_client = ApiClient::new(host, auth)?;
+        let api_client = ApiClient::new(host, auth)?.with_configured_middleware("xai")?;
 
-        Ok(Self { api_client, model })
+        Ok(Self {
+            api_client,
+            model,
+            retry_policy: RetryPolicy::from_config(config, "GOOSE"),
+        })
     }
 
     async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
@@ -88,6 +94,10 @@ impl Provider for XaiProvider {
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
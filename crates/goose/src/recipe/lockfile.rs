@@ -0,0 +1,158 @@
+//! Lockfiles recording the configuration a pinned recipe ran with.
+//!
+//! Providers move aliases like `gpt-4o` or `claude-sonnet-4-0` to new snapshots over time, so
+//! pinning the model name in a recipe is not enough to reproduce a run. The first successful run
+//! of a pinned recipe writes `<recipe>.lock.yaml` next to it with the provider, the snapshot the
+//! provider reported serving and the sampling parameters; later runs use those instead. Delete
+//! the lockfile to lock the recipe again.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::Settings;
+
+/// The configuration a recipe ran with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecipeLock {
+    pub provider: String,
+    /// The model the recipe asked for
+    pub model: String,
+    /// The model snapshot the provider reported serving, when it differs from `model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+    pub goose_version: String,
+    pub locked_at: DateTime<Utc>,
+}
+
+impl RecipeLock {
+    pub fn new(
+        provider: impl Into<String>,
+        model: impl Into<String>,
+        resolved_model: Option<String>,
+        temperature: Option<f32>,
+        max_tokens: Option<i32>,
+    ) -> Self {
+        let model = model.into();
+        Self {
+            provider: provider.into(),
+            resolved_model: resolved_model.filter(|resolved| *resolved != model),
+            model,
+            temperature,
+            max_tokens,
+            goose_version: env!("CARGO_PKG_VERSION").to_string(),
+            locked_at: Utc::now(),
+        }
+    }
+
+    /// Where the lockfile of a recipe file lives: `recipe.yaml` locks to `recipe.lock.yaml`
+    pub fn path_for(recipe_path: &Path) -> PathBuf {
+        let stem = recipe_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("recipe");
+        recipe_path.with_file_name(format!("{}.lock.yaml", stem))
+    }
+
+    /// Read a lockfile, if there is one at `path`
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read lockfile {}: {}", path.display(), e))?;
+        let lock = serde_yaml::from_str(&content)
+            .map_err(|e| anyhow!("Invalid lockfile {}: {}", path.display(), e))?;
+        Ok(Some(lock))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_yaml::to_string(self)?;
+        fs::write(path, content)
+            .map_err(|e| anyhow!("Failed to write lockfile {}: {}", path.display(), e))
+    }
+
+    /// The model to request to reproduce the locked run
+    pub fn locked_model(&self) -> &str {
+        self.resolved_model.as_deref().unwrap_or(&self.model)
+    }
+
+    /// Recipe settings with the locked provider, model and parameters in place of their own
+    pub fn apply(&self, settings: Settings) -> Settings {
+        Settings {
+            goose_provider: Some(self.provider.clone()),
+            goose_model: Some(self.locked_model().to_string()),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            ..settings
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pinned_settings() -> Settings {
+        Settings {
+            goose_provider: Some("openai".to_string()),
+            goose_model: Some("gpt-4o".to_string()),
+            temperature: Some(0.7),
+            max_tokens: None,
+            batch: Some(true),
+            pin: Some(true),
+        }
+    }
+
+    #[test]
+    fn test_lockfile_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let recipe_path = temp_dir.path().join("weekly-report.yaml");
+        let lock_path = RecipeLock::path_for(&recipe_path);
+        assert_eq!(lock_path, temp_dir.path().join("weekly-report.lock.yaml"));
+        assert_eq!(RecipeLock::load(&lock_path).unwrap(), None);
+
+        let lock = RecipeLock::new(
+            "openai",
+            "gpt-4o",
+            Some("gpt-4o-2024-08-06".to_string()),
+            Some(0.2),
+            Some(4096),
+        );
+        lock.save(&lock_path).unwrap();
+
+        assert_eq!(RecipeLock::load(&lock_path).unwrap(), Some(lock));
+    }
+
+    #[test]
+    fn test_apply_uses_the_locked_snapshot() {
+        let lock = RecipeLock::new(
+            "openai",
+            "gpt-4o",
+            Some("gpt-4o-2024-08-06".to_string()),
+            Some(0.2),
+            Some(4096),
+        );
+
+        let settings = lock.apply(pinned_settings());
+        assert_eq!(settings.goose_model.as_deref(), Some("gpt-4o-2024-08-06"));
+        assert_eq!(settings.temperature, Some(0.2));
+        assert_eq!(settings.max_tokens, Some(4096));
+        assert_eq!(settings.batch, Some(true));
+
+        // A provider that serves the alias itself records no snapshot
+        let lock = RecipeLock::new("ollama", "qwen3", Some("qwen3".to_string()), None, None);
+        assert_eq!(lock.resolved_model, None);
+        assert_eq!(
+            lock.apply(pinned_settings()).goose_model.as_deref(),
+            Some("qwen3")
+        );
+    }
+}
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 pub mod build_recipe;
+pub mod lockfile;
 pub mod read_recipe_file_content;
 pub mod template_recipe;
 
@@ -135,6 +136,18 @@ pub struct Settings {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+
+    /// Send requests through the provider's batch API when the recipe runs unattended
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<bool>,
+
+    /// Run with exactly these settings, ignoring provider and model overrides, and record the
+    /// configuration of the first successful run in a lockfile that later runs reuse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -0,0 +1,346 @@
+use std::path::Path;
+
+/// Definitions up to this many lines are kept whole; longer ones are split along the
+/// definitions inside them, or into windows when there are none
+pub const MAX_CHUNK_LINES: usize = 120;
+
+/// Size of the sliding windows used for unsupported files and for code between definitions
+pub const WINDOW_LINES: usize = 60;
+
+/// Lines shared by consecutive windows so nothing is only ever seen cut in half
+pub const WINDOW_OVERLAP: usize = 10;
+
+/// A span of a file, with 1-based inclusive line numbers that match the file on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    /// The syntax node kind for definitions, e.g. `function_item`; None for windows
+    pub kind: Option<String>,
+}
+
+/// Split a file into chunks that follow its functions, classes and other definitions.
+///
+/// Each definition that fits in [`MAX_CHUNK_LINES`] becomes one chunk along with the comments
+/// above it. Code outside any definition, and files in languages without a grammar here, are
+/// covered by overlapping windows of [`WINDOW_LINES`].
+pub fn chunk_file(path: &Path, content: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+
+    let Some(mut chunks) = syntax::definition_chunks(path, content, &lines) else {
+        return windows(&lines, 1, lines.len());
+    };
+    chunks.sort_by_key(|chunk| chunk.start_line);
+
+    // Cover what the definitions left out: imports, top-level statements, class headers
+    let mut gaps = Vec::new();
+    let mut next = 1;
+    for chunk in &chunks {
+        if chunk.start_line > next {
+            gaps.extend(gap_windows(&lines, next, chunk.start_line - 1));
+        }
+        next = next.max(chunk.end_line + 1);
+    }
+    if next <= lines.len() {
+        gaps.extend(gap_windows(&lines, next, lines.len()));
+    }
+    chunks.extend(gaps);
+    chunks.sort_by_key(|chunk| chunk.start_line);
+    chunks
+}
+
+/// Definitions found with tree-sitter, for the languages goose has grammars for
+#[cfg(feature = "code-chunking")]
+mod syntax {
+    use super::{windows, Chunk, MAX_CHUNK_LINES};
+    use std::path::Path;
+    use tree_sitter::{Language, Node, Parser};
+
+    struct Grammar {
+        language: Language,
+        definitions: &'static [&'static str],
+    }
+
+    fn grammar(path: &Path) -> Option<Grammar> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        let (language, definitions): (Language, &'static [&'static str]) = match extension.as_str()
+        {
+            "rs" => (
+                tree_sitter_rust::LANGUAGE.into(),
+                &[
+                    "function_item",
+                    "impl_item",
+                    "struct_item",
+                    "enum_item",
+                    "union_item",
+                    "trait_item",
+                    "mod_item",
+                    "macro_definition",
+                    "const_item",
+                    "static_item",
+                    "type_item",
+                ],
+            ),
+            "py" | "pyi" => (
+                tree_sitter_python::LANGUAGE.into(),
+                &[
+                    "function_definition",
+                    "class_definition",
+                    "decorated_definition",
+                ],
+            ),
+            "js" | "jsx" | "mjs" | "cjs" => {
+                (tree_sitter_javascript::LANGUAGE.into(), JS_DEFINITIONS)
+            }
+            "ts" | "mts" | "cts" => (
+                tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+                JS_DEFINITIONS,
+            ),
+            "tsx" => (tree_sitter_typescript::LANGUAGE_TSX.into(), JS_DEFINITIONS),
+            "go" => (
+                tree_sitter_go::LANGUAGE.into(),
+                &[
+                    "function_declaration",
+                    "method_declaration",
+                    "type_declaration",
+                ],
+            ),
+            _ => return None,
+        };
+        Some(Grammar {
+            language,
+            definitions,
+        })
+    }
+
+    const JS_DEFINITIONS: &[&str] = &[
+        "function_declaration",
+        "generator_function_declaration",
+        "class_declaration",
+        "method_definition",
+        "interface_declaration",
+        "type_alias_declaration",
+        "enum_declaration",
+        "export_statement",
+    ];
+
+    /// Comments and attributes directly above a definition belong to it
+    const LEADING_KINDS: &[&str] = &["comment", "line_comment", "block_comment", "attribute_item"];
+
+    /// Chunks for the definitions in a file, or None when its language has no grammar here
+    pub(super) fn definition_chunks(
+        path: &Path,
+        content: &str,
+        lines: &[&str],
+    ) -> Option<Vec<Chunk>> {
+        let grammar = grammar(path)?;
+        let mut parser = Parser::new();
+        parser.set_language(&grammar.language).ok()?;
+        let tree = parser.parse(content, None)?;
+
+        let mut chunks = Vec::new();
+        collect(tree.root_node(), &grammar, lines, &mut chunks);
+        Some(chunks)
+    }
+
+    fn collect(node: Node, grammar: &Grammar, lines: &[&str], chunks: &mut Vec<Chunk>) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for (i, child) in children.iter().enumerate() {
+            if !grammar.definitions.contains(&child.kind()) {
+                collect(*child, grammar, lines, chunks);
+                continue;
+            }
+
+            let mut start = child.start_position().row + 1;
+            let end = (child.end_position().row + 1).min(lines.len());
+            if end - start < MAX_CHUNK_LINES {
+                // Pull in the comments and attributes stacked directly above
+                for leading in children[..i].iter().rev() {
+                    if !LEADING_KINDS.contains(&leading.kind())
+                        || leading.end_position().row + 2 < start
+                    {
+                        break;
+                    }
+                    start = leading.start_position().row + 1;
+                }
+                chunks.push(Chunk {
+                    start_line: start,
+                    end_line: end,
+                    text: lines[start - 1..end].join("\n"),
+                    kind: Some(child.kind().to_string()),
+                });
+                continue;
+            }
+
+            let before = chunks.len();
+            collect(*child, grammar, lines, chunks);
+            if chunks.len() == before {
+                chunks.extend(windows(lines, start, end));
+            }
+        }
+    }
+}
+
+/// Without tree-sitter every file is chunked into windows
+#[cfg(not(feature = "code-chunking"))]
+mod syntax {
+    use super::Chunk;
+    use std::path::Path;
+
+    pub(super) fn definition_chunks(
+        _path: &Path,
+        _content: &str,
+        _lines: &[&str],
+    ) -> Option<Vec<Chunk>> {
+        None
+    }
+}
+
+/// Windows over a gap between definitions, skipping gaps with nothing but closing brackets
+fn gap_windows(lines: &[&str], start: usize, end: usize) -> Vec<Chunk> {
+    let meaningful = lines[start - 1..end].iter().any(|line| {
+        line.trim()
+            .chars()
+            .any(|c| !matches!(c, '}' | ')' | ']' | ';' | ','))
+    });
+    if meaningful {
+        windows(lines, start, end)
+    } else {
+        Vec::new()
+    }
+}
+
+/// Overlapping windows over lines `start..=end`
+fn windows(lines: &[&str], start: usize, end: usize) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut from = start;
+    loop {
+        let to = (from + WINDOW_LINES - 1).min(end);
+        chunks.push(Chunk {
+            start_line: from,
+            end_line: to,
+            text: lines[from - 1..to].join("\n"),
+            kind: None,
+        });
+        if to == end {
+            return chunks;
+        }
+        from = to + 1 - WINDOW_OVERLAP;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"use std::fmt;
+
+/// Adds two numbers
+#[inline]
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+pub struct Point {
+    x: i32,
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.x)
+    }
+}
+"#;
+
+    #[test]
+    #[cfg(feature = "code-chunking")]
+    fn test_rust_chunks_follow_definitions() {
+        let chunks = chunk_file(Path::new("src/lib.rs"), RUST);
+        let spans: Vec<(usize, usize, Option<&str>)> = chunks
+            .iter()
+            .map(|c| (c.start_line, c.end_line, c.kind.as_deref()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (1, 2, None),
+                (3, 7, Some("function_item")),
+                (9, 11, Some("struct_item")),
+                (13, 17, Some("impl_item")),
+            ]
+        );
+        assert!(chunks[1]
+            .text
+            .starts_with("/// Adds two numbers\n#[inline]"));
+    }
+
+    #[test]
+    #[cfg(feature = "code-chunking")]
+    fn test_line_numbers_map_back_to_the_file() {
+        let lines: Vec<&str> = RUST.lines().collect();
+        for chunk in chunk_file(Path::new("lib.rs"), RUST) {
+            assert_eq!(
+                chunk.text,
+                lines[chunk.start_line - 1..chunk.end_line].join("\n")
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "code-chunking")]
+    fn test_large_definitions_split_along_inner_definitions() {
+        let mut source = String::from("class Big:\n");
+        for i in 0..40 {
+            source.push_str(&format!(
+                "    def method_{}(self):\n        x = {}\n        return x\n\n",
+                i, i
+            ));
+        }
+        let chunks = chunk_file(Path::new("big.py"), &source);
+
+        assert_eq!(chunks[0].start_line, 1);
+        assert_eq!(chunks[0].kind, None);
+        let methods: Vec<&Chunk> = chunks
+            .iter()
+            .filter(|c| c.kind.as_deref() == Some("function_definition"))
+            .collect();
+        assert_eq!(methods.len(), 40);
+        assert_eq!((methods[1].start_line, methods[1].end_line), (6, 8));
+    }
+
+    #[test]
+    #[cfg(feature = "code-chunking")]
+    fn test_large_definitions_without_inner_definitions_are_windowed() {
+        let mut source = String::from("fn long() {\n");
+        for i in 0..200 {
+            source.push_str(&format!("    let x{} = {};\n", i, i));
+        }
+        source.push_str("}\n");
+        let chunks = chunk_file(Path::new("long.rs"), &source);
+
+        assert!(chunks.len() > 3);
+        assert!(chunks
+            .iter()
+            .all(|c| c.end_line - c.start_line < WINDOW_LINES));
+        assert_eq!(
+            chunks[1].start_line,
+            chunks[0].end_line + 1 - WINDOW_OVERLAP
+        );
+        assert_eq!(chunks.last().unwrap().end_line, 202);
+    }
+
+    #[test]
+    fn test_unknown_languages_use_windows() {
+        let source: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+        let chunks = chunk_file(Path::new("notes.txt"), &source);
+        let spans: Vec<(usize, usize)> =
+            chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(spans, vec![(1, 60), (51, 100)]);
+        assert!(chunk_file(Path::new("empty.rs"), "").is_empty());
+    }
+}
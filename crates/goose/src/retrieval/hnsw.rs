@@ -0,0 +1,532 @@
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::Path;
+
+const GRAPH_FILE: &str = "graph.json";
+const VECTORS_FILE: &str = "vectors.bin";
+
+/// Tuning for the graph; the defaults suit embeddings of a few hundred to a few thousand
+/// dimensions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HnswParams {
+    /// Neighbors kept per node on the upper layers (twice this on layer 0)
+    pub m: usize,
+    /// Candidates considered when linking a new node
+    pub ef_construction: usize,
+    /// Candidates considered when searching
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    key: String,
+    /// Neighbor ids for each layer this node is on, from layer 0 up
+    neighbors: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Graph {
+    dim: usize,
+    params: HnswParams,
+    nodes: Vec<Node>,
+    entry: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    distance: f32,
+    id: u32,
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+/// A hierarchical navigable small world graph over cosine distance.
+///
+/// Inserts link the new node into the graph as they happen; removes leave a tombstone that
+/// search walks through but never returns, and the graph is rebuilt once tombstones make up
+/// half of it.
+pub struct HnswIndex {
+    graph: Graph,
+    /// Normalized vectors, `dim` floats per node in node order
+    vectors: Vec<f32>,
+    ids: HashMap<String, u32>,
+}
+
+impl HnswIndex {
+    pub fn new(dim: usize, params: HnswParams) -> Self {
+        Self {
+            graph: Graph {
+                dim,
+                params,
+                nodes: Vec::new(),
+                entry: None,
+            },
+            vectors: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.graph.dim
+    }
+
+    /// Number of live entries
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.ids.contains_key(key)
+    }
+
+    /// Add an entry, replacing any entry with the same key
+    pub fn insert(&mut self, key: &str, vector: &[f32]) -> Result<()> {
+        if vector.len() != self.graph.dim {
+            return Err(anyhow!(
+                "Expected a vector of {} dimensions, got {}",
+                self.graph.dim,
+                vector.len()
+            ));
+        }
+        self.remove(key);
+
+        let id = self.graph.nodes.len() as u32;
+        let level = self.random_level();
+        self.vectors.extend(normalize(vector));
+        self.graph.nodes.push(Node {
+            key: key.to_string(),
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.ids.insert(key.to_string(), id);
+
+        let Some(entry) = self.graph.entry else {
+            self.graph.entry = Some(id);
+            return Ok(());
+        };
+
+        let query = self.vector(id).to_vec();
+        let top = self.level(entry);
+        let mut nearest = Scored {
+            distance: self.distance(&query, entry),
+            id: entry,
+        };
+        for layer in (level + 1..=top).rev() {
+            nearest = self.greedy(&query, nearest, layer);
+        }
+
+        let mut entries = vec![nearest];
+        for layer in (0..=level.min(top)).rev() {
+            let candidates =
+                self.search_layer(&query, &entries, self.graph.params.ef_construction, layer);
+            let neighbors: Vec<u32> = candidates
+                .iter()
+                .take(self.graph.params.m)
+                .map(|scored| scored.id)
+                .collect();
+            for &neighbor in &neighbors {
+                self.link(neighbor, id, layer);
+            }
+            self.graph.nodes[id as usize].neighbors[layer] = neighbors;
+            entries = candidates;
+        }
+
+        if level > top {
+            self.graph.entry = Some(id);
+        }
+        Ok(())
+    }
+
+    /// Drop an entry; returns whether it was present
+    pub fn remove(&mut self, key: &str) -> bool {
+        let Some(id) = self.ids.remove(key) else {
+            return false;
+        };
+        self.graph.nodes[id as usize].deleted = true;
+        if self.graph.nodes.len() >= 2 * self.ids.len().max(1) {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// The `k` nearest live entries to `query`, closest first, with their cosine similarity
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        let Some(entry) = self.graph.entry else {
+            return Vec::new();
+        };
+        if query.len() != self.graph.dim || k == 0 {
+            return Vec::new();
+        }
+
+        let query = normalize(query);
+        let mut nearest = Scored {
+            distance: self.distance(&query, entry),
+            id: entry,
+        };
+        for layer in (1..=self.level(entry)).rev() {
+            nearest = self.greedy(&query, nearest, layer);
+        }
+        // Widen the beam by the share of tombstones so deleted nodes don't crowd out live ones
+        let ef =
+            self.graph.params.ef_search.max(k) * self.graph.nodes.len() / self.ids.len().max(1);
+        self.search_layer(&query, &[nearest], ef, 0)
+            .into_iter()
+            .filter(|scored| !self.graph.nodes[scored.id as usize].deleted)
+            .take(k)
+            .map(|scored| {
+                (
+                    self.graph.nodes[scored.id as usize].key.clone(),
+                    1.0 - scored.distance,
+                )
+            })
+            .collect()
+    }
+
+    /// The `k` nearest live entries found by comparing against every entry
+    pub fn search_exact(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        if query.len() != self.graph.dim {
+            return Vec::new();
+        }
+        let query = normalize(query);
+        let mut scored: Vec<Scored> = self
+            .ids
+            .values()
+            .map(|&id| Scored {
+                distance: self.distance(&query, id),
+                id,
+            })
+            .collect();
+        scored.sort();
+        scored
+            .into_iter()
+            .take(k)
+            .map(|scored| {
+                (
+                    self.graph.nodes[scored.id as usize].key.clone(),
+                    1.0 - scored.distance,
+                )
+            })
+            .collect()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let bytes: Vec<u8> = self
+            .vectors
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        std::fs::write(dir.join(VECTORS_FILE), bytes)?;
+        std::fs::write(dir.join(GRAPH_FILE), serde_json::to_vec(&self.graph)?)?;
+        Ok(())
+    }
+
+    /// Whether `dir` holds a saved index
+    pub fn exists(dir: &Path) -> bool {
+        dir.join(GRAPH_FILE).exists()
+    }
+
+    pub fn load(dir: &Path) -> Result<Self> {
+        let graph: Graph = serde_json::from_slice(
+            &std::fs::read(dir.join(GRAPH_FILE))
+                .with_context(|| format!("Failed to read index in {}", dir.display()))?,
+        )?;
+        let bytes = std::fs::read(dir.join(VECTORS_FILE))?;
+        if bytes.len() != graph.nodes.len() * graph.dim * 4 {
+            return Err(anyhow!("Index in {} is corrupt", dir.display()));
+        }
+        let vectors = bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        let ids = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.deleted)
+            .map(|(id, node)| (node.key.clone(), id as u32))
+            .collect();
+        Ok(Self {
+            graph,
+            vectors,
+            ids,
+        })
+    }
+
+    fn random_level(&self) -> usize {
+        let scale = 1.0 / (self.graph.params.m.max(2) as f64).ln();
+        let uniform: f64 = rand::thread_rng().gen_range(f64::EPSILON..1.0);
+        (-uniform.ln() * scale).floor() as usize
+    }
+
+    fn level(&self, id: u32) -> usize {
+        self.graph.nodes[id as usize].neighbors.len() - 1
+    }
+
+    fn vector(&self, id: u32) -> &[f32] {
+        let start = id as usize * self.graph.dim;
+        &self.vectors[start..start + self.graph.dim]
+    }
+
+    fn distance(&self, query: &[f32], id: u32) -> f32 {
+        1.0 - query
+            .iter()
+            .zip(self.vector(id))
+            .map(|(a, b)| a * b)
+            .sum::<f32>()
+    }
+
+    fn greedy(&self, query: &[f32], mut nearest: Scored, layer: usize) -> Scored {
+        loop {
+            let mut improved = false;
+            for &neighbor in &self.graph.nodes[nearest.id as usize].neighbors[layer] {
+                let distance = self.distance(query, neighbor);
+                if distance < nearest.distance {
+                    nearest = Scored {
+                        distance,
+                        id: neighbor,
+                    };
+                    improved = true;
+                }
+            }
+            if !improved {
+                return nearest;
+            }
+        }
+    }
+
+    /// Beam search on one layer; the `ef` closest nodes found, closest first
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entries: &[Scored],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<u32> = entries.iter().map(|scored| scored.id).collect();
+        let mut candidates: BinaryHeap<Reverse<Scored>> =
+            entries.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Scored> = entries.iter().copied().collect();
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            if found
+                .peek()
+                .is_some_and(|furthest| candidate.distance > furthest.distance)
+                && found.len() >= ef
+            {
+                break;
+            }
+            let node = &self.graph.nodes[candidate.id as usize];
+            let Some(neighbors) = node.neighbors.get(layer) else {
+                continue;
+            };
+            for &neighbor in neighbors {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let scored = Scored {
+                    distance: self.distance(query, neighbor),
+                    id: neighbor,
+                };
+                if found.len() < ef
+                    || found
+                        .peek()
+                        .is_some_and(|furthest| scored.distance < furthest.distance)
+                {
+                    candidates.push(Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Add `id` to `node`'s neighbors on `layer`, keeping only the closest when it overflows
+    fn link(&mut self, node: u32, id: u32, layer: usize) {
+        let capacity = if layer == 0 {
+            2 * self.graph.params.m
+        } else {
+            self.graph.params.m
+        };
+        let mut neighbors = std::mem::take(&mut self.graph.nodes[node as usize].neighbors[layer]);
+        neighbors.push(id);
+        if neighbors.len() > capacity {
+            let base = self.vector(node).to_vec();
+            let mut scored: Vec<Scored> = neighbors
+                .iter()
+                .map(|&neighbor| Scored {
+                    distance: self.distance(&base, neighbor),
+                    id: neighbor,
+                })
+                .collect();
+            scored.sort();
+            neighbors = scored
+                .into_iter()
+                .take(capacity)
+                .map(|scored| scored.id)
+                .collect();
+        }
+        self.graph.nodes[node as usize].neighbors[layer] = neighbors;
+    }
+
+    /// Rebuild the graph from the live entries, dropping tombstones
+    fn rebuild(&mut self) {
+        let mut live: Vec<(String, u32)> = self.ids.drain().collect();
+        live.sort_by_key(|(_, id)| *id);
+        let vectors = std::mem::take(&mut self.vectors);
+        let dim = self.graph.dim;
+        self.graph.nodes.clear();
+        self.graph.entry = None;
+        for (key, id) in live {
+            let start = id as usize * dim;
+            // Stored vectors are already normalized and of the right size
+            let _ = self.insert(&key, &vectors[start..start + dim]);
+        }
+    }
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|value| value / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn random_vectors(count: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..count)
+            .map(|_| (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    fn build(vectors: &[Vec<f32>]) -> HnswIndex {
+        let mut index = HnswIndex::new(vectors[0].len(), HnswParams::default());
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&format!("chunk-{}", i), vector).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_search_matches_exact_search() {
+        let vectors = random_vectors(2_000, 32);
+        let index = build(&vectors);
+
+        let queries = random_vectors(50, 32);
+        let mut hits = 0;
+        for query in &queries {
+            let exact: HashSet<String> = index
+                .search_exact(query, 10)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect();
+            hits += index
+                .search(query, 10)
+                .iter()
+                .filter(|(key, _)| exact.contains(key))
+                .count();
+        }
+        let recall = hits as f64 / (queries.len() * 10) as f64;
+        assert!(recall > 0.9, "recall was {}", recall);
+    }
+
+    #[test]
+    fn test_insert_and_remove_are_incremental() {
+        let vectors = random_vectors(200, 16);
+        let mut index = build(&vectors);
+
+        let top = index.search(&vectors[5], 1);
+        assert_eq!(top[0].0, "chunk-5");
+        assert!((top[0].1 - 1.0).abs() < 1e-5);
+
+        assert!(index.remove("chunk-5"));
+        assert!(!index.remove("chunk-5"));
+        assert_eq!(index.len(), 199);
+        assert!(index
+            .search(&vectors[5], 10)
+            .iter()
+            .all(|(key, _)| key != "chunk-5"));
+
+        // Re-inserting a key replaces its vector
+        index.insert("chunk-6", &vectors[5]).unwrap();
+        assert_eq!(index.len(), 199);
+        assert_eq!(index.search(&vectors[5], 1)[0].0, "chunk-6");
+    }
+
+    #[test]
+    fn test_rebuilds_after_many_removals() {
+        let vectors = random_vectors(100, 8);
+        let mut index = build(&vectors);
+        for i in 0..60 {
+            index.remove(&format!("chunk-{}", i));
+        }
+        assert_eq!(index.len(), 40);
+        assert!(index.graph.nodes.len() < 100);
+        assert_eq!(index.search(&vectors[80], 1)[0].0, "chunk-80");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let vectors = random_vectors(100, 8);
+        let mut index = build(&vectors);
+        index.remove("chunk-3");
+        index.save(dir.path()).unwrap();
+
+        let loaded = HnswIndex::load(dir.path()).unwrap();
+        assert_eq!(loaded.len(), 99);
+        assert!(!loaded.contains("chunk-3"));
+        assert_eq!(
+            loaded.search(&vectors[42], 3),
+            index.search(&vectors[42], 3)
+        );
+        assert!(index.insert("bad", &[1.0]).is_err());
+    }
+}
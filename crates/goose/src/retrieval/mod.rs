@@ -0,0 +1,105 @@
+pub mod chunking;
+pub mod hnsw;
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use hnsw::{HnswIndex, HnswParams};
+
+/// Below this many entries a linear scan is exact and already fast enough, so search skips
+/// the graph
+pub const ANN_MIN_ENTRIES: usize = 100_000;
+
+/// Vector index for retrieval, persisted in a directory.
+///
+/// Entries are kept in an HNSW graph that is updated on every insert and remove, so the
+/// indexer can apply changes as it observes them without rebuilding. Large indexes are
+/// searched through the graph; small ones by a linear scan.
+pub struct VectorIndex {
+    dir: PathBuf,
+    index: HnswIndex,
+}
+
+impl VectorIndex {
+    /// Open the index saved in `dir`, or start an empty one
+    pub fn open(dir: &Path, dim: usize) -> Result<Self> {
+        let index = if HnswIndex::exists(dir) {
+            let index = HnswIndex::load(dir)?;
+            if index.dim() == dim {
+                index
+            } else {
+                tracing::info!(
+                    "Embedding size changed from {} to {}, starting a new index",
+                    index.dim(),
+                    dim
+                );
+                HnswIndex::new(dim, HnswParams::default())
+            }
+        } else {
+            HnswIndex::new(dim, HnswParams::default())
+        };
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            index,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.index.contains(key)
+    }
+
+    pub fn insert(&mut self, key: &str, vector: &[f32]) -> Result<()> {
+        self.index.insert(key, vector)
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.index.remove(key)
+    }
+
+    /// The `k` entries most similar to `query`, most similar first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        if self.index.len() < ANN_MIN_ENTRIES {
+            self.index.search_exact(query, k)
+        } else {
+            self.index.search(query, k)
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.index.save(&self.dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_save_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+
+        let mut index = VectorIndex::open(&path, 3).unwrap();
+        assert!(index.is_empty());
+        index.insert("a.rs:1", &[1.0, 0.0, 0.0]).unwrap();
+        index.insert("b.rs:1", &[0.0, 1.0, 0.0]).unwrap();
+        index.save().unwrap();
+
+        let mut index = VectorIndex::open(&path, 3).unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.search(&[0.9, 0.1, 0.0], 1)[0].0, "a.rs:1");
+        assert!(index.remove("a.rs:1"));
+        assert_eq!(index.search(&[0.9, 0.1, 0.0], 1)[0].0, "b.rs:1");
+
+        // A different embedding size can't reuse the saved vectors
+        assert!(VectorIndex::open(&path, 4).unwrap().is_empty());
+    }
+}
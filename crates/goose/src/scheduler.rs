@@ -7,6 +7,7 @@ use std::sync::Arc;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use etcetera::{choose_app_strategy, AppStrategy};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
@@ -14,20 +15,36 @@ use tokio_cron_scheduler::{job::JobId, Job, JobScheduler as TokioJobScheduler};
 
 use crate::agents::AgentEvent;
 use crate::agents::{Agent, SessionConfig};
+use crate::clock;
 use crate::config::{self, Config};
-use crate::conversation::message::Message;
+use crate::conversation::message::{Message, MessageContent};
 use crate::conversation::Conversation;
+use crate::permission::permission_confirmation::PrincipalType;
+use crate::permission::{Permission, PermissionConfirmation};
+use crate::project::workspace_lock::{self, LockAttempt, WorkspaceLock, WorkspaceLockPolicy};
 use crate::providers::base::Provider as GooseProvider; // Alias to avoid conflict in test section
 use crate::providers::create;
 use crate::recipe::Recipe;
 use crate::scheduler_trait::SchedulerTrait;
 use crate::session;
+use crate::session::database::SessionDatabase;
 use crate::session::storage::SessionMetadata;
+use crate::session::store::StorageBackend;
 
 // Track running tasks with their abort handles
 type RunningTasksMap = HashMap<String, tokio::task::AbortHandle>;
 type JobsMap = HashMap<String, (JobId, ScheduledJob)>;
 
+/// Config key: set to `false` to leave runs cut short by a shutdown or restart until the job's
+/// next scheduled time instead of resuming them when the scheduler starts
+pub const RESUME_INTERRUPTED_KEY: &str = "GOOSE_SCHEDULER_RESUME_INTERRUPTED";
+
+/// Added to the session of an interrupted run when it is resumed
+const RESUME_PROMPT: &str = "This scheduled task was interrupted before it finished: goose was \
+    stopped or the machine restarted. Background processes and commands you started may no \
+    longer be running. Check the state of what you had started, then continue the task from the \
+    last step that completed. Do not redo work that is already done.";
+
 /// Normalize a cron string so that:
 /// 1. It is always in **quartz 7-field format** expected by Temporal
 ///    (seconds minutes hours dom month dow year).
@@ -160,20 +177,130 @@ pub struct ScheduledJob {
     pub process_start_time: Option<DateTime<Utc>>,
     #[serde(default)]
     pub execution_mode: Option<String>, // "foreground" or "background"
+    /// IANA timezone the cron expression is read in. Recorded when the job is added;
+    /// jobs saved before timezones were recorded run in UTC.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// The timezone a job's cron expression fires in
+fn job_timezone(job: &ScheduledJob) -> Tz {
+    match job.timezone.as_deref().map(clock::parse_timezone) {
+        Some(Ok(tz)) => tz,
+        Some(Err(e)) => {
+            tracing::warn!("Job '{}' has an invalid timezone, using UTC: {}", job.id, e);
+            Tz::UTC
+        }
+        None => Tz::UTC,
+    }
+}
+
+/// Whether jobs are kept in the session database rather than `schedules.json`
+fn schedules_in_database() -> bool {
+    StorageBackend::from_config(Config::global()) == StorageBackend::Sqlite
+}
+
+/// Store the job list. With SQLite storage the jobs go into the session database and a
+/// leftover `schedules.json` is removed once they are stored.
+async fn write_jobs(storage_path: &Path, list: Vec<ScheduledJob>) -> Result<(), SchedulerError> {
+    if schedules_in_database() {
+        tokio::task::spawn_blocking(move || SessionDatabase::global()?.save_schedules(&list))
+            .await
+            .map_err(|e| SchedulerError::PersistError(e.to_string()))??;
+        if storage_path.exists() {
+            fs::remove_file(storage_path)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(parent) = storage_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let data = serde_json::to_string_pretty(&list)?;
+    fs::write(storage_path, data)?;
+    Ok(())
+}
+
+/// Read the stored job list. With SQLite storage, jobs only found in `schedules.json` are read
+/// from there until they are next saved.
+async fn read_jobs(storage_path: &Path) -> Result<Vec<ScheduledJob>, SchedulerError> {
+    if schedules_in_database() {
+        let jobs = tokio::task::spawn_blocking(|| SessionDatabase::global()?.load_schedules())
+            .await
+            .map_err(|e| SchedulerError::PersistError(e.to_string()))??;
+        if !jobs.is_empty() {
+            return Ok(jobs);
+        }
+    }
+
+    if !storage_path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(storage_path)?;
+    if data.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&data).map_err(|e| {
+        SchedulerError::PersistError(format!("Failed to deserialize schedules.json: {}", e))
+    })
 }
 
 async fn persist_jobs_from_arc(
     storage_path: &Path,
     jobs_arc: &Arc<Mutex<JobsMap>>,
 ) -> Result<(), SchedulerError> {
-    let jobs_guard = jobs_arc.lock().await;
-    let list: Vec<ScheduledJob> = jobs_guard.values().map(|(_, j)| j.clone()).collect();
-    if let Some(parent) = storage_path.parent() {
-        fs::create_dir_all(parent).map_err(SchedulerError::StorageError)?;
+    let list: Vec<ScheduledJob> = {
+        let jobs_guard = jobs_arc.lock().await;
+        jobs_guard.values().map(|(_, j)| j.clone()).collect()
+    };
+    write_jobs(storage_path, list).await
+}
+
+/// Clear the running state of jobs that were persisted while running, which means the process
+/// stopped mid-run, and return them as they were so their runs can be resumed
+fn take_interrupted_jobs(jobs: &mut [ScheduledJob]) -> Vec<ScheduledJob> {
+    let mut interrupted = Vec::new();
+    for job in jobs.iter_mut().filter(|job| job.currently_running) {
+        interrupted.push(job.clone());
+        job.currently_running = false;
+        job.current_session_id = None;
+        job.process_start_time = None;
     }
-    let data = serde_json::to_string_pretty(&list).map_err(SchedulerError::from)?;
-    fs::write(storage_path, data).map_err(SchedulerError::StorageError)?;
-    Ok(())
+    interrupted
+}
+
+/// The session an interrupted run was writing to: the one recorded on the job, or else the
+/// newest session of the schedule written since the run started
+fn interrupted_session(job: &ScheduledJob) -> Option<String> {
+    if let Some(session_id) = &job.current_session_id {
+        let path =
+            session::storage::get_path(session::storage::Identifier::Name(session_id.clone()));
+        if path.is_ok_and(|path| path.exists()) {
+            return Some(session_id.clone());
+        }
+    }
+
+    let started = job.process_start_time?;
+    session::storage::list_sessions()
+        .ok()?
+        .into_iter()
+        .filter(|(_, path)| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| DateTime::<Utc>::from(modified) >= started)
+        })
+        .filter(|(_, path)| {
+            session::storage::read_metadata(path)
+                .is_ok_and(|metadata| metadata.schedule_id.as_deref() == Some(job.id.as_str()))
+        })
+        .map(|(name, _)| name)
+        .max()
+}
+
+/// The messages of an interrupted run with a note asking the agent to pick up where it left off
+fn resume_conversation(mut messages: Conversation) -> Conversation {
+    messages.push(Message::user().with_text(RESUME_PROMPT));
+    messages
 }
 
 pub struct Scheduler {
@@ -218,6 +345,13 @@ impl Scheduler {
             return Err(SchedulerError::JobIdExists(original_job_spec.id.clone()));
         }
 
+        // Cron expressions are written in the user's local time, so record which one that is
+        // rather than leaving it to wherever the scheduler happens to run
+        let timezone = match &original_job_spec.timezone {
+            Some(name) => clock::parse_timezone(name).map_err(SchedulerError::AnyhowError)?,
+            None => clock::user_timezone(),
+        };
+
         let original_recipe_path = Path::new(&original_job_spec.source);
         if !original_recipe_path.exists() {
             return Err(SchedulerError::RecipeLoadError(format!(
@@ -262,6 +396,7 @@ impl Scheduler {
         stored_job.source = destination_recipe_path.to_string_lossy().into_owned();
         stored_job.current_session_id = None;
         stored_job.process_start_time = None;
+        stored_job.timezone = Some(timezone.name().to_string());
         tracing::info!("Updated job source path to: {}", stored_job.source);
 
         let job_for_task = stored_job.clone();
@@ -287,7 +422,7 @@ impl Scheduler {
                 tokio_cron
             );
         }
-        let cron_task = Job::new_async(&tokio_cron, move |_uuid, _l| {
+        let cron_task = Job::new_async_tz(&tokio_cron, timezone, move |_uuid, _l| {
             let task_job_id = job_for_task.id.clone();
             let current_jobs_arc = jobs_arc_for_task.clone();
             let local_storage_path = storage_path_for_task.clone();
@@ -340,6 +475,7 @@ impl Scheduler {
                     None,
                     Some(current_jobs_arc.clone()),
                     Some(task_job_id.clone()),
+                    None,
                 ));
 
                 // Store the abort handle at the scheduler level
@@ -419,17 +555,8 @@ impl Scheduler {
     }
 
     async fn load_jobs_from_storage(self: &Arc<Self>) -> Result<(), SchedulerError> {
-        if !self.storage_path.exists() {
-            return Ok(());
-        }
-        let data = fs::read_to_string(&self.storage_path)?;
-        if data.trim().is_empty() {
-            return Ok(());
-        }
-
-        let list: Vec<ScheduledJob> = serde_json::from_str(&data).map_err(|e| {
-            SchedulerError::PersistError(format!("Failed to deserialize schedules.json: {}", e))
-        })?;
+        let mut list = read_jobs(&self.storage_path).await?;
+        let interrupted = take_interrupted_jobs(&mut list);
 
         let mut jobs_guard = self.jobs.lock().await;
         for job_to_load in list {
@@ -465,7 +592,8 @@ impl Scheduler {
                     tokio_cron
                 );
             }
-            let cron_task = Job::new_async(&tokio_cron, move |_uuid, _l| {
+            let timezone = job_timezone(&job_to_load);
+            let cron_task = Job::new_async_tz(&tokio_cron, timezone, move |_uuid, _l| {
                 let task_job_id = job_for_task.id.clone();
                 let current_jobs_arc = jobs_arc_for_task.clone();
                 let local_storage_path = storage_path_for_task.clone();
@@ -518,6 +646,7 @@ impl Scheduler {
                         None,
                         Some(current_jobs_arc.clone()),
                         Some(task_job_id.clone()),
+                        None,
                     ));
 
                     // Store the abort handle at the scheduler level
@@ -594,21 +723,57 @@ impl Scheduler {
                 .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
             jobs_guard.insert(job_to_load.id.clone(), (job_uuid, job_to_load));
         }
+        drop(jobs_guard);
+
+        if !interrupted.is_empty() {
+            self.persist_jobs().await?;
+            self.resume_interrupted_jobs(interrupted).await;
+        }
         Ok(())
     }
 
+    /// Continue the runs that were cut short when goose last stopped, in their original sessions
+    async fn resume_interrupted_jobs(self: &Arc<Self>, interrupted: Vec<ScheduledJob>) {
+        let resume = Config::global()
+            .get_param::<bool>(RESUME_INTERRUPTED_KEY)
+            .unwrap_or(true);
+        for job in interrupted {
+            if !resume || !self.jobs.lock().await.contains_key(&job.id) {
+                tracing::info!(
+                    "Scheduled job '{}' was interrupted and will not be resumed",
+                    job.id
+                );
+                continue;
+            }
+            let Some(session_id) = interrupted_session(&job) else {
+                tracing::info!(
+                    "Scheduled job '{}' was interrupted before it saved a session; it will run at its next scheduled time",
+                    job.id
+                );
+                continue;
+            };
+
+            tracing::info!(
+                "Resuming scheduled job '{}' in session {}",
+                job.id,
+                session_id
+            );
+            let scheduler = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = scheduler.execute_job(&job.id, Some(session_id)).await {
+                    tracing::error!("Failed to resume scheduled job '{}': {}", job.id, e);
+                }
+            });
+        }
+    }
+
     // Renamed and kept for direct use when a guard is already held (e.g. add/remove)
     async fn persist_jobs_to_storage_with_guard(
         &self,
         jobs_guard: &tokio::sync::MutexGuard<'_, JobsMap>,
     ) -> Result<(), SchedulerError> {
         let list: Vec<ScheduledJob> = jobs_guard.values().map(|(_, j)| j.clone()).collect();
-        if let Some(parent) = self.storage_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let data = serde_json::to_string_pretty(&list)?;
-        fs::write(&self.storage_path, data)?;
-        Ok(())
+        write_jobs(&self.storage_path, list).await
     }
 
     // New function that locks and calls the helper, for run_now and potentially other places
@@ -684,12 +849,22 @@ impl Scheduler {
     }
 
     pub async fn run_now(&self, sched_id: &str) -> Result<String, SchedulerError> {
+        self.execute_job(sched_id, None).await
+    }
+
+    /// Run a job right away, either afresh or continuing an interrupted run's session
+    async fn execute_job(
+        &self,
+        sched_id: &str,
+        resume_session_id: Option<String>,
+    ) -> Result<String, SchedulerError> {
         let job_to_run: ScheduledJob = {
             let mut jobs_guard = self.jobs.lock().await;
             match jobs_guard.get_mut(sched_id) {
                 Some((_, job_def)) => {
                     // Set the currently_running flag before executing
                     job_def.currently_running = true;
+                    job_def.process_start_time = Some(Utc::now());
                     let job_clone = job_def.clone();
                     // Drop the guard before persisting to avoid borrow issues
                     drop(jobs_guard);
@@ -708,6 +883,7 @@ impl Scheduler {
             None,
             Some(self.jobs.clone()),
             Some(sched_id.to_string()),
+            resume_session_id,
         ));
 
         // Store the abort handle for run_now jobs
@@ -845,7 +1021,8 @@ impl Scheduler {
                         tokio_cron
                     );
                 }
-                let cron_task = Job::new_async(&tokio_cron, move |_uuid, _l| {
+                let timezone = job_timezone(job_def);
+                let cron_task = Job::new_async_tz(&tokio_cron, timezone, move |_uuid, _l| {
                     let task_job_id = job_for_task.id.clone();
                     let current_jobs_arc = jobs_arc_for_task.clone();
                     let local_storage_path = storage_path_for_task.clone();
@@ -901,6 +1078,7 @@ impl Scheduler {
                             None,
                             Some(current_jobs_arc.clone()),
                             Some(task_job_id.clone()),
+                            None,
                         ));
 
                         // Store the abort handle at the scheduler level
@@ -1069,6 +1247,7 @@ async fn run_scheduled_job_internal(
     provider_override: Option<Arc<dyn GooseProvider>>, // New optional parameter
     jobs_arc: Option<Arc<Mutex<JobsMap>>>,
     job_id: Option<String>,
+    resume_session_id: Option<String>,
 ) -> std::result::Result<String, JobExecutionError> {
     tracing::info!("Executing job: {} (Source: {})", job.id, job.source);
 
@@ -1147,7 +1326,18 @@ async fn run_scheduled_job_internal(
                 error: format!("Model config error: {}", e),
             })?;
 
-        agent_provider = create(&provider_name, model_config).map_err(|e| JobExecutionError {
+        // Nobody waits on a scheduled run, so it can go through the batch API when asked to
+        let batch = recipe
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.batch)
+            .unwrap_or(false);
+        let provider = if batch {
+            crate::providers::openai_batch::create(&provider_name, model_config)
+        } else {
+            create(&provider_name, model_config)
+        };
+        agent_provider = provider.map_err(|e| JobExecutionError {
             job_id: job.id.clone(),
             error: format!(
                 "Failed to create provider instance '{}': {}",
@@ -1179,7 +1369,9 @@ async fn run_scheduled_job_internal(
     let execution_mode = job.execution_mode.as_deref().unwrap_or("background");
     tracing::info!("Job '{}' running in {} mode", job.id, execution_mode);
 
-    let session_id_for_return = session::generate_session_id();
+    let session_id_for_return = resume_session_id
+        .clone()
+        .unwrap_or_else(session::generate_session_id);
 
     // Update the job with the session ID if we have access to the jobs arc
     if let (Some(jobs_arc), Some(job_id_str)) = (jobs_arc.as_ref(), job_id.as_ref()) {
@@ -1201,10 +1393,28 @@ async fn run_scheduled_job_internal(
         }
     };
 
-    if let Some(prompt_text) = recipe.prompt {
-        let mut all_session_messages =
-            Conversation::new_unvalidated(vec![Message::user().with_text(prompt_text.clone())]);
+    let initial_messages = match resume_session_id {
+        Some(_) => {
+            let messages = session::storage::read_messages(&session_file_path).map_err(|e| {
+                JobExecutionError {
+                    job_id: job.id.clone(),
+                    error: format!("Failed to read interrupted session: {}", e),
+                }
+            })?;
+            tracing::info!(
+                "[Job {}] Resuming session {} after {} messages",
+                job.id,
+                session_id_for_return,
+                messages.len()
+            );
+            Some(resume_conversation(messages))
+        }
+        None => recipe.prompt.map(|prompt_text| {
+            Conversation::new_unvalidated(vec![Message::user().with_text(prompt_text)])
+        }),
+    };
 
+    if let Some(mut all_session_messages) = initial_messages {
         let current_dir = match std::env::current_dir() {
             Ok(cd) => cd,
             Err(e) => {
@@ -1215,6 +1425,38 @@ async fn run_scheduled_job_internal(
             }
         };
 
+        // Nobody is there to ask, so a job waits for whoever is working in the same workspace
+        // unless it may attach read-only
+        let policy = WorkspaceLockPolicy::from_config();
+        let owner = format!("schedule:{}", job.id);
+        let _workspace_lock = match policy {
+            WorkspaceLockPolicy::Off => None,
+            WorkspaceLockPolicy::ReadOnly => match WorkspaceLock::try_acquire(&current_dir, &owner)
+            {
+                Ok(LockAttempt::Acquired(lock)) => Some(lock),
+                Ok(LockAttempt::Held(_)) => {
+                    tracing::info!("[Job {}] Workspace is busy, running read-only", job.id);
+                    agent.set_read_only(true);
+                    None
+                }
+                Err(e) => {
+                    tracing::warn!("[Job {}] Failed to lock the workspace: {}", job.id, e);
+                    None
+                }
+            },
+            WorkspaceLockPolicy::Prompt | WorkspaceLockPolicy::Queue => Some(
+                WorkspaceLock::acquire(&current_dir, &owner, workspace_lock::queue_timeout())
+                    .await
+                    .map_err(|e| JobExecutionError {
+                        job_id: job.id.clone(),
+                        error: e.to_string(),
+                    })?,
+            ),
+        };
+
+        // Saved as the run goes, so a run cut short can be resumed from where it got to
+        save_job_session(&job.id, &session_file_path, &all_session_messages);
+
         let session_config = SessionConfig {
             id: crate::session::storage::Identifier::Name(session_id_for_return.clone()),
             working_dir: current_dir.clone(),
@@ -1241,10 +1483,31 @@ async fn run_scheduled_job_internal(
 
                     match message_result {
                         Ok(AgentEvent::Message(msg)) => {
+                            // Nobody is there to approve a tool that needs confirmation
+                            if let Some(MessageContent::ToolConfirmationRequest(confirmation)) =
+                                msg.content.first()
+                            {
+                                tracing::warn!(
+                                    "[Job {}] Denying {}: it needs the user's confirmation",
+                                    job.id,
+                                    confirmation.tool_name
+                                );
+                                agent
+                                    .handle_confirmation(
+                                        confirmation.id.clone(),
+                                        PermissionConfirmation {
+                                            principal_type: PrincipalType::Tool,
+                                            permission: Permission::DenyOnce,
+                                        },
+                                    )
+                                    .await;
+                                continue;
+                            }
                             if msg.role == rmcp::model::Role::Assistant {
                                 tracing::info!("[Job {}] Assistant: {:?}", job.id, msg.content);
                             }
                             all_session_messages.push(msg);
+                            save_job_session(&job.id, &session_file_path, &all_session_messages);
                         }
                         Ok(AgentEvent::McpNotification(_)) => {
                             // Handle notifications if needed
@@ -1255,6 +1518,36 @@ async fn run_scheduled_job_internal(
                         Ok(AgentEvent::HistoryReplaced(_)) => {
                             // Handle history replacement events if needed
                         }
+                        Ok(AgentEvent::ContextCompacted(_)) => {
+                            // The compacted history arrives as a HistoryReplaced event
+                        }
+                        Ok(AgentEvent::ContextUsage(_)) => {
+                            // Context usage is only shown by interactive clients
+                        }
+                        Ok(AgentEvent::CredentialRequest(request)) => {
+                            // Nobody is there to answer in a scheduled run
+                            tracing::warn!(
+                                "[Job {}] Declining request for credential {}",
+                                job.id,
+                                request.name
+                            );
+                            agent.provide_credential(request.id, None).await;
+                        }
+                        Ok(AgentEvent::Refusal(refusal)) => {
+                            tracing::warn!(
+                                "[Job {}] Model {} declined the request",
+                                job.id,
+                                refusal.model
+                            );
+                        }
+                        Ok(AgentEvent::SecretBlocked(blocked)) => {
+                            tracing::warn!(
+                                "[Job {}] Blocked {} for writing credentials: {:?}",
+                                job.id,
+                                blocked.tool_name,
+                                blocked.findings
+                            );
+                        }
                         Err(e) => {
                             tracing::error!(
                                 "[Job {}] Error receiving message from agent: {}",
@@ -1266,49 +1559,7 @@ async fn run_scheduled_job_internal(
                     }
                 }
 
-                match crate::session::storage::read_metadata(&session_file_path) {
-                    Ok(mut updated_metadata) => {
-                        updated_metadata.message_count = all_session_messages.len();
-                        if let Err(e) = crate::session::storage::save_messages_with_metadata(
-                            &session_file_path,
-                            &updated_metadata,
-                            &all_session_messages,
-                        ) {
-                            tracing::error!(
-                                "[Job {}] Failed to persist final messages: {}",
-                                job.id,
-                                e
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            "[Job {}] Failed to read updated metadata before final save: {}",
-                            job.id,
-                            e
-                        );
-                        let fallback_metadata = crate::session::storage::SessionMetadata {
-                            working_dir: current_dir.clone(),
-                            description: String::new(),
-                            schedule_id: Some(job.id.clone()),
-                            project_id: None,
-                            message_count: all_session_messages.len(),
-                            total_tokens: None,
-                            input_tokens: None,
-                            output_tokens: None,
-                            accumulated_total_tokens: None,
-                            accumulated_input_tokens: None,
-                            accumulated_output_tokens: None,
-                        };
-                        if let Err(e_fb) = crate::session::storage::save_messages_with_metadata(
-                            &session_file_path,
-                            &fallback_metadata,
-                            &all_session_messages,
-                        ) {
-                            tracing::error!("[Job {}] Failed to persist final messages with fallback metadata: {}", job.id, e_fb);
-                        }
-                    }
-                }
+                save_job_session(&job.id, &session_file_path, &all_session_messages);
             }
             Err(e) => {
                 return Err(JobExecutionError {
@@ -1347,6 +1598,28 @@ async fn run_scheduled_job_internal(
     Ok(session_id_for_return)
 }
 
+/// Write a job's messages to its session, keeping the metadata the agent has recorded
+fn save_job_session(job_id: &str, session_file_path: &Path, messages: &Conversation) {
+    let mut metadata = match session::storage::read_metadata(session_file_path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            tracing::error!(
+                "[Job {}] Failed to read session metadata before saving: {}",
+                job_id,
+                e
+            );
+            SessionMetadata::default()
+        }
+    };
+    metadata.schedule_id = Some(job_id.to_string());
+    metadata.message_count = messages.len();
+    if let Err(e) =
+        session::storage::save_messages_with_metadata(session_file_path, &metadata, messages)
+    {
+        tracing::error!("[Job {}] Failed to persist messages: {}", job_id, e);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1471,16 +1744,22 @@ mod tests {
             current_session_id: None,
             process_start_time: None,
             execution_mode: Some("background".to_string()), // Default for test
+            timezone: None,
         };
 
         let mock_model_config = ModelConfig::new_or_fail("test_model");
         let mock_provider_instance = create_scheduler_test_mock_provider(mock_model_config);
 
         // Call run_scheduled_job_internal, passing the mock provider
-        let created_session_id =
-            run_scheduled_job_internal(dummy_job.clone(), Some(mock_provider_instance), None, None)
-                .await
-                .expect("run_scheduled_job_internal failed");
+        let created_session_id = run_scheduled_job_internal(
+            dummy_job.clone(),
+            Some(mock_provider_instance),
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("run_scheduled_job_internal failed");
 
         let session_dir = session::storage::ensure_session_dir()?;
         let expected_session_path = session_dir.join(format!("{}.jsonl", created_session_id));
@@ -1523,6 +1802,53 @@ mod tests {
 
         Ok(())
     }
+
+    fn job(id: &str, running: bool) -> ScheduledJob {
+        ScheduledJob {
+            id: id.to_string(),
+            source: format!("/recipes/{}.yaml", id),
+            cron: "0 0 9 * * *".to_string(),
+            last_run: None,
+            currently_running: running,
+            paused: false,
+            current_session_id: running.then(|| "20261016_090000".to_string()),
+            process_start_time: running.then(Utc::now),
+            execution_mode: Some("background".to_string()),
+            timezone: None,
+        }
+    }
+
+    #[test]
+    fn test_take_interrupted_jobs() {
+        let mut jobs = vec![job("idle", false), job("cut_short", true)];
+
+        let interrupted = take_interrupted_jobs(&mut jobs);
+
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].id, "cut_short");
+        assert_eq!(
+            interrupted[0].current_session_id.as_deref(),
+            Some("20261016_090000")
+        );
+        assert!(jobs.iter().all(|job| !job.currently_running
+            && job.current_session_id.is_none()
+            && job.process_start_time.is_none()));
+    }
+
+    #[test]
+    fn test_resume_conversation_asks_to_continue() {
+        let messages = Conversation::new_unvalidated(vec![
+            Message::user().with_text("Back up the photos"),
+            Message::assistant().with_text("Copied the first album."),
+        ]);
+
+        let resumed = resume_conversation(messages);
+
+        assert_eq!(resumed.len(), 3);
+        let last = resumed.last().unwrap();
+        assert_eq!(last.role, Role::User);
+        assert!(last.as_concat_text().contains("last step that completed"));
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,306 @@
+//! Suggestions for trading cost against capability, read from a session's usage ledger.
+//!
+//! The advisor looks at the most recent requests for patterns that point at a better model
+//! choice, such as many short turns on an expensive model or a small model whose context keeps
+//! filling up, and offers a one-line suggestion with the command to act on it.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::Config;
+use crate::model::ModelConfig;
+use crate::providers::pricing::{get_all_pricing, get_model_pricing, parse_model_id};
+use crate::session::usage::{UsageEntry, UsageLedger};
+
+/// Config key for turning the suggestions off
+pub const COST_ADVISOR_KEY: &str = "GOOSE_COST_ADVISOR";
+
+/// Requests looked at to spot a pattern
+const WINDOW: usize = 10;
+/// Turns answering with fewer output tokens than this are short
+const SHORT_TURN_OUTPUT_TOKENS: i64 = 400;
+/// Share of the window that must be short turns before a cheaper model is suggested
+const SHORT_TURN_SHARE: f64 = 0.8;
+/// The least a cheaper model has to save to be worth suggesting
+const MIN_SAVINGS: f64 = 0.5;
+/// A request this close to the context limit counts as overflowing it
+const NEAR_LIMIT: f64 = 0.9;
+/// Requests near the limit in the window before a larger model is suggested
+const OVERFLOW_REQUESTS: usize = 3;
+
+/// What the advisor knows about a model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelProfile {
+    pub name: String,
+    /// Cost per input and output token, when the model has a known price
+    pub pricing: Option<(f64, f64)>,
+    pub context_limit: usize,
+}
+
+impl ModelProfile {
+    fn cost(&self, entries: &[&UsageEntry]) -> Option<f64> {
+        let (input_cost, output_cost) = self.pricing?;
+        Some(
+            entries
+                .iter()
+                .map(|entry| {
+                    input_cost * entry.input_tokens as f64
+                        + output_cost * entry.output_tokens as f64
+                })
+                .sum(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SuggestionKind {
+    /// The turns are simple enough for a cheaper model
+    CheaperModel,
+    /// The model's context is too small for the work
+    LargerContext,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    pub message: String,
+    /// The in-session command that acts on it
+    pub command: String,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (run `{}`)", self.message, self.command)
+    }
+}
+
+/// Suggestions for the model serving `current` given its recent requests, choosing among
+/// `candidates`
+pub fn advise(
+    ledger: &UsageLedger,
+    current: &ModelProfile,
+    candidates: &[ModelProfile],
+) -> Vec<Suggestion> {
+    let recent: Vec<&UsageEntry> = ledger
+        .entries
+        .iter()
+        .rev()
+        .take_while(|entry| entry.model == current.name)
+        .take(WINDOW)
+        .collect();
+    if recent.len() < WINDOW {
+        return Vec::new();
+    }
+    let largest_input = recent.iter().map(|entry| entry.input_tokens).max();
+    let largest_input = largest_input.unwrap_or(0).max(0) as usize;
+
+    let mut suggestions = Vec::new();
+
+    let short_turns = recent
+        .iter()
+        .filter(|entry| entry.output_tokens < SHORT_TURN_OUTPUT_TOKENS)
+        .count();
+    if short_turns as f64 >= SHORT_TURN_SHARE * recent.len() as f64 {
+        if let Some(current_cost) = current.cost(&recent).filter(|cost| *cost > 0.0) {
+            // The cheapest model that would still have fit every recent request
+            let cheaper = candidates
+                .iter()
+                .filter(|candidate| candidate.name != current.name)
+                .filter(|candidate| candidate.context_limit > largest_input)
+                .filter_map(|candidate| Some((candidate, candidate.cost(&recent)?)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((candidate, cost)) = cheaper {
+                let savings = 1.0 - cost / current_cost;
+                if savings >= MIN_SAVINGS {
+                    suggestions.push(Suggestion {
+                        kind: SuggestionKind::CheaperModel,
+                        message: format!(
+                            "Most recent turns on {} are short; switch to {}, est. {:.0}% cost reduction",
+                            current.name,
+                            candidate.name,
+                            savings * 100.0
+                        ),
+                        command: format!("/model {}", candidate.name),
+                    });
+                }
+            }
+        }
+    }
+
+    let overflowing = recent
+        .iter()
+        .filter(|entry| entry.input_tokens as f64 >= NEAR_LIMIT * current.context_limit as f64)
+        .count();
+    if overflowing >= OVERFLOW_REQUESTS {
+        // The cheapest model with at least twice the room, or the largest when none is priced
+        let larger: Vec<&ModelProfile> = candidates
+            .iter()
+            .filter(|candidate| candidate.context_limit >= 2 * current.context_limit)
+            .collect();
+        let pick = larger
+            .iter()
+            .filter_map(|candidate| Some((*candidate, candidate.cost(&recent)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(candidate, _)| candidate)
+            .or_else(|| {
+                larger
+                    .iter()
+                    .max_by_key(|candidate| candidate.context_limit)
+                    .copied()
+            });
+        if let Some(candidate) = pick {
+            suggestions.push(Suggestion {
+                kind: SuggestionKind::LargerContext,
+                message: format!(
+                    "{} of the last {} requests filled {}'s {}k context; switch to {} with {}k",
+                    overflowing,
+                    recent.len(),
+                    current.name,
+                    current.context_limit / 1000,
+                    candidate.name,
+                    candidate.context_limit / 1000
+                ),
+                command: format!("/model {}", candidate.name),
+            });
+        }
+    }
+
+    suggestions
+}
+
+/// Whether suggestions are shown, from GOOSE_COST_ADVISOR
+pub fn enabled() -> bool {
+    Config::global()
+        .get_param::<bool>(COST_ADVISOR_KEY)
+        .unwrap_or(true)
+}
+
+/// Suggestions for a session served by `provider` with `model`, using the cached model prices
+pub async fn suggestions(
+    provider: &str,
+    model: &ModelConfig,
+    ledger: &UsageLedger,
+) -> Vec<Suggestion> {
+    if ledger.entries.len() < WINDOW {
+        return Vec::new();
+    }
+    // Models served through a router carry their real provider in the name
+    let (billed_by, model_name) = parse_model_id(&model.model_name)
+        .unwrap_or_else(|| (provider.to_string(), model.model_name.clone()));
+    let current = ModelProfile {
+        name: model.model_name.clone(),
+        pricing: get_model_pricing(&billed_by, &model_name)
+            .await
+            .map(|pricing| (pricing.input_cost, pricing.output_cost)),
+        context_limit: model.context_limit(),
+    };
+
+    let all_pricing = get_all_pricing().await;
+    let candidates: Vec<ModelProfile> = all_pricing
+        .get(&billed_by)
+        .unwrap_or(&HashMap::new())
+        .iter()
+        .filter_map(|(name, pricing)| {
+            Some(ModelProfile {
+                // Keep the router prefix so the suggested name works with the same provider
+                name: if billed_by == provider {
+                    name.clone()
+                } else {
+                    format!("{}/{}", billed_by, name)
+                },
+                pricing: Some((pricing.input_cost, pricing.output_cost)),
+                context_limit: pricing.context_length? as usize,
+            })
+        })
+        .collect();
+
+    advise(ledger, &current, &candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(model: &str, input_tokens: i64, output_tokens: i64) -> UsageEntry {
+        UsageEntry {
+            timestamp: Utc::now(),
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+        }
+    }
+
+    fn profile(name: &str, pricing: Option<(f64, f64)>, context_limit: usize) -> ModelProfile {
+        ModelProfile {
+            name: name.to_string(),
+            pricing,
+            context_limit,
+        }
+    }
+
+    fn ledger(entries: impl IntoIterator<Item = UsageEntry>) -> UsageLedger {
+        UsageLedger {
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_suggests_a_cheaper_model_for_short_turns() {
+        let ledger = ledger((0..WINDOW).map(|_| entry("big", 8_000, 120)));
+        let current = profile("big", Some((10e-6, 30e-6)), 128_000);
+        let candidates = vec![
+            profile("small", Some((0.5e-6, 1.5e-6)), 128_000),
+            profile("tiny", Some((0.1e-6, 0.2e-6)), 4_000),
+        ];
+
+        let suggestions = advise(&ledger, &current, &candidates);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].kind, SuggestionKind::CheaperModel);
+        // tiny is cheaper but the recent requests wouldn't fit in it
+        assert_eq!(suggestions[0].command, "/model small");
+        assert!(suggestions[0].message.contains("95% cost reduction"));
+    }
+
+    #[test]
+    fn test_no_suggestion_for_long_turns_or_a_short_history() {
+        let current = profile("big", Some((10e-6, 30e-6)), 128_000);
+        let candidates = vec![profile("small", Some((0.5e-6, 1.5e-6)), 128_000)];
+
+        let long = ledger((0..WINDOW).map(|_| entry("big", 8_000, 2_000)));
+        assert!(advise(&long, &current, &candidates).is_empty());
+
+        let short = ledger((0..WINDOW - 1).map(|_| entry("big", 8_000, 100)));
+        assert!(advise(&short, &current, &candidates).is_empty());
+
+        // Turns on a model the session has since moved away from don't count
+        let switched = ledger(
+            (0..WINDOW)
+                .map(|_| entry("other", 8_000, 100))
+                .chain([entry("big", 8_000, 100)]),
+        );
+        assert!(advise(&switched, &current, &candidates).is_empty());
+    }
+
+    #[test]
+    fn test_suggests_a_larger_context_when_it_keeps_filling() {
+        let ledger = ledger((0..WINDOW).map(|i| {
+            let input = if i % 3 == 0 { 7_800 } else { 3_000 };
+            entry("small", input, 1_000)
+        }));
+        let current = profile("small", Some((0.1e-6, 0.2e-6)), 8_000);
+        let candidates = vec![
+            profile("medium", Some((1e-6, 2e-6)), 32_000),
+            profile("large", Some((5e-6, 10e-6)), 200_000),
+            profile("same", Some((0.05e-6, 0.1e-6)), 8_000),
+        ];
+
+        let suggestions = advise(&ledger, &current, &candidates);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].kind, SuggestionKind::LargerContext);
+        assert_eq!(suggestions[0].command, "/model medium");
+        assert!(suggestions[0].message.starts_with("4 of the last 10"));
+    }
+}
@@ -0,0 +1,360 @@
+//! Portable session bundles.
+//!
+//! A `.goosebundle` file carries what is needed to pick a session up on another machine: its
+//! metadata and messages, its event log, the large tool outputs its messages refer to (which
+//! otherwise live in a temporary directory on the machine that ran the tools), and a snapshot of
+//! the configuration it ran with. Secrets never go into a bundle: values from the secret store,
+//! and config keys named like credentials, are left out of the snapshot.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::agents::large_response_handler::{
+    output_references, relocate_output_paths, store_dir, stored_output_path,
+};
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::session::events::{event_log_path, SessionEvent, SessionEventLog};
+use crate::session::storage::{
+    get_path, read_messages, read_metadata, save_messages_with_metadata, session_exists,
+    Identifier, SessionMetadata,
+};
+
+/// File extension of session bundles
+pub const BUNDLE_EXTENSION: &str = "goosebundle";
+
+const BUNDLE_FORMAT: &str = "goosebundle";
+const BUNDLE_VERSION: u32 = 1;
+
+/// Last `_`-separated part of the config keys whose values are credentials
+const SECRET_KEY_SUFFIXES: &[&str] = &[
+    "KEY",
+    "APIKEY",
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIALS",
+];
+
+/// A session packed into one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBundle {
+    pub format: String,
+    pub version: u32,
+    /// Name of the session the bundle was exported from
+    pub name: String,
+    /// Unix timestamp of the export
+    pub exported_at: i64,
+    pub metadata: SessionMetadata,
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<SessionEvent>,
+    /// Stored tool outputs the messages refer to, by reference
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tool_outputs: BTreeMap<String, String>,
+    /// The configuration values the session ran with, without secrets
+    #[serde(default)]
+    pub config: BTreeMap<String, Value>,
+}
+
+/// What importing a bundle did
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    pub session_file: PathBuf,
+    pub messages: usize,
+    pub tool_outputs: usize,
+    /// The working directory the session will resume in
+    pub working_dir: PathBuf,
+    /// Whether the session's own working directory was missing here and replaced
+    pub working_dir_replaced: bool,
+}
+
+/// Whether a config key names a credential
+fn is_secret_key(key: &str) -> bool {
+    let last = key.rsplit(['_', '-', '.']).next().unwrap_or(key);
+    SECRET_KEY_SUFFIXES.contains(&last.to_uppercase().as_str())
+}
+
+/// `value` without credentials: keys named like one, and strings equal to a known secret
+fn strip_secrets(value: &mut Value, secrets: &HashSet<String>) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, value| {
+                !is_secret_key(key) && !value.as_str().is_some_and(|s| secrets.contains(s))
+            });
+            map.values_mut()
+                .for_each(|value| strip_secrets(value, secrets));
+        }
+        Value::Array(items) => {
+            items.retain(|item| !item.as_str().is_some_and(|s| secrets.contains(s)));
+            items
+                .iter_mut()
+                .for_each(|item| strip_secrets(item, secrets));
+        }
+        _ => {}
+    }
+}
+
+/// The config values, with everything the secret store holds and anything that looks like a
+/// credential left out
+pub fn config_snapshot(config: &Config) -> BTreeMap<String, Value> {
+    let values = config.load_values().unwrap_or_default();
+    let secrets = config.load_secrets().unwrap_or_default();
+    let secret_values: HashSet<String> = secrets
+        .values()
+        .filter_map(|value| value.as_str())
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect();
+
+    let mut snapshot = Value::Object(
+        values
+            .into_iter()
+            .filter(|(key, _)| !secrets.contains_key(key))
+            .collect(),
+    );
+    strip_secrets(&mut snapshot, &secret_values);
+    match snapshot {
+        Value::Object(map) => map.into_iter().collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+/// Apply `f` to every string in `value`
+fn visit_strings(value: &mut Value, f: &mut impl FnMut(&mut String)) {
+    match value {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter_mut().for_each(|item| visit_strings(item, f)),
+        Value::Object(map) => map.values_mut().for_each(|item| visit_strings(item, f)),
+        _ => {}
+    }
+}
+
+impl SessionBundle {
+    /// Pack a stored session, with the config of this machine
+    pub fn from_session(session_file: &Path) -> Result<Self> {
+        if !session_exists(session_file) {
+            return Err(anyhow!(
+                "Session file not found (expected path: {})",
+                session_file.display()
+            ));
+        }
+        let metadata = read_metadata(session_file)?;
+        let messages = read_messages(session_file)?;
+        let events = SessionEventLog::load(&event_log_path(session_file))?
+            .events()
+            .to_vec();
+        let name = session_file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("session")
+            .to_string();
+        Self::new(
+            name,
+            metadata,
+            messages.messages().clone(),
+            events,
+            config_snapshot(Config::global()),
+        )
+    }
+
+    fn new(
+        name: String,
+        metadata: SessionMetadata,
+        messages: Vec<Message>,
+        events: Vec<SessionEvent>,
+        config: BTreeMap<String, Value>,
+    ) -> Result<Self> {
+        let mut references = Vec::new();
+        let mut serialized = serde_json::to_value(&messages)?;
+        visit_strings(&mut serialized, &mut |text| {
+            for reference in output_references(text) {
+                if !references.contains(&reference) {
+                    references.push(reference);
+                }
+            }
+        });
+        // Outputs can be cleared from the temporary directory; the messages still have previews
+        let tool_outputs = references
+            .into_iter()
+            .filter_map(|reference| {
+                let content = fs::read_to_string(stored_output_path(&reference)?).ok()?;
+                Some((reference, content))
+            })
+            .collect();
+
+        Ok(Self {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            name,
+            exported_at: chrono::Utc::now().timestamp(),
+            metadata,
+            messages,
+            events,
+            tool_outputs,
+            config,
+        })
+    }
+
+    /// Read a bundle written by [`SessionBundle::write`]
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read bundle {}", path.display()))?;
+        let header: Value = serde_json::from_str(&content)
+            .with_context(|| format!("{} is not a session bundle", path.display()))?;
+        if header["format"].as_str() != Some(BUNDLE_FORMAT) {
+            return Err(anyhow!("{} is not a session bundle", path.display()));
+        }
+        match header["version"].as_u64() {
+            Some(version) if version <= BUNDLE_VERSION as u64 => {}
+            version => {
+                return Err(anyhow!(
+                    "{} is a version {} bundle; this goose reads up to version {}",
+                    path.display(),
+                    version.unwrap_or_default(),
+                    BUNDLE_VERSION
+                ))
+            }
+        }
+        Ok(serde_json::from_value(header)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write bundle {}", path.display()))
+    }
+
+    /// The messages, with paths of stored outputs moved into this machine's store
+    fn relocated_messages(&self) -> Result<Vec<Message>> {
+        let mut serialized = serde_json::to_value(&self.messages)?;
+        visit_strings(&mut serialized, &mut |text| {
+            if !output_references(text).is_empty() {
+                *text = relocate_output_paths(text);
+            }
+        });
+        Ok(serde_json::from_value(serialized)?)
+    }
+
+    /// Store the bundled session as `name`, resuming in `working_dir` if given, or otherwise in
+    /// its own working directory when that exists here and the current directory when it doesn't.
+    /// An existing session of that name is never overwritten.
+    pub fn import(&self, name: &str, working_dir: Option<PathBuf>) -> Result<ImportReport> {
+        let session_file = get_path(Identifier::Name(name.to_string()))?;
+        if session_exists(&session_file) {
+            return Err(anyhow!(
+                "A session named '{}' already exists; import it under another name",
+                name
+            ));
+        }
+
+        let mut metadata = self.metadata.clone();
+        let working_dir_replaced = working_dir.is_none() && !metadata.working_dir.is_dir();
+        metadata.working_dir = match working_dir {
+            Some(dir) => dir,
+            None if working_dir_replaced => std::env::current_dir()?,
+            None => metadata.working_dir,
+        };
+
+        let dir = store_dir();
+        fs::create_dir_all(&dir)?;
+        for (reference, content) in &self.tool_outputs {
+            let path = stored_output_path(reference)
+                .ok_or_else(|| anyhow!("The bundle has an invalid output reference"))?;
+            fs::write(path, content)?;
+        }
+
+        // The bundled events come first, so saving records the relocation on top of them
+        if !self.events.is_empty() {
+            SessionEventLog::from_events(self.events.clone())
+                .save_as(&event_log_path(&session_file))?;
+        }
+
+        let messages = self.relocated_messages()?;
+        save_messages_with_metadata(
+            &session_file,
+            &metadata,
+            &Conversation::new_unvalidated(messages.clone()),
+        )?;
+
+        Ok(ImportReport {
+            session_file,
+            messages: messages.len(),
+            tool_outputs: self.tool_outputs.len(),
+            working_dir: metadata.working_dir,
+            working_dir_replaced,
+        })
+    }
+
+    /// Set the bundled config values this machine doesn't have yet. Returns the keys set.
+    pub fn apply_config(&self, config: &Config) -> Result<Vec<String>> {
+        let current = config.load_values().unwrap_or_default();
+        let mut applied = Vec::new();
+        for (key, value) in &self.config {
+            if !current.contains_key(key) {
+                config.set_param(key, value.clone())?;
+                applied.push(key.clone());
+            }
+        }
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bundle_strips_secrets_and_carries_tool_outputs() {
+        let mut config = json!({
+            "GOOSE_PROVIDER": "openai",
+            "GOOSE_MAX_TOKENS": 4096,
+            "OPENAI_API_KEY": "sk-test",
+            "extensions": {
+                "github": {"envs": {"GITHUB_TOKEN": "ghp-test", "GITHUB_ORG": "block"}}
+            },
+            "GOOSE_FALLBACK_MODEL": "kept-in-keyring"
+        });
+        strip_secrets(&mut config, &HashSet::from(["kept-in-keyring".to_string()]));
+        assert_eq!(
+            config,
+            json!({
+                "GOOSE_PROVIDER": "openai",
+                "GOOSE_MAX_TOKENS": 4096,
+                "extensions": {"github": {"envs": {"GITHUB_ORG": "block"}}}
+            })
+        );
+
+        let reference = format!("output_{}", uuid::Uuid::new_v4().simple());
+        let stored = stored_output_path(&reference).unwrap();
+        fs::create_dir_all(stored.parent().unwrap()).unwrap();
+        fs::write(&stored, "line 1\nline 2").unwrap();
+        let elsewhere = format!("/var/folders/xy/T/goose_mcp_responses/{}.txt", reference);
+        let messages = vec![Message::user().with_text(format!(
+            "[Read more with reference \"{}\". The full output is also saved at {} for searching.]",
+            reference, elsewhere
+        ))];
+
+        let bundle = SessionBundle::new(
+            "build".to_string(),
+            SessionMetadata::default(),
+            messages,
+            Vec::new(),
+            BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(bundle.tool_outputs[&reference], "line 1\nline 2");
+
+        let relocated = bundle.relocated_messages().unwrap()[0].as_concat_text();
+        assert!(!relocated.contains(&elsewhere));
+        assert!(relocated.contains(&stored.display().to_string()));
+        fs::remove_file(stored).unwrap();
+    }
+}
@@ -0,0 +1,449 @@
+//! SQLite storage for sessions and schedules.
+//!
+//! Enabled with `GOOSE_SESSION_STORAGE=sqlite`. Every session lives in one database next to the
+//! sessions directory, opened in WAL mode with a busy timeout so several goose processes can read
+//! and write the same sessions without clobbering each other. Writes run in immediate
+//! transactions, so a session is always replaced as a whole or not at all.
+//!
+//! Sessions that only exist as JSONL files stay readable and move into the database the next
+//! time they are saved. The scheduler keeps its jobs here too, and the memory extension keeps
+//! its own table in the same file.
+//!
+//! Queries block, so when they are made from a multi-threaded tokio runtime they run through
+//! `block_in_place` to keep the other tasks on that worker moving.
+
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::scheduler::ScheduledJob;
+use crate::session::storage::{ensure_session_dir, SessionMetadata};
+use crate::session::store::{SessionStore, StoredSession};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::runtime::RuntimeFlavor;
+
+const DATABASE_FILE_NAME: &str = "goose.db";
+
+/// How long a write waits for another process to release the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Initial schema: sessions with their metadata, and their messages in order
+const CREATE_SESSIONS: &str = "
+    CREATE TABLE sessions (
+        path TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        metadata TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE messages (
+        session_path TEXT NOT NULL REFERENCES sessions(path) ON DELETE CASCADE,
+        idx INTEGER NOT NULL,
+        message TEXT NOT NULL,
+        PRIMARY KEY (session_path, idx)
+    );
+    CREATE INDEX sessions_updated_at ON sessions(updated_at);
+";
+
+/// Scheduled jobs, one serialized job per row
+const CREATE_SCHEDULES: &str = "
+    CREATE TABLE schedules (
+        id TEXT PRIMARY KEY,
+        job TEXT NOT NULL
+    );
+";
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many have run.
+const MIGRATIONS: &[&str] = &[CREATE_SESSIONS, CREATE_SCHEDULES];
+
+static DATABASE: OnceCell<SessionDatabase> = OnceCell::new();
+
+/// Sessions are keyed by the path of their (possibly legacy) JSONL file, which is how the rest
+/// of goose identifies them
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: i64 = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let version = version.max(0) as usize;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(migration)
+            .with_context(|| format!("Failed to apply session database migration {}", index + 1))?;
+    }
+    if version < MIGRATIONS.len() {
+        tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+        tracing::info!(
+            "Migrated session database from version {} to {}",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// A handle to the session database
+pub struct SessionDatabase {
+    conn: Mutex<Connection>,
+}
+
+impl SessionDatabase {
+    /// Open (creating if needed) the database at `path` and bring its schema up to date
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Failed to open session database {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let journal_mode: String =
+            conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            tracing::warn!(
+                "Session database is using {} journal mode instead of WAL",
+                journal_mode
+            );
+        }
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        migrate(&mut conn)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Where the process-wide database lives, next to the sessions directory
+    pub fn default_path() -> Result<PathBuf> {
+        let session_dir = ensure_session_dir()?;
+        let data_dir = session_dir.parent().unwrap_or(&session_dir);
+        Ok(data_dir.join(DATABASE_FILE_NAME))
+    }
+
+    /// The process-wide database
+    pub fn global() -> Result<&'static SessionDatabase> {
+        DATABASE.get_or_try_init(|| Self::open(&Self::default_path()?))
+    }
+
+    /// Run `f` with the connection, telling a multi-threaded runtime that this worker is about
+    /// to block so it can hand its other tasks to another thread
+    fn with_conn<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        let run = || {
+            let mut conn = self
+                .conn
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Session database lock poisoned"))?;
+            f(&mut conn)
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(run)
+            }
+            _ => run(),
+        }
+    }
+
+    /// Every scheduled job, in no particular order
+    pub fn load_schedules(&self) -> Result<Vec<ScheduledJob>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT job FROM schedules")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            let mut jobs = Vec::new();
+            for row in rows {
+                jobs.push(serde_json::from_str(&row?).context("Failed to parse scheduled job")?);
+            }
+            Ok(jobs)
+        })
+    }
+
+    /// Replace every scheduled job in a single transaction
+    pub fn save_schedules(&self, jobs: &[ScheduledJob]) -> Result<()> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute("DELETE FROM schedules", [])?;
+            {
+                let mut insert = tx.prepare("INSERT INTO schedules (id, job) VALUES (?1, ?2)")?;
+                for job in jobs {
+                    insert.execute(params![job.id, serde_json::to_string(job)?])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+}
+
+impl SessionStore for SessionDatabase {
+    /// Replace a session's metadata and messages in a single transaction
+    fn save_session(
+        &self,
+        path: &Path,
+        metadata: &SessionMetadata,
+        messages: &Conversation,
+    ) -> Result<()> {
+        let key = path_key(path);
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| key.clone());
+
+        self.with_conn(|conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute(
+                "INSERT INTO sessions (path, name, metadata, updated_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(path) DO UPDATE SET
+                    metadata = excluded.metadata,
+                    updated_at = excluded.updated_at",
+                params![
+                    key,
+                    name,
+                    serde_json::to_string(metadata)?,
+                    Utc::now().timestamp_millis()
+                ],
+            )?;
+            tx.execute("DELETE FROM messages WHERE session_path = ?1", params![key])?;
+            {
+                let mut insert = tx.prepare(
+                    "INSERT INTO messages (session_path, idx, message) VALUES (?1, ?2, ?3)",
+                )?;
+                for (idx, message) in messages.iter().enumerate() {
+                    insert.execute(params![key, idx as i64, serde_json::to_string(message)?])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })?;
+
+        tracing::debug!("Saved session {} to database", key);
+        Ok(())
+    }
+
+    /// When the session was last saved, in Unix milliseconds
+    fn updated_at(&self, path: &Path) -> Result<Option<i64>> {
+        self.with_conn(|conn| {
+            let updated_at = conn
+                .query_row(
+                    "SELECT updated_at FROM sessions WHERE path = ?1",
+                    params![path_key(path)],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(updated_at)
+        })
+    }
+
+    /// The session's metadata, or `None` if it is not in the database
+    fn load_metadata(&self, path: &Path) -> Result<Option<SessionMetadata>> {
+        let metadata: Option<String> = self.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT metadata FROM sessions WHERE path = ?1",
+                    params![path_key(path)],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })?;
+        metadata
+            .map(|json| serde_json::from_str(&json).context("Failed to parse session metadata"))
+            .transpose()
+    }
+
+    /// The session's messages in order, or `None` if it is not in the database.
+    /// Messages that no longer deserialize are skipped rather than failing the whole session.
+    fn load_messages(&self, path: &Path) -> Result<Option<Conversation>> {
+        let key = path_key(path);
+        let rows: Option<Vec<String>> = self.with_conn(|conn| {
+            let exists = conn
+                .query_row(
+                    "SELECT 1 FROM sessions WHERE path = ?1",
+                    params![key],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !exists {
+                return Ok(None);
+            }
+
+            let mut stmt =
+                conn.prepare("SELECT message FROM messages WHERE session_path = ?1 ORDER BY idx")?;
+            let rows = stmt
+                .query_map(params![key], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(Some(rows))
+        })?;
+        let Some(rows) = rows else {
+            return Ok(None);
+        };
+
+        let mut messages = Vec::new();
+        for row in rows {
+            match serde_json::from_str::<Message>(&row) {
+                Ok(message) => messages.push(message),
+                Err(e) => tracing::warn!("[SESSION] Skipping unreadable stored message: {}", e),
+            }
+        }
+        Ok(Some(Conversation::new_unvalidated(messages)))
+    }
+
+    /// All stored sessions, most recently updated first
+    fn list_sessions(&self) -> Result<Vec<StoredSession>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT name, path, updated_at FROM sessions ORDER BY updated_at DESC")?;
+            let sessions = stmt
+                .query_map([], |row| {
+                    Ok(StoredSession {
+                        name: row.get(0)?,
+                        path: PathBuf::from(row.get::<_, String>(1)?),
+                        updated_at: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(sessions)
+        })
+    }
+
+    /// Remove a session and its messages
+    fn delete_session(&self, path: &Path) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM sessions WHERE path = ?1",
+                params![path_key(path)],
+            )?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample_conversation() -> Conversation {
+        Conversation::new_unvalidated(vec![
+            Message::user().with_text("hello"),
+            Message::assistant().with_text("hi there"),
+        ])
+    }
+
+    #[test]
+    fn test_save_and_load_session() {
+        let dir = tempdir().unwrap();
+        let db = SessionDatabase::open(&dir.path().join(DATABASE_FILE_NAME)).unwrap();
+        let path = dir.path().join("20250101_120000.jsonl");
+
+        assert!(db.load_messages(&path).unwrap().is_none());
+        assert!(!db.contains(&path).unwrap());
+
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        metadata.description = "greeting".to_string();
+        metadata.total_tokens = Some(42);
+        db.save_session(&path, &metadata, &sample_conversation())
+            .unwrap();
+
+        let messages = db.load_messages(&path).unwrap().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages.messages()[1].as_concat_text(), "hi there");
+
+        let loaded = db.load_metadata(&path).unwrap().unwrap();
+        assert_eq!(loaded.description, "greeting");
+        assert_eq!(loaded.total_tokens, Some(42));
+
+        let sessions = db.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "20250101_120000");
+        assert_eq!(sessions[0].path, path);
+    }
+
+    #[test]
+    fn test_save_replaces_messages() {
+        let dir = tempdir().unwrap();
+        let db = SessionDatabase::open(&dir.path().join(DATABASE_FILE_NAME)).unwrap();
+        let path = dir.path().join("session.jsonl");
+        let metadata = SessionMetadata::new(dir.path().to_path_buf());
+
+        db.save_session(&path, &metadata, &sample_conversation())
+            .unwrap();
+        let shorter = Conversation::new_unvalidated(vec![Message::user().with_text("summary")]);
+        db.save_session(&path, &metadata, &shorter).unwrap();
+
+        let messages = db.load_messages(&path).unwrap().unwrap();
+        assert_eq!(messages.len(), 1);
+
+        db.delete_session(&path).unwrap();
+        assert!(db.load_messages(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reopen_keeps_schema_and_data() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join(DATABASE_FILE_NAME);
+        let path = dir.path().join("session.jsonl");
+        {
+            let db = SessionDatabase::open(&db_path).unwrap();
+            let metadata = SessionMetadata::new(dir.path().to_path_buf());
+            db.save_session(&path, &metadata, &sample_conversation())
+                .unwrap();
+        }
+
+        // A second handle, as another goose process would open it
+        let db = SessionDatabase::open(&db_path).unwrap();
+        let version: i64 = db
+            .with_conn(|conn| Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        assert_eq!(db.load_messages(&path).unwrap().unwrap().len(), 2);
+    }
+
+    fn scheduled_job(id: &str) -> ScheduledJob {
+        ScheduledJob {
+            id: id.to_string(),
+            source: format!("/recipes/{}.yaml", id),
+            cron: "0 0 * * * *".to_string(),
+            last_run: None,
+            currently_running: false,
+            paused: false,
+            current_session_id: None,
+            process_start_time: None,
+            execution_mode: None,
+            timezone: None,
+        }
+    }
+
+    #[test]
+    fn test_save_schedules_replaces_all_jobs() {
+        let dir = tempdir().unwrap();
+        let db = SessionDatabase::open(&dir.path().join(DATABASE_FILE_NAME)).unwrap();
+        assert!(db.load_schedules().unwrap().is_empty());
+
+        db.save_schedules(&[scheduled_job("daily"), scheduled_job("hourly")])
+            .unwrap();
+        assert_eq!(db.load_schedules().unwrap().len(), 2);
+
+        db.save_schedules(&[scheduled_job("hourly")]).unwrap();
+        let jobs = db.load_schedules().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "hourly");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_queries_from_a_multi_thread_runtime() {
+        let dir = tempdir().unwrap();
+        let db = SessionDatabase::open(&dir.path().join(DATABASE_FILE_NAME)).unwrap();
+        let path = dir.path().join("session.jsonl");
+        let metadata = SessionMetadata::new(dir.path().to_path_buf());
+
+        db.save_session(&path, &metadata, &sample_conversation())
+            .unwrap();
+        assert_eq!(db.load_messages(&path).unwrap().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,391 @@
+//! Compare two sessions, to find out why the same recipe behaved differently across two runs.
+//!
+//! Every session records a snapshot of the context its model was given: the system prompt, the
+//! tool definitions and the model configuration. A diff reports where those differ and the first
+//! turn at which the two conversations stop matching.
+
+use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::Conversation;
+use crate::model::ModelConfig;
+use crate::session::storage::SessionMetadata;
+use crate::utils::safe_truncate;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Longest message summary shown at a divergence point
+const SUMMARY_CHARS: usize = 200;
+
+/// The context a session's model was last given
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ContextSnapshot {
+    pub system_prompt: String,
+    /// Tool definitions offered to the model, keyed by tool name
+    #[schema(value_type = Object)]
+    pub tools: BTreeMap<String, Value>,
+    /// The model configuration the provider ran with
+    #[schema(value_type = Object)]
+    pub model_config: Value,
+}
+
+impl ContextSnapshot {
+    pub fn new(system_prompt: &str, tools: &[Tool], model_config: &ModelConfig) -> Self {
+        let tools = tools
+            .iter()
+            .map(|tool| {
+                let definition = serde_json::to_value(tool).unwrap_or_default();
+                (tool.name.to_string(), definition)
+            })
+            .collect();
+        Self {
+            system_prompt: system_prompt.to_string(),
+            tools,
+            model_config: serde_json::to_value(model_config).unwrap_or_default(),
+        }
+    }
+}
+
+/// One line of a line-by-line diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", content = "line", rename_all = "lowercase")]
+pub enum LineChange {
+    Removed(String),
+    Added(String),
+}
+
+/// A model configuration field set differently in the two sessions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub a: Value,
+    pub b: Value,
+}
+
+/// How the recorded contexts of two sessions differ
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ContextDiff {
+    /// Lines of the system prompt only in one session, in prompt order
+    pub system_prompt: Vec<LineChange>,
+    pub tools_added: Vec<String>,
+    pub tools_removed: Vec<String>,
+    /// Tools both sessions had, with different descriptions or schemas
+    pub tools_changed: Vec<String>,
+    pub model_config: Vec<FieldChange>,
+}
+
+impl ContextDiff {
+    pub fn new(a: &ContextSnapshot, b: &ContextSnapshot) -> Self {
+        let tools_added = b
+            .tools
+            .keys()
+            .filter(|name| !a.tools.contains_key(*name))
+            .cloned()
+            .collect();
+        let tools_removed = a
+            .tools
+            .keys()
+            .filter(|name| !b.tools.contains_key(*name))
+            .cloned()
+            .collect();
+        let tools_changed = a
+            .tools
+            .iter()
+            .filter(|(name, definition)| b.tools.get(*name).is_some_and(|d| d != *definition))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        Self {
+            system_prompt: diff_lines(&a.system_prompt, &b.system_prompt),
+            tools_added,
+            tools_removed,
+            tools_changed,
+            model_config: diff_fields(&a.model_config, &b.model_config),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.system_prompt.is_empty()
+            && self.tools_added.is_empty()
+            && self.tools_removed.is_empty()
+            && self.tools_changed.is_empty()
+            && self.model_config.is_empty()
+    }
+}
+
+/// The first message at which two conversations differ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Divergence {
+    /// Index of the message, counted from the start of both conversations
+    pub index: usize,
+    /// The message in each session, absent when that conversation ended first
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+/// Everything that differs between two sessions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionDiff {
+    /// None when either session has no recorded context, as with sessions from older versions
+    pub context: Option<ContextDiff>,
+    pub message_counts: (usize, usize),
+    /// None when the conversations are identical
+    pub divergence: Option<Divergence>,
+}
+
+impl SessionDiff {
+    pub fn new(a: (&SessionMetadata, &Conversation), b: (&SessionMetadata, &Conversation)) -> Self {
+        let context = match (&a.0.context, &b.0.context) {
+            (Some(context_a), Some(context_b)) => Some(ContextDiff::new(context_a, context_b)),
+            _ => None,
+        };
+        Self {
+            context,
+            message_counts: (a.1.len(), b.1.len()),
+            divergence: find_divergence(a.1.messages(), b.1.messages()),
+        }
+    }
+}
+
+impl fmt::Display for SessionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.context {
+            None => {
+                writeln!(f, "Context: not recorded for both sessions")?;
+            }
+            Some(context) if context.is_empty() => {
+                writeln!(f, "Context: identical")?;
+            }
+            Some(context) => {
+                if !context.system_prompt.is_empty() {
+                    writeln!(f, "System prompt:")?;
+                    for change in &context.system_prompt {
+                        match change {
+                            LineChange::Removed(line) => writeln!(f, "  - {}", line)?,
+                            LineChange::Added(line) => writeln!(f, "  + {}", line)?,
+                        }
+                    }
+                }
+                let tool_lists = [
+                    ("Tools only in b", &context.tools_added),
+                    ("Tools only in a", &context.tools_removed),
+                    ("Tools with different definitions", &context.tools_changed),
+                ];
+                for (label, tools) in tool_lists {
+                    if !tools.is_empty() {
+                        writeln!(f, "{}: {}", label, tools.join(", "))?;
+                    }
+                }
+                if !context.model_config.is_empty() {
+                    writeln!(f, "Model config:")?;
+                    for change in &context.model_config {
+                        writeln!(f, "  {}: {} -> {}", change.field, change.a, change.b)?;
+                    }
+                }
+            }
+        }
+
+        let (count_a, count_b) = self.message_counts;
+        writeln!(f, "Messages: {} vs {}", count_a, count_b)?;
+        match &self.divergence {
+            None => {
+                writeln!(f, "Conversations: identical")?;
+            }
+            Some(divergence) => {
+                let ended = "(conversation ended)".to_string();
+                writeln!(f, "Conversations diverge at message {}:", divergence.index)?;
+                writeln!(f, "  a: {}", divergence.a.as_ref().unwrap_or(&ended))?;
+                writeln!(f, "  b: {}", divergence.b.as_ref().unwrap_or(&ended))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lines only in one of two texts, using the longest common subsequence of their lines
+fn diff_lines(a: &str, b: &str) -> Vec<LineChange> {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(LineChange::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// Top-level fields of two JSON objects that hold different values
+fn diff_fields(a: &Value, b: &Value) -> Vec<FieldChange> {
+    let empty = serde_json::Map::new();
+    let a = a.as_object().unwrap_or(&empty);
+    let b = b.as_object().unwrap_or(&empty);
+    let mut fields: Vec<&String> = a.keys().chain(b.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let value_a = a.get(field).cloned().unwrap_or(Value::Null);
+            let value_b = b.get(field).cloned().unwrap_or(Value::Null);
+            (value_a != value_b).then(|| FieldChange {
+                field: field.clone(),
+                a: value_a,
+                b: value_b,
+            })
+        })
+        .collect()
+}
+
+fn find_divergence(a: &[Message], b: &[Message]) -> Option<Divergence> {
+    let index = (0..a.len().max(b.len())).find(|&i| {
+        let signature_a = a.get(i).map(message_signature);
+        let signature_b = b.get(i).map(message_signature);
+        signature_a != signature_b
+    })?;
+    let summary = |message: &Message| safe_truncate(&message_signature(message), SUMMARY_CHARS);
+    Some(Divergence {
+        index,
+        a: a.get(index).map(summary),
+        b: b.get(index).map(summary),
+    })
+}
+
+/// What a message says, leaving out ids and timestamps that always differ between runs
+fn message_signature(message: &Message) -> String {
+    let content: Vec<String> = message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::Text(text) => Some(text.text.trim().to_string()),
+            MessageContent::ToolRequest(request) => Some(match &request.tool_call {
+                Ok(call) => format!("[tool call {} {}]", call.name, call.arguments),
+                Err(e) => format!("[invalid tool call: {}]", e),
+            }),
+            MessageContent::ToolResponse(response) => Some(match &response.tool_result {
+                Ok(contents) => {
+                    let text: Vec<&str> = contents
+                        .iter()
+                        .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+                        .collect();
+                    format!("[tool result {}]", text.join(" "))
+                }
+                Err(e) => format!("[tool error: {}]", e),
+            }),
+            _ => None,
+        })
+        .collect();
+    format!("{:?}: {}", message.role, content.join(" ")).replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::ToolCall;
+    use serde_json::json;
+
+    fn snapshot(prompt: &str, tools: &[(&str, &str)], temperature: f32) -> ContextSnapshot {
+        let tools: Vec<Tool> = tools
+            .iter()
+            .map(|(name, description)| {
+                Tool::new(
+                    name.to_string(),
+                    description.to_string(),
+                    rmcp::object!({"type": "object"}),
+                )
+            })
+            .collect();
+        let mut model_config = ModelConfig::new_or_fail("gpt-4o");
+        model_config.temperature = Some(temperature);
+        ContextSnapshot::new(prompt, &tools, &model_config)
+    }
+
+    #[test]
+    fn test_context_diff_reports_prompt_tools_and_config() {
+        let a = snapshot(
+            "You are goose.\nBe brief.",
+            &[("shell", "Run a command"), ("read", "Read a file")],
+            0.0,
+        );
+        let b = snapshot(
+            "You are goose.\nBe thorough.",
+            &[("shell", "Run a shell command"), ("write", "Write a file")],
+            0.7,
+        );
+
+        let diff = ContextDiff::new(&a, &b);
+        assert_eq!(
+            diff.system_prompt,
+            vec![
+                LineChange::Removed("Be brief.".to_string()),
+                LineChange::Added("Be thorough.".to_string()),
+            ]
+        );
+        assert_eq!(diff.tools_added, vec!["write"]);
+        assert_eq!(diff.tools_removed, vec!["read"]);
+        assert_eq!(diff.tools_changed, vec!["shell"]);
+        assert_eq!(diff.model_config.len(), 1);
+        assert_eq!(diff.model_config[0].field, "temperature");
+
+        assert!(ContextDiff::new(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_divergence_ignores_ids_and_finds_first_difference() {
+        let a = vec![
+            Message::user().with_text("fix the build"),
+            Message::assistant().with_tool_request(
+                "call-1",
+                Ok(ToolCall::new("shell", json!({"command": "cargo build"}))),
+            ),
+            Message::assistant().with_text("Done"),
+        ];
+        let b = vec![
+            Message::user().with_text("fix the build"),
+            Message::assistant().with_tool_request(
+                "call-9",
+                Ok(ToolCall::new("shell", json!({"command": "cargo build"}))),
+            ),
+            Message::assistant().with_text("I could not fix it"),
+            Message::user().with_text("try again"),
+        ];
+
+        let divergence = find_divergence(&a, &b).unwrap();
+        assert_eq!(divergence.index, 2);
+        assert_eq!(divergence.a.as_deref(), Some("Assistant: Done"));
+        assert_eq!(
+            divergence.b.as_deref(),
+            Some("Assistant: I could not fix it")
+        );
+
+        let divergence = find_divergence(&a[..2], &b).unwrap();
+        assert_eq!(divergence.index, 2);
+        assert!(divergence.a.is_none());
+
+        assert!(find_divergence(&a, &a).is_none());
+    }
+}
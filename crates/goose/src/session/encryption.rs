@@ -0,0 +1,421 @@
+//! At-rest encryption of session files.
+//!
+//! With `GOOSE_SESSION_ENCRYPTION` on, every line of a session file (its metadata and each
+//! message) and of its event log is sealed with AES-256-GCM before it is written. The key is a
+//! random one kept with goose's other secrets in the OS keyring, or, when
+//! `GOOSE_SESSION_PASSPHRASE` is set, derived from that passphrase with PBKDF2 and a salt chosen
+//! once per install. Both settings are read from the config like any other, so each config can
+//! choose for itself.
+//!
+//! Each line is bound to the name of its file and its position in it, so sealed lines can't be
+//! reordered or moved between sessions without failing to decrypt.
+//!
+//! Encrypted lines are recognised by their prefix and decrypted wherever sessions are read, so
+//! resuming, listing and exporting work the same either way, and plaintext sessions stay readable
+//! after encryption is turned on. A session is re-encrypted (or decrypted) the next time it is
+//! saved. This covers JSONL session files; sessions kept in a [`SessionStore`] are left to that
+//! store.
+//!
+//! [`SessionStore`]: crate::session::store::SessionStore
+
+use crate::config::{Config, ConfigError};
+use anyhow::{Context, Result};
+use base64::Engine;
+use once_cell::sync::Lazy;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Config key turning encryption of new session writes on
+pub const SESSION_ENCRYPTION_KEY: &str = "GOOSE_SESSION_ENCRYPTION";
+/// Secret to derive the session key from instead of keeping a random key in the keyring
+pub const SESSION_PASSPHRASE_KEY: &str = "GOOSE_SESSION_PASSPHRASE";
+/// Secret holding the random session key
+const SESSION_KEY_SECRET: &str = "GOOSE_SESSION_ENCRYPTION_KEY";
+/// Config key holding this install's passphrase salt, which isn't secret
+const SESSION_SALT_KEY: &str = "GOOSE_SESSION_ENCRYPTION_SALT";
+
+/// Marks an encrypted line; followed by base64 of salt, nonce and sealed plaintext. The sealed
+/// data is bound to the line's position.
+const LINE_PREFIX: &str = "goose-enc2:";
+const KEY_LEN: usize = 32;
+/// Salt for passphrase keys, stored on every line so any line can be decrypted on its own
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+type Salt = [u8; SALT_LEN];
+
+/// The salt written with when a cipher wasn't given one
+static WRITE_SALT: Lazy<Salt> = Lazy::new(|| {
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .expect("system random number generator failed");
+    salt
+});
+
+/// Keys derived from passphrases, by salt and passphrase digest
+static DERIVED_KEYS: Lazy<Mutex<HashMap<(Salt, [u8; 32]), [u8; KEY_LEN]>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether a line of a session file is encrypted
+pub fn is_encrypted(line: &str) -> bool {
+    line.starts_with(LINE_PREFIX)
+}
+
+/// Where a line sits: the name of its file and its zero-based index there
+#[derive(Debug, Clone, Copy)]
+pub struct LinePosition<'a> {
+    pub file: &'a str,
+    pub index: usize,
+}
+
+impl LinePosition<'_> {
+    fn aad(&self) -> Vec<u8> {
+        format!("{}:{}", self.file, self.index).into_bytes()
+    }
+}
+
+/// The name lines of a file are bound to
+pub fn file_id(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Whether session writes should be encrypted
+pub fn is_enabled() -> bool {
+    Config::global()
+        .get_param(SESSION_ENCRYPTION_KEY)
+        .unwrap_or(false)
+}
+
+#[derive(Clone)]
+enum KeySource {
+    Key([u8; KEY_LEN]),
+    Passphrase(String),
+}
+
+/// Seals and opens the lines of session files
+#[derive(Clone)]
+pub struct SessionCipher {
+    source: KeySource,
+    salt: Salt,
+}
+
+impl SessionCipher {
+    pub fn with_key(key: [u8; KEY_LEN]) -> Self {
+        Self {
+            source: KeySource::Key(key),
+            salt: *WRITE_SALT,
+        }
+    }
+
+    pub fn with_passphrase(passphrase: impl Into<String>) -> Self {
+        Self {
+            source: KeySource::Passphrase(passphrase.into()),
+            salt: *WRITE_SALT,
+        }
+    }
+
+    /// Write with this salt, so lines written by different processes share one derived key
+    fn with_salt(mut self, salt: Salt) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    /// The cipher session writes should use, or `None` if encryption is off. The first write
+    /// without a passphrase creates the keyring key.
+    pub fn for_writing() -> Result<Option<Self>> {
+        if !is_enabled() {
+            return Ok(None);
+        }
+        if let Some(cipher) = Self::from_passphrase()? {
+            return Ok(Some(cipher.with_salt(install_salt()?)));
+        }
+        let config = Config::global();
+        let key = match Self::stored_key()? {
+            Some(key) => key,
+            None => {
+                let mut key = [0u8; KEY_LEN];
+                SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(|_| anyhow::anyhow!("Failed to generate a session key"))?;
+                config
+                    .set_secret(
+                        SESSION_KEY_SECRET,
+                        base64::engine::general_purpose::STANDARD.encode(key).into(),
+                    )
+                    .context("Failed to store the session key")?;
+                key
+            }
+        };
+        Ok(Some(Self::with_key(key)))
+    }
+
+    /// The cipher to read encrypted sessions with, whether or not encryption is still on
+    pub fn for_reading() -> Result<Self> {
+        if let Some(cipher) = Self::from_passphrase()? {
+            return Ok(cipher);
+        }
+        Self::stored_key()?.map(Self::with_key).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Session is encrypted but no key was found; set {} or restore the keyring entry",
+                SESSION_PASSPHRASE_KEY
+            )
+        })
+    }
+
+    fn from_passphrase() -> Result<Option<Self>> {
+        match Config::global().get_secret::<String>(SESSION_PASSPHRASE_KEY) {
+            Ok(passphrase) if !passphrase.is_empty() => Ok(Some(Self::with_passphrase(passphrase))),
+            Ok(_) | Err(ConfigError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn stored_key() -> Result<Option<[u8; KEY_LEN]>> {
+        let encoded = match Config::global().get_secret::<String>(SESSION_KEY_SECRET) {
+            Ok(encoded) => encoded,
+            Err(ConfigError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+            .ok_or_else(|| anyhow::anyhow!("Stored session key is malformed"))?;
+        Ok(Some(key))
+    }
+
+    fn key(&self, salt: &Salt) -> Result<LessSafeKey> {
+        let bytes = match &self.source {
+            KeySource::Key(key) => *key,
+            KeySource::Passphrase(passphrase) => {
+                let digest: [u8; 32] = Sha256::digest(passphrase.as_bytes()).into();
+                let mut derived = DERIVED_KEYS
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("Session key cache lock poisoned"))?;
+                *derived.entry((*salt, digest)).or_insert_with(|| {
+                    let mut key = [0u8; KEY_LEN];
+                    pbkdf2::derive(
+                        pbkdf2::PBKDF2_HMAC_SHA256,
+                        NonZeroU32::new(PBKDF2_ITERATIONS).expect("iterations are non-zero"),
+                        salt,
+                        passphrase.as_bytes(),
+                        &mut key,
+                    );
+                    key
+                })
+            }
+        };
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| anyhow::anyhow!("Invalid session key"))?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    /// Seal one line of a session file, bound to where it is written
+    pub fn encrypt_line(&self, plaintext: &str, position: LinePosition) -> Result<String> {
+        let salt = self.salt;
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("Failed to generate a nonce"))?;
+
+        let mut sealed = plaintext.as_bytes().to_vec();
+        self.key(&salt)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(position.aad()),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt session data"))?;
+
+        let mut payload = Vec::with_capacity(SALT_LEN + NONCE_LEN + sealed.len());
+        payload.extend_from_slice(&salt);
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&sealed);
+        Ok(format!(
+            "{}{}",
+            LINE_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(payload)
+        ))
+    }
+
+    /// Open a line written by [`Self::encrypt_line`] at the same position; plaintext lines are
+    /// returned as they are
+    pub fn decrypt_line(&self, line: &str, position: LinePosition) -> Result<String> {
+        let Some(encoded) = line.strip_prefix(LINE_PREFIX) else {
+            return Ok(line.to_string());
+        };
+        let mut payload = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim_end())
+            .context("Encrypted session line is not valid base64")?;
+        if payload.len() < SALT_LEN + NONCE_LEN {
+            return Err(anyhow::anyhow!("Encrypted session line is truncated"));
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&payload[..SALT_LEN]);
+        let nonce = Nonce::try_assume_unique_for_key(&payload[SALT_LEN..SALT_LEN + NONCE_LEN])
+            .map_err(|_| anyhow::anyhow!("Encrypted session line has an invalid nonce"))?;
+        let plaintext = self
+            .key(&salt)?
+            .open_in_place(
+                nonce,
+                Aad::from(position.aad()),
+                &mut payload[SALT_LEN + NONCE_LEN..],
+            )
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Failed to decrypt session; the key or passphrase is wrong, or the line was moved"
+                )
+            })?;
+        Ok(String::from_utf8(plaintext.to_vec())?)
+    }
+}
+
+/// This install's passphrase salt, created the first time it is needed
+fn install_salt() -> Result<Salt> {
+    static SALT: Lazy<Mutex<Option<Salt>>> = Lazy::new(|| Mutex::new(None));
+    let mut cached = SALT
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Session salt lock poisoned"))?;
+    if let Some(salt) = *cached {
+        return Ok(salt);
+    }
+
+    let config = Config::global();
+    let stored = config
+        .get_param::<String>(SESSION_SALT_KEY)
+        .ok()
+        .and_then(|encoded| {
+            base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .ok()
+        })
+        .and_then(|bytes| Salt::try_from(bytes).ok());
+    let salt = match stored {
+        Some(salt) => salt,
+        None => {
+            let mut salt = [0u8; SALT_LEN];
+            SystemRandom::new()
+                .fill(&mut salt)
+                .map_err(|_| anyhow::anyhow!("Failed to generate a session salt"))?;
+            config
+                .set_param(
+                    SESSION_SALT_KEY,
+                    base64::engine::general_purpose::STANDARD
+                        .encode(salt)
+                        .into(),
+                )
+                .context("Failed to store the session salt")?;
+            salt
+        }
+    };
+    *cached = Some(salt);
+    Ok(salt)
+}
+
+/// Decrypts the lines of a file as they are read, only looking up the key once the first
+/// encrypted line turns up. Every line of the file goes through it, in order, so it knows each
+/// line's position.
+pub(crate) struct LineDecryptor {
+    cipher: Option<SessionCipher>,
+    file: String,
+    next_index: usize,
+}
+
+impl LineDecryptor {
+    pub(crate) fn for_file(path: &Path) -> Self {
+        Self {
+            cipher: None,
+            file: file_id(path),
+            next_index: 0,
+        }
+    }
+
+    pub(crate) fn decrypt(&mut self, line: String) -> Result<String> {
+        let position = LinePosition {
+            file: &self.file,
+            index: self.next_index,
+        };
+        self.next_index += 1;
+        if !is_encrypted(&line) {
+            return Ok(line);
+        }
+        let cipher = match &self.cipher {
+            Some(cipher) => cipher,
+            None => self.cipher.insert(SessionCipher::for_reading()?),
+        };
+        cipher.decrypt_line(&line, position)
+    }
+
+    /// Account for a line that couldn't be read
+    pub(crate) fn skip(&mut self) {
+        self.next_index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: LinePosition = LinePosition {
+        file: "a.jsonl",
+        index: 1,
+    };
+
+    #[test]
+    fn test_round_trip_with_key_and_passphrase() {
+        for cipher in [
+            SessionCipher::with_key([7u8; KEY_LEN]),
+            SessionCipher::with_passphrase("correct horse battery staple"),
+        ] {
+            let line = r#"{"role":"user","content":[{"type":"text","text":"api key sk-123"}]}"#;
+            let sealed = cipher.encrypt_line(line, FIRST).unwrap();
+            assert!(is_encrypted(&sealed));
+            assert!(!sealed.contains("sk-123"));
+            // A fresh nonce every time
+            assert_ne!(sealed, cipher.encrypt_line(line, FIRST).unwrap());
+            assert_eq!(cipher.decrypt_line(&sealed, FIRST).unwrap(), line);
+        }
+    }
+
+    #[test]
+    fn test_wrong_key_fails_and_plaintext_passes_through() {
+        let sealed = SessionCipher::with_passphrase("one")
+            .encrypt_line("secret", FIRST)
+            .unwrap();
+        assert!(SessionCipher::with_passphrase("two")
+            .decrypt_line(&sealed, FIRST)
+            .is_err());
+        assert!(SessionCipher::with_key([1u8; KEY_LEN])
+            .decrypt_line(&sealed, FIRST)
+            .is_err());
+
+        let cipher = SessionCipher::with_key([1u8; KEY_LEN]);
+        assert_eq!(
+            cipher.decrypt_line("{\"plain\":true}", FIRST).unwrap(),
+            "{\"plain\":true}"
+        );
+    }
+
+    #[test]
+    fn test_moved_lines_fail_to_decrypt() {
+        let cipher = SessionCipher::with_key([3u8; KEY_LEN]);
+        let sealed = cipher.encrypt_line("secret", FIRST).unwrap();
+        let reordered = LinePosition { index: 2, ..FIRST };
+        let other_session = LinePosition {
+            file: "b.jsonl",
+            ..FIRST
+        };
+        assert!(cipher.decrypt_line(&sealed, reordered).is_err());
+        assert!(cipher.decrypt_line(&sealed, other_session).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+//! Append-only event log for session state.
+//!
+//! Instead of mutating a message vector in place, everything that happens in a session is
+//! recorded as a [`SessionEvent`]. The provider-format history is a projection derived by
+//! folding the events in order, which makes it possible to replay a session up to any point,
+//! branch from it, and audit tool activity after the fact.
+//!
+//! Every session writer saves through the storage module, which records what changed in the log
+//! next to the session file; the session file itself holds the projected history.
+
+use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::Conversation;
+use crate::permission::Permission;
+use crate::session::encryption::{self, LineDecryptor, LinePosition, SessionCipher};
+use anyhow::Result;
+use chrono::Utc;
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// File extension of the event log kept next to a session file
+const EVENT_LOG_EXTENSION: &str = "events";
+
+/// What happened in a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// A message from the user, including tool results sent back to the model
+    UserMessage { message: Message },
+    /// Output from the model; streamed deltas sharing a message id are merged into one event
+    ModelDelta { message: Message },
+    /// The model requested a tool call
+    ToolStarted {
+        request_id: String,
+        tool_name: Option<String>,
+    },
+    /// A tool call produced its result
+    ToolFinished { request_id: String, is_error: bool },
+    /// The user answered a tool permission request
+    Approval {
+        request_id: String,
+        permission: Permission,
+    },
+    /// The history was summarized, truncated or edited: its messages `start..end` were
+    /// replaced by `messages`
+    Compaction {
+        start: usize,
+        end: usize,
+        messages: Vec<Message>,
+    },
+}
+
+/// A single entry in the session event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// Position in the log, starting at 1
+    pub seq: u64,
+    /// Unix timestamp when the event was recorded
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+}
+
+/// Apply one event to the history projection
+fn apply(history: &mut Conversation, kind: &SessionEventKind) {
+    match kind {
+        SessionEventKind::UserMessage { message } | SessionEventKind::ModelDelta { message } => {
+            history.push(message.clone())
+        }
+        SessionEventKind::Compaction {
+            start,
+            end,
+            messages,
+        } => {
+            let mut replaced = history.messages().clone();
+            let end = (*end).min(replaced.len());
+            let start = (*start).min(end);
+            replaced.splice(start..end, messages.iter().cloned());
+            *history = Conversation::new_unvalidated(replaced)
+        }
+        SessionEventKind::ToolStarted { .. }
+        | SessionEventKind::ToolFinished { .. }
+        | SessionEventKind::Approval { .. } => {}
+    }
+}
+
+/// The path of the event log that belongs to a session file
+pub fn event_log_path(session_file: &Path) -> PathBuf {
+    session_file.with_extension(EVENT_LOG_EXTENSION)
+}
+
+/// An append-only log of session events with the derived message history
+#[derive(Debug, Clone)]
+pub struct SessionEventLog {
+    events: Vec<SessionEvent>,
+    history: Conversation,
+    /// Number of events already written to disk
+    persisted: usize,
+    /// Number of lines in the file it was loaded from, which encrypted lines are bound to
+    lines_on_disk: Option<usize>,
+}
+
+impl Default for SessionEventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionEventLog {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            history: Conversation::empty(),
+            persisted: 0,
+            lines_on_disk: None,
+        }
+    }
+
+    /// Start a log from an existing history, recording each message as an event
+    pub fn from_conversation(conversation: &Conversation) -> Self {
+        let mut log = Self::new();
+        for message in conversation.iter() {
+            log.record_message(message.clone());
+        }
+        log
+    }
+
+    /// Rebuild a log from previously recorded events
+    pub fn from_events(events: Vec<SessionEvent>) -> Self {
+        let mut history = Conversation::empty();
+        for event in &events {
+            apply(&mut history, &event.kind);
+        }
+        let persisted = events.len();
+        Self {
+            events,
+            history,
+            persisted,
+            lines_on_disk: None,
+        }
+    }
+
+    /// Append an event and update the history projection. Returns its sequence number.
+    pub fn append(&mut self, kind: SessionEventKind) -> u64 {
+        let seq = self.events.last().map_or(1, |e| e.seq + 1);
+        apply(&mut self.history, &kind);
+        self.events.push(SessionEvent {
+            seq,
+            timestamp: Utc::now().timestamp(),
+            kind,
+        });
+        seq
+    }
+
+    /// Record a history written by any session writer: messages added after the recorded history
+    /// are recorded as events, and a history that differs otherwise records only the changed part
+    pub fn record_history(&mut self, messages: &[Message]) {
+        let recorded = self.history.messages();
+        if messages.len() >= recorded.len() && messages[..recorded.len()] == recorded[..] {
+            let added = messages[recorded.len()..].to_vec();
+            for message in added {
+                self.record_message(message);
+            }
+        } else {
+            self.record_compaction(messages.to_vec());
+        }
+    }
+
+    /// Record a message, along with the tool activity it carries
+    pub fn record_message(&mut self, message: Message) {
+        let tool_events: Vec<SessionEventKind> = message
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::ToolRequest(req) => Some(SessionEventKind::ToolStarted {
+                    request_id: req.id.clone(),
+                    tool_name: req.tool_call.as_ref().ok().map(|call| call.name.clone()),
+                }),
+                MessageContent::ToolResponse(res) => Some(SessionEventKind::ToolFinished {
+                    request_id: res.id.clone(),
+                    is_error: res.tool_result.is_err(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        match message.role {
+            Role::Assistant => self.record_delta(message),
+            Role::User => {
+                self.append(SessionEventKind::UserMessage { message });
+            }
+        };
+        for kind in tool_events {
+            self.append(kind);
+        }
+    }
+
+    /// Merge a delta into the unsaved event of the message it continues, or start a new one
+    fn record_delta(&mut self, message: Message) {
+        let persisted = self.persisted;
+        let continued = self.events[persisted.min(self.events.len())..]
+            .last_mut()
+            .and_then(|event| match &mut event.kind {
+                SessionEventKind::ModelDelta { message: last }
+                    if last.id.is_some() && last.id == message.id =>
+                {
+                    Some(last)
+                }
+                _ => None,
+            });
+        match continued {
+            Some(last) => {
+                let mut merged = Conversation::new_unvalidated([last.clone()]);
+                merged.push(message.clone());
+                *last = merged.messages()[0].clone();
+                self.history.push(message);
+            }
+            None => {
+                self.append(SessionEventKind::ModelDelta { message });
+            }
+        }
+    }
+
+    /// Record the user's answer to a tool permission request
+    pub fn record_approval(&mut self, request_id: impl Into<String>, permission: Permission) {
+        self.append(SessionEventKind::Approval {
+            request_id: request_id.into(),
+            permission,
+        });
+    }
+
+    /// Record that the history was compacted into `messages`. The messages it shares with the
+    /// recorded history at either end are left out of the event.
+    pub fn record_compaction(&mut self, mut messages: Vec<Message>) {
+        let recorded = self.history.messages();
+        let start = recorded
+            .iter()
+            .zip(&messages)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let kept_at_end = recorded[start..]
+            .iter()
+            .rev()
+            .zip(messages[start..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let end = recorded.len() - kept_at_end;
+        if start == end && start + kept_at_end == messages.len() {
+            return;
+        }
+        messages.truncate(messages.len() - kept_at_end);
+        messages.drain(..start);
+        self.append(SessionEventKind::Compaction {
+            start,
+            end,
+            messages,
+        });
+    }
+
+    /// All recorded events, oldest first
+    pub fn events(&self) -> &[SessionEvent] {
+        &self.events
+    }
+
+    /// The message history to send to the provider
+    pub fn conversation(&self) -> &Conversation {
+        &self.history
+    }
+
+    /// The message history as it was right after event `seq`
+    pub fn replay(&self, seq: u64) -> Conversation {
+        let mut history = Conversation::empty();
+        for event in self.events.iter().take_while(|e| e.seq <= seq) {
+            apply(&mut history, &event.kind);
+        }
+        history
+    }
+
+    /// A new log containing the events up to and including `seq`, to continue from that point
+    pub fn branch(&self, seq: u64) -> SessionEventLog {
+        let events = self
+            .events
+            .iter()
+            .take_while(|e| e.seq <= seq)
+            .cloned()
+            .collect();
+        let mut branch = Self::from_events(events);
+        branch.persisted = 0;
+        branch
+    }
+
+    /// Tool calls that were started but have not produced a result
+    pub fn pending_tool_calls(&self) -> Vec<&str> {
+        let finished: HashSet<&str> = self
+            .events
+            .iter()
+            .filter_map(|e| match &e.kind {
+                SessionEventKind::ToolFinished { request_id, .. } => Some(request_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        self.events
+            .iter()
+            .filter_map(|e| match &e.kind {
+                SessionEventKind::ToolStarted { request_id, .. }
+                    if !finished.contains(request_id.as_str()) =>
+                {
+                    Some(request_id.as_str())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Read an event log written by [`SessionEventLog::save`]
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let reader = io::BufReader::new(fs::File::open(path)?);
+        let mut decryptor = LineDecryptor::for_file(path);
+        let mut events = Vec::new();
+        let mut lines = 0;
+        for line in reader.lines() {
+            lines += 1;
+            let line = decryptor.decrypt(line?)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<SessionEvent>(&line) {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    tracing::warn!("[SESSION] Skipping unreadable event: {}", e);
+                }
+            }
+        }
+        let mut log = Self::from_events(events);
+        log.lines_on_disk = Some(lines);
+        Ok(log)
+    }
+
+    /// Append the events recorded since the last save to the log file
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        if self.persisted == self.events.len() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let cipher = SessionCipher::for_writing()?;
+        let mut index = match self.lines_on_disk {
+            Some(lines) => lines,
+            None if path.exists() => io::BufReader::new(fs::File::open(path)?).lines().count(),
+            None => 0,
+        };
+        let file_id = encryption::file_id(path);
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let mut writer = io::BufWriter::new(file);
+        for event in &self.events[self.persisted..] {
+            let line = serde_json::to_string(event)?;
+            let line = match &cipher {
+                Some(cipher) => cipher.encrypt_line(
+                    &line,
+                    LinePosition {
+                        file: &file_id,
+                        index,
+                    },
+                )?,
+                None => line,
+            };
+            writeln!(writer, "{}", line)?;
+            index += 1;
+        }
+        writer.flush()?;
+        self.persisted = self.events.len();
+        self.lines_on_disk = Some(index);
+        Ok(())
+    }
+
+    /// Write the whole log to `path`, replacing what is there
+    pub fn save_as(&mut self, path: &Path) -> Result<()> {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        self.persisted = 0;
+        self.lines_on_disk = Some(0);
+        self.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::ToolCall;
+    use rmcp::model::Content;
+    use serde_json::json;
+
+    fn sample_log() -> SessionEventLog {
+        let mut log = SessionEventLog::new();
+        log.record_message(Message::user().with_text("list files"));
+        log.record_message(
+            Message::assistant()
+                .with_text("Sure")
+                .with_tool_request("call_1", Ok(ToolCall::new("shell", json!({"cmd": "ls"})))),
+        );
+        log.record_approval("call_1", Permission::AllowOnce);
+        log.record_message(
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("a.txt")])),
+        );
+        log
+    }
+
+    #[test]
+    fn test_history_projection() {
+        let log = sample_log();
+        let kinds: Vec<&str> = log
+            .events()
+            .iter()
+            .map(|e| match e.kind {
+                SessionEventKind::UserMessage { .. } => "user",
+                SessionEventKind::ModelDelta { .. } => "model",
+                SessionEventKind::ToolStarted { .. } => "started",
+                SessionEventKind::ToolFinished { .. } => "finished",
+                SessionEventKind::Approval { .. } => "approval",
+                SessionEventKind::Compaction { .. } => "compaction",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!["user", "model", "started", "approval", "user", "finished"]
+        );
+        assert_eq!(log.conversation().len(), 3);
+        assert!(log.pending_tool_calls().is_empty());
+    }
+
+    #[test]
+    fn test_replay_and_branch() {
+        let mut log = sample_log();
+        assert_eq!(log.replay(1).len(), 1);
+        assert_eq!(log.replay(3).len(), 2);
+
+        let branch = log.branch(3);
+        assert_eq!(branch.events().len(), 3);
+        assert_eq!(branch.pending_tool_calls(), vec!["call_1"]);
+
+        log.record_compaction(vec![Message::user().with_text("summary")]);
+        assert_eq!(log.conversation().len(), 1);
+        assert_eq!(log.replay(6).len(), 3);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = event_log_path(&dir.path().join("session.jsonl"));
+        assert_eq!(path.extension().unwrap(), "events");
+
+        let mut log = sample_log();
+        log.save(&path).unwrap();
+        log.record_message(Message::assistant().with_text("Found a.txt"));
+        log.save(&path).unwrap();
+
+        let loaded = SessionEventLog::load(&path).unwrap();
+        assert_eq!(loaded.events(), log.events());
+        assert_eq!(loaded.conversation().len(), 4);
+    }
+
+    #[test]
+    fn test_streamed_deltas_are_merged() {
+        let mut log = SessionEventLog::new();
+        log.record_message(Message::user().with_text("hi"));
+        for delta in ["Hel", "lo", "!"] {
+            log.record_message(Message::assistant().with_id("msg_1").with_text(delta));
+        }
+        assert_eq!(log.events().len(), 2);
+        let SessionEventKind::ModelDelta { message } = &log.events()[1].kind else {
+            panic!("expected a model event");
+        };
+        assert_eq!(message.as_concat_text(), "Hello!");
+        assert_eq!(log.conversation().len(), 2);
+        assert_eq!(log.conversation().messages()[1].as_concat_text(), "Hello!");
+    }
+
+    #[test]
+    fn test_record_history_only_compacts_when_history_differs() {
+        let mut log = sample_log();
+        let events = log.events().len();
+        let mut messages = log.conversation().messages().clone();
+        log.record_history(&messages);
+        assert_eq!(log.events().len(), events);
+
+        messages.push(Message::user().with_text("thanks"));
+        log.record_history(&messages);
+        assert!(matches!(
+            log.events().last().unwrap().kind,
+            SessionEventKind::UserMessage { .. }
+        ));
+
+        log.record_history(&[Message::user().with_text("summary")]);
+        assert!(matches!(
+            log.events().last().unwrap().kind,
+            SessionEventKind::Compaction { .. }
+        ));
+        assert_eq!(log.conversation().len(), 1);
+    }
+
+    #[test]
+    fn test_compaction_records_only_changed_messages() {
+        let mut log = sample_log();
+        log.record_message(Message::assistant().with_text("Found a.txt"));
+        let mut messages = log.conversation().messages().clone();
+        assert_eq!(messages.len(), 4);
+
+        // The first two messages are summarized, the last two are kept
+        messages.splice(0..2, [Message::user().with_text("summary")]);
+        log.record_history(&messages);
+        let SessionEventKind::Compaction {
+            start,
+            end,
+            messages: replacement,
+        } = &log.events().last().unwrap().kind
+        else {
+            panic!("expected a compaction");
+        };
+        assert_eq!((*start, *end), (0, 2));
+        assert_eq!(replacement.len(), 1);
+        assert_eq!(log.conversation().messages(), &messages);
+
+        let events = log.events().len();
+        log.record_compaction(messages);
+        assert_eq!(log.events().len(), events);
+    }
+}
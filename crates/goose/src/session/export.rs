@@ -1,7 +1,16 @@
-use goose::conversation::message::{Message, MessageContent, ToolRequest, ToolResponse};
-use goose::utils::safe_truncate;
+//! Render sessions as Markdown, HTML or structured JSON, for sharing or archiving conversations.
+
+use crate::conversation::message::{
+    Message, MessageContent, TokenLogprob, ToolRequest, ToolResponse,
+};
+use crate::session::report::escape_html;
+use crate::session::storage::SessionMetadata;
+use crate::utils::safe_truncate;
+use anyhow::Result;
 use rmcp::model::{RawContent, ResourceContents, Role};
+use serde::Serialize;
 use serde_json::Value;
+use std::fmt::Write;
 
 const MAX_STRING_LENGTH_MD_EXPORT: usize = 4096; // Generous limit for export
 const REDACTED_PREFIX_LENGTH: usize = 100; // Show first 100 chars before trimming
@@ -316,6 +325,7 @@ pub fn tool_response_to_markdown(resp: &ToolResponse, export_all_content: bool)
 
 pub fn message_to_markdown(message: &Message, export_all_content: bool) -> String {
     let mut md = String::new();
+    let mut sources: Vec<String> = Vec::new();
     for content in &message.content {
         match content {
             MessageContent::Text(text) => {
@@ -347,6 +357,16 @@ pub fn message_to_markdown(message: &Message, export_all_content: bool) -> Strin
                 md.push_str("**Thinking:**\n");
                 md.push_str("> *Thinking was redacted*\n\n");
             }
+            MessageContent::Citation(citation) => {
+                let source = match (&citation.title, &citation.url) {
+                    (Some(title), Some(url)) => format!("[{}]({})", title, url),
+                    (None, Some(url)) => format!("<{}>", url),
+                    _ => citation.label(),
+                };
+                if !sources.contains(&source) {
+                    sources.push(source);
+                }
+            }
             _ => {
                 md.push_str(
                     "`WARNING: Message content type could not be rendered to Markdown`\n\n",
@@ -354,13 +374,288 @@ pub fn message_to_markdown(message: &Message, export_all_content: bool) -> Strin
             }
         }
     }
+    // Citations become footnotes listing each source once
+    if !sources.is_empty() {
+        md.push_str("**Sources:**\n\n");
+        for (i, source) in sources.iter().enumerate() {
+            md.push_str(&format!("{}. {}\n", i + 1, source));
+        }
+    }
+    if let Some(summary) = logprobs_summary(&message.logprobs) {
+        md.push_str(&format!("\n*{}*\n", summary));
+    }
     md.trim_end_matches("\n").to_string()
 }
 
+/// One line on how confident the model was in a message's tokens, when logprobs were captured
+fn logprobs_summary(logprobs: &[TokenLogprob]) -> Option<String> {
+    let least_likely = logprobs
+        .iter()
+        .min_by(|a, b| a.logprob.total_cmp(&b.logprob))?;
+    let mean = logprobs.iter().map(|t| t.logprob).sum::<f64>() / logprobs.len() as f64;
+    Some(format!(
+        "Token logprobs: {} tokens, mean {:.3}, least likely {:?} ({:.1}%)",
+        logprobs.len(),
+        mean,
+        least_likely.token,
+        least_likely.probability() * 100.0
+    ))
+}
+
+/// Whether a message only carries tool results, which exports attach to the preceding tool calls
+fn is_only_tool_response(message: &Message) -> bool {
+    message.role == Role::User
+        && message
+            .content
+            .iter()
+            .all(|content| matches!(content, MessageContent::ToolResponse(_)))
+}
+
+/// Renders a whole session, with its messages, tool calls and tool results
+pub struct SessionExporter<'a> {
+    name: &'a str,
+    metadata: &'a SessionMetadata,
+    messages: &'a [Message],
+    export_all_content: bool,
+}
+
+#[derive(Serialize)]
+struct SessionExport<'a> {
+    name: &'a str,
+    metadata: &'a SessionMetadata,
+    messages: &'a [Message],
+}
+
+impl<'a> SessionExporter<'a> {
+    pub fn new(name: &'a str, metadata: &'a SessionMetadata, messages: &'a [Message]) -> Self {
+        Self {
+            name,
+            metadata,
+            messages,
+            export_all_content: false,
+        }
+    }
+
+    /// Keep long strings in tool arguments and results instead of trimming them
+    pub fn with_all_content(mut self, export_all_content: bool) -> Self {
+        self.export_all_content = export_all_content;
+        self
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown_output = String::new();
+        markdown_output.push_str(&format!("# Session Export: {}\n\n", self.name));
+
+        if self.messages.is_empty() {
+            markdown_output.push_str("*(This session has no messages)*\n");
+            return markdown_output;
+        }
+
+        markdown_output.push_str(&format!(
+            "*Total messages: {}*\n\n---\n\n",
+            self.messages.len()
+        ));
+
+        // Track if the last message had tool requests to properly handle tool responses
+        let mut skip_next_if_tool_response = false;
+
+        for message in self.messages {
+            let is_only_tool_response = is_only_tool_response(message);
+
+            // If the previous message had tool requests and this one is just tool responses,
+            // don't create a new User section - we'll attach the responses to the tool calls
+            if skip_next_if_tool_response && is_only_tool_response {
+                markdown_output.push_str(&message_to_markdown(message, self.export_all_content));
+                markdown_output.push_str("\n\n---\n\n");
+                skip_next_if_tool_response = false;
+                continue;
+            }
+            skip_next_if_tool_response = false;
+
+            if !is_only_tool_response {
+                let role_prefix = match message.role {
+                    Role::User => "### User:\n",
+                    Role::Assistant => "### Assistant:\n",
+                };
+                markdown_output.push_str(role_prefix);
+            }
+
+            markdown_output.push_str(&message_to_markdown(message, self.export_all_content));
+            markdown_output.push_str("\n\n---\n\n");
+
+            if message
+                .content
+                .iter()
+                .any(|content| matches!(content, MessageContent::ToolRequest(_)))
+            {
+                skip_next_if_tool_response = true;
+            }
+        }
+
+        markdown_output
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>\
+             <style>body{{font-family:sans-serif;margin:2em;max-width:60em}}\
+             section{{border-top:1px solid #ccc;padding:0.5em 0}}.text{{white-space:pre-wrap}}\
+             pre{{background:#f6f6f6;padding:0.5em;overflow-x:auto}}\
+             blockquote{{color:#666;white-space:pre-wrap}}.error{{color:#b00}}</style></head><body>\n",
+            escape_html(self.name)
+        );
+        let _ = writeln!(
+            out,
+            "<h1>Session Export: {}</h1><p>{} messages</p>",
+            escape_html(self.name),
+            self.messages.len()
+        );
+
+        for message in self.messages {
+            // Tool results read as part of the assistant's turn rather than as something the user said
+            let heading = match message.role {
+                _ if is_only_tool_response(message) => "Tool results",
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+            };
+            let _ = writeln!(out, "<section><h3>{}</h3>", heading);
+            let mut sources = Vec::new();
+            for content in &message.content {
+                match content {
+                    MessageContent::Text(text) => {
+                        let _ =
+                            writeln!(out, "<div class=\"text\">{}</div>", escape_html(&text.text));
+                    }
+                    MessageContent::ToolRequest(req) => match &req.tool_call {
+                        Ok(call) => {
+                            let arguments =
+                                serde_json::to_string_pretty(&call.arguments).unwrap_or_default();
+                            let _ = writeln!(
+                                out,
+                                "<details open><summary>Tool call: <code>{}</code></summary><pre>{}</pre></details>",
+                                escape_html(&call.name),
+                                escape_html(&arguments)
+                            );
+                        }
+                        Err(e) => {
+                            let _ = writeln!(
+                                out,
+                                "<p class=\"error\">Invalid tool call: {}</p>",
+                                escape_html(&e.to_string())
+                            );
+                        }
+                    },
+                    MessageContent::ToolResponse(resp) => match &resp.tool_result {
+                        Ok(contents) => {
+                            let text: Vec<&str> = contents
+                                .iter()
+                                .filter(|content| {
+                                    self.export_all_content
+                                        || content.audience().is_none_or(|audience| {
+                                            audience.contains(&Role::Assistant)
+                                        })
+                                })
+                                .filter_map(|content| content.as_text().map(|t| t.text.as_str()))
+                                .collect();
+                            let _ = writeln!(
+                                out,
+                                "<details><summary>Tool result</summary><pre>{}</pre></details>",
+                                escape_html(&text.join("\n"))
+                            );
+                        }
+                        Err(e) => {
+                            let _ = writeln!(
+                                out,
+                                "<p class=\"error\">Tool error: {}</p>",
+                                escape_html(&e.to_string())
+                            );
+                        }
+                    },
+                    MessageContent::Image(image) => {
+                        let _ = writeln!(
+                            out,
+                            "<img alt=\"image\" src=\"data:{};base64,{}\">",
+                            escape_html(&image.mime_type),
+                            escape_html(&image.data)
+                        );
+                    }
+                    MessageContent::Thinking(thinking) => {
+                        let _ = writeln!(
+                            out,
+                            "<blockquote>{}</blockquote>",
+                            escape_html(&thinking.thinking)
+                        );
+                    }
+                    MessageContent::RedactedThinking(_) => {
+                        let _ = writeln!(
+                            out,
+                            "<blockquote><em>Thinking was redacted</em></blockquote>"
+                        );
+                    }
+                    MessageContent::Citation(citation) => {
+                        let source = match &citation.url {
+                            Some(url) => format!(
+                                "<a href=\"{}\">{}</a>",
+                                escape_html(url),
+                                escape_html(citation.title.as_deref().unwrap_or(url))
+                            ),
+                            None => escape_html(&citation.label()),
+                        };
+                        if !sources.contains(&source) {
+                            sources.push(source);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(summary) = logprobs_summary(&message.logprobs) {
+                // Each token with its probability on hover, for a closer look at the summary
+                let _ = write!(
+                    out,
+                    "<details><summary>{}</summary><p class=\"text\">",
+                    escape_html(&summary)
+                );
+                for token in &message.logprobs {
+                    let _ = write!(
+                        out,
+                        "<span title=\"{:.1}%\" style=\"opacity:{:.2}\">{}</span>",
+                        token.probability() * 100.0,
+                        0.3 + 0.7 * token.probability(),
+                        escape_html(&token.token)
+                    );
+                }
+                let _ = writeln!(out, "</p></details>");
+            }
+            if !sources.is_empty() {
+                let _ = write!(out, "<p>Sources:</p><ol>");
+                for source in &sources {
+                    let _ = write!(out, "<li>{}</li>", source);
+                }
+                let _ = writeln!(out, "</ol>");
+            }
+            let _ = writeln!(out, "</section>");
+        }
+
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    /// The session metadata and messages in the same form goose stores them
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&SessionExport {
+            name: self.name,
+            metadata: self.metadata,
+            messages: self.messages,
+        })?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use goose::conversation::message::{Message, ToolRequest, ToolResponse};
+    use crate::conversation::message::{Citation, Message, ToolRequest, ToolResponse};
     use mcp_core::tool::ToolCall;
     use rmcp::model::{Content, RawTextContent, TextContent};
     use serde_json::json;
@@ -598,6 +893,29 @@ mod tests {
         assert!(result.contains("> *Thinking was redacted*"));
     }
 
+    #[test]
+    fn test_message_to_markdown_citations_become_sources() {
+        let rust = Citation {
+            title: Some("Rust".to_string()),
+            url: Some("https://www.rust-lang.org".to_string()),
+            ..Default::default()
+        };
+        let message = Message::assistant()
+            .with_text("Rust is memory safe.")
+            .with_citation(rust.clone())
+            .with_citation(rust)
+            .with_citation(Citation {
+                url: Some("https://doc.rust-lang.org".to_string()),
+                ..Default::default()
+            });
+
+        let result = message_to_markdown(&message, true);
+        assert_eq!(
+            result,
+            "Rust is memory safe.\n\n**Sources:**\n\n1. [Rust](https://www.rust-lang.org)\n2. <https://doc.rust-lang.org>"
+        );
+    }
+
     #[test]
     fn test_recursive_value_to_markdown() {
         // Test that complex nested structures are properly handled with recursion
@@ -1122,4 +1440,78 @@ found 0 vulnerabilities"#;
         assert!(response_result.contains("added 57 packages"));
         assert!(response_result.contains("found 0 vulnerabilities"));
     }
+
+    fn shell_session() -> Vec<Message> {
+        vec![
+            Message::user().with_text("list <files>"),
+            Message::assistant().with_tool_request(
+                "1",
+                Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+            ),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("Cargo.toml")])),
+            Message::assistant().with_text("There is one file"),
+        ]
+    }
+
+    #[test]
+    fn test_exporter_markdown_attaches_tool_results_to_calls() {
+        let messages = shell_session();
+        let metadata = SessionMetadata::default();
+        let markdown = SessionExporter::new("demo", &metadata, &messages).to_markdown();
+
+        assert!(markdown.starts_with("# Session Export: demo\n"));
+        assert!(markdown.contains("*Total messages: 4*"));
+        assert_eq!(markdown.matches("### User:").count(), 1);
+        assert_eq!(markdown.matches("### Assistant:").count(), 2);
+        assert!(markdown.contains("Cargo.toml"));
+    }
+
+    #[test]
+    fn test_exporter_html_escapes_content() {
+        let messages = shell_session();
+        let metadata = SessionMetadata::default();
+        let html = SessionExporter::new("demo", &metadata, &messages).to_html();
+
+        assert!(html.contains("list &lt;files&gt;"));
+        assert!(!html.contains("<files>"));
+        assert!(html.contains("Tool call: <code>developer__shell</code>"));
+        assert!(html.contains("<h3>Tool results</h3>"));
+        assert!(html.ends_with("</body></html>\n"));
+    }
+
+    #[test]
+    fn test_exporter_json_keeps_messages_and_metadata() {
+        let messages = shell_session();
+        let metadata = SessionMetadata::default();
+        let json = SessionExporter::new("demo", &metadata, &messages)
+            .to_json()
+            .unwrap();
+
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["name"], "demo");
+        assert_eq!(value["messages"].as_array().unwrap().len(), 4);
+        let restored: Vec<Message> = serde_json::from_value(value["messages"].clone()).unwrap();
+        assert_eq!(restored[1].content, messages[1].content);
+        assert!(value["metadata"]["working_dir"].is_string());
+    }
+
+    #[test]
+    fn test_exports_summarize_logprobs() {
+        let messages =
+            vec![Message::assistant()
+                .with_text("Yes")
+                .with_logprobs(vec![TokenLogprob {
+                    token: "Yes".to_string(),
+                    logprob: -0.5,
+                    top_logprobs: vec![],
+                }])];
+        let metadata = SessionMetadata::default();
+        let exporter = SessionExporter::new("demo", &metadata, &messages);
+
+        assert!(exporter
+            .to_markdown()
+            .contains("*Token logprobs: 1 tokens, mean -0.500, least likely \"Yes\" (60.7%)*"));
+        assert!(exporter.to_html().contains("title=\"60.7%\""));
+        assert!(exporter.to_json().unwrap().contains("\"logprob\": -0.5"));
+    }
 }
@@ -32,9 +32,7 @@ pub fn get_valid_sorted_sessions(sort_order: SortOrder) -> Result<Vec<SessionInf
 
     for (id, path) in sessions {
         // Get file modification time with fallback
-        let modified = path
-            .metadata()
-            .and_then(|m| m.modified())
+        let modified = session::get_modified_time(&path)
             .map(|time| {
                 chrono::DateTime::<chrono::Utc>::from(time)
                     .format("%Y-%m-%d %H:%M:%S UTC")
@@ -1,12 +1,37 @@
+pub mod advisor;
+pub mod bundle;
+pub mod database;
+pub mod diff;
+pub mod encryption;
+pub mod events;
+pub mod export;
 pub mod info;
+#[cfg(feature = "postgres-sessions")]
+pub mod postgres;
+pub mod replay;
+pub mod report;
+pub mod retention;
+#[cfg(feature = "s3-sessions")]
+pub mod s3;
+pub mod search;
 pub mod storage;
+pub mod store;
+pub mod title;
+pub mod usage;
 
 // Re-export common session types and functions
 pub use storage::{
-    ensure_session_dir, generate_description, generate_description_with_schedule_id,
-    generate_session_id, get_most_recent_session, get_path, list_sessions, persist_messages,
-    persist_messages_with_schedule_id, read_messages, read_metadata, update_metadata, Identifier,
-    SessionMetadata,
+    delete_session, ensure_session_dir, fork_session, generate_description,
+    generate_description_with_schedule_id, generate_session_id, get_modified_time,
+    get_most_recent_session, get_path, list_sessions, persist_messages,
+    persist_messages_with_schedule_id, read_messages, read_metadata, session_exists,
+    update_metadata, Identifier, ModelUsage, SessionMetadata,
 };
 
+pub use bundle::{ImportReport, SessionBundle};
+pub use diff::{ContextSnapshot, SessionDiff};
+pub use events::{event_log_path, SessionEvent, SessionEventKind, SessionEventLog};
+pub use export::{message_to_markdown, SessionExporter};
 pub use info::{get_valid_sorted_sessions, SessionInfo};
+pub use search::{SearchHit, SearchIndex};
+pub use usage::{SessionCost, UsageEntry, UsageLedger, UsageTotals};
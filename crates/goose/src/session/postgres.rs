@@ -0,0 +1,265 @@
+//! Postgres storage for sessions, shared by every goose that connects to the same database.
+//!
+//! Enabled with `GOOSE_SESSION_STORAGE=postgres` and a connection string in
+//! `GOOSE_SESSION_POSTGRES_URL`. Each session is a single row, so a save replaces the whole
+//! session atomically. Schema migrations run under an advisory lock so replicas starting at the
+//! same time do not race each other.
+//!
+//! Connections are pooled and negotiate TLS against the system's root certificates, as the
+//! connection string's `sslmode` asks (`prefer` when it says nothing). Built with the
+//! `postgres-sessions` feature.
+
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::session::storage::SessionMetadata;
+use crate::session::store::{block_on, session_name, session_path, SessionStore, StoredSession};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tokio_postgres::Client;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// Config key holding the Postgres connection string
+pub const POSTGRES_URL_KEY: &str = "GOOSE_SESSION_POSTGRES_URL";
+
+/// Most connections one goose process keeps open to the session database
+const MAX_CONNECTIONS: usize = 4;
+
+/// Arbitrary key for the advisory lock held while migrating
+const MIGRATION_LOCK_ID: i64 = 0x676f_6f73_65;
+
+/// Initial schema: one row per session, keyed by session name
+const CREATE_SESSIONS: &str = "
+    CREATE TABLE IF NOT EXISTS goose_sessions (
+        name TEXT PRIMARY KEY,
+        metadata TEXT NOT NULL,
+        messages TEXT NOT NULL,
+        updated_at BIGINT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS goose_sessions_updated_at ON goose_sessions(updated_at);
+";
+
+/// Schema migrations, applied in order and recorded in `goose_schema_migrations`
+const MIGRATIONS: &[&str] = &[CREATE_SESSIONS];
+
+pub struct PostgresStore {
+    pool: Pool,
+    migrated: OnceCell<()>,
+}
+
+impl PostgresStore {
+    pub fn new(url: &str) -> Result<Self> {
+        let config: tokio_postgres::Config = url
+            .parse()
+            .with_context(|| format!("{} is not a valid connection string", POSTGRES_URL_KEY))?;
+        let manager = Manager::from_config(
+            config,
+            tls_connector()?,
+            ManagerConfig {
+                recycling_method: RecyclingMethod::Fast,
+            },
+        );
+        let pool = Pool::builder(manager)
+            .max_size(MAX_CONNECTIONS)
+            .build()
+            .context("Failed to set up the session database pool")?;
+        Ok(Self {
+            pool,
+            migrated: OnceCell::new(),
+        })
+    }
+
+    pub fn from_config() -> Result<Self> {
+        let url: String = Config::global()
+            .get_secret(POSTGRES_URL_KEY)
+            .with_context(|| {
+                format!(
+                    "{} must be set to store sessions in Postgres",
+                    POSTGRES_URL_KEY
+                )
+            })?;
+        Self::new(&url)
+    }
+
+    /// A pooled connection, bringing the schema up to date on first use
+    async fn connect(&self) -> Result<Object> {
+        let mut client = self
+            .pool
+            .get()
+            .await
+            .context("Failed to connect to the session database")?;
+        self.migrated
+            .get_or_try_init(|| migrate(&mut client))
+            .await?;
+        Ok(client)
+    }
+}
+
+/// TLS with the system's root certificates
+fn tls_connector() -> Result<MakeRustlsConnect> {
+    let mut roots = rustls::RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs();
+    for error in &native.errors {
+        tracing::warn!("Skipping a system root certificate: {}", error);
+    }
+    roots.add_parsable_certificates(native.certs);
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(MakeRustlsConnect::new(config))
+}
+
+async fn migrate(client: &mut Client) -> Result<()> {
+    let tx = client.transaction().await?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_ID])
+        .await?;
+    tx.batch_execute(
+        "CREATE TABLE IF NOT EXISTS goose_schema_migrations (version INTEGER PRIMARY KEY)",
+    )
+    .await?;
+    let row = tx
+        .query_one(
+            "SELECT COALESCE(MAX(version), 0) FROM goose_schema_migrations",
+            &[],
+        )
+        .await?;
+    let version = row.get::<_, i32>(0).max(0) as usize;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.batch_execute(migration)
+            .await
+            .with_context(|| format!("Failed to apply session database migration {}", index + 1))?;
+        tx.execute(
+            "INSERT INTO goose_schema_migrations (version) VALUES ($1)",
+            &[&((index + 1) as i32)],
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+impl SessionStore for PostgresStore {
+    fn save_session(
+        &self,
+        path: &Path,
+        metadata: &SessionMetadata,
+        messages: &Conversation,
+    ) -> Result<()> {
+        let name = session_name(path);
+        let metadata = serde_json::to_string(metadata)?;
+        let messages = serde_json::to_string(messages.messages())?;
+        block_on(async {
+            let client = self.connect().await?;
+            client
+                .execute(
+                    "INSERT INTO goose_sessions (name, metadata, messages, updated_at)
+                     VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (name) DO UPDATE SET
+                        metadata = EXCLUDED.metadata,
+                        messages = EXCLUDED.messages,
+                        updated_at = EXCLUDED.updated_at",
+                    &[&name, &metadata, &messages, &Utc::now().timestamp_millis()],
+                )
+                .await?;
+            anyhow::Ok(())
+        })?
+    }
+
+    fn load_messages(&self, path: &Path) -> Result<Option<Conversation>> {
+        let name = session_name(path);
+        let messages: Option<String> = block_on(async {
+            let client = self.connect().await?;
+            let row = client
+                .query_opt(
+                    "SELECT messages FROM goose_sessions WHERE name = $1",
+                    &[&name],
+                )
+                .await?;
+            anyhow::Ok(row.map(|row| row.get(0)))
+        })??;
+
+        messages
+            .map(|json| {
+                let messages: Vec<Message> =
+                    serde_json::from_str(&json).context("Failed to parse stored messages")?;
+                Ok(Conversation::new_unvalidated(messages))
+            })
+            .transpose()
+    }
+
+    fn load_metadata(&self, path: &Path) -> Result<Option<SessionMetadata>> {
+        let name = session_name(path);
+        let metadata: Option<String> = block_on(async {
+            let client = self.connect().await?;
+            let row = client
+                .query_opt(
+                    "SELECT metadata FROM goose_sessions WHERE name = $1",
+                    &[&name],
+                )
+                .await?;
+            anyhow::Ok(row.map(|row| row.get(0)))
+        })??;
+
+        metadata
+            .map(|json| serde_json::from_str(&json).context("Failed to parse session metadata"))
+            .transpose()
+    }
+
+    fn updated_at(&self, path: &Path) -> Result<Option<i64>> {
+        let name = session_name(path);
+        block_on(async {
+            let client = self.connect().await?;
+            let row = client
+                .query_opt(
+                    "SELECT updated_at FROM goose_sessions WHERE name = $1",
+                    &[&name],
+                )
+                .await?;
+            anyhow::Ok(row.map(|row| row.get(0)))
+        })?
+    }
+
+    fn list_sessions(&self) -> Result<Vec<StoredSession>> {
+        let rows = block_on(async {
+            let client = self.connect().await?;
+            let rows = client
+                .query(
+                    "SELECT name, updated_at FROM goose_sessions ORDER BY updated_at DESC",
+                    &[],
+                )
+                .await?;
+            anyhow::Ok(rows)
+        })??;
+
+        rows.into_iter()
+            .map(|row| {
+                let name: String = row.get(0);
+                Ok(StoredSession {
+                    path: session_path(&name)?,
+                    name,
+                    updated_at: row.get(1),
+                })
+            })
+            .collect()
+    }
+
+    fn delete_session(&self, path: &Path) -> Result<()> {
+        let name = session_name(path);
+        block_on(async {
+            let client = self.connect().await?;
+            client
+                .execute("DELETE FROM goose_sessions WHERE name = $1", &[&name])
+                .await?;
+            anyhow::Ok(())
+        })?
+    }
+}
@@ -0,0 +1,398 @@
+//! Re-driving a stored session.
+//!
+//! A replay sends the user's messages from a recorded session to an agent again, one turn at a
+//! time. Recorded replays answer with what was recorded: [`ReplayProvider`] returns the model's
+//! responses in order and [`RecordedTools`] the results its tool calls got, so the agent's own
+//! logic runs again without calling a model or touching anything. Live replays send the same
+//! inputs to a real model with real tools, and [`compare`] lines the two runs up turn by turn.
+//!
+//! [`ReplayProvider`]: crate::providers::replay::ReplayProvider
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use mcp_client::client::{Error, McpClientTrait};
+use mcp_core::ToolResult;
+use rmcp::model::{
+    CallToolResult, Content, ErrorCode, ErrorData, GetPromptResult, InitializeResult,
+    ListPromptsResult, ListResourcesResult, ListToolsResult, ReadResourceResult, Role,
+    ServerNotification, Tool, ToolAnnotations,
+};
+use rmcp::object;
+use serde::Serialize;
+use serde_json::Value;
+use similar::TextDiff;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::Conversation;
+
+/// Separates an extension's name from its tools' names
+const TOOL_PREFIX_SEPARATOR: &str = "__";
+/// Tools the agent answers itself, so there is no extension to stand in for
+const AGENT_TOOL_PREFIXES: &[&str] = &["platform"];
+
+/// The messages the user sent in a session, in order, leaving out tool results
+pub fn user_inputs(conversation: &Conversation) -> Vec<Message> {
+    conversation
+        .messages()
+        .iter()
+        .filter(|message| message.role == Role::User && !message.is_tool_response())
+        .filter(|message| !message.content.is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Stands in for one extension of a recorded session, answering each of its tool calls with the
+/// result the same call got when it was recorded
+pub struct RecordedTools {
+    tools: Vec<Tool>,
+    results: Mutex<HashMap<String, VecDeque<ToolResult<Vec<Content>>>>>,
+}
+
+impl RecordedTools {
+    /// One stand-in per extension whose tools the session called, by extension name
+    pub fn from_conversation(conversation: &Conversation) -> Vec<(String, Self)> {
+        let mut requests = HashMap::new();
+        for message in conversation.messages() {
+            for content in &message.content {
+                if let MessageContent::ToolRequest(request) = content {
+                    if let Ok(call) = &request.tool_call {
+                        requests.insert(request.id.clone(), call.clone());
+                    }
+                }
+            }
+        }
+
+        let mut extensions: HashMap<String, HashMap<String, VecDeque<_>>> = HashMap::new();
+        for message in conversation.messages() {
+            for content in &message.content {
+                let MessageContent::ToolResponse(response) = content else {
+                    continue;
+                };
+                let Some(call) = requests.get(&response.id) else {
+                    continue;
+                };
+                let Some((extension, tool)) = call.name.split_once(TOOL_PREFIX_SEPARATOR) else {
+                    continue;
+                };
+                if AGENT_TOOL_PREFIXES.contains(&extension) {
+                    continue;
+                }
+                extensions
+                    .entry(extension.to_string())
+                    .or_default()
+                    .entry(call_key(tool, &call.arguments))
+                    .or_default()
+                    .push_back(response.tool_result.clone());
+            }
+        }
+
+        let mut tool_names: HashMap<String, Vec<String>> = HashMap::new();
+        for call in requests.values() {
+            if let Some((extension, tool)) = call.name.split_once(TOOL_PREFIX_SEPARATOR) {
+                let names = tool_names.entry(extension.to_string()).or_default();
+                if !names.iter().any(|name| name == tool) {
+                    names.push(tool.to_string());
+                }
+            }
+        }
+
+        extensions
+            .into_iter()
+            .map(|(extension, results)| {
+                let tools = tool_names
+                    .remove(&extension)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|name| {
+                        // Nothing runs, so the approval a real call would need isn't asked for
+                        Tool::new(
+                            name,
+                            "Answers with the result recorded in the session being replayed",
+                            object!({"type": "object"}),
+                        )
+                        .annotate(ToolAnnotations {
+                            title: None,
+                            read_only_hint: Some(true),
+                            destructive_hint: Some(false),
+                            idempotent_hint: Some(true),
+                            open_world_hint: Some(false),
+                        })
+                    })
+                    .collect();
+                let client = Self {
+                    tools,
+                    results: Mutex::new(results),
+                };
+                (extension, client)
+            })
+            .collect()
+    }
+}
+
+/// Calls match their recording by tool and arguments, so one answered in a different order
+/// still gets its own result
+fn call_key(name: &str, arguments: &Value) -> String {
+    format!("{}{}", name, arguments)
+}
+
+fn replay_error(message: String) -> Error {
+    Error::McpError(ErrorData {
+        code: ErrorCode::INTERNAL_ERROR,
+        message: message.into(),
+        data: None,
+    })
+}
+
+#[async_trait::async_trait]
+impl McpClientTrait for RecordedTools {
+    async fn list_resources(
+        &self,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<ListResourcesResult, Error> {
+        Ok(ListResourcesResult {
+            resources: vec![],
+            next_cursor: None,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        uri: &str,
+        _cancel_token: CancellationToken,
+    ) -> Result<ReadResourceResult, Error> {
+        Err(replay_error(format!("{} was not recorded", uri)))
+    }
+
+    async fn list_tools(
+        &self,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            tools: self.tools.clone(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        _cancel_token: CancellationToken,
+    ) -> Result<CallToolResult, Error> {
+        let recorded = self.results.lock().ok().and_then(|mut results| {
+            results
+                .get_mut(&call_key(name, &arguments))
+                .and_then(|queue| queue.pop_front())
+        });
+        match recorded {
+            Some(Ok(content)) => Ok(CallToolResult {
+                content: Some(content),
+                is_error: None,
+                structured_content: None,
+            }),
+            Some(Err(e)) => Err(replay_error(e.to_string())),
+            None => Err(replay_error(format!(
+                "No recorded result for {} with these arguments",
+                name
+            ))),
+        }
+    }
+
+    async fn list_prompts(
+        &self,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<ListPromptsResult, Error> {
+        Ok(ListPromptsResult {
+            prompts: vec![],
+            next_cursor: None,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        name: &str,
+        _arguments: Value,
+        _cancel_token: CancellationToken,
+    ) -> Result<GetPromptResult, Error> {
+        Err(replay_error(format!("Prompt {} was not recorded", name)))
+    }
+
+    async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+        mpsc::channel(1).1
+    }
+
+    fn get_info(&self) -> Option<&InitializeResult> {
+        None
+    }
+}
+
+/// What the agent did in answer to one user message
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Turn {
+    pub input: String,
+    /// Tools called, in order
+    pub tools: Vec<String>,
+    /// The last text the assistant sent
+    pub reply: String,
+}
+
+/// The turns of a conversation, one per user message
+pub fn turns(conversation: &Conversation) -> Vec<Turn> {
+    let mut turns: Vec<Turn> = Vec::new();
+    for message in conversation.messages() {
+        if message.role == Role::User && !message.is_tool_response() {
+            turns.push(Turn {
+                input: message.as_concat_text(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(turn) = turns.last_mut() else {
+            continue;
+        };
+        for content in &message.content {
+            match content {
+                MessageContent::ToolRequest(request) => {
+                    if let Ok(call) = &request.tool_call {
+                        turn.tools.push(call.name.clone());
+                    }
+                }
+                MessageContent::Text(text) if message.role == Role::Assistant => {
+                    if !text.text.trim().is_empty() {
+                        turn.reply = text.text.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    turns
+}
+
+/// A recorded turn next to the same turn replayed
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnComparison {
+    pub recorded: Turn,
+    /// `None` when the replay stopped before this turn
+    pub replayed: Option<Turn>,
+    /// How alike the two replies are, from 0 to 1
+    pub reply_similarity: f32,
+}
+
+impl TurnComparison {
+    pub fn same_tools(&self) -> bool {
+        self.replayed
+            .as_ref()
+            .is_some_and(|replayed| replayed.tools == self.recorded.tools)
+    }
+}
+
+/// Line up a recorded session and its replay turn by turn
+pub fn compare(recorded: &Conversation, replayed: &Conversation) -> Vec<TurnComparison> {
+    let mut replayed = turns(replayed).into_iter();
+    turns(recorded)
+        .into_iter()
+        .map(|recorded| {
+            let replayed = replayed.next();
+            let reply_similarity = replayed.as_ref().map_or(0.0, |replayed| {
+                TextDiff::from_words(recorded.reply.as_str(), replayed.reply.as_str()).ratio()
+            });
+            TurnComparison {
+                recorded,
+                replayed,
+                reply_similarity,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{ToolCall, ToolError};
+    use serde_json::json;
+
+    fn recorded_session() -> Conversation {
+        let call = ToolCall::new("developer__shell", json!({"command": "ls"}));
+        Conversation::new_unvalidated(vec![
+            Message::user().with_text("what is here?"),
+            Message::assistant().with_tool_request("1", Ok(call.clone())),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("README.md")])),
+            Message::assistant().with_text("A README."),
+            Message::user().with_text("and now?"),
+            Message::assistant().with_tool_request("2", Ok(call)),
+            Message::user()
+                .with_tool_response("2", Err(ToolError::ExecutionError("gone".to_string()))),
+            Message::assistant().with_text("Nothing."),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_recorded_tools_answer_in_order() {
+        let session = recorded_session();
+        assert_eq!(user_inputs(&session).len(), 2);
+
+        let mut clients = RecordedTools::from_conversation(&session);
+        assert_eq!(clients.len(), 1);
+        let (extension, client) = clients.pop().unwrap();
+        assert_eq!(extension, "developer");
+        let tools = client
+            .list_tools(None, CancellationToken::default())
+            .await
+            .unwrap();
+        assert_eq!(tools.tools[0].name, "shell");
+
+        let first = client
+            .call_tool(
+                "shell",
+                json!({"command": "ls"}),
+                CancellationToken::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            first.content.unwrap()[0].as_text().unwrap().text,
+            "README.md"
+        );
+        // The second call got an error when it was recorded
+        assert!(client
+            .call_tool(
+                "shell",
+                json!({"command": "ls"}),
+                CancellationToken::default()
+            )
+            .await
+            .is_err());
+        // A call that was never made has nothing to answer with
+        assert!(client
+            .call_tool(
+                "shell",
+                json!({"command": "pwd"}),
+                CancellationToken::default()
+            )
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_compare_lines_up_turns() {
+        let recorded = recorded_session();
+        let replayed = Conversation::new_unvalidated(vec![
+            Message::user().with_text("what is here?"),
+            Message::assistant().with_text("A README."),
+        ]);
+
+        let comparison = compare(&recorded, &replayed);
+        assert_eq!(comparison.len(), 2);
+        assert_eq!(comparison[0].recorded.tools, vec!["developer__shell"]);
+        assert!(!comparison[0].same_tools());
+        assert_eq!(comparison[0].reply_similarity, 1.0);
+        assert!(comparison[1].replayed.is_none());
+    }
+}
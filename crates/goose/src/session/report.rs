@@ -0,0 +1,902 @@
+//! Usage reports across sessions, for people evaluating how goose is used by a team.
+//!
+//! A report reads every session changed within a period and summarizes what was attempted,
+//! how it ended, what it cost and which tools did the work.
+
+use crate::agents::types::{MAX_RETRIES_PREFIX, MAX_TURNS_MESSAGE, PROVIDER_ERROR_PREFIX};
+use crate::config::Config;
+use crate::conversation::message::{Message, MessageContent};
+use crate::conversation::Conversation;
+use crate::providers::pricing::{get_model_pricing, parse_model_id};
+use crate::providers::prompt_cache::cache_savings;
+use crate::session::storage::{self, ModelUsage, SessionMetadata};
+use crate::tool_monitor::{EDIT_OSCILLATION_REJECTED_MESSAGE, REPETITION_REJECTED_MESSAGE};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use rmcp::model::Role;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+
+/// Config key for the minutes of manual work one successful tool call is assumed to save
+pub const MINUTES_PER_TOOL_CALL_KEY: &str = "GOOSE_REPORT_MINUTES_PER_TOOL_CALL";
+const DEFAULT_MINUTES_PER_TOOL_CALL: f64 = 2.0;
+
+/// Number of tools and failure clusters listed in a report
+const TOP_N: usize = 10;
+/// Longest error message kept as the example of a cluster
+const EXAMPLE_CHARS: usize = 200;
+/// Share of words two error signatures need in common to be counted as the same problem
+const CLUSTER_SIMILARITY: f64 = 0.7;
+
+/// What the CLI records when the user interrupts a running tool or the tool loop
+const INTERRUPTED_TOOL_ERROR: &str = "Interrupted by the user";
+const INTERRUPTED_SUFFIX: &str = "was interrupted. How would you like to proceed?";
+
+/// Parse a period like `30d`, `12h` or `2w`
+pub fn parse_period(period: &str) -> Result<Duration> {
+    let period = period.trim();
+    let split = period.char_indices().last().map_or(0, |(i, _)| i);
+    let (amount, unit) = period.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow!("Invalid period '{}': use e.g. 30d, 12h or 2w", period))?;
+    match unit {
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(anyhow!(
+            "Invalid period '{}': use e.g. 30d, 12h or 2w",
+            period
+        )),
+    }
+}
+
+/// Why a session ended without the task done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureKind {
+    /// The model provider returned an error the agent couldn't recover from
+    ProviderError,
+    /// The session stopped on a failed tool call
+    ToolError,
+    /// The user interrupted the agent or left before it answered
+    UserAbort,
+    /// The agent kept repeating the same tool call until it was rejected
+    LoopDetected,
+    /// The session ran out of turns or context
+    BudgetExceeded,
+    /// A recipe's success checks still failed after every retry
+    AssertionFailed,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FailureKind::ProviderError => "provider error",
+            FailureKind::ToolError => "tool error",
+            FailureKind::UserAbort => "user abort",
+            FailureKind::LoopDetected => "loop detected",
+            FailureKind::BudgetExceeded => "budget exceeded",
+            FailureKind::AssertionFailed => "assertion failed",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Succeeded,
+    Failed(FailureKind),
+}
+
+/// The kind of failure a tool error stands for
+fn tool_failure_kind(error: &str) -> FailureKind {
+    if error.contains(REPETITION_REJECTED_MESSAGE)
+        || error.contains(EDIT_OSCILLATION_REJECTED_MESSAGE)
+    {
+        FailureKind::LoopDetected
+    } else if error.contains(INTERRUPTED_TOOL_ERROR) {
+        FailureKind::UserAbort
+    } else {
+        FailureKind::ToolError
+    }
+}
+
+/// What one session did
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub description: String,
+    pub outcome: Outcome,
+    pub tool_calls: BTreeMap<String, usize>,
+    /// Failed tool calls as (tool name, error message)
+    pub tool_errors: Vec<(String, String)>,
+    /// Provider errors the agent reported back to the user
+    pub provider_errors: Vec<String>,
+    pub model_usage: BTreeMap<String, ModelUsage>,
+}
+
+impl SessionSummary {
+    pub fn new(id: &str, metadata: &SessionMetadata, messages: &Conversation) -> Self {
+        let mut tool_names: HashMap<&str, String> = HashMap::new();
+        let mut tool_calls: BTreeMap<String, usize> = BTreeMap::new();
+        let mut tool_errors = Vec::new();
+        let mut provider_errors = Vec::new();
+
+        for message in messages.iter() {
+            if message.role == Role::Assistant {
+                if let Some(error) = message.as_concat_text().strip_prefix(PROVIDER_ERROR_PREFIX) {
+                    let error = error.split("\n\n").next().unwrap_or(error);
+                    provider_errors.push(error.trim_end_matches('.').to_string());
+                }
+            }
+            for content in &message.content {
+                match content {
+                    MessageContent::ToolRequest(request) => {
+                        let name = match &request.tool_call {
+                            Ok(call) => call.name.clone(),
+                            Err(_) => "invalid tool call".to_string(),
+                        };
+                        *tool_calls.entry(name.clone()).or_default() += 1;
+                        tool_names.insert(&request.id, name);
+                    }
+                    MessageContent::ToolResponse(response) => {
+                        if let Err(e) = &response.tool_result {
+                            let name = tool_names
+                                .get(response.id.as_str())
+                                .cloned()
+                                .unwrap_or_else(|| "unknown".to_string());
+                            tool_errors.push((name, e.to_string()));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Self {
+            id: id.to_string(),
+            description: metadata.description.clone(),
+            outcome: outcome(messages.messages()),
+            tool_calls,
+            tool_errors,
+            provider_errors,
+            model_usage: metadata.model_usage.clone(),
+        }
+    }
+
+    pub fn tool_call_count(&self) -> usize {
+        self.tool_calls.values().sum()
+    }
+}
+
+/// A session succeeded when the model had the last word with an answer of its own. Anything
+/// else is classified by how it ended: on the user's message or a failed tool call, on one of
+/// the messages the agent writes when it gives up, or on the context running out.
+fn outcome(messages: &[Message]) -> Outcome {
+    let Some(last) = messages.last() else {
+        return Outcome::Failed(FailureKind::UserAbort);
+    };
+    let ran_out = last
+        .content
+        .iter()
+        .any(|content| matches!(content, MessageContent::ContextLengthExceeded(_)));
+    if ran_out {
+        return Outcome::Failed(FailureKind::BudgetExceeded);
+    }
+
+    if last.role == Role::User {
+        let kind = last.content.iter().find_map(|content| match content {
+            MessageContent::ToolResponse(response) => response
+                .tool_result
+                .as_ref()
+                .err()
+                .map(|e| tool_failure_kind(&e.to_string())),
+            _ => None,
+        });
+        return Outcome::Failed(kind.unwrap_or(FailureKind::UserAbort));
+    }
+
+    let text = last.as_concat_text();
+    let text = text.trim();
+    if text.starts_with(MAX_TURNS_MESSAGE) {
+        Outcome::Failed(FailureKind::BudgetExceeded)
+    } else if text.starts_with(PROVIDER_ERROR_PREFIX) {
+        Outcome::Failed(FailureKind::ProviderError)
+    } else if text.starts_with(MAX_RETRIES_PREFIX) {
+        Outcome::Failed(FailureKind::AssertionFailed)
+    } else if text.ends_with(INTERRUPTED_SUFFIX) {
+        Outcome::Failed(FailureKind::UserAbort)
+    } else if text.is_empty() {
+        // A tool request nobody answered means the session was stopped while it ran
+        let requested_tool = last
+            .content
+            .iter()
+            .any(|content| matches!(content, MessageContent::ToolRequest(_)));
+        Outcome::Failed(if requested_tool {
+            FailureKind::UserAbort
+        } else {
+            FailureKind::ProviderError
+        })
+    } else {
+        Outcome::Succeeded
+    }
+}
+
+/// Tokens and cost for one model across the report
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct ModelSpend {
+    pub model: String,
+    pub usage: ModelUsage,
+    /// None when there is no price for the model
+    pub cost: Option<f64>,
+    /// What prompt caching saved, None when there is no price for the model
+    pub cache_savings: Option<f64>,
+}
+
+/// Similar errors from one tool or from the provider, grouped by their message with the
+/// specifics taken out
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureCluster {
+    pub kind: FailureKind,
+    /// The tool name, or `provider` for provider errors
+    pub source: String,
+    pub signature: String,
+    pub count: usize,
+    pub sessions: usize,
+    pub example: String,
+}
+
+/// Reduce an error message to its shape, so the same failure with different paths, numbers
+/// or quoted values lands in the same cluster
+pub fn error_signature(message: &str) -> String {
+    let mut signature = String::new();
+    for word in message.split_whitespace() {
+        let word = if word.contains('/') || word.contains('\\') {
+            "<path>".to_string()
+        } else if word.starts_with(['\'', '"', '`']) {
+            "<value>".to_string()
+        } else {
+            let mut shape = String::new();
+            for c in word.chars() {
+                if !c.is_ascii_digit() {
+                    shape.extend(c.to_lowercase());
+                } else if !shape.ends_with('#') {
+                    shape.push('#');
+                }
+            }
+            shape
+        };
+        if !signature.is_empty() {
+            signature.push(' ');
+        }
+        signature.push_str(&word);
+        if signature.len() > 120 {
+            break;
+        }
+    }
+    signature
+}
+
+/// The share of distinct words two signatures have in common
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: HashSet<&str> = a.split(' ').collect();
+    let b: HashSet<&str> = b.split(' ').collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionReport {
+    pub since: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub sessions: Vec<SessionSummary>,
+    pub minutes_per_tool_call: f64,
+    pub spend: Vec<ModelSpend>,
+}
+
+impl SessionReport {
+    /// Summarize every session changed in the last `period`
+    pub async fn collect(period: Duration) -> Result<Self> {
+        let now = Utc::now();
+        let since = now - period;
+        let mut sessions = Vec::new();
+        for (id, path) in storage::list_sessions()? {
+            let modified: DateTime<Utc> = match storage::get_modified_time(&path) {
+                Ok(modified) => modified.into(),
+                Err(_) => continue,
+            };
+            if modified < since {
+                continue;
+            }
+            let (metadata, messages) =
+                match (storage::read_metadata(&path), storage::read_messages(&path)) {
+                    (Ok(metadata), Ok(messages)) => (metadata, messages),
+                    _ => {
+                        tracing::debug!("Skipping unreadable session {}", id);
+                        continue;
+                    }
+                };
+            sessions.push(SessionSummary::new(&id, &metadata, &messages));
+        }
+
+        let minutes_per_tool_call = Config::global()
+            .get_param::<f64>(MINUTES_PER_TOOL_CALL_KEY)
+            .unwrap_or(DEFAULT_MINUTES_PER_TOOL_CALL);
+        let provider = Config::global()
+            .get_param::<String>("GOOSE_PROVIDER")
+            .unwrap_or_default();
+
+        let mut report = Self {
+            since,
+            generated_at: now,
+            sessions,
+            minutes_per_tool_call,
+            spend: Vec::new(),
+        };
+        report.spend = model_spend(&provider, report.model_usage()).await;
+        Ok(report)
+    }
+
+    pub fn succeeded(&self) -> usize {
+        self.sessions
+            .iter()
+            .filter(|session| session.outcome == Outcome::Succeeded)
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.sessions.len() - self.succeeded()
+    }
+
+    /// Failed sessions counted by why they failed, most first
+    pub fn failures_by_kind(&self) -> Vec<(FailureKind, usize)> {
+        let mut counts: BTreeMap<FailureKind, usize> = BTreeMap::new();
+        for session in &self.sessions {
+            if let Outcome::Failed(kind) = session.outcome {
+                *counts.entry(kind).or_default() += 1;
+            }
+        }
+        let mut kinds: Vec<(FailureKind, usize)> = counts.into_iter().collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        kinds
+    }
+
+    /// A rough estimate: the tool calls of successful sessions times the minutes each saves
+    pub fn time_saved_hours(&self) -> f64 {
+        let calls: usize = self
+            .sessions
+            .iter()
+            .filter(|session| session.outcome == Outcome::Succeeded)
+            .map(SessionSummary::tool_call_count)
+            .sum();
+        calls as f64 * self.minutes_per_tool_call / 60.0
+    }
+
+    pub fn model_usage(&self) -> BTreeMap<String, ModelUsage> {
+        let mut totals: BTreeMap<String, ModelUsage> = BTreeMap::new();
+        for session in &self.sessions {
+            for (model, usage) in &session.model_usage {
+                let total = totals.entry(model.clone()).or_default();
+                total.requests += usage.requests;
+                total.input_tokens += usage.input_tokens;
+                total.output_tokens += usage.output_tokens;
+                total.cache_read_tokens += usage.cache_read_tokens;
+            }
+        }
+        totals
+    }
+
+    /// The most called tools, most first
+    pub fn top_tools(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for session in &self.sessions {
+            for (tool, count) in &session.tool_calls {
+                *counts.entry(tool).or_default() += count;
+            }
+        }
+        let mut tools: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(tool, count)| (tool.to_string(), count))
+            .collect();
+        tools.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        tools.truncate(TOP_N);
+        tools
+    }
+
+    /// The most frequent recurring problems. Tool and provider errors from the same source
+    /// are grouped when their signatures are close enough, so one problem reported with
+    /// slightly different wording still counts once. Interruptions by the user are left out.
+    pub fn failure_clusters(&self) -> Vec<FailureCluster> {
+        let mut clusters: Vec<(FailureCluster, HashSet<&str>)> = Vec::new();
+        for session in &self.sessions {
+            let tool_errors = session
+                .tool_errors
+                .iter()
+                .map(|(tool, error)| (tool_failure_kind(error), tool.as_str(), error));
+            let provider_errors = session
+                .provider_errors
+                .iter()
+                .map(|error| (FailureKind::ProviderError, "provider", error));
+            for (kind, source, error) in tool_errors.chain(provider_errors) {
+                if kind == FailureKind::UserAbort {
+                    continue;
+                }
+                let signature = error_signature(error);
+                let existing = clusters.iter_mut().find(|(cluster, _)| {
+                    cluster.kind == kind
+                        && cluster.source == source
+                        && similarity(&cluster.signature, &signature) >= CLUSTER_SIMILARITY
+                });
+                let (cluster, sessions) = match existing {
+                    Some(existing) => existing,
+                    None => {
+                        clusters.push((
+                            FailureCluster {
+                                kind,
+                                source: source.to_string(),
+                                signature,
+                                count: 0,
+                                sessions: 0,
+                                example: crate::utils::safe_truncate(error, EXAMPLE_CHARS),
+                            },
+                            HashSet::new(),
+                        ));
+                        clusters.last_mut().unwrap()
+                    }
+                };
+                cluster.count += 1;
+                if sessions.insert(&session.id) {
+                    cluster.sessions += 1;
+                }
+            }
+        }
+        let mut clusters: Vec<FailureCluster> =
+            clusters.into_iter().map(|(cluster, _)| cluster).collect();
+        clusters.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.sessions.cmp(&a.sessions))
+                .then_with(|| a.source.cmp(&b.source))
+        });
+        clusters.truncate(TOP_N);
+        clusters
+    }
+
+    pub fn total_cost(&self) -> Option<f64> {
+        let costs: Vec<f64> = self.spend.iter().filter_map(|spend| spend.cost).collect();
+        (!costs.is_empty()).then(|| costs.iter().sum())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# goose usage report\n");
+        let _ = writeln!(
+            out,
+            "{} to {}\n",
+            self.since.format("%Y-%m-%d"),
+            self.generated_at.format("%Y-%m-%d")
+        );
+
+        let _ = writeln!(out, "## Summary\n");
+        let _ = writeln!(out, "| | |\n|---|---|");
+        let _ = writeln!(out, "| Sessions | {} |", self.sessions.len());
+        let _ = writeln!(out, "| Succeeded | {} |", self.succeeded());
+        let _ = writeln!(out, "| Failed | {} |", self.failed());
+        let _ = writeln!(
+            out,
+            "| Estimated time saved | {:.1} h |",
+            self.time_saved_hours()
+        );
+        if let Some(cost) = self.total_cost() {
+            let _ = writeln!(out, "| Spend | ${:.2} |", cost);
+        }
+
+        let _ = writeln!(out, "\n## Spend by model\n");
+        let _ = writeln!(
+            out,
+            "| Model | Requests | Input tokens | Cached tokens | Output tokens | Cost | Cache savings |\n|---|---|---|---|---|---|---|"
+        );
+        for spend in &self.spend {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                spend.model,
+                spend.usage.requests,
+                spend.usage.input_tokens,
+                spend.usage.cache_read_tokens,
+                spend.usage.output_tokens,
+                format_cost(spend.cost),
+                format_cost(spend.cache_savings)
+            );
+        }
+
+        let _ = writeln!(out, "\n## Most used tools\n");
+        let _ = writeln!(out, "| Tool | Calls |\n|---|---|");
+        for (tool, count) in self.top_tools() {
+            let _ = writeln!(out, "| {} | {} |", tool, count);
+        }
+
+        let _ = writeln!(out, "\n## Failures by kind\n");
+        let _ = writeln!(out, "| Kind | Sessions |\n|---|---|");
+        for (kind, count) in self.failures_by_kind() {
+            let _ = writeln!(out, "| {} | {} |", kind, count);
+        }
+
+        let _ = writeln!(out, "\n## Recurring problems\n");
+        let _ = writeln!(
+            out,
+            "| Kind | Source | Errors | Sessions | Example |\n|---|---|---|---|---|"
+        );
+        for cluster in self.failure_clusters() {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                cluster.kind,
+                cluster.source,
+                cluster.count,
+                cluster.sessions,
+                cluster.example.replace('|', "\\|").replace('\n', " ")
+            );
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>goose usage report</title>\
+             <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse;margin-bottom:2em}}\
+             td,th{{border:1px solid #ccc;padding:4px 8px;text-align:left}}</style></head><body>\n"
+        );
+        let _ = writeln!(
+            out,
+            "<h1>goose usage report</h1><p>{} to {}</p>",
+            self.since.format("%Y-%m-%d"),
+            self.generated_at.format("%Y-%m-%d")
+        );
+
+        let mut summary = vec![
+            ("Sessions".to_string(), self.sessions.len().to_string()),
+            ("Succeeded".to_string(), self.succeeded().to_string()),
+            ("Failed".to_string(), self.failed().to_string()),
+            (
+                "Estimated time saved".to_string(),
+                format!("{:.1} h", self.time_saved_hours()),
+            ),
+        ];
+        if let Some(cost) = self.total_cost() {
+            summary.push(("Spend".to_string(), format!("${:.2}", cost)));
+        }
+        html_table(
+            &mut out,
+            "Summary",
+            &["", ""],
+            summary.into_iter().map(|(k, v)| vec![k, v]),
+        );
+
+        html_table(
+            &mut out,
+            "Spend by model",
+            &[
+                "Model",
+                "Requests",
+                "Input tokens",
+                "Cached tokens",
+                "Output tokens",
+                "Cost",
+                "Cache savings",
+            ],
+            self.spend.iter().map(|spend| {
+                vec![
+                    spend.model.clone(),
+                    spend.usage.requests.to_string(),
+                    spend.usage.input_tokens.to_string(),
+                    spend.usage.cache_read_tokens.to_string(),
+                    spend.usage.output_tokens.to_string(),
+                    format_cost(spend.cost),
+                    format_cost(spend.cache_savings),
+                ]
+            }),
+        );
+        html_table(
+            &mut out,
+            "Most used tools",
+            &["Tool", "Calls"],
+            self.top_tools()
+                .into_iter()
+                .map(|(tool, count)| vec![tool, count.to_string()]),
+        );
+        html_table(
+            &mut out,
+            "Failures by kind",
+            &["Kind", "Sessions"],
+            self.failures_by_kind()
+                .into_iter()
+                .map(|(kind, count)| vec![kind.to_string(), count.to_string()]),
+        );
+        html_table(
+            &mut out,
+            "Recurring problems",
+            &["Kind", "Source", "Errors", "Sessions", "Example"],
+            self.failure_clusters().into_iter().map(|cluster| {
+                vec![
+                    cluster.kind.to_string(),
+                    cluster.source,
+                    cluster.count.to_string(),
+                    cluster.sessions.to_string(),
+                    cluster.example,
+                ]
+            }),
+        );
+        out.push_str("</body></html>\n");
+        out
+    }
+}
+
+/// Price each model's usage, with `provider` as the one serving models without a router prefix
+pub(crate) async fn model_spend(
+    provider: &str,
+    model_usage: BTreeMap<String, ModelUsage>,
+) -> Vec<ModelSpend> {
+    let mut spend = Vec::new();
+    for (model, usage) in model_usage {
+        let (cost, cache_savings) = match model_cost(provider, &model, &usage).await {
+            Some((cost, savings)) => (Some(cost), Some(savings)),
+            None => (None, None),
+        };
+        spend.push(ModelSpend {
+            model,
+            usage,
+            cost,
+            cache_savings,
+        });
+    }
+    spend
+}
+
+/// The cost of a model's usage and what prompt caching saved on it
+async fn model_cost(provider: &str, model: &str, usage: &ModelUsage) -> Option<(f64, f64)> {
+    // Models served through a router carry their real provider in the name
+    let (billed_by, model) =
+        parse_model_id(model).unwrap_or_else(|| (provider.to_string(), model.to_string()));
+    let pricing = get_model_pricing(&billed_by, &model).await?;
+    let savings = cache_savings(&billed_by, pricing.input_cost, usage.cache_read_tokens);
+    Some((
+        pricing.input_cost * usage.input_tokens as f64
+            + pricing.output_cost * usage.output_tokens as f64
+            - savings,
+        savings,
+    ))
+}
+
+fn format_cost(cost: Option<f64>) -> String {
+    cost.map(|cost| format!("${:.2}", cost))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn html_table(
+    out: &mut String,
+    title: &str,
+    headers: &[&str],
+    rows: impl Iterator<Item = Vec<String>>,
+) {
+    let _ = write!(out, "<h2>{}</h2><table><tr>", escape_html(title));
+    for header in headers {
+        let _ = write!(out, "<th>{}</th>", escape_html(header));
+    }
+    out.push_str("</tr>\n");
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            let _ = write!(out, "<td>{}</td>", escape_html(&cell));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{ToolCall, ToolError};
+    use serde_json::json;
+
+    fn tool_call(name: &str) -> ToolCall {
+        ToolCall::new(name, json!({}))
+    }
+
+    fn session(id: &str, error: Option<&str>, answered: bool) -> SessionSummary {
+        let mut messages = vec![
+            Message::user().with_text("fix the build"),
+            Message::assistant().with_tool_request("1", Ok(tool_call("developer__shell"))),
+        ];
+        messages.push(match error {
+            Some(error) => Message::user()
+                .with_tool_response("1", Err(ToolError::ExecutionError(error.to_string()))),
+            None => Message::user().with_tool_response("1", Ok(vec![])),
+        });
+        if answered {
+            messages.push(Message::assistant().with_text("Done"));
+        }
+
+        let mut metadata = SessionMetadata::default();
+        metadata.model_usage.insert(
+            "gpt-4o".to_string(),
+            ModelUsage {
+                requests: 2,
+                input_tokens: 1_000,
+                output_tokens: 100,
+                cache_read_tokens: 0,
+            },
+        );
+        SessionSummary::new(id, &metadata, &Conversation::new_unvalidated(messages))
+    }
+
+    fn report(sessions: Vec<SessionSummary>) -> SessionReport {
+        let now = Utc::now();
+        SessionReport {
+            since: now - Duration::days(30),
+            generated_at: now,
+            sessions,
+            minutes_per_tool_call: 30.0,
+            spend: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_period("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_period("2w").unwrap(), Duration::weeks(2));
+        assert!(parse_period("30").is_err());
+        assert!(parse_period("d").is_err());
+    }
+
+    #[test]
+    fn test_session_outcomes() {
+        assert_eq!(session("a", None, true).outcome, Outcome::Succeeded);
+        assert_eq!(
+            session("b", Some("boom"), false).outcome,
+            Outcome::Failed(FailureKind::ToolError)
+        );
+        assert_eq!(
+            session("c", None, false).outcome,
+            Outcome::Failed(FailureKind::UserAbort)
+        );
+        assert_eq!(
+            session("d", Some(REPETITION_REJECTED_MESSAGE), false).outcome,
+            Outcome::Failed(FailureKind::LoopDetected)
+        );
+    }
+
+    #[test]
+    fn test_failure_taxonomy() {
+        let ending = |last: Message| outcome(&[Message::user().with_text("fix the build"), last]);
+        assert_eq!(
+            ending(Message::assistant().with_text(MAX_TURNS_MESSAGE)),
+            Outcome::Failed(FailureKind::BudgetExceeded)
+        );
+        assert_eq!(
+            ending(Message::assistant().with_context_length_exceeded("too long")),
+            Outcome::Failed(FailureKind::BudgetExceeded)
+        );
+        assert_eq!(
+            ending(Message::assistant().with_text(format!(
+                "{}Server error: overloaded.\n\nPlease retry",
+                PROVIDER_ERROR_PREFIX
+            ))),
+            Outcome::Failed(FailureKind::ProviderError)
+        );
+        assert_eq!(
+            ending(Message::assistant().with_text(format!(
+                "{} (3) exceeded. Unable to complete the task successfully.",
+                MAX_RETRIES_PREFIX
+            ))),
+            Outcome::Failed(FailureKind::AssertionFailed)
+        );
+        assert_eq!(
+            ending(Message::assistant().with_text(
+                "The existing call to developer__shell was interrupted. How would you like to proceed?"
+            )),
+            Outcome::Failed(FailureKind::UserAbort)
+        );
+        assert_eq!(
+            ending(Message::assistant().with_tool_request("1", Ok(tool_call("developer__shell")))),
+            Outcome::Failed(FailureKind::UserAbort)
+        );
+        assert_eq!(
+            ending(Message::assistant().with_text("All fixed")),
+            Outcome::Succeeded
+        );
+    }
+
+    #[test]
+    fn test_similar_errors_cluster_across_sessions() {
+        let provider_failure = |id: &str, error: &str| {
+            let messages = Conversation::new_unvalidated(vec![
+                Message::user().with_text("hi"),
+                Message::assistant().with_text(format!(
+                    "{}{}.\n\nPlease retry if you think this is a transient or recoverable error.",
+                    PROVIDER_ERROR_PREFIX, error
+                )),
+            ]);
+            SessionSummary::new(id, &SessionMetadata::default(), &messages)
+        };
+        let report = report(vec![
+            provider_failure("a", "Server error: upstream connect error or disconnect"),
+            provider_failure(
+                "b",
+                "Server error: upstream connect error or disconnect/reset",
+            ),
+            provider_failure("c", "Rate limit exceeded: try again in 20s"),
+            session(
+                "d",
+                Some("Interrupted by the user to make a correction"),
+                false,
+            ),
+        ]);
+        assert_eq!(report.sessions[0].provider_errors.len(), 1);
+        assert_eq!(
+            report.failures_by_kind(),
+            vec![(FailureKind::ProviderError, 3), (FailureKind::UserAbort, 1)]
+        );
+
+        let clusters = report.failure_clusters();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].kind, FailureKind::ProviderError);
+        assert_eq!(clusters[0].source, "provider");
+        assert_eq!((clusters[0].count, clusters[0].sessions), (2, 2));
+        assert!(report.to_markdown().contains("| provider error | 3 |"));
+    }
+
+    #[test]
+    fn test_report_aggregates_sessions() {
+        let report = report(vec![
+            session("a", None, true),
+            session("b", Some("No such file /tmp/a.txt"), false),
+            session("c", Some("No such file /home/b.rs"), false),
+        ]);
+
+        assert_eq!(report.succeeded(), 1);
+        assert_eq!(report.failed(), 2);
+        assert_eq!(report.time_saved_hours(), 0.5);
+        assert_eq!(
+            report.top_tools(),
+            vec![("developer__shell".to_string(), 3)]
+        );
+        assert_eq!(report.model_usage()["gpt-4o"].input_tokens, 3_000);
+
+        let clusters = report.failure_clusters();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].count, 2);
+        assert_eq!(clusters[0].sessions, 2);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| Failed | 2 |"));
+        assert!(markdown.contains("| developer__shell | 3 |"));
+        assert!(report.to_html().contains("<td>developer__shell</td>"));
+    }
+
+    #[test]
+    fn test_error_signature() {
+        assert_eq!(
+            error_signature("Exit code 127 for /usr/bin/foo"),
+            error_signature("Exit code 1 for /bin/bar")
+        );
+        assert_ne!(
+            error_signature("Permission denied"),
+            error_signature("Timed out")
+        );
+        assert_eq!(escape_html("<b>&</b>"), "&lt;b&gt;&amp;&lt;/b&gt;");
+    }
+}
@@ -0,0 +1,281 @@
+//! Retention policies for stored sessions.
+//!
+//! Sessions accumulate forever unless something removes them. A [`RetentionPolicy`] bounds them
+//! by age, by count and by total size on disk; `goose session prune` applies it, and with
+//! `GOOSE_SESSION_PRUNE_ON_STARTUP` set it is applied whenever a session starts. Tagged sessions
+//! are never pruned, so tagging a session (for example `goose session tag pinned`) keeps it.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::session::events::event_log_path;
+use crate::session::storage::{delete_session, get_modified_time, list_sessions, read_metadata};
+
+/// Config key for the age in days after which sessions are pruned
+pub const RETENTION_MAX_AGE_DAYS_KEY: &str = "GOOSE_SESSION_RETENTION_MAX_AGE_DAYS";
+/// Config key for the number of sessions kept
+pub const RETENTION_MAX_COUNT_KEY: &str = "GOOSE_SESSION_RETENTION_MAX_COUNT";
+/// Config key for the total size of session files kept, in megabytes
+pub const RETENTION_MAX_SIZE_MB_KEY: &str = "GOOSE_SESSION_RETENTION_MAX_SIZE_MB";
+/// Config key enabling pruning whenever a session starts
+pub const PRUNE_ON_STARTUP_KEY: &str = "GOOSE_SESSION_PRUNE_ON_STARTUP";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Limits on the sessions kept; unset limits don't prune anything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_count: Option<usize>,
+    pub max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_age: config
+                .get_param::<u64>(RETENTION_MAX_AGE_DAYS_KEY)
+                .ok()
+                .map(|days| Duration::from_secs(days * SECONDS_PER_DAY)),
+            max_count: config.get_param::<usize>(RETENTION_MAX_COUNT_KEY).ok(),
+            max_total_bytes: config
+                .get_param::<u64>(RETENTION_MAX_SIZE_MB_KEY)
+                .ok()
+                .map(|mb| mb * 1024 * 1024),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_age.is_none() && self.max_count.is_none() && self.max_total_bytes.is_none()
+    }
+}
+
+/// A stored session as the policy sees it
+#[derive(Debug, Clone, Serialize)]
+pub struct RetainedSession {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub modified: SystemTime,
+    /// Bytes of the session's local files, its event log included
+    pub size: u64,
+    /// Tagged sessions, and the one in use, are never pruned
+    pub protected: bool,
+}
+
+/// Why a session is pruned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    Age,
+    Count,
+    Size,
+}
+
+impl std::fmt::Display for PruneReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Age => "older than the maximum age",
+            Self::Count => "over the maximum number of sessions",
+            Self::Size => "over the maximum total size",
+        })
+    }
+}
+
+/// The sessions the policy removes, oldest first, each with the first limit it breaks. Newer
+/// sessions are kept first; protected sessions are always kept and don't count toward the limits.
+pub fn select_prunable(
+    sessions: &[RetainedSession],
+    policy: &RetentionPolicy,
+    now: SystemTime,
+) -> Vec<(usize, PruneReason)> {
+    let mut newest_first: Vec<usize> = (0..sessions.len()).collect();
+    newest_first.sort_by(|a, b| sessions[*b].modified.cmp(&sessions[*a].modified));
+
+    let mut pruned: Vec<(usize, PruneReason)> = Vec::new();
+    let mut kept = 0;
+    let mut kept_bytes = 0u64;
+    for index in newest_first {
+        let session = &sessions[index];
+        let age = now.duration_since(session.modified).unwrap_or_default();
+        let reason = if session.protected {
+            None
+        } else if policy.max_age.is_some_and(|max_age| age > max_age) {
+            Some(PruneReason::Age)
+        } else if policy.max_count.is_some_and(|max_count| kept >= max_count) {
+            Some(PruneReason::Count)
+        } else if policy
+            .max_total_bytes
+            .is_some_and(|max_bytes| kept_bytes + session.size > max_bytes)
+        {
+            Some(PruneReason::Size)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => pruned.push((index, reason)),
+            None => {
+                kept += 1;
+                kept_bytes += session.size;
+            }
+        }
+    }
+    pruned.reverse();
+    pruned
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Every stored session, with `in_use` protected along with tagged sessions
+pub fn retained_sessions(in_use: Option<&Path>) -> Result<Vec<RetainedSession>> {
+    let mut sessions = Vec::new();
+    for (name, path) in list_sessions()? {
+        let Ok(modified) = get_modified_time(&path) else {
+            continue;
+        };
+        // Sessions whose metadata can't be read are left for the user to look at
+        let protected = match read_metadata(&path) {
+            Ok(metadata) => !metadata.tags.is_empty(),
+            Err(_) => true,
+        } || in_use == Some(path.as_path());
+        sessions.push(RetainedSession {
+            size: file_size(&path) + file_size(&event_log_path(&path)),
+            name,
+            path,
+            modified,
+            protected,
+        });
+    }
+    Ok(sessions)
+}
+
+/// What pruning did, or would do on a dry run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub pruned: Vec<(RetainedSession, PruneReason)>,
+    pub kept: usize,
+    pub freed_bytes: u64,
+    /// Sessions that could not be removed, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Remove the sessions `policy` doesn't keep, sparing `in_use`. Nothing is removed on a dry run.
+pub fn prune(
+    policy: &RetentionPolicy,
+    in_use: Option<&Path>,
+    dry_run: bool,
+) -> Result<PruneReport> {
+    let sessions = retained_sessions(in_use)?;
+    let selected = select_prunable(&sessions, policy, SystemTime::now());
+
+    let mut report = PruneReport {
+        kept: sessions.len() - selected.len(),
+        ..Default::default()
+    };
+    for (index, reason) in selected {
+        let session = sessions[index].clone();
+        if !dry_run {
+            if let Err(e) = delete_session(&session.path) {
+                report.failed.push((session.name, e.to_string()));
+                continue;
+            }
+            let _ = std::fs::remove_file(event_log_path(&session.path));
+        }
+        report.freed_bytes += session.size;
+        report.pruned.push((session, reason));
+    }
+    Ok(report)
+}
+
+/// Apply the configured policy if pruning on startup is enabled, sparing the session starting
+pub fn prune_on_startup(session_file: &Path) {
+    let config = Config::global();
+    if !config
+        .get_param::<bool>(PRUNE_ON_STARTUP_KEY)
+        .unwrap_or(false)
+    {
+        return;
+    }
+    let policy = RetentionPolicy::from_config(config);
+    if policy.is_empty() {
+        return;
+    }
+    match prune(&policy, Some(session_file), false) {
+        Ok(report) if !report.pruned.is_empty() => tracing::info!(
+            "Pruned {} sessions, freeing {} bytes",
+            report.pruned.len(),
+            report.freed_bytes
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to prune sessions: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, days_old: u64, size: u64, protected: bool) -> RetainedSession {
+        RetainedSession {
+            name: name.to_string(),
+            path: PathBuf::from(format!("{}.jsonl", name)),
+            modified: SystemTime::UNIX_EPOCH
+                + Duration::from_secs((100 - days_old) * SECONDS_PER_DAY),
+            size,
+            protected,
+        }
+    }
+
+    #[test]
+    fn test_select_prunable_spares_protected_sessions() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * SECONDS_PER_DAY);
+        let sessions = vec![
+            session("today", 0, 400, false),
+            session("pinned", 90, 400, true),
+            session("last_week", 7, 400, false),
+            session("yesterday", 1, 400, false),
+            session("last_month", 30, 400, false),
+        ];
+        let names = |policy: &RetentionPolicy| {
+            select_prunable(&sessions, policy, now)
+                .into_iter()
+                .map(|(index, reason)| (sessions[index].name.as_str(), reason))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(names(&RetentionPolicy::default()).is_empty());
+        assert_eq!(
+            names(&RetentionPolicy {
+                max_age: Some(Duration::from_secs(14 * SECONDS_PER_DAY)),
+                ..Default::default()
+            }),
+            vec![("last_month", PruneReason::Age)]
+        );
+        assert_eq!(
+            names(&RetentionPolicy {
+                max_count: Some(2),
+                ..Default::default()
+            }),
+            vec![
+                ("last_month", PruneReason::Count),
+                ("last_week", PruneReason::Count)
+            ]
+        );
+        assert_eq!(
+            names(&RetentionPolicy {
+                max_total_bytes: Some(1000),
+                ..Default::default()
+            }),
+            vec![
+                ("last_month", PruneReason::Size),
+                ("last_week", PruneReason::Size)
+            ]
+        );
+    }
+}
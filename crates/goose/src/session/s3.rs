@@ -0,0 +1,208 @@
+//! S3 storage for session transcripts.
+//!
+//! Enabled with `GOOSE_SESSION_STORAGE=s3` and a bucket in `GOOSE_SESSION_S3_BUCKET`. Each
+//! session is one JSON object under `GOOSE_SESSION_S3_PREFIX` (default `sessions/`), written with
+//! a single put so readers never see a partial transcript. Credentials and region come from the
+//! standard AWS configuration, as for the Bedrock provider. Built with the `s3-sessions` feature.
+
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::session::storage::SessionMetadata;
+use crate::session::store::{block_on, session_name, session_path, SessionStore, StoredSession};
+use anyhow::{Context, Result};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Config key holding the bucket sessions are stored in
+pub const S3_BUCKET_KEY: &str = "GOOSE_SESSION_S3_BUCKET";
+/// Config key holding the key prefix for session objects
+pub const S3_PREFIX_KEY: &str = "GOOSE_SESSION_S3_PREFIX";
+
+const DEFAULT_PREFIX: &str = "sessions/";
+const OBJECT_SUFFIX: &str = ".json";
+
+/// The stored form of a session
+#[derive(Serialize, Deserialize)]
+struct Transcript {
+    metadata: SessionMetadata,
+    messages: Vec<Message>,
+}
+
+pub struct S3Store {
+    client: Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Store {
+    pub fn new(client: Client, bucket: impl Into<String>, prefix: impl Into<String>) -> Self {
+        Self {
+            client,
+            bucket: bucket.into(),
+            prefix: prefix.into(),
+        }
+    }
+
+    pub fn from_config() -> Result<Self> {
+        let config = Config::global();
+        let bucket: String = config
+            .get_param(S3_BUCKET_KEY)
+            .with_context(|| format!("{} must be set to store sessions in S3", S3_BUCKET_KEY))?;
+        let prefix: String = config
+            .get_param(S3_PREFIX_KEY)
+            .unwrap_or_else(|_| DEFAULT_PREFIX.to_string());
+
+        let sdk_config = block_on(aws_config::load_from_env())?;
+        Ok(Self::new(Client::new(&sdk_config), bucket, prefix))
+    }
+
+    fn object_key(&self, path: &Path) -> String {
+        format!("{}{}{}", self.prefix, session_name(path), OBJECT_SUFFIX)
+    }
+
+    fn load(&self, path: &Path) -> Result<Option<Transcript>> {
+        let key = self.object_key(path);
+        block_on(async {
+            let output = match self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+                    return Ok(None);
+                }
+                Err(e) => return Err(anyhow::anyhow!("Failed to read session {}: {}", key, e)),
+            };
+            let body = output
+                .body
+                .collect()
+                .await
+                .context("Failed to download session")?;
+            let transcript = serde_json::from_slice(&body.into_bytes())
+                .with_context(|| format!("Failed to parse session {}", key))?;
+            Ok(Some(transcript))
+        })?
+    }
+}
+
+impl SessionStore for S3Store {
+    fn save_session(
+        &self,
+        path: &Path,
+        metadata: &SessionMetadata,
+        messages: &Conversation,
+    ) -> Result<()> {
+        let key = self.object_key(path);
+        let body = serde_json::to_vec(&Transcript {
+            metadata: metadata.clone(),
+            messages: messages.messages().clone(),
+        })?;
+        block_on(async {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .content_type("application/json")
+                .body(ByteStream::from(body))
+                .send()
+                .await
+                .with_context(|| format!("Failed to write session {}", key))?;
+            anyhow::Ok(())
+        })?
+    }
+
+    fn load_messages(&self, path: &Path) -> Result<Option<Conversation>> {
+        Ok(self
+            .load(path)?
+            .map(|transcript| Conversation::new_unvalidated(transcript.messages)))
+    }
+
+    fn load_metadata(&self, path: &Path) -> Result<Option<SessionMetadata>> {
+        Ok(self.load(path)?.map(|transcript| transcript.metadata))
+    }
+
+    fn updated_at(&self, path: &Path) -> Result<Option<i64>> {
+        let key = self.object_key(path);
+        block_on(async {
+            match self
+                .client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(output
+                    .last_modified()
+                    .and_then(|time| time.to_millis().ok())),
+                Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+                Err(e) => Err(anyhow::anyhow!("Failed to look up session {}: {}", key, e)),
+            }
+        })?
+    }
+
+    fn list_sessions(&self) -> Result<Vec<StoredSession>> {
+        let objects = block_on(async {
+            let mut objects = Vec::new();
+            let mut continuation_token = None;
+            loop {
+                let output = self
+                    .client
+                    .list_objects_v2()
+                    .bucket(&self.bucket)
+                    .prefix(&self.prefix)
+                    .set_continuation_token(continuation_token)
+                    .send()
+                    .await
+                    .context("Failed to list sessions")?;
+                objects.extend(output.contents().iter().cloned());
+                continuation_token = output.next_continuation_token().map(str::to_string);
+                if continuation_token.is_none() {
+                    break;
+                }
+            }
+            anyhow::Ok(objects)
+        })??;
+
+        let mut sessions = Vec::new();
+        for object in objects {
+            let Some(name) = object
+                .key()
+                .and_then(|key| key.strip_prefix(self.prefix.as_str()))
+                .and_then(|key| key.strip_suffix(OBJECT_SUFFIX))
+            else {
+                continue;
+            };
+            sessions.push(StoredSession {
+                path: session_path(name)?,
+                name: name.to_string(),
+                updated_at: object
+                    .last_modified()
+                    .and_then(|time| time.to_millis().ok())
+                    .unwrap_or_default(),
+            });
+        }
+        Ok(sessions)
+    }
+
+    fn delete_session(&self, path: &Path) -> Result<()> {
+        let key = self.object_key(path);
+        block_on(async {
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .send()
+                .await
+                .with_context(|| format!("Failed to delete session {}", key))?;
+            anyhow::Ok(())
+        })?
+    }
+}
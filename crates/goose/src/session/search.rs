@@ -0,0 +1,468 @@
+//! Full-text search across session history.
+//!
+//! Every saved session is indexed in an SQLite FTS5 table in `search.db` next to the sessions
+//! directory: the text of each message, the tools it called and the file paths those calls
+//! touched. Saving a session only indexes the messages added since the last save, so keeping the
+//! index current costs little even for long sessions. Sessions written before the index existed,
+//! or by another machine sharing a session store, are picked up by [`SearchIndex::refresh`].
+//!
+//! While session encryption is on nothing new is indexed, and whatever was indexed before it was
+//! turned on is purged, so the index doesn't keep a plaintext copy of encrypted sessions.
+
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::session::encryption;
+use crate::session::storage::{self, ensure_session_dir};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+const INDEX_FILE_NAME: &str = "search.db";
+
+/// How long a write waits for another process to release the index
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tool arguments that name the files a call touches
+const PATH_ARGUMENTS: &[&str] = &["path", "paths", "file_path", "filepath", "filename"];
+
+/// Words of context on each side of a match in a snippet
+const SNIPPET_WORDS: i64 = 12;
+
+/// Initial schema: one row per indexed message, and how far each session has been indexed
+const CREATE_INDEX: &str = "
+    CREATE TABLE indexed_sessions (
+        path TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        message_count INTEGER NOT NULL,
+        indexed_at INTEGER NOT NULL
+    );
+    CREATE VIRTUAL TABLE entries USING fts5(
+        session_path UNINDEXED,
+        idx UNINDEXED,
+        created UNINDEXED,
+        text
+    );
+";
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many have run.
+const MIGRATIONS: &[&str] = &[CREATE_INDEX];
+
+static INDEX: OnceCell<SearchIndex> = OnceCell::new();
+
+/// A message matching a search, one per session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    /// The session's name (its file stem)
+    pub session: String,
+    pub path: PathBuf,
+    /// Index of the best matching message in the session
+    pub message_index: usize,
+    /// When the matching message was created, in Unix seconds
+    pub timestamp: i64,
+    /// The matching text with the matched terms in `[brackets]`
+    pub snippet: String,
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn session_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path_key(path))
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: i64 = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let version = version.max(0) as usize;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(migration)
+            .with_context(|| format!("Failed to apply search index migration {}", index + 1))?;
+    }
+    if version < MIGRATIONS.len() {
+        tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// The text a message is found by: what it says, the tools it called and the files they touched
+fn searchable_text(message: &Message) -> String {
+    let mut parts = Vec::new();
+    for content in &message.content {
+        if let Some(text) = content.as_text() {
+            parts.push(text.to_string());
+        }
+        if let Some(Ok(call)) = content.as_tool_request().map(|r| &r.tool_call) {
+            parts.push(call.name.clone());
+            for key in PATH_ARGUMENTS {
+                match call.arguments.get(*key) {
+                    Some(Value::String(path)) => parts.push(path.clone()),
+                    Some(Value::Array(paths)) => {
+                        parts.extend(paths.iter().filter_map(Value::as_str).map(str::to_string))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    parts.join("\n")
+}
+
+/// Turn free text into an FTS5 query matching messages that contain every word, so quotes,
+/// colons and the like in what the user typed are searched for rather than parsed
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A handle to the search index
+pub struct SearchIndex {
+    conn: Mutex<Connection>,
+}
+
+impl SearchIndex {
+    /// Open (creating if needed) the index at `path` and bring its schema up to date
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Failed to open search index {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// The process-wide index, stored next to the sessions directory
+    pub fn global() -> Result<&'static SearchIndex> {
+        INDEX.get_or_try_init(|| {
+            let session_dir = ensure_session_dir()?;
+            let data_dir = session_dir.parent().unwrap_or(&session_dir);
+            Self::open(&data_dir.join(INDEX_FILE_NAME))
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Search index lock poisoned"))
+    }
+
+    /// Bring a session's entries up to date with its messages.
+    ///
+    /// Sessions only grow between saves, so just the messages past those already indexed are
+    /// added, along with the last indexed one again in case it was extended. A session with
+    /// fewer messages than were indexed has been compacted or truncated and is indexed afresh.
+    pub fn index_session(&self, path: &Path, messages: &Conversation) -> Result<()> {
+        let key = path_key(path);
+        let mut conn = self.lock()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let indexed: usize = tx
+            .query_row(
+                "SELECT message_count FROM indexed_sessions WHERE path = ?1",
+                params![key],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(|count| count.max(0) as usize)
+            .unwrap_or(0);
+        let start = if indexed > messages.len() {
+            0
+        } else {
+            indexed.saturating_sub(1)
+        };
+
+        tx.execute(
+            "DELETE FROM entries WHERE session_path = ?1 AND CAST(idx AS INTEGER) >= ?2",
+            params![key, start as i64],
+        )?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO entries (session_path, idx, created, text) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (idx, message) in messages.iter().enumerate().skip(start) {
+                let text = searchable_text(message);
+                if !text.is_empty() {
+                    insert.execute(params![key, idx as i64, message.created, text])?;
+                }
+            }
+        }
+        tx.execute(
+            "INSERT INTO indexed_sessions (path, name, message_count, indexed_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(path) DO UPDATE SET
+                message_count = excluded.message_count,
+                indexed_at = excluded.indexed_at",
+            params![
+                key,
+                session_name(path),
+                messages.len() as i64,
+                Utc::now().timestamp_millis()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drop a session from the index
+    pub fn remove_session(&self, path: &Path) -> Result<()> {
+        let key = path_key(path);
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM entries WHERE session_path = ?1", params![key])?;
+        tx.execute("DELETE FROM indexed_sessions WHERE path = ?1", params![key])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Forget everything indexed and compact the file, so none of the indexed text is left in
+    /// it. Does nothing once the index is empty.
+    pub fn purge(&self) -> Result<()> {
+        let conn = self.lock()?;
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM indexed_sessions", [], |row| {
+            row.get(0)
+        })?;
+        if indexed == 0 {
+            return Ok(());
+        }
+        conn.execute_batch(
+            "DELETE FROM entries;
+             DELETE FROM indexed_sessions;
+             INSERT INTO entries(entries) VALUES('optimize');
+             VACUUM;",
+        )?;
+        Ok(())
+    }
+
+    /// Index sessions saved since they were last indexed and forget deleted ones, returning how
+    /// many sessions were (re)indexed
+    pub fn refresh(&self) -> Result<usize> {
+        if encryption::is_enabled() {
+            self.purge()?;
+            return Ok(0);
+        }
+        let sessions = storage::list_sessions()?;
+        let indexed: Vec<(String, i64)> = {
+            let conn = self.lock()?;
+            let mut stmt = conn.prepare("SELECT path, indexed_at FROM indexed_sessions")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+
+        let current: HashSet<String> = sessions.iter().map(|(_, path)| path_key(path)).collect();
+        for (key, _) in indexed.iter().filter(|(key, _)| !current.contains(key)) {
+            self.remove_session(Path::new(key))?;
+        }
+
+        let mut refreshed = 0;
+        for (_, path) in &sessions {
+            let indexed_at = indexed
+                .iter()
+                .find(|(key, _)| *key == path_key(path))
+                .map(|(_, indexed_at)| *indexed_at);
+            let modified = storage::get_modified_time(path)
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as i64);
+            if indexed_at.is_some() && indexed_at >= modified {
+                continue;
+            }
+            match storage::read_messages(path) {
+                Ok(messages) => {
+                    self.index_session(path, &messages)?;
+                    refreshed += 1;
+                }
+                Err(e) => tracing::warn!("Not indexing session {}: {}", path.display(), e),
+            }
+        }
+        Ok(refreshed)
+    }
+
+    /// The sessions with messages containing every word of `query`, best match first
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let query = fts_query(query);
+        if query.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(
+            "SELECT entries.session_path, indexed_sessions.name, entries.idx, entries.created,
+                    snippet(entries, 3, '[', ']', '…', ?2)
+             FROM entries JOIN indexed_sessions ON indexed_sessions.path = entries.session_path
+             WHERE entries MATCH ?1
+             ORDER BY rank",
+        )?;
+        let rows = stmt.query_map(params![query, SNIPPET_WORDS], |row| {
+            Ok(SearchHit {
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                session: row.get(1)?,
+                message_index: row.get::<_, i64>(2)?.max(0) as usize,
+                timestamp: row.get(3)?,
+                snippet: row.get(4)?,
+            })
+        })?;
+
+        let mut seen = HashSet::new();
+        let mut hits = Vec::new();
+        for hit in rows {
+            let hit = hit?;
+            if seen.insert(hit.path.clone()) {
+                hits.push(hit);
+                if hits.len() == limit {
+                    break;
+                }
+            }
+        }
+        Ok(hits)
+    }
+}
+
+/// Update the index after a session is saved. Search is a convenience, so failures are logged
+/// rather than failing the save.
+pub(crate) fn index_saved_session(path: &Path, messages: &Conversation) {
+    if encryption::is_enabled() {
+        if let Err(e) = SearchIndex::global().and_then(|index| index.purge()) {
+            tracing::warn!("Failed to purge the search index: {}", e);
+        }
+        return;
+    }
+    if let Err(e) = SearchIndex::global().and_then(|index| index.index_session(path, messages)) {
+        tracing::warn!(
+            "Failed to update search index for {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// Remove a deleted session from the index, logging rather than failing on error
+pub(crate) fn unindex_deleted_session(path: &Path) {
+    if let Err(e) = SearchIndex::global().and_then(|index| index.remove_session(path)) {
+        tracing::warn!(
+            "Failed to remove {} from search index: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::ToolCall;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn open(dir: &Path) -> SearchIndex {
+        SearchIndex::open(&dir.join(INDEX_FILE_NAME)).unwrap()
+    }
+
+    fn entry_count(index: &SearchIndex, path: &Path) -> i64 {
+        index
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM entries WHERE session_path = ?1",
+                params![path_key(path)],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_search_finds_text_tools_and_paths() {
+        let dir = tempdir().unwrap();
+        let index = open(dir.path());
+        let path = dir.path().join("20250101_120000.jsonl");
+        let messages = Conversation::new_unvalidated(vec![
+            Message::user().with_text("fix the flaky retry test"),
+            Message::assistant().with_tool_request(
+                "1",
+                Ok(ToolCall::new(
+                    "developer__text_editor",
+                    json!({"command": "view", "path": "/repo/src/retry.rs"}),
+                )),
+            ),
+        ]);
+        index.index_session(&path, &messages).unwrap();
+
+        let hits = index.search("flaky retry", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session, "20250101_120000");
+        assert_eq!(hits[0].message_index, 0);
+        assert!(hits[0].snippet.contains("[flaky]"));
+
+        assert_eq!(index.search("text_editor", 10).unwrap()[0].message_index, 1);
+        assert_eq!(index.search("retry.rs", 10).unwrap()[0].message_index, 1);
+        assert!(index.search("deploy", 10).unwrap().is_empty());
+        // Query syntax is searched for, not parsed
+        assert!(index.search("\"unbalanced AND (", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_is_incremental_and_resets_on_compaction() {
+        let dir = tempdir().unwrap();
+        let index = open(dir.path());
+        let path = dir.path().join("session.jsonl");
+        let mut messages = vec![
+            Message::user().with_text("first question"),
+            Message::assistant().with_text("first answer"),
+        ];
+        index
+            .index_session(&path, &Conversation::new_unvalidated(messages.clone()))
+            .unwrap();
+        messages.push(Message::user().with_text("second question"));
+        index
+            .index_session(&path, &Conversation::new_unvalidated(messages))
+            .unwrap();
+        assert_eq!(entry_count(&index, &path), 3);
+        assert_eq!(index.search("question", 10).unwrap().len(), 1);
+
+        let compacted = Conversation::new_unvalidated(vec![Message::user().with_text("summary")]);
+        index.index_session(&path, &compacted).unwrap();
+        assert_eq!(entry_count(&index, &path), 1);
+        assert!(index.search("question", 10).unwrap().is_empty());
+
+        index.remove_session(&path).unwrap();
+        assert!(index.search("summary", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge_removes_everything() {
+        let dir = tempdir().unwrap();
+        let index = open(dir.path());
+        let path = dir.path().join("session.jsonl");
+        let messages = Conversation::new_unvalidated(vec![Message::user().with_text("secret")]);
+        index.index_session(&path, &messages).unwrap();
+
+        index.purge().unwrap();
+        assert_eq!(entry_count(&index, &path), 0);
+        assert!(index.search("secret", 10).unwrap().is_empty());
+        // Purging an empty index is a no-op
+        index.purge().unwrap();
+    }
+}
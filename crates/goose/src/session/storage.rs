@@ -8,12 +8,18 @@
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
 use crate::providers::base::Provider;
+use crate::session::diff::ContextSnapshot;
+use crate::session::encryption::{self, LineDecryptor, LinePosition, SessionCipher};
+use crate::session::events::{event_log_path, SessionEventLog};
+use crate::session::usage::UsageLedger;
+use crate::session::{search, store, title};
 use crate::utils::safe_truncate;
 use anyhow::Result;
 use chrono::Local;
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self, BufRead, Write};
 use std::ops::DerefMut;
@@ -47,6 +53,9 @@ pub struct SessionMetadata {
     pub working_dir: PathBuf,
     /// A short description of the session, typically 3 words or less
     pub description: String,
+    /// A title generated once the session has a couple of turns
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
     /// ID of the schedule that triggered this session, if any
     pub schedule_id: Option<String>,
     /// ID of the project this session belongs to, if any
@@ -65,6 +74,41 @@ pub struct SessionMetadata {
     pub accumulated_input_tokens: Option<i32>,
     /// The number of output tokens used in the session. Accumulated across all messages.
     pub accumulated_output_tokens: Option<i32>,
+    /// Tokens used in the session by each model that served it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_usage: BTreeMap<String, ModelUsage>,
+    /// Tokens used by each provider response in the session
+    #[serde(default, skip_serializing_if = "UsageLedger::is_empty")]
+    pub usage_ledger: UsageLedger,
+    /// The context the model was last given, for comparing sessions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextSnapshot>,
+    /// Tags the user organizes sessions by, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Key/value metadata the user attached to the session
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Tokens one model used over a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ModelUsage {
+    pub requests: u32,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Input tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cache_read_tokens: i64,
+}
+
+impl ModelUsage {
+    pub fn add(&mut self, usage: &crate::providers::base::Usage) {
+        self.requests += 1;
+        self.input_tokens += usage.input_tokens.unwrap_or(0) as i64;
+        self.output_tokens += usage.output_tokens.unwrap_or(0) as i64;
+        self.cache_read_tokens += usage.cache_read_tokens.unwrap_or(0) as i64;
+    }
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -76,6 +120,8 @@ impl<'de> Deserialize<'de> for SessionMetadata {
         #[derive(Deserialize)]
         struct Helper {
             description: String,
+            #[serde(default)]
+            title: String,
             message_count: usize,
             schedule_id: Option<String>, // For backward compatibility
             project_id: Option<String>,  // For backward compatibility
@@ -86,6 +132,16 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_input_tokens: Option<i32>,
             accumulated_output_tokens: Option<i32>,
             working_dir: Option<PathBuf>,
+            #[serde(default)]
+            model_usage: BTreeMap<String, ModelUsage>,
+            #[serde(default)]
+            usage_ledger: UsageLedger,
+            #[serde(default)]
+            context: Option<ContextSnapshot>,
+            #[serde(default)]
+            tags: Vec<String>,
+            #[serde(default)]
+            properties: BTreeMap<String, String>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -98,6 +154,7 @@ impl<'de> Deserialize<'de> for SessionMetadata {
 
         Ok(SessionMetadata {
             description: helper.description,
+            title: helper.title,
             message_count: helper.message_count,
             schedule_id: helper.schedule_id,
             project_id: helper.project_id,
@@ -107,6 +164,11 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: helper.accumulated_total_tokens,
             accumulated_input_tokens: helper.accumulated_input_tokens,
             accumulated_output_tokens: helper.accumulated_output_tokens,
+            model_usage: helper.model_usage,
+            usage_ledger: helper.usage_ledger,
+            context: helper.context,
+            tags: helper.tags,
+            properties: helper.properties,
             working_dir,
         })
     }
@@ -124,6 +186,7 @@ impl SessionMetadata {
         Self {
             working_dir,
             description: String::new(),
+            title: String::new(),
             schedule_id: None,
             project_id: None,
             message_count: 0,
@@ -133,8 +196,49 @@ impl SessionMetadata {
             accumulated_total_tokens: None,
             accumulated_input_tokens: None,
             accumulated_output_tokens: None,
+            model_usage: BTreeMap::new(),
+            usage_ledger: UsageLedger::default(),
+            context: None,
+            tags: Vec::new(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    /// What to call the session in lists: its title, or its description until it has one
+    pub fn display_name(&self) -> &str {
+        if self.title.is_empty() {
+            &self.description
+        } else {
+            &self.title
         }
     }
+
+    /// Add a tag, returning whether the session didn't have it yet
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.trim();
+        match self.tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+            Ok(_) => false,
+            Err(index) => {
+                self.tags.insert(index, tag.to_string());
+                true
+            }
+        }
+    }
+
+    /// Remove a tag, returning whether the session had it
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| t != tag.trim());
+        self.tags.len() != before
+    }
+
+    /// Whether the session has every tag and property value in the filter
+    pub fn matches(&self, tags: &[String], properties: &[(String, String)]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+            && properties
+                .iter()
+                .all(|(key, value)| self.properties.get(key) == Some(value))
+    }
 }
 
 impl Default for SessionMetadata {
@@ -340,6 +444,16 @@ pub fn ensure_session_dir() -> Result<PathBuf> {
 
 /// Get the path to the most recently modified session file
 pub fn get_most_recent_session() -> Result<PathBuf> {
+    if let Some(store) = store::active()? {
+        if let Some(session) = store
+            .list_sessions()?
+            .into_iter()
+            .max_by_key(|session| session.updated_at)
+        {
+            return Ok(session.path);
+        }
+    }
+
     let session_dir = ensure_session_dir()?;
     let mut entries = fs::read_dir(&session_dir)?
         .filter_map(|entry| entry.ok())
@@ -366,9 +480,12 @@ pub fn get_most_recent_session() -> Result<PathBuf> {
 }
 
 /// List all available session files
+///
+/// With a session store configured this includes stored sessions as well as local files that
+/// have not been saved to the store yet.
 pub fn list_sessions() -> Result<Vec<(String, PathBuf)>> {
     let session_dir = ensure_session_dir()?;
-    let entries = fs::read_dir(&session_dir)?
+    let mut entries = fs::read_dir(&session_dir)?
         .filter_map(|entry| {
             let entry = entry.ok()?;
             let path = entry.path();
@@ -382,9 +499,55 @@ pub fn list_sessions() -> Result<Vec<(String, PathBuf)>> {
         })
         .collect::<Vec<_>>();
 
+    if let Some(store) = store::active()? {
+        let stored = store.list_sessions()?;
+        entries.retain(|(_, path)| !stored.iter().any(|s| &s.path == path));
+        entries.extend(stored.into_iter().map(|s| (s.name, s.path)));
+    }
+
     Ok(entries)
 }
 
+/// Whether a session has been saved, either to the session store or as a file
+pub fn session_exists(session_file: &Path) -> bool {
+    let stored = store::active()
+        .and_then(|store| match store {
+            Some(store) => store.contains(session_file),
+            None => Ok(false),
+        })
+        .unwrap_or(false);
+    stored || session_file.exists()
+}
+
+/// When a session was last saved
+pub fn get_modified_time(session_file: &Path) -> Result<std::time::SystemTime> {
+    if let Some(store) = store::active()? {
+        if let Some(updated_at) = store.updated_at(session_file)? {
+            return Ok(
+                std::time::UNIX_EPOCH + std::time::Duration::from_millis(updated_at.max(0) as u64)
+            );
+        }
+    }
+    Ok(fs::metadata(session_file)?.modified()?)
+}
+
+/// Delete a session from the session store and remove its file
+pub fn delete_session(session_file: &Path) -> Result<()> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    if let Some(store) = store::active()? {
+        store.delete_session(&secure_path)?;
+    }
+    if secure_path.exists() {
+        fs::remove_file(&secure_path)?;
+    }
+    let events_path = event_log_path(&secure_path);
+    if events_path.exists() {
+        fs::remove_file(&events_path)?;
+    }
+    search::unindex_deleted_session(&secure_path);
+    Ok(())
+}
+
 /// Generate a session ID using timestamp format (yyyymmdd_hhmmss)
 pub fn generate_session_id() -> String {
     Local::now().format("%Y%m%d_%H%M%S").to_string()
@@ -404,6 +567,28 @@ pub fn read_messages(session_file: &Path) -> Result<Conversation> {
     // Validate the path for security
     let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
 
+    if let Some(store) = store::active()? {
+        if let Some(messages) = store.load_messages(&secure_path)? {
+            return Ok(messages);
+        }
+        if !secure_path.exists() {
+            return Ok(Conversation::empty());
+        }
+    }
+
+    // The event log is the session's record; the messages in the session file are projected from it
+    let events_path = event_log_path(&secure_path);
+    if events_path.exists() {
+        let log = SessionEventLog::load(&events_path)?;
+        if !log.events().is_empty() {
+            let mut messages = log.conversation().messages().clone();
+            for message in &mut messages {
+                truncate_message_content_in_place(message, 50000);
+            }
+            return Ok(Conversation::new_unvalidated(messages));
+        }
+    }
+
     let result = read_messages_with_truncation(&secure_path, Some(50000)); // 50KB limit per message content
     match &result {
         Ok(_messages) => {}
@@ -466,6 +651,7 @@ pub fn read_messages_with_truncation(
 
     let reader = io::BufReader::new(file);
     let mut lines = reader.lines();
+    let mut decryptor = LineDecryptor::for_file(session_file);
     let mut messages = Vec::new();
     let mut corrupted_lines = Vec::new();
     let mut line_number = 1;
@@ -475,6 +661,9 @@ pub fn read_messages_with_truncation(
     if let Some(line_result) = lines.next() {
         match line_result {
             Ok(line) => {
+                // A line that can't be decrypted isn't corrupt, so fail rather than recover
+                let line = decryptor.decrypt(line)?;
+
                 // Security check: line length
                 if line.len() > MAX_LINE_LENGTH {
                     tracing::warn!("Line {} exceeds length limit", line_number);
@@ -518,6 +707,7 @@ pub fn read_messages_with_truncation(
                 }
             }
             Err(e) => {
+                decryptor.skip();
                 println!("[SESSION] Failed to read first line: {}", e);
                 tracing::error!("Failed to read first line: {}", e);
                 corrupted_lines.push((line_number, "[Unreadable line]".to_string()));
@@ -540,6 +730,8 @@ pub fn read_messages_with_truncation(
 
         match line_result {
             Ok(line) => {
+                let line = decryptor.decrypt(line)?;
+
                 // Security check: line length
                 if line.len() > MAX_LINE_LENGTH {
                     tracing::warn!("Line {} exceeds length limit", line_number);
@@ -586,6 +778,7 @@ pub fn read_messages_with_truncation(
                 }
             }
             Err(e) => {
+                decryptor.skip();
                 println!("[SESSION] Failed to read line {}: {}", line_number, e);
                 tracing::error!("Failed to read line {}: {}", line_number, e);
                 corrupted_lines.push((line_number, "[Unreadable line]".to_string()));
@@ -1002,6 +1195,12 @@ pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
     // Validate the path for security
     let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
 
+    if let Some(store) = store::active()? {
+        if let Some(metadata) = store.load_metadata(&secure_path)? {
+            return Ok(metadata);
+        }
+    }
+
     if !secure_path.exists() {
         return Ok(SessionMetadata::default());
     }
@@ -1022,6 +1221,8 @@ pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
 
     // Read just the first line
     if reader.read_line(&mut first_line)? > 0 {
+        let first_line = LineDecryptor::for_file(&secure_path).decrypt(first_line)?;
+
         // Security check: line length
         if first_line.len() > MAX_LINE_LENGTH {
             tracing::warn!("Metadata line exceeds length limit");
@@ -1105,7 +1306,7 @@ pub async fn persist_messages_with_schedule_id(
         }
         _ => {
             // Read existing metadata or create new with proper working_dir
-            let mut metadata = if secure_path.exists() {
+            let mut metadata = if session_exists(&secure_path) {
                 read_metadata(&secure_path)?
             } else {
                 // Create new metadata with the provided working_dir or fall back to home
@@ -1129,8 +1330,42 @@ pub async fn persist_messages_with_schedule_id(
     }
 }
 
+/// Copy a session's metadata and `messages` into a new session file next to it, leaving the
+/// original untouched. The fork starts with no token usage. Returns the new session's path.
+pub fn fork_session(session_file: &Path, messages: &Conversation) -> Result<PathBuf> {
+    let stem = session_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("session");
+    let fork_file =
+        session_file.with_file_name(format!("{}_fork_{}.jsonl", stem, generate_session_id()));
+    if fork_file.exists() {
+        return Err(anyhow::anyhow!(
+            "Session {} was already forked this second, try again",
+            stem
+        ));
+    }
+
+    let original = read_metadata(session_file)?;
+    let mut metadata = SessionMetadata::new(original.working_dir);
+    metadata.description = if original.description.is_empty() {
+        format!("Fork of {}", stem)
+    } else {
+        format!("Fork of {}", original.description)
+    };
+    metadata.project_id = original.project_id;
+    metadata.message_count = messages.len();
+    metadata.context = original.context;
+
+    save_messages_with_metadata(&fork_file, &metadata, messages)?;
+    Ok(fork_file)
+}
+
 /// Write messages to a session file with the provided metadata using secure atomic operations
 ///
+/// The messages are recorded in the session's event log first, as the events that changed since
+/// the last save, and the file is written from the history the log projects.
+///
 /// This function uses atomic file operations to prevent corruption:
 /// 1. Writes to a temporary file first with secure permissions
 /// 2. Uses fs2 file locking to prevent concurrent writes
@@ -1161,6 +1396,28 @@ pub fn save_messages_with_metadata(
         return Err(anyhow::anyhow!("Too many messages to save"));
     }
 
+    if let Some(store) = store::active()? {
+        store.save_session(&secure_path, metadata, messages)?;
+        search::index_saved_session(&secure_path, messages);
+        return Ok(());
+    }
+
+    // Record what changed in the event log first; the session file holds its projection
+    let events_path = event_log_path(&secure_path);
+    let mut event_log = SessionEventLog::load(&events_path)?;
+    event_log.record_history(messages.messages());
+    event_log.save(&events_path)?;
+    let messages = event_log.conversation();
+
+    let cipher = SessionCipher::for_writing()?;
+    let file = encryption::file_id(&secure_path);
+    let encode = |line: String, index: usize| -> Result<String> {
+        match &cipher {
+            Some(cipher) => cipher.encrypt_line(&line, LinePosition { file: &file, index }),
+            None => Ok(line),
+        }
+    };
+
     // Create a temporary file in the same directory to ensure atomic move
     let temp_file = secure_path.with_extension("tmp");
 
@@ -1206,19 +1463,19 @@ pub fn save_messages_with_metadata(
         let mut writer = io::BufWriter::new(&file);
 
         // Write metadata as the first line
-        serde_json::to_writer(&mut writer, &metadata).map_err(|e| {
+        let line = serde_json::to_string(&metadata).map_err(|e| {
             tracing::error!("Failed to serialize metadata: {}", e);
             anyhow::anyhow!("Failed to write session metadata")
         })?;
-        writeln!(writer)?;
+        writeln!(writer, "{}", encode(line, 0)?)?;
 
         // Write all messages with progress tracking
         for (i, message) in messages.iter().enumerate() {
-            serde_json::to_writer(&mut writer, &message).map_err(|e| {
+            let line = serde_json::to_string(&message).map_err(|e| {
                 tracing::error!("Failed to serialize message {}: {}", i, e);
                 anyhow::anyhow!("Failed to write session message")
             })?;
-            writeln!(writer)?;
+            writeln!(writer, "{}", encode(line, i + 1)?)?;
         }
 
         // Ensure all data is written to disk
@@ -1249,6 +1506,7 @@ pub fn save_messages_with_metadata(
     })?;
 
     tracing::debug!("Successfully saved session file: {:?}", secure_path);
+    search::index_saved_session(&secure_path, messages);
     Ok(())
 }
 
@@ -1296,6 +1554,7 @@ pub async fn generate_description_with_schedule_id(
     }
 
     // Use the provider's session naming capability
+    let provider = title::naming_provider(provider);
     let sanitized_description = provider
         .generate_session_name(messages)
         .await
@@ -1305,7 +1564,7 @@ pub async fn generate_description_with_schedule_id(
         })?;
 
     // Create metadata with proper working_dir or read existing and update
-    let mut metadata = if secure_path.exists() {
+    let mut metadata = if session_exists(&secure_path) {
         read_metadata(&secure_path)?
     } else {
         // Create new metadata with the provided working_dir or fall back to home
@@ -1315,6 +1574,17 @@ pub async fn generate_description_with_schedule_id(
 
     // Update description and schedule_id
     metadata.description = sanitized_description;
+    let user_message_count = messages
+        .iter()
+        .filter(|m| m.role == rmcp::model::Role::User && !m.as_concat_text().trim().is_empty())
+        .count();
+    if title::needs_title(&metadata, user_message_count) {
+        // A missing title is tried again on the next save, so a failure doesn't stop this one
+        match title::generate_title(provider.as_ref(), messages).await {
+            Ok(title) => metadata.title = title,
+            Err(e) => tracing::warn!("Failed to generate session title: {}", e),
+        }
+    }
     if schedule_id.is_some() {
         metadata.schedule_id = schedule_id;
     }
@@ -1350,6 +1620,34 @@ mod tests {
     use crate::conversation::message::{Message, MessageContent};
     use tempfile::tempdir;
 
+    #[test]
+    fn test_fork_session_leaves_original_untouched() -> Result<()> {
+        let dir = tempdir()?;
+        let session_file = dir.path().join("original.jsonl");
+        let messages = Conversation::new_unvalidated(vec![
+            Message::user().with_text("first question"),
+            Message::assistant().with_text("first answer"),
+        ]);
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        metadata.description = "Fix the build".to_string();
+        metadata.accumulated_total_tokens = Some(500);
+        save_messages_with_metadata(&session_file, &metadata, &messages)?;
+
+        let forked = Conversation::new_unvalidated(messages.messages()[..1].to_vec());
+        let fork_file = fork_session(&session_file, &forked)?;
+
+        assert_eq!(fork_file.parent(), session_file.parent());
+        assert_ne!(fork_file, session_file);
+        assert_eq!(read_messages(&fork_file)?.len(), 1);
+        assert_eq!(read_messages(&session_file)?.len(), 2);
+
+        let fork_metadata = read_metadata(&fork_file)?;
+        assert_eq!(fork_metadata.description, "Fork of Fix the build");
+        assert_eq!(fork_metadata.message_count, 1);
+        assert_eq!(fork_metadata.accumulated_total_tokens, None);
+        Ok(())
+    }
+
     #[test]
     fn test_corruption_recovery() -> Result<()> {
         let test_cases = vec![
@@ -1459,6 +1757,27 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_saves_are_recorded_in_the_event_log() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("events.jsonl");
+
+        let mut messages = Conversation::new_unvalidated(vec![Message::user().with_text("Hello")]);
+        persist_messages(&file_path, &messages, None, None).await?;
+        messages.push(Message::assistant().with_text("Hi there"));
+        persist_messages(&file_path, &messages, None, None).await?;
+        persist_messages(&file_path, &messages, None, None).await?;
+
+        // Each save recorded only what it added
+        let log = SessionEventLog::load(&event_log_path(&file_path))?;
+        assert_eq!(log.events().len(), 2);
+        assert_eq!(read_messages(&file_path)?.len(), 2);
+
+        delete_session(&file_path)?;
+        assert!(!event_log_path(&file_path).exists());
+        Ok(())
+    }
+
     #[test]
     fn test_empty_file() -> Result<()> {
         let dir = tempdir()?;
@@ -1667,6 +1986,36 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_tags_and_properties_survive_saves() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("tagged.jsonl");
+        let messages = Conversation::new_unvalidated(vec![Message::user().with_text("test")]);
+
+        let mut metadata = SessionMetadata::default();
+        assert!(metadata.add_tag("release"));
+        assert!(metadata.add_tag("billing"));
+        assert!(!metadata.add_tag("release"));
+        metadata
+            .properties
+            .insert("ticket".to_string(), "PAY-123".to_string());
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+
+        // Saving the conversation again keeps what the user attached
+        persist_messages(&file_path, &messages, None, None).await?;
+        let mut read = read_metadata(&file_path)?;
+        assert_eq!(read.tags, vec!["billing", "release"]);
+        assert!(read.matches(
+            &["release".to_string()],
+            &[("ticket".to_string(), "PAY-123".to_string())]
+        ));
+        assert!(!read.matches(&["hotfix".to_string()], &[]));
+
+        assert!(read.remove_tag("billing"));
+        assert!(!read.remove_tag("billing"));
+        Ok(())
+    }
+
     #[test]
     fn test_invalid_working_dir() -> Result<()> {
         let dir = tempdir()?;
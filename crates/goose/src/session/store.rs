@@ -0,0 +1,345 @@
+//! Pluggable storage for sessions.
+//!
+//! By default sessions are JSONL files in the local sessions directory. `GOOSE_SESSION_STORAGE`
+//! selects a [`SessionStore`] instead:
+//!
+//! - `sqlite`: a local database, see [`SessionDatabase`]
+//! - `postgres`: a shared database at `GOOSE_SESSION_POSTGRES_URL`, see `PostgresStore`
+//! - `s3`: one object per session in `GOOSE_SESSION_S3_BUCKET`, see `S3Store`
+//!
+//! The remote backends let several goosed replicas serve the same sessions, and let users pick
+//! up their session history on another machine. They are built with the `postgres-sessions`
+//! and `s3-sessions` features.
+
+use crate::config::Config;
+use crate::conversation::Conversation;
+use crate::session::database::SessionDatabase;
+#[cfg(feature = "postgres-sessions")]
+use crate::session::postgres::PostgresStore;
+#[cfg(feature = "s3-sessions")]
+use crate::session::s3::S3Store;
+use crate::session::storage::{self, ensure_session_dir, SessionMetadata};
+use anyhow::{bail, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Config key selecting the session storage backend
+pub const SESSION_STORAGE_KEY: &str = "GOOSE_SESSION_STORAGE";
+
+/// A session as listed from a store
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredSession {
+    pub name: String,
+    pub path: PathBuf,
+    /// Unix timestamp in milliseconds of the last save
+    pub updated_at: i64,
+}
+
+/// Where sessions are kept.
+///
+/// Sessions are identified by the path of their JSONL file in the sessions directory, which is
+/// how the rest of goose refers to them. Stores that are shared between machines key sessions by
+/// name (the file stem) so the same session resolves on every machine.
+pub trait SessionStore: Send + Sync {
+    /// Replace a session's metadata and messages as a whole
+    fn save_session(
+        &self,
+        path: &Path,
+        metadata: &SessionMetadata,
+        messages: &Conversation,
+    ) -> Result<()>;
+
+    /// The session's messages in order, or `None` if it is not stored
+    fn load_messages(&self, path: &Path) -> Result<Option<Conversation>>;
+
+    /// The session's metadata, or `None` if it is not stored
+    fn load_metadata(&self, path: &Path) -> Result<Option<SessionMetadata>>;
+
+    /// When the session was last saved, in Unix milliseconds
+    fn updated_at(&self, path: &Path) -> Result<Option<i64>>;
+
+    /// All stored sessions
+    fn list_sessions(&self) -> Result<Vec<StoredSession>>;
+
+    /// Remove a session
+    fn delete_session(&self, path: &Path) -> Result<()>;
+
+    /// Whether the session is stored
+    fn contains(&self, path: &Path) -> Result<bool> {
+        Ok(self.updated_at(path)?.is_some())
+    }
+}
+
+/// The available session storage backends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Jsonl,
+    Sqlite,
+    Postgres,
+    S3,
+}
+
+impl StorageBackend {
+    /// Read the backend from `GOOSE_SESSION_STORAGE`, defaulting to JSONL files
+    pub fn from_config(config: &Config) -> Self {
+        let Ok(name) = config.get_param::<String>(SESSION_STORAGE_KEY) else {
+            return Self::default();
+        };
+        match name.to_lowercase().as_str() {
+            "jsonl" => Self::Jsonl,
+            "sqlite" => Self::Sqlite,
+            "postgres" | "postgresql" => Self::Postgres,
+            "s3" => Self::S3,
+            other => {
+                tracing::warn!(
+                    "Unknown {} '{}', storing sessions as JSONL files",
+                    SESSION_STORAGE_KEY,
+                    other
+                );
+                Self::default()
+            }
+        }
+    }
+}
+
+static REMOTE_STORE: OnceCell<Box<dyn SessionStore>> = OnceCell::new();
+
+/// The configured session store, or `None` when sessions are kept as JSONL files
+pub fn active() -> Result<Option<&'static dyn SessionStore>> {
+    match StorageBackend::from_config(Config::global()) {
+        StorageBackend::Jsonl => Ok(None),
+        StorageBackend::Sqlite => Ok(Some(SessionDatabase::global()?)),
+        backend => {
+            let store = REMOTE_STORE.get_or_try_init(|| remote_store(backend))?;
+            Ok(Some(store.as_ref()))
+        }
+    }
+}
+
+fn remote_store(backend: StorageBackend) -> Result<Box<dyn SessionStore>> {
+    match backend {
+        #[cfg(feature = "postgres-sessions")]
+        StorageBackend::Postgres => Ok(Box::new(PostgresStore::from_config()?)),
+        #[cfg(feature = "s3-sessions")]
+        StorageBackend::S3 => Ok(Box::new(S3Store::from_config()?)),
+        other => bail!(
+            "This goose was built without {:?} session storage; rebuild it with the {} feature",
+            other,
+            match other {
+                StorageBackend::Postgres => "postgres-sessions",
+                _ => "s3-sessions",
+            }
+        ),
+    }
+}
+
+/// What moving session files into a store did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationReport {
+    pub migrated: usize,
+    /// Files of sessions the store already had, left as they are
+    pub skipped: usize,
+    /// Files that could not be read or stored, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Copy every JSONL session file in `session_dir` into `store`, deleting each file once it is
+/// stored if `remove_files` is set.
+///
+/// Sessions the store already has are skipped: once a session is in the store, that copy is the
+/// one goose reads and saves, so its file is stale. A file that fails to migrate is reported and
+/// kept, and doesn't stop the others.
+pub fn migrate_files(
+    store: &dyn SessionStore,
+    session_dir: &Path,
+    remove_files: bool,
+) -> Result<MigrationReport> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(session_dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    files.sort();
+
+    let mut report = MigrationReport::default();
+    for path in files {
+        let migrate = || -> Result<bool> {
+            if store.contains(&path)? {
+                return Ok(false);
+            }
+            let metadata = storage::read_metadata(&path)?;
+            let messages = storage::read_messages_with_truncation(&path, None)?;
+            store.save_session(&path, &metadata, &messages)?;
+            Ok(true)
+        };
+        match migrate() {
+            Ok(true) => {
+                report.migrated += 1;
+                if remove_files {
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        tracing::warn!(
+                            "Migrated {} but could not remove it: {}",
+                            path.display(),
+                            e
+                        );
+                    }
+                }
+            }
+            Ok(false) => report.skipped += 1,
+            Err(e) => report.failed.push((path, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// The name a session is stored under in shared stores
+#[cfg_attr(
+    not(any(feature = "postgres-sessions", feature = "s3-sessions")),
+    allow(dead_code)
+)]
+pub(crate) fn session_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// The local path that stands for a session stored under `name`
+#[cfg_attr(
+    not(any(feature = "postgres-sessions", feature = "s3-sessions")),
+    allow(dead_code)
+)]
+pub(crate) fn session_path(name: &str) -> Result<PathBuf> {
+    Ok(ensure_session_dir()?.join(format!("{}.jsonl", name)))
+}
+
+/// Run an async storage call from the synchronous session API.
+///
+/// Inside a multi-threaded runtime (goosed, the CLI) the current worker is handed off while the
+/// call runs. A current-thread runtime can't hand off its only thread, so there the call runs
+/// on a temporary runtime in a thread of its own, as it does outside of any runtime.
+#[cfg_attr(
+    not(any(feature = "postgres-sessions", feature = "s3-sessions")),
+    allow(dead_code)
+)]
+pub(crate) fn block_on<F>(future: F) -> Result<F::Output>
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    let temporary = |future: F| -> Result<F::Output> {
+        Ok(tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(future))
+    };
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            Ok(tokio::task::block_in_place(|| handle.block_on(future)))
+        }
+        Ok(_) => std::thread::scope(|scope| {
+            scope
+                .spawn(|| temporary(future))
+                .join()
+                .unwrap_or_else(|_| bail!("The session storage call panicked"))
+        }),
+        Err(_) => temporary(future),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::Message;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn test_backend_from_config() {
+        temp_env::with_var(SESSION_STORAGE_KEY, Some("Postgres"), || {
+            assert_eq!(
+                StorageBackend::from_config(Config::global()),
+                StorageBackend::Postgres
+            );
+        });
+        temp_env::with_var(SESSION_STORAGE_KEY, Some("s3"), || {
+            assert_eq!(
+                StorageBackend::from_config(Config::global()),
+                StorageBackend::S3
+            );
+        });
+        temp_env::with_var(SESSION_STORAGE_KEY, Some("floppy"), || {
+            assert_eq!(
+                StorageBackend::from_config(Config::global()),
+                StorageBackend::Jsonl
+            );
+        });
+        temp_env::with_var(SESSION_STORAGE_KEY, None::<&str>, || {
+            assert_eq!(
+                StorageBackend::from_config(Config::global()),
+                StorageBackend::Jsonl
+            );
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_migrate_files_into_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SessionDatabase::open(&dir.path().join("goose.db")).unwrap();
+        let sessions = dir.path().join("sessions");
+        std::fs::create_dir_all(&sessions).unwrap();
+
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        metadata.description = "from a file".to_string();
+        let lines = [
+            serde_json::to_string(&metadata).unwrap(),
+            serde_json::to_string(&Message::user().with_text("hello")).unwrap(),
+            serde_json::to_string(&Message::assistant().with_text("hi")).unwrap(),
+        ];
+        let file = sessions.join("20250101_120000.jsonl");
+        std::fs::write(&file, lines.join("\n") + "\n").unwrap();
+        let stored = sessions.join("20250102_120000.jsonl");
+        std::fs::write(&stored, "").unwrap();
+        db.save_session(&stored, &metadata, &Conversation::empty())
+            .unwrap();
+
+        let report = migrate_files(&db, &sessions, true).unwrap();
+        assert_eq!(report.migrated, 1);
+        assert_eq!(report.skipped, 1);
+        assert!(report.failed.is_empty());
+        assert!(!file.exists());
+        assert!(stored.exists());
+        assert_eq!(db.load_messages(&file).unwrap().unwrap().len(), 2);
+        assert_eq!(
+            db.load_metadata(&file).unwrap().unwrap().description,
+            "from a file"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_block_on_in_a_current_thread_runtime() {
+        assert_eq!(block_on(async { 1 + 1 }).unwrap(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_on_in_a_multi_thread_runtime() {
+        assert_eq!(block_on(async { 1 + 1 }).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_block_on_outside_a_runtime() {
+        assert_eq!(block_on(async { 1 + 1 }).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_session_name() {
+        assert_eq!(
+            session_name(Path::new("/data/sessions/20250101_120000.jsonl")),
+            "20250101_120000"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Generated session titles.
+//!
+//! Once a session has a couple of turns, a short title is generated from its opening and kept in
+//! its metadata, so `session list` and the session pickers can say what a session was about rather
+//! than only when it started. Naming doesn't need the session's own model: with
+//! `GOOSE_SESSION_TITLE_MODEL` set, titles and descriptions come from that model of the configured
+//! provider instead.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::model::ModelConfig;
+use crate::providers::base::Provider;
+use crate::session::storage::SessionMetadata;
+use crate::utils::safe_truncate;
+
+/// Config key for the model sessions are named with
+pub const SESSION_TITLE_MODEL_KEY: &str = "GOOSE_SESSION_TITLE_MODEL";
+/// User messages a session needs before it is titled
+pub const TITLE_AFTER_USER_MESSAGES: usize = 2;
+
+const MAX_TITLE_LEN: usize = 80;
+/// How much of each message the title is generated from
+const MAX_EXCERPT_LEN: usize = 500;
+const TITLE_SYSTEM_PROMPT: &str = "Reply with only a title of eight words or less";
+
+/// The provider to name sessions with: the configured title model, or else the session's own
+pub fn naming_provider(session_provider: Arc<dyn Provider>) -> Arc<dyn Provider> {
+    let config = Config::global();
+    let model = match config.get_param::<String>(SESSION_TITLE_MODEL_KEY) {
+        Ok(model) if !model.trim().is_empty() => model,
+        _ => return session_provider,
+    };
+    let provider = config
+        .get_param::<String>("GOOSE_PROVIDER")
+        .map_err(anyhow::Error::from)
+        .and_then(|provider_name| {
+            crate::providers::create(&provider_name, ModelConfig::new(model.trim())?)
+        });
+    match provider {
+        Ok(provider) => provider,
+        Err(e) => {
+            tracing::warn!(
+                "Naming sessions with the session's model, {} is unusable: {}",
+                SESSION_TITLE_MODEL_KEY,
+                e
+            );
+            session_provider
+        }
+    }
+}
+
+/// Whether a session should be titled now that it has this many user messages
+pub fn needs_title(metadata: &SessionMetadata, user_messages: usize) -> bool {
+    metadata.title.is_empty() && user_messages >= TITLE_AFTER_USER_MESSAGES
+}
+
+/// Ask the provider for a title describing the conversation so far
+pub async fn generate_title(provider: &dyn Provider, messages: &Conversation) -> Result<String> {
+    let excerpt = messages
+        .iter()
+        .filter(|message| !message.is_tool_response())
+        .map(|message| (message.role.clone(), message.as_concat_text()))
+        .filter(|(_, text)| !text.trim().is_empty())
+        .take(4)
+        .map(|(role, text)| {
+            let speaker = match role {
+                rmcp::model::Role::User => "User",
+                rmcp::model::Role::Assistant => "Assistant",
+            };
+            format!(
+                "{}: {}",
+                speaker,
+                safe_truncate(text.trim(), MAX_EXCERPT_LEN)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "Here is the start of a conversation:\n{}\n\nWrite a short, specific title for it, in eight words or less, that would help find it again in a list of sessions. Reply *ONLY* with the title.",
+        excerpt
+    );
+
+    let (message, _) = provider
+        .complete(
+            TITLE_SYSTEM_PROMPT,
+            &[Message::user().with_text(prompt)],
+            &[],
+        )
+        .await?;
+    let title = clean_title(&message.as_concat_text());
+    if title.is_empty() {
+        return Err(anyhow::anyhow!("The model returned an empty title"));
+    }
+    Ok(title)
+}
+
+/// Reduce a model's reply to a one-line title, without the quotes and labels models add
+fn clean_title(reply: &str) -> String {
+    let line = reply
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let decoration = |c: char| matches!(c, '"' | '\'' | '`' | '*' | '#' | '.');
+    let line = line.trim_matches(decoration);
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = line.trim().trim_matches(decoration).trim();
+    safe_truncate(title, MAX_TITLE_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_titles_are_cleaned_and_wait_for_turns() {
+        assert_eq!(
+            clean_title("\n**Title: \"Fix flaky scheduler tests.\"**\nMore text"),
+            "Fix flaky scheduler tests"
+        );
+        assert_eq!(
+            clean_title("  `Migrate to Postgres`  "),
+            "Migrate to Postgres"
+        );
+        assert_eq!(clean_title("\n\n"), "");
+
+        let mut metadata = SessionMetadata::new(std::env::temp_dir());
+        assert!(!needs_title(&metadata, 1));
+        assert!(needs_title(&metadata, TITLE_AFTER_USER_MESSAGES));
+        metadata.title = "Fix flaky scheduler tests".to_string();
+        assert!(!needs_title(&metadata, 3));
+    }
+}
@@ -0,0 +1,175 @@
+//! Token usage for each provider response in a session, kept with the session so its cost can
+//! be totalled and broken down by model afterwards.
+
+use crate::config::Config;
+use crate::providers::base::ProviderUsage;
+use crate::session::report::{model_spend, ModelSpend};
+use crate::session::storage::{ModelUsage, SessionMetadata};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// The tokens one provider response used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UsageEntry {
+    pub timestamp: DateTime<Utc>,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Input tokens read from the provider's prompt cache, included in `input_tokens`
+    #[serde(default)]
+    pub cache_read_tokens: i64,
+    /// Input tokens written to the provider's prompt cache, included in `input_tokens`
+    #[serde(default)]
+    pub cache_write_tokens: i64,
+}
+
+/// Every provider response in a session, in order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
+pub struct UsageLedger {
+    pub entries: Vec<UsageEntry>,
+}
+
+/// Tokens summed over a ledger
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct UsageTotals {
+    pub requests: usize,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+}
+
+impl UsageLedger {
+    pub fn record(&mut self, usage: &ProviderUsage) {
+        self.entries.push(UsageEntry {
+            timestamp: Utc::now(),
+            model: usage.model.clone(),
+            input_tokens: usage.usage.input_tokens.unwrap_or(0) as i64,
+            output_tokens: usage.usage.output_tokens.unwrap_or(0) as i64,
+            cache_read_tokens: usage.usage.cache_read_tokens.unwrap_or(0) as i64,
+            cache_write_tokens: usage.usage.cache_write_tokens.unwrap_or(0) as i64,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn totals(&self) -> UsageTotals {
+        self.entries
+            .iter()
+            .fold(UsageTotals::default(), |mut totals, entry| {
+                totals.requests += 1;
+                totals.input_tokens += entry.input_tokens;
+                totals.output_tokens += entry.output_tokens;
+                totals.cache_read_tokens += entry.cache_read_tokens;
+                totals.cache_write_tokens += entry.cache_write_tokens;
+                totals
+            })
+    }
+
+    pub fn by_model(&self) -> BTreeMap<String, ModelUsage> {
+        let mut models: BTreeMap<String, ModelUsage> = BTreeMap::new();
+        for entry in &self.entries {
+            let usage = models.entry(entry.model.clone()).or_default();
+            usage.requests += 1;
+            usage.input_tokens += entry.input_tokens;
+            usage.output_tokens += entry.output_tokens;
+            usage.cache_read_tokens += entry.cache_read_tokens;
+        }
+        models
+    }
+}
+
+/// What a session used and cost
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionCost {
+    pub totals: UsageTotals,
+    pub models: Vec<ModelSpend>,
+    /// None when no model in the session has a price
+    pub total_cost: Option<f64>,
+}
+
+impl SessionCost {
+    /// Price a session's usage. Sessions recorded before the ledger existed fall back to their
+    /// per-model totals.
+    pub async fn for_session(metadata: &SessionMetadata) -> Self {
+        let (totals, by_model) = if metadata.usage_ledger.is_empty() {
+            let by_model = metadata.model_usage.clone();
+            let totals = by_model
+                .values()
+                .fold(UsageTotals::default(), |mut totals, usage| {
+                    totals.requests += usage.requests as usize;
+                    totals.input_tokens += usage.input_tokens;
+                    totals.output_tokens += usage.output_tokens;
+                    totals.cache_read_tokens += usage.cache_read_tokens;
+                    totals
+                });
+            (totals, by_model)
+        } else {
+            (
+                metadata.usage_ledger.totals(),
+                metadata.usage_ledger.by_model(),
+            )
+        };
+
+        let provider = Config::global()
+            .get_param::<String>("GOOSE_PROVIDER")
+            .unwrap_or_default();
+        let models = model_spend(&provider, by_model).await;
+        let costs: Vec<f64> = models.iter().filter_map(|spend| spend.cost).collect();
+        Self {
+            totals,
+            total_cost: (!costs.is_empty()).then(|| costs.iter().sum()),
+            models,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    fn usage(model: &str, input: i32, output: i32, cache_read: Option<i32>) -> ProviderUsage {
+        ProviderUsage::new(
+            model.to_string(),
+            Usage {
+                cache_read_tokens: cache_read,
+                ..Usage::new(Some(input), Some(output), Some(input + output))
+            },
+        )
+    }
+
+    #[test]
+    fn test_ledger_totals_and_models() {
+        let mut ledger = UsageLedger::default();
+        ledger.record(&usage("gpt-4o", 1_000, 200, Some(600)));
+        ledger.record(&usage("gpt-4o", 1_500, 100, None));
+        ledger.record(&usage("gpt-4o-mini", 300, 50, None));
+
+        let totals = ledger.totals();
+        assert_eq!(totals.requests, 3);
+        assert_eq!(totals.input_tokens, 2_800);
+        assert_eq!(totals.output_tokens, 350);
+        assert_eq!(totals.cache_read_tokens, 600);
+
+        let models = ledger.by_model();
+        assert_eq!(models["gpt-4o"].requests, 2);
+        assert_eq!(models["gpt-4o"].input_tokens, 2_500);
+        assert_eq!(models["gpt-4o-mini"].output_tokens, 50);
+    }
+
+    #[test]
+    fn test_ledger_serializes_as_a_list() {
+        let mut ledger = UsageLedger::default();
+        ledger.record(&usage("gpt-4o", 10, 5, None));
+        let value = serde_json::to_value(&ledger).unwrap();
+        assert!(value.is_array());
+        let parsed: UsageLedger = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, ledger);
+    }
+}
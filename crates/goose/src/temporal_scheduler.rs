@@ -601,6 +601,7 @@ impl TemporalScheduler {
                         current_session_id: None, // Not provided by Temporal service
                         process_start_time: None, // Not provided by Temporal service
                         execution_mode: tj.execution_mode,
+                        timezone: None, // Not provided by Temporal service
                     }
                 })
                 .collect();
@@ -1,5 +1,6 @@
 use ahash::AHasher;
 use dashmap::DashMap;
+use once_cell::sync::Lazy;
 use rmcp::model::Tool;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
@@ -11,12 +12,79 @@ use crate::conversation::message::Message;
 // Global tokenizer instance to avoid repeated initialization
 static TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
 
+// Tokenizers already loaded for a model family, so each is read or downloaded once
+static MODEL_TOKENIZERS: Lazy<DashMap<TokenizerKind, Arc<dyn Tokenizer>>> = Lazy::new(DashMap::new);
+
 // Cache size limits to prevent unbounded growth
 const MAX_TOKEN_CACHE_SIZE: usize = 10_000;
 
+/// Something that can count the tokens a model sees for a piece of text
+pub trait Tokenizer: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+impl Tokenizer for CoreBPE {
+    fn count(&self, text: &str) -> usize {
+        self.encode_with_special_tokens(text).len()
+    }
+}
+
+/// The tokenizer families goose knows how to load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenizerKind {
+    /// tiktoken o200k_base: GPT-4o, GPT-4.1, GPT-5 and the o-series
+    O200kBase,
+    /// tiktoken cl100k_base: GPT-4, GPT-3.5 and the OpenAI embedding models
+    Cl100kBase,
+    /// A tokenizer.json from the given HuggingFace repository
+    HuggingFace(&'static str),
+}
+
+impl TokenizerKind {
+    /// Pick the tokenizer for a model from its name. Models from families we don't have a
+    /// tokenizer for use o200k_base, which is close enough for most modern models.
+    pub fn for_model(model_name: &str) -> Self {
+        let name = model_name.to_lowercase();
+        // Drop routing prefixes such as "openai/" or "meta-llama/"
+        let name = name.rsplit('/').next().unwrap_or(&name);
+
+        if name.contains("qwen") {
+            Self::HuggingFace("Qwen/Qwen2.5-7B-Instruct")
+        } else if name.contains("llama-3") || name.contains("llama3") || name.contains("llama-4") {
+            Self::HuggingFace("NousResearch/Meta-Llama-3-8B-Instruct")
+        } else if name.contains("llama") {
+            Self::HuggingFace("hf-internal-testing/llama-tokenizer")
+        } else if name.contains("gpt-4o")
+            || name.contains("gpt-4.1")
+            || name.contains("gpt-5")
+            || name.starts_with("o1")
+            || name.starts_with("o3")
+            || name.starts_with("o4")
+        {
+            Self::O200kBase
+        } else if name.contains("gpt-4")
+            || name.contains("gpt-3.5")
+            || name.starts_with("text-embedding")
+        {
+            Self::Cl100kBase
+        } else {
+            Self::O200kBase
+        }
+    }
+
+    /// A short name for logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::O200kBase => "o200k_base",
+            Self::Cl100kBase => "cl100k_base",
+            Self::HuggingFace(repo) => repo,
+        }
+    }
+}
+
 /// Async token counter with caching capabilities
 pub struct AsyncTokenCounter {
-    tokenizer: Arc<CoreBPE>,
+    tokenizer: Arc<dyn Tokenizer>,
     token_cache: Arc<DashMap<u64, usize>>, // content hash -> token count
 }
 
@@ -29,10 +97,20 @@ impl AsyncTokenCounter {
     /// Creates a new async token counter with caching
     pub async fn new() -> Result<Self, String> {
         let tokenizer = get_tokenizer().await?;
-        Ok(Self {
+        Ok(Self::with_tokenizer(tokenizer))
+    }
+
+    /// Creates a token counter using the tokenizer of the given model's family
+    pub async fn for_model(model_name: &str) -> Result<Self, String> {
+        let tokenizer = get_model_tokenizer(TokenizerKind::for_model(model_name)).await?;
+        Ok(Self::with_tokenizer(tokenizer))
+    }
+
+    fn with_tokenizer(tokenizer: Arc<dyn Tokenizer>) -> Self {
+        Self {
             tokenizer,
             token_cache: Arc::new(DashMap::new()),
-        })
+        }
     }
 
     /// Count tokens with optimized caching
@@ -48,8 +126,7 @@ impl AsyncTokenCounter {
         }
 
         // Compute and cache result with size management
-        let tokens = self.tokenizer.encode_with_special_tokens(text);
-        let count = tokens.len();
+        let count = self.tokenizer.count(text);
 
         // Manage cache size to prevent unbounded growth
         if self.token_cache.len() >= MAX_TOKEN_CACHE_SIZE {
@@ -372,11 +449,122 @@ fn get_tokenizer_blocking() -> Result<Arc<CoreBPE>, String> {
     }
 }
 
+/// Get the tokenizer for a model family, loading it on first use. HuggingFace tokenizers that
+/// can't be loaded (offline, gated repository) fall back to o200k_base.
+async fn get_model_tokenizer(kind: TokenizerKind) -> Result<Arc<dyn Tokenizer>, String> {
+    if let Some(tokenizer) = MODEL_TOKENIZERS.get(&kind) {
+        return Ok(tokenizer.clone());
+    }
+
+    let tokenizer: Arc<dyn Tokenizer> = match kind {
+        TokenizerKind::O200kBase => get_tokenizer().await?,
+        TokenizerKind::Cl100kBase => Arc::new(
+            tiktoken_rs::cl100k_base()
+                .map_err(|e| format!("Failed to initialize cl100k_base tokenizer: {}", e))?,
+        ),
+        TokenizerKind::HuggingFace(repo) => match huggingface::load(repo).await {
+            Ok(tokenizer) => tokenizer,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not load tokenizer from {}, counting with o200k_base: {:#}",
+                    repo,
+                    e
+                );
+                get_tokenizer().await?
+            }
+        },
+    };
+
+    MODEL_TOKENIZERS.insert(kind, tokenizer.clone());
+    Ok(tokenizer)
+}
+
+/// Tokenizers from the HuggingFace hub, for model families tiktoken doesn't cover
+#[cfg(feature = "hf-tokenizers")]
+mod huggingface {
+    use super::Tokenizer;
+    use anyhow::Context;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    const HUGGINGFACE_URL: &str = "https://huggingface.co";
+
+    impl Tokenizer for tokenizers::Tokenizer {
+        fn count(&self, text: &str) -> usize {
+            match self.encode(text, false) {
+                Ok(encoding) => encoding.len(),
+                // Same rough estimate used elsewhere when no tokenizer is available
+                Err(_) => text.len() / 4,
+            }
+        }
+    }
+
+    fn tokenizer_cache_path(repo: &str) -> anyhow::Result<PathBuf> {
+        let cache_dir = if let Ok(goose_dir) = std::env::var("GOOSE_CACHE_DIR") {
+            PathBuf::from(goose_dir)
+        } else {
+            dirs::cache_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not determine cache directory"))?
+                .join("goose")
+        };
+        Ok(cache_dir
+            .join("tokenizers")
+            .join(repo.replace('/', "--"))
+            .join("tokenizer.json"))
+    }
+
+    /// Load a tokenizer.json from the local cache, downloading it from the hub if needed
+    pub(super) async fn load(repo: &str) -> anyhow::Result<Arc<dyn Tokenizer>> {
+        let path = tokenizer_cache_path(repo)?;
+        if !path.exists() {
+            let url = format!("{}/{}/resolve/main/tokenizer.json", HUGGINGFACE_URL, repo);
+            let mut request = reqwest::Client::new().get(&url);
+            if let Ok(token) = std::env::var("HF_TOKEN") {
+                request = request.bearer_auth(token);
+            }
+            let bytes = request
+                .send()
+                .await?
+                .error_for_status()
+                .with_context(|| format!("Failed to download {}", url))?
+                .bytes()
+                .await?;
+
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            tokio::fs::write(&path, &bytes).await?;
+            tracing::debug!("Cached tokenizer for {} at {}", repo, path.display());
+        }
+
+        let tokenizer = tokenizers::Tokenizer::from_file(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read tokenizer {}: {}", path.display(), e))?;
+        Ok(Arc::new(tokenizer))
+    }
+}
+
+#[cfg(not(feature = "hf-tokenizers"))]
+mod huggingface {
+    use super::Tokenizer;
+    use std::sync::Arc;
+
+    pub(super) async fn load(_repo: &str) -> anyhow::Result<Arc<dyn Tokenizer>> {
+        anyhow::bail!("goose was built without the hf-tokenizers feature")
+    }
+}
+
 /// Factory function for creating async token counters with proper error handling
 pub async fn create_async_token_counter() -> Result<AsyncTokenCounter, String> {
     AsyncTokenCounter::new().await
 }
 
+/// Factory function for creating async token counters that match a model's tokenizer
+pub async fn create_async_token_counter_for_model(
+    model_name: &str,
+) -> Result<AsyncTokenCounter, String> {
+    AsyncTokenCounter::for_model(model_name).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -693,4 +881,58 @@ mod tests {
             "Longer text should have more tokens"
         );
     }
+
+    #[test]
+    fn test_tokenizer_kind_for_model() {
+        assert_eq!(TokenizerKind::for_model("gpt-4o"), TokenizerKind::O200kBase);
+        assert_eq!(
+            TokenizerKind::for_model("o3-mini"),
+            TokenizerKind::O200kBase
+        );
+        assert_eq!(
+            TokenizerKind::for_model("openai/gpt-4.1"),
+            TokenizerKind::O200kBase
+        );
+        assert_eq!(
+            TokenizerKind::for_model("gpt-4-turbo"),
+            TokenizerKind::Cl100kBase
+        );
+        assert_eq!(
+            TokenizerKind::for_model("gpt-3.5-turbo"),
+            TokenizerKind::Cl100kBase
+        );
+        assert_eq!(
+            TokenizerKind::for_model("meta-llama/Llama-3.3-70B-Instruct"),
+            TokenizerKind::HuggingFace("NousResearch/Meta-Llama-3-8B-Instruct")
+        );
+        assert_eq!(
+            TokenizerKind::for_model("qwen2.5-coder:32b"),
+            TokenizerKind::HuggingFace("Qwen/Qwen2.5-7B-Instruct")
+        );
+        assert_eq!(
+            TokenizerKind::for_model("claude-sonnet-4"),
+            TokenizerKind::O200kBase
+        );
+    }
+
+    #[tokio::test]
+    async fn test_counter_for_model_uses_family_encoding() {
+        // Text where o200k_base and cl100k_base disagree
+        let text = "Съешь же ещё этих мягких французских булок, да выпей чаю";
+
+        let gpt4 = create_async_token_counter_for_model("gpt-4").await.unwrap();
+        let gpt4o = create_async_token_counter_for_model("gpt-4o")
+            .await
+            .unwrap();
+
+        let cl100k = tiktoken_rs::cl100k_base().unwrap();
+        assert_eq!(
+            gpt4.count_tokens(text),
+            cl100k.encode_with_special_tokens(text).len()
+        );
+        assert_eq!(
+            gpt4o.count_tokens(text),
+            TokenCounter::new().count_tokens(text)
+        );
+    }
 }
@@ -1,5 +1,69 @@
+use crate::config::Config;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// The tool error returned for a call rejected as a repetition
+pub const REPETITION_REJECTED_MESSAGE: &str =
+    "Tool call rejected: exceeded maximum allowed repetitions";
+
+/// The tool error returned for an edit rejected for undoing earlier edits once too often
+pub const EDIT_OSCILLATION_REJECTED_MESSAGE: &str =
+    "Tool call rejected: the edit reverses an earlier edit to the same file too many times";
+
+/// Config key for how many identical tool calls in a row are allowed
+pub const MAX_TOOL_REPETITIONS_KEY: &str = "GOOSE_MAX_TOOL_REPETITIONS";
+/// Config key for how many times edits may undo earlier edits to the same file
+pub const MAX_EDIT_REVERSALS_KEY: &str = "GOOSE_MAX_EDIT_REVERSALS";
+
+/// Going back once is fixing a mistake, going back again is going in circles
+const DEFAULT_MAX_EDIT_REVERSALS: u32 = 1;
+
+const TEXT_EDITOR_TOOL_NAME: &str = "developer__text_editor";
+
+/// Why the monitor stopped the agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopDetected {
+    /// The same call, with the same arguments, over and over
+    Repetition { tool_name: String },
+    /// Edits to a file that keep undoing each other
+    OscillatingEdit { path: String },
+}
+
+impl LoopDetected {
+    /// The tool error for the rejected call
+    pub fn rejection(&self) -> &'static str {
+        match self {
+            LoopDetected::Repetition { .. } => REPETITION_REJECTED_MESSAGE,
+            LoopDetected::OscillatingEdit { .. } => EDIT_OSCILLATION_REJECTED_MESSAGE,
+        }
+    }
+
+    /// What the agent tells the user when it stops
+    pub fn stop_message(&self) -> String {
+        match self {
+            LoopDetected::Repetition { tool_name } => format!(
+                "I've stopped because I kept calling `{}` with the same arguments without making \
+                 progress. How would you like me to proceed?",
+                tool_name
+            ),
+            LoopDetected::OscillatingEdit { path } => format!(
+                "I've stopped because my edits to `{}` keep undoing each other. Which version \
+                 do you want, or how should I approach it differently?",
+                path
+            ),
+        }
+    }
+}
+
+/// One change to a file, as fingerprints of the text before and after
+#[derive(Debug, PartialEq, Eq)]
+struct Edit {
+    path: String,
+    from: u64,
+    to: u64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -20,36 +84,75 @@ impl ToolCall {
 #[derive(Debug)]
 pub struct ToolMonitor {
     max_repetitions: Option<u32>,
+    max_edit_reversals: Option<u32>,
     last_call: Option<ToolCall>,
     repeat_count: u32,
     call_counts: HashMap<String, u32>,
+    edits: Vec<Edit>,
+    written: HashMap<String, u64>,
+    reversals: HashMap<String, u32>,
+    detected: Option<LoopDetected>,
 }
 
 impl ToolMonitor {
     pub fn new(max_repetitions: Option<u32>) -> Self {
         Self {
             max_repetitions,
+            max_edit_reversals: None,
             last_call: None,
             repeat_count: 0,
             call_counts: HashMap::new(),
+            edits: Vec::new(),
+            written: HashMap::new(),
+            reversals: HashMap::new(),
+            detected: None,
         }
     }
 
-    pub fn check_tool_call(&mut self, tool_call: ToolCall) -> bool {
+    /// A monitor with the limits set in the config
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        Self::new(config.get_param(MAX_TOOL_REPETITIONS_KEY).ok()).with_max_edit_reversals(Some(
+            config
+                .get_param(MAX_EDIT_REVERSALS_KEY)
+                .unwrap_or(DEFAULT_MAX_EDIT_REVERSALS),
+        ))
+    }
+
+    pub fn with_max_repetitions(mut self, max_repetitions: Option<u32>) -> Self {
+        self.max_repetitions = max_repetitions;
+        self
+    }
+
+    pub fn with_max_edit_reversals(mut self, max_edit_reversals: Option<u32>) -> Self {
+        self.max_edit_reversals = max_edit_reversals;
+        self
+    }
+
+    pub fn check_tool_call(&mut self, tool_call: ToolCall) -> Result<(), LoopDetected> {
         let total_calls = self.call_counts.entry(tool_call.name.clone()).or_insert(0);
         *total_calls += 1;
 
+        if let Err(detected) = self.check_edit(&tool_call) {
+            self.detected = Some(detected.clone());
+            return Err(detected);
+        }
+
         if self.max_repetitions.is_none() {
             self.last_call = Some(tool_call);
             self.repeat_count = 1;
-            return true;
+            return Ok(());
         }
 
         if let Some(last) = &self.last_call {
             if last.matches(&tool_call) {
                 self.repeat_count += 1;
                 if self.repeat_count > self.max_repetitions.unwrap() {
-                    return false;
+                    let detected = LoopDetected::Repetition {
+                        tool_name: tool_call.name,
+                    };
+                    self.detected = Some(detected.clone());
+                    return Err(detected);
                 }
             } else {
                 self.repeat_count = 1;
@@ -59,7 +162,76 @@ impl ToolMonitor {
         }
 
         self.last_call = Some(tool_call);
-        true
+        Ok(())
+    }
+
+    /// Record a text editor change, failing once edits to a file have undone earlier ones
+    /// more often than allowed. A `str_replace` undoes one with its strings swapped; a `write`
+    /// undoes the write before it when it restores the content that write replaced.
+    fn check_edit(&mut self, tool_call: &ToolCall) -> Result<(), LoopDetected> {
+        let Some(max_reversals) = self.max_edit_reversals else {
+            return Ok(());
+        };
+        if tool_call.name != TEXT_EDITOR_TOOL_NAME {
+            return Ok(());
+        }
+        let argument = |key: &str| tool_call.parameters.get(key).and_then(|v| v.as_str());
+        let Some(path) = argument("path") else {
+            return Ok(());
+        };
+        let (from, to) = match argument("command") {
+            Some("str_replace") => match (argument("old_str"), argument("new_str")) {
+                (Some(old), Some(new)) => (fingerprint(old), fingerprint(new)),
+                _ => return Ok(()),
+            },
+            Some("write") => {
+                let Some(text) = argument("file_text") else {
+                    return Ok(());
+                };
+                let to = fingerprint(text);
+                match self.written.insert(path.to_string(), to) {
+                    Some(from) if from != to => (from, to),
+                    _ => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        let edit = Edit {
+            path: path.to_string(),
+            from,
+            to,
+        };
+        let reverses = self
+            .edits
+            .iter()
+            .any(|earlier| earlier.path == edit.path && earlier.from == to && earlier.to == from);
+        self.edits.push(edit);
+        if reverses {
+            let count = self.reversals.entry(path.to_string()).or_insert(0);
+            *count += 1;
+            if *count > max_reversals {
+                return Err(LoopDetected::OscillatingEdit {
+                    path: path.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The loop found since the last call, if any, so the agent can stop and ask the user
+    pub fn take_detected(&mut self) -> Option<LoopDetected> {
+        self.detected.take()
+    }
+
+    /// Forget the calls and edits of the previous request; the user has weighed in since
+    pub fn start_request(&mut self) {
+        self.last_call = None;
+        self.repeat_count = 0;
+        self.edits.clear();
+        self.written.clear();
+        self.reversals.clear();
+        self.detected = None;
     }
 
     pub fn get_stats(&self) -> HashMap<String, u32> {
@@ -67,8 +239,45 @@ impl ToolMonitor {
     }
 
     pub fn reset(&mut self) {
-        self.last_call = None;
-        self.repeat_count = 0;
+        self.start_request();
         self.call_counts.clear();
     }
 }
+
+fn fingerprint(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn replace(old: &str, new: &str) -> ToolCall {
+        ToolCall::new(
+            TEXT_EDITOR_TOOL_NAME.to_string(),
+            json!({"command": "str_replace", "path": "src/lib.rs", "old_str": old, "new_str": new}),
+        )
+    }
+
+    #[test]
+    fn test_oscillating_edits_are_detected() {
+        let mut monitor = ToolMonitor::new(None).with_max_edit_reversals(Some(1));
+        assert!(monitor.check_tool_call(replace("a", "b")).is_ok());
+        // Going back once is allowed
+        assert!(monitor.check_tool_call(replace("b", "a")).is_ok());
+        assert_eq!(monitor.take_detected(), None);
+        assert_eq!(
+            monitor.check_tool_call(replace("a", "b")),
+            Err(LoopDetected::OscillatingEdit {
+                path: "src/lib.rs".to_string()
+            })
+        );
+        assert!(monitor.take_detected().is_some());
+
+        monitor.start_request();
+        assert!(monitor.check_tool_call(replace("a", "b")).is_ok());
+    }
+}
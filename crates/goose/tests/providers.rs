@@ -4,8 +4,8 @@ use goose::conversation::message::{Message, MessageContent};
 use goose::providers::base::Provider;
 use goose::providers::errors::ProviderError;
 use goose::providers::{
-    anthropic, azure, bedrock, databricks, google, groq, litellm, ollama, openai, openrouter,
-    snowflake, xai,
+    anthropic, azure, bedrock, databricks, deepseek, google, groq, litellm, mistral, ollama,
+    openai, openrouter, snowflake, xai,
 };
 use rmcp::model::Tool;
 use rmcp::model::{AnnotateAble, Content, RawImageContent};
@@ -619,6 +619,28 @@ async fn test_litellm_provider() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_deepseek_provider() -> Result<()> {
+    test_provider(
+        "DeepSeek",
+        &["DEEPSEEK_API_KEY"],
+        None,
+        deepseek::DeepSeekProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_mistral_provider() -> Result<()> {
+    test_provider(
+        "Mistral",
+        &["MISTRAL_API_KEY"],
+        None,
+        mistral::MistralProvider::default,
+    )
+    .await
+}
+
 #[tokio::test]
 async fn test_xai_provider() -> Result<()> {
     test_provider("Xai", &["XAI_API_KEY"], None, xai::XaiProvider::default).await
@@ -363,6 +363,7 @@ impl ScheduleToolTestBuilder {
             current_session_id: None,
             process_start_time: None,
             execution_mode: Some("background".to_string()),
+            timezone: None,
         };
         {
             let mut jobs = self.scheduler.jobs.lock().await;
@@ -404,6 +405,7 @@ pub fn create_test_session_metadata(message_count: usize, working_dir: &str) ->
         message_count,
         working_dir: PathBuf::from(working_dir),
         description: "Test session".to_string(),
+        title: String::new(),
         schedule_id: Some("test_job".to_string()),
         project_id: None,
         total_tokens: Some(100),
@@ -412,5 +414,10 @@ pub fn create_test_session_metadata(message_count: usize, working_dir: &str) ->
         accumulated_total_tokens: Some(100),
         accumulated_input_tokens: Some(50),
         accumulated_output_tokens: Some(50),
+        model_usage: Default::default(),
+        usage_ledger: Default::default(),
+        context: None,
+        tags: Vec::new(),
+        properties: Default::default(),
     }
 }
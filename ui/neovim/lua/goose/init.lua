@@ -0,0 +1,238 @@
+-- Reference Neovim client for goosed's editor integration.
+--
+-- Keeps the daemon up to date with the open buffers and the visual selection, and applies the
+-- edits the agent requests to the buffers themselves, so they can be undone with `u` like any
+-- other change. Talks to goosed over HTTP with curl.
+
+local M = {}
+
+M.config = {
+  url = vim.env.GOOSE_URL or "http://127.0.0.1:3000",
+  secret_key = vim.env.GOOSE_SERVER__SECRET_KEY or "test",
+  -- Milliseconds to wait after a change before sending the buffer
+  debounce = 300,
+}
+
+local events_job = nil
+local pending = {}
+
+local function send(path, body)
+  local job = vim.fn.jobstart({
+    "curl", "-sS", "-X", "POST",
+    "-H", "Content-Type: application/json",
+    "-H", "X-Secret-Key: " .. M.config.secret_key,
+    "--data-binary", "@-",
+    M.config.url .. path,
+  })
+  if job > 0 then
+    vim.fn.chansend(job, vim.json.encode(body))
+    vim.fn.chanclose(job, "stdin")
+  end
+end
+
+local function is_file_buffer(bufnr)
+  return vim.api.nvim_buf_is_loaded(bufnr)
+    and vim.bo[bufnr].buftype == ""
+    and vim.api.nvim_buf_get_name(bufnr) ~= ""
+end
+
+local function buffer_text(bufnr)
+  local lines = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+  local text = table.concat(lines, "\n")
+  if vim.bo[bufnr].eol then
+    text = text .. "\n"
+  end
+  return text
+end
+
+local function sync_buffer(bufnr)
+  if not events_job or not is_file_buffer(bufnr) then
+    return
+  end
+  send("/editor/buffer", {
+    path = vim.api.nvim_buf_get_name(bufnr),
+    text = buffer_text(bufnr),
+    dirty = vim.bo[bufnr].modified,
+  })
+end
+
+local function sync_buffer_later(bufnr)
+  if pending[bufnr] then
+    pending[bufnr]:stop()
+  end
+  pending[bufnr] = vim.defer_fn(function()
+    pending[bufnr] = nil
+    if vim.api.nvim_buf_is_valid(bufnr) then
+      sync_buffer(bufnr)
+    end
+  end, M.config.debounce)
+end
+
+local function close_buffer(bufnr)
+  if not events_job or not is_file_buffer(bufnr) then
+    return
+  end
+  send("/editor/buffer", { path = vim.api.nvim_buf_get_name(bufnr), text = vim.NIL })
+end
+
+local function buffer_for(path)
+  for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
+    if vim.api.nvim_buf_is_loaded(bufnr) and vim.api.nvim_buf_get_name(bufnr) == path then
+      return bufnr
+    end
+  end
+  return nil
+end
+
+-- Apply one edit; ranges are zero-based lines and byte columns, as nvim_buf_set_text takes them
+local function apply_text_edit(bufnr, edit)
+  local line_count = vim.api.nvim_buf_line_count(bufnr)
+  local start, finish = edit.range.start, edit.range["end"]
+  local new_text = edit.new_text
+  -- The daemon counts the final newline as a line break, Neovim doesn't store it
+  if finish.line >= line_count then
+    local last = vim.api.nvim_buf_get_lines(bufnr, line_count - 1, line_count, false)[1] or ""
+    finish = { line = line_count - 1, character = #last }
+    new_text = new_text:gsub("\n$", "")
+    if start.line >= line_count then
+      start = finish
+      new_text = "\n" .. new_text
+    end
+  end
+  vim.api.nvim_buf_set_text(
+    bufnr,
+    start.line,
+    start.character,
+    finish.line,
+    finish.character,
+    vim.split(new_text, "\n", { plain = true })
+  )
+end
+
+local function handle_request(request)
+  local bufnr = buffer_for(request.path)
+  if not bufnr then
+    return send("/editor/result", { id = request.id, error = "the file is not open" })
+  end
+  local ok, err = pcall(function()
+    if request.type == "apply_edit" then
+      -- Join the edits into one undo step
+      for index, edit in ipairs(request.edits) do
+        if index > 1 then
+          vim.api.nvim_buf_call(bufnr, function()
+            vim.cmd("undojoin")
+          end)
+        end
+        apply_text_edit(bufnr, edit)
+      end
+      if request.save then
+        vim.api.nvim_buf_call(bufnr, function()
+          vim.cmd("silent update")
+        end)
+      end
+    elseif request.type == "undo" then
+      vim.api.nvim_buf_call(bufnr, function()
+        vim.cmd("silent undo")
+      end)
+    else
+      error("unknown request " .. tostring(request.type))
+    end
+  end)
+  send("/editor/result", { id = request.id, error = ok and vim.NIL or tostring(err) })
+  sync_buffer(bufnr)
+end
+
+local function on_event_lines(_, lines)
+  for _, line in ipairs(lines) do
+    local data = line:match("^data: (.*)")
+    if data then
+      local ok, request = pcall(vim.json.decode, data)
+      if ok and request.type ~= "ping" then
+        vim.schedule(function()
+          handle_request(request)
+        end)
+      end
+    end
+  end
+end
+
+--- Send the visual selection to the agent as context, or clear it when called without a range
+function M.send_selection(opts)
+  local bufnr = vim.api.nvim_get_current_buf()
+  if not opts or opts.range == 0 then
+    return send("/editor/selection", { selection = vim.NIL })
+  end
+  local lines = vim.api.nvim_buf_get_lines(bufnr, opts.line1 - 1, opts.line2, false)
+  send("/editor/selection", {
+    selection = {
+      path = vim.api.nvim_buf_get_name(bufnr),
+      start_line = opts.line1,
+      end_line = opts.line2,
+      text = table.concat(lines, "\n"),
+    },
+  })
+end
+
+function M.connect()
+  if events_job then
+    return
+  end
+  events_job = vim.fn.jobstart({
+    "curl", "-sS", "-N",
+    "-H", "X-Secret-Key: " .. M.config.secret_key,
+    M.config.url .. "/editor/events",
+  }, {
+    on_stdout = on_event_lines,
+    on_exit = function()
+      events_job = nil
+    end,
+  })
+  if events_job <= 0 then
+    events_job = nil
+    return vim.notify("goose: could not start curl", vim.log.levels.ERROR)
+  end
+  -- Give the daemon a moment to register the connection before reporting the buffers
+  vim.defer_fn(function()
+    for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
+      sync_buffer(bufnr)
+    end
+  end, 200)
+end
+
+function M.disconnect()
+  if events_job then
+    vim.fn.jobstop(events_job)
+    events_job = nil
+  end
+end
+
+function M.setup(opts)
+  M.config = vim.tbl_extend("force", M.config, opts or {})
+
+  local group = vim.api.nvim_create_augroup("goose", { clear = true })
+  vim.api.nvim_create_autocmd({ "BufReadPost", "BufWritePost", "BufEnter" }, {
+    group = group,
+    callback = function(args)
+      sync_buffer(args.buf)
+    end,
+  })
+  vim.api.nvim_create_autocmd({ "TextChanged", "TextChangedI" }, {
+    group = group,
+    callback = function(args)
+      sync_buffer_later(args.buf)
+    end,
+  })
+  vim.api.nvim_create_autocmd({ "BufDelete", "BufWipeout" }, {
+    group = group,
+    callback = function(args)
+      close_buffer(args.buf)
+    end,
+  })
+  vim.api.nvim_create_autocmd("VimLeavePre", { group = group, callback = M.disconnect })
+
+  vim.api.nvim_create_user_command("GooseConnect", M.connect, {})
+  vim.api.nvim_create_user_command("GooseDisconnect", M.disconnect, {})
+  vim.api.nvim_create_user_command("GooseSelection", M.send_selection, { range = true })
+end
+
+return M